@@ -0,0 +1,108 @@
+//! Encodes and writes 500 small checkpoint files to an in-memory store,
+//! timing them and counting heap allocations. `BufferPool` encoding is
+//! compared against a fresh `Vec` per file, which the writers used before.
+//!
+//!     cargo bench --bench checkpoint_writes
+
+use backend::{BufferPool, Checkpoint, CheckpointSnapshot, MarkoutTime, ParallelParquetWriter, ParquetWriteOptions};
+use bytes::Bytes;
+use object_store::memory::InMemory;
+use parquet::arrow::ArrowWriter;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const CHECKPOINTS: usize = 500;
+
+/// Counts every allocation and reallocation
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// What `run` returned, with the allocations it made and how long it took
+fn measure<T>(run: impl FnOnce() -> T) -> (T, usize, Duration) {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let started = Instant::now();
+    let result = run();
+    let elapsed = started.elapsed();
+    (result, ALLOCATIONS.load(Ordering::Relaxed) - allocations, elapsed)
+}
+
+fn report(name: &str, allocations: usize, elapsed: Duration) {
+    println!(
+        "{:<24} {:>8.1} allocations/file {:>10.1?}/file",
+        name,
+        allocations as f64 / CHECKPOINTS as f64,
+        elapsed / CHECKPOINTS as u32
+    );
+}
+
+fn snapshots() -> Vec<CheckpointSnapshot> {
+    (0..CHECKPOINTS)
+        .map(|i| {
+            let checkpoint = Checkpoint::new(format!("0x{:040x}", i), MarkoutTime::Brontes);
+            for block in 0..20u64 {
+                checkpoint.update_max_lvr(15_537_393 + block * 97, (block * 7_919 + i as u64) % 10_000);
+            }
+            checkpoint.to_snapshot()
+        })
+        .collect()
+}
+
+fn main() {
+    let snapshots = snapshots();
+    let batches: Vec<_> = snapshots
+        .iter()
+        .map(|snapshot| CheckpointSnapshot::to_record_batch(std::slice::from_ref(snapshot)).unwrap())
+        .collect();
+    let props = ParquetWriteOptions::default().writer_properties();
+
+    let (fresh, allocations, elapsed) = measure(|| {
+        batches
+            .iter()
+            .map(|batch| {
+                let mut buffer = Vec::new();
+                let mut writer = ArrowWriter::try_new(&mut buffer, batch.schema(), Some(props.clone())).unwrap();
+                writer.write(batch).unwrap();
+                writer.close().unwrap();
+                Bytes::from(buffer)
+            })
+            .collect::<Vec<_>>()
+    });
+    report("encode, fresh Vec", allocations, elapsed);
+
+    let pool = BufferPool::new();
+    let (pooled, allocations, elapsed) = measure(|| {
+        batches
+            .iter()
+            .map(|batch| pool.encode_parquet(batch, props.clone()).unwrap())
+            .collect::<Vec<_>>()
+    });
+    report("encode, BufferPool", allocations, elapsed);
+    assert_eq!(fresh, pooled, "pooled encoding changed the output");
+
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    let writer = ParallelParquetWriter::new(Arc::new(InMemory::new()));
+    let ((), allocations, elapsed) = measure(|| runtime.block_on(writer.write_checkpoints(snapshots)).unwrap());
+    report("write_checkpoints", allocations, elapsed);
+}
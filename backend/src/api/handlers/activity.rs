@@ -0,0 +1,84 @@
+use axum::{
+    extract::State,
+    response::Json,
+    http::StatusCode,
+};
+use crate::MarkoutTime;
+use crate::{ApiQuery, DatasetKind, api::handlers::common::{open_precomputed, get_float64_column, get_string_column, get_uint64_column},
+    AppState, ActivityRunsQuery, ActivityRunsResponse, PoolActivityRuns};
+use tracing::{error, info, warn};
+use std::sync::Arc;
+
+pub async fn get_activity_runs(
+    State(state): State<Arc<AppState>>,
+    ApiQuery(params): ApiQuery<ActivityRunsQuery>,
+) -> Result<Json<ActivityRunsResponse>, StatusCode> {
+    let markout_time = params.markout_time.unwrap_or(MarkoutTime::Brontes).to_string();
+    let pool_address = params.pool_address;
+
+    if let Some(pool_address) = &pool_address {
+        if !state.pools.contains(pool_address) {
+            warn!("Invalid pool address requested: {}", pool_address);
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
+
+    info!(
+        "Fetching activity runs for pool {:?} (markout_time: {})",
+        pool_address, markout_time
+    );
+
+    let reader = open_precomputed(&state.store, DatasetKind::ActivityRuns).await?;
+
+    let mut pools = Vec::new();
+
+    for batch_result in reader {
+        let batch = batch_result.map_err(|e| {
+            error!("Failed to read batch: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+        let pool_addresses = get_string_column(&batch, "pool_address")?;
+        let pool_names = get_string_column(&batch, "pool_name")?;
+        let markout_times = get_string_column(&batch, "markout_time")?;
+        let first_active_blocks = get_uint64_column(&batch, "first_active_block")?;
+        let dry_spells = get_uint64_column(&batch, "dry_spells")?;
+        let longest_dry_spells = get_uint64_column(&batch, "longest_dry_spell")?;
+        let mean_dry_spells = get_float64_column(&batch, "mean_dry_spell")?;
+        let longest_active_streaks = get_uint64_column(&batch, "longest_active_streak")?;
+
+        for i in 0..batch.num_rows() {
+            if markout_times.value(i) != markout_time {
+                continue;
+            }
+            if pool_address.as_ref().is_some_and(|address| !address.matches(pool_addresses.value(i))) {
+                continue;
+            }
+
+            pools.push(PoolActivityRuns {
+                pool_name: pool_names.value(i).to_string(),
+                pool_address: pool_addresses.value(i).to_string(),
+                first_active_block: first_active_blocks.value(i),
+                dry_spells: dry_spells.value(i),
+                longest_dry_spell: longest_dry_spells.value(i),
+                mean_dry_spell: mean_dry_spells.value(i),
+                longest_active_streak: longest_active_streaks.value(i),
+            });
+        }
+    }
+
+    if pools.is_empty() {
+        warn!(
+            "No activity runs found for pool {:?} (markout_time: {})",
+            pool_address, markout_time
+        );
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    pools.sort_by(|a, b| a.pool_name.cmp(&b.pool_name));
+
+    Ok(Json(ActivityRunsResponse {
+        markout_time,
+        pools,
+    }))
+}
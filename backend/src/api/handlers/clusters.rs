@@ -1,81 +1,39 @@
 use axum::{
-    extract::{State, Query},
+    extract::State,
     response::Json,
     http::StatusCode,
 };
 use std::{sync::Arc, collections::HashMap};
 use tracing::{error, info, warn};
-use parquet::arrow::arrow_reader::ParquetRecordBatchReader;
-use arrow::array::Array;
-use crate::{
+use crate::{ApiQuery, Cents, MarkoutTime};
+use crate::{DatasetKind, 
     AppState,
-    api::handlers::common::{get_uint64_column, get_string_column, get_float64_column},
-    STABLE_POOLS, WBTC_WETH_POOLS, USDC_WETH_POOLS, USDT_WETH_POOLS, INTERVAL_RANGES,
-    DAI_WETH_POOLS, USDC_WBTC_POOLS, ALTCOIN_WETH_POOLS,
+    api::handlers::common::{open_precomputed, get_uint64_column, get_int64_column, get_string_column, get_float64_column, get_bucket_range_end, BUCKET_CONFIG},
+    INTERVAL_RANGES,
     ClusterPieResponse, ClusterQuery, ClusterTotal,
     ClusterHistogramBucket, ClusterHistogramData, ClusterHistogramQuery, ClusterHistogramResponse,
     MonthlyClusterQuery, MonthlyData, ClusterMonthlyResponse,
     ClusterNonZero, ClusterNonZeroQuery, ClusterNonZeroResponse
 };
-use object_store::path::Path;
-
-
-pub fn get_cluster_name(pool_address: &str) -> Option<&'static str> {
-    let address = pool_address.to_lowercase();
-    if STABLE_POOLS.contains_key(address.as_str()) {
-        Some("Stable Pairs")
-    } else if WBTC_WETH_POOLS.contains_key(address.as_str()) {
-        Some("WBTC-WETH")
-    } else if USDC_WETH_POOLS.contains_key(address.as_str()) {
-        Some("USDC-WETH")
-    } else if USDT_WETH_POOLS.contains_key(address.as_str()) {
-        Some("USDT-WETH")
-    } else if DAI_WETH_POOLS.contains_key(address.as_str()) {
-        Some("DAI-WETH")
-    } else if USDC_WBTC_POOLS.contains_key(address.as_str()) {
-        Some("USDC-WBTC")
-    } else if ALTCOIN_WETH_POOLS.contains_key(address.as_str()) {
-        Some("Altcoin-WETH")
-    } else {
-        None
-    }
-}
+
 
 pub async fn get_cluster_proportion(
     State(state): State<Arc<AppState>>,
-    Query(params): Query<ClusterQuery>,
+    ApiQuery(params): ApiQuery<ClusterQuery>,
 ) -> Result<Json<ClusterPieResponse>, StatusCode> {
-    let markout_time = params.markout_time.unwrap_or_else(|| String::from("brontes"));
+    let markout_time = params.markout_time.unwrap_or(MarkoutTime::Brontes).to_string();
     
     info!(
         "Analyzing cluster distribution metrics for markout time: {}", 
         markout_time
     );
 
-    // Read from precomputed file
-    let bytes = state.store.get(&Path::from("precomputed/clusters/proportions.parquet"))
-        .await
-        .map_err(|e| {
-            error!("Failed to read precomputed cluster distribution data: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?
-        .bytes()
-        .await
-        .map_err(|e| {
-            error!("Failed to get bytes from precomputed cluster data: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-
-    let reader = ParquetRecordBatchReader::try_new(bytes, 1024)
-        .map_err(|e| {
-            error!("Failed to create Parquet reader: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    let reader = open_precomputed(&state.store, DatasetKind::ClusterProportions).await?;
 
     let mut clusters = Vec::new();
-    let mut total_lvr_cents = 0u64;
+    let mut total_lvr_cents = Cents::ZERO;
     let mut largest_cluster_name = String::new();
-    let mut largest_cluster_amount = 0u64;
+    let mut largest_cluster_amount = Cents::ZERO;
 
     for batch_result in reader {
         let batch = batch_result.map_err(|e| {
@@ -85,7 +43,7 @@ pub async fn get_cluster_proportion(
 
         let cluster_names = get_string_column(&batch, "cluster_name")?;
         let markout_times = get_string_column(&batch, "markout_time")?;
-        let lvr_cents = get_uint64_column(&batch, "total_lvr_cents")?;
+        let lvr_cents = get_int64_column(&batch, "total_lvr_cents")?;
 
         for i in 0..batch.num_rows() {
             // Early filter by markout time
@@ -94,7 +52,7 @@ pub async fn get_cluster_proportion(
             }
 
             let cluster_name = cluster_names.value(i).to_string();
-            let cluster_total = lvr_cents.value(i);
+            let cluster_total = Cents(lvr_cents.value(i));
             
             // Track largest cluster
             if cluster_total > largest_cluster_amount {
@@ -118,15 +76,15 @@ pub async fn get_cluster_proportion(
         );
         return Ok(Json(ClusterPieResponse {
             clusters: Vec::new(),
-            total_lvr_cents: 0,
+            total_lvr_cents: Cents::ZERO,
         }));
     }
 
     // Sort clusters by total for consistent presentation
-    clusters.sort_by(|a, b| b.total_lvr_cents.cmp(&a.total_lvr_cents));
+    clusters.sort_by_key(|c| std::cmp::Reverse(c.total_lvr_cents));
 
-    let largest_proportion = if total_lvr_cents > 0 {
-        (largest_cluster_amount as f64 / total_lvr_cents as f64) * 100.0
+    let largest_proportion = if total_lvr_cents.is_positive() {
+        (largest_cluster_amount.as_i64() as f64 / total_lvr_cents.as_i64() as f64) * 100.0
     } else {
         0.0
     };
@@ -134,7 +92,7 @@ pub async fn get_cluster_proportion(
     info!(
         "Analyzed {} clusters. Total volume: ${:.2}. Largest cluster: {} ({:.1}%)", 
         clusters.len(),
-        total_lvr_cents as f64 / 100.0,
+        total_lvr_cents.to_dollars(),
         largest_cluster_name,
         largest_proportion
     );
@@ -147,34 +105,16 @@ pub async fn get_cluster_proportion(
 
 pub async fn get_cluster_histogram(
     State(state): State<Arc<AppState>>,
-    Query(params): Query<ClusterHistogramQuery>,
+    ApiQuery(params): ApiQuery<ClusterHistogramQuery>,
 ) -> Result<Json<ClusterHistogramResponse>, StatusCode> {
-    let markout_time = params.markout_time.unwrap_or_else(|| String::from("brontes"));
+    let markout_time = params.markout_time.unwrap_or(MarkoutTime::Brontes).to_string();
     
     info!(
         "Analyzing transaction size distribution by cluster for markout time: {}", 
         markout_time
     );
 
-    // Read from precomputed file
-    let bytes = state.store.get(&Path::from("precomputed/clusters/histograms.parquet"))
-        .await
-        .map_err(|e| {
-            error!("Failed to read precomputed cluster distribution data: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?
-        .bytes()
-        .await
-        .map_err(|e| {
-            error!("Failed to get bytes from precomputed distribution data: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-
-    let reader = ParquetRecordBatchReader::try_new(bytes, 1024)
-        .map_err(|e| {
-            error!("Failed to create Parquet reader: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    let reader = open_precomputed(&state.store, DatasetKind::ClusterHistograms).await?;
 
     let mut cluster_data: HashMap<String, (Vec<ClusterHistogramBucket>, u64)> = HashMap::new();
 
@@ -201,8 +141,8 @@ pub async fn get_cluster_histogram(
             let count = counts.value(i);
 
             let bucket = ClusterHistogramBucket {
-                range_start: bucket_starts.value(i),
-                range_end: if bucket_ends.is_null(i) { None } else { Some(bucket_ends.value(i)) },
+                range_start_dollars: bucket_starts.value(i),
+                range_end_dollars: get_bucket_range_end(bucket_ends, i),
                 count,
                 label: labels.value(i).to_string(),
             };
@@ -229,8 +169,20 @@ pub async fn get_cluster_histogram(
     let mut clusters: Vec<ClusterHistogramData> = cluster_data
         .into_iter()
         .map(|(name, (mut buckets, total_observations))| {
+            // Files written before empty buckets were kept only carry non-zero ones
+            for spec in &BUCKET_CONFIG {
+                if !buckets.iter().any(|bucket| bucket.label == spec.label) {
+                    buckets.push(ClusterHistogramBucket {
+                        range_start_dollars: spec.range_start,
+                        range_end_dollars: spec.range_end,
+                        count: 0,
+                        label: spec.label.to_string(),
+                    });
+                }
+            }
+
             // Sort buckets by range start for consistent presentation
-            buckets.sort_by(|a, b| a.range_start.partial_cmp(&b.range_start)
+            buckets.sort_by(|a, b| a.range_start_dollars.partial_cmp(&b.range_start_dollars)
                 .unwrap_or(std::cmp::Ordering::Equal));
             ClusterHistogramData {
                 name,
@@ -241,7 +193,7 @@ pub async fn get_cluster_histogram(
         .collect();
 
     // Sort clusters by total observations for consistent ordering
-    clusters.sort_by(|a, b| b.total_observations.cmp(&a.total_observations));
+    clusters.sort_by_key(|c| std::cmp::Reverse(c.total_observations));
 
     info!(
         "Retrieved distribution data for {} clusters for markout time {}", 
@@ -254,36 +206,18 @@ pub async fn get_cluster_histogram(
 
 pub async fn get_monthly_cluster_totals(
     State(state): State<Arc<AppState>>,
-    Query(params): Query<MonthlyClusterQuery>,
+    ApiQuery(params): ApiQuery<MonthlyClusterQuery>,
 ) -> Result<Json<ClusterMonthlyResponse>, StatusCode> {
-    let markout_time = params.markout_time.unwrap_or_else(|| String::from("brontes"));
+    let markout_time = params.markout_time.unwrap_or(MarkoutTime::Brontes).to_string();
     
     info!(
         "Analyzing monthly volume distribution across clusters for markout time: {}", 
         markout_time
     );
 
-    // Read from precomputed file
-    let bytes = state.store.get(&Path::from("precomputed/clusters/monthly_totals.parquet"))
-        .await
-        .map_err(|e| {
-            error!("Failed to read precomputed monthly distribution data: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?
-        .bytes()
-        .await
-        .map_err(|e| {
-            error!("Failed to get bytes from precomputed monthly data: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    let reader = open_precomputed(&state.store, DatasetKind::ClusterMonthlyTotals).await?;
 
-    let reader = ParquetRecordBatchReader::try_new(bytes, 1024)
-        .map_err(|e| {
-            error!("Failed to create Parquet reader: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-
-    let mut time_range_data: HashMap<String, (HashMap<String, u64>, u64)> = HashMap::new();
+    let mut time_range_data: HashMap<String, (HashMap<String, Cents>, Cents)> = HashMap::new();
     let mut unique_clusters = std::collections::HashSet::new();
 
     for batch_result in reader {
@@ -295,7 +229,7 @@ pub async fn get_monthly_cluster_totals(
         let time_ranges = get_string_column(&batch, "time_range")?;
         let cluster_names = get_string_column(&batch, "cluster_name")?;
         let markout_times = get_string_column(&batch, "markout_time")?;
-        let total_lvr = get_uint64_column(&batch, "total_lvr_cents")?;
+        let total_lvr = get_int64_column(&batch, "total_lvr_cents")?;
 
         for i in 0..batch.num_rows() {
             // Early filter by markout time
@@ -305,7 +239,7 @@ pub async fn get_monthly_cluster_totals(
 
             let time_range = time_ranges.value(i).to_string();
             let cluster_name = cluster_names.value(i).to_string();
-            let lvr_cents = total_lvr.value(i);
+            let lvr_cents = Cents(total_lvr.value(i));
 
             unique_clusters.insert(cluster_name.clone());
             
@@ -339,7 +273,7 @@ pub async fn get_monthly_cluster_totals(
         .into_iter()
         .map(|(time_range, (cluster_totals, total_lvr_cents))| MonthlyData {
             time_range,
-            cluster_totals,
+            cluster_totals_cents: cluster_totals,
             total_lvr_cents,
         })
         .collect();
@@ -370,34 +304,16 @@ pub async fn get_monthly_cluster_totals(
 
 pub async fn get_cluster_non_zero(
     State(state): State<Arc<AppState>>,
-    Query(params): Query<ClusterNonZeroQuery>,
+    ApiQuery(params): ApiQuery<ClusterNonZeroQuery>,
 ) -> Result<Json<ClusterNonZeroResponse>, StatusCode> {
-    let markout_time = params.markout_time.unwrap_or_else(|| String::from("brontes"));
+    let markout_time = params.markout_time.unwrap_or(MarkoutTime::Brontes).to_string();
     
     info!(
         "Analyzing activity patterns across clusters for markout time: {}", 
         markout_time
     );
 
-    // Read from precomputed file
-    let bytes = state.store.get(&Path::from("precomputed/clusters/non_zero.parquet"))
-        .await
-        .map_err(|e| {
-            error!("Failed to read precomputed cluster activity data: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?
-        .bytes()
-        .await
-        .map_err(|e| {
-            error!("Failed to get bytes from precomputed activity data: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-
-    let reader = ParquetRecordBatchReader::try_new(bytes, 1024)
-        .map_err(|e| {
-            error!("Failed to create Parquet reader: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    let reader = open_precomputed(&state.store, DatasetKind::ClusterNonZero).await?;
 
     let mut clusters = Vec::new();
 
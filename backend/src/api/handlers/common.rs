@@ -1,78 +1,186 @@
 use arrow::array::{StringArray, UInt64Array, Float64Array, Array, Int64Array};
 use arrow::record_batch::RecordBatch;
-use axum::http::StatusCode;
-use tracing::error;
-use std::collections::HashSet;
-use crate::{POOL_NAMES, POOL_ADDRESSES};
-use arrow::datatypes::DataType;
+use axum::extract::{FromRequestParts, Query};
+use axum::http::{request::Parts, StatusCode};
+use serde::de::DeserializeOwned;
+use tracing::{error, warn};
+use std::sync::Arc;
+use object_store::{path::Path, ObjectStore};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReader;
+use crate::{DatasetKind, footer_metadata, parquet_reader, read_footer_metadata, read_matching_rows, INTERVAL_END_BLOCK_COLUMN, INTERVAL_ID_COLUMN, INTERVAL_START_BLOCK_COLUMN};
 
 pub const BLOCKS_PER_INTERVAL: u64 = 7200;
-pub const FINAL_PARTIAL_BLOCKS: u64 = 5808;
-pub const FINAL_INTERVAL_FILE: &str = "19857392_20000000.parquet";
 
-pub fn get_valid_pools() -> HashSet<String> {
-    POOL_ADDRESSES.iter()
-        .map(|&addr| addr.to_lowercase())
-        .collect()
+/// `Query` that rejects parameters it cannot parse, such as a malformed
+/// pool address, with 422 and the reason as the body instead of axum's 400
+#[derive(Debug, Clone)]
+pub struct ApiQuery<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for ApiQuery<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, String);
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        match Query::<T>::from_request_parts(parts, state).await {
+            Ok(Query(params)) => Ok(ApiQuery(params)),
+            Err(rejection) => {
+                warn!("Rejected query {:?}: {}", parts.uri.query().unwrap_or(""), rejection.body_text());
+                Err((StatusCode::UNPROCESSABLE_ENTITY, rejection.body_text()))
+            }
+        }
+    }
+}
+
+/// A histogram bucket in dollars, backed by one checkpoint bucket counter
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BucketSpec {
+    pub range_start: f64,
+    pub range_end: Option<f64>,
+    pub column: &'static str,
+    pub label: &'static str,
 }
 
-pub fn get_pool_name(pool_address: &str) -> String {
-    POOL_NAMES
+/// Canonical non-zero LVR buckets, one per checkpoint counter. Each bucket
+/// includes `range_start` and excludes `range_end`, so $10.00 is counted in
+/// "$10-$100". The zero bucket (`total_bucket_0`) is tracked separately and
+/// never plotted.
+pub const BUCKET_CONFIG: [BucketSpec; 6] = [
+    BucketSpec { range_start: 0.01, range_end: Some(10.0), column: "total_bucket_0_10", label: "$0.01-$10" },
+    BucketSpec { range_start: 10.0, range_end: Some(100.0), column: "total_bucket_10_100", label: "$10-$100" },
+    BucketSpec { range_start: 100.0, range_end: Some(500.0), column: "total_bucket_100_500", label: "$100-$500" },
+    BucketSpec { range_start: 500.0, range_end: Some(1000.0), column: "total_bucket_500_1000", label: "$500-$1K" },
+    BucketSpec { range_start: 1000.0, range_end: Some(10000.0), column: "total_bucket_1000_10000", label: "$1K-$10K" },
+    BucketSpec { range_start: 10000.0, range_end: None, column: "total_bucket_10000_plus", label: "$10K+" },
+];
+
+/// Footer key of checkpoint files recording how LVR was assigned to buckets
+pub const BUCKET_RULE_METADATA_KEY: &str = "lvr.bucket_rule";
+
+/// `BUCKET_RULE_METADATA_KEY` value of checkpoints bucketed by
+/// `bucket_index`. Checkpoints without the key counted a value equal to an
+/// upper bound in the bucket below it.
+pub const HALF_OPEN_BUCKET_RULE: &str = "half_open";
+
+/// Index into `BUCKET_CONFIG` of a positive value in cents, or `None` for zero
+pub fn bucket_index(lvr_cents: u64) -> Option<usize> {
+    if lvr_cents == 0 {
+        return None;
+    }
+    let dollars = lvr_cents as f64 / 100.0;
+    BUCKET_CONFIG
         .iter()
-        .find(|(addr, _)| addr.to_lowercase() == pool_address)
-        .map(|(_, name)| name.to_string())
-        .unwrap_or_else(|| pool_address.to_string())
+        .position(|spec| spec.range_end.is_none_or(|end| dollars < end))
 }
 
-pub fn calculate_block_number(base_block: u64, interval_id: u64, file_path: &str) -> u64 {
-    let file_start = file_path
-        .split("intervals/")
-        .nth(1)
-        .and_then(|name| name.trim_end_matches(".parquet").split('_').next())
-        .and_then(|num| num.parse::<u64>().ok())
-        .unwrap_or(base_block);
+/// Per-bucket counts in `BUCKET_CONFIG` order
+pub type BucketCounts = [u64; BUCKET_CONFIG.len()];
 
-    if file_path.ends_with(FINAL_INTERVAL_FILE) && interval_id == 19 {
-        file_start + (interval_id * BLOCKS_PER_INTERVAL) + FINAL_PARTIAL_BLOCKS
+/// Reads `bucket_range_end`, where null marks the open-ended top bucket
+pub fn get_bucket_range_end(array: &Float64Array, index: usize) -> Option<f64> {
+    if array.is_null(index) {
+        None
     } else {
-        file_start + (interval_id * BLOCKS_PER_INTERVAL)
+        Some(array.value(index))
     }
 }
 
-pub fn get_column_value<A: Array + 'static>(
-    batch: &RecordBatch, 
-    column_name: &str
-) -> Result<u64, StatusCode> {
-    let idx = batch.schema().index_of(column_name).map_err(|e| {
-        error!("Failed to find {} column: {}", column_name, e);
+/// Opens a precomputed file after checking its footer against the schema this
+/// binary reads. A missing file is a 404; a file written with an incompatible
+/// schema is rejected with 400 and logged as needing regeneration.
+pub async fn open_precomputed(
+    store: &Arc<dyn ObjectStore>,
+    kind: DatasetKind,
+) -> Result<ParquetRecordBatchReader, StatusCode> {
+    open_precomputed_at(store, kind, &kind.path()).await
+}
+
+/// Like `open_precomputed`, for a file of `kind` stored somewhere other than
+/// `kind.path()` (e.g. a per-pool partition)
+pub async fn open_precomputed_at(
+    store: &Arc<dyn ObjectStore>,
+    kind: DatasetKind,
+    path: &Path,
+) -> Result<ParquetRecordBatchReader, StatusCode> {
+    let bytes = store.get(path)
+        .await
+        .map_err(|e| match e {
+            object_store::Error::NotFound { .. } => {
+                warn!("Precomputed file {} does not exist", path);
+                StatusCode::NOT_FOUND
+            }
+            e => {
+                error!("Failed to read precomputed file {}: {}", path, e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        })?
+        .bytes()
+        .await
+        .map_err(|e| {
+            error!("Failed to get bytes from precomputed file {}: {}", path, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let metadata = read_footer_metadata(bytes.clone()).map_err(|e| {
+        error!("Failed to read footer of {}: {}", path, e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
+    kind.check_footer(&metadata).map_err(|e| {
+        error!("{}", e);
+        StatusCode::BAD_REQUEST
+    })?;
 
-    let column = batch.column(idx);
-    match column.data_type() {
-        DataType::UInt64 => {
-            column.as_any()
-                .downcast_ref::<UInt64Array>()
-                .map(|arr| arr.value(0))
-                .ok_or_else(|| {
-                    error!("Failed to cast {} as UInt64Array", column_name);
-                    StatusCode::INTERNAL_SERVER_ERROR
-                })
-        }
-        DataType::Int64 => {
-            column.as_any()
-                .downcast_ref::<Int64Array>()
-                .map(|arr| arr.value(0) as u64)
-                .ok_or_else(|| {
-                    error!("Failed to cast {} as Int64Array", column_name);
-                    StatusCode::INTERNAL_SERVER_ERROR
-                })
-        }
-        _ => {
-            error!("Unexpected type for {}: {:?}", column_name, column.data_type());
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+    parquet_reader(bytes, path, 1024)
+        .map_err(|e| {
+            error!("{:#}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+/// The rows of the precomputed file of `kind` whose string columns equal
+/// `filters`, e.g. one pool at one markout. Row groups and pages holding
+/// only other rows are neither fetched nor decoded.
+pub async fn read_precomputed_matching(
+    store: &Arc<dyn ObjectStore>,
+    kind: DatasetKind,
+    filters: &[(&str, &str)],
+) -> Result<Vec<RecordBatch>, StatusCode> {
+    let path = kind.path();
+    let rows = read_matching_rows(store.clone(), &path, filters).await.map_err(|e| {
+        if let Some(object_store::Error::NotFound { .. }) = e.downcast_ref::<object_store::Error>() {
+            warn!("Precomputed file {} does not exist", path);
+            return StatusCode::NOT_FOUND;
         }
+        error!("{:#}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    kind.check_footer(&footer_metadata(rows.metadata.file_metadata())).map_err(|e| {
+        error!("{}", e);
+        StatusCode::BAD_REQUEST
+    })?;
+    Ok(rows.batches)
+}
+
+/// Block range `start..end` of each row of an interval batch, read from its
+/// range columns. Files written before those columns existed fall back to
+/// the file's own range: intervals are `BLOCKS_PER_INTERVAL` long from
+/// `file_start`, and the last one ends at `file_end`.
+pub fn interval_block_ranges(batch: &RecordBatch, file_start: u64, file_end: u64) -> Result<Vec<(u64, u64)>, StatusCode> {
+    if batch.schema().column_with_name(INTERVAL_START_BLOCK_COLUMN).is_some() {
+        let starts = get_uint64_column(batch, INTERVAL_START_BLOCK_COLUMN)?;
+        let ends = get_uint64_column(batch, INTERVAL_END_BLOCK_COLUMN)?;
+        return Ok(starts.values().iter().copied().zip(ends.values().iter().copied()).collect());
     }
+
+    let interval_ids = get_uint64_column(batch, INTERVAL_ID_COLUMN)?;
+    Ok(interval_ids.values().iter()
+        .map(|&interval_id| {
+            let start = file_start + interval_id * BLOCKS_PER_INTERVAL;
+            (start, (start + BLOCKS_PER_INTERVAL).min(file_end))
+        })
+        .collect())
 }
 
 pub fn get_string_column<'a>(batch: &'a RecordBatch, name: &str) -> Result<&'a StringArray, StatusCode> {
@@ -115,4 +223,19 @@ pub fn get_float64_column<'a>(batch: &'a RecordBatch, name: &str) -> Result<&'a
             error!("Failed to cast {} column to Float64Array", name);
             StatusCode::INTERNAL_SERVER_ERROR
         })
-}
\ No newline at end of file
+}
+
+pub fn get_int64_column<'a>(batch: &'a RecordBatch, name: &str) -> Result<&'a Int64Array, StatusCode> {
+    batch
+        .column(batch.schema().index_of(name).map_err(|e| {
+            error!("Failed to get {} column index: {}", name, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?)
+        .as_any()
+        .downcast_ref::<Int64Array>()
+        .ok_or_else(|| {
+            error!("Failed to cast {} column to Int64Array", name);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
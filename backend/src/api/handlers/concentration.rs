@@ -0,0 +1,51 @@
+use axum::{
+    extract::State,
+    response::Json,
+    http::StatusCode,
+};
+use crate::MarkoutTime;
+use crate::{ApiQuery, DatasetKind, api::handlers::common::{open_precomputed, get_float64_column, get_string_column, get_uint64_column},
+    AppState, ConcentrationQuery, ConcentrationResponse};
+use tracing::{error, info, warn};
+use std::sync::Arc;
+
+pub async fn get_concentration(
+    State(state): State<Arc<AppState>>,
+    ApiQuery(params): ApiQuery<ConcentrationQuery>,
+) -> Result<Json<ConcentrationResponse>, StatusCode> {
+    let markout_time = params.markout_time.unwrap_or(MarkoutTime::Brontes).to_string();
+
+    info!("Fetching LVR concentration metrics for markout_time: {}", markout_time);
+
+    let reader = open_precomputed(&state.store, DatasetKind::Concentration).await?;
+
+    for batch_result in reader {
+        let batch = batch_result.map_err(|e| {
+            error!("Failed to read batch: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+        let markout_times = get_string_column(&batch, "markout_time")?;
+        let pool_counts = get_uint64_column(&batch, "pool_count")?;
+        let hhis = get_float64_column(&batch, "hhi")?;
+        let top_1_shares = get_float64_column(&batch, "top_1_share")?;
+        let top_3_shares = get_float64_column(&batch, "top_3_share")?;
+        let top_5_shares = get_float64_column(&batch, "top_5_share")?;
+        let effective_pools = get_float64_column(&batch, "effective_pools")?;
+
+        if let Some(i) = (0..batch.num_rows()).find(|&i| markout_times.value(i) == markout_time) {
+            return Ok(Json(ConcentrationResponse {
+                markout_time,
+                pool_count: pool_counts.value(i),
+                hhi: hhis.value(i),
+                top_1_share: top_1_shares.value(i),
+                top_3_share: top_3_shares.value(i),
+                top_5_share: top_5_shares.value(i),
+                effective_pools: effective_pools.value(i),
+            }));
+        }
+    }
+
+    warn!("No concentration metrics found for markout_time: {}", markout_time);
+    Err(StatusCode::NOT_FOUND)
+}
@@ -0,0 +1,61 @@
+use axum::{
+    extract::State,
+    response::Json,
+    http::StatusCode,
+};
+use crate::MarkoutTime;
+use crate::{ApiQuery, DatasetKind, api::handlers::common::{open_precomputed, get_float64_column, get_string_column, get_uint64_column},
+    AppState, CorrelationsQuery, CorrelationsResponse, PoolCorrelation};
+use tracing::{error, info, warn};
+use std::sync::Arc;
+
+pub async fn get_correlations(
+    State(state): State<Arc<AppState>>,
+    ApiQuery(params): ApiQuery<CorrelationsQuery>,
+) -> Result<Json<CorrelationsResponse>, StatusCode> {
+    let markout_time = params.markout_time.unwrap_or(MarkoutTime::Brontes).to_string();
+
+    info!("Fetching cross-pool LVR correlations for markout_time: {}", markout_time);
+
+    let reader = open_precomputed(&state.store, DatasetKind::PoolCorrelations).await?;
+
+    let mut correlations = Vec::new();
+
+    for batch_result in reader {
+        let batch = batch_result.map_err(|e| {
+            error!("Failed to read batch: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+        let pools_a = get_string_column(&batch, "pool_a")?;
+        let pools_b = get_string_column(&batch, "pool_b")?;
+        let markout_times = get_string_column(&batch, "markout_time")?;
+        let correlation_values = get_float64_column(&batch, "correlation")?;
+        let n_intervals = get_uint64_column(&batch, "n_intervals")?;
+
+        for i in 0..batch.num_rows() {
+            if markout_times.value(i) != markout_time {
+                continue;
+            }
+
+            correlations.push(PoolCorrelation {
+                pool_a: pools_a.value(i).to_string(),
+                pool_a_name: state.pools.pool_name(pools_a.value(i)),
+                pool_b: pools_b.value(i).to_string(),
+                pool_b_name: state.pools.pool_name(pools_b.value(i)),
+                correlation: correlation_values.value(i),
+                n_intervals: n_intervals.value(i),
+            });
+        }
+    }
+
+    if correlations.is_empty() {
+        warn!("No pool correlations found for markout_time: {}", markout_time);
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(Json(CorrelationsResponse {
+        markout_time,
+        correlations,
+    }))
+}
@@ -5,8 +5,8 @@ use crate::HealthResponse;
 
 pub async fn health_check() -> impl IntoResponse {
     let response = HealthResponse {
-        status: "OK",
-        version: env!("CARGO_PKG_VERSION"),
+        status: "OK".to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
         timestamp: OffsetDateTime::now_utc().to_string(),
     };
 
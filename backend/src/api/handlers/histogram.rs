@@ -1,28 +1,23 @@
 use axum::{
-    extract::{State, Query},
+    extract::State,
     response::Json,
     http::StatusCode,
 };
-use crate::{AppState, 
-    HistogramBucket, HistogramResponse, HistogramQuery,
-    api::handlers::common::{get_string_column, get_float64_column, get_uint64_column, get_valid_pools}};
+use crate::{Address, ApiQuery, DatasetKind, AppState, 
+    HistogramBucket, HistogramResponse, HistogramQuery, HistogramDetail, EquiDepthBin,
+    api::handlers::common::{open_precomputed, get_string_column, get_float64_column, get_uint64_column, get_bucket_range_end, BUCKET_CONFIG}};
 use tracing::{error, info, warn};
 use std::sync::Arc;
-use parquet::arrow::arrow_reader::ParquetRecordBatchReader;
-use arrow::array::{Int64Array,UInt64Array, Array};
-use arrow::datatypes::DataType;
-use object_store::path::Path;
 
 pub async fn get_lvr_histogram(
     State(state): State<Arc<AppState>>,
-    Query(params): Query<HistogramQuery>,
+    ApiQuery(params): ApiQuery<HistogramQuery>,
 ) -> Result<Json<HistogramResponse>, StatusCode> {
-    let pool_address = params.pool_address.to_lowercase();
-    let markout_time = params.markout_time;
+    let pool_address = params.pool_address;
+    let markout_time = params.markout_time.to_string();
     
     // Validate pool address early
-    let valid_pools = get_valid_pools();
-    if !valid_pools.contains(&pool_address) {
+    if !state.pools.contains(&pool_address) {
         warn!("Invalid pool address requested: {}", pool_address);
         return Err(StatusCode::BAD_REQUEST);
     }
@@ -32,25 +27,7 @@ pub async fn get_lvr_histogram(
         pool_address, markout_time
     );
 
-    // Read from precomputed file
-    let bytes = state.store.get(&Path::from("precomputed/distributions/histograms.parquet"))
-        .await
-        .map_err(|e| {
-            error!("Failed to read precomputed histogram data: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?
-        .bytes()
-        .await
-        .map_err(|e| {
-            error!("Failed to get bytes from precomputed histogram data: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-
-    let reader = ParquetRecordBatchReader::try_new(bytes, 1024)
-        .map_err(|e| {
-            error!("Failed to create Parquet reader: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    let reader = open_precomputed(&state.store, DatasetKind::Histograms).await?;
 
     let mut buckets = Vec::new();
     let mut total_observations = 0u64;
@@ -74,7 +51,7 @@ pub async fn get_lvr_histogram(
 
         for i in 0..batch.num_rows() {
             // Early filtering
-            if pool_addresses.value(i).to_lowercase() != pool_address ||
+            if !pool_address.matches(pool_addresses.value(i)) ||
                markout_times.value(i) != markout_time {
                 continue;
             }
@@ -96,8 +73,8 @@ pub async fn get_lvr_histogram(
             total_observations += count;
 
             buckets.push(HistogramBucket {
-                range_start: bucket_starts.value(i),
-                range_end: if bucket_ends.is_null(i) { None } else { Some(bucket_ends.value(i)) },
+                range_start_dollars: bucket_starts.value(i),
+                range_end_dollars: get_bucket_range_end(bucket_ends, i),
                 count,
                 label: label.to_string(),
             });
@@ -113,8 +90,20 @@ pub async fn get_lvr_histogram(
         return Err(StatusCode::NOT_FOUND);
     }
 
+    // Files written before empty buckets were kept only carry non-zero ones
+    for spec in &BUCKET_CONFIG {
+        if !buckets.iter().any(|bucket| bucket.label == spec.label) {
+            buckets.push(HistogramBucket {
+                range_start_dollars: spec.range_start,
+                range_end_dollars: spec.range_end,
+                count: 0,
+                label: spec.label.to_string(),
+            });
+        }
+    }
+
     // Sort buckets by range start for consistent ordering
-    buckets.sort_by(|a, b| a.range_start.partial_cmp(&b.range_start).unwrap_or(std::cmp::Ordering::Equal));
+    buckets.sort_by(|a, b| a.range_start_dollars.partial_cmp(&b.range_start_dollars).unwrap_or(std::cmp::Ordering::Equal));
 
     info!(
         "Retrieved distribution with {} buckets for {}. Most frequent range: {} ({:.2}% of {} total observations)", 
@@ -125,37 +114,53 @@ pub async fn get_lvr_histogram(
         total_observations
     );
 
+    let equidepth_bins = match params.detail {
+        Some(HistogramDetail::Equidepth) => Some(read_equidepth_bins(&state, &pool_address, &markout_time).await?),
+        None => None,
+    };
+
     Ok(Json(HistogramResponse {
         pool_name,
-        pool_address,
+        pool_address: pool_address.to_string(),
         buckets,
         total_observations,
+        equidepth_bins,
     }))
 }
 
-pub fn get_bucket_value(batch: &arrow::record_batch::RecordBatch, column_name: &str) -> Result<u64, StatusCode> {
-    let idx = batch.schema().index_of(column_name).map_err(|e| {
-        error!("Failed to find {} column: {}", column_name, e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-
-    let column = batch.column(idx);
-    match column.data_type() {
-        DataType::UInt64 => column.as_any().downcast_ref::<UInt64Array>()
-            .map(|arr| arr.value(0))
-            .ok_or_else(|| {
-                error!("Failed to cast {} as UInt64Array", column_name);
-                StatusCode::INTERNAL_SERVER_ERROR
-            }),
-        DataType::Int64 => column.as_any().downcast_ref::<Int64Array>()
-            .map(|arr| arr.value(0) as u64)
-            .ok_or_else(|| {
-                error!("Failed to cast {} as Int64Array", column_name);
-                StatusCode::INTERNAL_SERVER_ERROR
-            }),
-        _ => {
-            error!("Unexpected type for {}: {:?}", column_name, column.data_type());
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+/// The pool's equi-depth bins in bin order
+async fn read_equidepth_bins(state: &AppState, pool_address: &Address, markout_time: &str) -> Result<Vec<EquiDepthBin>, StatusCode> {
+    let reader = open_precomputed(&state.store, DatasetKind::EquiDepthHistograms).await?;
+
+    let mut bins = Vec::new();
+    for batch_result in reader {
+        let batch = batch_result.map_err(|e| {
+            error!("Failed to read batch: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+        let pool_addresses = get_string_column(&batch, "pool_address")?;
+        let markout_times = get_string_column(&batch, "markout_time")?;
+        let bin_indices = get_uint64_column(&batch, "bin")?;
+        let range_starts = get_float64_column(&batch, "range_start")?;
+        let range_ends = get_float64_column(&batch, "range_end")?;
+        let masses = get_float64_column(&batch, "mass")?;
+
+        for i in 0..batch.num_rows() {
+            if !pool_address.matches(pool_addresses.value(i)) || markout_times.value(i) != markout_time {
+                continue;
+            }
+            bins.push((bin_indices.value(i), EquiDepthBin {
+                range_start_dollars: range_starts.value(i),
+                range_end_dollars: range_ends.value(i),
+                mass: masses.value(i),
+            }));
         }
     }
-}
\ No newline at end of file
+
+    if bins.is_empty() {
+        warn!("No equi-depth bins for pool {} with markout time {}", pool_address, markout_time);
+    }
+    bins.sort_by_key(|(bin, _)| *bin);
+    Ok(bins.into_iter().map(|(_, bin)| bin).collect())
+}
@@ -1,57 +1,31 @@
 use axum::{
-    extract::{State, Query},
+    extract::State,
     response::Json,
     http::StatusCode,
 };
-use crate::{AppState, 
+use crate::{ApiQuery, DatasetKind, AppState, 
     MaxLVRResponse, MaxLVRQuery, MaxLVRPoolData,
-    api::handlers::common::{get_uint64_column, 
+    api::handlers::common::{read_precomputed_matching, get_uint64_column, 
     get_string_column}};
-use tracing::{error, info, warn};
+use tracing::{info, warn};
 use std::sync::Arc;
-use parquet::arrow::arrow_reader::ParquetRecordBatchReader;
-use object_store::ObjectStore;
-use object_store::path::Path;
 
 pub async fn get_max_lvr(
     State(state): State<Arc<AppState>>,
-    Query(params): Query<MaxLVRQuery>,
+    ApiQuery(params): ApiQuery<MaxLVRQuery>,
 ) -> Result<Json<MaxLVRResponse>, StatusCode> {
-    let markout_time = params.markout_time;
+    let markout_time = params.markout_time.to_string();
     
     info!("Fetching maximum LVR values for markout_time: {}", markout_time);
 
-    // Read from precomputed file
-    let bytes = state.store.get(&Path::from("precomputed/pool_metrics/max_lvr.parquet"))
-        .await
-        .map_err(|e| {
-            error!("Failed to read precomputed max LVR data: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?
-        .bytes()
-        .await
-        .map_err(|e| {
-            error!("Failed to get bytes from precomputed max LVR data: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-
-    let reader = ParquetRecordBatchReader::try_new(bytes, 1024)
-        .map_err(|e| {
-            error!("Failed to create Parquet reader: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    let batches = read_precomputed_matching(&state.store, DatasetKind::MaxLvr, &[("markout_time", &markout_time)]).await?;
 
     let mut pool_data = Vec::new();
     let mut highest_lvr = 0u64;
     let mut earliest_max = u64::MAX;
     let mut latest_max = 0u64;
 
-    for batch_result in reader {
-        let batch = batch_result.map_err(|e| {
-            error!("Failed to read batch: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-
+    for batch in batches {
         let pool_addresses = get_string_column(&batch, "pool_address")?;
         let pool_names = get_string_column(&batch, "pool_name")?;
         let markout_times = get_string_column(&batch, "markout_time")?;
@@ -84,7 +58,7 @@ pub async fn get_max_lvr(
     }
 
     // Sort by LVR value descending for consistent ordering
-    pool_data.sort_by(|a, b| b.lvr_cents.cmp(&a.lvr_cents));
+    pool_data.sort_by_key(|p| std::cmp::Reverse(p.lvr_cents));
 
     if pool_data.is_empty() {
         warn!(
@@ -1,6 +1,8 @@
 // Submodules
 pub mod common;  // Common utilities used by other modules
 pub mod health;  // Health check endpoint
+pub mod schema;  // Precomputed dataset metadata
+pub mod pools;  // Pool registry listing
 pub mod clusters;  // Cluster analysis endpoints
 
 // Data analysis endpoints
@@ -8,27 +10,40 @@ pub mod total;
 pub mod running_total;
 pub mod regression;
 pub mod pool_totals;
+pub mod concentration;
+pub mod activity;
+pub mod correlations;
+pub mod rolling;
 pub mod max;
 pub mod histogram;
 pub mod nonzero;
 pub mod percentile;
 pub mod quartile;
 pub mod moment; 
+pub mod raw;
 
 // Re-exports
+pub use common::ApiQuery;
 pub use health::health_check;
+pub use schema::get_schema;
+pub use pools::get_pools;
 
 // Data analysis endpoints
 pub use running_total::get_running_total;
 pub use total::get_total_lvr;
 //pub use regression::get_markout_regression;
-pub use pool_totals::get_pool_totals;
+pub use pool_totals::{get_pool_totals, get_monthly_pool_totals};
+pub use concentration::get_concentration;
+pub use activity::get_activity_runs;
+pub use correlations::get_correlations;
+pub use rolling::get_rolling_series;
 pub use max::get_max_lvr;
 pub use histogram::get_lvr_histogram;
 pub use nonzero::get_non_zero_proportion;
 pub use percentile::get_percentile_band;
 pub use quartile::get_quartile_plot;
 pub use moment::get_distribution_metrics;
+pub use raw::get_raw_series;
 
 // Cluster analysis endpoints
 pub use clusters::*;
\ No newline at end of file
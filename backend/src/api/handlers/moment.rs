@@ -1,56 +1,44 @@
 use axum::{
-    extract::{State, Query},
+    extract::State,
     response::Json,
     http::StatusCode,
 };
+use arrow::array::{Array, UInt64Array};
 use std::sync::Arc;
-use parquet::arrow::arrow_reader::ParquetRecordBatchReader;
 use tracing::{error, info, warn};
-use crate::{
+use crate::{ApiQuery, DatasetKind, 
     AppState,
-    api::handlers::common::{get_string_column, get_float64_column, get_valid_pools},
-    DistributionQuery, DistributionResponse,
+    api::handlers::common::{open_precomputed, get_string_column, get_float64_column, get_uint64_column},
+    DistributionQuery, DistributionResponse, AGGREGATE_POOL_ADDRESS,
 };
-use object_store::path::Path;
 
 pub async fn get_distribution_metrics(
     State(state): State<Arc<AppState>>,
-    Query(params): Query<DistributionQuery>,
+    ApiQuery(params): ApiQuery<DistributionQuery>,
 ) -> Result<Json<DistributionResponse>, StatusCode> {
-    let pool_address = params.pool_address.to_lowercase();
-    let markout_time = params.markout_time;
-    
-    // Validate pool address early
-    let valid_pools = get_valid_pools();
-    if !valid_pools.contains(&pool_address) {
-        warn!("Invalid pool address requested: {}", pool_address);
-        return Err(StatusCode::BAD_REQUEST);
-    }
+    let markout_time = params.markout_time.to_string();
+    let pool_address = if params.aggregate.unwrap_or(false) {
+        AGGREGATE_POOL_ADDRESS.to_string()
+    } else {
+        let Some(pool_address) = params.pool_address else {
+            warn!("Distribution metrics requested without a pool address or aggregate=true");
+            return Err(StatusCode::BAD_REQUEST);
+        };
+
+        // Validate pool address early
+        if !state.pools.contains(&pool_address) {
+            warn!("Invalid pool address requested: {}", pool_address);
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        pool_address.to_string()
+    };
 
     info!(
         "Fetching distribution metrics for pool: {} (markout_time: {})", 
         pool_address, markout_time
     );
 
-    // Read from precomputed file
-    let bytes = state.store.get(&Path::from("precomputed/distributions/metrics.parquet"))
-        .await
-        .map_err(|e| {
-            error!("Failed to read precomputed distribution metrics: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?
-        .bytes()
-        .await
-        .map_err(|e| {
-            error!("Failed to get bytes from precomputed metrics data: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-
-    let reader = ParquetRecordBatchReader::try_new(bytes, 1024)
-        .map_err(|e| {
-            error!("Failed to create Parquet reader: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    let reader = open_precomputed(&state.store, DatasetKind::DistributionMetrics).await?;
 
     for batch_result in reader {
         let batch = batch_result.map_err(|e| {
@@ -93,9 +81,24 @@ pub async fn get_distribution_metrics(
                 error!("Failed to get kurtosis column: {}", e);
                 StatusCode::INTERNAL_SERVER_ERROR
             })?;
+        // Files written before 1.1 and 1.2 lack the digest-derived columns
+        let has_column = |name: &str| batch.schema().column_with_name(name).is_some();
+        let fractions_above = if has_column("fraction_above_1000_dollars") {
+            Some(get_float64_column(&batch, "fraction_above_1000_dollars")?)
+        } else {
+            None
+        };
+        let (trimmed_means, iqrs) = if has_column("trimmed_mean_cents") && has_column("iqr_cents") {
+            (Some(get_uint64_column(&batch, "trimmed_mean_cents")?), Some(get_uint64_column(&batch, "iqr_cents")?))
+        } else {
+            (None, None)
+        };
+        let nullable = |values: Option<&UInt64Array>, i: usize| {
+            values.filter(|values| values.is_valid(i)).map(|values| values.value(i))
+        };
 
         for i in 0..batch.num_rows() {
-            if pool_addresses.value(i).to_lowercase() == pool_address && 
+            if pool_addresses.value(i).eq_ignore_ascii_case(&pool_address) && 
                markout_times.value(i) == markout_time {
                 
                 info!(
@@ -111,10 +114,15 @@ pub async fn get_distribution_metrics(
                     pool_name: pool_names.value(i).to_string(),
                     pool_address: pool_address.clone(),
                     markout_time: markout_time.clone(),
-                    mean: means.value(i),
-                    std_dev: std_devs.value(i),
+                    mean_dollars: means.value(i),
+                    std_dev_dollars: std_devs.value(i),
                     skewness: skewness.value(i),
-                    kurtosis: kurtosis.value(i)
+                    kurtosis: kurtosis.value(i),
+                    fraction_above_1000_dollars: fractions_above
+                        .filter(|fractions| fractions.is_valid(i))
+                        .map(|fractions| fractions.value(i)),
+                    trimmed_mean_cents: nullable(trimmed_means, i),
+                    iqr_cents: nullable(iqrs, i),
                 }));
             }
         }
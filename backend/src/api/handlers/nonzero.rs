@@ -1,25 +1,22 @@
 use axum::{
-    extract::{State, Query},
+    extract::State,
     response::Json,
     http::StatusCode,
 };
-use crate::{api::handlers::common::{get_float64_column, get_string_column, get_valid_pools, get_uint64_column}, 
+use crate::{ApiQuery, DatasetKind, api::handlers::common::{open_precomputed, get_float64_column, get_string_column, get_uint64_column}, 
     AppState, NonZeroProportionQuery, NonZeroProportionResponse};
 use tracing::{error, info, warn};
 use std::sync::Arc;
-use parquet::arrow::arrow_reader::ParquetRecordBatchReader;
-use object_store::path::Path;
 
 pub async fn get_non_zero_proportion(
     State(state): State<Arc<AppState>>,
-    Query(params): Query<NonZeroProportionQuery>,
+    ApiQuery(params): ApiQuery<NonZeroProportionQuery>,
 ) -> Result<Json<NonZeroProportionResponse>, StatusCode> {
-    let pool_address = params.pool_address.to_lowercase();
-    let markout_time = params.markout_time;
+    let pool_address = params.pool_address;
+    let markout_time = params.markout_time.to_string();
     
     // Early validation of pool address
-    let valid_pools = get_valid_pools();
-    if !valid_pools.contains(&pool_address) {
+    if !state.pools.contains(&pool_address) {
         warn!("Invalid pool address requested: {}", pool_address);
         return Err(StatusCode::BAD_REQUEST);
     }
@@ -29,25 +26,7 @@ pub async fn get_non_zero_proportion(
         pool_address, markout_time
     );
 
-    // Read from precomputed file
-    let bytes = state.store.get(&Path::from("precomputed/pool_metrics/non_zero.parquet"))
-        .await
-        .map_err(|e| {
-            error!("Failed to read precomputed activity metrics: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?
-        .bytes()
-        .await
-        .map_err(|e| {
-            error!("Failed to get bytes from precomputed activity metrics: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-
-    let reader = ParquetRecordBatchReader::try_new(bytes, 1024)
-        .map_err(|e| {
-            error!("Failed to create Parquet reader: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    let reader = open_precomputed(&state.store, DatasetKind::NonZeroProportions).await?;
 
     for batch_result in reader {
         let batch = batch_result.map_err(|e| {
@@ -61,9 +40,16 @@ pub async fn get_non_zero_proportion(
         let non_zero_blocks = get_uint64_column(&batch, "non_zero_blocks")?;
         let total_blocks = get_uint64_column(&batch, "total_blocks")?;
         let non_zero_proportions = get_float64_column(&batch, "non_zero_proportion")?;
+        // Files written before 1.1 lack it; the registry's is what they
+        // should have counted from
+        let deployment_blocks = if batch.schema().column_with_name("deployment_block").is_some() {
+            Some(get_uint64_column(&batch, "deployment_block")?)
+        } else {
+            None
+        };
 
         for i in 0..batch.num_rows() {
-            if pool_addresses.value(i).to_lowercase() == pool_address && 
+            if pool_address.matches(pool_addresses.value(i)) && 
                markout_times.value(i) == markout_time {
                 
                 let pool_name = pool_names.value(i).to_string();
@@ -81,10 +67,14 @@ pub async fn get_non_zero_proportion(
 
                 return Ok(Json(NonZeroProportionResponse {
                     pool_name,
-                    pool_address: pool_address.clone(),
+                    pool_address: pool_address.to_string(),
                     non_zero_proportion: proportion,
                     total_blocks: total_count,
                     non_zero_blocks: non_zero_count,
+                    deployment_block: deployment_blocks.map_or_else(
+                        || state.pools.lookup(&pool_address).map_or(0, |pool| pool.deployment_block),
+                        |blocks| blocks.value(i),
+                    ),
                 }));
             }
         }
@@ -1,35 +1,33 @@
 use axum::{
-    extract::{State, Query},
+    extract::State,
     response::Json,
     http::StatusCode,
 };
-use crate::{AppState, 
-    MERGE_BLOCK, POOL_ADDRESSES,
+use crate::MarkoutTime;
+use crate::{ApiQuery, DatasetKind, AppState, 
+    MERGE_BLOCK,
     PercentileBandQuery, PercentileBandResponse, PercentileDataPoint,
-    api::handlers::common::{get_uint64_column, get_valid_pools, get_string_column, get_float64_column}};
-use tracing::{error, info, warn};
+    api::handlers::common::{read_precomputed_matching, get_uint64_column, get_string_column, get_float64_column}};
+use tracing::{info, warn};
 use std::sync::Arc;
-use parquet::arrow::arrow_reader::ParquetRecordBatchReader;
-use object_store::path::Path;
 
 pub async fn get_percentile_band(
     State(state): State<Arc<AppState>>,
-    Query(params): Query<PercentileBandQuery>,
+    ApiQuery(params): ApiQuery<PercentileBandQuery>,
 ) -> Result<Json<PercentileBandResponse>, StatusCode> {
     let start_block = params.start_block.unwrap_or(*MERGE_BLOCK - 1);
     let end_block = params.end_block.unwrap_or(20_000_000);
-    let markout_time = params.markout_time.unwrap_or_else(|| String::from("brontes"));
+    let markout_time = params.markout_time.unwrap_or(MarkoutTime::Brontes).to_string();
 
     // Determine pool to analyze
-    let pool_filter = if let Some(pool_address) = params.pool_address.clone() {
-        let pool_address = pool_address.to_lowercase();
-        if !get_valid_pools().contains(&pool_address) {
+    let pool_filter = if let Some(pool_address) = params.pool_address {
+        if !state.pools.contains(&pool_address) {
             warn!("Invalid pool address provided: {}", pool_address);
             return Err(StatusCode::BAD_REQUEST);
         }
         pool_address
     } else {
-        POOL_ADDRESSES[0].to_lowercase()
+        state.pools.addresses()[0].clone()
     };
 
     info!(
@@ -37,36 +35,17 @@ pub async fn get_percentile_band(
         pool_filter, start_block, end_block, markout_time
     );
 
-    let bytes = state.store.get(&Path::from("precomputed/distributions/percentile_bands.parquet"))
-        .await
-        .map_err(|e| {
-            error!("Failed to read precomputed percentile distribution data: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?
-        .bytes()
-        .await
-        .map_err(|e| {
-            error!("Failed to get bytes from precomputed percentile data: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-
-    let reader = ParquetRecordBatchReader::try_new(bytes, 1024)
-        .map_err(|e| {
-            error!("Failed to create Parquet reader: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    // Precomputed files store addresses lowercase, as `Address` does
+    let pool = pool_filter.to_string();
+    let filters = [("pool_address", pool.as_str()), ("markout_time", markout_time.as_str())];
+    let batches = read_precomputed_matching(&state.store, DatasetKind::PercentileBands, &filters).await?;
 
     let mut data_points = Vec::new();
     let mut pool_name = String::new();
     let mut max_median = 0f64;
     let mut min_median = f64::MAX;
 
-    for batch_result in reader {
-        let batch = batch_result.map_err(|e| {
-            error!("Failed to read batch: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-
+    for batch in batches {
         let pool_addresses = get_string_column(&batch, "pool_address")?;
         let pool_names = get_string_column(&batch, "pool_name")?;
         let markout_times = get_string_column(&batch, "markout_time")?;
@@ -78,7 +57,6 @@ pub async fn get_percentile_band(
         let percentile_75 = get_float64_column(&batch, "percentile_75_dollars")?;
 
         for i in 0..batch.num_rows() {
-            let current_pool = pool_addresses.value(i).to_lowercase();
             let interval_start = start_blocks.value(i);
             let interval_end = end_blocks.value(i);
             
@@ -87,7 +65,7 @@ pub async fn get_percentile_band(
                 continue;
             }
 
-            if current_pool != pool_filter || markout_times.value(i) != markout_time {
+            if !pool_filter.matches(pool_addresses.value(i)) || markout_times.value(i) != markout_time {
                 continue;
             }
 
@@ -132,7 +110,7 @@ pub async fn get_percentile_band(
 
     Ok(Json(PercentileBandResponse {
         pool_name,
-        pool_address: pool_filter,
+        pool_address: pool_filter.to_string(),
         markout_time,
         data_points,
     }))
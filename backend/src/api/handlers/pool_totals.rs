@@ -1,46 +1,28 @@
 use axum::{
-    extract::{State, Query},
+    extract::State,
     response::Json,
     http::StatusCode,
 };
-use crate::{AppState, 
+use crate::{ApiQuery, Cents, MarkoutTime};
+use crate::{DatasetKind, AppState, 
     PoolTotalsQuery, PoolTotalsResponse, PoolTotal,
-    api::handlers::common::{get_uint64_column, get_string_column}};
+    MonthlyPoolTotalsQuery, MonthlyPoolTotalsResponse, MonthlyPoolTotal,
+    api::handlers::common::{open_precomputed, get_uint64_column, get_int64_column, get_string_column}};
 use tracing::{error, info, warn};
 use std::sync::Arc;
-use parquet::arrow::arrow_reader::ParquetRecordBatchReader;
-use object_store::path::Path;
 
 pub async fn get_pool_totals(
     State(state): State<Arc<AppState>>,
-    Query(params): Query<PoolTotalsQuery>,
+    ApiQuery(params): ApiQuery<PoolTotalsQuery>,
 ) -> Result<Json<PoolTotalsResponse>, StatusCode> {
-    let markout_time = params.markout_time.unwrap_or_else(|| String::from("brontes"));
+    let markout_time = params.markout_time.unwrap_or(MarkoutTime::Brontes).to_string();
     
     info!("Fetching pool performance metrics for markout_time: {}", markout_time);
 
-    // Read from precomputed file
-    let bytes = state.store.get(&Path::from("precomputed/pool_metrics/totals.parquet"))
-        .await
-        .map_err(|e| {
-            error!("Failed to read precomputed pool totals: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?
-        .bytes()
-        .await
-        .map_err(|e| {
-            error!("Failed to get bytes from precomputed pool totals: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-
-    let reader = ParquetRecordBatchReader::try_new(bytes, 1024)
-        .map_err(|e| {
-            error!("Failed to create Parquet reader: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    let reader = open_precomputed(&state.store, DatasetKind::PoolTotals).await?;
 
     let mut pool_totals = Vec::new();
-    let mut total_lvr = 0u64;
+    let mut total_lvr = Cents::ZERO;
 
     for batch_result in reader {
         let batch = batch_result.map_err(|e| {
@@ -51,7 +33,7 @@ pub async fn get_pool_totals(
         let pool_addresses = get_string_column(&batch, "pool_address")?;
         let pool_names = get_string_column(&batch, "pool_name")?;
         let markout_times = get_string_column(&batch, "markout_time")?;
-        let total_lvr_cents = get_uint64_column(&batch, "total_lvr_cents")?;
+        let total_lvr_cents = get_int64_column(&batch, "total_lvr_cents")?;
         let non_zero_blocks = get_uint64_column(&batch, "non_zero_blocks")?;
 
         for i in 0..batch.num_rows() {
@@ -60,7 +42,7 @@ pub async fn get_pool_totals(
                 continue;
             }
 
-            let lvr = total_lvr_cents.value(i);
+            let lvr = Cents(total_lvr_cents.value(i));
             total_lvr = total_lvr.saturating_add(lvr);
 
             // Only include pools with activity
@@ -75,7 +57,7 @@ pub async fn get_pool_totals(
     }
 
     // Sort by total LVR cents descending for consistent ordering
-    pool_totals.sort_by(|a, b| b.total_lvr_cents.cmp(&a.total_lvr_cents));
+    pool_totals.sort_by_key(|p| std::cmp::Reverse(p.total_lvr_cents));
 
     if pool_totals.is_empty() {
         warn!(
@@ -87,9 +69,61 @@ pub async fn get_pool_totals(
             "Found {} active pools for markout time {}. Total LVR: ${:.2}", 
             pool_totals.len(),
             markout_time,
-            total_lvr as f64 / 100.0
+            total_lvr.to_dollars()
         );
     }
 
     Ok(Json(PoolTotalsResponse { totals: pool_totals }))
-}
\ No newline at end of file
+}
+
+pub async fn get_monthly_pool_totals(
+    State(state): State<Arc<AppState>>,
+    ApiQuery(params): ApiQuery<MonthlyPoolTotalsQuery>,
+) -> Result<Json<MonthlyPoolTotalsResponse>, StatusCode> {
+    let pool_address = params.pool_address;
+    let markout_time = params.markout_time.unwrap_or(MarkoutTime::Brontes).to_string();
+
+    info!("Fetching monthly LVR totals for pool {} and markout_time: {}", pool_address, markout_time);
+
+    let reader = open_precomputed(&state.store, DatasetKind::MonthlyPoolTotals).await?;
+
+    // Rows are stored chronologically per pool, so file order is kept
+    let mut monthly_totals = Vec::new();
+
+    for batch_result in reader {
+        let batch = batch_result.map_err(|e| {
+            error!("Failed to read batch: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+        let time_ranges = get_string_column(&batch, "time_range")?;
+        let pool_addresses = get_string_column(&batch, "pool_address")?;
+        let markout_times = get_string_column(&batch, "markout_time")?;
+        let total_lvr_cents = get_int64_column(&batch, "total_lvr_cents")?;
+
+        for i in 0..batch.num_rows() {
+            if !pool_address.matches(pool_addresses.value(i)) || markout_times.value(i) != markout_time {
+                continue;
+            }
+
+            monthly_totals.push(MonthlyPoolTotal {
+                time_range: time_ranges.value(i).to_string(),
+                total_lvr_cents: Cents(total_lvr_cents.value(i)),
+            });
+        }
+    }
+
+    if monthly_totals.is_empty() {
+        warn!(
+            "No monthly totals found for pool {} and markout_time: {}",
+            pool_address, markout_time
+        );
+    }
+
+    Ok(Json(MonthlyPoolTotalsResponse {
+        pool_name: state.pools.pool_name(pool_address.as_str()),
+        pool_address: pool_address.to_string(),
+        markout_time,
+        monthly_totals,
+    }))
+}
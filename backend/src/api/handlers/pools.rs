@@ -0,0 +1,25 @@
+use axum::{
+    extract::State,
+    response::Json,
+};
+use crate::{AppState, PoolInfo, PoolsResponse};
+use std::sync::Arc;
+
+/// Lists the registry the server was started with, in registry order
+pub async fn get_pools(State(state): State<Arc<AppState>>) -> Json<PoolsResponse> {
+    let pools = state.pools.pools()
+        .iter()
+        .zip(state.pools.addresses())
+        .map(|(pool, address)| PoolInfo {
+            pool_address: address.to_string(),
+            pool_name: pool.name.clone(),
+            cluster: pool.cluster.clone(),
+            deployment_block: pool.deployment_block,
+            brontes_tracked: pool.brontes_tracked,
+            token0_decimals: pool.token0_decimals,
+            token1_decimals: pool.token1_decimals,
+        })
+        .collect();
+
+    Json(PoolsResponse { pools })
+}
@@ -1,56 +1,44 @@
 use axum::{
-    extract::{State, Query},
+    extract::State,
     response::Json,
     http::StatusCode,
 };
-use crate::{
+use crate::MarkoutTime;
+use crate::{ApiQuery, DatasetKind, 
     AppState,
-    api::handlers::common::{get_uint64_column, get_string_column, get_valid_pools},
-    QuartilePlotResponse, QuartilePlotQuery
+    api::handlers::common::{open_precomputed, get_uint64_column, get_string_column},
+    QuartilePlotResponse, QuartilePlotQuery, AGGREGATE_POOL_ADDRESS,
 };
 use tracing::{error, info, warn};
 use std::sync::Arc;
-use parquet::arrow::arrow_reader::ParquetRecordBatchReader;
-use object_store::path::Path;
 
 pub async fn get_quartile_plot(
     State(state): State<Arc<AppState>>,
-    Query(params): Query<QuartilePlotQuery>,
+    ApiQuery(params): ApiQuery<QuartilePlotQuery>,
 ) -> Result<Json<QuartilePlotResponse>, StatusCode> {
-    let pool_address = params.pool_address.to_lowercase();
-    let markout_time = params.markout_time.unwrap_or_else(|| String::from("brontes"));
+    let markout_time = params.markout_time.unwrap_or(MarkoutTime::Brontes).to_string();
+    let pool_address = if params.aggregate.unwrap_or(false) {
+        AGGREGATE_POOL_ADDRESS.to_string()
+    } else {
+        let Some(pool_address) = params.pool_address else {
+            warn!("Quartile plot requested without a pool address or aggregate=true");
+            return Err(StatusCode::BAD_REQUEST);
+        };
 
-    // Validate pool address early
-    let valid_pools = get_valid_pools();
-    if !valid_pools.contains(&pool_address) {
-        warn!("Invalid pool address provided: {}", pool_address);
-        return Err(StatusCode::BAD_REQUEST);
-    }
+        // Validate pool address early
+        if !state.pools.contains(&pool_address) {
+            warn!("Invalid pool address provided: {}", pool_address);
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        pool_address.to_string()
+    };
 
     info!(
         "Analyzing distribution metrics for pool {} with markout time: {}", 
         pool_address, markout_time
     );
 
-    // Read from precomputed file
-    let bytes = state.store.get(&Path::from("precomputed/distributions/quartile_plots.parquet"))
-        .await
-        .map_err(|e| {
-            error!("Failed to read precomputed quartile metrics: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?
-        .bytes()
-        .await
-        .map_err(|e| {
-            error!("Failed to get bytes from precomputed quartile data: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-
-    let reader = ParquetRecordBatchReader::try_new(bytes, 1024)
-        .map_err(|e| {
-            error!("Failed to create Parquet reader: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    let reader = open_precomputed(&state.store, DatasetKind::QuartilePlots).await?;
 
     for batch_result in reader {
         let batch = batch_result.map_err(|e| {
@@ -66,10 +54,8 @@ pub async fn get_quartile_plot(
         let percentile_75 = get_uint64_column(&batch, "percentile_75_cents")?;
 
         for i in 0..batch.num_rows() {
-            let current_pool = pool_addresses.value(i).to_lowercase();
-            
             // Filter by pool and markout time
-            if current_pool != pool_address || markout_times.value(i) != markout_time {
+            if !pool_addresses.value(i).eq_ignore_ascii_case(&pool_address) || markout_times.value(i) != markout_time {
                 continue;
             }
 
@@ -84,7 +70,7 @@ pub async fn get_quartile_plot(
 
             return Ok(Json(QuartilePlotResponse {
                 pool_name: pool_names.value(i).to_string(),
-                pool_address: current_pool,
+                pool_address,
                 markout_time,
                 percentile_25_cents: percentile_25.value(i),
                 median_cents: median.value(i),
@@ -0,0 +1,114 @@
+use axum::{
+    extract::State,
+    response::Json,
+    http::StatusCode,
+};
+use crate::{ApiQuery, Cents, api::handlers::common::{get_int64_column, get_string_column, get_uint64_column},
+    AppState, parquet_reader, RawLvrPoint, RawSeriesQuery, RawSeriesResponse,
+    RAW_BLOCK_NUMBER_COLUMN, RAW_LVR_COLUMN, RAW_MARKOUT_TIME_COLUMN};
+use futures::StreamExt;
+use object_store::path::Path;
+use tracing::{error, info, warn};
+use std::sync::Arc;
+
+/// Chunk range of a `raw/{pool}/{start}_{end}.parquet` file name
+fn raw_file_range(path: &Path) -> Option<(u64, u64)> {
+    let (start, end) = path.filename()?.strip_suffix(".parquet")?.split_once('_')?;
+    Some((start.parse().ok()?, end.parse().ok()?))
+}
+
+pub async fn get_raw_series(
+    State(state): State<Arc<AppState>>,
+    ApiQuery(params): ApiQuery<RawSeriesQuery>,
+) -> Result<Json<RawSeriesResponse>, StatusCode> {
+    let pool_address = params.pool_address;
+    let start_block = params.start_block.unwrap_or(0);
+    let end_block = params.end_block.unwrap_or(u64::MAX);
+
+    if !state.pools.contains(&pool_address) {
+        warn!("Invalid pool address requested: {}", pool_address);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if start_block >= end_block {
+        warn!("Empty block range requested: {}..{}", start_block, end_block);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    info!(
+        "Fetching raw series for pool {} in blocks {}..{} (markout_time: {:?})",
+        pool_address, start_block, end_block, params.markout_time
+    );
+
+    // Only the files overlapping the requested range are read
+    let mut files = Vec::new();
+    let mut listing = state.store.list(Some(&Path::from(format!("raw/{}", pool_address))));
+    while let Some(meta) = listing.next().await {
+        let meta = meta.map_err(|e| {
+            error!("Failed to list raw files: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+        if raw_file_range(&meta.location).is_some_and(|(file_start, file_end)| file_start < end_block && file_end > start_block) {
+            files.push(meta.location);
+        }
+    }
+
+    if files.is_empty() {
+        warn!("No raw series stored for pool {} in blocks {}..{}", pool_address, start_block, end_block);
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let mut points = Vec::new();
+    for path in files {
+        let bytes = state.store.get(&path)
+            .await
+            .map_err(|e| {
+                error!("Failed to read raw file {}: {}", path, e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?
+            .bytes()
+            .await
+            .map_err(|e| {
+                error!("Failed to get bytes from raw file {}: {}", path, e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+        let reader = parquet_reader(bytes, &path, 1024).map_err(|e| {
+            error!("{:#}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+        for batch_result in reader {
+            let batch = batch_result.map_err(|e| {
+                error!("Failed to read batch: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+
+            let block_numbers = get_uint64_column(&batch, RAW_BLOCK_NUMBER_COLUMN)?;
+            let markout_times = get_string_column(&batch, RAW_MARKOUT_TIME_COLUMN)?;
+            let lvr_values = get_int64_column(&batch, RAW_LVR_COLUMN)?;
+
+            for i in 0..batch.num_rows() {
+                let block_number = block_numbers.value(i);
+                if block_number < start_block || block_number >= end_block {
+                    continue;
+                }
+                if params.markout_time.is_some_and(|markout| markout.to_string() != markout_times.value(i)) {
+                    continue;
+                }
+
+                points.push(RawLvrPoint {
+                    block_number,
+                    markout_time: markout_times.value(i).to_string(),
+                    lvr_cents: Cents(lvr_values.value(i)),
+                });
+            }
+        }
+    }
+
+    points.sort_by(|a, b| a.block_number.cmp(&b.block_number).then_with(|| a.markout_time.cmp(&b.markout_time)));
+
+    Ok(Json(RawSeriesResponse {
+        pool_name: state.pools.pool_name(pool_address.as_str()),
+        pool_address: pool_address.to_string(),
+        points,
+    }))
+}
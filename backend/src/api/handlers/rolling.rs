@@ -0,0 +1,89 @@
+use axum::{
+    extract::State,
+    response::Json,
+    http::StatusCode,
+};
+use crate::{ApiQuery, Cents, MarkoutTime};
+use crate::{DatasetKind, api::handlers::common::{open_precomputed, get_string_column, get_uint64_column, get_int64_column},
+    AppState, RollingSeriesQuery, RollingSeriesResponse, RollingPoint, ROLLING_WINDOW_INTERVALS};
+use tracing::{error, info, warn};
+use std::sync::Arc;
+use arrow::array::Array;
+
+pub async fn get_rolling_series(
+    State(state): State<Arc<AppState>>,
+    ApiQuery(params): ApiQuery<RollingSeriesQuery>,
+) -> Result<Json<RollingSeriesResponse>, StatusCode> {
+    let window = params.window.unwrap_or(ROLLING_WINDOW_INTERVALS);
+    let markout_time = params.markout_time.unwrap_or(MarkoutTime::Brontes).to_string();
+    let pool_address = params.pool_address;
+
+    if let Some(pool_address) = &pool_address {
+        if !state.pools.contains(pool_address) {
+            warn!("Invalid pool address requested: {}", pool_address);
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
+
+    info!(
+        "Fetching {}-interval rolling series for pool {:?} (markout_time: {})",
+        window, pool_address, markout_time
+    );
+
+    // Each window size is its own precomputed file
+    let reader = open_precomputed(&state.store, DatasetKind::RollingSeries { window }).await?;
+
+    let mut points = Vec::new();
+
+    for batch_result in reader {
+        let batch = batch_result.map_err(|e| {
+            error!("Failed to read batch: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+        let end_blocks = get_uint64_column(&batch, "end_block")?;
+        let pool_addresses = get_string_column(&batch, "pool_address")?;
+        let markout_times = get_string_column(&batch, "markout_time")?;
+        let window_intervals = get_uint64_column(&batch, "window_intervals")?;
+        let rolling_totals = get_int64_column(&batch, "rolling_total_cents")?;
+
+        for i in 0..batch.num_rows() {
+            if markout_times.value(i) != markout_time {
+                continue;
+            }
+
+            // A null pool address marks the aggregate series
+            let row_pool = (!pool_addresses.is_null(i)).then(|| pool_addresses.value(i));
+            let matches = match (&pool_address, row_pool) {
+                (Some(address), Some(row_pool)) => address.matches(row_pool),
+                (None, None) => true,
+                _ => false,
+            };
+            if !matches {
+                continue;
+            }
+
+            points.push(RollingPoint {
+                end_block: end_blocks.value(i),
+                window_intervals: window_intervals.value(i),
+                rolling_total_cents: Cents(rolling_totals.value(i)),
+            });
+        }
+    }
+
+    points.sort_by_key(|point| point.end_block);
+
+    if points.is_empty() {
+        warn!(
+            "No rolling series data found for pool {:?} (markout_time: {})",
+            pool_address, markout_time
+        );
+    }
+
+    Ok(Json(RollingSeriesResponse {
+        window,
+        markout_time,
+        pool_address: pool_address.map(|address| address.to_string()),
+        points,
+    }))
+}
@@ -1,20 +1,18 @@
 use axum::{
-    extract::{State, Query},
+    extract::State,
     response::Json,
     http::StatusCode,
 };
-use crate::{AppState, 
+use crate::{ApiQuery, Cents, DatasetKind, AppState, 
     TimeRangeQuery, RunningTotal, 
-    MERGE_BLOCK, api::handlers::common::{get_uint64_column, get_valid_pools, get_pool_name,
+    MERGE_BLOCK, api::handlers::common::{open_precomputed, open_precomputed_at, get_uint64_column, get_int64_column,
     get_string_column}};
 use tracing::{error, info, warn};
 use std::sync::Arc;
-use parquet::arrow::arrow_reader::ParquetRecordBatchReader;
-use object_store::path::Path;
 
 pub async fn get_running_total(
     State(state): State<Arc<AppState>>,
-    Query(params): Query<TimeRangeQuery>,
+    ApiQuery(params): ApiQuery<TimeRangeQuery>,
 ) -> Result<Json<Vec<RunningTotal>>, StatusCode> {
     let start_block = params.start_block.unwrap_or(*MERGE_BLOCK - 1);
     let end_block = params.end_block.unwrap_or(20_000_000);
@@ -28,8 +26,7 @@ pub async fn get_running_total(
 
     // Pool validation when specified
     if let Some(ref pool) = params.pool {
-        let valid_pools = get_valid_pools();
-        if !valid_pools.contains(&pool.to_lowercase()) {
+        if !state.pools.contains(pool) {
             warn!("Invalid pool address provided: {}", pool);
             return Err(StatusCode::BAD_REQUEST);
         }
@@ -44,7 +41,7 @@ pub async fn get_running_total(
     );
 
     let results = if is_aggregate {
-        read_aggregate_running_totals(&state, start_block, end_block, params.markout_time).await?
+        read_aggregate_running_totals(&state, start_block, end_block, params.markout_time.map(|markout| markout.to_string())).await?
     } else {
         read_individual_running_totals(&state, start_block, end_block, &params).await?
     };
@@ -60,24 +57,7 @@ async fn read_aggregate_running_totals(
     markout_filter: Option<String>,
 ) -> Result<Vec<RunningTotal>, StatusCode> {
     // Read from precomputed aggregate file
-    let bytes = state.store.get(&Path::from("precomputed/running_totals/aggregate.parquet"))
-        .await
-        .map_err(|e| {
-            error!("Failed to read precomputed aggregate running totals: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?
-        .bytes()
-        .await
-        .map_err(|e| {
-            error!("Failed to get bytes from precomputed aggregate data: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-
-    let reader = ParquetRecordBatchReader::try_new(bytes, 1024)
-        .map_err(|e| {
-            error!("Failed to create Parquet reader: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    let reader = open_precomputed(&state.store, DatasetKind::AggregateRunningTotals).await?;
 
     let mut results = Vec::new();
 
@@ -89,7 +69,7 @@ async fn read_aggregate_running_totals(
 
         let block_numbers = get_uint64_column(&batch, "block_number")?;
         let markout_times = get_string_column(&batch, "markout_time")?;
-        let running_totals = get_uint64_column(&batch, "running_total_cents")?;
+        let running_totals = get_int64_column(&batch, "running_total_cents")?;
 
         for i in 0..batch.num_rows() {
             let block_number = block_numbers.value(i);
@@ -113,7 +93,7 @@ async fn read_aggregate_running_totals(
                 markout: markout_time,
                 pool_name: None,
                 pool_address: None,
-                running_total_cents: running_totals.value(i),
+                running_total_cents: Cents(running_totals.value(i)),
             });
         }
     }
@@ -122,7 +102,7 @@ async fn read_aggregate_running_totals(
     results.sort_by(|a, b| {
         a.block_number
             .cmp(&b.block_number)
-            .then_with(|| a.markout.to_lowercase().cmp(&b.markout.to_lowercase()))
+            .then_with(|| a.markout.cmp(&b.markout))
     });
 
     Ok(results)
@@ -134,25 +114,19 @@ async fn read_individual_running_totals(
     end_block: u64,
     params: &TimeRangeQuery,
 ) -> Result<Vec<RunningTotal>, StatusCode> {
-    // Read from precomputed individual file
-    let bytes = state.store.get(&Path::from("precomputed/running_totals/individual.parquet"))
-        .await
-        .map_err(|e| {
-            error!("Failed to read precomputed individual running totals: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?
-        .bytes()
-        .await
-        .map_err(|e| {
-            error!("Failed to get bytes from precomputed individual data: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-
-    let reader = ParquetRecordBatchReader::try_new(bytes, 1024)
-        .map_err(|e| {
-            error!("Failed to create Parquet reader: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    // Only the requested pool's partition is downloaded; data precomputed
+    // before partitioning only has the combined file
+    let kind = DatasetKind::IndividualRunningTotals;
+    let reader = match &params.pool {
+        Some(pool) => match open_precomputed_at(&state.store, kind, &DatasetKind::pool_partition_path(pool)).await {
+            Err(StatusCode::NOT_FOUND) => {
+                warn!("No running total partition for pool {}; reading the combined file", pool);
+                open_precomputed(&state.store, kind).await?
+            }
+            result => result?,
+        },
+        None => open_precomputed(&state.store, kind).await?,
+    };
 
     let mut results = Vec::new();
 
@@ -165,7 +139,7 @@ async fn read_individual_running_totals(
         let block_numbers = get_uint64_column(&batch, "block_number")?;
         let markout_times = get_string_column(&batch, "markout_time")?;
         let pool_addresses = get_string_column(&batch, "pool_address")?;
-        let running_totals = get_uint64_column(&batch, "running_total_cents")?;
+        let running_totals = get_int64_column(&batch, "running_total_cents")?;
 
         for i in 0..batch.num_rows() {
             let block_number = block_numbers.value(i);
@@ -176,28 +150,29 @@ async fn read_individual_running_totals(
             }
 
             let markout_time = markout_times.value(i).to_string();
-            let pool_address = pool_addresses.value(i).to_lowercase();
 
             // Apply markout time filter if specified
-            if let Some(ref filter) = params.markout_time {
-                if filter != &markout_time {
+            if let Some(filter) = params.markout_time {
+                if filter.to_string() != markout_time {
                     continue;
                 }
             }
 
             // Apply pool filter
             if let Some(ref requested_pool) = params.pool {
-                if requested_pool.to_lowercase() != pool_address {
+                if !requested_pool.matches(pool_addresses.value(i)) {
                     continue;
                 }
             }
 
+            let pool_address = pool_addresses.value(i);
+
             results.push(RunningTotal {
                 block_number,
                 markout: markout_time,
-                pool_name: Some(get_pool_name(&pool_address)),
-                pool_address: Some(pool_address),
-                running_total_cents: running_totals.value(i),
+                pool_name: Some(state.pools.pool_name(pool_address)),
+                pool_address: Some(pool_address.to_string()),
+                running_total_cents: Cents(running_totals.value(i)),
             });
         }
     }
@@ -206,7 +181,7 @@ async fn read_individual_running_totals(
     results.sort_by(|a, b| {
         a.block_number
             .cmp(&b.block_number)
-            .then_with(|| a.markout.to_lowercase().cmp(&b.markout.to_lowercase()))
+            .then_with(|| a.markout.cmp(&b.markout))
             .then(a.pool_name.cmp(&b.pool_name))
     });
 
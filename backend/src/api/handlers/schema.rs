@@ -0,0 +1,54 @@
+use axum::{
+    extract::State,
+    response::Json,
+    http::StatusCode,
+};
+use crate::{AppState, DatasetKind, DatasetSchemaInfo, SchemaResponse, read_footer_metadata};
+use tracing::{error, info};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+pub async fn get_schema(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<SchemaResponse>, StatusCode> {
+    info!("Fetching precomputed dataset schema metadata");
+
+    let mut datasets = Vec::with_capacity(DatasetKind::ALL.len());
+
+    for kind in DatasetKind::ALL {
+        let path = kind.path();
+        let bytes = match state.store.get(&path).await {
+            Ok(result) => Some(result.bytes().await.map_err(|e| {
+                error!("Failed to get bytes from precomputed file {}: {}", path, e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?),
+            Err(object_store::Error::NotFound { .. }) => None,
+            Err(e) => {
+                error!("Failed to read precomputed file {}: {}", path, e);
+                return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            }
+        };
+
+        let present = bytes.is_some();
+        let (metadata, message) = match bytes.map(read_footer_metadata) {
+            Some(Ok(metadata)) => {
+                let message = kind.check_footer(&metadata).err().map(|e| e.to_string());
+                (metadata, message)
+            }
+            Some(Err(e)) => (HashMap::new(), Some(format!("{:#}", e))),
+            None => (HashMap::new(), None),
+        };
+
+        datasets.push(DatasetSchemaInfo {
+            dataset: kind.name().to_string(),
+            path: path.to_string(),
+            expected_schema_version: kind.schema_version().to_string(),
+            present,
+            needs_regeneration: message.is_some(),
+            metadata,
+            message,
+        });
+    }
+
+    Ok(Json(SchemaResponse { datasets }))
+}
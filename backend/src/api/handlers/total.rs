@@ -3,11 +3,9 @@ use axum::{
     response::Json,
     http::StatusCode,
 };
-use crate::{AppState, api::handlers::common::get_string_column, TotalLVRResponse, MarkoutTotal};
+use crate::{DatasetKind, AppState, api::handlers::common::{open_precomputed, get_string_column}, TotalLVRResponse, MarkoutTotal};
 use tracing::{error, info};
 use std::sync::Arc;
-use parquet::arrow::arrow_reader::ParquetRecordBatchReader;
-use object_store::path::Path;
 
 
 pub async fn get_total_lvr(
@@ -16,24 +14,7 @@ pub async fn get_total_lvr(
     info!("Fetching latest LVR totals across all markout times (excluding Brontes)");
     
     // Read from precomputed aggregate file
-    let bytes = state.store.get(&Path::from("precomputed/running_totals/aggregate.parquet"))
-        .await
-        .map_err(|e| {
-            error!("Failed to read precomputed aggregate running totals: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?
-        .bytes()
-        .await
-        .map_err(|e| {
-            error!("Failed to get bytes from precomputed aggregate data: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-
-    let reader = ParquetRecordBatchReader::try_new(bytes, 1024)
-        .map_err(|e| {
-            error!("Failed to create Parquet reader: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    let reader = open_precomputed(&state.store, DatasetKind::AggregateRunningTotals).await?;
 
     // Track the latest block number for each markout time
     let mut latest_blocks: std::collections::HashMap<String, u64> = std::collections::HashMap::new();
@@ -52,7 +33,7 @@ pub async fn get_total_lvr(
             let markout_time = markout_times.value(i).to_string();
             
             // Skip Brontes
-            if markout_time.to_lowercase() == "brontes" {
+            if markout_time.eq_ignore_ascii_case("brontes") {
                 continue;
             }
             
@@ -67,24 +48,7 @@ pub async fn get_total_lvr(
     }
 
     // Now read the file again to get the total for each markout time at its latest block
-    let bytes = state.store.get(&Path::from("precomputed/running_totals/aggregate.parquet"))
-        .await
-        .map_err(|e| {
-            error!("Failed to read precomputed aggregate running totals: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?
-        .bytes()
-        .await
-        .map_err(|e| {
-            error!("Failed to get bytes from precomputed aggregate data: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-
-    let reader = ParquetRecordBatchReader::try_new(bytes, 1024)
-        .map_err(|e| {
-            error!("Failed to create Parquet reader: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    let reader = open_precomputed(&state.store, DatasetKind::AggregateRunningTotals).await?;
 
     let mut markout_totals = Vec::new();
 
@@ -96,13 +60,13 @@ pub async fn get_total_lvr(
 
         let block_numbers = crate::api::handlers::common::get_uint64_column(&batch, "block_number")?;
         let markout_times = get_string_column(&batch, "markout_time")?;
-        let running_totals = crate::api::handlers::common::get_uint64_column(&batch, "running_total_cents")?;
+        let running_totals = crate::api::handlers::common::get_int64_column(&batch, "running_total_cents")?;
 
         for i in 0..batch.num_rows() {
             let markout_time = markout_times.value(i).to_string();
             
             // Skip Brontes
-            if markout_time.to_lowercase() == "brontes" {
+            if markout_time.eq_ignore_ascii_case("brontes") {
                 continue;
             }
             
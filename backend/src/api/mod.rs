@@ -18,11 +18,12 @@ use std::net::SocketAddr;
 use object_store::ObjectStore;
 use tracing::info;
 use anyhow::Result;
+use crate::PoolRegistry;
 use std::time::Duration;
 
-pub async fn serve(host: String, port: u16, store: Arc<dyn ObjectStore>) -> Result<()> {
+pub async fn serve(host: String, port: u16, store: Arc<dyn ObjectStore>, pools: Arc<PoolRegistry>) -> Result<()> {
     // Create application state
-    let state = Arc::new(AppState::new(store));
+    let state = Arc::new(AppState::new(store).with_pool_registry(pools));
 
     // Configure CORS
     let cors = CorsLayer::new()
@@ -41,11 +42,18 @@ pub async fn serve(host: String, port: u16, store: Arc<dyn ObjectStore>) -> Resu
     let app = Router::new()
         // Core endpoints
         .route("/health", get(health_check))
+        .route("/schema", get(get_schema))
+        .route("/pools", get(get_pools))
         
         // Data analysis endpoints
         .route("/running_total", get(get_running_total))
         //.route("/regression", get(get_markout_regression))
         .route("/pool_totals", get(get_pool_totals))
+        .route("/pool_totals/monthly", get(get_monthly_pool_totals))
+        .route("/concentration", get(get_concentration))
+        .route("/activity_runs", get(get_activity_runs))
+        .route("/correlations", get(get_correlations))
+        .route("/rolling_series", get(get_rolling_series))
         .route("/markout_totals", get(get_total_lvr))
         .route("/max_lvr", get(get_max_lvr))
         .route("/histogram", get(get_lvr_histogram))
@@ -53,6 +61,7 @@ pub async fn serve(host: String, port: u16, store: Arc<dyn ObjectStore>) -> Resu
         .route("/percentile_band", get(get_percentile_band))
         .route("/quartile_plot", get(get_quartile_plot))
         .route("/metrics", get(get_distribution_metrics))
+        .route("/raw_series", get(get_raw_series))
         
         // Cluster analysis endpoints
         .route("/clusters/pie", get(get_cluster_proportion))
@@ -1,145 +1,571 @@
 use arrow::{
-    array::{StringArray, UInt64Array, Float64Array, Int64Array},
+    array::{StringArray, UInt32Array, UInt64Array, Float64Array, Int64Array},
+    compute::take_record_batch,
     record_batch::RecordBatch,
-    datatypes::DataType
+    row::{RowConverter, SortField},
+    datatypes::Schema
 };
+use chrono::{DateTime, Utc};
 use object_store::{path::Path, ObjectStore};
 use parquet::{
-    arrow::{ArrowWriter, arrow_reader::ParquetRecordBatchReader},
-    basic::Compression,
+    arrow::ArrowWriter,
     file::properties::WriterProperties,
+    format::SortingColumn,
 };
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::{sync::{OnceCell, Semaphore}, task::JoinSet};
 use anyhow::Context;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use bytes::Bytes;
 use tracing::{info, warn, debug, error};
 use futures::StreamExt;
 use crate::{
-    api::handlers::*,
-    POOL_NAMES, INTERVAL_RANGES,
-    common::{BLOCKS_PER_INTERVAL, FINAL_INTERVAL_FILE,
-        get_string_column, get_uint64_column, get_valid_pools, get_column_value, get_pool_name, get_float64_column}
+    writer::{list_checkpoints, list_interval_files, parse_interval_path, CLUSTER_ACTIVITY_PATH},
+    storage::{parquet_reader, retry_atomic_put, BufferPool, RetryPolicy},
+    config::{OutputLayout, ParquetWriteOptions},
+    schema::*,
+    models::{Address, CheckpointSnapshot, TRIMMED_MEAN_CUT},
+    tdigest::{DistributionMetrics, OnlineStats, TDigest},
+    INTERVAL_RANGES, PoolRegistry,
+    common::{get_string_column, interval_block_ranges, get_uint64_column, get_int64_column, get_float64_column,
+        BucketCounts, BUCKET_CONFIG}
 };
 use arrow::array::Array;
 
+// (total_lvr_cents, non_zero_count, total_count) for a single interval row
+type IntervalPoint = (u64, u64, u64);
+
+/// Every precomputation task, in the order they are spawned by `run_all`.
+/// Tasks only read `intervals/`, `checkpoints/` and `cluster_activity/` and
+/// each writes its own output file, so they are free to run concurrently.
+pub const PRECOMPUTE_TASKS: &[&str] = &[
+    "running_totals",
+    "pool_totals",
+    "max_lvr",
+    "non_zero_proportions",
+    "histograms",
+    "equidepth_histograms",
+    "percentile_bands",
+    "quartile_plots",
+    "daily_time_series",
+    "rolling_series",
+    "cluster_proportions",
+    "cluster_histograms",
+    "cluster_non_zero",
+    "monthly_cluster_totals",
+    "monthly_pool_totals",
+    "concentration_metrics",
+    "activity_runs",
+    "pool_correlations",
+    "distribution_metrics",
+];
+
+/// `pool_address` of the per-markout rows and checkpoints combining every pool
+pub const AGGREGATE_POOL_ADDRESS: &str = "__aggregate__";
+
+/// LVR, in dollars, above which `fraction_above_1000_dollars` counts a block
+pub const LARGE_LVR_THRESHOLD_DOLLARS: f64 = 1000.0;
+
+/// Equal-probability bins per pool and markout in the equi-depth histograms
+pub const EQUIDEPTH_BINS: usize = 20;
+
+/// Window, in daily intervals, of the rolling series written by `run_all`
+pub const ROLLING_WINDOW_INTERVALS: usize = 7;
+
+/// Fraction of the digest's non-zero blocks with LVR above `dollars`, or
+/// `None` for an empty digest
+pub fn fraction_above(digest: &TDigest, dollars: f64) -> Option<f64> {
+    digest.cdf(dollars).map(|below| 1.0 - below)
+}
+
+/// Rolling sums of `values` over the last `window` points of `timeline`
+/// (sorted interval end blocks), as (end_block, window_intervals, total).
+/// A series starts at its first non-zero value, so windows covering earlier
+/// intervals shrink instead of counting pre-deployment zeros.
+pub fn rolling_sums(timeline: &[u64], values: &BTreeMap<u64, i64>, window: usize) -> Vec<(u64, u64, i64)> {
+    let Some((&first_block, _)) = values.iter().find(|(_, &v)| v != 0) else {
+        return Vec::new();
+    };
+    let start = timeline.partition_point(|&block| block < first_block);
+
+    let mut sums = Vec::with_capacity(timeline.len() - start);
+    let mut total = 0i64;
+    for (i, &end_block) in timeline.iter().enumerate().skip(start) {
+        total += values.get(&end_block).copied().unwrap_or(0);
+        if i >= start + window {
+            total -= values.get(&timeline[i - window]).copied().unwrap_or(0);
+        }
+        let window_intervals = (i + 1 - start).min(window) as u64;
+        sums.push((end_block, window_intervals, total));
+    }
+    sums
+}
+
+/// Stable sort of `batch` by `columns`, ascending with nulls first
+fn sort_batch(batch: &RecordBatch, columns: &[&str]) -> Result<RecordBatch, anyhow::Error> {
+    let schema = batch.schema();
+    let mut fields = Vec::with_capacity(columns.len());
+    let mut arrays = Vec::with_capacity(columns.len());
+    for name in columns {
+        let index = schema.index_of(name)?;
+        fields.push(SortField::new(schema.field(index).data_type().clone()));
+        arrays.push(batch.column(index).clone());
+    }
+
+    let rows = RowConverter::new(fields)?.convert_columns(&arrays)?;
+    let mut indices: Vec<u32> = (0..batch.num_rows() as u32).collect();
+    indices.sort_by(|&a, &b| rows.row(a as usize).cmp(&rows.row(b as usize)));
+    Ok(take_record_batch(batch, &UInt32Array::from(indices))?)
+}
+
+/// Lifetime totals of one pool at one markout time
+struct PoolTotalRow {
+    pool_address: Address,
+    pool_name: String,
+    markout_time: String,
+    /// Net of negative LVR
+    total_lvr_cents: i64,
+    non_zero_blocks: u64,
+    total_blocks: u64,
+}
+
+/// How concentrated LVR is across pools, computed from per-pool totals
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConcentrationMetrics {
+    /// Herfindahl–Hirschman index: the sum of squared pool shares, in (0, 1]
+    pub hhi: f64,
+    pub top_1_share: f64,
+    pub top_3_share: f64,
+    pub top_5_share: f64,
+    /// `1 / hhi`, the number of equally sized pools with the same HHI
+    pub effective_pools: f64,
+}
+
+impl ConcentrationMetrics {
+    /// Returns `None` when the totals sum to zero and shares are undefined
+    pub fn from_totals(totals: &[u64]) -> Option<Self> {
+        let sum: u64 = totals.iter().sum();
+        if sum == 0 {
+            return None;
+        }
+
+        let mut shares: Vec<f64> = totals.iter().map(|&t| t as f64 / sum as f64).collect();
+        shares.sort_by(|a, b| b.total_cmp(a));
+        let top_share = |n: usize| shares.iter().take(n).sum::<f64>();
+        let hhi: f64 = shares.iter().map(|s| s * s).sum();
+
+        Some(Self {
+            hhi,
+            top_1_share: top_share(1),
+            top_3_share: top_share(3),
+            top_5_share: top_share(5),
+            effective_pools: 1.0 / hhi,
+        })
+    }
+}
+
+/// Run lengths of a pool's consecutive active (non-zero LVR) and dry
+/// intervals, measured in intervals
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ActivityRuns {
+    /// Position in the series of the pool's first active interval
+    pub first_active_interval: usize,
+    pub dry_spells: u64,
+    pub longest_dry_spell: u64,
+    pub mean_dry_spell: f64,
+    pub longest_active_streak: u64,
+}
+
+impl ActivityRuns {
+    /// `activity` holds one flag per interval in block order. Intervals
+    /// before the first active one predate the pool's deployment and are not
+    /// counted as a dry spell. Returns `None` for a pool that was never active.
+    pub fn from_activity(activity: &[bool]) -> Option<Self> {
+        let first_active_interval = activity.iter().position(|&active| active)?;
+
+        let mut dry_spells = 0u64;
+        let mut dry_intervals = 0u64;
+        let mut longest_dry_spell = 0u64;
+        let mut longest_active_streak = 0u64;
+        let mut run_start = first_active_interval;
+        for i in first_active_interval + 1..=activity.len() {
+            // Close the current run at the end of the series or when it flips
+            if i < activity.len() && activity[i] == activity[run_start] {
+                continue;
+            }
+            let length = (i - run_start) as u64;
+            if activity[run_start] {
+                longest_active_streak = longest_active_streak.max(length);
+            } else {
+                dry_spells += 1;
+                dry_intervals += length;
+                longest_dry_spell = longest_dry_spell.max(length);
+            }
+            run_start = i;
+        }
 
+        let mean_dry_spell = if dry_spells > 0 {
+            dry_intervals as f64 / dry_spells as f64
+        } else {
+            0.0
+        };
+        Some(Self {
+            first_active_interval,
+            dry_spells,
+            longest_dry_spell,
+            mean_dry_spell,
+            longest_active_streak,
+        })
+    }
+}
+
+/// One-pass Pearson correlation of paired samples. Means and co-moments are
+/// updated incrementally (Welford), avoiding the cancellation of the naive
+/// sum-of-products formula on large LVR values.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PearsonCorrelation {
+    n: u64,
+    mean_x: f64,
+    mean_y: f64,
+    m2_x: f64,
+    m2_y: f64,
+    c_xy: f64,
+}
+
+impl PearsonCorrelation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, x: f64, y: f64) {
+        self.n += 1;
+        let n = self.n as f64;
+        let dx = x - self.mean_x;
+        self.mean_x += dx / n;
+        let dy = y - self.mean_y;
+        self.mean_y += dy / n;
+        self.m2_x += dx * (x - self.mean_x);
+        self.m2_y += dy * (y - self.mean_y);
+        self.c_xy += dx * (y - self.mean_y);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.n
+    }
+
+    /// Returns `None` for fewer than two samples or when either series is
+    /// constant, since the correlation is undefined
+    pub fn correlation(&self) -> Option<f64> {
+        if self.n < 2 || self.m2_x <= 0.0 || self.m2_y <= 0.0 {
+            return None;
+        }
+        Some((self.c_xy / (self.m2_x * self.m2_y).sqrt()).clamp(-1.0, 1.0))
+    }
+}
+
+/// What a run's footers record about the files it read
+struct PrecomputeInputs {
+    manifest: String,
+    /// From the first interval file's start to the last one's end
+    block_range: Option<(u64, u64)>,
+}
+
+#[derive(Clone)]
 pub struct PrecomputedWriter {
     object_store: Arc<dyn ObjectStore>,
-    max_retries: u32,
+    retry_policy: RetryPolicy,
+    write_options: ParquetWriteOptions,
+    /// Layout interval files are read in
+    layout: OutputLayout,
+    inputs: Arc<OnceCell<PrecomputeInputs>>,
+    buffers: Arc<BufferPool>,
+    /// Recorded in every footer; shared by all files of one run
+    generated_at: DateTime<Utc>,
+    /// Also write the single-file individual running totals, for readers
+    /// that predate the per-pool partitions
+    combined_running_totals: bool,
+    pools: Arc<PoolRegistry>,
 }
 
 impl PrecomputedWriter {
     pub fn new(object_store: Arc<dyn ObjectStore>) -> Self {
         Self {
             object_store,
-            max_retries: 3,
+            retry_policy: RetryPolicy::default(),
+            write_options: ParquetWriteOptions::default(),
+            layout: OutputLayout::default(),
+            inputs: Arc::new(OnceCell::new()),
+            buffers: BufferPool::shared(),
+            generated_at: Utc::now(),
+            combined_running_totals: true,
+            pools: Arc::new(PoolRegistry::default()),
         }
     }
 
-    async fn write_batch_to_store(
-        &self,
-        path: Path,
-        batch: RecordBatch,
-    ) -> Result<(), anyhow::Error> {
-        let props = WriterProperties::builder()
-            .set_compression(Compression::SNAPPY)
-            .set_write_batch_size(1024 * 1024)
-            .build();
-
-        let mut buffer = Vec::new();
-        {
-            let mut writer = ArrowWriter::try_new(&mut buffer, batch.schema(), Some(props))?;
-            writer.write(&batch)?;
-            writer.close()?;
+    pub fn with_write_options(mut self, write_options: ParquetWriteOptions) -> Self {
+        self.write_options = write_options;
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn with_layout(mut self, layout: OutputLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    pub fn with_combined_running_totals(mut self, enabled: bool) -> Self {
+        self.combined_running_totals = enabled;
+        self
+    }
+
+    pub fn with_pool_registry(mut self, pools: Arc<PoolRegistry>) -> Self {
+        self.pools = pools;
+        self
+    }
+
+    /// Pins the `generated_at` footer entry, making output byte-identical
+    /// across runs over the same inputs
+    pub fn with_generated_at(mut self, generated_at: DateTime<Utc>) -> Self {
+        self.generated_at = generated_at;
+        self
+    }
+
+    /// Runs every task in `PRECOMPUTE_TASKS`, at most `concurrency` at a time.
+    ///
+    /// The first failure stops any task that has not started yet; tasks that are
+    /// already running are allowed to finish before the error is returned.
+    /// Returns the wall time of each completed task.
+    pub async fn run_all(&self, concurrency: usize) -> Result<Vec<(&'static str, Duration)>, anyhow::Error> {
+        let semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
+        let mut tasks = JoinSet::new();
+
+        for &name in PRECOMPUTE_TASKS {
+            let writer = self.clone();
+            let semaphore = Arc::clone(&semaphore);
+            tasks.spawn(async move {
+                // A closed semaphore means another task already failed
+                let Ok(_permit) = semaphore.acquire_owned().await else {
+                    return (name, None);
+                };
+                let started = Instant::now();
+                let result = writer.run_task(name).await;
+                (name, Some(result.map(|_| started.elapsed())))
+            });
         }
 
-        let mut retries = 0;
-        while retries < self.max_retries {
-            match self.object_store.put(&path, Bytes::from(buffer.clone()).into()).await {
-                Ok(_) => return Ok(()),
-                Err(e) if retries < self.max_retries - 1 => {
-                    retries += 1;
-                    let delay = std::time::Duration::from_secs(2u64.pow(retries));
-                    warn!(
-                        "Write attempt {} failed for path {}: {}. Retrying in {} seconds...",
-                        retries, path, e, delay.as_secs()
-                    );
-                    tokio::time::sleep(delay).await;
+        let mut timings = Vec::with_capacity(PRECOMPUTE_TASKS.len());
+        let mut first_error: Option<anyhow::Error> = None;
+
+        while let Some(joined) = tasks.join_next().await {
+            let (name, outcome) = match joined {
+                Ok(result) => result,
+                Err(e) => {
+                    error!("Precompute task panicked: {}", e);
+                    semaphore.close();
+                    first_error.get_or_insert_with(|| anyhow::anyhow!("Precompute task panicked: {}", e));
+                    continue;
+                }
+            };
+
+            match outcome {
+                Some(Ok(elapsed)) => {
+                    info!("Completed {} precomputation in {:.2?}", name, elapsed);
+                    timings.push((name, elapsed));
+                }
+                Some(Err(e)) => {
+                    error!("Precompute task {} failed: {}", name, e);
+                    semaphore.close();
+                    first_error.get_or_insert_with(|| e.context(format!("Precompute task {} failed", name)));
                 }
-                Err(e) => return Err(e.into()),
+                None => debug!("Skipped {} precomputation after an earlier failure", name),
             }
         }
-        
-        Err(anyhow::anyhow!("Failed to write after {} retries", self.max_retries))
+
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(timings),
+        }
+    }
+
+    /// Runs one `PRECOMPUTE_TASKS` entry by name
+    pub async fn run_task(&self, name: &str) -> Result<(), anyhow::Error> {
+        match name {
+            "running_totals" => self.write_running_totals().await,
+            "pool_totals" => self.write_pool_totals().await,
+            "max_lvr" => self.write_max_lvr().await,
+            "non_zero_proportions" => self.write_non_zero_proportions().await,
+            "histograms" => self.write_histograms().await,
+            "equidepth_histograms" => self.write_equidepth_histograms().await,
+            "percentile_bands" => self.write_percentile_bands().await,
+            "quartile_plots" => self.write_quartile_plots().await,
+            "daily_time_series" => self.write_daily_time_series().await,
+            "rolling_series" => self.write_rolling_series(ROLLING_WINDOW_INTERVALS).await,
+            "cluster_proportions" => self.write_cluster_proportions().await,
+            "cluster_histograms" => self.write_cluster_histograms().await,
+            "cluster_non_zero" => self.write_cluster_non_zero().await,
+            "monthly_cluster_totals" => self.write_monthly_cluster_totals().await,
+            "monthly_pool_totals" => self.write_monthly_pool_totals().await,
+            "concentration_metrics" => self.write_concentration_metrics().await,
+            "activity_runs" => self.write_activity_runs().await,
+            "pool_correlations" => self.write_pool_correlations().await,
+            "distribution_metrics" => self.write_distribution_metrics().await,
+            _ => Err(anyhow::anyhow!("Unknown precompute task: {}", name)),
+        }
+    }
+
+    /// Hash of the `intervals/`, `checkpoints/` and `cluster_activity/`
+    /// listing, and the blocks from the first interval file's start to the
+    /// last one's end. Computed once per writer and recorded in every output
+    /// footer.
+    async fn inputs(&self) -> Result<(&str, Option<(u64, u64)>), anyhow::Error> {
+        let inputs = self.inputs.get_or_try_init(|| async {
+            let mut entries = Vec::new();
+            let mut block_range: Option<(u64, u64)> = None;
+            for prefix in ["intervals", "checkpoints", "cluster_activity"] {
+                let mut listing = self.object_store.list(Some(&Path::from(prefix)));
+                while let Some(meta) = listing.next().await {
+                    let meta = meta.context("Failed to list precompute inputs")?;
+                    if prefix == "intervals" {
+                        if let Some((_, start, end)) = parse_interval_path(meta.location.as_ref())
+                            .filter(|(layout, _, _)| *layout == self.layout)
+                        {
+                            block_range = Some(block_range.map_or((start, end), |(first, last)| (first.min(start), last.max(end))));
+                        }
+                    }
+                    entries.push((meta.location.to_string(), meta.size as u64));
+                }
+            }
+            Ok::<_, anyhow::Error>(PrecomputeInputs { manifest: input_manifest_hash(entries), block_range })
+        }).await?;
+        Ok((&inputs.manifest, inputs.block_range))
+    }
+
+    /// Writer properties for `kind`, recording its sort order in every row
+    /// group. Rows must already be in that order.
+    async fn dataset_properties(&self, kind: DatasetKind, schema: &Schema) -> Result<WriterProperties, anyhow::Error> {
+        let (input_manifest, block_range) = self.inputs().await?;
+        let metadata = kind.footer_metadata(input_manifest, &self.generated_at, block_range);
+        let sorting_columns = kind.sort_columns()
+            .iter()
+            .map(|name| Ok(SortingColumn::new(schema.index_of(name)? as i32, false, true)))
+            .collect::<Result<Vec<_>, anyhow::Error>>()?;
+        Ok(self.write_options
+            .writer_properties_builder(metadata)
+            .set_sorting_columns(Some(sorting_columns))
+            .build())
+    }
+
+    async fn write_batch_to_store(
+        &self,
+        kind: DatasetKind,
+        batch: RecordBatch,
+    ) -> Result<(), anyhow::Error> {
+        let batch = sort_batch(&batch, kind.sort_columns())?;
+        let props = self.dataset_properties(kind, &batch.schema()).await?;
+
+        let payload = self.buffers.encode_parquet(&batch, props)?;
+        self.put_with_retries(kind.path(), payload, batch.num_rows()).await
+    }
+
+    async fn put_with_retries(
+        &self,
+        path: Path,
+        payload: Bytes,
+        expected_rows: usize,
+    ) -> Result<(), anyhow::Error> {
+        retry_atomic_put(self.object_store.as_ref(), &path, payload, expected_rows, &self.retry_policy).await?;
+        Ok(())
     }
 
     pub async fn write_running_totals(&self) -> Result<(), anyhow::Error> {
         info!("Starting precomputation of running totals (individual and aggregate)");
         
-        // Get all data from interval files
-        let intervals_path = object_store::path::Path::from("intervals");
-        let mut interval_files = self.object_store.list(Some(&intervals_path));
-        let valid_pools = get_valid_pools();
-            
-        let mut interval_data: HashMap<(u64, String, String), u64> = HashMap::new();
-        let mut aggregate_data: HashMap<(u64, String), u64> = HashMap::new();
-    
-        // Process all interval files to collect interval data
-        while let Some(meta_result) = interval_files.next().await {
-            let meta = meta_result.context("Failed to get file metadata")?;
-            let file_path = meta.location.to_string();
-            
-            // Extract start and end blocks from the file name
-            let (file_start, file_end) = Self::extract_block_range_from_path(&file_path)?;
-    
-            let bytes = self.object_store.get(&meta.location)
+        // Interval files are visited in block order so running totals can be
+        // emitted file by file; only the per-pool totals persist between files
+        let interval_files = self.interval_files().await?;
+
+        let individual_schema = Arc::new(arrow::datatypes::Schema::new(vec![
+            arrow::datatypes::Field::new("block_number", arrow::datatypes::DataType::UInt64, false),
+            arrow::datatypes::Field::new("markout_time", arrow::datatypes::DataType::Utf8, false),
+            arrow::datatypes::Field::new("pool_address", arrow::datatypes::DataType::Utf8, false),
+            arrow::datatypes::Field::new("running_total_cents", arrow::datatypes::DataType::Int64, false),
+        ]));
+        let aggregate_schema = Arc::new(arrow::datatypes::Schema::new(vec![
+            arrow::datatypes::Field::new("block_number", arrow::datatypes::DataType::UInt64, false),
+            arrow::datatypes::Field::new("markout_time", arrow::datatypes::DataType::Utf8, false),
+            arrow::datatypes::Field::new("running_total_cents", arrow::datatypes::DataType::Int64, false),
+        ]));
+
+        // Rows are produced in sort order: files are visited by block and
+        // each file's rows come out of ordered maps
+        let individual_props = self.dataset_properties(DatasetKind::IndividualRunningTotals, &individual_schema).await?;
+        let aggregate_props = self.dataset_properties(DatasetKind::AggregateRunningTotals, &aggregate_schema).await?;
+        let mut individual_writer = if self.combined_running_totals {
+            Some(ArrowWriter::try_new(Vec::new(), individual_schema.clone(), Some(individual_props.clone()))?)
+        } else {
+            None
+        };
+        let mut aggregate_writer = ArrowWriter::try_new(Vec::new(), aggregate_schema.clone(), Some(aggregate_props))?;
+        let mut individual_rows = 0;
+        let mut aggregate_rows = 0;
+
+        // One partition per valid pool, written even when empty so a missing
+        // partition always means the data predates partitioning
+        let mut partition_writers = BTreeMap::new();
+        for pool_address in self.pools.addresses() {
+            let writer = ArrowWriter::try_new(Vec::new(), individual_schema.clone(), Some(individual_props.clone()))?;
+            partition_writers.insert(pool_address.clone(), (writer, 0usize));
+        }
+
+        // Running totals per pool/markout and per markout, carried across files
+        let mut pool_totals: HashMap<(Address, String), i64> = HashMap::new();
+        let mut markout_totals: HashMap<String, i64> = HashMap::new();
+
+        for (location, file_start, file_end) in interval_files {
+            let bytes = self.object_store.get(&location)
                 .await?
                 .bytes()
                 .await?;
     
-            let record_reader = ParquetRecordBatchReader::try_new(bytes, 1024)?;
+            let record_reader = parquet_reader(bytes, &location, 1024)?;
+
+            // This file's interval totals, ordered by block then markout then pool
+            let mut interval_data: BTreeMap<(u64, String, Address), i64> = BTreeMap::new();
+            let mut aggregate_data: BTreeMap<(u64, String), i64> = BTreeMap::new();
     
             for batch_result in record_reader {
-                let batch = batch_result?;
+                let batch = normalize_interval_batch(batch_result?)?;
+                let block_ranges = interval_block_ranges(&batch, file_start, file_end)
+                    .map_err(|e| anyhow::anyhow!("Failed to read interval block ranges: {}", e))?;
                 
-                let interval_ids = get_uint64_column(&batch, "interval_id")
-                    .map_err(|e| anyhow::anyhow!("Failed to get interval_id column: {}", e))?;
-                let markout_times_col = get_string_column(&batch, "markout_time")
+                let markout_times_col = get_string_column(&batch, INTERVAL_MARKOUT_TIME_COLUMN)
                     .map_err(|e| anyhow::anyhow!("Failed to get markout_time column: {}", e))?;
-                let pool_addresses_col = get_string_column(&batch, "pair_address")
+                let pool_addresses_col = get_string_column(&batch, INTERVAL_PAIR_ADDRESS_COLUMN)
                     .map_err(|e| anyhow::anyhow!("Failed to get pair_address column: {}", e))?;
-                let total_lvr_cents = get_uint64_column(&batch, "total_lvr_cents")
+                let total_lvr_cents = get_int64_column(&batch, INTERVAL_TOTAL_LVR_COLUMN)
                     .map_err(|e| anyhow::anyhow!("Failed to get total_lvr_cents column: {}", e))?;
-                let non_zero_counts = get_uint64_column(&batch, "non_zero_count")
+                let non_zero_counts = get_uint64_column(&batch, INTERVAL_NON_ZERO_COUNT_COLUMN)
                     .map_err(|e| anyhow::anyhow!("Failed to get non_zero_count column: {}", e))?;
     
-                for i in 0..batch.num_rows() {
+                for (i, &(_, end_block)) in block_ranges.iter().enumerate() {
                     if total_lvr_cents.is_null(i) || non_zero_counts.value(i) == 0 {
                         continue;
                     }
     
-                    let pool_address = pool_addresses_col.value(i).to_lowercase();
-                    if !valid_pools.contains(&pool_address) {
+                    let Some(pool_address) = self.pools.address(pool_addresses_col.value(i)) else {
                         continue;
-                    }
+                    };
     
-                    let interval_id = interval_ids.value(i);
                     let markout_time = markout_times_col.value(i).to_string();
                     let lvr_cents = total_lvr_cents.value(i);
     
-                    // Calculate the actual end block for this interval's data
-                    // This is where the LVR activity belongs when displaying running totals
-                    let end_block = if file_path.ends_with(FINAL_INTERVAL_FILE) && interval_id == 19 {
-                        // Special case for the final interval in the last file
-                        file_end  // Use the exact end boundary from the filename
-                    } else {
-                        // Regular intervals end at the end of their range
-                        file_start + ((interval_id + 1) * BLOCKS_PER_INTERVAL)
-                    };
-    
-                    // FIX: Use the end block for the running total as that's where
-                    // the cumulative value appears after all activity in the interval
+                    // The running total appears at the interval's end block,
+                    // after all of its activity
                     let block_number = end_block;
     
                     // Update individual pool data
@@ -150,286 +576,487 @@ impl PrecomputedWriter {
     
                     // Update aggregate data
                     aggregate_data
-                        .entry((block_number, markout_time.clone()))
+                        .entry((block_number, markout_time))
                         .and_modify(|total| *total = total.saturating_add(lvr_cents))
                         .or_insert(lvr_cents);
                 }
             }
+
+            if !interval_data.is_empty() {
+                let mut block_numbers = Vec::with_capacity(interval_data.len());
+                let mut markout_times = Vec::with_capacity(interval_data.len());
+                let mut pool_addresses = Vec::with_capacity(interval_data.len());
+                let mut totals = Vec::with_capacity(interval_data.len());
+
+                for ((block_number, markout_time, pool_address), interval_total) in interval_data {
+                    let current_total = pool_totals
+                        .entry((pool_address.clone(), markout_time.clone()))
+                        .and_modify(|total| *total = total.saturating_add(interval_total))
+                        .or_insert(interval_total);
+
+                    block_numbers.push(block_number);
+                    markout_times.push(markout_time);
+                    pool_addresses.push(pool_address);
+                    totals.push(*current_total);
+                }
+
+                let batch = RecordBatch::try_new(
+                    individual_schema.clone(),
+                    vec![
+                        Arc::new(UInt64Array::from(block_numbers)),
+                        Arc::new(StringArray::from(markout_times)),
+                        Arc::new(StringArray::from_iter_values(pool_addresses.iter().map(Address::as_str))),
+                        Arc::new(Int64Array::from(totals)),
+                    ],
+                )?;
+
+                // Row indices per pool keep each partition in block order
+                let mut pool_rows: BTreeMap<&Address, Vec<u32>> = BTreeMap::new();
+                for (row, pool_address) in pool_addresses.iter().enumerate() {
+                    pool_rows.entry(pool_address).or_default().push(row as u32);
+                }
+                for (pool_address, indices) in pool_rows {
+                    let (writer, rows) = partition_writers
+                        .get_mut(pool_address)
+                        .context("Running total for a pool without a partition")?;
+                    *rows += indices.len();
+                    writer.write(&take_record_batch(&batch, &UInt32Array::from(indices))?)?;
+                }
+
+                individual_rows += batch.num_rows();
+                if let Some(writer) = individual_writer.as_mut() {
+                    writer.write(&batch)?;
+                }
+            }
+
+            if !aggregate_data.is_empty() {
+                let mut block_numbers = Vec::with_capacity(aggregate_data.len());
+                let mut markout_times = Vec::with_capacity(aggregate_data.len());
+                let mut totals = Vec::with_capacity(aggregate_data.len());
+
+                for ((block_number, markout_time), interval_total) in aggregate_data {
+                    let current_total = markout_totals
+                        .entry(markout_time.clone())
+                        .and_modify(|total| *total = total.saturating_add(interval_total))
+                        .or_insert(interval_total);
+
+                    block_numbers.push(block_number);
+                    markout_times.push(markout_time);
+                    totals.push(*current_total);
+                }
+
+                aggregate_rows += block_numbers.len();
+                aggregate_writer.write(&RecordBatch::try_new(
+                    aggregate_schema.clone(),
+                    vec![
+                        Arc::new(UInt64Array::from(block_numbers)),
+                        Arc::new(StringArray::from(markout_times)),
+                        Arc::new(Int64Array::from(totals)),
+                    ],
+                )?)?;
+            }
         }
-    
-        // Write individual running totals
-        self.write_individual_running_totals(interval_data).await?;
-    
-        // Write aggregate running totals
-        self.write_aggregate_running_totals(aggregate_data).await?;
+
+        let partitions = partition_writers.len();
+        for (pool_address, (writer, rows)) in partition_writers {
+            let partition = Bytes::from(writer.into_inner()?);
+            self.put_with_retries(DatasetKind::pool_partition_path(&pool_address), partition, rows).await?;
+        }
+        info!("Successfully wrote precomputed individual running totals for {} pools", partitions);
+
+        if let Some(writer) = individual_writer {
+            let individual = Bytes::from(writer.into_inner()?);
+            self.put_with_retries(DatasetKind::IndividualRunningTotals.path(), individual, individual_rows).await?;
+            info!("Successfully wrote combined precomputed individual running totals");
+        }
+
+        let aggregate = Bytes::from(aggregate_writer.into_inner()?);
+        self.put_with_retries(DatasetKind::AggregateRunningTotals.path(), aggregate, aggregate_rows).await?;
+        info!("Successfully wrote precomputed aggregate running totals");
     
         info!("Successfully wrote precomputed running totals (individual and aggregate)");
         Ok(())
     }
     
-    // Helper function to extract start and end blocks from file path
-    fn extract_block_range_from_path(file_path: &str) -> Result<(u64, u64), anyhow::Error> {
-        let file_name = file_path
-            .split('/')
-            .last()
-            .context("Failed to extract filename from path")?;
+    /// `(path, start, end)` of every interval file in this writer's layout,
+    /// by block range
+    async fn interval_files(&self) -> Result<Vec<(Path, u64, u64)>, anyhow::Error> {
+        list_interval_files(self.object_store.as_ref(), self.layout).await
+    }
+    
+    /// Lifetime totals of every valid pool/markout with at least one observed
+    /// block, read from checkpoints
+    async fn collect_pool_totals(&self) -> Result<Vec<PoolTotalRow>, anyhow::Error> {
+        let mut totals = Vec::new();
+        let checkpoint_files = list_checkpoints(self.object_store.as_ref()).await?;
         
-        let parts: Vec<&str> = file_name.trim_end_matches(".parquet").split('_').collect();
-        if parts.len() != 2 {
-            return Err(anyhow::anyhow!("Invalid file name format: {}", file_name));
+        for location in checkpoint_files {
+            let bytes = self.object_store.get(&location)
+                .await?
+                .bytes()
+                .await?;
+
+            let record_reader = parquet_reader(bytes, &location, 1)?;
+
+            for batch_result in record_reader {
+                let batch = batch_result?;
+
+                if batch.num_rows() > 0 {
+                    let snapshot = read_checkpoint_snapshot(&batch)?;
+                    let Some(pair_address) = self.pools.address(&snapshot.pair_address) else {
+                        continue;
+                    };
+
+                    let markout_time = snapshot.markout_time;
+                    let running_total = snapshot.running_total.as_i64();
+                    let non_zero_count = snapshot.non_zero_blocks();
+                    let total_count = snapshot.total_blocks();
+
+                    if total_count > 0 {
+                        let pool_name = self.pools.pool_name(pair_address);
+
+                        totals.push(PoolTotalRow {
+                            pool_address: pair_address.clone(),
+                            pool_name,
+                            markout_time: markout_time.to_string(),
+                            total_lvr_cents: running_total,
+                            non_zero_blocks: non_zero_count,
+                            total_blocks: total_count,
+                        });
+                    }
+                }
+            }
         }
-        
-        let start_block = parts[0].parse::<u64>()
-            .context(format!("Failed to parse start block from filename: {}", file_name))?;
-        
-        let end_block = parts[1].parse::<u64>()
-            .context(format!("Failed to parse end block from filename: {}", file_name))?;
-        
-        Ok((start_block, end_block))
+
+        Ok(totals)
     }
-    
-    async fn write_individual_running_totals(
-        &self,
-        interval_data: HashMap<(u64, String, String), u64>
-    ) -> Result<(), anyhow::Error> {
-        // Create output schema for individual running totals
+
+    pub async fn write_pool_totals(&self) -> Result<(), anyhow::Error> {
+        info!("Starting precomputation of pool totals");
+        
+        // Create schema for pool totals
         let schema = arrow::datatypes::Schema::new(vec![
-            arrow::datatypes::Field::new("block_number", arrow::datatypes::DataType::UInt64, false),
-            arrow::datatypes::Field::new("markout_time", arrow::datatypes::DataType::Utf8, false),
             arrow::datatypes::Field::new("pool_address", arrow::datatypes::DataType::Utf8, false),
-            arrow::datatypes::Field::new("running_total_cents", arrow::datatypes::DataType::UInt64, false),
+            arrow::datatypes::Field::new("pool_name", arrow::datatypes::DataType::Utf8, false),
+            arrow::datatypes::Field::new("markout_time", arrow::datatypes::DataType::Utf8, false),
+            arrow::datatypes::Field::new("total_lvr_cents", arrow::datatypes::DataType::Int64, false),
+            arrow::datatypes::Field::new("non_zero_blocks", arrow::datatypes::DataType::UInt64, false),
+            arrow::datatypes::Field::new("total_blocks", arrow::datatypes::DataType::UInt64, false),
         ]);
-    
-        // Track running totals per pool/markout combination
-        let mut running_totals: HashMap<(String, String), u64> = HashMap::new();
-    
-        // Sort data points by block number
-        let mut data_points: Vec<_> = interval_data.into_iter().collect();
-        data_points.sort_by_key(|((block, _, _), _)| *block);
-    
-        let mut block_numbers = Vec::new();
-        let mut markout_times = Vec::new();
-        let mut pool_addresses = Vec::new();
-        let mut totals = Vec::new();
-    
-        // Process sorted data points to maintain monotonicity
-        for ((block_number, markout_time, pool_address), interval_total) in data_points {
-            let current_total = running_totals
-                .entry((pool_address.clone(), markout_time.clone()))
-                .and_modify(|total| *total = total.saturating_add(interval_total))
-                .or_insert(interval_total);
-    
-            block_numbers.push(block_number);
-            markout_times.push(markout_time);
-            pool_addresses.push(pool_address);
-            totals.push(*current_total);
-        }
-    
-        // Create and write record batch
+
+        let totals = self.collect_pool_totals().await?;
+
+        // Create record batch
         let batch = RecordBatch::try_new(
             Arc::new(schema),
             vec![
-                Arc::new(UInt64Array::from(block_numbers)),
-                Arc::new(StringArray::from(markout_times)),
-                Arc::new(StringArray::from(pool_addresses)),
-                Arc::new(UInt64Array::from(totals)),
+                Arc::new(StringArray::from_iter_values(totals.iter().map(|t| t.pool_address.as_str()))),
+                Arc::new(StringArray::from_iter_values(totals.iter().map(|t| t.pool_name.as_str()))),
+                Arc::new(StringArray::from_iter_values(totals.iter().map(|t| t.markout_time.as_str()))),
+                Arc::new(Int64Array::from_iter_values(totals.iter().map(|t| t.total_lvr_cents))),
+                Arc::new(UInt64Array::from_iter_values(totals.iter().map(|t| t.non_zero_blocks))),
+                Arc::new(UInt64Array::from_iter_values(totals.iter().map(|t| t.total_blocks))),
             ],
         )?;
-    
-        let output_path = Path::from("precomputed/running_totals/individual.parquet");
-        self.write_batch_to_store(output_path, batch).await?;
-    
-        info!("Successfully wrote precomputed individual running totals");
+
+        // Write to output file
+        self.write_batch_to_store(DatasetKind::PoolTotals, batch).await?;
+
+        info!("Successfully wrote precomputed pool totals");
         Ok(())
     }
-    
-    async fn write_aggregate_running_totals(
-        &self,
-        aggregate_data: HashMap<(u64, String), u64>
-    ) -> Result<(), anyhow::Error> {
-        // Create output schema for aggregate running totals
+
+    pub async fn write_concentration_metrics(&self) -> Result<(), anyhow::Error> {
+        info!("Starting precomputation of concentration metrics");
+
         let schema = arrow::datatypes::Schema::new(vec![
-            arrow::datatypes::Field::new("block_number", arrow::datatypes::DataType::UInt64, false),
             arrow::datatypes::Field::new("markout_time", arrow::datatypes::DataType::Utf8, false),
-            arrow::datatypes::Field::new("running_total_cents", arrow::datatypes::DataType::UInt64, false),
+            arrow::datatypes::Field::new("pool_count", arrow::datatypes::DataType::UInt64, false),
+            arrow::datatypes::Field::new("hhi", arrow::datatypes::DataType::Float64, false),
+            arrow::datatypes::Field::new("top_1_share", arrow::datatypes::DataType::Float64, false),
+            arrow::datatypes::Field::new("top_3_share", arrow::datatypes::DataType::Float64, false),
+            arrow::datatypes::Field::new("top_5_share", arrow::datatypes::DataType::Float64, false),
+            arrow::datatypes::Field::new("effective_pools", arrow::datatypes::DataType::Float64, false),
         ]);
-    
-        // Track running totals per markout time
-        let mut running_totals: HashMap<String, u64> = HashMap::new();
-    
-        // Sort data points by block number
-        let mut data_points: Vec<_> = aggregate_data.into_iter().collect();
-        data_points.sort_by_key(|((block, _), _)| *block);
-    
-        let mut block_numbers = Vec::new();
+
+        // Shares are of positive LVR: a pool with a net-negative total
+        // counts as holding none of it
+        let mut markout_totals: BTreeMap<String, Vec<u64>> = BTreeMap::new();
+        for row in self.collect_pool_totals().await? {
+            markout_totals.entry(row.markout_time).or_default().push(row.total_lvr_cents.max(0) as u64);
+        }
+
         let mut markout_times = Vec::new();
-        let mut totals = Vec::new();
-    
-        // Process sorted data points to maintain monotonicity
-        for ((block_number, markout_time), interval_total) in data_points {
-            let current_total = running_totals
-                .entry(markout_time.clone())
-                .and_modify(|total| *total = total.saturating_add(interval_total))
-                .or_insert(interval_total);
-    
-            block_numbers.push(block_number);
+        let mut pool_counts = Vec::new();
+        let mut hhis = Vec::new();
+        let mut top_1_shares = Vec::new();
+        let mut top_3_shares = Vec::new();
+        let mut top_5_shares = Vec::new();
+        let mut effective_pools = Vec::new();
+
+        for (markout_time, totals) in markout_totals {
+            let Some(metrics) = ConcentrationMetrics::from_totals(&totals) else {
+                debug!("Skipping concentration metrics for markout {}: no LVR", markout_time);
+                continue;
+            };
             markout_times.push(markout_time);
-            totals.push(*current_total);
+            pool_counts.push(totals.len() as u64);
+            hhis.push(metrics.hhi);
+            top_1_shares.push(metrics.top_1_share);
+            top_3_shares.push(metrics.top_3_share);
+            top_5_shares.push(metrics.top_5_share);
+            effective_pools.push(metrics.effective_pools);
         }
-    
-        // Create and write record batch
+
         let batch = RecordBatch::try_new(
             Arc::new(schema),
             vec![
-                Arc::new(UInt64Array::from(block_numbers)),
                 Arc::new(StringArray::from(markout_times)),
-                Arc::new(UInt64Array::from(totals)),
+                Arc::new(UInt64Array::from(pool_counts)),
+                Arc::new(Float64Array::from(hhis)),
+                Arc::new(Float64Array::from(top_1_shares)),
+                Arc::new(Float64Array::from(top_3_shares)),
+                Arc::new(Float64Array::from(top_5_shares)),
+                Arc::new(Float64Array::from(effective_pools)),
             ],
         )?;
-    
-        let output_path = Path::from("precomputed/running_totals/aggregate.parquet");
-        self.write_batch_to_store(output_path, batch).await?;
-    
-        info!("Successfully wrote precomputed aggregate running totals");
+
+        self.write_batch_to_store(DatasetKind::Concentration, batch).await?;
+
+        info!("Successfully wrote precomputed concentration metrics");
         Ok(())
     }
 
-    pub async fn write_pool_totals(&self) -> Result<(), anyhow::Error> {
-        info!("Starting precomputation of pool totals");
-        
-        // Create schema for pool totals
+    pub async fn write_activity_runs(&self) -> Result<(), anyhow::Error> {
+        info!("Starting precomputation of pool activity runs");
+
         let schema = arrow::datatypes::Schema::new(vec![
             arrow::datatypes::Field::new("pool_address", arrow::datatypes::DataType::Utf8, false),
             arrow::datatypes::Field::new("pool_name", arrow::datatypes::DataType::Utf8, false),
             arrow::datatypes::Field::new("markout_time", arrow::datatypes::DataType::Utf8, false),
-            arrow::datatypes::Field::new("total_lvr_cents", arrow::datatypes::DataType::UInt64, false),
-            arrow::datatypes::Field::new("non_zero_blocks", arrow::datatypes::DataType::UInt64, false),
-            arrow::datatypes::Field::new("total_blocks", arrow::datatypes::DataType::UInt64, false),
+            arrow::datatypes::Field::new("first_active_block", arrow::datatypes::DataType::UInt64, false),
+            arrow::datatypes::Field::new("dry_spells", arrow::datatypes::DataType::UInt64, false),
+            arrow::datatypes::Field::new("longest_dry_spell", arrow::datatypes::DataType::UInt64, false),
+            arrow::datatypes::Field::new("mean_dry_spell", arrow::datatypes::DataType::Float64, false),
+            arrow::datatypes::Field::new("longest_active_streak", arrow::datatypes::DataType::UInt64, false),
         ]);
 
-        // Prepare vectors for collecting data
+
+        // End blocks of every observed interval, and the end blocks at which
+        // each (pool_address, markout_time) had non-zero LVR
+        let mut timeline: std::collections::BTreeSet<u64> = std::collections::BTreeSet::new();
+        let mut active_blocks: BTreeMap<(Address, String), std::collections::BTreeSet<u64>> = BTreeMap::new();
+
+        for (location, file_start, file_end) in self.interval_files().await? {
+
+            let bytes = self.object_store.get(&location).await?.bytes().await?;
+            let record_reader = parquet_reader(bytes, &location, 1024)?;
+
+            for batch_result in record_reader {
+                let batch = normalize_interval_batch(batch_result?)?;
+                let block_ranges = interval_block_ranges(&batch, file_start, file_end)
+                    .map_err(|e| anyhow::anyhow!("Failed to read interval block ranges: {}", e))?;
+
+                let markout_times_col = get_string_column(&batch, INTERVAL_MARKOUT_TIME_COLUMN)
+                    .map_err(|e| anyhow::anyhow!("Failed to get markout_time column: {}", e))?;
+                let pool_addresses_col = get_string_column(&batch, INTERVAL_PAIR_ADDRESS_COLUMN)
+                    .map_err(|e| anyhow::anyhow!("Failed to get pair_address column: {}", e))?;
+                let non_zero_counts = get_uint64_column(&batch, INTERVAL_NON_ZERO_COUNT_COLUMN)
+                    .map_err(|e| anyhow::anyhow!("Failed to get non_zero_count column: {}", e))?;
+                let total_counts = get_uint64_column(&batch, INTERVAL_TOTAL_COUNT_COLUMN)
+                    .map_err(|e| anyhow::anyhow!("Failed to get total_count column: {}", e))?;
+
+                for (i, &(_, end_block)) in block_ranges.iter().enumerate() {
+                    let Some(pool_address) = self.pools.address(pool_addresses_col.value(i)) else {
+                        continue;
+                    };
+                    if total_counts.value(i) == 0 {
+                        continue;
+                    }
+
+                    timeline.insert(end_block);
+
+                    let blocks = active_blocks
+                        .entry((pool_address.clone(), markout_times_col.value(i).to_string()))
+                        .or_default();
+                    if non_zero_counts.value(i) > 0 {
+                        blocks.insert(end_block);
+                    }
+                }
+            }
+        }
+
+        let timeline: Vec<u64> = timeline.into_iter().collect();
+
         let mut pool_addresses = Vec::new();
         let mut pool_names = Vec::new();
         let mut markout_times = Vec::new();
-        let mut total_lvr_cents = Vec::new();
-        let mut non_zero_blocks = Vec::new();
-        let mut total_blocks = Vec::new();
+        let mut first_active_blocks = Vec::new();
+        let mut dry_spells = Vec::new();
+        let mut longest_dry_spells = Vec::new();
+        let mut mean_dry_spells = Vec::new();
+        let mut longest_active_streaks = Vec::new();
+
+        for ((pool_address, markout_time), blocks) in active_blocks {
+            // Intervals a pool has no row for are treated as inactive
+            let activity: Vec<bool> = timeline.iter().map(|block| blocks.contains(block)).collect();
+            let Some(runs) = ActivityRuns::from_activity(&activity) else {
+                debug!("Skipping activity runs for pool {} ({}): never active", pool_address, markout_time);
+                continue;
+            };
 
-        let valid_pools = get_valid_pools();
-        let checkpoints_path = object_store::path::Path::from("checkpoints");
-        let mut checkpoint_files = self.object_store.list(Some(&checkpoints_path));
-        
-        while let Some(meta_result) = checkpoint_files.next().await {
-            let meta = meta_result.context("Failed to get file metadata")?;
-            let file_path = meta.location.to_string();
-            
-            let bytes = self.object_store.get(&meta.location)
-                .await?
-                .bytes()
-                .await?;
+            pool_names.push(self.pools.pool_name(&pool_address));
+            pool_addresses.push(pool_address.to_string());
+            markout_times.push(markout_time);
+            first_active_blocks.push(timeline[runs.first_active_interval]);
+            dry_spells.push(runs.dry_spells);
+            longest_dry_spells.push(runs.longest_dry_spell);
+            mean_dry_spells.push(runs.mean_dry_spell);
+            longest_active_streaks.push(runs.longest_active_streak);
+        }
 
-            let record_reader = ParquetRecordBatchReader::try_new(bytes, 1)?;
+        let batch = RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(StringArray::from(pool_addresses)),
+                Arc::new(StringArray::from(pool_names)),
+                Arc::new(StringArray::from(markout_times)),
+                Arc::new(UInt64Array::from(first_active_blocks)),
+                Arc::new(UInt64Array::from(dry_spells)),
+                Arc::new(UInt64Array::from(longest_dry_spells)),
+                Arc::new(Float64Array::from(mean_dry_spells)),
+                Arc::new(UInt64Array::from(longest_active_streaks)),
+            ],
+        )?;
 
-            for batch_result in record_reader {
-                let batch = batch_result?;
+        self.write_batch_to_store(DatasetKind::ActivityRuns, batch).await?;
 
-                // Get running total with dynamic type handling
-                let running_total_idx = batch.schema().index_of("running_total")?;
-                let running_total = {
-                    let column = batch.column(running_total_idx);
-                    match column.data_type() {
-                        DataType::Int64 => {
-                            column.as_any()
-                                .downcast_ref::<Int64Array>()
-                                .map(|arr| arr.value(0))
-                                .context("Failed to cast running_total as Int64Array")?
-                        },
-                        DataType::UInt64 => {
-                            column.as_any()
-                                .downcast_ref::<UInt64Array>()
-                                .map(|arr| arr.value(0) as i64)
-                                .context("Failed to cast running_total as UInt64Array")?
-                        },
-                        other => return Err(anyhow::anyhow!("Unexpected type for running_total: {:?}", other))
-                    }
-                };
+        info!("Successfully wrote precomputed pool activity runs");
+        Ok(())
+    }
 
-                let pair_addresses = get_string_column(&batch, "pair_address")
+    pub async fn write_pool_correlations(&self) -> Result<(), anyhow::Error> {
+        info!("Starting precomputation of cross-pool interval LVR correlations");
+
+        let schema = arrow::datatypes::Schema::new(vec![
+            arrow::datatypes::Field::new("pool_a", arrow::datatypes::DataType::Utf8, false),
+            arrow::datatypes::Field::new("pool_b", arrow::datatypes::DataType::Utf8, false),
+            arrow::datatypes::Field::new("markout_time", arrow::datatypes::DataType::Utf8, false),
+            arrow::datatypes::Field::new("correlation", arrow::datatypes::DataType::Float64, false),
+            arrow::datatypes::Field::new("n_intervals", arrow::datatypes::DataType::UInt64, false),
+        ]);
+
+
+        // End blocks of every observed interval, and per-interval LVR keyed
+        // by markout_time then pool_address
+        let mut timeline: std::collections::BTreeSet<u64> = std::collections::BTreeSet::new();
+        let mut interval_totals: BTreeMap<String, BTreeMap<Address, BTreeMap<u64, i64>>> = BTreeMap::new();
+
+        for (location, file_start, file_end) in self.interval_files().await? {
+
+            let bytes = self.object_store.get(&location).await?.bytes().await?;
+            let record_reader = parquet_reader(bytes, &location, 1024)?;
+
+            for batch_result in record_reader {
+                let batch = normalize_interval_batch(batch_result?)?;
+                let block_ranges = interval_block_ranges(&batch, file_start, file_end)
+                    .map_err(|e| anyhow::anyhow!("Failed to read interval block ranges: {}", e))?;
+
+                let markout_times_col = get_string_column(&batch, INTERVAL_MARKOUT_TIME_COLUMN)
+                    .map_err(|e| anyhow::anyhow!("Failed to get markout_time column: {}", e))?;
+                let pool_addresses_col = get_string_column(&batch, INTERVAL_PAIR_ADDRESS_COLUMN)
                     .map_err(|e| anyhow::anyhow!("Failed to get pair_address column: {}", e))?;
-                    
-                // Get additional metrics
-                let total_bucket_0 = get_uint64_column(&batch, "total_bucket_0")
-                    .map_err(|e| anyhow::anyhow!("Failed to get total_bucket_0 column: {}", e))?;
-                
-                let non_zero_buckets = [
-                    "total_bucket_0_10",
-                    "total_bucket_10_100",
-                    "total_bucket_100_500",
-                    "total_bucket_500_1000",
-                    "total_bucket_1000_10000",
-                    "total_bucket_10000_plus",
-                ];
+                let total_lvr_cents = get_int64_column(&batch, INTERVAL_TOTAL_LVR_COLUMN)
+                    .map_err(|e| anyhow::anyhow!("Failed to get total_lvr_cents column: {}", e))?;
+                let total_counts = get_uint64_column(&batch, INTERVAL_TOTAL_COUNT_COLUMN)
+                    .map_err(|e| anyhow::anyhow!("Failed to get total_count column: {}", e))?;
 
-                if batch.num_rows() > 0 {
-                    let pair_address = pair_addresses.value(0).to_lowercase();
-                    if !valid_pools.contains(&pair_address) {
+                for (i, &(_, end_block)) in block_ranges.iter().enumerate() {
+                    let Some(pool_address) = self.pools.address(pool_addresses_col.value(i)) else {
+                        continue;
+                    };
+                    if total_counts.value(i) == 0 {
                         continue;
                     }
 
-                    // Calculate non_zero and total blocks
-                    let mut non_zero_count = 0u64;
-                    for bucket_name in &non_zero_buckets {
-                        let bucket = get_uint64_column(&batch, bucket_name)
-                            .map_err(|e| anyhow::anyhow!("Failed to get {} column: {}", bucket_name, e))?;
-                        non_zero_count += bucket.value(0);
+                    timeline.insert(end_block);
+
+                    let lvr_cents = total_lvr_cents.value(i);
+                    if lvr_cents != 0 {
+                        let total = interval_totals
+                            .entry(markout_times_col.value(i).to_string())
+                            .or_default()
+                            .entry(pool_address.clone())
+                            .or_default()
+                            .entry(end_block)
+                            .or_default();
+                        *total = total.saturating_add(lvr_cents);
                     }
+                }
+            }
+        }
 
-                    let zero_count = total_bucket_0.value(0);
-                    let total_count = zero_count + non_zero_count;
+        let timeline: Vec<u64> = timeline.into_iter().collect();
 
-                    if total_count > 0 {
-                        // Extract markout time from file path
-                        let markout_time = file_path
-                            .split('_')
-                            .last()
-                            .and_then(|s| s.strip_suffix(".parquet"))
-                            .context("Failed to extract markout time from file path")?;
-
-                        let pool_name = POOL_NAMES
-                            .iter()
-                            .find(|(addr, _)| addr.to_lowercase() == pair_address)
-                            .map(|(_, name)| name.to_string())
-                            .unwrap_or_else(|| pair_address.clone());
-
-                        pool_addresses.push(pair_address);
-                        pool_names.push(pool_name);
-                        markout_times.push(markout_time.to_string());
-                        total_lvr_cents.push(running_total.unsigned_abs());
-                        non_zero_blocks.push(non_zero_count);
-                        total_blocks.push(total_count);
+        let mut pools_a = Vec::new();
+        let mut pools_b = Vec::new();
+        let mut markout_times = Vec::new();
+        let mut correlations = Vec::new();
+        let mut n_intervals = Vec::new();
+
+        for (markout_time, pools) in &interval_totals {
+            // Each pool's series over the whole timeline, and the position
+            // of its first non-zero interval (its deployment)
+            let series: Vec<(&Address, usize, Vec<f64>)> = pools.iter()
+                .filter_map(|(pool_address, totals)| {
+                    let &first_block = totals.keys().next()?;
+                    let start = timeline.partition_point(|&block| block < first_block);
+                    let values = timeline.iter()
+                        .map(|block| totals.get(block).copied().unwrap_or(0) as f64)
+                        .collect();
+                    Some((pool_address, start, values))
+                })
+                .collect();
+
+            for (a, (pool_a, start_a, values_a)) in series.iter().enumerate() {
+                for (pool_b, start_b, values_b) in &series[a + 1..] {
+                    // Only intervals after both pools were deployed are
+                    // compared; missing intervals within that span are zero
+                    let mut pearson = PearsonCorrelation::new();
+                    for i in (*start_a).max(*start_b)..timeline.len() {
+                        pearson.add(values_a[i], values_b[i]);
                     }
+                    let Some(correlation) = pearson.correlation() else {
+                        debug!(
+                            "Skipping correlation of {} and {} ({}): undefined over {} intervals",
+                            pool_a, pool_b, markout_time, pearson.count()
+                        );
+                        continue;
+                    };
+
+                    pools_a.push(pool_a.to_string());
+                    pools_b.push(pool_b.to_string());
+                    markout_times.push(markout_time.clone());
+                    correlations.push(correlation);
+                    n_intervals.push(pearson.count());
                 }
             }
         }
 
-        // Create record batch
         let batch = RecordBatch::try_new(
             Arc::new(schema),
             vec![
-                Arc::new(StringArray::from(pool_addresses)),
-                Arc::new(StringArray::from(pool_names)),
+                Arc::new(StringArray::from(pools_a)),
+                Arc::new(StringArray::from(pools_b)),
                 Arc::new(StringArray::from(markout_times)),
-                Arc::new(UInt64Array::from(total_lvr_cents)),
-                Arc::new(UInt64Array::from(non_zero_blocks)),
-                Arc::new(UInt64Array::from(total_blocks)),
+                Arc::new(Float64Array::from(correlations)),
+                Arc::new(UInt64Array::from(n_intervals)),
             ],
         )?;
 
-        // Write to output file
-        let output_path = Path::from("precomputed/pool_metrics/totals.parquet");
-        self.write_batch_to_store(output_path, batch).await?;
+        self.write_batch_to_store(DatasetKind::PoolCorrelations, batch).await?;
 
-        info!("Successfully wrote precomputed pool totals");
+        info!("Successfully wrote precomputed pool correlations");
         Ok(())
     }
 
@@ -450,34 +1077,12 @@ impl PrecomputedWriter {
         let mut block_numbers = Vec::new();
         let mut max_lvr_cents = Vec::new();
 
-        let valid_pools = get_valid_pools();
-        let checkpoints_path = object_store::path::Path::from("checkpoints");
-        let mut checkpoint_files = self.object_store.list(Some(&checkpoints_path));
+        let checkpoint_files = list_checkpoints(self.object_store.as_ref()).await?;
 
         // Process checkpoint files
-        while let Some(meta_result) = checkpoint_files.next().await {
-            let meta = meta_result.context("Failed to get file metadata")?;
-            let file_path = meta.location.to_string();
-            
-            let pool_address = file_path
-                .split('/')
-                .last()
-                .and_then(|s| s.split('_').next())
-                .context("Failed to extract pool address")?
-                .to_lowercase();
-
-            if !valid_pools.contains(&pool_address) {
-                continue;
-            }
-
-            let markout_time = file_path
-                .split('_')
-                .last()
-                .and_then(|s| s.strip_suffix(".parquet"))
-                .context("Failed to extract markout time")?;
-
-            let bytes = self.object_store.get(&meta.location).await?.bytes().await?;
-            let reader = ParquetRecordBatchReader::try_new(bytes, 1)?;
+        for location in checkpoint_files {
+            let bytes = self.object_store.get(&location).await?.bytes().await?;
+            let reader = parquet_reader(bytes, &location, 1)?;
 
             for batch_result in reader {
                 let batch = batch_result.map_err(|e| {
@@ -485,14 +1090,21 @@ impl PrecomputedWriter {
                     anyhow::anyhow!("Failed to read batch: {}", e)
                 })?;
 
-                let value = get_column_value::<UInt64Array>(&batch, "max_lvr_value")
-                .map_err(|e| anyhow::anyhow!("Failed to get max_lvr_value column: {}", e))?;
-                let block = get_column_value::<UInt64Array>(&batch, "max_lvr_block")
-                .map_err(|e| anyhow::anyhow!("Failed to get max_lvr_block column: {}", e))?;
+                if batch.num_rows() == 0 {
+                    continue;
+                }
+
+                let snapshot = read_checkpoint_snapshot(&batch)?;
+                let markout_time = snapshot.markout_time;
+                let Some(pool_address) = self.pools.address(&snapshot.pair_address) else {
+                    continue;
+                };
+
+                let (value, block) = (snapshot.max_lvr_value, snapshot.max_lvr_block);
 
                 if value > 0 {
-                    let pool_name = get_pool_name(&pool_address);
-                    pool_addresses.push(pool_address.clone());
+                    let pool_name = self.pools.pool_name(pool_address);
+                    pool_addresses.push(pool_address.to_string());
                     pool_names.push(pool_name);
                     markout_times.push(markout_time.to_string());
                     block_numbers.push(block);
@@ -514,8 +1126,7 @@ impl PrecomputedWriter {
         )?;
 
         // Write to output file
-        let output_path = Path::from("precomputed/pool_metrics/max_lvr.parquet");
-        self.write_batch_to_store(output_path, batch).await?;
+        self.write_batch_to_store(DatasetKind::MaxLvr, batch).await?;
 
         info!("Successfully wrote precomputed max LVR values");
         Ok(())
@@ -531,6 +1142,7 @@ impl PrecomputedWriter {
             arrow::datatypes::Field::new("non_zero_blocks", arrow::datatypes::DataType::UInt64, false),
             arrow::datatypes::Field::new("total_blocks", arrow::datatypes::DataType::UInt64, false),
             arrow::datatypes::Field::new("non_zero_proportion", arrow::datatypes::DataType::Float64, false),
+            arrow::datatypes::Field::new("deployment_block", arrow::datatypes::DataType::UInt64, false),
         ]);
 
         let mut pool_addresses = Vec::new();
@@ -539,18 +1151,14 @@ impl PrecomputedWriter {
         let mut non_zero_blocks_vec = Vec::new();
         let mut total_blocks_vec = Vec::new();
         let mut proportions = Vec::new();
+        let mut deployment_blocks = Vec::new();
 
-        let valid_pools = get_valid_pools();
-        let checkpoints_path = object_store::path::Path::from("checkpoints");
-        let mut checkpoint_files = self.object_store.list(Some(&checkpoints_path));
+        let checkpoint_files = list_checkpoints(self.object_store.as_ref()).await?;
 
         // Process all checkpoint files
-        while let Some(meta_result) = checkpoint_files.next().await {
-            let meta = meta_result.context("Failed to get file metadata")?;
-            let file_path = meta.location.to_string();
-
-            let bytes = self.object_store.get(&meta.location).await?.bytes().await?;
-            let record_reader = ParquetRecordBatchReader::try_new(bytes, 1)?;
+        for location in checkpoint_files {
+            let bytes = self.object_store.get(&location).await?.bytes().await?;
+            let record_reader = parquet_reader(bytes, &location, 1)?;
 
             for batch_result in record_reader {
                 let batch = batch_result?;
@@ -560,44 +1168,18 @@ impl PrecomputedWriter {
                 }
 
                 // Get pool address and validate
-                let pair_addresses = get_string_column(&batch, "pair_address")
-                    .map_err(|e| anyhow::anyhow!("Failed to get pair_address column: {}", e))?;
-                let pool_address = pair_addresses.value(0).to_lowercase();
-                
-                if !valid_pools.contains(&pool_address) {
+                let snapshot = read_checkpoint_snapshot(&batch)?;
+                let markout_time = snapshot.markout_time;
+                let Some(pool_address) = self.pools.address(&snapshot.pair_address) else {
                     continue;
-                }
-
-                // Calculate total blocks from buckets
-                let zero_bucket = get_uint64_column(&batch, "total_bucket_0")
-                    .map_err(|e| anyhow::anyhow!("Failed to get total_bucket_0 column: {}", e))?;
-                
-                let non_zero_buckets = [
-                    "total_bucket_0_10",
-                    "total_bucket_10_100",
-                    "total_bucket_100_500",
-                    "total_bucket_500_1000",
-                    "total_bucket_1000_10000",
-                    "total_bucket_10000_plus",
-                ];
-
-                let mut non_zero_count = 0u64;
-                for bucket_name in &non_zero_buckets {
-                    let bucket = get_uint64_column(&batch, bucket_name)
-                        .map_err(|e| anyhow::anyhow!("Failed to get {} column: {}", bucket_name, e))?;
-                    non_zero_count += bucket.value(0);
-                }
+                };
 
-                let zero_count = zero_bucket.value(0);
-                let total_count = zero_count + non_zero_count;
+                // Blocks before the pool existed are not zeros of its
+                let deployment_block = snapshot.deployment_block_or(self.pools.deployment_block(pool_address));
+                let non_zero_count = snapshot.non_zero_blocks();
+                let total_count = snapshot.blocks_since_deployment(deployment_block);
 
                 if total_count > 0 {
-                    // Extract markout time from file path
-                    let markout_time = file_path
-                        .split('_')
-                        .last()
-                        .and_then(|s| s.strip_suffix(".parquet"))
-                        .context("Failed to extract markout time")?;
 
                     let proportion = if total_count > 0 {
                         non_zero_count as f64 / total_count as f64
@@ -605,18 +1187,15 @@ impl PrecomputedWriter {
                         0.0
                     };
 
-                    let pool_name = POOL_NAMES
-                        .iter()
-                        .find(|(addr, _)| addr.to_lowercase() == pool_address)
-                        .map(|(_, name)| name.to_string())
-                        .unwrap_or_else(|| pool_address.clone());
+                    let pool_name = self.pools.pool_name(pool_address);
 
-                    pool_addresses.push(pool_address);
+                    pool_addresses.push(pool_address.to_string());
                     pool_names.push(pool_name);
                     markout_times.push(markout_time.to_string());
                     non_zero_blocks_vec.push(non_zero_count);
                     total_blocks_vec.push(total_count);
                     proportions.push(proportion);
+                    deployment_blocks.push(deployment_block);
                 }
             }
         }
@@ -631,12 +1210,12 @@ impl PrecomputedWriter {
                 Arc::new(UInt64Array::from(non_zero_blocks_vec)),
                 Arc::new(UInt64Array::from(total_blocks_vec)),
                 Arc::new(Float64Array::from(proportions)),
+                Arc::new(UInt64Array::from(deployment_blocks)),
             ],
         )?;
 
         // Write to output file
-        let output_path = Path::from("precomputed/pool_metrics/non_zero.parquet");
-        self.write_batch_to_store(output_path, batch).await?;
+        self.write_batch_to_store(DatasetKind::NonZeroProportions, batch).await?;
 
         info!("Successfully wrote precomputed non-zero proportions");
         Ok(())
@@ -659,78 +1238,52 @@ impl PrecomputedWriter {
         let mut pool_names = Vec::new();
         let mut markout_times = Vec::new();
         let mut bucket_starts = Vec::new();
-        let mut bucket_ends = Vec::new();
+        let mut bucket_ends: Vec<Option<f64>> = Vec::new();
         let mut counts = Vec::new();
         let mut labels = Vec::new();
 
-        let valid_pools = get_valid_pools();
-        let checkpoints_path = object_store::path::Path::from("checkpoints");
-        let mut checkpoint_files = self.object_store.list(Some(&checkpoints_path));
-
-        while let Some(meta_result) = checkpoint_files.next().await {
-            let meta = meta_result.context("Failed to get file metadata")?;
-            let file_path = meta.location.to_string();
+        let checkpoint_files = list_checkpoints(self.object_store.as_ref()).await?;
 
-            // Extract pool address and markout time from file path
-            let pool_address = file_path
-                .split('/')
-                .last()
-                .and_then(|s| s.split('_').next())
-                .context("Failed to extract pool address")?
-                .to_lowercase();
+        for location in checkpoint_files {
+            let bytes = self.object_store.get(&location).await?.bytes().await?;
+            let record_reader = parquet_reader(bytes, &location, 1)?;
 
-            if !valid_pools.contains(&pool_address) {
-                continue;
-            }
+            for batch_result in record_reader {
+                let batch = batch_result?;
 
-            let markout_time = file_path
-                .split('_')
-                .last()
-                .and_then(|s| s.strip_suffix(".parquet"))
-                .context("Failed to extract markout time")?;
+                if batch.num_rows() == 0 {
+                    continue;
+                }
 
-            let bytes = self.object_store.get(&meta.location).await?.bytes().await?;
-            let record_reader = ParquetRecordBatchReader::try_new(bytes, 1)?;
+                let snapshot = read_checkpoint_snapshot(&batch)?;
+                let markout_time = snapshot.markout_time;
+                let Some(pool_address) = self.pools.address(&snapshot.pair_address) else {
+                    continue;
+                };
 
-            for batch_result in record_reader {
-                let batch = batch_result?;
+                let bucket_counts: BucketCounts = snapshot.positive_bucket_counts();
 
-                // Define bucket configurations
-                let bucket_configs = vec![
-                    (0.01, Some(10.0), "total_bucket_0_10", "$0.01-$10"),
-                    (10.0, Some(100.0), "total_bucket_10_100", "$10-$100"),
-                    (100.0, Some(500.0), "total_bucket_100_500", "$100-$500"),
-                    (500.0, Some(1000.0), "total_bucket_500_1000", "$500-$1K"),
-                    (1000.0, Some(10000.0), "total_bucket_1000_10000", "$1K-$10K"),
-                    (10000.0, None, "total_bucket_10000_plus", "$10K+"),
-                ];
-
-                let mut has_data = false;
-                let pool_name = get_pool_name(&pool_address);
-
-                // Process each bucket
-                for (start, end, column_name, label) in bucket_configs {
-                    let count = histogram::get_bucket_value(&batch, column_name)
-                        .map_err(|e| anyhow::anyhow!("Failed to get {} value: {}", column_name, e))?;
-
-                    if count > 0 {
-                        has_data = true;
-                        pool_addresses.push(pool_address.clone());
-                        pool_names.push(pool_name.clone());
-                        markout_times.push(markout_time.to_string());
-                        bucket_starts.push(start);
-                        bucket_ends.push(end);
-                        counts.push(count);
-                        labels.push(label.to_string());
-                    }
+                // Pools with any non-zero activity get every bucket, including
+                // empty ones, so the histogram always spans the full range
+                if bucket_counts.iter().all(|&count| count == 0) {
+                    continue;
                 }
 
-                if has_data {
-                    debug!(
-                        "Added histogram data for pool {} with markout time {}", 
-                        pool_address, markout_time
-                    );
+                let pool_name = self.pools.pool_name(pool_address);
+                for (spec, count) in BUCKET_CONFIG.iter().zip(bucket_counts) {
+                    pool_addresses.push(pool_address.to_string());
+                    pool_names.push(pool_name.clone());
+                    markout_times.push(markout_time.to_string());
+                    bucket_starts.push(spec.range_start);
+                    bucket_ends.push(spec.range_end);
+                    counts.push(count);
+                    labels.push(spec.label.to_string());
                 }
+
+                debug!(
+                    "Added histogram data for pool {} with markout time {}", 
+                    pool_address, markout_time
+                );
             }
         }
 
@@ -742,22 +1295,101 @@ impl PrecomputedWriter {
                 Arc::new(StringArray::from(pool_names)),
                 Arc::new(StringArray::from(markout_times)),
                 Arc::new(Float64Array::from(bucket_starts)),
-                Arc::new(Float64Array::from(
-                    bucket_ends.iter().map(|opt| *opt).collect::<Vec<_>>()
-                )),
+                Arc::new(Float64Array::from(bucket_ends)),
                 Arc::new(UInt64Array::from(counts)),
                 Arc::new(StringArray::from(labels)),
             ],
         )?;
 
         // Write to output file
-        let output_path = Path::from("precomputed/distributions/histograms.parquet");
-        self.write_batch_to_store(output_path, batch).await?;
+        self.write_batch_to_store(DatasetKind::Histograms, batch).await?;
 
         info!("Successfully wrote precomputed histogram distributions");
         Ok(())
     }
 
+    /// `EQUIDEPTH_BINS` equal-probability bins per pool and markout, from the
+    /// finalized checkpoint digests. Checkpoints that predate stored digests
+    /// or have no non-zero blocks get no rows.
+    pub async fn write_equidepth_histograms(&self) -> Result<(), anyhow::Error> {
+        info!("Starting precomputation of equi-depth histograms");
+
+        let schema = arrow::datatypes::Schema::new(vec![
+            arrow::datatypes::Field::new("pool_address", arrow::datatypes::DataType::Utf8, false),
+            arrow::datatypes::Field::new("pool_name", arrow::datatypes::DataType::Utf8, false),
+            arrow::datatypes::Field::new("markout_time", arrow::datatypes::DataType::Utf8, false),
+            arrow::datatypes::Field::new("bin", arrow::datatypes::DataType::UInt64, false),
+            arrow::datatypes::Field::new("range_start", arrow::datatypes::DataType::Float64, false),
+            arrow::datatypes::Field::new("range_end", arrow::datatypes::DataType::Float64, false),
+            arrow::datatypes::Field::new("mass", arrow::datatypes::DataType::Float64, false),
+        ]);
+
+        let mut pool_addresses = Vec::new();
+        let mut pool_names = Vec::new();
+        let mut markout_times = Vec::new();
+        let mut bin_indices = Vec::new();
+        let mut range_starts = Vec::new();
+        let mut range_ends = Vec::new();
+        let mut masses = Vec::new();
+
+        let checkpoint_files = list_checkpoints(self.object_store.as_ref()).await?;
+
+        for location in checkpoint_files {
+            let bytes = self.object_store.get(&location).await?.bytes().await?;
+            let record_reader = parquet_reader(bytes, &location, 1024)?;
+
+            for batch_result in record_reader {
+                let batch = batch_result?;
+                if batch.schema().column_with_name(CHECKPOINT_DIGEST_COLUMN).is_none() {
+                    debug!("Skipping {}, which predates stored digests", location);
+                    continue;
+                }
+
+                for snapshot in CheckpointSnapshot::from_record_batch(&batch)? {
+                    let Some(pool_address) = self.pools.address(&snapshot.pair_address) else {
+                        continue;
+                    };
+                    if snapshot.non_zero_samples == 0 {
+                        continue;
+                    }
+
+                    // Read finalized, like the checkpoint's own quantiles
+                    let mut digest = snapshot.digest;
+                    digest.finalize();
+
+                    let pool_name = self.pools.pool_name(pool_address);
+                    for (bin, (range_start, range_end, mass)) in digest.equi_depth_histogram(EQUIDEPTH_BINS).into_iter().enumerate() {
+                        pool_addresses.push(pool_address.to_string());
+                        pool_names.push(pool_name.clone());
+                        markout_times.push(snapshot.markout_time.to_string());
+                        bin_indices.push(bin as u64);
+                        range_starts.push(range_start);
+                        range_ends.push(range_end);
+                        masses.push(mass);
+                    }
+                }
+            }
+        }
+
+        let batch = RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(StringArray::from(pool_addresses)),
+                Arc::new(StringArray::from(pool_names)),
+                Arc::new(StringArray::from(markout_times)),
+                Arc::new(UInt64Array::from(bin_indices)),
+                Arc::new(Float64Array::from(range_starts)),
+                Arc::new(Float64Array::from(range_ends)),
+                Arc::new(Float64Array::from(masses)),
+            ],
+        )?;
+
+        self.write_batch_to_store(DatasetKind::EquiDepthHistograms, batch).await?;
+
+        info!("Successfully wrote precomputed equi-depth histograms");
+        Ok(())
+    }
+
     pub async fn write_percentile_bands(&self) -> Result<(), anyhow::Error> {
         info!("Starting precomputation of percentile band distributions");
     
@@ -783,67 +1415,58 @@ impl PrecomputedWriter {
         let mut median_values = Vec::new();
         let mut percentile_75_values = Vec::new();
     
-        let valid_pools = get_valid_pools();
     
         // Process all interval files
-        let intervals_path = object_store::path::Path::from("intervals");
-        let mut interval_files = self.object_store.list(Some(&intervals_path));
-    
-        while let Some(meta_result) = interval_files.next().await {
-            let meta = meta_result.context("Failed to get file metadata")?;
-            let file_path = meta.location.to_string();
-    
-            // Extract block range from file name
-            let (file_start, file_end) = if let Some(file_name) = file_path.split('/').last() {
-                let parts: Vec<&str> = file_name.split('_').collect();
-                if parts.len() == 2 {
-                    let start = parts[0].parse::<u64>().context("Failed to parse start block")?;
-                    let end = parts[1].trim_end_matches(".parquet").parse::<u64>()
-                        .context("Failed to parse end block")?;
-                    (start, end)
-                } else {
-                    continue;
-                }
-            } else {
-                continue;
-            };
     
-            let bytes = self.object_store.get(&meta.location).await?.bytes().await?;
-            let record_reader = ParquetRecordBatchReader::try_new(bytes, 1024)?;
+        for (location, file_start, file_end) in self.interval_files().await? {
+    
+            let bytes = self.object_store.get(&location).await?.bytes().await?;
+            let record_reader = parquet_reader(bytes, &location, 1024)?;
     
-            // Collect and group data for this interval file
-            let mut interval_data: HashMap<(String, String), Vec<(u64, u64, u64)>> = HashMap::new();
+            // Collect and group data for this interval file, with the blocks
+            // each group's intervals span
+            let mut interval_data: HashMap<(Address, String), Vec<IntervalPoint>> = HashMap::new();
+            let mut block_spans: HashMap<(Address, String), (u64, u64)> = HashMap::new();
     
             for batch_result in record_reader {
-                let batch = batch_result?;
+                let batch = normalize_interval_batch(batch_result?)?;
+                let block_ranges = interval_block_ranges(&batch, file_start, file_end)
+                    .map_err(|e| anyhow::anyhow!("Failed to read interval block ranges: {}", e))?;
     
-                let markout_times_col = get_string_column(&batch, "markout_time")
+                let markout_times_col = get_string_column(&batch, INTERVAL_MARKOUT_TIME_COLUMN)
                 .map_err(|e| anyhow::anyhow!("Failed to get markout_time column: {}", e))?;
-                let pool_addresses_col = get_string_column(&batch, "pair_address")
+                let pool_addresses_col = get_string_column(&batch, INTERVAL_PAIR_ADDRESS_COLUMN)
                     .map_err(|e| anyhow::anyhow!("Failed to get pair_address column: {}", e))?;
-                let total_lvr_cents = get_uint64_column(&batch, "total_lvr_cents")
+                let total_lvr_cents = get_int64_column(&batch, INTERVAL_TOTAL_LVR_COLUMN)
                     .map_err(|e| anyhow::anyhow!("Failed to get total_lvr_cents column: {}", e))?;
-                let non_zero_counts = get_uint64_column(&batch, "non_zero_count")
+                let non_zero_counts = get_uint64_column(&batch, INTERVAL_NON_ZERO_COUNT_COLUMN)
                     .map_err(|e| anyhow::anyhow!("Failed to get non_zero_count column: {}", e))?;
-                let total_counts = get_uint64_column(&batch, "total_count")
+                let total_counts = get_uint64_column(&batch, INTERVAL_TOTAL_COUNT_COLUMN)
                     .map_err(|e| anyhow::anyhow!("Failed to get total_count column: {}", e))?;
     
-                for i in 0..batch.num_rows() {
-                    let pool_address = pool_addresses_col.value(i).to_lowercase();
-                    if !valid_pools.contains(&pool_address) {
+                for (i, &(start_block, end_block)) in block_ranges.iter().enumerate() {
+                    let Some(pool_address) = self.pools.address(pool_addresses_col.value(i)) else {
                         continue;
-                    }
+                    };
     
                     let markout_time = markout_times_col.value(i).to_string();
                     let lvr_cents = total_lvr_cents.value(i);
                     let non_zero_count = non_zero_counts.value(i);
                     let total_count = total_counts.value(i);
     
+                    // Bands describe positive LVR; net-negative intervals are left out
                     if lvr_cents > 0 && total_count > 0 {
+                        block_spans
+                            .entry((pool_address.clone(), markout_time.clone()))
+                            .and_modify(|(start, end)| {
+                                *start = (*start).min(start_block);
+                                *end = (*end).max(end_block);
+                            })
+                            .or_insert((start_block, end_block));
                         interval_data
                             .entry((pool_address.clone(), markout_time.clone()))
                             .or_default()
-                            .push((lvr_cents, non_zero_count, total_count));
+                            .push((lvr_cents as u64, non_zero_count, total_count));
                     }
                 }
             }
@@ -851,19 +1474,21 @@ impl PrecomputedWriter {
             // Process collected data for this interval
             for ((pool_address, markout_time), values) in interval_data {
                 // Calculate unweighted percentiles
-                let unweighted_values: Vec<u64> = values.iter().map(|(lvr, _, _)| *lvr).collect();
-                let total_lvr = unweighted_values.iter().map(|lvr| *lvr).sum::<u64>() as f64 / 100.0;
-                let p25 = Self::calculate_unweighted_percentile(&unweighted_values, 25);
-                let p50 = Self:: calculate_unweighted_percentile(&unweighted_values, 50);
-                let p75 = Self::calculate_unweighted_percentile(&unweighted_values, 75);
+                let mut unweighted_values: Vec<u64> = values.iter().map(|(lvr, _, _)| *lvr).collect();
+                unweighted_values.sort_unstable();
+                let total_lvr = unweighted_values.iter().copied().sum::<u64>() as f64 / 100.0;
+                let p25 = Self::percentile_of_sorted(&unweighted_values, 25);
+                let p50 = Self::percentile_of_sorted(&unweighted_values, 50);
+                let p75 = Self::percentile_of_sorted(&unweighted_values, 75);
     
-                let pool_name = get_pool_name(&pool_address);
+                let pool_name = self.pools.pool_name(&pool_address);
+                let (start_block, end_block) = block_spans[&(pool_address.clone(), markout_time.clone())];
     
-                pool_addresses.push(pool_address);
+                pool_addresses.push(pool_address.to_string());
                 pool_names.push(pool_name);
                 markout_times.push(markout_time);
-                start_blocks.push(file_start);
-                end_blocks.push(file_end);
+                start_blocks.push(start_block);
+                end_blocks.push(end_block);
                 total_lvr_values.push(total_lvr);
                 percentile_25_values.push(p25);
                 median_values.push(p50);
@@ -888,8 +1513,7 @@ impl PrecomputedWriter {
         )?;
     
         // Write to output file
-        let output_path = Path::from("precomputed/distributions/percentile_bands.parquet");
-        self.write_batch_to_store(output_path, batch).await?;
+        self.write_batch_to_store(DatasetKind::PercentileBands, batch).await?;
     
         info!("Successfully wrote precomputed percentile band distributions");
         Ok(())
@@ -915,38 +1539,43 @@ impl PrecomputedWriter {
         let mut median_values = Vec::new();
         let mut percentile_75_values = Vec::new();
     
+        // The aggregate quartiles merge the pools' digests; the aggregate
+        // checkpoint's own are only used when a pool's checkpoint predates
+        // stored digests
+        let mut merged_digests: BTreeMap<String, TDigest> = BTreeMap::new();
+        let mut markouts_without_digest: HashSet<String> = HashSet::new();
+        let mut stored_aggregates: BTreeMap<String, (u64, u64, u64)> = BTreeMap::new();
+
         // Process checkpoint files
-        let checkpoints_path = object_store::path::Path::from("checkpoints");
-        let mut checkpoint_files = self.object_store.list(Some(&checkpoints_path));
-        let valid_pools = get_valid_pools();
-    
-        while let Some(meta_result) = checkpoint_files.next().await {
-            let meta = meta_result.context("Failed to get file metadata")?;
-            let file_path = meta.location.to_string();
-    
-            // Parse pool address and markout time from filename
-            let pool_address = file_path
-                .split('/')
-                .last()
-                .and_then(|s| s.split('_').next())
-                .map(|s| s.to_lowercase())
-                .context("Failed to extract pool address")?;
-    
-            if !valid_pools.contains(&pool_address) {
-                continue;
-            }
+        let checkpoint_files = list_checkpoints(self.object_store.as_ref()).await?;
     
-            let markout_time = file_path
-                .split('_')
-                .last()
-                .and_then(|s| s.strip_suffix(".parquet"))
-                .context("Failed to extract markout time")?;
-    
-            let bytes = self.object_store.get(&meta.location).await?.bytes().await?;
-            let record_reader = ParquetRecordBatchReader::try_new(bytes, 1)?;
+        for location in checkpoint_files {
+            let bytes = self.object_store.get(&location).await?.bytes().await?;
+            let record_reader = parquet_reader(bytes, &location, 1)?;
     
             for batch_result in record_reader {
                 let batch = batch_result?;
+
+                if batch.num_rows() == 0 {
+                    continue;
+                }
+
+                let snapshot = read_checkpoint_snapshot(&batch)?;
+                let markout_time = snapshot.markout_time;
+                if snapshot.pair_address == AGGREGATE_POOL_ADDRESS {
+                    let quartiles = (snapshot.percentile_25_cents, snapshot.median_cents, snapshot.percentile_75_cents);
+                    stored_aggregates.insert(markout_time.to_string(), quartiles);
+                    continue;
+                }
+                let Some(pool_address) = self.pools.address(&snapshot.pair_address) else {
+                    continue;
+                };
+
+                if batch.schema().column_with_name(CHECKPOINT_DIGEST_COLUMN).is_some() {
+                    merged_digests.entry(markout_time.to_string()).or_default().merge(&snapshot.digest);
+                } else {
+                    markouts_without_digest.insert(markout_time.to_string());
+                }
                 
                 let p25 = get_uint64_column(&batch, "percentile_25_cents")
                     .map_err(|e| anyhow::anyhow!("Failed to get percentile_25_cents column: {}", e))?;
@@ -955,11 +1584,9 @@ impl PrecomputedWriter {
                 let p75 = get_uint64_column(&batch, "percentile_75_cents")
                     .map_err(|e| anyhow::anyhow!("Failed to get percentile_75_cents column: {}", e))?;
     
-                if p25.len() > 0 && p50.len() > 0 && p75.len() > 0 {
-                    let pool_name = get_pool_name(&pool_address);
-                    
-                    pool_addresses.push(pool_address.clone());
-                    pool_names.push(pool_name);
+                if !p25.is_empty() && !p50.is_empty() && !p75.is_empty() {
+                    pool_addresses.push(pool_address.to_string());
+                    pool_names.push(self.pools.pool_name(pool_address));
                     markout_times.push(markout_time.to_string());
                     percentile_25_values.push(p25.value(0));
                     median_values.push(p50.value(0));
@@ -967,6 +1594,22 @@ impl PrecomputedWriter {
                 }
             }
         }
+
+        for (markout_time, stored) in stored_aggregates {
+            let (p25, p50, p75) = match merged_digests.get(&markout_time) {
+                Some(digest) if !markouts_without_digest.contains(&markout_time) => {
+                    let cents = |q: f64| digest.quantile(q).map(|x| (x * 100.0).round() as u64).unwrap_or(0);
+                    (cents(0.25), cents(0.50), cents(0.75))
+                }
+                _ => stored,
+            };
+            pool_addresses.push(AGGREGATE_POOL_ADDRESS.to_string());
+            pool_names.push("All pools".to_string());
+            markout_times.push(markout_time);
+            percentile_25_values.push(p25);
+            median_values.push(p50);
+            percentile_75_values.push(p75);
+        }
     
         // Create record batch
         let batch = RecordBatch::try_new(
@@ -982,20 +1625,24 @@ impl PrecomputedWriter {
         )?;
     
         // Write to output file
-        let output_path = Path::from("precomputed/distributions/quartile_plots.parquet");
-        self.write_batch_to_store(output_path, batch).await?;
+        self.write_batch_to_store(DatasetKind::QuartilePlots, batch).await?;
     
         info!("Successfully wrote precomputed quartile plot distributions");
         Ok(())
     }
 
     pub fn calculate_unweighted_percentile(values: &[u64], percentile: u64) -> f64 {
-        if values.is_empty() {
-            return 0.0;
-        }
-    
         let mut sorted = values.to_vec();
         sorted.sort_unstable();
+        Self::percentile_of_sorted(&sorted, percentile)
+    }
+
+    /// Linearly interpolated percentile of cent values that are already sorted
+    /// ascending, in dollars. Lets callers sort once for several percentiles.
+    pub fn percentile_of_sorted(sorted: &[u64], percentile: u64) -> f64 {
+        if sorted.is_empty() {
+            return 0.0;
+        }
     
         let rank = (percentile as f64 / 100.0) * (sorted.len() - 1) as f64;
         let i = rank.floor() as usize;
@@ -1014,7 +1661,7 @@ impl PrecomputedWriter {
         let schema = arrow::datatypes::Schema::new(vec![
             arrow::datatypes::Field::new("cluster_name", arrow::datatypes::DataType::Utf8, false),
             arrow::datatypes::Field::new("markout_time", arrow::datatypes::DataType::Utf8, false),
-            arrow::datatypes::Field::new("total_lvr_cents", arrow::datatypes::DataType::UInt64, false),
+            arrow::datatypes::Field::new("total_lvr_cents", arrow::datatypes::DataType::Int64, false),
             arrow::datatypes::Field::new("proportion", arrow::datatypes::DataType::Float64, false),
         ]);
 
@@ -1024,57 +1671,44 @@ impl PrecomputedWriter {
         let mut proportions = Vec::new();
 
         // Process checkpoint files to get proportions for each markout time
-        let checkpoints_path = object_store::path::Path::from("checkpoints");
-        let mut checkpoint_files = self.object_store.list(Some(&checkpoints_path));
+        let checkpoint_files = list_checkpoints(self.object_store.as_ref()).await?;
 
         // Map to store results by markout time
-        let mut markout_data: HashMap<String, HashMap<String, u64>> = HashMap::new();
+        let mut markout_data: HashMap<String, HashMap<String, i64>> = HashMap::new();
 
         // Process all checkpoint files
-        while let Some(meta_result) = checkpoint_files.next().await {
-            let meta = meta_result.context("Failed to get file metadata")?;
-            let file_path = meta.location.to_string();
-
-            // Extract markout time from file path
-            let markout_time = file_path
-                .split('_')
-                .last()
-                .and_then(|s| s.strip_suffix(".parquet"))
-                .context("Failed to extract markout time from file path")?;
-
-            let bytes = self.object_store.get(&meta.location).await?.bytes().await?;
-            let record_reader = ParquetRecordBatchReader::try_new(bytes, 1)?;
+        for location in checkpoint_files {
+            let bytes = self.object_store.get(&location).await?.bytes().await?;
+            let record_reader = parquet_reader(bytes, &location, 1)?;
 
             for batch_result in record_reader {
                 let batch = batch_result?;
 
-                let pair_addresses = get_string_column(&batch, "pair_address")
-                    .map_err(|e| anyhow::anyhow!("Failed to get pair_address column: {}", e))?;
-                let running_totals = get_uint64_column(&batch, "running_total")
-                    .map_err(|e| anyhow::anyhow!("Failed to get running_total column: {}", e))?;
-
-                // Process each row
-                for i in 0..batch.num_rows() {
-                    let pool_address = pair_addresses.value(i);
-                    let running_total = running_totals.value(i);
+                if batch.num_rows() == 0 {
+                    continue;
+                }
 
-                    // Get the cluster name for this pool
-                    if let Some(cluster_name) = clusters::get_cluster_name(pool_address) {
-                        markout_data
-                            .entry(markout_time.to_string())
-                            .or_default()
-                            .entry(cluster_name.to_string())
-                            .and_modify(|total| *total = total.saturating_add(running_total))
-                            .or_insert(running_total);
-                    }
+                let snapshot = read_checkpoint_snapshot(&batch)?;
+                let markout_time = snapshot.markout_time;
+                let running_total = snapshot.running_total.as_i64();
+
+                // Get the cluster name for this pool
+                if let Some(cluster_name) = self.pools.cluster_name(&snapshot.pair_address) {
+                    markout_data
+                        .entry(markout_time.to_string())
+                        .or_default()
+                        .entry(cluster_name.to_string())
+                        .and_modify(|total| *total = total.saturating_add(running_total))
+                        .or_insert(running_total);
                 }
             }
         }
 
         // Convert aggregated data into final format
         for (markout_time, cluster_totals) in markout_data {
-            let total_lvr_cents: u64 = cluster_totals.values().sum();
+            let total_lvr_cents: i64 = cluster_totals.values().sum();
 
+            // Shares are undefined when negative LVR cancels the whole market
             for (cluster_name, cluster_total) in cluster_totals {
                 let proportion = if total_lvr_cents > 0 {
                     cluster_total as f64 / total_lvr_cents as f64
@@ -1095,19 +1729,130 @@ impl PrecomputedWriter {
             vec![
                 Arc::new(StringArray::from(cluster_names)),
                 Arc::new(StringArray::from(markout_times)),
-                Arc::new(UInt64Array::from(total_lvr_values)),
+                Arc::new(Int64Array::from(total_lvr_values)),
                 Arc::new(Float64Array::from(proportions)),
             ],
         )?;
 
         // Write to output file
-        let output_path = Path::from("precomputed/clusters/proportions.parquet");
-        self.write_batch_to_store(output_path, batch).await?;
+        self.write_batch_to_store(DatasetKind::ClusterProportions, batch).await?;
 
         info!("Successfully wrote precomputed cluster proportions");
         Ok(())
     }
 
+    /// Share of each cluster's blocks with non-zero LVR. Reads the activity the
+    /// processor recorded, which counts a block once however many member pools
+    /// were active in it. Without that file the counts are estimated from the
+    /// checkpoint buckets, which can only bound the overlap.
+    pub async fn write_cluster_non_zero(&self) -> Result<(), anyhow::Error> {
+        info!("Starting precomputation of cluster non-zero proportions");
+
+        let schema = arrow::datatypes::Schema::new(vec![
+            arrow::datatypes::Field::new("cluster_name", arrow::datatypes::DataType::Utf8, false),
+            arrow::datatypes::Field::new("markout_time", arrow::datatypes::DataType::Utf8, false),
+            arrow::datatypes::Field::new("total_blocks", arrow::datatypes::DataType::UInt64, false),
+            arrow::datatypes::Field::new("non_zero_blocks", arrow::datatypes::DataType::UInt64, false),
+            arrow::datatypes::Field::new("non_zero_proportion", arrow::datatypes::DataType::Float64, false),
+        ]);
+
+        // (total_blocks, non_zero_blocks) per cluster and markout time
+        let counts = match self.object_store.get(&Path::from(CLUSTER_ACTIVITY_PATH)).await {
+            Ok(result) => {
+                let bytes = result.bytes().await?;
+                let mut counts: BTreeMap<(String, String), (u64, u64)> = BTreeMap::new();
+                for batch_result in parquet_reader(bytes, &Path::from(CLUSTER_ACTIVITY_PATH), 1024)? {
+                    let batch = batch_result?;
+                    let cluster_names = get_string_column(&batch, "cluster_name")
+                        .map_err(|e| anyhow::anyhow!("Failed to get cluster_name column: {}", e))?;
+                    let markout_times = get_string_column(&batch, "markout_time")
+                        .map_err(|e| anyhow::anyhow!("Failed to get markout_time column: {}", e))?;
+                    let total_blocks = get_uint64_column(&batch, "total_blocks")
+                        .map_err(|e| anyhow::anyhow!("Failed to get total_blocks column: {}", e))?;
+                    let non_zero_blocks = get_uint64_column(&batch, "non_zero_blocks")
+                        .map_err(|e| anyhow::anyhow!("Failed to get non_zero_blocks column: {}", e))?;
+                    for i in 0..batch.num_rows() {
+                        counts.insert(
+                            (cluster_names.value(i).to_string(), markout_times.value(i).to_string()),
+                            (total_blocks.value(i), non_zero_blocks.value(i)),
+                        );
+                    }
+                }
+                counts
+            }
+            Err(object_store::Error::NotFound { .. }) => {
+                warn!("No {} found; estimating cluster activity from checkpoint buckets", CLUSTER_ACTIVITY_PATH);
+                self.estimate_cluster_non_zero().await?
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut cluster_names = Vec::with_capacity(counts.len());
+        let mut markout_times = Vec::with_capacity(counts.len());
+        let mut total_blocks_vec = Vec::with_capacity(counts.len());
+        let mut non_zero_blocks_vec = Vec::with_capacity(counts.len());
+        let mut proportions = Vec::with_capacity(counts.len());
+        for ((cluster_name, markout_time), (total_blocks, non_zero_blocks)) in counts {
+            cluster_names.push(cluster_name);
+            markout_times.push(markout_time);
+            total_blocks_vec.push(total_blocks);
+            non_zero_blocks_vec.push(non_zero_blocks);
+            proportions.push(if total_blocks > 0 { non_zero_blocks as f64 / total_blocks as f64 } else { 0.0 });
+        }
+
+        let batch = RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(StringArray::from(cluster_names)),
+                Arc::new(StringArray::from(markout_times)),
+                Arc::new(UInt64Array::from(total_blocks_vec)),
+                Arc::new(UInt64Array::from(non_zero_blocks_vec)),
+                Arc::new(Float64Array::from(proportions)),
+            ],
+        )?;
+
+        self.write_batch_to_store(DatasetKind::ClusterNonZero, batch).await?;
+
+        info!("Successfully wrote precomputed cluster non-zero proportions");
+        Ok(())
+    }
+
+    /// Cluster block counts from checkpoint buckets alone. Member pools cover
+    /// overlapping blocks, so the cluster spans as many blocks as its longest
+    /// member and at most that many of them can be non-zero.
+    async fn estimate_cluster_non_zero(&self) -> Result<BTreeMap<(String, String), (u64, u64)>, anyhow::Error> {
+        let mut counts: BTreeMap<(String, String), (u64, u64)> = BTreeMap::new();
+        let checkpoint_files = list_checkpoints(self.object_store.as_ref()).await?;
+
+        for location in checkpoint_files {
+            let bytes = self.object_store.get(&location).await?.bytes().await?;
+            let record_reader = parquet_reader(bytes, &location, 1)?;
+
+            for batch_result in record_reader {
+                let batch = batch_result?;
+
+                if batch.num_rows() == 0 {
+                    continue;
+                }
+
+                let snapshot = read_checkpoint_snapshot(&batch)?;
+                let markout_time = snapshot.markout_time;
+                let Some(cluster_name) = self.pools.cluster_name(&snapshot.pair_address) else {
+                    continue;
+                };
+
+                let entry = counts.entry((cluster_name.to_string(), markout_time.to_string())).or_insert((0, 0));
+                entry.0 = entry.0.max(snapshot.total_blocks());
+                entry.1 += snapshot.non_zero_blocks();
+            }
+        }
+
+        for (total_blocks, non_zero_blocks) in counts.values_mut() {
+            *non_zero_blocks = (*non_zero_blocks).min(*total_blocks);
+        }
+        Ok(counts)
+    }
+
     pub async fn write_cluster_histograms(&self) -> Result<(), anyhow::Error> {
         info!("Starting precomputation of cluster histogram distributions");
         
@@ -1123,100 +1868,65 @@ impl PrecomputedWriter {
         let mut cluster_names = Vec::new();
         let mut markout_times = Vec::new();
         let mut bucket_starts = Vec::new();
-        let mut bucket_ends = Vec::new();
+        let mut bucket_ends: Vec<Option<f64>> = Vec::new();
         let mut counts = Vec::new();
         let mut labels = Vec::new();
 
         // Process checkpoint files
-        let checkpoints_path = object_store::path::Path::from("checkpoints");
-        let mut checkpoint_files = self.object_store.list(Some(&checkpoints_path));
+        let checkpoint_files = list_checkpoints(self.object_store.as_ref()).await?;
 
         // Map to store intermediate histogram data
-        let mut cluster_data: HashMap<(String, String), Vec<u64>> = HashMap::new();
-
-        while let Some(meta_result) = checkpoint_files.next().await {
-            let meta = meta_result.context("Failed to get file metadata")?;
-            let file_path = meta.location.to_string();
-
-            let markout_time = file_path
-                .split('_')
-                .last()
-                .and_then(|s| s.strip_suffix(".parquet"))
-                .context("Failed to extract markout time")?;
+        let mut cluster_data: HashMap<(String, String), BucketCounts> = HashMap::new();
 
-            let bytes = self.object_store.get(&meta.location).await?.bytes().await?;
-            let record_reader = ParquetRecordBatchReader::try_new(bytes, 1)?;
+        for location in checkpoint_files {
+            let bytes = self.object_store.get(&location).await?.bytes().await?;
+            let record_reader = parquet_reader(bytes, &location, 1)?;
 
             for batch_result in record_reader {
                 let batch = batch_result?;
 
                 let pair_addresses = get_string_column(&batch, "pair_address")
                     .map_err(|e| anyhow::anyhow!("Failed to get pair_address column: {}", e))?;
+                let markout_times_col = get_string_column(&batch, "markout_time")
+                    .map_err(|e| anyhow::anyhow!("Failed to get markout_time column: {}", e))?;
 
                 // Get all bucket columns
-                let bucket_names = [
-                    "total_bucket_0_10",
-                    "total_bucket_10_100",
-                    "total_bucket_100_500",
-                    "total_bucket_500_1000",
-                    "total_bucket_1000_10000",
-                    "total_bucket_10000_plus",
-                ];
-
-                let mut bucket_columns = Vec::new();
-                for name in &bucket_names {
-                    let column = get_uint64_column(&batch, name)
-                        .map_err(|e| anyhow::anyhow!("Failed to get {} column: {}", name, e))?;
+                let mut bucket_columns = Vec::with_capacity(BUCKET_CONFIG.len());
+                for spec in &BUCKET_CONFIG {
+                    let column = get_uint64_column(&batch, spec.column)
+                        .map_err(|e| anyhow::anyhow!("Failed to get {} column: {}", spec.column, e))?;
                     bucket_columns.push(column);
                 }
 
                 // Process each row
                 for row in 0..batch.num_rows() {
                     let pool_address = pair_addresses.value(row);
+                    let markout_time = markout_times_col.value(row);
                     
                     // Get cluster name for this pool
-                    if let Some(cluster_name) = get_cluster_name(&pool_address.to_lowercase()) {
-                        let bucket_values: Vec<u64> = bucket_columns
-                            .iter()
-                            .map(|col| col.value(row))
-                            .collect();
+                    if let Some(cluster_name) = self.pools.cluster_name(pool_address) {
+                        let buckets = cluster_data
+                            .entry((cluster_name.to_string(), markout_time.to_string()))
+                            .or_insert([0; BUCKET_CONFIG.len()]);
 
                         // Aggregate values by cluster and markout time
-                        cluster_data
-                            .entry((cluster_name.to_string(), markout_time.to_string()))
-                            .and_modify(|buckets| {
-                                for (i, &value) in bucket_values.iter().enumerate() {
-                                    buckets[i] = buckets[i].saturating_add(value);
-                                }
-                            })
-                            .or_insert_with(|| bucket_values);
+                        for (total, column) in buckets.iter_mut().zip(&bucket_columns) {
+                            *total = total.saturating_add(column.value(row));
+                        }
                     }
                 }
             }
         }
 
-        // Define bucket configurations
-        let bucket_configs = vec![
-            (0.01, Some(10.0), "$0.01-$10"),
-            (10.0, Some(100.0), "$10-$100"),
-            (100.0, Some(500.0), "$100-$500"),
-            (500.0, Some(3000.0), "$500-$3K"),
-            (3000.0, Some(10000.0), "$3K-$10K"),
-            (10000.0, Some(30000.0), "$10K-$30K"),
-            (30000.0, None, "$30K+"),
-        ];
-
         // Convert aggregated data into row format
         for ((cluster_name, markout_time), bucket_counts) in cluster_data {
-            for ((start, end, label), count) in bucket_configs.iter().zip(bucket_counts.iter()) {
-                if *count > 0 {
-                    cluster_names.push(cluster_name.clone());
-                    markout_times.push(markout_time.clone());
-                    bucket_starts.push(*start);
-                    bucket_ends.push(*end);
-                    counts.push(*count);
-                    labels.push(label.to_string());
-                }
+            for (spec, count) in BUCKET_CONFIG.iter().zip(bucket_counts) {
+                cluster_names.push(cluster_name.clone());
+                markout_times.push(markout_time.clone());
+                bucket_starts.push(spec.range_start);
+                bucket_ends.push(spec.range_end);
+                counts.push(count);
+                labels.push(spec.label.to_string());
             }
         }
 
@@ -1227,17 +1937,14 @@ impl PrecomputedWriter {
                 Arc::new(StringArray::from(cluster_names)),
                 Arc::new(StringArray::from(markout_times)),
                 Arc::new(Float64Array::from(bucket_starts)),
-                Arc::new(Float64Array::from(
-                    bucket_ends.into_iter().map(|opt| opt).collect::<Vec<Option<f64>>>()
-                )),
+                Arc::new(Float64Array::from(bucket_ends)),
                 Arc::new(UInt64Array::from(counts)),
                 Arc::new(StringArray::from(labels)),
             ],
         )?;
 
         // Write to output file
-        let output_path = Path::from("precomputed/clusters/histograms.parquet");
-        self.write_batch_to_store(output_path, batch).await?;
+        self.write_batch_to_store(DatasetKind::ClusterHistograms, batch).await?;
 
         info!("Successfully wrote precomputed cluster histogram distributions");
         Ok(())
@@ -1250,7 +1957,7 @@ impl PrecomputedWriter {
             arrow::datatypes::Field::new("time_range", arrow::datatypes::DataType::Utf8, false),
             arrow::datatypes::Field::new("cluster_name", arrow::datatypes::DataType::Utf8, false),
             arrow::datatypes::Field::new("markout_time", arrow::datatypes::DataType::Utf8, false),
-            arrow::datatypes::Field::new("total_lvr_cents", arrow::datatypes::DataType::UInt64, false),
+            arrow::datatypes::Field::new("total_lvr_cents", arrow::datatypes::DataType::Int64, false),
         ]);
 
         let mut time_ranges = Vec::new();
@@ -1258,44 +1965,33 @@ impl PrecomputedWriter {
         let mut markout_times = Vec::new();
         let mut total_lvr_values = Vec::new();
 
-        let intervals_path = object_store::path::Path::from("intervals");
-        let mut interval_files = self.object_store.list(Some(&intervals_path));
         
-        // Collect data by start block and cluster
-        let mut monthly_data: HashMap<(u64, String, String), u64> = HashMap::new();
+        // Collect data by start block and cluster, ordered so each cluster's
+        // months stay chronological once rows are sorted by cluster
+        let mut monthly_data: BTreeMap<(u64, String, String), i64> = BTreeMap::new();
         let mut files_processed = 0;
         
-        while let Some(meta_result) = interval_files.next().await {
+        for (location, start_block, _) in self.interval_files().await? {
             files_processed += 1;
-            let meta = meta_result.context("Failed to get file metadata")?;
-            let file_path = meta.location.to_string();
-            
-            // Extract start block from file path
-            let start_block = file_path
-                .split('/')
-                .last()
-                .and_then(|name| name.split('_').next())
-                .and_then(|num| num.parse::<u64>().ok())
-                .context("Failed to parse start block")?;
 
             // Skip if we don't have a time range for this start block
             if !INTERVAL_RANGES.contains_key(&start_block) {
                 continue;
             }
 
-            let bytes = self.object_store.get(&meta.location).await?.bytes().await?;
-            let record_reader = ParquetRecordBatchReader::try_new(bytes, 1024)?;
+            let bytes = self.object_store.get(&location).await?.bytes().await?;
+            let record_reader = parquet_reader(bytes, &location, 1024)?;
 
             for batch_result in record_reader {
-                let batch = batch_result?;
+                let batch = normalize_interval_batch(batch_result?)?;
 
-                let markout_times_col = get_string_column(&batch, "markout_time")
+                let markout_times_col = get_string_column(&batch, INTERVAL_MARKOUT_TIME_COLUMN)
                     .map_err(|e| anyhow::anyhow!("Failed to get markout_time column: {}", e))?;
-                let pair_addresses = get_string_column(&batch, "pair_address")
+                let pair_addresses = get_string_column(&batch, INTERVAL_PAIR_ADDRESS_COLUMN)
                     .map_err(|e| anyhow::anyhow!("Failed to get pair_address column: {}", e))?;
-                let total_lvr_cents = get_uint64_column(&batch, "total_lvr_cents")
+                let total_lvr_cents = get_int64_column(&batch, INTERVAL_TOTAL_LVR_COLUMN)
                     .map_err(|e| anyhow::anyhow!("Failed to get total_lvr_cents column: {}", e))?;
-                let non_zero_counts = get_uint64_column(&batch, "non_zero_count")
+                let non_zero_counts = get_uint64_column(&batch, INTERVAL_NON_ZERO_COUNT_COLUMN)
                     .map_err(|e| anyhow::anyhow!("Failed to get non_zero_count column: {}", e))?;
 
                 for i in 0..batch.num_rows() {
@@ -1303,8 +1999,7 @@ impl PrecomputedWriter {
                         continue;
                     }
 
-                    let pool_address = pair_addresses.value(i).to_lowercase();
-                    if let Some(cluster_name) = get_cluster_name(&pool_address) {
+                    if let Some(cluster_name) = self.pools.cluster_name(pair_addresses.value(i)) {
                         let markout_time = markout_times_col.value(i).to_string();
                         let lvr_cents = total_lvr_cents.value(i);
 
@@ -1334,13 +2029,12 @@ impl PrecomputedWriter {
                 Arc::new(StringArray::from(time_ranges)),
                 Arc::new(StringArray::from(cluster_names)),
                 Arc::new(StringArray::from(markout_times)),
-                Arc::new(UInt64Array::from(total_lvr_values)),
+                Arc::new(Int64Array::from(total_lvr_values)),
             ],
         )?;
 
         // Write to output file
-        let output_path = Path::from("precomputed/clusters/monthly_totals.parquet");
-        self.write_batch_to_store(output_path, batch).await?;
+        self.write_batch_to_store(DatasetKind::ClusterMonthlyTotals, batch).await?;
 
         info!(
             "Successfully wrote precomputed monthly cluster totals (processed {} files)", 
@@ -1349,6 +2043,110 @@ impl PrecomputedWriter {
         Ok(())
     }
 
+    pub async fn write_monthly_pool_totals(&self) -> Result<(), anyhow::Error> {
+        info!("Starting precomputation of monthly pool totals");
+
+        let schema = arrow::datatypes::Schema::new(vec![
+            arrow::datatypes::Field::new("time_range", arrow::datatypes::DataType::Utf8, false),
+            arrow::datatypes::Field::new("pool_address", arrow::datatypes::DataType::Utf8, false),
+            arrow::datatypes::Field::new("pool_name", arrow::datatypes::DataType::Utf8, false),
+            arrow::datatypes::Field::new("markout_time", arrow::datatypes::DataType::Utf8, false),
+            arrow::datatypes::Field::new("total_lvr_cents", arrow::datatypes::DataType::Int64, false),
+        ]);
+
+
+        // Monthly totals keyed by pool/markout, then by the range's start block
+        let mut monthly_data: HashMap<(Address, String), BTreeMap<u64, i64>> = HashMap::new();
+        let mut months: BTreeMap<u64, &str> = BTreeMap::new();
+
+        for (location, start_block, _) in self.interval_files().await? {
+            let Some(&time_range) = INTERVAL_RANGES.get(&start_block) else {
+                continue;
+            };
+            months.insert(start_block, time_range);
+
+            let bytes = self.object_store.get(&location).await?.bytes().await?;
+            let record_reader = parquet_reader(bytes, &location, 1024)?;
+
+            for batch_result in record_reader {
+                let batch = normalize_interval_batch(batch_result?)?;
+
+                let markout_times_col = get_string_column(&batch, INTERVAL_MARKOUT_TIME_COLUMN)
+                    .map_err(|e| anyhow::anyhow!("Failed to get markout_time column: {}", e))?;
+                let pair_addresses = get_string_column(&batch, INTERVAL_PAIR_ADDRESS_COLUMN)
+                    .map_err(|e| anyhow::anyhow!("Failed to get pair_address column: {}", e))?;
+                let total_lvr_cents = get_int64_column(&batch, INTERVAL_TOTAL_LVR_COLUMN)
+                    .map_err(|e| anyhow::anyhow!("Failed to get total_lvr_cents column: {}", e))?;
+                let non_zero_counts = get_uint64_column(&batch, INTERVAL_NON_ZERO_COUNT_COLUMN)
+                    .map_err(|e| anyhow::anyhow!("Failed to get non_zero_count column: {}", e))?;
+
+                for i in 0..batch.num_rows() {
+                    if non_zero_counts.value(i) == 0 {
+                        continue;
+                    }
+
+                    let Some(pool_address) = self.pools.address(pair_addresses.value(i)) else {
+                        continue;
+                    };
+
+                    let total = monthly_data
+                        .entry((pool_address.clone(), markout_times_col.value(i).to_string()))
+                        .or_default()
+                        .entry(start_block)
+                        .or_default();
+                    *total = total.saturating_add(total_lvr_cents.value(i));
+                }
+            }
+        }
+
+        let mut keys: Vec<_> = monthly_data.keys().cloned().collect();
+        keys.sort();
+
+        let mut time_ranges = Vec::new();
+        let mut pool_addresses = Vec::new();
+        let mut pool_names = Vec::new();
+        let mut markout_times = Vec::new();
+        let mut total_lvr_values = Vec::new();
+
+        // Series start at the pool's first active month, so pools deployed
+        // mid-range have no rows before deployment; later quiet months are zero
+        for key in keys {
+            let totals = &monthly_data[&key];
+            let Some(&first_block) = totals.keys().next() else {
+                continue;
+            };
+            let (pool_address, markout_time) = key;
+            let pool_name = self.pools.pool_name(&pool_address);
+
+            for (&start_block, &time_range) in months.range(first_block..) {
+                time_ranges.push(time_range.to_string());
+                pool_addresses.push(pool_address.to_string());
+                pool_names.push(pool_name.clone());
+                markout_times.push(markout_time.clone());
+                total_lvr_values.push(totals.get(&start_block).copied().unwrap_or(0));
+            }
+        }
+
+        let batch = RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(StringArray::from(time_ranges)),
+                Arc::new(StringArray::from(pool_addresses)),
+                Arc::new(StringArray::from(pool_names)),
+                Arc::new(StringArray::from(markout_times)),
+                Arc::new(Int64Array::from(total_lvr_values)),
+            ],
+        )?;
+
+        self.write_batch_to_store(DatasetKind::MonthlyPoolTotals, batch).await?;
+
+        info!(
+            "Successfully wrote precomputed monthly pool totals (processed {} files)",
+            months.len()
+        );
+        Ok(())
+    }
+
     pub async fn write_distribution_metrics(&self) -> Result<(), anyhow::Error> {
         info!("Starting precomputation of distribution metrics");
     
@@ -1360,6 +2158,9 @@ impl PrecomputedWriter {
             arrow::datatypes::Field::new("std_dev", arrow::datatypes::DataType::Float64, false),
             arrow::datatypes::Field::new("skewness", arrow::datatypes::DataType::Float64, false),
             arrow::datatypes::Field::new("kurtosis", arrow::datatypes::DataType::Float64, false),
+            arrow::datatypes::Field::new("fraction_above_1000_dollars", arrow::datatypes::DataType::Float64, true),
+            arrow::datatypes::Field::new("trimmed_mean_cents", arrow::datatypes::DataType::UInt64, true),
+            arrow::datatypes::Field::new("iqr_cents", arrow::datatypes::DataType::UInt64, true),
         ]);
     
         let mut pool_addresses = Vec::new();
@@ -1369,21 +2170,32 @@ impl PrecomputedWriter {
         let mut std_devs = Vec::new();
         let mut skewness_values = Vec::new();
         let mut kurtosis_values = Vec::new();
-    
+        let mut fractions_above = Vec::new();
+        let mut trimmed_means = Vec::new();
+        let mut iqrs = Vec::new();
+        let mut push_digest_stats = |digest: Option<&TDigest>| {
+            let cents = |x: f64| (x * 100.0).round() as u64;
+            fractions_above.push(digest.and_then(|digest| fraction_above(digest, LARGE_LVR_THRESHOLD_DOLLARS)));
+            trimmed_means.push(digest.and_then(|digest| digest.trimmed_mean(TRIMMED_MEAN_CUT, 1.0 - TRIMMED_MEAN_CUT)).map(cents));
+            iqrs.push(digest.and_then(TDigest::iqr).map(cents));
+        };
+    
+        // Each pool's moments per markout, combined into the aggregate rows
+        let mut markout_stats: BTreeMap<String, Vec<(Address, OnlineStats)>> = BTreeMap::new();
+        // Each pool's digest per markout, or None for a checkpoint that
+        // predates stored digests
+        let mut markout_digests: BTreeMap<String, Vec<(Address, Option<TDigest>)>> = BTreeMap::new();
+
         // Process checkpoint files
-        let checkpoints_path = object_store::path::Path::from("checkpoints");
-        let mut checkpoint_files = self.object_store.list(Some(&checkpoints_path));
-        let valid_pools = get_valid_pools();
+        let checkpoint_files = list_checkpoints(self.object_store.as_ref()).await?;
     
-        while let Some(meta_result) = checkpoint_files.next().await {
-            let meta = meta_result.context("Failed to get file metadata")?;
-    
-            let bytes = self.object_store.get(&meta.location)
+        for location in checkpoint_files {
+            let bytes = self.object_store.get(&location)
                 .await?
                 .bytes()
                 .await?;
     
-            let reader = ParquetRecordBatchReader::try_new(bytes, 1024)?;
+            let reader = parquet_reader(bytes, &location, 1024)?;
     
             for batch_result in reader {
                 let batch = batch_result?;
@@ -1402,24 +2214,45 @@ impl PrecomputedWriter {
                     .map_err(|e| anyhow::anyhow!("Failed to get kurtosis column: {}", e))?;
                 let samples_col = get_uint64_column(&batch, "non_zero_samples")
                     .map_err(|e| anyhow::anyhow!("Failed to get non_zero_samples column: {}", e))?;
+                let snapshots = if batch.schema().column_with_name(CHECKPOINT_DIGEST_COLUMN).is_some() {
+                    Some(CheckpointSnapshot::from_record_batch(&batch)?)
+                } else {
+                    None
+                };
                 
                 for i in 0..batch.num_rows() {
-                    let pool_address = pool_addresses_col.value(i).to_lowercase();
-                    
-                    // Skip invalid pools
-                    if !valid_pools.contains(&pool_address) {
+                    let Some(pool_address) = self.pools.address(pool_addresses_col.value(i)) else {
                         continue;
-                    }
+                    };
     
                     // Get or compute pool name
-                    let pool_name = get_pool_name(&pool_address);
+                    let pool_name = self.pools.pool_name(pool_address);
     
                     // Only add metrics if we have valid samples
                     let sample_count = samples_col.value(i);
                     if sample_count > 0 {
-                        pool_addresses.push(pool_address);
+                        let markout_time = markout_times_col.value(i).to_string();
+                        let pool_stats = OnlineStats::from_metrics(&DistributionMetrics {
+                            mean: means_col.value(i),
+                            variance: std_devs_col.value(i) * std_devs_col.value(i),
+                            std_dev: std_devs_col.value(i),
+                            skewness: skewness_col.value(i),
+                            kurtosis: kurtosis_col.value(i),
+                            sample_count,
+                        });
+                        markout_stats.entry(markout_time.clone()).or_default().push((pool_address.clone(), pool_stats));
+
+                        let digest = snapshots.as_ref().map(|snapshots| snapshots[i].digest.clone());
+                        // Read finalized, like the checkpoint's own quantiles
+                        push_digest_stats(digest.clone().map(|mut digest| {
+                            digest.finalize();
+                            digest
+                        }).as_ref());
+                        markout_digests.entry(markout_time.clone()).or_default().push((pool_address.clone(), digest));
+
+                        pool_addresses.push(pool_address.to_string());
                         pool_names.push(pool_name);
-                        markout_times.push(markout_times_col.value(i).to_string());
+                        markout_times.push(markout_time);
                         means.push(means_col.value(i));
                         std_devs.push(std_devs_col.value(i));
                         skewness_values.push(skewness_col.value(i));
@@ -1433,6 +2266,36 @@ impl PrecomputedWriter {
             warn!("No distribution metrics found in checkpoint files");
             return Ok(());
         }
+
+        // One row per markout combining every pool's moments. Pools are
+        // combined in address order so the result does not depend on listing.
+        for (markout_time, mut stats) in markout_stats {
+            stats.sort_by(|a, b| a.0.cmp(&b.0));
+            let Some(combined) = stats.iter()
+                .map(|(_, pool_stats)| pool_stats.clone())
+                .reduce(|a, b| OnlineStats::combine(&a, &b)) else {
+                continue;
+            };
+            let metrics = combined.to_metrics();
+
+            // Merged like the aggregate quartiles, and unknown if any pool
+            // has no digest
+            let mut digests = markout_digests.remove(&markout_time).unwrap_or_default();
+            digests.sort_by(|a, b| a.0.cmp(&b.0));
+            let merged = digests.into_iter().try_fold(TDigest::new(), |mut merged, (_, digest)| {
+                merged.merge(&digest?);
+                Some(merged)
+            });
+
+            pool_addresses.push(AGGREGATE_POOL_ADDRESS.to_string());
+            pool_names.push("All pools".to_string());
+            markout_times.push(markout_time);
+            means.push(metrics.mean);
+            std_devs.push(metrics.std_dev);
+            skewness_values.push(metrics.skewness);
+            kurtosis_values.push(metrics.kurtosis);
+            push_digest_stats(merged.as_ref());
+        }
     
         // Create record batch
         let batch = RecordBatch::try_new(
@@ -1445,21 +2308,132 @@ impl PrecomputedWriter {
                 Arc::new(Float64Array::from(std_devs)),
                 Arc::new(Float64Array::from(skewness_values)),
                 Arc::new(Float64Array::from(kurtosis_values)),
+                Arc::new(Float64Array::from(fractions_above)),
+                Arc::new(UInt64Array::from(trimmed_means)),
+                Arc::new(UInt64Array::from(iqrs)),
             ],
         )?;
     
         // Write to output file
-        let output_path = Path::from("precomputed/distributions/metrics.parquet");
-        self.write_batch_to_store(output_path, batch).await?;
+        self.write_batch_to_store(DatasetKind::DistributionMetrics, batch).await?;
     
         info!(
-            "Successfully wrote precomputed distribution metrics for {} pool-markout combinations",
+            "Successfully wrote precomputed distribution metrics for {} pool-markout combinations (including aggregates)",
             pool_addresses.len()
         );
     
         Ok(())
     }
 
+    pub async fn write_rolling_series(&self, window: usize) -> Result<(), anyhow::Error> {
+        if window == 0 {
+            return Err(anyhow::anyhow!("Rolling window must cover at least one interval"));
+        }
+        info!("Starting precomputation of {}-interval rolling LVR series", window);
+
+        let schema = arrow::datatypes::Schema::new(vec![
+            arrow::datatypes::Field::new("end_block", arrow::datatypes::DataType::UInt64, false),
+            arrow::datatypes::Field::new("pool_address", arrow::datatypes::DataType::Utf8, true),
+            arrow::datatypes::Field::new("markout_time", arrow::datatypes::DataType::Utf8, false),
+            arrow::datatypes::Field::new("window_intervals", arrow::datatypes::DataType::UInt64, false),
+            arrow::datatypes::Field::new("rolling_total_cents", arrow::datatypes::DataType::Int64, false),
+        ]);
+
+
+        // End blocks of every observed interval, and per-interval LVR keyed
+        // by (markout_time, pool_address)
+        let mut timeline: std::collections::BTreeSet<u64> = std::collections::BTreeSet::new();
+        let mut daily_totals: BTreeMap<(String, Address), BTreeMap<u64, i64>> = BTreeMap::new();
+
+        for (location, file_start, file_end) in self.interval_files().await? {
+
+            let bytes = self.object_store.get(&location).await?.bytes().await?;
+            let record_reader = parquet_reader(bytes, &location, 1024)?;
+
+            for batch_result in record_reader {
+                let batch = normalize_interval_batch(batch_result?)?;
+                let block_ranges = interval_block_ranges(&batch, file_start, file_end)
+                    .map_err(|e| anyhow::anyhow!("Failed to read interval block ranges: {}", e))?;
+
+                let markout_times_col = get_string_column(&batch, INTERVAL_MARKOUT_TIME_COLUMN)
+                    .map_err(|e| anyhow::anyhow!("Failed to get markout_time column: {}", e))?;
+                let pool_addresses_col = get_string_column(&batch, INTERVAL_PAIR_ADDRESS_COLUMN)
+                    .map_err(|e| anyhow::anyhow!("Failed to get pair_address column: {}", e))?;
+                let total_lvr_cents = get_int64_column(&batch, INTERVAL_TOTAL_LVR_COLUMN)
+                    .map_err(|e| anyhow::anyhow!("Failed to get total_lvr_cents column: {}", e))?;
+                let total_counts = get_uint64_column(&batch, INTERVAL_TOTAL_COUNT_COLUMN)
+                    .map_err(|e| anyhow::anyhow!("Failed to get total_count column: {}", e))?;
+
+                for (i, &(_, end_block)) in block_ranges.iter().enumerate() {
+                    let Some(pool_address) = self.pools.address(pool_addresses_col.value(i)) else {
+                        continue;
+                    };
+                    if total_counts.value(i) == 0 {
+                        continue;
+                    }
+
+                    timeline.insert(end_block);
+
+                    let lvr_cents = total_lvr_cents.value(i);
+                    if lvr_cents != 0 {
+                        let total = daily_totals
+                            .entry((markout_times_col.value(i).to_string(), pool_address.clone()))
+                            .or_default()
+                            .entry(end_block)
+                            .or_default();
+                        *total = total.saturating_add(lvr_cents);
+                    }
+                }
+            }
+        }
+
+        let timeline: Vec<u64> = timeline.into_iter().collect();
+
+        let mut aggregate_totals: BTreeMap<String, BTreeMap<u64, i64>> = BTreeMap::new();
+        for ((markout_time, _), totals) in &daily_totals {
+            let aggregate = aggregate_totals.entry(markout_time.clone()).or_default();
+            for (&end_block, &cents) in totals {
+                *aggregate.entry(end_block).or_default() += cents;
+            }
+        }
+
+        let mut end_blocks = Vec::new();
+        let mut pool_addresses: Vec<Option<String>> = Vec::new();
+        let mut markout_times = Vec::new();
+        let mut window_intervals = Vec::new();
+        let mut rolling_totals = Vec::new();
+
+        // Aggregate series (null pool) first, then each pool, per markout
+        let series = aggregate_totals.iter()
+            .map(|(markout_time, totals)| (markout_time, None, totals))
+            .chain(daily_totals.iter().map(|((markout_time, pool), totals)| (markout_time, Some(pool), totals)));
+        for (markout_time, pool_address, totals) in series {
+            for (end_block, intervals, total) in rolling_sums(&timeline, totals, window) {
+                end_blocks.push(end_block);
+                pool_addresses.push(pool_address.map(ToString::to_string));
+                markout_times.push(markout_time.clone());
+                window_intervals.push(intervals);
+                rolling_totals.push(total);
+            }
+        }
+
+        let batch = RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(UInt64Array::from(end_blocks)),
+                Arc::new(StringArray::from(pool_addresses)),
+                Arc::new(StringArray::from(markout_times)),
+                Arc::new(UInt64Array::from(window_intervals)),
+                Arc::new(Int64Array::from(rolling_totals)),
+            ],
+        )?;
+
+        self.write_batch_to_store(DatasetKind::RollingSeries { window }, batch).await?;
+
+        info!("Successfully wrote {}-interval rolling LVR series", window);
+        Ok(())
+    }
+
     pub async fn write_daily_time_series(&self) -> Result<(), anyhow::Error> {
         info!("Starting aggregation of total LVR across pools for daily time series");
     
@@ -1477,58 +2451,36 @@ impl PrecomputedWriter {
         let mut end_blocks = Vec::new();
         let mut total_lvr_values = Vec::new();
     
-        let valid_pools = get_valid_pools();
     
         // Process each interval file (monthly file).
-        let intervals_path = object_store::path::Path::from("intervals");
-        let mut interval_files = self.object_store.list(Some(&intervals_path));
-    
-        while let Some(meta_result) = interval_files.next().await {
-            let meta = meta_result.context("Failed to get file metadata")?;
-            let file_path = meta.location.to_string();
-    
-            // Extract the overall block range from the file name.
-            let (file_start, _file_end) = if let Some(file_name) = file_path.split('/').last() {
-                let parts: Vec<&str> = file_name.split('_').collect();
-                if parts.len() == 2 {
-                    let start = parts[0]
-                        .parse::<u64>()
-                        .context("Failed to parse start block")?;
-                    let end = parts[1]
-                        .trim_end_matches(".parquet")
-                        .parse::<u64>()
-                        .context("Failed to parse end block")?;
-                    (start, end)
-                } else {
-                    continue;
-                }
-            } else {
-                continue;
-            };
     
-            let bytes = self.object_store.get(&meta.location).await?.bytes().await?;
-            let record_reader = ParquetRecordBatchReader::try_new(bytes, 1024)?;
+        for (location, file_start, file_end) in self.interval_files().await? {
     
-            // Use a HashMap to aggregate LVR per (interval_id, markout_time) combination.
-            let mut aggregation: std::collections::HashMap<(u64, String), u64> = std::collections::HashMap::new();
+            let bytes = self.object_store.get(&location).await?.bytes().await?;
+            let record_reader = parquet_reader(bytes, &location, 1024)?;
+    
+            // Use a HashMap to aggregate LVR and the blocks covered per
+            // (interval_id, markout_time) combination.
+            let mut aggregation: std::collections::HashMap<(u64, String), (i64, u64, u64)> = std::collections::HashMap::new();
     
             for batch_result in record_reader {
-                let batch = batch_result?;
+                let batch = normalize_interval_batch(batch_result?)?;
+                let block_ranges = interval_block_ranges(&batch, file_start, file_end)
+                    .map_err(|e| anyhow::anyhow!("Failed to read interval block ranges: {}", e))?;
     
-                let markout_times_col = get_string_column(&batch, "markout_time")
+                let markout_times_col = get_string_column(&batch, INTERVAL_MARKOUT_TIME_COLUMN)
                     .map_err(|e| anyhow::anyhow!("Failed to get markout_time column: {}", e))?;
-                let pool_addresses_col = get_string_column(&batch, "pair_address")
+                let pool_addresses_col = get_string_column(&batch, INTERVAL_PAIR_ADDRESS_COLUMN)
                     .map_err(|e| anyhow::anyhow!("Failed to get pair_address column: {}", e))?;
-                let total_lvr_cents = get_uint64_column(&batch, "total_lvr_cents")
+                let total_lvr_cents = get_int64_column(&batch, INTERVAL_TOTAL_LVR_COLUMN)
                     .map_err(|e| anyhow::anyhow!("Failed to get total_lvr_cents column: {}", e))?;
-                let total_counts = get_uint64_column(&batch, "total_count")
+                let total_counts = get_uint64_column(&batch, INTERVAL_TOTAL_COUNT_COLUMN)
                     .map_err(|e| anyhow::anyhow!("Failed to get total_count column: {}", e))?;
-                let interval_ids_col = get_uint64_column(&batch, "interval_id")
+                let interval_ids_col = get_uint64_column(&batch, INTERVAL_ID_COLUMN)
                     .map_err(|e| anyhow::anyhow!("Failed to get interval_id column: {}", e))?;
     
-                for i in 0..batch.num_rows() {
-                    let pool_address = pool_addresses_col.value(i).to_lowercase();
-                    if !valid_pools.contains(&pool_address) {
+                for (i, &(start_block, end_block)) in block_ranges.iter().enumerate() {
+                    if self.pools.get(pool_addresses_col.value(i)).is_none() {
                         continue;
                     }
     
@@ -1537,19 +2489,21 @@ impl PrecomputedWriter {
                     let lvr_cents = total_lvr_cents.value(i);
                     let total_count = total_counts.value(i);
     
-                    // Only include valid rows.
-                    if lvr_cents > 0 && total_count > 0 {
-                        *aggregation.entry((interval_id, markout_time)).or_insert(0) += lvr_cents;
+                    // Only include valid rows; negative intervals lower the day's total
+                    if lvr_cents != 0 && total_count > 0 {
+                        let day = aggregation
+                            .entry((interval_id, markout_time))
+                            .or_insert((0, start_block, end_block));
+                        day.0 += lvr_cents;
+                        day.1 = day.1.min(start_block);
+                        day.2 = day.2.max(end_block);
                     }
                 }
             }
     
-            // For each (interval_id, markout_time) combination, compute the daily block range.
-            // Here we assume each interval covers 7200 blocks.
-            let interval_length = 7200;
-            for ((interval_id, markout_time), lvr_sum_cents) in aggregation {
-                let day_start = file_start + (interval_id * interval_length);
-                let day_end = day_start + interval_length - 1;
+            // Each day's block range is inclusive of its last block
+            for ((_, markout_time), (lvr_sum_cents, day_start, day_end)) in aggregation {
+                let day_end = day_end - 1;
     
                 markout_times.push(markout_time);
                 start_blocks.push(day_start);
@@ -1570,8 +2524,7 @@ impl PrecomputedWriter {
         )?;
     
         // Write the batch to the output file.
-        let output_path = Path::from("precomputed/distributions/daily_ts.parquet");
-        self.write_batch_to_store(output_path, batch).await?;
+        self.write_batch_to_store(DatasetKind::DailyTimeSeries, batch).await?;
     
         info!("Successfully wrote daily total LVR time series");
         Ok(())
@@ -1,13 +1,20 @@
 use std::sync::Arc;
 use object_store::ObjectStore;
+use crate::PoolRegistry;
 
 #[derive(Clone)]
 pub struct AppState {
     pub store: Arc<dyn ObjectStore>,
+    pub pools: Arc<PoolRegistry>,
 }
 
 impl AppState {
     pub fn new(store: Arc<dyn ObjectStore>) -> Self {
-        Self { store }
+        Self { store, pools: Arc::new(PoolRegistry::default()) }
+    }
+
+    pub fn with_pool_registry(mut self, pools: Arc<PoolRegistry>) -> Self {
+        self.pools = pools;
+        self
     }
 }
\ No newline at end of file
@@ -1,10 +1,17 @@
+//! Query and response types of the API. LVR amounts carry their unit in
+//! the field name: `_cents` fields are integer cents and `_dollars` fields
+//! are floating-point dollars. A field whose JSON key predates that rule
+//! keeps its key through `#[serde(rename)]`; its doc comment gives the
+//! unit. `testdata/api/responses.json` pins the JSON of every response.
+
+use crate::models::{Address, Cents, MarkoutTime};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-#[derive(Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct HealthResponse {
-    pub status: &'static str,
-    pub version: &'static str,
+    pub status: String,
+    pub version: String,
     pub timestamp: String,
 }
 
@@ -12,34 +19,34 @@ pub struct HealthResponse {
 pub struct TimeRangeQuery {
     pub start_block: Option<u64>,
     pub end_block: Option<u64>,
-    pub markout_time: Option<String>,
+    pub markout_time: Option<MarkoutTime>,
     pub aggregate: Option<bool>,
-    pub pool: Option<String>,
+    pub pool: Option<Address>,
 }
 
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct RunningTotal {
     pub block_number: u64,
     pub markout: String,
     pub pool_name: Option<String>, 
     pub pool_address: Option<String>,
-    pub running_total_cents: u64,
+    pub running_total_cents: Cents,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct IntervalAPIData {
     pub total: u64,
     pub file_path: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct LVRRatioResponse {
     /// Vector of ratios for each markout time
     pub ratios: Vec<MarkoutRatio>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct MarkoutRatio {
     pub markout_time: String,
     pub ratio: f64,
@@ -51,38 +58,177 @@ pub struct MarkoutRatio {
 pub struct LVRRatioQuery {
     pub start_block: Option<u64>,
     pub end_block: Option<u64>,
-    pub pool_address: Option<String>,
+    pub pool_address: Option<Address>,
 }
 
 #[derive(Debug)]
 pub struct LVRTotals {
-    pub realized: u64,
-    pub theoretical: HashMap<String, u64>,
+    pub realized_cents: u64,
+    /// By markout time
+    pub theoretical_cents: HashMap<String, u64>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct PoolTotalsQuery {
-    pub markout_time: Option<String>,
+    pub markout_time: Option<MarkoutTime>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct PoolTotal {
     pub pool_name: String,
     pub pool_address: String,
-    pub total_lvr_cents: u64,
+    pub total_lvr_cents: Cents,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct PoolTotalsResponse {
     pub totals: Vec<PoolTotal>,
 }
 
 #[derive(Debug, Deserialize)]
-pub struct MaxLVRQuery {
+pub struct MonthlyPoolTotalsQuery {
+    pub pool_address: Address,
+    pub markout_time: Option<MarkoutTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MonthlyPoolTotal {
+    pub time_range: String,
+    pub total_lvr_cents: Cents,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MonthlyPoolTotalsResponse {
+    pub pool_name: String,
+    pub pool_address: String,
+    pub markout_time: String,
+    pub monthly_totals: Vec<MonthlyPoolTotal>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DatasetSchemaInfo {
+    pub dataset: String,
+    pub path: String,
+    pub expected_schema_version: String,
+    pub present: bool,
+    /// `lvr.*` footer entries, empty for missing or pre-versioning files
+    pub metadata: HashMap<String, String>,
+    pub needs_regeneration: bool,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SchemaResponse {
+    pub datasets: Vec<DatasetSchemaInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RollingSeriesQuery {
+    pub window: Option<usize>,
+    pub markout_time: Option<MarkoutTime>,
+    /// Omitted for the aggregate series across all pools
+    pub pool_address: Option<Address>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RollingPoint {
+    pub end_block: u64,
+    pub window_intervals: u64,
+    pub rolling_total_cents: Cents,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RollingSeriesResponse {
+    pub window: usize,
+    pub markout_time: String,
+    pub pool_address: Option<String>,
+    pub points: Vec<RollingPoint>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConcentrationQuery {
+    pub markout_time: Option<MarkoutTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConcentrationResponse {
+    pub markout_time: String,
+    pub pool_count: u64,
+    pub hhi: f64,
+    pub top_1_share: f64,
+    pub top_3_share: f64,
+    pub top_5_share: f64,
+    pub effective_pools: f64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ActivityRunsQuery {
+    pub markout_time: Option<MarkoutTime>,
+    /// Omitted to return every pool
+    pub pool_address: Option<Address>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PoolActivityRuns {
+    pub pool_name: String,
+    pub pool_address: String,
+    pub first_active_block: u64,
+    pub dry_spells: u64,
+    pub longest_dry_spell: u64,
+    pub mean_dry_spell: f64,
+    pub longest_active_streak: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ActivityRunsResponse {
+    pub markout_time: String,
+    pub pools: Vec<PoolActivityRuns>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CorrelationsQuery {
+    pub markout_time: Option<MarkoutTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PoolCorrelation {
+    pub pool_a: String,
+    pub pool_a_name: String,
+    pub pool_b: String,
+    pub pool_b_name: String,
+    pub correlation: f64,
+    /// Intervals after both pools were deployed
+    pub n_intervals: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CorrelationsResponse {
     pub markout_time: String,
+    pub correlations: Vec<PoolCorrelation>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PoolInfo {
+    pub pool_address: String,
+    pub pool_name: String,
+    pub cluster: Option<String>,
+    pub deployment_block: u64,
+    pub brontes_tracked: bool,
+    pub token0_decimals: u8,
+    pub token1_decimals: u8,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PoolsResponse {
+    pub pools: Vec<PoolInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MaxLVRQuery {
+    pub markout_time: MarkoutTime,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct MaxLVRPoolData {
     pub pool_name: String,
     pub pool_address: String,
@@ -90,58 +236,86 @@ pub struct MaxLVRPoolData {
     pub lvr_cents: u64,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct MaxLVRResponse {
     pub pools: Vec<MaxLVRPoolData>,
 }
 
 
+/// Detail `/histogram` can add to the fixed dollar buckets
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HistogramDetail {
+    /// Equal-probability bins from the pool's digest
+    Equidepth,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct HistogramQuery {
-    pub pool_address: String,
-    pub markout_time: String,
+    pub pool_address: Address,
+    pub markout_time: MarkoutTime,
+    pub detail: Option<HistogramDetail>,
 }
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct HistogramBucket {
-    pub range_start: f64,
-    pub range_end: Option<f64>,
+    /// In dollars; `None` for the open-ended top bucket
+    #[serde(rename = "range_start")]
+    pub range_start_dollars: f64,
+    #[serde(rename = "range_end")]
+    pub range_end_dollars: Option<f64>,
     pub count: u64,
     pub label: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct HistogramResponse {
     pub pool_name: String,
     pub pool_address: String,
     pub buckets: Vec<HistogramBucket>,
     pub total_observations: u64,
+    /// Only with `detail=equidepth`; empty for a pool without a stored digest
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub equidepth_bins: Option<Vec<EquiDepthBin>>,
+}
+
+/// One of `EQUIDEPTH_BINS` intervals holding an equal share of the non-zero
+/// blocks, edges in dollars
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EquiDepthBin {
+    #[serde(rename = "range_start")]
+    pub range_start_dollars: f64,
+    #[serde(rename = "range_end")]
+    pub range_end_dollars: f64,
+    pub mass: f64,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct NonZeroProportionQuery {
-    pub pool_address: String,
-    pub markout_time: String,
+    pub pool_address: Address,
+    pub markout_time: MarkoutTime,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct NonZeroProportionResponse {
     pub pool_name: String,
     pub pool_address: String,
     pub non_zero_proportion: f64,
+    /// Blocks from the pool's deployment on
     pub total_blocks: u64,
     pub non_zero_blocks: u64,
+    pub deployment_block: u64,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct PercentileBandQuery {
     pub start_block: Option<u64>,
     pub end_block: Option<u64>,
-    pub pool_address: Option<String>,
-    pub markout_time: Option<String>,
+    pub pool_address: Option<Address>,
+    pub markout_time: Option<MarkoutTime>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct PercentileDataPoint {
     pub start_block: u64,
     pub end_block: u64,
@@ -151,7 +325,7 @@ pub struct PercentileDataPoint {
     pub percentile_75_dollars: f64
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct PercentileBandResponse {
     pub pool_name: String,
     pub pool_address: String,
@@ -162,67 +336,71 @@ pub struct PercentileBandResponse {
 
 #[derive(Debug)]
 pub struct AggregatedStats {
-    pub percentile_25: u64,
-    pub median: u64,
-    pub percentile_75: u64,
+    pub percentile_25_cents: u64,
+    pub median_cents: u64,
+    pub percentile_75_cents: u64,
     pub count: u64,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ClusterQuery {
-    pub markout_time: Option<String>,
+    pub markout_time: Option<MarkoutTime>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ClusterTotal {
     pub name: String,
-    pub total_lvr_cents: u64,
+    pub total_lvr_cents: Cents,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ClusterPieResponse {
     pub clusters: Vec<ClusterTotal>,
-    pub total_lvr_cents: u64,
+    pub total_lvr_cents: Cents,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ClusterHistogramQuery {
-    pub markout_time: Option<String>,
+    pub markout_time: Option<MarkoutTime>,
 }
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ClusterHistogramBucket {
-    pub range_start: f64,
-    pub range_end: Option<f64>,
+    /// In dollars; `None` for the open-ended top bucket
+    #[serde(rename = "range_start")]
+    pub range_start_dollars: f64,
+    #[serde(rename = "range_end")]
+    pub range_end_dollars: Option<f64>,
     pub count: u64,
     pub label: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ClusterHistogramData {
     pub name: String,
     pub buckets: Vec<ClusterHistogramBucket>,
     pub total_observations: u64,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ClusterHistogramResponse {
     pub clusters: Vec<ClusterHistogramData>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct MonthlyClusterQuery {
-    pub markout_time: Option<String>,
+    pub markout_time: Option<MarkoutTime>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct MonthlyData {
     pub time_range: String,
-    pub cluster_totals: HashMap<String, u64>,
-    pub total_lvr_cents: u64,
+    #[serde(rename = "cluster_totals")]
+    pub cluster_totals_cents: HashMap<String, Cents>,
+    pub total_lvr_cents: Cents,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ClusterMonthlyResponse {
     pub monthly_data: Vec<MonthlyData>,
     pub clusters: Vec<String>,
@@ -230,10 +408,10 @@ pub struct ClusterMonthlyResponse {
 
 #[derive(Debug, Deserialize)]
 pub struct ClusterNonZeroQuery {
-    pub markout_time: Option<String>,
+    pub markout_time: Option<MarkoutTime>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ClusterNonZero {
     pub name: String,
     pub total_observations: u64,
@@ -241,18 +419,21 @@ pub struct ClusterNonZero {
     pub non_zero_proportion: f64,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ClusterNonZeroResponse {
     pub clusters: Vec<ClusterNonZero>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct QuartilePlotQuery {
-    pub pool_address: String,
-    pub markout_time: Option<String>,
+    /// Required unless `aggregate` is set
+    pub pool_address: Option<Address>,
+    pub markout_time: Option<MarkoutTime>,
+    /// Return the quartiles across all pools instead of a single pool
+    pub aggregate: Option<bool>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct QuartilePlotResponse {
     pub markout_time: String,
     pub pool_name: String,
@@ -264,28 +445,64 @@ pub struct QuartilePlotResponse {
 
 #[derive(Debug, Deserialize)]
 pub struct DistributionQuery {
-    pub pool_address: String,
-    pub markout_time: String,
+    /// Required unless `aggregate` is set
+    pub pool_address: Option<Address>,
+    pub markout_time: MarkoutTime,
+    /// Return the distribution across all pools instead of a single pool
+    pub aggregate: Option<bool>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct DistributionResponse {
     pub pool_name: String,
     pub pool_address: String,
     pub markout_time: String,
-    pub mean: f64,
-    pub std_dev: f64,
+    /// Moments of the non-zero blocks, mean and standard deviation in dollars
+    #[serde(rename = "mean")]
+    pub mean_dollars: f64,
+    #[serde(rename = "std_dev")]
+    pub std_dev_dollars: f64,
     pub skewness: f64,
-    pub kurtosis: f64
+    pub kurtosis: f64,
+    /// Fraction of non-zero blocks with LVR above $1,000; null when the
+    /// checkpoints predate stored digests
+    pub fraction_above_1000_dollars: Option<f64>,
+    /// 5%-trimmed mean and interquartile range of the non-zero blocks, also
+    /// null without stored digests
+    pub trimmed_mean_cents: Option<u64>,
+    pub iqr_cents: Option<u64>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct TotalLVRResponse {
     pub markout_totals: Vec<MarkoutTotal>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct MarkoutTotal {
     pub markout_time: String,
     pub total_dollars: f64,
-}
\ No newline at end of file
+}
+#[derive(Debug, Deserialize)]
+pub struct RawSeriesQuery {
+    pub pool_address: Address,
+    pub start_block: Option<u64>,
+    pub end_block: Option<u64>,
+    /// All markout times when not given
+    pub markout_time: Option<MarkoutTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RawSeriesResponse {
+    pub pool_name: String,
+    pub pool_address: String,
+    /// Blocks without a point had zero LVR
+    pub points: Vec<RawLvrPoint>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RawLvrPoint {
+    pub block_number: u64,
+    pub markout_time: String,
+    pub lvr_cents: Cents,
+}
@@ -0,0 +1,53 @@
+use crate::{tdigest::TDigestConfig, AuroraQueryLimits, BrontesQueryLimits, Error, OutputConfig, ParquetWriteOptions, RetryConfig, ValidationPolicy};
+use anyhow::Result;
+use serde::Deserialize;
+use std::path::Path;
+
+/// Config file read when `--config` is not given; a missing default file is not an error
+pub const DEFAULT_CONFIG_PATH: &str = "lvr.toml";
+
+/// Settings loaded from the TOML config file. Database credentials stay in
+/// the environment (see `DatabaseConfig`); everything here has a default.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    pub parquet: ParquetWriteOptions,
+    pub output: OutputConfig,
+    pub retry: RetryConfig,
+    pub tdigest: TDigestConfig,
+    pub validation: ValidationPolicy,
+    pub aurora: AuroraQueryLimits,
+    pub brontes: BrontesQueryLimits,
+}
+
+impl AppConfig {
+    pub fn from_toml(contents: &str) -> Result<Self> {
+        let config: Self = toml::from_str(contents)
+            .map_err(|e| Error::Config(format!("Invalid config: {}", e)))?;
+        config.aurora.validate()?;
+        config.brontes.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| Error::Config(format!("Failed to read config {:?}: {}", path, e)))?;
+        Self::from_toml(&contents)
+    }
+
+    /// Loads `path` if given, otherwise `DEFAULT_CONFIG_PATH` when it exists,
+    /// otherwise the defaults
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        match path {
+            Some(path) => Self::from_file(path),
+            None => {
+                let default_path = Path::new(DEFAULT_CONFIG_PATH);
+                if default_path.exists() {
+                    Self::from_file(default_path)
+                } else {
+                    Ok(Self::default())
+                }
+            }
+        }
+    }
+}
@@ -1,18 +1,142 @@
+use crate::failover::Endpoint;
 use crate::Error;
 use anyhow::Result;
 use serde::Deserialize;
 use std::env;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Must be `true` for an `AuroraConfig` with `ssl_verify` off to connect
+pub const ALLOW_INSECURE_SSL_ENV_VAR: &str = "AURORA_ALLOW_INSECURE_SSL";
+
+/// `[aurora]` in the config file: how hard the processor may query the read
+/// replica. Applied by `AuroraConnection`, so every caller shares them.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct AuroraQueryLimits {
+    /// Blocks fetched per query
+    pub batch_size: u64,
+    /// Queries running at once across every markout and chunk
+    pub max_concurrent_queries: usize,
+    /// Seconds one batch query may take before it fails and is retried
+    pub query_timeout_secs: u64,
+}
+
+impl Default for AuroraQueryLimits {
+    fn default() -> Self {
+        // One query per theoretical markout of one chunk
+        Self { batch_size: 7200, max_concurrent_queries: 9, query_timeout_secs: 600 }
+    }
+}
+
+impl AuroraQueryLimits {
+    pub fn validate(&self) -> Result<()> {
+        if self.batch_size == 0 || self.max_concurrent_queries == 0 || self.query_timeout_secs == 0 {
+            return Err(Error::Config(
+                "aurora batch_size, max_concurrent_queries and query_timeout_secs must be positive".to_string()
+            ).into());
+        }
+        Ok(())
+    }
+
+    pub fn query_timeout(&self) -> Duration {
+        Duration::from_secs(self.query_timeout_secs)
+    }
+}
+
+/// `[brontes]` in the config file, applied by `BrontesConnection`
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct BrontesQueryLimits {
+    /// Seconds one batch query may take before it fails and is retried
+    pub query_timeout_secs: u64,
+}
+
+impl Default for BrontesQueryLimits {
+    fn default() -> Self {
+        Self { query_timeout_secs: 300 }
+    }
+}
+
+impl BrontesQueryLimits {
+    pub fn validate(&self) -> Result<()> {
+        if self.query_timeout_secs == 0 {
+            return Err(Error::Config("brontes query_timeout_secs must be positive".to_string()).into());
+        }
+        Ok(())
+    }
+
+    pub fn query_timeout(&self) -> Duration {
+        Duration::from_secs(self.query_timeout_secs)
+    }
+}
+
+/// Where a database's user and password come from
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum CredentialSource {
+    /// The `user` and `password` of the config
+    #[default]
+    Env,
+    /// An AWS Secrets Manager secret holding `username` and `password`
+    SecretsManager { secret_arn: String },
+}
+
+impl CredentialSource {
+    /// `{prefix}_CREDENTIALS` is `env`, the default, or `secrets_manager`,
+    /// which reads the secret named by `{prefix}_SECRET_ARN`
+    pub fn from_env(prefix: &str) -> Result<Self> {
+        let source = env::var(format!("{}_CREDENTIALS", prefix)).unwrap_or_default();
+        match source.trim() {
+            "" | "env" => Ok(CredentialSource::Env),
+            "secrets_manager" => {
+                let secret_arn = env::var(format!("{}_SECRET_ARN", prefix)).map_err(|_| {
+                    Error::Config(format!("{}_SECRET_ARN is required with secrets_manager credentials", prefix))
+                })?;
+                Ok(CredentialSource::SecretsManager { secret_arn })
+            }
+            other => Err(Error::Config(format!("Unknown {}_CREDENTIALS source {:?}", prefix, other)).into()),
+        }
+    }
+}
+
+fn default_host_cooldown() -> u64 {
+    crate::failover::ENDPOINT_COOLDOWN.as_secs()
+}
+
+fn default_ssl_verify() -> bool {
+    true
+}
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct AuroraConfig {
+    /// Endpoints to fail over between, as `host` or `host:port`, in the
+    /// order to try them. Empty means the environment's host alone.
+    #[serde(default)]
+    pub hosts: Vec<String>,
     pub gcp_host: String,
     pub public_host: String,
     pub port: u16,
     pub user: String,
     pub password: String,
+    #[serde(default)]
+    pub credentials: CredentialSource,
     pub database: String,
     pub connection_timeout: u64,
     pub retry_interval: u64,
+    /// Seconds a host that failed is skipped before it is tried again
+    #[serde(default = "default_host_cooldown")]
+    pub host_cooldown: u64,
+    /// Verify the server certificate and host name. Turning it off also
+    /// takes `AURORA_ALLOW_INSECURE_SSL=true`.
+    #[serde(default = "default_ssl_verify")]
+    pub ssl_verify: bool,
+    /// PEM or DER root certificates trusted besides the system ones, such
+    /// as the RDS CA bundle
+    #[serde(default)]
+    pub ssl_ca_path: Option<PathBuf>,
+    #[serde(default)]
+    pub limits: AuroraQueryLimits,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -21,6 +145,8 @@ pub struct BrontesConfig {
     pub port: u16,
     pub user: String,
     pub password: String,
+    #[serde(default)]
+    pub credentials: CredentialSource,
     pub connection_timeout: u64,
     pub retry_interval: u64,
 }
@@ -38,6 +164,9 @@ pub struct DatabaseConnectionConfig {
 impl AuroraConfig {
     pub fn from_env() -> Result<Self> {
         Ok(Self {
+            hosts: env::var("AURORA_HOSTS")
+                .map(|hosts| hosts.split(',').map(str::trim).filter(|host| !host.is_empty()).map(String::from).collect())
+                .unwrap_or_default(),
             gcp_host: env::var("AURORA_GCP_HOST").unwrap_or_else(|_| "dummy_gcp_host".to_string()),
             public_host: env::var("AURORA_PUBLIC_HOST").unwrap_or_else(|_| "dummy_public_host".to_string()),
             port: env::var("AURORA_PORT")
@@ -46,6 +175,7 @@ impl AuroraConfig {
                 .map_err(|_| Error::Config("Invalid AURORA_PORT format".to_string()))?,
             user: env::var("AURORA_USER").unwrap_or_else(|_| "dummy_user".to_string()),
             password: env::var("AURORA_PASSWORD").unwrap_or_else(|_| "dummy_password".to_string()),
+            credentials: CredentialSource::from_env("AURORA")?,
             database: env::var("AURORA_DATABASE").unwrap_or_else(|_| "dummy_database".to_string()),
             connection_timeout: env::var("AURORA_TIMEOUT")
                 .unwrap_or_else(|_| "30".to_string())
@@ -55,6 +185,19 @@ impl AuroraConfig {
                 .unwrap_or_else(|_| "5".to_string())
                 .parse()
                 .unwrap_or(5),
+            host_cooldown: env::var("AURORA_HOST_COOLDOWN")
+                .ok()
+                .and_then(|cooldown| cooldown.parse().ok())
+                .unwrap_or_else(default_host_cooldown),
+            ssl_verify: match env::var("AURORA_SSL_VERIFY") {
+                Ok(verify) => verify
+                    .trim()
+                    .parse()
+                    .map_err(|_| Error::Config(format!("Invalid AURORA_SSL_VERIFY {:?}, expected true or false", verify)))?,
+                Err(_) => default_ssl_verify(),
+            },
+            ssl_ca_path: env::var("AURORA_SSL_CA_PATH").ok().filter(|path| !path.is_empty()).map(PathBuf::from),
+            limits: AuroraQueryLimits::default(),
         })
     }
     
@@ -66,6 +209,14 @@ impl AuroraConfig {
             self.gcp_host.clone()
         }
     }
+
+    /// `hosts` in order, or the environment's host when there are none
+    pub fn endpoints(&self) -> Result<Vec<Endpoint>> {
+        if self.hosts.is_empty() {
+            return Ok(vec![Endpoint { host: self.get_host_for_environment(), port: self.port }]);
+        }
+        self.hosts.iter().map(|host| Endpoint::parse(host, self.port)).collect()
+    }
 }
 
 impl BrontesConfig {
@@ -78,6 +229,7 @@ impl BrontesConfig {
                 .map_err(|_| Error::Config("Invalid BRONTES_PORT format".to_string()))?,
             user: env::var("BRONTES_USER").unwrap_or_else(|_| "dummy_user".to_string()),
             password: env::var("BRONTES_PASSWORD").unwrap_or_else(|_| "dummy_password".to_string()),
+            credentials: CredentialSource::from_env("BRONTES")?,
             connection_timeout: env::var("BRONTES_TIMEOUT")
                 .unwrap_or_else(|_| "30".to_string())
                 .parse()
@@ -1,2 +1,12 @@
+mod app;
 mod db;
+mod output;
+mod pools;
+mod retry;
+mod write_options;
+pub use app::*;
 pub use db::*;
+pub use output::*;
+pub use pools::*;
+pub use retry::*;
+pub use write_options::*;
@@ -0,0 +1,21 @@
+use serde::Deserialize;
+
+/// How interval files are named under `intervals/`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputLayout {
+    /// `intervals/<start>_<end>.parquet`
+    #[default]
+    Flat,
+    /// `intervals/year=<yyyy>/month=<mm>/<start>_<end>.parquet`, partitioned
+    /// by the month of the start block, for engines that prune hive-style
+    /// `key=value` directories
+    Hive,
+}
+
+/// The `[output]` section of the config
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct OutputConfig {
+    pub layout: OutputLayout,
+}
@@ -0,0 +1,224 @@
+use crate::{
+    Address, Error, ALTCOIN_WETH_POOLS, BRONTES_ADDRESSES, DAI_WETH_POOLS, PEPE_DEPLOYMENT_V2, PEPE_DEPLOYMENT_V3,
+    POOL_ADDRESSES, POOL_NAMES, STABLE_POOLS, TOKEN_DECIMALS, USDC_WBTC_POOLS, USDC_WETH_POOLS,
+    USDT_WETH_POOLS, USDeUSDT_DEPLOYMENT, WBTC_WETH_POOLS, WETH_USDT_100_DEPLOYMENT,
+};
+use anyhow::Result;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Pool file read when `--pools` is not given; without it the embedded
+/// registry is used
+pub const DEFAULT_POOLS_PATH: &str = "pools.toml";
+
+fn default_brontes_tracked() -> bool {
+    true
+}
+
+/// One `[[pools]]` entry of the pool file
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct PoolEntry {
+    /// Kept as written: Aurora rows and existing checkpoint files use this
+    /// casing. Lookups are case-insensitive.
+    pub address: String,
+    pub name: String,
+    #[serde(default)]
+    pub cluster: Option<String>,
+    /// First block with data; 0 for pools live at the merge
+    #[serde(default)]
+    pub deployment_block: u64,
+    #[serde(default = "default_brontes_tracked")]
+    pub brontes_tracked: bool,
+    pub token0_decimals: u8,
+    pub token1_decimals: u8,
+}
+
+#[derive(Debug, Deserialize)]
+struct PoolsFile {
+    pools: Vec<PoolEntry>,
+}
+
+/// The pools processed, precomputed and served, in file order
+#[derive(Debug, Clone)]
+pub struct PoolRegistry {
+    pools: Vec<PoolEntry>,
+    /// The parsed address of each pool
+    addresses: Vec<Address>,
+    /// Address -> index into `pools`
+    index: HashMap<Address, usize>,
+}
+
+impl PoolRegistry {
+    pub fn new(pools: Vec<PoolEntry>) -> Result<Self> {
+        if pools.is_empty() {
+            return Err(Error::Config("Pool registry has no pools".to_string()).into());
+        }
+
+        let mut addresses = Vec::with_capacity(pools.len());
+        let mut index = HashMap::with_capacity(pools.len());
+        for (i, pool) in pools.iter().enumerate() {
+            let address: Address = pool.address.parse()
+                .map_err(|e| Error::Config(format!("Invalid pool: {}", e)))?;
+            if pool.name.is_empty() {
+                return Err(Error::Config(format!("Pool {} has no name", pool.address)).into());
+            }
+            if index.insert(address.clone(), i).is_some() {
+                return Err(Error::Config(format!("Duplicate pool address {}", pool.address)).into());
+            }
+            addresses.push(address);
+        }
+
+        Ok(Self { pools, addresses, index })
+    }
+
+    pub fn from_toml(contents: &str) -> Result<Self> {
+        let file: PoolsFile = toml::from_str(contents)
+            .map_err(|e| Error::Config(format!("Invalid pool file: {}", e)))?;
+        Self::new(file.pools)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| Error::Config(format!("Failed to read pool file {:?}: {}", path, e)))?;
+        Self::from_toml(&contents)
+    }
+
+    /// Loads `path` if given, otherwise `DEFAULT_POOLS_PATH` when it exists,
+    /// otherwise the embedded registry
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        match path {
+            Some(path) => Self::from_file(path),
+            None => {
+                let default_path = Path::new(DEFAULT_POOLS_PATH);
+                if default_path.exists() {
+                    Self::from_file(default_path)
+                } else {
+                    Ok(Self::default())
+                }
+            }
+        }
+    }
+
+    pub fn pools(&self) -> &[PoolEntry] {
+        &self.pools
+    }
+
+    /// The parsed address of each pool, in file order
+    pub fn addresses(&self) -> &[Address] {
+        &self.addresses
+    }
+
+    /// The pool `pool_address` spells in any casing, or `None` for unknown
+    /// pools. No checksum is checked: stored and source addresses carry none.
+    pub fn get(&self, pool_address: &str) -> Option<&PoolEntry> {
+        self.position(pool_address).map(|i| &self.pools[i])
+    }
+
+    /// The registry's `Address` of the pool `pool_address` spells in any
+    /// casing, for keying what is read from sources and stored files
+    pub fn address(&self, pool_address: &str) -> Option<&Address> {
+        self.position(pool_address).map(|i| &self.addresses[i])
+    }
+
+    fn position(&self, pool_address: &str) -> Option<usize> {
+        if pool_address.bytes().any(|b| b.is_ascii_uppercase()) {
+            self.index.get(pool_address.to_ascii_lowercase().as_str()).copied()
+        } else {
+            self.index.get(pool_address).copied()
+        }
+    }
+
+    pub fn lookup(&self, address: &Address) -> Option<&PoolEntry> {
+        self.index.get(address).map(|&i| &self.pools[i])
+    }
+
+    pub fn contains(&self, address: &Address) -> bool {
+        self.index.contains_key(address)
+    }
+
+    /// The pool's display name, or the address itself for unknown pools
+    pub fn pool_name(&self, pool_address: impl AsRef<str>) -> String {
+        let pool_address = pool_address.as_ref();
+        self.get(pool_address)
+            .map(|pool| pool.name.clone())
+            .unwrap_or_else(|| pool_address.to_string())
+    }
+
+    pub fn cluster_name(&self, pool_address: impl AsRef<str>) -> Option<&str> {
+        self.get(pool_address.as_ref()).and_then(|pool| pool.cluster.as_deref())
+    }
+
+    pub fn deployment_block(&self, pool_address: impl AsRef<str>) -> u64 {
+        self.get(pool_address.as_ref()).map_or(0, |pool| pool.deployment_block)
+    }
+
+    /// A registry of just `pool_addresses`, in this registry's order, for
+    /// runs limited to a few pools. Fails on an address it does not hold.
+    pub fn subset(&self, pool_addresses: &[String]) -> Result<Self> {
+        let mut selected = HashSet::new();
+        for pool_address in pool_addresses {
+            let pool = self.get(pool_address)
+                .ok_or_else(|| Error::Config(format!("Pool {} is not in the pool registry", pool_address)))?;
+            selected.insert(pool.address.clone());
+        }
+        Self::new(self.pools.iter().filter(|pool| selected.contains(&pool.address)).cloned().collect())
+    }
+
+    /// Addresses of the pools Brontes reports on
+    pub fn brontes_addresses(&self) -> impl Iterator<Item = &Address> {
+        self.pools
+            .iter()
+            .zip(&self.addresses)
+            .filter(|(pool, _)| pool.brontes_tracked)
+            .map(|(_, address)| address)
+    }
+}
+
+/// The registry built from the pool constants, used when no pool file exists
+impl Default for PoolRegistry {
+    fn default() -> Self {
+        let clusters = [
+            (&*STABLE_POOLS, "Stable Pairs"),
+            (&*WBTC_WETH_POOLS, "WBTC-WETH"),
+            (&*USDC_WETH_POOLS, "USDC-WETH"),
+            (&*USDT_WETH_POOLS, "USDT-WETH"),
+            (&*DAI_WETH_POOLS, "DAI-WETH"),
+            (&*USDC_WBTC_POOLS, "USDC-WBTC"),
+            (&*ALTCOIN_WETH_POOLS, "Altcoin-WETH"),
+        ];
+        let deployments = [
+            ("0x11950d141ecb863f01007add7d1a342041227b58", *PEPE_DEPLOYMENT_V3),
+            ("0xa43fe16908251ee70ef74718545e4fe6c5ccec9f", *PEPE_DEPLOYMENT_V2),
+            ("0x435664008f38b0650fbc1c9fc971d0a3bc2f1e47", *USDeUSDT_DEPLOYMENT),
+            ("0xc7bbec68d12a0d1830360f8ec58fa599ba1b0e9b", *WETH_USDT_100_DEPLOYMENT),
+        ];
+
+        let pools = POOL_ADDRESSES
+            .iter()
+            .map(|&address| {
+                let lowercase = address.to_ascii_lowercase();
+                let name = POOL_NAMES[address];
+                let mut tokens = name.split('-').map(|symbol| TOKEN_DECIMALS[symbol]);
+
+                PoolEntry {
+                    address: address.to_string(),
+                    name: name.to_string(),
+                    cluster: clusters
+                        .iter()
+                        .find(|(pools, _)| pools.keys().any(|a| a.eq_ignore_ascii_case(&lowercase)))
+                        .map(|(_, cluster)| cluster.to_string()),
+                    deployment_block: deployments
+                        .iter()
+                        .find(|(a, _)| *a == lowercase)
+                        .map_or(0, |(_, block)| *block),
+                    brontes_tracked: BRONTES_ADDRESSES.contains(&lowercase.as_str()),
+                    token0_decimals: tokens.next().unwrap(),
+                    token1_decimals: tokens.next().unwrap(),
+                }
+            })
+            .collect();
+
+        Self::new(pools).expect("embedded pool registry is valid")
+    }
+}
@@ -0,0 +1,30 @@
+use crate::storage::RetryPolicy;
+use serde::Deserialize;
+use std::time::Duration;
+
+/// `[retry.chunk]`, `[retry.database]` and `[retry.write]` in the config
+/// file. A section that is present but incomplete fills its missing keys
+/// from `RetryPolicy::default()`, not from the defaults below.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct RetryConfig {
+    /// Whole-chunk fetch and processing in `ParallelLVRProcessor`
+    pub chunk: RetryPolicy,
+    /// Each batch query against Aurora and Brontes
+    pub database: RetryPolicy,
+    /// Each file the processor stores, precomputed outputs included. Puts
+    /// are retried on their own, so a failed one never refetches a chunk.
+    pub write: RetryPolicy,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            chunk: RetryPolicy::new(20)
+                .with_base_delay(Duration::from_secs(5))
+                .with_max_delay(Duration::from_secs(120)),
+            database: RetryPolicy::new(3).with_base_delay(Duration::from_secs(5)),
+            write: RetryPolicy::new(20),
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use parquet::{
+    basic::Compression,
+    schema::types::ColumnPath,
+    file::properties::{EnabledStatistics, WriterProperties, WriterPropertiesBuilder},
+    format::KeyValue,
+};
+use serde::{Deserialize, Deserializer};
+use std::str::FromStr;
+
+/// Footer metadata key recording the codec a file was written with
+pub const COMPRESSION_METADATA_KEY: &str = "lvr.compression";
+
+/// Columns single-pool reads filter on, written with bloom filters so row
+/// groups without the requested pool or markout are skipped unread.
+/// Interval files name the pool `pair_address`, precomputed files
+/// `pool_address`; columns a file lacks are ignored.
+pub const BLOOM_FILTER_COLUMNS: [&str; 3] = ["pair_address", "pool_address", "markout_time"];
+
+/// Distinct values each bloom filter is sized for. A row group holds at most
+/// every registered pool, far fewer than parquet's default of a million,
+/// which would make each filter about a megabyte.
+pub const BLOOM_FILTER_NDV: u64 = 1024;
+
+/// Parquet writer settings shared by the interval/checkpoint writer and the
+/// precomputed outputs. Defaults reproduce the historical SNAPPY output.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ParquetWriteOptions {
+    /// Codec in parquet's `name` or `name(level)` form, e.g. `"snappy"` or `"zstd(3)"`
+    #[serde(deserialize_with = "deserialize_compression")]
+    pub compression: Compression,
+    /// Dictionary-encode columns (mostly benefits `pair_address` and `markout_time`)
+    pub dictionary: bool,
+    pub max_row_group_size: usize,
+}
+
+impl Default for ParquetWriteOptions {
+    fn default() -> Self {
+        Self {
+            compression: Compression::SNAPPY,
+            dictionary: true,
+            max_row_group_size: 1024 * 1024,
+        }
+    }
+}
+
+impl ParquetWriteOptions {
+    pub fn parse_compression(spec: &str) -> anyhow::Result<Compression> {
+        Compression::from_str(spec.trim())
+            .map_err(|e| crate::Error::Config(format!("Invalid parquet compression '{}': {}", spec, e)).into())
+    }
+
+    /// Lowercase `name` or `name(level)` label, the same form accepted by the config
+    pub fn compression_label(&self) -> String {
+        match self.compression {
+            Compression::UNCOMPRESSED => "uncompressed".to_string(),
+            Compression::SNAPPY => "snappy".to_string(),
+            Compression::LZO => "lzo".to_string(),
+            Compression::LZ4 => "lz4".to_string(),
+            Compression::LZ4_RAW => "lz4_raw".to_string(),
+            Compression::GZIP(level) => format!("gzip({})", level.compression_level()),
+            Compression::BROTLI(level) => format!("brotli({})", level.compression_level()),
+            Compression::ZSTD(level) => format!("zstd({})", level.compression_level()),
+        }
+    }
+
+    pub fn writer_properties(&self) -> WriterProperties {
+        self.writer_properties_with_metadata(Vec::new())
+    }
+
+    /// Like `writer_properties`, with extra footer key-value entries
+    pub fn writer_properties_with_metadata(&self, metadata: Vec<KeyValue>) -> WriterProperties {
+        self.writer_properties_builder(metadata).build()
+    }
+
+    /// Builder preloaded with these options, for callers that add more
+    /// settings (e.g. sorting columns) before building
+    pub fn writer_properties_builder(&self, mut metadata: Vec<KeyValue>) -> WriterPropertiesBuilder {
+        metadata.insert(0, KeyValue::new(COMPRESSION_METADATA_KEY.to_string(), self.compression_label()));
+        let builder = WriterProperties::builder()
+            .set_compression(self.compression)
+            .set_dictionary_enabled(self.dictionary)
+            .set_max_row_group_size(self.max_row_group_size)
+            .set_write_batch_size(1024 * 1024)
+            .set_data_page_size_limit(1024 * 1024)
+            // Min/max per row group (and page) so readers can prune
+            .set_statistics_enabled(EnabledStatistics::Page)
+            .set_key_value_metadata(Some(metadata));
+        BLOOM_FILTER_COLUMNS.iter().fold(builder, |builder, column| {
+            builder
+                .set_column_bloom_filter_enabled(ColumnPath::from(*column), true)
+                .set_column_bloom_filter_ndv(ColumnPath::from(*column), BLOOM_FILTER_NDV)
+        })
+    }
+}
+
+fn deserialize_compression<'de, D>(deserializer: D) -> Result<Compression, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let spec = String::deserialize(deserializer)?;
+    ParquetWriteOptions::parse_compression(&spec).map_err(serde::de::Error::custom)
+}
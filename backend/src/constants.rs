@@ -80,6 +80,20 @@ lazy_static! {
         m.insert("0xa43fe16908251ee70ef74718545e4fe6c5ccec9f", "PEPE-WETH-v2");
         m
     };
+    /// ERC-20 decimals of each token appearing in `POOL_NAMES`
+    pub static ref TOKEN_DECIMALS: HashMap<&'static str, u8> = {
+        let mut m = HashMap::new();
+        m.insert("USDC", 6);
+        m.insert("USDT", 6);
+        m.insert("WBTC", 8);
+        m.insert("WETH", 18);
+        m.insert("DAI", 18);
+        m.insert("USDe", 18);
+        m.insert("LINK", 18);
+        m.insert("PEPE", 18);
+        m.insert("UNI", 18);
+        m
+    };
     pub static ref STABLE_POOLS: HashMap<&'static str, &'static str> = {
         let mut m = HashMap::new();
         m.insert("0x3416cf6c708da44db2624d63ea0aaef7113527c6", "USDC-USDT-1bps");
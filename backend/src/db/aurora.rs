@@ -1,15 +1,29 @@
-use crate::config::AuroraConfig;
-use crate::Error;
-use anyhow::{anyhow, Context, Result};
+use crate::config::{AuroraConfig, AuroraQueryLimits, ALLOW_INSECURE_SSL_ENV_VAR};
+use crate::storage::{retry_with, with_timeout, RetryPolicy};
+use crate::credentials::{connect_with_credentials, CredentialProvider, Credentials};
+use crate::failover::{Endpoint, HostFailover};
+use crate::reconnect::ReconnectPolicy;
+use crate::MetricsRegistry;
+use crate::{is_auth_error, is_connection_error, is_transient_error, Error};
+use anyhow::{Context, Result};
 use async_trait::async_trait;
 use dashmap::DashMap;
 use mysql_async::{params, Pool, PoolConstraints, PoolOpts, SslOpts};
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
 use serde::Deserialize;
+use std::env;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
+use std::future::Future;
+use tokio::sync::{mpsc, Semaphore};
 use tracing::{error, info, warn};
-use crate::DatabaseConnection;
+use crate::{DatabaseConnection, HEALTH_CHECK_TIMEOUT};
 use mysql_async::prelude::Queryable;
 
+/// Rows `stream_lvr_details` fetches ahead of its consumer
+pub const DETAILS_STREAM_BUFFER: usize = 1024;
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct LVRDetails {
     pub block_number: u64,
@@ -17,168 +31,504 @@ pub struct LVRDetails {
     pub index: u32,
 }
 
-pub struct AuroraConnection {
-    pools: Arc<DashMap<u64, Pool>>, // Map index to its own pool
+/// Caps the queries running at once. Shared by everything fetching through
+/// one `AuroraConnection`, including its streaming tasks.
+pub struct QueryLimiter {
+    semaphore: Semaphore,
+    limit: usize,
+    in_flight: AtomicUsize,
+    peak_in_flight: AtomicUsize,
+}
+
+impl QueryLimiter {
+    pub fn new(limit: usize) -> Self {
+        let limit = limit.max(1);
+        Self {
+            semaphore: Semaphore::new(limit),
+            limit,
+            in_flight: AtomicUsize::new(0),
+            peak_in_flight: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Most queries that ever ran at once
+    pub fn peak_in_flight(&self) -> usize {
+        self.peak_in_flight.load(Ordering::Acquire)
+    }
+
+    /// Runs `query` once a slot is free
+    pub async fn run<F: Future>(&self, query: F) -> F::Output {
+        // Never closed, so acquiring cannot fail
+        let _permit = self.semaphore.acquire().await.expect("query semaphore closed");
+        let in_flight = self.in_flight.fetch_add(1, Ordering::AcqRel) + 1;
+        self.peak_in_flight.fetch_max(in_flight, Ordering::AcqRel);
+        let output = query.await;
+        self.in_flight.fetch_sub(1, Ordering::AcqRel);
+        output
+    }
+}
+
+/// The TLS options of `config`. With `ssl_verify` off the certificate is
+/// not checked, which needs `allow_insecure` and is logged as a warning.
+pub fn aurora_ssl_opts(config: &AuroraConfig, allow_insecure: bool) -> Result<SslOpts> {
+    if !config.ssl_verify {
+        if !allow_insecure {
+            return Err(Error::Config(format!(
+                "Aurora ssl_verify is off; set {}=true to connect without verifying the server certificate",
+                ALLOW_INSECURE_SSL_ENV_VAR
+            )).into());
+        }
+        warn!(
+            "INSECURE: Aurora server certificates are not verified ({}=true); the connection can be intercepted",
+            ALLOW_INSECURE_SSL_ENV_VAR
+        );
+        return Ok(SslOpts::default().with_danger_accept_invalid_certs(true));
+    }
+
+    let mut ssl_opts = SslOpts::default();
+    if let Some(ca_path) = &config.ssl_ca_path {
+        if !ca_path.is_file() {
+            return Err(Error::Config(format!("Aurora CA bundle {} does not exist", ca_path.display())).into());
+        }
+        ssl_opts = ssl_opts.with_root_certs(vec![ca_path.clone().into()]);
+    }
+    Ok(ssl_opts)
+}
+
+/// The connection pools of one `AuroraConnection`, one per markout index,
+/// each on the endpoint it was created against with the credentials
+/// current at the time
+pub struct AuroraPools {
     config: AuroraConfig,
-    reconnect_attempts: u32,
-    reconnect_delay: std::time::Duration,
+    failover: HostFailover,
+    credentials: Arc<dyn CredentialProvider>,
+    pools: DashMap<u64, (usize, Pool)>,
+    ssl_opts: SslOpts,
+    reconnect: ReconnectPolicy,
+    health_check_timeout: Duration,
+    metrics: Option<Arc<MetricsRegistry>>,
 }
 
-impl AuroraConnection {
+impl AuroraPools {
     pub fn new(config: AuroraConfig) -> Result<Self> {
+        let failover = HostFailover::new("Aurora", config.endpoints()?)
+            .with_cooldown(Duration::from_secs(config.host_cooldown));
+        let credentials = config.credentials.provider(&config.user, &config.password)?;
+        let allow_insecure = env::var(ALLOW_INSECURE_SSL_ENV_VAR).is_ok_and(|allow| allow.trim() == "true");
+        let ssl_opts = aurora_ssl_opts(&config, allow_insecure)?;
         Ok(Self {
-            pools: Arc::new(DashMap::new()),
             config,
-            reconnect_attempts: 3,
-            reconnect_delay: std::time::Duration::from_secs(5),
+            failover,
+            credentials,
+            pools: DashMap::new(),
+            ssl_opts,
+            reconnect: ReconnectPolicy::default(),
+            health_check_timeout: HEALTH_CHECK_TIMEOUT,
+            metrics: None,
         })
     }
 
-    async fn get_or_create_pool(&self, index: u64) -> Result<(Pool, bool)> {
-        if let Some(pool) = self.pools.get(&index) {
-            return Ok((pool.clone(), false));
+    pub fn failover(&self) -> &HostFailover {
+        &self.failover
+    }
+
+    /// The pool of `index` with the index of its endpoint, and whether it
+    /// was just created. A pool whose endpoint has since failed, or which
+    /// fails its test query, is dropped and replaced.
+    async fn get_or_create_pool(&self, index: u64) -> Result<(usize, Pool, bool)> {
+        let existing = self.pools.get(&index).map(|entry| entry.value().clone());
+        if let Some((endpoint, pool)) = existing {
+            let failure = if !self.failover.is_healthy(endpoint) {
+                format!("its endpoint {} failed", self.failover.endpoints()[endpoint])
+            } else {
+                match self.test(&pool).await {
+                    Ok(()) => return Ok((endpoint, pool, false)),
+                    Err(e) => format!("its test query failed: {:#}", e),
+                }
+            };
+            warn!("Replacing the pool for index {}: {}", index, failure);
+            self.pools.remove_if(&index, |_, (pool_endpoint, _)| *pool_endpoint == endpoint);
+            if let Some(metrics) = &self.metrics {
+                metrics.record_aurora_reconnect();
+            }
+        }
+
+        let (endpoint, pool) = self.create_with_retries().await?;
+        self.pools.insert(index, (endpoint, pool.clone()));
+        Ok((endpoint, pool, true))
+    }
+
+    /// Runs `SELECT 1` on a connection of `pool` within the health check timeout
+    async fn test(&self, pool: &Pool) -> Result<()> {
+        let probe = async {
+            let mut conn = pool.get_conn().await?;
+            conn.query_drop("SELECT 1").await
+        };
+        match tokio::time::timeout(self.health_check_timeout, probe).await {
+            Ok(result) => Ok(result?),
+            Err(_) => Err(Error::Database(format!("Test query timed out after {:?}", self.health_check_timeout)).into()),
+        }
+    }
+
+    /// Runs one batch query, timing it when there is a metrics registry
+    async fn timed<T>(&self, query: impl Future<Output = Result<T>>) -> Result<T> {
+        match &self.metrics {
+            Some(metrics) => metrics.time_aurora_query(query).await,
+            None => query.await,
+        }
+    }
+
+    /// Marks `endpoint` unhealthy after `error` lost a connection to it and
+    /// drops the pool `index` has there, so the next query fails over.
+    fn fail_over(&self, index: u64, endpoint: usize, error: &anyhow::Error) {
+        self.failover.mark_unhealthy(endpoint, error);
+        self.pools.remove_if(&index, |_, (pool_endpoint, _)| *pool_endpoint == endpoint);
+    }
+
+    /// Fetches the credentials again after the server rejected them and
+    /// drops every pool, so the next query connects with the new ones
+    async fn reauthenticate(&self, error: &anyhow::Error) {
+        warn!("Aurora rejected the credentials, fetching them again: {:#}", error);
+        if let Err(e) = self.credentials.refresh().await {
+            warn!("Failed to refresh the Aurora credentials: {:#}", e);
         }
+        self.pools.clear();
+    }
 
-        let pool = self.create_pool().await?;
-        self.pools.insert(index, pool.clone());
-        Ok((pool, true))
+    /// A pool on the first endpoint answering a test connection
+    async fn create(&self) -> Result<(usize, Pool)> {
+        self.failover.connect(|endpoint| self.open(endpoint)).await
+    }
+
+    /// `create`, tried under the reconnect policy
+    async fn create_with_retries(&self) -> Result<(usize, Pool)> {
+        self.reconnect.connect("Aurora", self.metrics.as_deref(), || self.create()).await
+    }
+
+    async fn open(&self, endpoint: Endpoint) -> Result<Pool> {
+        connect_with_credentials(self.credentials.as_ref(), "Aurora", |credentials| {
+            self.open_with(endpoint.clone(), credentials)
+        })
+        .await
     }
 
-    async fn create_pool(&self) -> Result<Pool> {
-        let host = self.config.get_host_for_environment();
+    async fn open_with(&self, endpoint: Endpoint, credentials: Credentials) -> Result<Pool> {
         info!("Creating connection pool with configuration:");
         info!(
             "Host: {}, Port: {}, Database: {}",
-            host, self.config.port, self.config.database
+            endpoint.host, endpoint.port, self.config.database
         );
     
         let pool_constraints = PoolConstraints::new(0, 12).context("Failed to create pool constraints")?;
         let pool_opts = PoolOpts::default().with_constraints(pool_constraints);
     
         let opts = mysql_async::OptsBuilder::default()
-            .ip_or_hostname(host)
-            .tcp_port(self.config.port)
-            .user(Some(self.config.user.clone()))
-            .pass(Some(self.config.password.clone()))
+            .ip_or_hostname(endpoint.host)
+            .tcp_port(endpoint.port)
+            .user(Some(credentials.user))
+            .pass(Some(credentials.password))
             .db_name(Some(self.config.database.clone()))
-            .ssl_opts(SslOpts::default().with_danger_accept_invalid_certs(true))
+            .ssl_opts(self.ssl_opts.clone())
             .pool_opts(pool_opts);
     
         let pool = Pool::new(opts);
+        let connection_timeout = Duration::from_secs(self.config.connection_timeout);
     
-        match pool.get_conn().await {
-            Ok(_) => {
+        match tokio::time::timeout(connection_timeout, pool.get_conn()).await {
+            Ok(Ok(_)) => {
                 info!("Successfully established test connection to database");
                 Ok(pool)
             }
-            Err(e) => {
+            Ok(Err(e)) => {
                 error!("Failed to establish test connection: {}", e);
-                Err(anyhow!("Failed to verify connection: {}", e))
+                // Keeps the server error, so rejected credentials are told apart
+                Err(anyhow::Error::new(e).context(Error::Database("Failed to verify connection".to_string())))
+            }
+            Err(_) => {
+                error!("Test connection timed out after {:?}", connection_timeout);
+                Err(Error::Database(format!("Test connection timed out after {:?}", connection_timeout)).into())
             }
         }
     }
+}
+
+pub struct AuroraConnection {
+    pools: Arc<AuroraPools>,
+    config: AuroraConfig,
+    limiter: Arc<QueryLimiter>,
+}
+
+impl AuroraConnection {
+    pub fn new(config: AuroraConfig) -> Result<Self> {
+        Ok(Self {
+            pools: Arc::new(AuroraPools::new(config.clone())?),
+            limiter: Arc::new(QueryLimiter::new(config.limits.max_concurrent_queries)),
+            config,
+        })
+    }
+
+    /// The pools, to configure before any query shares them
+    fn pools_mut(&mut self) -> &mut AuroraPools {
+        Arc::get_mut(&mut self.pools).expect("pools are only shared once queries start")
+    }
+
+    /// Gets the user and password from `credentials` instead of the config's
+    /// credential source
+    pub fn with_credentials(mut self, credentials: Arc<dyn CredentialProvider>) -> Self {
+        self.pools_mut().credentials = credentials;
+        self
+    }
+
+    /// Replaces the batch size and concurrency limit of the config
+    pub fn with_limits(mut self, limits: AuroraQueryLimits) -> Self {
+        self.limiter = Arc::new(QueryLimiter::new(limits.max_concurrent_queries));
+        self.config.limits = limits;
+        self
+    }
+
+    pub fn limits(&self) -> AuroraQueryLimits {
+        self.config.limits
+    }
+
+    pub fn limiter(&self) -> &Arc<QueryLimiter> {
+        &self.limiter
+    }
+
+    /// The configured endpoints and which of them are in use
+    pub fn failover(&self) -> &HostFailover {
+        self.pools.failover()
+    }
+
+    /// How many times creating a pool is tried, by `connect` and when a
+    /// query finds its pool broken, and how long to wait between tries
+    pub fn with_reconnect(mut self, attempts: u32, delay: Duration) -> Self {
+        self.pools_mut().reconnect = ReconnectPolicy::new(attempts, delay);
+        self
+    }
+
+    /// Bounds `is_connected` and the test query a pool is checked with
+    /// before reuse
+    pub fn with_health_check_timeout(mut self, timeout: Duration) -> Self {
+        self.pools_mut().health_check_timeout = timeout;
+        self
+    }
+
+    /// Counts pools created, reconnects, and batch query errors and
+    /// durations in `metrics`
+    pub fn with_metrics(mut self, metrics: Arc<MetricsRegistry>) -> Self {
+        self.pools_mut().metrics = Some(metrics);
+        self
+    }
+
+    /// The highest block `index` has details for; `None` when it has none
+    pub async fn latest_block(&self, index: u64) -> Result<Option<u64>> {
+        let description = format!("latest block query for index {}", index);
+        self.limiter.run(self.pools.timed(with_timeout(self.config.limits.query_timeout(), &description, async {
+            let (_, pool, _) = self.pools.get_or_create_pool(index).await?;
+            let mut conn = pool.get_conn().await.context("Failed to get connection from pool")?;
+            let latest: Option<Option<u64>> = conn
+                .exec_first(
+                    "SELECT MAX(blockNumber) FROM t_lvr WHERE details IS NOT NULL AND `index` = :index",
+                    params! { "index" => index },
+                )
+                .await
+                .with_context(|| format!("Failed to query the latest block for index {}", index))?;
+            Ok(latest.flatten())
+        })))
+        .await
+    }
 
+    /// Fetches `chunk_start..chunk_end` one day-sized batch at a time, retrying
+    /// each batch on transient errors under `retry_policy`. Buffers every
+    /// row; `stream_lvr_details` yields them as they arrive.
     pub async fn fetch_lvr_details(
         &self,
         index: u64,
         chunk_start: u64,
         chunk_end: u64,
+        retry_policy: &RetryPolicy,
     ) -> Result<Vec<LVRDetails>> {
+        self.stream_lvr_details(index, chunk_start, chunk_end, retry_policy)
+            .await?
+            .try_collect()
+            .await
+    }
+
+    /// Streams `chunk_start..chunk_end` one `batch_size` batch at a time, so
+    /// at most `DETAILS_STREAM_BUFFER` rows are held before the caller takes
+    /// them. Each batch query waits for a slot of the connection's limiter
+    /// and fails once it has run for the configured query timeout.
+    /// A batch is retried on transient errors under `retry_policy` until its
+    /// first row has been yielded; after that an error ends the stream,
+    /// since a retry would repeat rows. A batch losing its connection marks
+    /// the endpoint unhealthy, so its retry fails over to the next one, and
+    /// one whose credentials are rejected retries with refreshed ones.
+    pub async fn stream_lvr_details(
+        &self,
+        index: u64,
+        chunk_start: u64,
+        chunk_end: u64,
+        retry_policy: &RetryPolicy,
+    ) -> Result<BoxStream<'static, Result<LVRDetails>>> {
         info!(
             "Starting LVR details fetch for index {} from block {} to {}",
             index, chunk_start, chunk_end
         );
 
-        let mut all_results: Vec<LVRDetails> = Vec::new();
-        let batch_size: u64 = 7200;
-        let mut current_start = chunk_start;
-        let mut attempts = 0;
-        let total_blocks = chunk_end - chunk_start;
-        let total_batches = (total_blocks as f64 / batch_size as f64).ceil() as u64;
-        let mut completed_batches = 0;
-
-        while current_start < chunk_end {
-            attempts += 1;
-            let current_end = std::cmp::min(current_start + batch_size, chunk_end);
-
-            let (pool, created) = self.get_or_create_pool(index).await?;
+        let description = format!("LVR details pool for index {}", index);
+        retry_with(retry_policy, &description, is_transient_error, || async {
+            let (_, _, created) = self.pools.get_or_create_pool(index).await?;
             if created {
                 info!("Created pool for markout time index {}.", index);
             } else {
                 info!("Reusing pool for markout time index {}.", index);
             }
+            Ok::<_, anyhow::Error>(())
+        })
+        .await?;
 
-            match self
-                .try_fetch_lvr_details_batch(&pool, index, current_start, current_end)
-                .await
-            {
-                Ok(batch_results) => {
-                    let batch_count = batch_results.len();
-                    all_results.extend(batch_results);
-                    current_start = current_end;
-                    attempts = 0;
-                    completed_batches += 1;
-
-                    info!(
-                        "Completed batch {}/{} for index {} ({:.1}% complete). Retrieved {} records. Total records so far: {}",
-                        completed_batches,
-                        total_batches,
-                        index,
-                        (completed_batches as f64 / total_batches as f64) * 100.0,
-                        batch_count,
-                        all_results.len()
-                    );
+        let (sender, receiver) = mpsc::channel(DETAILS_STREAM_BUFFER);
+        let retry_policy = retry_policy.clone();
+        let pools = Arc::clone(&self.pools);
+        let limiter = Arc::clone(&self.limiter);
+        let batch_size = self.config.limits.batch_size.max(1);
+        let query_timeout = self.config.limits.query_timeout();
+        tokio::spawn(async move {
+            let batches = DetailsBatches { pools: &pools, limiter: &limiter, index, batch_size, query_timeout };
+            tokio::select! {
+                // Dropping the stream abandons the query in flight
+                () = sender.closed() => {
+                    info!("Stopped fetching LVR details for index {}: the receiver was dropped", index);
                 }
-                Err(e) => {
-                    if attempts >= self.reconnect_attempts {
-                        error!(
-                            "Failed to fetch LVR details after {} attempts for index {} (batch {}/{}, blocks {}-{}): {}",
-                            self.reconnect_attempts,
-                            index,
-                            completed_batches + 1,
-                            total_batches,
-                            current_start,
-                            current_end,
-                            e
-                        );
-                        return Err(Error::Database(format!(
-                            "Failed to fetch LVR details batch after {} attempts: {}",
-                            self.reconnect_attempts, e
-                        ))
-                        .into());
+                result = batches.send(chunk_start, chunk_end, &retry_policy, &sender) => {
+                    if let Err(e) = result {
+                        // Nobody is left to tell if the receiver is gone too
+                        let _ = sender.send(Err(e)).await;
                     }
+                }
+            }
+        });
 
-                    warn!(
-                        "Attempt {} to fetch LVR details batch {}-{} failed: {}. Retrying in {} seconds...",
-                        attempts,
-                        current_start,
-                        current_end,
-                        e,
-                        self.reconnect_delay.as_secs()
-                    );
+        Ok(stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|row| (row, receiver))
+        })
+        .boxed())
+    }
+}
 
-                    tokio::time::sleep(self.reconnect_delay).await;
-                }
+/// One markout index's batch queries for a chunk
+struct DetailsBatches<'a> {
+    pools: &'a AuroraPools,
+    limiter: &'a QueryLimiter,
+    index: u64,
+    batch_size: u64,
+    /// Budget of each attempt at a batch query
+    query_timeout: Duration,
+}
+
+impl DetailsBatches<'_> {
+    async fn send(
+        &self,
+        chunk_start: u64,
+        chunk_end: u64,
+        retry_policy: &RetryPolicy,
+        sender: &mpsc::Sender<Result<LVRDetails>>,
+    ) -> Result<()> {
+        let (index, batch_size) = (self.index, self.batch_size);
+        let mut current_start = chunk_start;
+        let total_blocks = chunk_end - chunk_start;
+        let total_batches = (total_blocks as f64 / batch_size as f64).ceil() as u64;
+        let mut completed_batches = 0;
+        let mut total_rows = 0;
+
+        while current_start < chunk_end {
+            let current_end = std::cmp::min(current_start + batch_size, chunk_end);
+            let description = format!("LVR details batch {}-{} for index {}", current_start, current_end, index);
+
+            let sent = AtomicUsize::new(0);
+            let retryable = |e: &anyhow::Error| {
+                sent.load(Ordering::Relaxed) == 0 && (is_transient_error(e) || is_auth_error(e))
+            };
+            let open = retry_with(retry_policy, &description, retryable, || {
+                self.limiter.run(self.pools.timed(with_timeout(
+                    self.query_timeout,
+                    &description,
+                    self.send_batch(current_start, current_end, sender, &sent),
+                )))
+            })
+            .await
+            .map_err(|e| {
+                error!(
+                    "Failed to fetch LVR details for index {} (batch {}/{}, blocks {}-{}): {}",
+                    index, completed_batches + 1, total_batches, current_start, current_end, e
+                );
+                e.context(format!("Failed to fetch {}", description))
+            })?;
+            if !open {
+                info!("Stopped fetching LVR details for index {}: the receiver was dropped", index);
+                return Ok(());
             }
+
+            let batch_count = sent.into_inner();
+            total_rows += batch_count;
+            current_start = current_end;
+            completed_batches += 1;
+
+            info!(
+                "Completed batch {}/{} for index {} ({:.1}% complete). Retrieved {} records. Total records so far: {}",
+                completed_batches,
+                total_batches,
+                index,
+                (completed_batches as f64 / total_batches as f64) * 100.0,
+                batch_count,
+                total_rows
+            );
         }
 
         info!(
             "Completed fetching all LVR details for index {}. Retrieved {} total records across {} batches",
             index,
-            all_results.len(),
+            total_rows,
             total_batches
         );
 
-        Ok(all_results)
+        Ok(())
     }
 
-    async fn try_fetch_lvr_details_batch(
+    /// Sends one batch's rows as the server returns them, counting them in
+    /// `sent`. `false` when the receiver is gone.
+    async fn send_batch(
+        &self,
+        batch_start: u64,
+        batch_end: u64,
+        sender: &mpsc::Sender<Result<LVRDetails>>,
+        sent: &AtomicUsize,
+    ) -> Result<bool> {
+        let (endpoint, pool, _) = self.pools.get_or_create_pool(self.index).await?;
+        let result = self.query_batch(&pool, batch_start, batch_end, sender, sent).await;
+        if let Err(e) = &result {
+            if is_auth_error(e) {
+                self.pools.reauthenticate(e).await;
+            } else if is_connection_error(e) {
+                self.pools.fail_over(self.index, endpoint, e);
+            }
+        }
+        result
+    }
+
+    async fn query_batch(
         &self,
         pool: &Pool,
-        index: u64,
         batch_start: u64,
         batch_end: u64,
-    ) -> Result<Vec<LVRDetails>> {
+        sender: &mpsc::Sender<Result<LVRDetails>>,
+        sent: &AtomicUsize,
+    ) -> Result<bool> {
+        let index = self.index;
         let mut conn = pool
             .get_conn()
             .await
@@ -206,78 +556,64 @@ impl AuroraConnection {
             "index" => index,
         };
 
-        let result: Vec<LVRDetails> = conn
-            .exec_map(
-                query,
-                params,
-                |(block_number, details, index): (u64, String, u32)| LVRDetails {
-                    block_number,
-                    details,
-                    index,
-                },
+        let context = || {
+            format!(
+                "Failed to execute LVR details query with parameters: batch_start={}, batch_end={}, index={}",
+                batch_start, batch_end, index
             )
+        };
+        let mut rows = conn
+            .exec_stream::<(u64, String, u32), _, _>(query, params)
             .await
-            .with_context(|| {
-                format!(
-                    "Failed to execute LVR details query with parameters: batch_start={}, batch_end={}, index={}",
-                    batch_start, batch_end, index
-                )
-            })?;
+            .with_context(context)?;
 
-        let elapsed = start_time.elapsed();
+        while let Some((block_number, details, index)) = rows.try_next().await.with_context(context)? {
+            if sender.send(Ok(LVRDetails { block_number, details, index })).await.is_err() {
+                return Ok(false);
+            }
+            sent.fetch_add(1, Ordering::Relaxed);
+        }
 
         info!(
             "Fetched LVR data for index {} for block range {}-{} ({} records) in {:?}",
             index,
             batch_start,
             batch_end,
-            result.len(),
-            elapsed
+            sent.load(Ordering::Relaxed),
+            start_time.elapsed()
         );
 
-        Ok(result)
+        Ok(true)
     }
 }
 
-
 #[async_trait]
 impl DatabaseConnection for AuroraConnection {
     async fn connect(&self) -> Result<()> {
-        // Create an initial test pool to verify connectivity
-        for attempt in 0..self.reconnect_attempts {
-            match self.create_pool().await {
-                Ok(pool) => {
-                    // Test the connection
-                    if pool.get_conn().await.is_ok() {
-                        // Store this as a default pool with index 0
-                        self.pools.insert(0, pool);
-                        return Ok(());
-                    }
-                }
-                Err(e) => {
-                    if attempt == self.reconnect_attempts - 1 {
-                        return Err(e.context("Failed to connect after maximum attempts"));
-                    }
-                    tokio::time::sleep(self.reconnect_delay).await;
-                }
-            }
-        }
-
-        Err(anyhow!("Failed to connect after maximum attempts"))
+        // The pool is only returned once a test connection succeeded; it
+        // becomes the default pool at index 0
+        let pool = self.pools.create_with_retries().await?;
+        self.pools.pools.insert(0, pool);
+        Ok(())
     }
 
     async fn disconnect(&self) -> Result<()> {
-        // Clear all pools
-        self.pools.clear();
+        // Each pool waits for its connections to be returned before closing
+        let indices: Vec<u64> = self.pools.pools.iter().map(|entry| *entry.key()).collect();
+        for index in indices {
+            if let Some((_, (_, pool))) = self.pools.pools.remove(&index) {
+                pool.disconnect()
+                    .await
+                    .with_context(|| format!("Failed to disconnect pool for index {}", index))?;
+            }
+        }
         Ok(())
     }
 
     async fn is_connected(&self) -> bool {
-        // Check if any pool is connected
-        if let Some(pool) = self.pools.get(&0) {
-            pool.get_conn().await.is_ok()
-        } else {
-            false
-        }
+        let Some((_, pool)) = self.pools.pools.get(&0).map(|entry| entry.value().clone()) else {
+            return false;
+        };
+        self.pools.test(&pool).await.is_ok()
     }
 }
@@ -1,14 +1,16 @@
-use crate::config::BrontesConfig;
-use crate::DatabaseConnection;
-use crate::Error;
-use crate::BRONTES_ADDRESSES;
+use crate::config::{BrontesConfig, BrontesQueryLimits};
+use crate::credentials::{connect_with_credentials, CredentialProvider, Credentials};
+use crate::{DatabaseConnection, Error, HEALTH_CHECK_TIMEOUT};
+use crate::{is_auth_error, is_transient_error};
+use crate::storage::{retry_with, with_timeout, RetryPolicy};
 use async_trait::async_trait;
 use clickhouse::Client;
 use serde::Deserialize;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
 use anyhow::Result;
-use tracing::{warn,info, error};
+use tracing::{info, error, warn};
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct LVRAnalysis {
@@ -21,33 +23,77 @@ pub struct LVRAnalysis {
 pub struct BrontesConnection {
     client: Arc<Mutex<Option<Client>>>,
     config: BrontesConfig,
+    credentials: Arc<dyn CredentialProvider>,
+    limits: BrontesQueryLimits,
     reconnect_attempts: u32,
-    reconnect_delay: std::time::Duration,
+    reconnect_delay: Duration,
+    health_check_timeout: Duration,
 }
 
 impl BrontesConnection {
     pub fn new(config: BrontesConfig) -> Result<Self> {
         Ok(Self {
             client: Arc::new(Mutex::new(None)),
+            credentials: config.credentials.provider(&config.user, &config.password)?,
             config,
+            limits: BrontesQueryLimits::default(),
             reconnect_attempts: 3,
-            reconnect_delay: std::time::Duration::from_secs(5),
+            reconnect_delay: Duration::from_secs(5),
+            health_check_timeout: HEALTH_CHECK_TIMEOUT,
         })
     }
 
-    async fn create_client(&self) -> Result<Client> {
+    /// Gets the user and password from `credentials` instead of the config's
+    /// credential source
+    pub fn with_credentials(mut self, credentials: Arc<dyn CredentialProvider>) -> Self {
+        self.credentials = credentials;
+        self
+    }
+
+    pub fn with_limits(mut self, limits: BrontesQueryLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// How many times `connect` tries, and how long it waits between tries
+    pub fn with_reconnect(mut self, attempts: u32, delay: Duration) -> Self {
+        self.reconnect_attempts = attempts.max(1);
+        self.reconnect_delay = delay;
+        self
+    }
+
+    pub fn with_health_check_timeout(mut self, timeout: Duration) -> Self {
+        self.health_check_timeout = timeout;
+        self
+    }
+
+    fn create_client(&self, credentials: Credentials) -> Client {
         let url = format!("http://{}:{}",
             self.config.host,
             self.config.port
         );
     
-        Ok(Client::default()
+        Client::default()
             .with_url(url)
-            .with_user(self.config.user.clone())
-            .with_password(self.config.password.clone()))
+            .with_user(credentials.user)
+            .with_password(credentials.password)
+    }
+
+    /// Fetches the credentials again after the server rejected them and
+    /// drops the client, so the next query is sent with the new ones
+    async fn reauthenticate(&self, error: &anyhow::Error) {
+        warn!("Brontes rejected the credentials, fetching them again: {:#}", error);
+        if let Err(e) = self.credentials.refresh().await {
+            warn!("Failed to refresh the Brontes credentials: {:#}", e);
+        }
+        *self.client.lock().await = None;
     }
 
-    pub async fn fetch_lvr_analysis(&self, chunk_start: u64, chunk_end: u64) -> Result<Vec<LVRAnalysis>> {
+    /// Fetches `chunk_start..chunk_end` for the lowercased `pools` one
+    /// day-sized batch at a time, retrying each batch on transient errors,
+    /// including running past the query timeout, under `retry_policy`. A
+    /// batch whose credentials are rejected retries with refreshed ones.
+    pub async fn fetch_lvr_analysis(&self, pools: &[String], chunk_start: u64, chunk_end: u64, retry_policy: &RetryPolicy) -> Result<Vec<LVRAnalysis>> {
         info!(
             "Starting LVR analysis fetch from block {} to {}", 
             chunk_start, chunk_end
@@ -56,63 +102,48 @@ impl BrontesConnection {
         let mut all_results = Vec::new();
         let batch_size: u64 = 7200;
         let mut current_start = chunk_start;
-        let mut attempts = 0;
         let total_blocks = chunk_end - chunk_start;
         let total_batches = (total_blocks as f64 / batch_size as f64).ceil() as u64;
         let mut completed_batches = 0;
 
         while current_start < chunk_end {
-            attempts += 1;
             let current_end = std::cmp::min(current_start + batch_size, chunk_end);
-            let client = self.get_or_create_client().await?;
+            let description = format!("LVR analysis batch {}-{}", current_start, current_end);
 
-            match self.try_fetch_lvr_analysis_batch(&client, current_start, current_end).await {
-                Ok(batch_results) => {
-                    let batch_count = batch_results.len();
-                    all_results.extend(batch_results);
-                    current_start = current_end;
-                    attempts = 0;
-                    completed_batches += 1;
-
-                    info!(
-                        "Completed batch {}/{} ({:.1}% complete). Retrieved {} records. Total records so far: {}", 
-                        completed_batches,
-                        total_batches,
-                        (completed_batches as f64 / total_batches as f64) * 100.0,
-                        batch_count,
-                        all_results.len()
-                    );
-                },
-                Err(e) => {
-                    if attempts >= self.reconnect_attempts {
-                        error!(
-                            "Failed to fetch LVR analysis after {} attempts (batch {}/{}, blocks {}-{}): {}", 
-                            self.reconnect_attempts,
-                            completed_batches + 1,
-                            total_batches,
-                            current_start,
-                            current_end,
-                            e
-                        );
-                        return Err(Error::Database(format!(
-                            "Failed to fetch LVR analysis batch after {} attempts: {}", 
-                            self.reconnect_attempts,
-                            e
-                        )).into());
+            let batch_results = retry_with(retry_policy, &description, is_transient_error, || {
+                with_timeout(self.limits.query_timeout(), &description, async {
+                    let client = self.get_or_create_client().await?;
+                    let result = self.try_fetch_lvr_analysis_batch(&client, pools, current_start, current_end).await;
+                    if let Err(e) = &result {
+                        if is_auth_error(e) {
+                            self.reauthenticate(e).await;
+                        }
                     }
+                    result
+                })
+            })
+            .await
+            .map_err(|e| {
+                error!(
+                    "Failed to fetch LVR analysis (batch {}/{}, blocks {}-{}): {}", 
+                    completed_batches + 1, total_batches, current_start, current_end, e
+                );
+                e.context(format!("Failed to fetch {}", description))
+            })?;
 
-                    warn!(
-                        "Attempt {} to fetch LVR analysis batch {}-{} failed: {}. Retrying in {} seconds...",
-                        attempts,
-                        current_start,
-                        current_end,
-                        e,
-                        self.reconnect_delay.as_secs()
-                    );
+            let batch_count = batch_results.len();
+            all_results.extend(batch_results);
+            current_start = current_end;
+            completed_batches += 1;
 
-                    tokio::time::sleep(self.reconnect_delay).await;
-                }
-            }
+            info!(
+                "Completed batch {}/{} ({:.1}% complete). Retrieved {} records. Total records so far: {}", 
+                completed_batches,
+                total_batches,
+                (completed_batches as f64 / total_batches as f64) * 100.0,
+                batch_count,
+                all_results.len()
+            );
         }
 
         info!(
@@ -124,9 +155,22 @@ impl BrontesConnection {
         Ok(all_results)
     }
 
-    async fn try_fetch_lvr_analysis_batch(&self, client: &Client, batch_start: u64, batch_end: u64) -> Result<Vec<LVRAnalysis>> {    
-        // De-checksum the addresses
-        let pools: Vec<_> = BRONTES_ADDRESSES.iter().map(|&s| s).collect();
+    /// The highest block with an analysis of the run `fetch_lvr_analysis`
+    /// reads; `None` when there is none
+    pub async fn latest_block(&self) -> Result<Option<u64>> {
+        with_timeout(self.limits.query_timeout(), "latest Brontes block query", async {
+            let client = self.get_or_create_client().await?;
+            // max() of no rows is 0 rather than NULL in ClickHouse
+            let latest = client
+                .query("SELECT max(block_number) FROM brontes.block_analysis WHERE run_id = 1000")
+                .fetch_one::<u64>()
+                .await?;
+            Ok((latest > 0).then_some(latest))
+        })
+        .await
+    }
+
+    async fn try_fetch_lvr_analysis_batch(&self, client: &Client, pools: &[String], batch_start: u64, batch_end: u64) -> Result<Vec<LVRAnalysis>> {    
         let mut cursor = client
             .query(
                 r#"
@@ -175,10 +219,21 @@ impl BrontesConnection {
         Ok(results)
     }
 
+    /// Clients are HTTP and connect lazily, so only a query shows whether
+    /// the server is reachable
+    async fn probe(client: &Client, timeout: Duration) -> Result<()> {
+        match tokio::time::timeout(timeout, client.query("SELECT 1").fetch_one::<u8>()).await {
+            Ok(Ok(_)) => Ok(()),
+            // Keeps the server error, so rejected credentials are told apart
+            Ok(Err(e)) => Err(anyhow::Error::new(e).context(Error::Database("Test query failed".to_string()))),
+            Err(_) => Err(Error::Database(format!("Test query timed out after {:?}", timeout)).into()),
+        }
+    }
+
     async fn get_or_create_client(&self) -> Result<Client> {
         let mut client_guard = self.client.lock().await;
         if client_guard.is_none() {
-            *client_guard = Some(self.create_client().await?);
+            *client_guard = Some(self.create_client(self.credentials.credentials().await?));
         }
         Ok(client_guard.as_ref().unwrap().clone())
     }
@@ -187,45 +242,47 @@ impl BrontesConnection {
 #[async_trait]
 impl DatabaseConnection for BrontesConnection {
     async fn connect(&self) -> Result<()> {
-        let mut current_attempt = 0;
-        
-        while current_attempt < self.reconnect_attempts {
-            match self.create_client().await {
+        let connection_timeout = Duration::from_secs(self.config.connection_timeout);
+        let mut last_error = None;
+
+        for attempt in 0..self.reconnect_attempts {
+            if attempt > 0 {
+                tokio::time::sleep(self.reconnect_delay).await;
+            }
+            let result = connect_with_credentials(self.credentials.as_ref(), "Brontes", |credentials| async {
+                let client = self.create_client(credentials);
+                Self::probe(&client, connection_timeout).await.map(|_| client)
+            })
+            .await;
+            match result {
                 Ok(client) => {
-                    let mut client_guard = self.client.lock().await;
-                    *client_guard = Some(client);
+                    info!("Successfully established test connection to Brontes");
+                    *self.client.lock().await = Some(client);
                     return Ok(());
                 }
                 Err(e) => {
-                    current_attempt += 1;
-                    if current_attempt == self.reconnect_attempts {
-                        return Err(e);
-                    }
-                    tokio::time::sleep(self.reconnect_delay).await;
+                    warn!("Brontes connection attempt {}/{} failed: {}", attempt + 1, self.reconnect_attempts, e);
+                    last_error = Some(e);
                 }
             }
         }
-        
-        Err(anyhow::anyhow!("Failed to connect after max attempts"))
+
+        Err(last_error
+            .unwrap_or_else(|| anyhow::anyhow!("No connection attempts made"))
+            .context("Failed to connect after max attempts"))
     }
     
     async fn disconnect(&self) -> Result<()> {
+        // Dropping the client closes its pooled HTTP connections
         let mut client_guard = self.client.lock().await;
         *client_guard = None;
         Ok(())
     }
     
     async fn is_connected(&self) -> bool {
-        let client_guard = self.client.lock().await;
-        if let Some(client) = &*client_guard {
-            match client.query("SELECT 1 as value")
-                .fetch::<u8>()
-            {
-                Ok(_) => true,
-                Err(_) => false,
-            }
-        } else {
-            false
-        }
+        let Some(client) = self.client.lock().await.clone() else {
+            return false;
+        };
+        Self::probe(&client, self.health_check_timeout).await.is_ok()
     }
 }
\ No newline at end of file
@@ -0,0 +1,228 @@
+use crate::config::CredentialSource;
+use crate::{is_auth_error, Error};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use object_store::aws::{AmazonS3Builder, AwsAuthorizer, AwsCredentialProvider};
+use serde::Deserialize;
+use std::env;
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+/// Overrides the Secrets Manager endpoint, as in the AWS SDKs
+pub const SECRETS_MANAGER_ENDPOINT_ENV_VAR: &str = "AWS_ENDPOINT_URL_SECRETS_MANAGER";
+
+/// How long one `GetSecretValue` request may take
+pub const SECRET_FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct Credentials {
+    pub user: String,
+    pub password: String,
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Credentials").field("user", &self.user).field("password", &"<redacted>").finish()
+    }
+}
+
+/// Where a database connection gets its user and password. Asked again for
+/// every pool or client created, so rotated credentials are picked up by
+/// the next reconnect.
+#[async_trait]
+pub trait CredentialProvider: Send + Sync {
+    /// The current credentials, cached by providers that fetch them
+    async fn credentials(&self) -> Result<Credentials>;
+    /// Fetches the credentials again after the server rejected the cached ones
+    async fn refresh(&self) -> Result<Credentials>;
+}
+
+/// Credentials from the config, which never change
+pub struct EnvCredentials {
+    credentials: Credentials,
+}
+
+impl EnvCredentials {
+    pub fn new(user: impl Into<String>, password: impl Into<String>) -> Self {
+        Self { credentials: Credentials { user: user.into(), password: password.into() } }
+    }
+}
+
+#[async_trait]
+impl CredentialProvider for EnvCredentials {
+    async fn credentials(&self) -> Result<Credentials> {
+        Ok(self.credentials.clone())
+    }
+
+    async fn refresh(&self) -> Result<Credentials> {
+        Ok(self.credentials.clone())
+    }
+}
+
+impl CredentialSource {
+    /// The provider of this source; `user` and `password` are the config's,
+    /// used by `Env` only
+    pub fn provider(&self, user: &str, password: &str) -> Result<Arc<dyn CredentialProvider>> {
+        Ok(match self {
+            CredentialSource::Env => Arc::new(EnvCredentials::new(user, password)),
+            CredentialSource::SecretsManager { secret_arn } => Arc::new(SecretsManagerCredentials::new(secret_arn)?),
+        })
+    }
+}
+
+/// Runs `connect` with the provider's credentials and, when the server
+/// rejects them, once more with refreshed ones
+pub async fn connect_with_credentials<T, F, Fut>(
+    provider: &dyn CredentialProvider,
+    name: &str,
+    mut connect: F,
+) -> Result<T>
+where
+    F: FnMut(Credentials) -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    match connect(provider.credentials().await?).await {
+        Err(e) if is_auth_error(&e) => {
+            warn!("{} rejected the credentials, fetching them again: {:#}", name, e);
+            connect(provider.refresh().await?).await
+        }
+        result => result,
+    }
+}
+
+/// The AWS credential chain object_store's S3 client resolves from the
+/// environment: access keys, then web identity, ECS task and EC2 instance
+/// credentials. No request is sent to the placeholder bucket.
+fn default_aws_credentials(region: &str) -> Result<AwsCredentialProvider> {
+    let s3 = AmazonS3Builder::from_env()
+        .with_region(region)
+        .with_bucket_name("secretsmanager-credentials")
+        .build()
+        .context("Failed to resolve AWS credentials")?;
+    Ok(Arc::clone(s3.credentials()))
+}
+
+#[derive(Deserialize)]
+struct GetSecretValueResponse {
+    #[serde(rename = "SecretString")]
+    secret_string: Option<String>,
+}
+
+/// The JSON an RDS-managed secret holds; other fields are ignored
+#[derive(Deserialize)]
+struct DatabaseSecret {
+    username: String,
+    password: String,
+}
+
+/// Credentials kept in an AWS Secrets Manager secret, fetched on first use
+/// and again whenever the server rejects them
+pub struct SecretsManagerCredentials {
+    secret_arn: String,
+    region: String,
+    endpoint: String,
+    aws_credentials: AwsCredentialProvider,
+    client: reqwest::Client,
+    cached: Mutex<Option<Credentials>>,
+}
+
+impl SecretsManagerCredentials {
+    /// The region comes from the ARN, which reads
+    /// `arn:aws:secretsmanager:<region>:<account>:secret:<name>`
+    pub fn new(secret_arn: &str) -> Result<Self> {
+        let region = match secret_arn.split(':').collect::<Vec<_>>()[..] {
+            ["arn", _, "secretsmanager", region, _, "secret", _, ..] if !region.is_empty() => region.to_string(),
+            _ => return Err(Error::Config(format!("Invalid Secrets Manager secret ARN {:?}", secret_arn)).into()),
+        };
+        let endpoint = env::var(SECRETS_MANAGER_ENDPOINT_ENV_VAR)
+            .unwrap_or_else(|_| format!("https://secretsmanager.{}.amazonaws.com", region));
+        let client = reqwest::Client::builder()
+            .timeout(SECRET_FETCH_TIMEOUT)
+            .build()
+            .context("Failed to build the Secrets Manager client")?;
+        let aws_credentials = default_aws_credentials(&region)?;
+        Ok(Self { secret_arn: secret_arn.to_string(), region, endpoint, aws_credentials, client, cached: Mutex::new(None) })
+    }
+
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = endpoint.into();
+        self
+    }
+
+    /// Signs with `aws_credentials` instead of the default chain's
+    pub fn with_aws_credentials(mut self, aws_credentials: AwsCredentialProvider) -> Self {
+        self.aws_credentials = aws_credentials;
+        self
+    }
+
+    async fn fetch(&self) -> Result<Credentials> {
+        let aws_credential = self
+            .aws_credentials
+            .get_credential()
+            .await
+            .map_err(|e| Error::IO(format!("Failed to get AWS credentials: {}", e)))?;
+        let url = reqwest::Url::parse(&self.endpoint)
+            .map_err(|e| Error::Config(format!("Invalid Secrets Manager endpoint {}: {}", self.endpoint, e)))?;
+
+        let body = serde_json::to_vec(&serde_json::json!({ "SecretId": self.secret_arn }))?;
+        let mut request = self
+            .client
+            .post(url)
+            .header("content-type", "application/x-amz-json-1.1")
+            .header("x-amz-target", "secretsmanager.GetSecretValue")
+            .body(body)
+            .build()
+            .context("Failed to build the Secrets Manager request")?;
+        AwsAuthorizer::new(&aws_credential, "secretsmanager", &self.region).authorize(&mut request, None);
+        let response = self
+            .client
+            .execute(request)
+            .await
+            .map_err(|e| Error::IO(format!("Failed to reach Secrets Manager: {}", e)))?;
+
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| Error::IO(format!("Failed to read the Secrets Manager response: {}", e)))?;
+        if status.is_server_error() {
+            return Err(Error::IO(format!("Secrets Manager answered {}: {}", status, text)).into());
+        }
+        if !status.is_success() {
+            return Err(Error::Config(format!("Failed to read secret {}: {} {}", self.secret_arn, status, text)).into());
+        }
+
+        let secret_string = serde_json::from_str::<GetSecretValueResponse>(&text)
+            .map_err(|e| Error::Json(format!("Invalid Secrets Manager response: {}", e)))?
+            .secret_string
+            .ok_or_else(|| Error::Config(format!("Secret {} holds no SecretString", self.secret_arn)))?;
+        let secret: DatabaseSecret = serde_json::from_str(&secret_string)
+            .map_err(|e| Error::Json(format!("Secret {} is not a database secret: {}", self.secret_arn, e)))?;
+        info!("Fetched database credentials for {} from {}", secret.username, self.secret_arn);
+        Ok(Credentials { user: secret.username, password: secret.password })
+    }
+}
+
+#[async_trait]
+impl CredentialProvider for SecretsManagerCredentials {
+    async fn credentials(&self) -> Result<Credentials> {
+        let mut cached = self.cached.lock().await;
+        if let Some(credentials) = cached.as_ref() {
+            return Ok(credentials.clone());
+        }
+        let credentials = self.fetch().await?;
+        *cached = Some(credentials.clone());
+        Ok(credentials)
+    }
+
+    async fn refresh(&self) -> Result<Credentials> {
+        let mut cached = self.cached.lock().await;
+        let credentials = self.fetch().await?;
+        *cached = Some(credentials.clone());
+        Ok(credentials)
+    }
+}
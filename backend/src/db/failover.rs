@@ -0,0 +1,145 @@
+use crate::Error;
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// How long a failed endpoint is skipped unless the config says otherwise
+pub const ENDPOINT_COOLDOWN: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoint {
+    pub host: String,
+    pub port: u16,
+}
+
+impl Endpoint {
+    /// Parses `host` or `host:port`; the former gets `default_port`
+    pub fn parse(address: &str, default_port: u16) -> Result<Self> {
+        let address = address.trim();
+        let (host, port) = match address.rsplit_once(':') {
+            Some((host, port)) => {
+                let port = port
+                    .parse()
+                    .map_err(|_| Error::Config(format!("Invalid port in database host {}", address)))?;
+                (host, port)
+            }
+            None => (address, default_port),
+        };
+        if host.is_empty() {
+            return Err(Error::Config(format!("Empty database host in {:?}", address)).into());
+        }
+        Ok(Self { host: host.to_string(), port })
+    }
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.host, self.port)
+    }
+}
+
+/// The ordered endpoints of one database. Connections go to the endpoint
+/// that last worked, then to the others in order, skipping any still
+/// cooling down after a failure.
+pub struct HostFailover {
+    name: &'static str,
+    endpoints: Vec<Endpoint>,
+    cooldown: Duration,
+    current: AtomicUsize,
+    unhealthy_until: Mutex<Vec<Option<Instant>>>,
+}
+
+impl HostFailover {
+    pub fn new(name: &'static str, endpoints: Vec<Endpoint>) -> Self {
+        Self {
+            name,
+            unhealthy_until: Mutex::new(vec![None; endpoints.len()]),
+            endpoints,
+            cooldown: ENDPOINT_COOLDOWN,
+            current: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn with_cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    pub fn endpoints(&self) -> &[Endpoint] {
+        &self.endpoints
+    }
+
+    /// Index of the endpoint the last successful connection went to
+    pub fn current(&self) -> usize {
+        self.current.load(Ordering::Acquire)
+    }
+
+    pub fn is_healthy(&self, endpoint: usize) -> bool {
+        let unhealthy_until = self.unhealthy_until.lock().unwrap();
+        unhealthy_until[endpoint].is_none_or(|until| Instant::now() >= until)
+    }
+
+    /// Endpoint indices in the order to try them: the current one, then the
+    /// rest in configured order. Endpoints cooling down are left out unless
+    /// every endpoint is.
+    pub fn candidates(&self) -> Vec<usize> {
+        let current = self.current();
+        let ordered: Vec<usize> = std::iter::once(current)
+            .chain((0..self.endpoints.len()).filter(|&endpoint| endpoint != current))
+            .filter(|&endpoint| endpoint < self.endpoints.len())
+            .collect();
+        let healthy: Vec<usize> = ordered.iter().copied().filter(|&endpoint| self.is_healthy(endpoint)).collect();
+        if healthy.is_empty() { ordered } else { healthy }
+    }
+
+    /// Skips `endpoint` for the cooldown after a connection-level `error`
+    pub fn mark_unhealthy(&self, endpoint: usize, error: &anyhow::Error) {
+        warn!(
+            "{} endpoint {} failed, skipping it for {:?}: {:#}",
+            self.name, self.endpoints[endpoint], self.cooldown, error
+        );
+        self.unhealthy_until.lock().unwrap()[endpoint] = Some(Instant::now() + self.cooldown);
+    }
+
+    fn mark_healthy(&self, endpoint: usize) {
+        self.unhealthy_until.lock().unwrap()[endpoint] = None;
+        let previous = self.current.swap(endpoint, Ordering::AcqRel);
+        if previous != endpoint {
+            info!(
+                "{} failed over from {} to {}",
+                self.name, self.endpoints[previous], self.endpoints[endpoint]
+            );
+        }
+    }
+
+    /// Runs `connect` against each candidate until one succeeds, marking the
+    /// ones that fail unhealthy. Returns the index of the endpoint used.
+    pub async fn connect<T, F, Fut>(&self, mut connect: F) -> Result<(usize, T)>
+    where
+        F: FnMut(Endpoint) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut failures = Vec::new();
+        for endpoint in self.candidates() {
+            match connect(self.endpoints[endpoint].clone()).await {
+                Ok(connection) => {
+                    self.mark_healthy(endpoint);
+                    return Ok((endpoint, connection));
+                }
+                Err(e) => {
+                    self.mark_unhealthy(endpoint, &e);
+                    failures.push(format!("{}: {:#}", self.endpoints[endpoint], e));
+                }
+            }
+        }
+
+        if failures.is_empty() {
+            return Err(anyhow!("No {} endpoints configured", self.name));
+        }
+        Err(Error::Database(format!("Every {} endpoint failed: {}", self.name, failures.join("; "))).into())
+    }
+}
@@ -1,12 +1,66 @@
 pub mod aurora;
 pub mod brontes;
+pub mod credentials;
+pub mod failover;
+pub mod reconnect;
 
 use async_trait::async_trait;
 use anyhow::Result;
+use std::fmt;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// How long `is_connected` waits for its probe query by default
+pub const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
 
 #[async_trait]
 pub trait DatabaseConnection: Send + Sync {
+    /// Opens the connection and verifies it with a test query
     async fn connect(&self) -> Result<()>;
+    /// Closes every pooled connection
     async fn disconnect(&self) -> Result<()>;
+    /// Whether a cheap probe query succeeds within the health check
+    /// timeout; `false` before `connect`
     async fn is_connected(&self) -> bool;
-}
\ No newline at end of file
+}
+
+/// What `probe_database` found, as printed by `lvr doctor`
+#[derive(Debug, Clone)]
+pub struct DatabaseStatus {
+    pub name: &'static str,
+    /// Why the database is unreachable; `None` when it answered
+    pub error: Option<String>,
+    pub elapsed: Duration,
+}
+
+impl DatabaseStatus {
+    pub fn reachable(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+impl fmt::Display for DatabaseStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.error {
+            None => write!(f, "{:<8} reachable ({:.2?})", self.name, self.elapsed),
+            Some(error) => write!(f, "{:<8} unreachable after {:.2?}: {}", self.name, self.elapsed, error),
+        }
+    }
+}
+
+/// Connects, runs the health check and disconnects again. Failures are
+/// reported in the status rather than returned.
+pub async fn probe_database(name: &'static str, connection: &dyn DatabaseConnection) -> DatabaseStatus {
+    let started = Instant::now();
+    let error = match connection.connect().await {
+        Err(e) => Some(format!("{:#}", e)),
+        Ok(()) if !connection.is_connected().await => Some("connected, but the health check failed".to_string()),
+        Ok(()) => None,
+    };
+    let elapsed = started.elapsed();
+
+    if let Err(e) = connection.disconnect().await {
+        warn!("Failed to disconnect from {}: {:#}", name, e);
+    }
+    DatabaseStatus { name, error, elapsed }
+}
@@ -0,0 +1,60 @@
+use crate::MetricsRegistry;
+use anyhow::{anyhow, Result};
+use std::future::Future;
+use std::time::Duration;
+use tracing::warn;
+
+/// How many times a connection is opened before giving up, and how long to
+/// wait between tries
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReconnectPolicy {
+    pub attempts: u32,
+    pub delay: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self { attempts: 3, delay: Duration::from_secs(5) }
+    }
+}
+
+impl ReconnectPolicy {
+    pub fn new(attempts: u32, delay: Duration) -> Self {
+        Self { attempts: attempts.max(1), delay }
+    }
+
+    /// Runs `connect` until it succeeds or every attempt failed. Counts the
+    /// pool it opens, and each attempt after a failure as a reconnect, in
+    /// `metrics`.
+    pub async fn connect<T, F, Fut>(&self, name: &str, metrics: Option<&MetricsRegistry>, mut connect: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut last_error = None;
+        for attempt in 0..self.attempts {
+            if attempt > 0 {
+                tokio::time::sleep(self.delay).await;
+                if let Some(metrics) = metrics {
+                    metrics.record_aurora_reconnect();
+                }
+            }
+            match connect().await {
+                Ok(connection) => {
+                    if let Some(metrics) = metrics {
+                        metrics.record_aurora_pool_created();
+                    }
+                    return Ok(connection);
+                }
+                Err(e) => {
+                    warn!("{} connection attempt {}/{} failed: {}", name, attempt + 1, self.attempts, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        Err(last_error
+            .unwrap_or_else(|| anyhow!("No connection attempts made"))
+            .context("Failed to connect after maximum attempts"))
+    }
+}
@@ -23,10 +23,114 @@ pub enum Error {
     #[error("Parquet error: {0}")]
     Parquet(String),
 
+    #[error("Coverage error: {0}")]
+    Coverage(String),
+
+    #[error("Timed out: {0}")]
+    Timeout(String),
+
+    #[error("Cancelled: {0}")]
+    Cancelled(String),
+
     #[error("General error: {0}")]
     Other(String),
 }
 
+impl Error {
+    /// Database and IO failures and timeouts may clear up on their own;
+    /// configuration, processing and decoding errors will fail the same way
+    /// again, and a cancelled run is not to be retried
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Error::Database(_) | Error::IO(_) | Error::Timeout(_))
+    }
+}
+
+/// Whether retrying the operation that produced `error` can help. The first
+/// error in the chain this crate knows how to classify decides; anything
+/// unrecognized is treated as a logic error and not retried.
+pub fn is_transient_error(error: &anyhow::Error) -> bool {
+    for cause in error.chain() {
+        if let Some(e) = cause.downcast_ref::<Error>() {
+            return e.is_transient();
+        }
+        if let Some(e) = cause.downcast_ref::<object_store::Error>() {
+            return crate::storage::is_retryable(e);
+        }
+        if let Some(e) = cause.downcast_ref::<mysql_async::Error>() {
+            return is_transient_mysql_error(e);
+        }
+        if cause.is::<std::io::Error>()
+            || cause.is::<clickhouse::error::Error>()
+            || cause.is::<tokio::time::error::Elapsed>()
+        {
+            return true;
+        }
+    }
+    false
+}
+
+/// MySQL server error codes of a query that may succeed if run again:
+/// lock wait timeout, deadlock, query interrupted, execution time exceeded,
+/// too many connections, shutdown in progress, connection killed
+pub const TRANSIENT_MYSQL_ERROR_CODES: [u16; 7] = [1205, 1213, 1317, 3024, 1040, 1053, 1927];
+
+/// Connection and driver failures are transient; a server error only is
+/// when its code is in `TRANSIENT_MYSQL_ERROR_CODES`. Other server errors,
+/// such as bad SQL or denied access, and bad URLs fail the same way again.
+pub fn is_transient_mysql_error(error: &mysql_async::Error) -> bool {
+    match error {
+        mysql_async::Error::Server(e) => TRANSIENT_MYSQL_ERROR_CODES.contains(&e.code),
+        mysql_async::Error::Url(_) => false,
+        mysql_async::Error::Driver(_) | mysql_async::Error::Io(_) | mysql_async::Error::Other(_) => true,
+    }
+}
+
+/// MySQL server error codes of a connection the server refused or dropped:
+/// too many connections, shutdown in progress, connection killed
+pub const CONNECTION_MYSQL_ERROR_CODES: [u16; 3] = [1040, 1053, 1927];
+
+/// Whether `error` lost the connection itself rather than failing a query on
+/// it, so another endpoint of the same database may still answer
+pub fn is_connection_error(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        if let Some(e) = cause.downcast_ref::<mysql_async::Error>() {
+            return match e {
+                mysql_async::Error::Server(e) => CONNECTION_MYSQL_ERROR_CODES.contains(&e.code),
+                mysql_async::Error::Io(_) | mysql_async::Error::Driver(_) => true,
+                mysql_async::Error::Other(_) | mysql_async::Error::Url(_) => false,
+            };
+        }
+        cause.is::<std::io::Error>()
+    })
+}
+
+/// MySQL server error codes of rejected credentials: access denied, with and
+/// without a password
+pub const AUTH_MYSQL_ERROR_CODES: [u16; 2] = [1045, 1698];
+
+/// ClickHouse exception codes of rejected credentials: unknown user and
+/// authentication failed
+pub const AUTH_CLICKHOUSE_ERROR_CODES: [u32; 2] = [192, 516];
+
+/// Whether `error` is the server rejecting the credentials, which a rotated
+/// secret explains and refreshed credentials may fix
+pub fn is_auth_error(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        if let Some(mysql_async::Error::Server(e)) = cause.downcast_ref::<mysql_async::Error>() {
+            return AUTH_MYSQL_ERROR_CODES.contains(&e.code);
+        }
+        if let Some(clickhouse::error::Error::BadResponse(reason)) = cause.downcast_ref::<clickhouse::error::Error>() {
+            return clickhouse_error_code(reason).is_some_and(|code| AUTH_CLICKHOUSE_ERROR_CODES.contains(&code));
+        }
+        false
+    })
+}
+
+/// The code of a ClickHouse exception, which reads `Code: 516. DB::Exception: ...`
+fn clickhouse_error_code(reason: &str) -> Option<u32> {
+    reason.strip_prefix("Code: ")?.split(|c: char| !c.is_ascii_digit()).next()?.parse().ok()
+}
+
 impl From<anyhow::Error> for Error {
     fn from(err: anyhow::Error) -> Self {
         Error::Other(err.to_string())
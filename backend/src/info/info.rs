@@ -0,0 +1,120 @@
+use anyhow::{Context, Result};
+use futures::StreamExt;
+use object_store::{path::Path, ObjectStore};
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use std::fmt::Write;
+use crate::models::ChunkSummary;
+use crate::storage::parquet_reader;
+
+/// Prefixes `lvr info` reports on, in display order
+pub const INFO_PREFIXES: &[&str] = &["intervals", "checkpoints", "chunks", "precomputed"];
+
+/// Number of objects and total bytes under `prefix`, ignoring leftover
+/// temporary uploads
+pub async fn prefix_usage(store: &dyn ObjectStore, prefix: &str) -> Result<(usize, u64)> {
+    let mut listing = store.list(Some(&Path::from(prefix)));
+    let (mut objects, mut bytes) = (0, 0u64);
+    while let Some(meta) = listing.next().await {
+        let meta = meta.with_context(|| format!("Failed to list {}", prefix))?;
+        if meta.location.as_ref().contains(".tmp-") {
+            continue;
+        }
+        objects += 1;
+        bytes += meta.size as u64;
+    }
+    Ok((objects, bytes))
+}
+
+/// Every `chunks/*_summary.parquet`, sorted by chunk start
+pub async fn read_chunk_summaries(store: &dyn ObjectStore) -> Result<Vec<ChunkSummary>> {
+    let mut listing = store.list(Some(&Path::from("chunks")));
+    let mut summaries = Vec::new();
+    while let Some(meta) = listing.next().await {
+        let meta = meta.context("Failed to list chunk summaries")?;
+        if !meta.location.as_ref().ends_with("_summary.parquet") {
+            continue;
+        }
+
+        let bytes = store.get(&meta.location).await?.bytes().await?;
+        let reader = parquet_reader(bytes, &meta.location, 1024)?;
+        for batch in reader {
+            let batch = batch.with_context(|| format!("Failed to read {}", meta.location))?;
+            summaries.extend(ChunkSummary::from_record_batch(&batch)?);
+        }
+    }
+    summaries.sort_by_key(|summary| (summary.chunk_start, summary.chunk_end));
+    Ok(summaries)
+}
+
+/// Size, row counts and footer metadata of the parquet object at `path`,
+/// as `lvr inspect` prints them
+pub async fn inspect_object(store: &dyn ObjectStore, path: &Path) -> Result<String> {
+    let bytes = store.get(path).await.with_context(|| format!("Failed to read {}", path))?.bytes().await?;
+    let size = bytes.len();
+    let reader = SerializedFileReader::new(bytes)
+        .with_context(|| format!("{} is not readable parquet (truncated or corrupt footer)", path))?;
+    let metadata = reader.metadata();
+    let file_metadata = metadata.file_metadata();
+
+    // Writing to a String cannot fail
+    let mut report = format!("{}\n", path);
+    let _ = writeln!(report, "  size        {} bytes", size);
+    let _ = writeln!(report, "  rows        {}", file_metadata.num_rows());
+    let _ = writeln!(report, "  row groups  {}", metadata.num_row_groups());
+    for (i, row_group) in metadata.row_groups().iter().enumerate() {
+        let _ = writeln!(
+            report,
+            "    {:>4}  {:>10} rows {:>12} bytes",
+            i, row_group.num_rows(), row_group.compressed_size()
+        );
+    }
+    let _ = writeln!(report, "  metadata");
+    for entry in file_metadata.key_value_metadata().into_iter().flatten() {
+        let _ = writeln!(report, "    {} = {}", entry.key, entry.value.as_deref().unwrap_or(""));
+    }
+    Ok(report)
+}
+
+/// One line per (chunk, markout time), chunks in block order
+pub fn format_chunk_summaries(summaries: &[ChunkSummary]) -> String {
+    let mut table = format!(
+        "{:>10} {:>10} {:>8} {:>16} {:>10} {:>11} {:>9} {:>12} {:>12} {:>13} {:>10} {:>9} {:>13} {:>7} {:>13}\n",
+        "start", "end", "markout", "total_lvr_usd", "non_zero",
+        "aurora_rows", "malformed", "brontes_rows", "aurora_ms", "brontes_ms",
+        "process_ms", "write_ms", "checkpoint_ms", "retries", "write_retries"
+    );
+    for summary in summaries {
+        for markout in &summary.markouts {
+            // Writing to a String cannot fail
+            let _ = writeln!(
+                table,
+                "{:>10} {:>10} {:>8} {:>16.2} {:>10} {:>11} {:>9} {:>12} {:>12} {:>13} {:>10} {:>9} {:>13} {:>7} {:>13}",
+                summary.chunk_start,
+                summary.chunk_end,
+                markout.markout_time,
+                markout.total_lvr_cents.to_dollars(),
+                markout.non_zero_count,
+                summary.aurora_rows,
+                summary.malformed_aurora_rows,
+                summary.brontes_rows,
+                summary.timings.aurora_fetch_ms,
+                summary.timings.brontes_fetch_ms,
+                summary.timings.process_ms,
+                summary.timings.interval_write_ms,
+                summary.timings.checkpoint_update_ms,
+                summary.retries,
+                summary.write_retries,
+            );
+        }
+        for warning in &summary.completeness_warnings {
+            let _ = writeln!(table, "{:>10} {:>10} warning: {}", summary.chunk_start, summary.chunk_end, warning);
+        }
+        if !summary.source_counts.is_empty() {
+            let sources: Vec<String> = summary.source_counts.iter()
+                .map(|(source, count)| format!("{}={}", source, count))
+                .collect();
+            let _ = writeln!(table, "{:>10} {:>10} sources: {}", summary.chunk_start, summary.chunk_end, sources.join(" "));
+        }
+    }
+    table
+}
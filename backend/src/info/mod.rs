@@ -0,0 +1,2 @@
+mod info;
+pub use info::*;
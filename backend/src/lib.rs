@@ -1,9 +1,16 @@
+#![allow(clippy::module_inception)]
+
 pub mod config;
 pub mod constants;
 pub mod db;
 pub mod error;
+pub mod info;
+pub mod metrics;
 pub mod models;
 pub mod processor;
+pub mod schema;
+pub mod source;
+pub mod storage;
 pub mod utils;
 pub mod writer;
 pub mod validator;
@@ -15,8 +22,13 @@ pub use config::*;
 pub use constants::*;
 pub use db::*;
 pub use error::*;
+pub use info::*;
+pub use metrics::*;
 pub use models::*;
 pub use processor::*;
+pub use schema::*;
+pub use source::*;
+pub use storage::*;
 pub use utils::*;
 pub use writer::*;
 pub use validator::*;
@@ -1,17 +1,26 @@
 use anyhow::Result;
-use backend::{init_logging, processor::ParallelLVRProcessor, serve, Validator, PrecomputedWriter};
+use backend::{
+    aurora::AuroraConnection, brontes::BrontesConnection, probe_database, DatabaseConfig,
+    compact_checkpoints, format_chunk_summaries, init_logging, inspect_object, migrate_checkpoint_layout, prefix_usage, processor::{ParallelLVRProcessor, ValidationCallback}, read_chunk_summaries, serve, serve_metrics,
+    AppConfig, Error, MetricsRegistry, ParallelParquetWriter, ParquetWriteOptions, PoolRegistry, PrecomputedWriter, Severity, SourceSpec, ValidationReport, Validator,
+    COVERAGE_GAP_CHECK, INFO_PREFIXES,
+};
 use clap::{Parser, Subcommand};
-use futures::future::BoxFuture;
+use futures::FutureExt;
 use object_store::local::LocalFileSystem;
 use object_store::ObjectStore;
-use std::{path::PathBuf, sync::Arc};
+use std::{path::PathBuf, sync::Arc, time::Duration};
 use tracing::{error, info, warn};
+use tokio_util::sync::CancellationToken;
 
 // Block boundaries for processing
 const START_BLOCK: u64 = 15537392;
 const END_BLOCK: u64 = 20000000;
 
-type ValidationCallback = for<'a> fn(&'a Arc<dyn ObjectStore>) -> BoxFuture<'a, Result<()>>;
+/// `validate` exits with this, rather than the 1 of every other failure,
+/// when interval files are missing, so a rerun of the missing chunks can be
+/// told apart from a data problem
+const COVERAGE_GAP_EXIT_CODE: i32 = 2;
 
 #[derive(Debug, Parser)]
 #[command(name = "lvr")]
@@ -19,6 +28,45 @@ type ValidationCallback = for<'a> fn(&'a Arc<dyn ObjectStore>) -> BoxFuture<'a,
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// TOML config file (defaults to lvr.toml when present)
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Pool registry file (defaults to pools.toml when present, otherwise
+    /// the built-in pool list)
+    #[arg(long, global = true)]
+    pools: Option<PathBuf>,
+
+    /// Parquet compression codec, e.g. "snappy" or "zstd(3)"
+    #[arg(long, global = true)]
+    compression: Option<String>,
+
+    /// Disable parquet dictionary encoding
+    #[arg(long, global = true)]
+    no_dictionary: bool,
+
+    /// Maximum rows per parquet row group
+    #[arg(long, global = true)]
+    max_row_group_size: Option<usize>,
+}
+
+impl Cli {
+    fn load_config(&self) -> Result<AppConfig> {
+        let mut config = AppConfig::load(self.config.as_deref())?;
+
+        if let Some(compression) = &self.compression {
+            config.parquet.compression = ParquetWriteOptions::parse_compression(compression)?;
+        }
+        if self.no_dictionary {
+            config.parquet.dictionary = false;
+        }
+        if let Some(max_row_group_size) = self.max_row_group_size {
+            config.parquet.max_row_group_size = max_row_group_size;
+        }
+
+        Ok(config)
+    }
 }
 
 #[derive(Debug, Subcommand)]
@@ -30,11 +78,97 @@ enum Commands {
 
         #[arg(short, long)]
         end_block: Option<u64>,
+
+        /// Chunks fetched and processed concurrently; checkpoints and
+        /// interval files are still written in chunk order
+        #[arg(long, default_value = "1")]
+        parallel_chunks: usize,
+
+        /// Where LVR is read from: "aurora" for the Aurora and Brontes
+        /// databases, or "parquet:<path>" for a local dump directory.
+        /// Defaults to AURORA_SOURCE, then "aurora".
+        #[arg(long)]
+        source: Option<String>,
+
+        /// Reprocess chunks whose interval file already exists. Nothing is
+        /// subtracted from the checkpoints, so only use this against fresh
+        /// checkpoints.
+        #[arg(long)]
+        overwrite: bool,
+
+        /// Continue an interrupted or shorter earlier run: its checkpoints
+        /// are loaded and the chunks it wrote are skipped
+        #[arg(long, conflicts_with = "overwrite")]
+        resume: bool,
+
+        /// Serve per-phase chunk timings and throughput for Prometheus at
+        /// /metrics on this port while processing
+        #[arg(long)]
+        metrics_port: Option<u16>,
+
+        /// Comma-separated pool addresses whose per-block LVR is also
+        /// written, under raw/<pool>/
+        #[arg(long, value_delimiter = ',')]
+        raw_pools: Vec<String>,
+
+        /// Fail a chunk when the source returns no or anomalously few rows
+        /// for a markout, instead of only noting it in the chunk summary
+        #[arg(long)]
+        strict_source_completeness: bool,
+
+        /// End the run at the latest block every source has data for, when
+        /// that is before the end block
+        #[arg(long)]
+        clamp_to_source: bool,
+    },
+    /// Reprocess some pools over chunks already written, replacing their
+    /// rows in each interval file and keeping every other pool's.
+    /// Checkpoints are not changed.
+    Backfill {
+        /// Comma-separated addresses of the pools to reprocess
+        #[arg(long, value_delimiter = ',', required = true)]
+        pool: Vec<String>,
+
+        /// Start of the first chunk to correct, as the chunk was processed
+        #[arg(short, long)]
+        start_block: u64,
+
+        /// End of the last chunk to correct, as the chunk was processed
+        #[arg(short, long)]
+        end_block: u64,
+
+        /// Chunks fetched and processed concurrently
+        #[arg(long, default_value = "1")]
+        parallel_chunks: usize,
+
+        /// Where LVR is read from, as for `process`
+        #[arg(long)]
+        source: Option<String>,
     },
     /// Validate processed data
     Validate {
         #[arg(short, long)]
         data_dir: Option<PathBuf>,
+
+        /// Fail, rather than warn, when a pool's brontes total exceeds a
+        /// theoretical markout's
+        #[arg(long)]
+        strict_realized: bool,
+
+        /// Percent running total difference above which a pool warns,
+        /// overriding `[validation] total_pct_warn`
+        #[arg(long)]
+        total_pct_warn: Option<f64>,
+
+        /// Percent running total difference above which a pool fails,
+        /// overriding `[validation] total_pct_fail`
+        #[arg(long)]
+        total_pct_fail: Option<f64>,
+
+        /// When only precomputed files fail, rerun the precompute steps
+        /// writing them and validate once more
+        #[arg(long)]
+        repair: bool,
     },
     /// Start the API server
     Serve {
@@ -45,7 +179,43 @@ enum Commands {
         host: String,
     },
     /// Precompute analytical data
-    Precompute,
+    Precompute {
+        /// Maximum number of precompute tasks running at once
+        #[arg(short, long, default_value = "4")]
+        concurrency: usize,
+        /// Skip the single-file individual running totals; readers use the
+        /// per-pool partitions instead
+        #[arg(long)]
+        no_combined_running_totals: bool,
+    },
+    /// Summarize stored data
+    Info {
+        /// Tabulate the per-chunk summaries written during processing
+        #[arg(long)]
+        chunks: bool,
+    },
+    /// Print the row counts and footer metadata of a stored parquet file
+    Inspect {
+        /// Path within the store, e.g. intervals/15537392_15753392.parquet
+        path: String,
+    },
+    /// Check that Aurora and Brontes are reachable with the environment's
+    /// configuration
+    Doctor,
+    /// Rewrite stored data into the layout this version writes
+    Maintain {
+        /// Move checkpoints named `<pool>_<markout>.parquet` to
+        /// `pool=<pool>/markout=<markout>.parquet`
+        #[arg(long)]
+        migrate_layout: bool,
+        /// Write every checkpoint into `checkpoints/all.parquet`, which
+        /// readers use in place of the per-file checkpoints, and remove those
+        #[arg(long)]
+        compact_checkpoints: bool,
+        /// With --compact-checkpoints, keep the per-file checkpoints
+        #[arg(long, requires = "compact_checkpoints")]
+        keep_checkpoint_files: bool,
+    },
 }
 
 fn ensure_directories() -> Result<PathBuf> {
@@ -63,48 +233,64 @@ fn ensure_directories() -> Result<PathBuf> {
     Ok(data_dir)
 }
 
-async fn run_validation(store: Arc<dyn ObjectStore>) -> Result<()> {
+/// Logs the outcome of `validate_all` and fails if any finding fails under
+/// the validator's policy, with `Error::Coverage` when interval files are
+/// missing
+async fn run_validation(validator: &Validator) -> Result<()> {
     info!("Running data validation");
-    let validator = Validator::new(Arc::clone(&store));
-
-    match validator.validate_all().await {
-        Ok(results) => {
-            let mut has_significant_errors = false;
-            let mut has_minor_discrepancies = false;
-
-            for (key, stats) in results {
-                if stats.difference != 0 {
-                    if stats.difference_percent.abs() > 1.0 {
-                        has_significant_errors = true;
-                        error!(
-                            "Significant discrepancy for {}: Difference of {} ({:.2}%)",
-                            key, stats.difference, stats.difference_percent
-                        );
-                    } else {
-                        has_minor_discrepancies = true;
-                        warn!(
-                            "Minor discrepancy for {}: Difference of {} ({:.2}%)",
-                            key, stats.difference, stats.difference_percent
-                        );
-                    }
-                }
-            }
 
-            if has_significant_errors {
-                return Err(anyhow::anyhow!(
-                    "Validation failed with significant discrepancies"
-                ));
-            }
+    let report = validator
+        .validate_all()
+        .await
+        .map_err(|e| anyhow::anyhow!("Validation failed: {}", e))?;
+    conclude_validation(&report)
+}
 
-            if has_minor_discrepancies {
-                warn!("Validation completed with minor discrepancies");
-            } else {
-                info!("Validation completed successfully with no discrepancies");
-            }
+/// Validates, repairs inconsistent precomputed files and, if any were
+/// regenerated, judges the revalidation instead
+async fn run_validation_with_repair(validator: &Validator, writer: &PrecomputedWriter) -> Result<()> {
+    info!("Running data validation with repair");
+
+    let repair = validator
+        .validate_and_repair(writer)
+        .await
+        .map_err(|e| anyhow::anyhow!("Validation failed: {}", e))?;
+
+    for violation in &repair.unrepairable {
+        warn!("No precompute step repairs {}", violation);
+    }
+    if repair.regenerated.is_empty() {
+        info!("No precomputed files were regenerated");
+    } else {
+        info!("Regenerated precomputed outputs: {}", repair.regenerated.join(", "));
+    }
+    conclude_validation(&repair.report)
+}
 
+fn conclude_validation(report: &ValidationReport) -> Result<()> {
+    match report.severity() {
+        Severity::Fail => {
+            let gaps: Vec<&str> = report
+                .failures()
+                .filter(|finding| finding.check == COVERAGE_GAP_CHECK)
+                .map(|finding| finding.subject.as_str())
+                .collect();
+            if !gaps.is_empty() {
+                return Err(Error::Coverage(format!("No interval file covers {}", gaps.join(", "))).into());
+            }
+            Err(anyhow::anyhow!(
+                "Validation failed with {} failing findings",
+                report.failures().count()
+            ))
+        }
+        Severity::Warn => {
+            warn!("Validation completed with minor discrepancies");
+            Ok(())
+        }
+        Severity::Info => {
+            info!("Validation completed successfully with no discrepancies");
             Ok(())
         }
-        Err(e) => Err(anyhow::anyhow!("Validation failed: {}", e)),
     }
 }
 
@@ -119,6 +305,10 @@ async fn main() -> Result<()> {
     // Load environment variables
     dotenv::dotenv().ok();
 
+    let config = cli.load_config()?;
+    let pools = Arc::new(PoolRegistry::load(cli.pools.as_deref())?);
+    info!("Loaded {} pools", pools.pools().len());
+
     // Ensure data directories exist
     let data_dir = ensure_directories()?;
 
@@ -130,25 +320,79 @@ async fn main() -> Result<()> {
         Commands::Process {
             start_block,
             end_block,
+            parallel_chunks,
+            source,
+            overwrite,
+            resume,
+            metrics_port,
+            raw_pools,
+            strict_source_completeness,
+            clamp_to_source,
         } => {
             let start_block = start_block.unwrap_or(START_BLOCK);
             let end_block = end_block.unwrap_or(END_BLOCK);
+            let raw_pools = raw_pools
+                .iter()
+                .map(|pool| pools.address(pool).cloned().ok_or_else(|| {
+                    Error::Config(format!("Raw pool {} is not in the pool registry", pool))
+                }))
+                .collect::<Result<Vec<_>, _>>()?;
+            let metrics = metrics_port.map(|_| Arc::new(MetricsRegistry::new()));
+            let source = SourceSpec::from_arg_or_env(source.as_deref())?
+                .open(&config, metrics.clone())
+                .await?;
 
             info!("Starting LVR data processing");
 
-            let processor = Arc::new(
-                ParallelLVRProcessor::new(start_block, end_block, Arc::clone(&store)).await?
-            );
+            // Ctrl-C abandons the chunks being fetched; `--resume` continues
+            let cancellation = CancellationToken::new();
+            let on_ctrl_c = cancellation.clone();
+            tokio::spawn(async move {
+                if tokio::signal::ctrl_c().await.is_ok() {
+                    warn!("Ctrl-C received; cancelling in-flight fetches");
+                    on_ctrl_c.cancel();
+                }
+            });
 
-            // Define validation callback
-            let validation_callback: Option<ValidationCallback> =
-                Some(|store: &Arc<dyn ObjectStore>| {
-                    Box::pin(async move { run_validation(Arc::clone(store)).await })
+            let mut processor = ParallelLVRProcessor::new(start_block, end_block, Arc::clone(&store)).await?
+                .with_write_options(config.parquet.clone())
+                .with_layout(config.output.layout)
+                .with_parallel_chunks(parallel_chunks)
+                .with_retry_config(config.retry.clone())
+                .with_digest_config(config.tdigest)
+                .with_pool_registry(Arc::clone(&pools))
+                .with_source(source)
+                .with_overwrite(overwrite)
+                .with_resume(resume)
+                .with_raw_pools(&raw_pools)
+                .with_strict_source_completeness(strict_source_completeness)
+                .with_clamp_to_source(clamp_to_source)
+                .with_cancellation(cancellation);
+            if let (Some(port), Some(metrics)) = (metrics_port, metrics) {
+                processor = processor.with_metrics(Arc::clone(&metrics));
+                tokio::spawn(async move {
+                    if let Err(e) = serve_metrics(metrics, port).await {
+                        error!("Metrics server failed: {}", e);
+                    }
                 });
+            }
+            let processor = Arc::new(processor);
+
+            // Validate after each chunk with a validator over the output store
+            let validator = Arc::new(
+                Validator::new(Arc::clone(&store))
+                    .with_pool_registry(Arc::clone(&pools))
+                    .with_policy(config.validation.clone().during_processing())
+                    .with_layout(config.output.layout),
+            );
+            let validation_callback: ValidationCallback = Arc::new(move |_store| {
+                let validator = Arc::clone(&validator);
+                async move { run_validation(&validator).await }.boxed()
+            });
 
             // Process blocks with validation after each chunk
             let processor_clone = Arc::clone(&processor);
-            match processor_clone.process_blocks(validation_callback).await {
+            match processor_clone.process_blocks(Some(validation_callback)).await {
                 Ok(_) => info!("Processing completed successfully"),
                 Err(e) => {
                     error!("Processing failed: {}", e);
@@ -156,64 +400,137 @@ async fn main() -> Result<()> {
                 }
             }
         }
-        Commands::Validate { data_dir } => {
+        Commands::Backfill { pool, start_block, end_block, parallel_chunks, source } => {
+            let backfilled = Arc::new(pools.subset(&pool)?);
+            let source = SourceSpec::from_arg_or_env(source.as_deref())?
+                .open(&config, None)
+                .await?;
+
+            info!("Backfilling {} from block {} to {}", pool.join(", "), start_block, end_block);
+            ParallelLVRProcessor::new(start_block, end_block, Arc::clone(&store)).await?
+                .with_write_options(config.parquet.clone())
+                .with_layout(config.output.layout)
+                .with_parallel_chunks(parallel_chunks)
+                .with_retry_config(config.retry.clone())
+                .with_pool_registry(backfilled)
+                .with_precompute_pool_registry(Arc::clone(&pools))
+                .with_source(source)
+                .with_backfill(true)
+                .process_blocks(None)
+                .await?;
+            info!("Backfill completed; run `validate` to check the result");
+        }
+        Commands::Validate { data_dir, strict_realized, total_pct_warn, total_pct_fail, repair } => {
             let data_dir = data_dir.unwrap_or_else(|| PathBuf::from("smeed"));
             info!("Starting validation of data in {:?}", data_dir);
 
             let store: Arc<dyn ObjectStore> =
                 Arc::new(LocalFileSystem::new_with_prefix(data_dir)?);
 
-            run_validation(Arc::clone(&store)).await?;
+            let mut policy = config.validation.clone();
+            policy.realized_excess_fail |= strict_realized;
+            if let Some(percent) = total_pct_warn {
+                policy.total_pct_warn = percent;
+            }
+            if let Some(percent) = total_pct_fail {
+                policy.total_pct_fail = percent;
+            }
+
+            let validator = Validator::new(Arc::clone(&store))
+                .with_pool_registry(Arc::clone(&pools))
+                .with_policy(policy)
+                .with_layout(config.output.layout);
+            let result = if repair {
+                let writer = PrecomputedWriter::new(Arc::clone(&store))
+                    .with_write_options(config.parquet.clone())
+                    .with_retry_policy(config.retry.write.clone())
+                    .with_layout(config.output.layout)
+                    .with_pool_registry(Arc::clone(&pools));
+                run_validation_with_repair(&validator, &writer).await
+            } else {
+                run_validation(&validator).await
+            };
+            if let Err(e) = result {
+                if let Some(Error::Coverage(_)) = e.downcast_ref::<Error>() {
+                    error!("Validation failed: {}", e);
+                    std::process::exit(COVERAGE_GAP_EXIT_CODE);
+                }
+                return Err(e);
+            }
+            validator.check_precomputed_schemas().await?;
         }
         Commands::Serve { host, port } => {
             let store: Arc<dyn ObjectStore> = Arc::new(LocalFileSystem::new_with_prefix("smeed")?);
 
             info!("Starting API server using data from smeed/");
-            serve(host, port, store).await?;
+            serve(host, port, store, pools).await?;
         }
-        Commands::Precompute => {
+        Commands::Precompute { concurrency, no_combined_running_totals } => {
             info!("Starting precomputation of analytical data");
             
-            let writer = Arc::new(PrecomputedWriter::new(Arc::clone(&store)));
-            
-            info!("Computing running totals...");
-            writer.write_running_totals().await?;
-            
-            info!("Computing pool totals...");
-            writer.write_pool_totals().await?;
-            
-            info!("Computing max LVR values...");
-            writer.write_max_lvr().await?;
-            
-            info!("Computing non-zero proportions...");
-            writer.write_non_zero_proportions().await?;
-            
-            info!("Computing histograms...");
-            writer.write_histograms().await?;
-            
-            info!("Computing percentile bands...");
-            writer.write_percentile_bands().await?;
-            
-            info!("Computing quartile plots...");
-            writer.write_quartile_plots().await?;
-
-            info!("Computing daily time series...");
-            writer.write_daily_time_series().await?;
-            
-            info!("Computing cluster proportions...");
-            writer.write_cluster_proportions().await?;
-            
-            info!("Computing cluster histograms...");
-            writer.write_cluster_histograms().await?;
-            
-            info!("Computing monthly cluster totals...");
-            writer.write_monthly_cluster_totals().await?;
-            
-            info!("Computing distribution metrics...");
-            writer.write_distribution_metrics().await?;
+            let writer = PrecomputedWriter::new(Arc::clone(&store))
+                .with_write_options(config.parquet.clone())
+                .with_retry_policy(config.retry.write.clone())
+                .with_layout(config.output.layout)
+                .with_combined_running_totals(!no_combined_running_totals)
+                .with_pool_registry(Arc::clone(&pools));
+            writer.run_all(concurrency).await?;
     
             info!("Successfully completed all precomputation tasks");
         }
+        Commands::Doctor => {
+            let database = DatabaseConfig::new()?;
+            // One attempt each: the doctor reports, the processor retries
+            let aurora = AuroraConnection::new(database.aurora)?.with_reconnect(1, Duration::ZERO);
+            let brontes = BrontesConnection::new(database.brontes)?.with_reconnect(1, Duration::ZERO);
+            let (aurora, brontes) = tokio::join!(
+                probe_database("aurora", &aurora),
+                probe_database("brontes", &brontes)
+            );
+
+            for status in [&aurora, &brontes] {
+                println!("{}", status);
+            }
+            let unreachable = [&aurora, &brontes].iter().filter(|status| !status.reachable()).count();
+            if unreachable > 0 {
+                return Err(anyhow::anyhow!("{} of 2 databases are unreachable", unreachable));
+            }
+        }
+        Commands::Info { chunks } => {
+            if chunks {
+                let summaries = read_chunk_summaries(store.as_ref()).await?;
+                if summaries.is_empty() {
+                    warn!("No chunk summaries found under chunks/");
+                }
+                print!("{}", format_chunk_summaries(&summaries));
+            } else {
+                for prefix in INFO_PREFIXES {
+                    let (objects, bytes) = prefix_usage(store.as_ref(), prefix).await?;
+                    println!("{:<12} {:>8} files {:>14} bytes", prefix, objects, bytes);
+                }
+            }
+        }
+        Commands::Inspect { path } => {
+            print!("{}", inspect_object(store.as_ref(), &object_store::path::Path::from(path)).await?);
+        }
+        Commands::Maintain { migrate_layout, compact_checkpoints: compact, keep_checkpoint_files } => {
+            if !migrate_layout && !compact {
+                return Err(Error::Config(
+                    "Nothing to do; pass --migrate-layout or --compact-checkpoints".to_string()
+                ).into());
+            }
+            if migrate_layout {
+                let migrated = migrate_checkpoint_layout(store.as_ref()).await?;
+                info!("Migrated {} checkpoints to the partitioned layout", migrated);
+            }
+            if compact {
+                let writer = ParallelParquetWriter::new(Arc::clone(&store))
+                    .with_write_options(config.parquet.clone())
+                    .with_retry_policy(config.retry.write.clone());
+                let compacted = compact_checkpoints(store.as_ref(), &writer, keep_checkpoint_files).await?;
+                info!("Compacted {} checkpoints", compacted);
+            }
+        }
     }
 
     Ok(())
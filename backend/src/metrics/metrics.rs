@@ -0,0 +1,202 @@
+use anyhow::Result;
+use axum::{extract::State, routing::get, Router};
+use std::fmt::Write;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::net::TcpListener;
+use tracing::info;
+use crate::models::{ChunkSummary, ChunkTimings};
+
+/// Upper bounds, in seconds, of the `lvr_chunk_phase_seconds` and
+/// `aurora_query_seconds` buckets
+pub const PHASE_SECONDS_BUCKETS: [f64; 10] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 120.0];
+
+#[derive(Debug, Default, Clone)]
+struct Histogram {
+    /// Observations at or below each bound of `PHASE_SECONDS_BUCKETS`
+    bucket_counts: [u64; PHASE_SECONDS_BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        for (bound, count) in PHASE_SECONDS_BUCKETS.iter().zip(self.bucket_counts.iter_mut()) {
+            if seconds <= *bound {
+                *count += 1;
+            }
+        }
+        self.count += 1;
+        self.sum += seconds;
+    }
+
+    /// Writes the bucket, sum and count series of `name`, each carrying
+    /// `labels`, `key="value"` pairs joined by commas
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let separator = if labels.is_empty() { "" } else { "," };
+        for (bound, count) in PHASE_SECONDS_BUCKETS.iter().zip(self.bucket_counts) {
+            let _ = writeln!(out, "{}_bucket{{{}{}le=\"{}\"}} {}", name, labels, separator, bound, count);
+        }
+        let _ = writeln!(out, "{}_bucket{{{}{}le=\"+Inf\"}} {}", name, labels, separator, self.count);
+        let labels = if labels.is_empty() { String::new() } else { format!("{{{}}}", labels) };
+        let _ = writeln!(out, "{}_sum{} {}", name, labels, self.sum);
+        let _ = writeln!(out, "{}_count{} {}", name, labels, self.count);
+    }
+}
+
+#[derive(Debug, Default)]
+struct ProcessingMetrics {
+    /// Per phase, in `ChunkTimings::phases` order
+    phases: [Histogram; 5],
+    chunks: u64,
+    blocks: u64,
+    aurora_rows: u64,
+    malformed_aurora_rows: u64,
+    brontes_rows: u64,
+}
+
+#[derive(Debug, Default)]
+struct AuroraMetrics {
+    pools_created: u64,
+    reconnects: u64,
+    query_errors: u64,
+    query_seconds: Histogram,
+}
+
+/// Cumulative processing and Aurora connection counters, rendered in the
+/// Prometheus text format
+pub struct MetricsRegistry {
+    started: Instant,
+    processing: Mutex<ProcessingMetrics>,
+    aurora: Mutex<AuroraMetrics>,
+}
+
+impl Default for MetricsRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MetricsRegistry {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            processing: Mutex::new(ProcessingMetrics::default()),
+            aurora: Mutex::new(AuroraMetrics::default()),
+        }
+    }
+
+    /// Adds a committed chunk's timings and row counts
+    pub fn record_chunk(&self, summary: &ChunkSummary) {
+        let mut metrics = self.processing.lock().unwrap_or_else(|e| e.into_inner());
+        for (histogram, (_, ms)) in metrics.phases.iter_mut().zip(summary.timings.phases()) {
+            histogram.observe(ms as f64 / 1000.0);
+        }
+        metrics.chunks += 1;
+        metrics.blocks += summary.chunk_end - summary.chunk_start;
+        metrics.aurora_rows += summary.aurora_rows;
+        metrics.malformed_aurora_rows += summary.malformed_aurora_rows;
+        metrics.brontes_rows += summary.brontes_rows;
+    }
+
+    fn aurora(&self) -> std::sync::MutexGuard<'_, AuroraMetrics> {
+        self.aurora.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Counts an Aurora pool that passed its test connection
+    pub fn record_aurora_pool_created(&self) {
+        self.aurora().pools_created += 1;
+    }
+
+    /// Counts an Aurora connection retried, or a pool replaced, after a failure
+    pub fn record_aurora_reconnect(&self) {
+        self.aurora().reconnects += 1;
+    }
+
+    /// Runs one Aurora query, adding its duration and whether it failed
+    pub async fn time_aurora_query<T, F>(&self, query: F) -> Result<T>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        let started = Instant::now();
+        let result = query.await;
+        let mut metrics = self.aurora();
+        metrics.query_seconds.observe(started.elapsed().as_secs_f64());
+        if result.is_err() {
+            metrics.query_errors += 1;
+        }
+        result
+    }
+
+    pub fn render(&self) -> String {
+        let metrics = self.processing.lock().unwrap_or_else(|e| e.into_inner());
+        let mut out = String::new();
+
+        // Writing to a String cannot fail
+        let _ = writeln!(out, "# HELP lvr_chunk_phase_seconds Wall-clock time of each chunk processing phase");
+        let _ = writeln!(out, "# TYPE lvr_chunk_phase_seconds histogram");
+        let phase_names = ChunkTimings::default().phases().map(|(phase, _)| phase);
+        for (phase, histogram) in phase_names.iter().zip(&metrics.phases) {
+            histogram.render(&mut out, "lvr_chunk_phase_seconds", &format!("phase=\"{}\"", phase));
+        }
+
+        let _ = writeln!(out, "# HELP lvr_chunks_processed_total Chunks committed by this process");
+        let _ = writeln!(out, "# TYPE lvr_chunks_processed_total counter");
+        let _ = writeln!(out, "lvr_chunks_processed_total {}", metrics.chunks);
+        let _ = writeln!(out, "# HELP lvr_blocks_processed_total Blocks covered by the committed chunks");
+        let _ = writeln!(out, "# TYPE lvr_blocks_processed_total counter");
+        let _ = writeln!(out, "lvr_blocks_processed_total {}", metrics.blocks);
+        let _ = writeln!(out, "# HELP lvr_rows_fetched_total Rows fetched for the committed chunks");
+        let _ = writeln!(out, "# TYPE lvr_rows_fetched_total counter");
+        let _ = writeln!(out, "lvr_rows_fetched_total{{source=\"aurora\"}} {}", metrics.aurora_rows);
+        let _ = writeln!(out, "lvr_rows_fetched_total{{source=\"brontes\"}} {}", metrics.brontes_rows);
+        let _ = writeln!(out, "# HELP lvr_malformed_rows_total Fetched rows dropped in whole or in part for not parsing");
+        let _ = writeln!(out, "# TYPE lvr_malformed_rows_total counter");
+        let _ = writeln!(out, "lvr_malformed_rows_total{{source=\"aurora\"}} {}", metrics.malformed_aurora_rows);
+
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let blocks_per_second = if elapsed > 0.0 { metrics.blocks as f64 / elapsed } else { 0.0 };
+        let _ = writeln!(out, "# HELP lvr_blocks_per_second Blocks processed per second since the process started");
+        let _ = writeln!(out, "# TYPE lvr_blocks_per_second gauge");
+        let _ = writeln!(out, "lvr_blocks_per_second {}", blocks_per_second);
+        drop(metrics);
+
+        let aurora = self.aurora();
+        let _ = writeln!(out, "# HELP aurora_pool_created_total Aurora connection pools opened");
+        let _ = writeln!(out, "# TYPE aurora_pool_created_total counter");
+        let _ = writeln!(out, "aurora_pool_created_total {}", aurora.pools_created);
+        let _ = writeln!(out, "# HELP aurora_reconnects_total Aurora connections retried or pools replaced after a failure");
+        let _ = writeln!(out, "# TYPE aurora_reconnects_total counter");
+        let _ = writeln!(out, "aurora_reconnects_total {}", aurora.reconnects);
+        let _ = writeln!(out, "# HELP aurora_query_errors_total Aurora batch queries that failed or timed out");
+        let _ = writeln!(out, "# TYPE aurora_query_errors_total counter");
+        let _ = writeln!(out, "aurora_query_errors_total {}", aurora.query_errors);
+        let _ = writeln!(out, "# HELP aurora_query_seconds Wall-clock time of each Aurora batch query");
+        let _ = writeln!(out, "# TYPE aurora_query_seconds histogram");
+        aurora.query_seconds.render(&mut out, "aurora_query_seconds", "");
+        out
+    }
+}
+
+async fn get_metrics(State(registry): State<Arc<MetricsRegistry>>) -> String {
+    registry.render()
+}
+
+/// Serves `registry` at `/metrics` on every interface, so Prometheus can
+/// scrape a processing run. Runs until the listener fails.
+pub async fn serve_metrics(registry: Arc<MetricsRegistry>, port: u16) -> Result<()> {
+    let app = Router::new()
+        .route("/metrics", get(get_metrics))
+        .with_state(registry);
+
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    let listener = TcpListener::bind(&addr).await?;
+    info!("Metrics server listening on {}", addr);
+
+    axum::serve(listener, app)
+        .await
+        .map_err(|e| anyhow::anyhow!("Metrics server error: {}", e))?;
+    Ok(())
+}
@@ -0,0 +1,2 @@
+mod metrics;
+pub use metrics::*;
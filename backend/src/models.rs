@@ -1,19 +1,249 @@
-use serde::{Serialize, Deserialize};
-use std::sync::atomic::{AtomicU64, AtomicI64};
+use serde::{de, Deserializer, Serialize, Serializer, Deserialize};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicI64};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
 use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Sub};
+use std::str::FromStr;
 use std::sync::atomic::Ordering;
 use std::sync::{Arc, Mutex};
-use num_traits::cast::ToPrimitive;
 use crate::tdigest::*;
 use bitvec::prelude::*;
 
+/// An amount of LVR in whole cents, the unit values are stored and served
+/// in. Serialized as the bare integer, so `_cents` fields keep their wire
+/// format. Arithmetic panics on overflow like the integer it wraps in debug
+/// builds; use the `checked_` methods where an overflow is an input error.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Cents(pub i64);
+
+impl Cents {
+    pub const ZERO: Cents = Cents(0);
+
+    /// Rounds `dollars` to the nearest cent; `None` when it is not finite or
+    /// does not fit an i64
+    pub fn from_dollars(dollars: f64) -> Option<Cents> {
+        let cents = (dollars * 100.0).round();
+        if !cents.is_finite() || cents > i64::MAX as f64 || cents < i64::MIN as f64 {
+            return None;
+        }
+        Some(Cents(cents as i64))
+    }
+
+    pub fn to_dollars(self) -> f64 {
+        self.0 as f64 / 100.0
+    }
+
+    pub fn as_i64(self) -> i64 {
+        self.0
+    }
+
+    /// The value as u64, 0 for negative amounts
+    pub fn positive_part(self) -> u64 {
+        self.0.max(0) as u64
+    }
+
+    pub fn is_positive(self) -> bool {
+        self.0 > 0
+    }
+
+    pub fn is_negative(self) -> bool {
+        self.0 < 0
+    }
+
+    pub fn checked_add(self, other: Cents) -> Option<Cents> {
+        self.0.checked_add(other.0).map(Cents)
+    }
+
+    pub fn checked_sub(self, other: Cents) -> Option<Cents> {
+        self.0.checked_sub(other.0).map(Cents)
+    }
+
+    pub fn saturating_add(self, other: Cents) -> Cents {
+        Cents(self.0.saturating_add(other.0))
+    }
+}
+
+impl Add for Cents {
+    type Output = Cents;
+
+    fn add(self, other: Cents) -> Cents {
+        self.checked_add(other).expect("LVR cents overflowed i64")
+    }
+}
+
+impl Sub for Cents {
+    type Output = Cents;
+
+    fn sub(self, other: Cents) -> Cents {
+        self.checked_sub(other).expect("LVR cents overflowed i64")
+    }
+}
+
+impl AddAssign for Cents {
+    fn add_assign(&mut self, other: Cents) {
+        *self = *self + other;
+    }
+}
+
+impl Sum for Cents {
+    fn sum<I: Iterator<Item = Cents>>(iter: I) -> Cents {
+        iter.fold(Cents::ZERO, Add::add)
+    }
+}
+
+impl<'a> Sum<&'a Cents> for Cents {
+    fn sum<I: Iterator<Item = &'a Cents>>(iter: I) -> Cents {
+        iter.copied().sum()
+    }
+}
+
+impl From<Cents> for i64 {
+    fn from(cents: Cents) -> i64 {
+        cents.0
+    }
+}
+
+impl fmt::Display for Cents {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// An Ethereum address, kept lowercase so every spelling of it compares
+/// equal. Parses `0x` followed by 40 hex digits; input in mixed case must
+/// carry a valid EIP-55 checksum, all-lowercase and all-uppercase input is
+/// taken as is. Displayed and serialized lowercase, the form stored data
+/// and file paths use.
+///
+/// Maps keyed by `Address` can be looked up with the lowercase `&str`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Address(String);
+
+impl Address {
+    /// Parses `address` in any casing without checking a checksum, for
+    /// addresses read from sources and stored files, which carry none
+    pub fn from_any_case(address: &str) -> Result<Self, crate::Error> {
+        let hex = address
+            .strip_prefix("0x")
+            .ok_or_else(|| invalid_address(address, "expected 0x followed by 40 hex digits".to_string()))?;
+        if let Some(digit) = hex.chars().find(|c| !c.is_ascii_hexdigit()) {
+            return Err(invalid_address(address, format!("{:?} is not a hex digit", digit)));
+        }
+        if hex.len() != 40 {
+            return Err(invalid_address(address, format!("{} hex digits, expected 40", hex.len())));
+        }
+        Ok(Address(format!("0x{}", hex.to_ascii_lowercase())))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The EIP-55 mixed-case form
+    pub fn checksummed(&self) -> String {
+        let hex = &self.0[2..];
+        let mut hash = [0u8; 32];
+        let mut keccak = tiny_keccak::Keccak::v256();
+        tiny_keccak::Hasher::update(&mut keccak, hex.as_bytes());
+        tiny_keccak::Hasher::finalize(keccak, &mut hash);
+
+        let digits: String = hex
+            .chars()
+            .enumerate()
+            .map(|(i, digit)| {
+                let nibble = (hash[i / 2] >> (if i % 2 == 0 { 4 } else { 0 })) & 0xf;
+                if nibble >= 8 { digit.to_ascii_uppercase() } else { digit }
+            })
+            .collect();
+        format!("0x{}", digits)
+    }
+
+    /// Whether `address` spells this address in any case, for comparing
+    /// against stored strings without parsing them
+    pub fn matches(&self, address: &str) -> bool {
+        self.0.eq_ignore_ascii_case(address)
+    }
+}
+
+impl FromStr for Address {
+    type Err = crate::Error;
+
+    fn from_str(address: &str) -> Result<Self, Self::Err> {
+        let parsed = Address::from_any_case(address)?;
+        let hex = &address[2..];
+        let mixed_case = hex.chars().any(|c| c.is_ascii_lowercase()) && hex.chars().any(|c| c.is_ascii_uppercase());
+        if mixed_case {
+            let checksummed = parsed.checksummed();
+            if checksummed != address {
+                return Err(invalid_address(address, format!("checksum mismatch, did you mean {}?", checksummed)));
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+/// How maps and file names shared by pools and the aggregate key a pool:
+/// an address in any casing becomes its lowercase form, anything else is
+/// kept as given
+pub fn pool_key(pool_address: &str) -> String {
+    Address::from_any_case(pool_address).map_or_else(|_| pool_address.to_string(), |address| address.0)
+}
+
+fn invalid_address(address: &str, reason: String) -> crate::Error {
+    crate::Error::Other(format!("Invalid address {:?}: {}", address, reason))
+}
+
+impl std::borrow::Borrow<str> for Address {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for Address {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Serialize for Address {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Address {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let address = String::deserialize(deserializer)?;
+        address.parse().map_err(de::Error::custom)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct UnifiedLVRData {
+    pub pool_address: Address,
     pub block_number: u64,
-    pub lvr_cents: u64,
+    /// Negative when the markout moved against the arbitrageur
+    pub lvr_cents: Cents,
     pub source: DataSource,
 }
 
+/// One block's LVR for a pool whose per-block series is kept
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawLvrRow {
+    pub block_number: u64,
+    pub markout_time: MarkoutTime,
+    pub lvr_cents: Cents,
+}
+
 #[derive(Debug, Clone)]
 pub struct ClusterBlockActivity {
     pub cluster_name: String,
@@ -39,6 +269,13 @@ impl ClusterBlockActivity {
             max_chunk_size,
         }
     }
+
+    /// Starts from the counts of an earlier run's activity file
+    pub fn with_accumulated(mut self, total_blocks: u64, non_zero_blocks: u64) -> Self {
+        self.accumulated_total = total_blocks;
+        self.accumulated_non_zero = non_zero_blocks;
+        self
+    }
     
     pub fn process_block(&mut self, block_number: u64, has_nonzero: bool) {
         if block_number < self.base_block {
@@ -106,6 +343,16 @@ impl ClusterBlockActivity {
         }
     }
     
+    /// Moves the bitmap to the chunk starting at `chunk_start`, so every
+    /// member pool's blocks of that chunk land in the same bitmap whatever
+    /// block the first of them started at. Does nothing for the current chunk.
+    pub fn begin_chunk(&mut self, chunk_start: u64) {
+        if chunk_start > self.base_block {
+            self.finalize_chunk();
+            self.base_block = chunk_start;
+        }
+    }
+
     // Explicitly finalize current chunk and accumulate counts
     pub fn finalize_chunk(&mut self) {
         self.accumulated_total += self.processed_blocks.count_ones() as u64;
@@ -119,13 +366,65 @@ impl ClusterBlockActivity {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Where a row of LVR data came from. Serialized as its display form;
+/// a source this build does not know reads back as `Custom`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum DataSource {
     Aurora,
     Brontes,
+    Custom(String),
 }
 
-#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Serialize, Deserialize)]
+impl DataSource {
+    pub fn as_str(&self) -> &str {
+        match self {
+            DataSource::Aurora => "aurora",
+            DataSource::Brontes => "brontes",
+            DataSource::Custom(name) => name,
+        }
+    }
+}
+
+impl fmt::Display for DataSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for DataSource {
+    type Err = crate::Error;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let source = source.trim();
+        if source.is_empty() {
+            return Err(crate::Error::Other("Empty data source name".to_string()));
+        }
+        Ok(if source.eq_ignore_ascii_case("aurora") {
+            DataSource::Aurora
+        } else if source.eq_ignore_ascii_case("brontes") {
+            DataSource::Brontes
+        } else {
+            DataSource::Custom(source.to_string())
+        })
+    }
+}
+
+impl Serialize for DataSource {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for DataSource {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let source = String::deserialize(deserializer)?;
+        source.parse().map_err(de::Error::custom)
+    }
+}
+
+/// Serialized as its display form, which is also how parquet columns, file
+/// names and query parameters spell it
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
 pub enum MarkoutTime {
     Negative2,
     Negative15,
@@ -156,7 +455,52 @@ impl fmt::Display for MarkoutTime {
     }
 }
 
+impl FromStr for MarkoutTime {
+    type Err = crate::Error;
+
+    /// The display form, any other spelling of the same number (`0`,
+    /// `-0.50`, `+1.5`), or `brontes` in any case
+    fn from_str(markout: &str) -> Result<Self, Self::Err> {
+        let markout = markout.trim();
+        if markout.eq_ignore_ascii_case("brontes") {
+            return Ok(MarkoutTime::Brontes);
+        }
+        markout
+            .parse::<f64>()
+            .ok()
+            .and_then(MarkoutTime::from_f64)
+            .ok_or_else(|| crate::Error::Other(format!("Invalid markout time {:?}", markout)))
+    }
+}
+
+impl Serialize for MarkoutTime {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for MarkoutTime {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let markout = String::deserialize(deserializer)?;
+        markout.parse().map_err(de::Error::custom)
+    }
+}
+
 impl MarkoutTime {
+    /// Every markout time, theoretical ones in increasing order then Brontes
+    pub const ALL: [MarkoutTime; 10] = [
+        MarkoutTime::Negative2,
+        MarkoutTime::Negative15,
+        MarkoutTime::Negative1,
+        MarkoutTime::Negative05,
+        MarkoutTime::Zero,
+        MarkoutTime::Positive05,
+        MarkoutTime::Positive1,
+        MarkoutTime::Positive15,
+        MarkoutTime::Positive2,
+        MarkoutTime::Brontes,
+    ];
+
     pub fn as_f64(&self) -> Option<f64> {
         match self {
             MarkoutTime::Negative2 => Some(-2.0),
@@ -199,10 +543,61 @@ impl MarkoutTime {
     }
 }
 
+/// Number of largest-LVR blocks each checkpoint keeps
+pub const TOP_LVR_CAPACITY: usize = 16;
+
+/// Share of the smallest and of the largest values the trimmed mean drops
+pub const TRIMMED_MEAN_CUT: f64 = 0.05;
+
+/// The `TOP_LVR_CAPACITY` largest non-zero LVR values seen, with their blocks.
+/// Ties on value keep the earlier block.
+#[derive(Debug, Clone, Default)]
+pub struct TopLvr {
+    // Min-heap on (value, later block first), so the root is evicted first
+    heap: BinaryHeap<Reverse<(u64, Reverse<u64>)>>,
+}
+
+impl TopLvr {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, block_number: u64, lvr_cents: u64) {
+        if lvr_cents == 0 {
+            return;
+        }
+        let entry = Reverse((lvr_cents, Reverse(block_number)));
+        if self.heap.len() < TOP_LVR_CAPACITY {
+            self.heap.push(entry);
+        } else if self.heap.peek().is_some_and(|smallest| entry < *smallest) {
+            self.heap.pop();
+            self.heap.push(entry);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    /// Entries as (lvr_cents, block_number), largest first
+    pub fn entries(&self) -> Vec<(u64, u64)> {
+        let mut entries: Vec<(u64, u64)> = self.heap.iter()
+            .map(|Reverse((value, Reverse(block)))| (*value, *block))
+            .collect();
+        entries.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        entries
+    }
+}
+
 #[derive(Debug)]
 pub struct MaxLVRData {
     pub value: u64,
     pub block: u64,
+    pub top: TopLvr,
 }
 
 #[derive(Debug)]
@@ -218,8 +613,19 @@ pub struct Checkpoint {
     pub total_bucket_500_1000: AtomicU64, 
     pub total_bucket_1000_10000: AtomicU64, 
     pub total_bucket_10000_plus: AtomicU64, 
+    /// Blocks with negative LVR; the other non-zero buckets are positive
+    pub total_bucket_negative: AtomicU64,
     pub last_updated_block: AtomicU64,
+    /// First block an update counted, 0 before the first update
+    pub first_processed_block: AtomicU64,
+    /// Blocks the updates counted; for the aggregate, summed over pools
+    pub blocks_processed: AtomicU64,
+    /// First block of the pool, from the pool registry; earlier blocks are
+    /// not counted. 0 for the aggregate and pools live at the merge.
+    pub deployment_block: u64,
     pub digest: Arc<Mutex<TDigest>>,
+    /// Set by updates since the checkpoint was last written
+    pub dirty: AtomicBool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -228,7 +634,9 @@ pub struct CheckpointSnapshot {
     pub markout_time: MarkoutTime,
     pub max_lvr_value: u64,
     pub max_lvr_block: u64,
-    pub running_total: u64,
+    /// (lvr_cents, block_number) of the largest values, largest first
+    pub top_lvr: Vec<(u64, u64)>,
+    pub running_total: Cents,
     pub total_bucket_0: u64,           
     pub total_bucket_0_10: u64,       
     pub total_bucket_10_100: u64,      
@@ -236,21 +644,127 @@ pub struct CheckpointSnapshot {
     pub total_bucket_500_1000: u64,   
     pub total_bucket_1000_10000: u64,  
     pub total_bucket_10000_plus: u64,  
+    pub total_bucket_negative: u64,
     pub last_updated_block: u64,
+    /// The blocks `first_processed_block..=last_updated_block` the updates
+    /// covered, and how many they counted; both 0 for checkpoints stored
+    /// before they were tracked
+    pub first_processed_block: u64,
+    pub blocks_processed: u64,
+    /// The pool's deployment block when the checkpoint was written; 0 for
+    /// checkpoints stored before it was tracked
+    pub deployment_block: u64,
     pub non_zero_proportion: f64,
     pub percentile_25_cents: u64,
     pub median_cents: u64,
     pub percentile_75_cents: u64,
+    /// 5%-trimmed mean and interquartile range of the positive values
+    pub trimmed_mean_cents: u64,
+    pub iqr_cents: u64,
     pub non_zero_samples: u64,
+    /// NaN and infinite values the digest skipped
+    pub rejected_samples: u64,
     pub mean: f64,
     pub std_dev: f64,
     pub skewness: f64,
     pub kurtosis: f64,
+    /// Exact smallest and largest positive LVR the digest saw; `None` for
+    /// checkpoints stored before they were tracked
+    pub min_nonzero_cents: Option<u64>,
+    pub max_nonzero_cents: Option<u64>,
+    /// Size and merge history of the live digest
+    pub digest_diagnostics: DigestDiagnostics,
+    /// Compression the live digest has adapted to
+    pub digest_delta_final: u64,
+    pub digest_buffer_size: u64,
+    /// The live digest, unfinalized, so a resumed run continues exactly where
+    /// this one stopped; the quantiles above come from a finalized copy
+    pub digest: TDigest,
+}
+
+/// LVR one chunk added at one markout time, summed over pools
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkMarkoutTotals {
+    pub markout_time: String,
+    pub total_lvr_cents: Cents,
+    pub non_zero_count: u64,
+}
+
+/// What the processor fetched and added for one chunk, written beside the
+/// interval files to trace data-quality regressions back to a chunk
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkSummary {
+    pub chunk_start: u64,
+    pub chunk_end: u64,
+    pub aurora_rows: u64,
+    /// Aurora rows dropped in whole or in part for not parsing
+    pub malformed_aurora_rows: u64,
+    pub brontes_rows: u64,
+    pub timings: ChunkTimings,
+    /// Failed attempts before the chunk succeeded
+    pub retries: u64,
+    /// Failed puts of the chunk's files that the writer retried, without
+    /// retrying the chunk
+    pub write_retries: u64,
+    /// Sorted by markout time
+    pub markouts: Vec<ChunkMarkoutTotals>,
+    /// Markouts whose row count looked like a silent upstream gap
+    pub completeness_warnings: Vec<CompletenessWarning>,
+    /// Blocks with a value in the chunk's intervals by the source it came from
+    pub source_counts: BTreeMap<DataSource, u64>,
+}
+
+/// A markout the source returned no or anomalously few rows for in a chunk
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletenessWarning {
+    pub markout_time: String,
+    pub chunk_start: u64,
+    pub chunk_end: u64,
+    pub rows: u64,
+    /// The larger of what recent chunks and the chunk's other markouts
+    /// suggest, in rows
+    pub expected_rows: u64,
+}
+
+impl fmt::Display for CompletenessWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "markout {} returned {} rows for blocks {}-{}, expected about {}",
+            self.markout_time, self.rows, self.chunk_start, self.chunk_end, self.expected_rows
+        )
+    }
+}
+
+/// Wall-clock time of each phase of one chunk, in milliseconds. The two
+/// fetches run concurrently, so phases can overlap.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChunkTimings {
+    pub aurora_fetch_ms: u64,
+    pub brontes_fetch_ms: u64,
+    /// Grouping rows by pool and computing interval metrics
+    pub process_ms: u64,
+    pub interval_write_ms: u64,
+    /// Applying the checkpoint updates and writing the changed checkpoints
+    pub checkpoint_update_ms: u64,
+}
+
+impl ChunkTimings {
+    /// Each phase's duration, labelled as in `lvr_chunk_phase_seconds`
+    pub fn phases(&self) -> [(&'static str, u64); 5] {
+        [
+            ("aurora_fetch", self.aurora_fetch_ms),
+            ("brontes_fetch", self.brontes_fetch_ms),
+            ("process", self.process_ms),
+            ("interval_write", self.interval_write_ms),
+            ("checkpoint_update", self.checkpoint_update_ms),
+        ]
+    }
 }
 
 #[derive(Debug)]
 pub struct CheckpointUpdate {
-    pub pool_address: String,
+    pub pool_address: Address,
     pub markout_time: MarkoutTime,
     pub data: Vec<UnifiedLVRData>,
     pub chunk_start: u64,
@@ -260,12 +774,18 @@ pub struct CheckpointUpdate {
 
 impl Checkpoint {
     pub fn new(pair_address: String, markout_time: MarkoutTime) -> Self {
+        Self::with_config(pair_address, markout_time, &TDigestConfig::default())
+    }
+
+    /// `new` with a digest built from `digest_config`
+    pub fn with_config(pair_address: String, markout_time: MarkoutTime, digest_config: &TDigestConfig) -> Self {
         Self {
             pair_address,
             markout_time,
             max_lvr: Arc::new(Mutex::new(MaxLVRData {
                 value: 0,
                 block: 0,
+                top: TopLvr::new(),
             })),
             running_total: AtomicI64::new(0),
             total_bucket_0: AtomicU64::new(0),
@@ -275,65 +795,48 @@ impl Checkpoint {
             total_bucket_500_1000: AtomicU64::new(0),
             total_bucket_1000_10000: AtomicU64::new(0),
             total_bucket_10000_plus: AtomicU64::new(0),
+            total_bucket_negative: AtomicU64::new(0),
             last_updated_block: AtomicU64::new(0),
+            first_processed_block: AtomicU64::new(0),
+            blocks_processed: AtomicU64::new(0),
+            deployment_block: 0,
 
-            digest: Arc::new(Mutex::new(TDigest::new()))
+            digest: Arc::new(Mutex::new(TDigest::with_config(digest_config))),
+            dirty: AtomicBool::new(false),
         }
     }
 
-    pub fn to_snapshot(&self) -> CheckpointSnapshot {
-        let max_lvr_data = self.max_lvr.lock().unwrap();
-        let digest = self.digest.lock().unwrap();
-        
-        let total_observations = self.total_bucket_0.load(Ordering::Acquire) +
-            self.total_bucket_0_10.load(Ordering::Acquire) +
-            self.total_bucket_10_100.load(Ordering::Acquire) +
-            self.total_bucket_100_500.load(Ordering::Acquire) +
-            self.total_bucket_500_1000.load(Ordering::Acquire) +
-            self.total_bucket_1000_10000.load(Ordering::Acquire) +
-            self.total_bucket_10000_plus.load(Ordering::Acquire);
-
-        let non_zero_observations = total_observations - self.total_bucket_0.load(Ordering::Acquire);
-        
-        let non_zero_proportion = if total_observations > 0 {
-            non_zero_observations as f64 / total_observations as f64
-        } else {
-            0.0
-        };
+    pub fn with_deployment_block(mut self, deployment_block: u64) -> Self {
+        self.deployment_block = deployment_block;
+        self
+    }
 
-        // Calculate percentiles using TDigest
-        let p25 = digest.quantile(0.25).map(|x| (x * 100.0).round() as u64).unwrap_or(0);
-        let p50 = digest.quantile(0.50).map(|x| (x * 100.0).round() as u64).unwrap_or(0);
-        let p75 = digest.quantile(0.75).map(|x| (x * 100.0).round() as u64).unwrap_or(0);
+    pub fn with_processed_blocks(mut self, first_processed_block: u64, blocks_processed: u64) -> Self {
+        self.first_processed_block = AtomicU64::new(first_processed_block);
+        self.blocks_processed = AtomicU64::new(blocks_processed);
+        self
+    }
 
-        // Get distribution metrics from TDigest
-        let distribution_metrics = digest.online_stats.to_metrics();
+    /// Records that an update counted the blocks `start..end`
+    pub fn record_processed(&self, start: u64, end: u64) {
+        let _ = self.first_processed_block.fetch_update(Ordering::AcqRel, Ordering::Acquire, |first| {
+            (first == 0 || start < first).then_some(start)
+        });
+        self.blocks_processed.fetch_add(end - start, Ordering::Release);
+        self.last_updated_block.fetch_max(end - 1, Ordering::Release);
+    }
 
-        CheckpointSnapshot {
-            pair_address: self.pair_address.clone(),
-            markout_time: self.markout_time,
-            max_lvr_value: max_lvr_data.value,
-            max_lvr_block: max_lvr_data.block,
-            running_total: self.running_total.load(Ordering::Acquire).to_u64().unwrap(),
-            total_bucket_0: self.total_bucket_0.load(Ordering::Acquire),
-            total_bucket_0_10: self.total_bucket_0_10.load(Ordering::Acquire),
-            total_bucket_10_100: self.total_bucket_10_100.load(Ordering::Acquire),
-            total_bucket_100_500: self.total_bucket_100_500.load(Ordering::Acquire),
-            total_bucket_500_1000: self.total_bucket_500_1000.load(Ordering::Acquire),
-            total_bucket_1000_10000: self.total_bucket_1000_10000.load(Ordering::Acquire),
-            total_bucket_10000_plus: self.total_bucket_10000_plus.load(Ordering::Acquire),
-            last_updated_block: self.last_updated_block.load(Ordering::Acquire),
-            non_zero_proportion,
-            percentile_25_cents: p25,
-            median_cents: p50,
-            percentile_75_cents: p75,
-            non_zero_samples: digest.samples(),
-            mean: distribution_metrics.mean,
-            std_dev: distribution_metrics.std_dev,
-            skewness: distribution_metrics.skewness,
-            kurtosis: distribution_metrics.kurtosis,
-        }
+    /// Rebuilds a checkpoint written by `to_snapshot`, whose own
+    /// `to_snapshot` gives the same snapshot back. It starts clean: only
+    /// later updates mark it dirty.
+    pub fn from_snapshot(snapshot: &CheckpointSnapshot) -> Self {
+        Self::from(snapshot)
     }
+
+    pub fn to_snapshot(&self) -> CheckpointSnapshot {
+        CheckpointSnapshot::from(self)
+    }
+
     pub fn update_digest(&self, value: f64) -> Result<(), String> {
         if let Ok(mut digest) = self.digest.lock() {
             digest.add(value);
@@ -345,10 +848,15 @@ impl Checkpoint {
     
     pub fn update_max_lvr(&self, block_number: u64, lvr_cents: u64) {
         let mut max_lvr = self.max_lvr.lock().unwrap();
-        if lvr_cents > max_lvr.value {
+        // Ties keep the earlier block, matching `TopLvr`, so the result does
+        // not depend on the order chunks are merged in
+        let replaces = lvr_cents > max_lvr.value
+            || (lvr_cents > 0 && lvr_cents == max_lvr.value && block_number < max_lvr.block);
+        if replaces {
             max_lvr.value = lvr_cents;
             max_lvr.block = block_number;
         }
+        max_lvr.top.push(block_number, lvr_cents);
     }
 
     pub fn finalize(&self) -> Result<(), String> {
@@ -361,21 +869,143 @@ impl Checkpoint {
     }
 }
 
+impl From<&CheckpointSnapshot> for Checkpoint {
+    fn from(snapshot: &CheckpointSnapshot) -> Self {
+        let mut top = TopLvr::new();
+        for &(lvr_cents, block_number) in &snapshot.top_lvr {
+            top.push(block_number, lvr_cents);
+        }
+
+        Self {
+            pair_address: snapshot.pair_address.clone(),
+            markout_time: snapshot.markout_time,
+            max_lvr: Arc::new(Mutex::new(MaxLVRData {
+                value: snapshot.max_lvr_value,
+                block: snapshot.max_lvr_block,
+                top,
+            })),
+            running_total: AtomicI64::new(snapshot.running_total.as_i64()),
+            total_bucket_0: AtomicU64::new(snapshot.total_bucket_0),
+            total_bucket_0_10: AtomicU64::new(snapshot.total_bucket_0_10),
+            total_bucket_10_100: AtomicU64::new(snapshot.total_bucket_10_100),
+            total_bucket_100_500: AtomicU64::new(snapshot.total_bucket_100_500),
+            total_bucket_500_1000: AtomicU64::new(snapshot.total_bucket_500_1000),
+            total_bucket_1000_10000: AtomicU64::new(snapshot.total_bucket_1000_10000),
+            total_bucket_10000_plus: AtomicU64::new(snapshot.total_bucket_10000_plus),
+            total_bucket_negative: AtomicU64::new(snapshot.total_bucket_negative),
+            last_updated_block: AtomicU64::new(snapshot.last_updated_block),
+            first_processed_block: AtomicU64::new(snapshot.first_processed_block),
+            blocks_processed: AtomicU64::new(snapshot.blocks_processed),
+            deployment_block: snapshot.deployment_block,
+
+            digest: Arc::new(Mutex::new(snapshot.digest.clone())),
+            dirty: AtomicBool::new(false),
+        }
+    }
+}
+
+impl From<&Checkpoint> for CheckpointSnapshot {
+    fn from(checkpoint: &Checkpoint) -> Self {
+        let max_lvr_data = checkpoint.max_lvr.lock().unwrap();
+        let stored = checkpoint.digest.lock().unwrap().clone();
+        // Quantiles come from a finalized copy, covering the buffered values
+        let mut digest = stored.clone();
+        digest.finalize();
+    
+        let total_observations = checkpoint.total_bucket_0.load(Ordering::Acquire) +
+            checkpoint.total_bucket_0_10.load(Ordering::Acquire) +
+            checkpoint.total_bucket_10_100.load(Ordering::Acquire) +
+            checkpoint.total_bucket_100_500.load(Ordering::Acquire) +
+            checkpoint.total_bucket_500_1000.load(Ordering::Acquire) +
+            checkpoint.total_bucket_1000_10000.load(Ordering::Acquire) +
+            checkpoint.total_bucket_10000_plus.load(Ordering::Acquire) +
+            checkpoint.total_bucket_negative.load(Ordering::Acquire);
+
+        let non_zero_observations = total_observations - checkpoint.total_bucket_0.load(Ordering::Acquire);
+    
+        let non_zero_proportion = if total_observations > 0 {
+            non_zero_observations as f64 / total_observations as f64
+        } else {
+            0.0
+        };
+
+        // Calculate percentiles using TDigest, which only holds positive values
+        let cents = |x: Option<f64>| x.map(|x| (x * 100.0).round() as u64).unwrap_or(0);
+        let [q25, q50, q75] = digest.quantiles(&[0.25, 0.50, 0.75])[..] else {
+            unreachable!("one result per quantile")
+        };
+        let (p25, p50, p75) = (cents(q25), cents(q50), cents(q75));
+        let trimmed_mean = cents(digest.trimmed_mean(TRIMMED_MEAN_CUT, 1.0 - TRIMMED_MEAN_CUT));
+        let iqr = cents(q75.zip(q25).map(|(q75, q25)| q75 - q25));
+
+        // Get distribution metrics from TDigest
+        let distribution_metrics = digest.online_stats.to_metrics();
+
+        CheckpointSnapshot {
+            pair_address: checkpoint.pair_address.clone(),
+            markout_time: checkpoint.markout_time,
+            max_lvr_value: max_lvr_data.value,
+            max_lvr_block: max_lvr_data.block,
+            top_lvr: max_lvr_data.top.entries(),
+            running_total: Cents(checkpoint.running_total.load(Ordering::Acquire)),
+            total_bucket_0: checkpoint.total_bucket_0.load(Ordering::Acquire),
+            total_bucket_0_10: checkpoint.total_bucket_0_10.load(Ordering::Acquire),
+            total_bucket_10_100: checkpoint.total_bucket_10_100.load(Ordering::Acquire),
+            total_bucket_100_500: checkpoint.total_bucket_100_500.load(Ordering::Acquire),
+            total_bucket_500_1000: checkpoint.total_bucket_500_1000.load(Ordering::Acquire),
+            total_bucket_1000_10000: checkpoint.total_bucket_1000_10000.load(Ordering::Acquire),
+            total_bucket_10000_plus: checkpoint.total_bucket_10000_plus.load(Ordering::Acquire),
+            total_bucket_negative: checkpoint.total_bucket_negative.load(Ordering::Acquire),
+            last_updated_block: checkpoint.last_updated_block.load(Ordering::Acquire),
+            first_processed_block: checkpoint.first_processed_block.load(Ordering::Acquire),
+            blocks_processed: checkpoint.blocks_processed.load(Ordering::Acquire),
+            deployment_block: checkpoint.deployment_block,
+            non_zero_proportion,
+            percentile_25_cents: p25,
+            median_cents: p50,
+            percentile_75_cents: p75,
+            trimmed_mean_cents: trimmed_mean,
+            iqr_cents: iqr,
+            non_zero_samples: digest.samples(),
+            rejected_samples: stored.rejected_samples,
+            mean: distribution_metrics.mean,
+            std_dev: distribution_metrics.std_dev,
+            skewness: distribution_metrics.skewness,
+            kurtosis: distribution_metrics.kurtosis,
+            min_nonzero_cents: stored.min.map(|x| (x * 100.0).round() as u64),
+            max_nonzero_cents: stored.max.map(|x| (x * 100.0).round() as u64),
+            digest_diagnostics: stored.diagnostics(),
+            digest_delta_final: stored.compression.delta_final,
+            digest_buffer_size: stored.compression.buffer_size as u64,
+            digest: stored,
+        }
+    }
+}
+
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct IntervalData {
     pub interval_id: u64,
     pub pair_address: String,
     pub markout_time: MarkoutTime,
-    pub total_lvr_cents: u64,     
+    /// Net LVR; negative blocks offset positive ones
+    pub total_lvr_cents: Cents,     
+    /// Largest positive block value, 0 when there is none
     pub max_lvr_cents: u64,       
     pub non_zero_count: u64,        
     pub total_count: u64,            
+    /// The blocks `start_block..end_block` the interval covers
+    pub start_block: u64,
+    pub end_block: u64,
+    /// Blocks with a value by the source it came from; empty in files
+    /// written before provenance was recorded
+    #[serde(default)]
+    pub source_counts: BTreeMap<DataSource, u64>,
 }
 
 impl IntervalData {
     pub fn total_lvr_dollars(&self) -> f64 {
-        self.total_lvr_cents as f64 / 100.0
+        self.total_lvr_cents.to_dollars()
     }
 
     pub fn non_zero_proportion(&self) -> f64 {
@@ -0,0 +1,76 @@
+use crate::models::CompletenessWarning;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+/// Recent chunks whose row counts make up a markout's expectation
+pub const COMPLETENESS_WINDOW: usize = 8;
+
+/// Share of its expected rows below which a markout's count is anomalous
+pub const COMPLETENESS_MIN_RATIO: f64 = 0.25;
+
+fn median(values: &mut [f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f64::total_cmp);
+    let middle = values.len() / 2;
+    Some(if values.len().is_multiple_of(2) { (values[middle - 1] + values[middle]) / 2.0 } else { values[middle] })
+}
+
+/// Catches a source silently returning nothing for one markout: each
+/// markout's row count is compared against the median of its recent chunks
+/// and against the median of the chunk's markouts, which fetch the same
+/// blocks and so return similar counts.
+pub struct CompletenessCheck {
+    window: usize,
+    min_ratio: f64,
+    /// Rows per block of each markout's latest unflagged chunks, oldest first
+    history: Mutex<HashMap<String, VecDeque<f64>>>,
+}
+
+impl Default for CompletenessCheck {
+    fn default() -> Self {
+        Self::new(COMPLETENESS_WINDOW, COMPLETENESS_MIN_RATIO)
+    }
+}
+
+impl CompletenessCheck {
+    pub fn new(window: usize, min_ratio: f64) -> Self {
+        Self { window: window.max(1), min_ratio, history: Mutex::new(HashMap::new()) }
+    }
+
+    /// The markouts of `rows`, `(markout time, row count)` pairs, whose
+    /// count is zero or below the minimum ratio of their expectation. The
+    /// other counts join the history; flagged ones are left out so a lasting
+    /// gap does not become the expectation.
+    pub fn check(&self, chunk_start: u64, chunk_end: u64, rows: &[(String, u64)]) -> Vec<CompletenessWarning> {
+        let blocks = chunk_end.saturating_sub(chunk_start).max(1) as f64;
+        let across_markouts = median(&mut rows.iter().map(|(_, count)| *count as f64).collect::<Vec<_>>()).unwrap_or(0.0);
+        let mut history = self.history.lock().unwrap_or_else(|e| e.into_inner());
+
+        let mut warnings = Vec::new();
+        for (markout_time, count) in rows {
+            let recent = history.entry(markout_time.clone()).or_default();
+            let per_block = median(&mut recent.iter().copied().collect::<Vec<_>>()).unwrap_or(0.0);
+            let expected = (per_block * blocks).max(across_markouts);
+
+            let count = *count as f64;
+            if expected > 0.0 && (count == 0.0 || count < expected * self.min_ratio) {
+                warnings.push(CompletenessWarning {
+                    markout_time: markout_time.clone(),
+                    chunk_start,
+                    chunk_end,
+                    rows: count as u64,
+                    expected_rows: expected.round() as u64,
+                });
+                continue;
+            }
+
+            recent.push_back(count / blocks);
+            if recent.len() > self.window {
+                recent.pop_front();
+            }
+        }
+        warnings
+    }
+}
@@ -0,0 +1,64 @@
+use crate::models::{DataSource, MarkoutTime};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// One source query of a chunk
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FetchKey {
+    pub source: DataSource,
+    pub markout_time: MarkoutTime,
+    pub chunk_start: u64,
+    pub chunk_end: u64,
+}
+
+/// Fetched chunk data kept so a retry of the chunk does not query the
+/// source again for what an earlier attempt already got. Holds at most
+/// `capacity` fetches, dropping the least recently used; a chunk's entries
+/// are meant to be invalidated once it no longer needs retrying.
+pub struct FetchCache<V> {
+    capacity: usize,
+    /// Most recently used last
+    entries: Mutex<VecDeque<(FetchKey, V)>>,
+}
+
+impl<V: Clone> FetchCache<V> {
+    /// A cache of capacity 0 keeps nothing
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, entries: Mutex::new(VecDeque::new()) }
+    }
+
+    pub fn get(&self, key: &FetchKey) -> Option<V> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let index = entries.iter().position(|(cached, _)| cached == key)?;
+        let entry = entries.remove(index)?;
+        let value = entry.1.clone();
+        entries.push_back(entry);
+        Some(value)
+    }
+
+    pub fn insert(&self, key: FetchKey, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.retain(|(cached, _)| *cached != key);
+        while entries.len() >= self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back((key, value));
+    }
+
+    /// Drops every fetch of the chunk `chunk_start..chunk_end`
+    pub fn invalidate(&self, chunk_start: u64, chunk_end: u64) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.retain(|(key, _)| key.chunk_start != chunk_start || key.chunk_end != chunk_end);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -1,2 +1,6 @@
+pub mod completeness;
+pub mod fetch_cache;
 pub mod processor;
+pub use completeness::*;
+pub use fetch_cache::*;
 pub use processor::*;
\ No newline at end of file
@@ -1,45 +1,122 @@
 use crate::{
-    api::precompute::PrecomputedWriter, aurora::{AuroraConnection, LVRDetails}, brontes::{BrontesConnection, LVRAnalysis}, config::{AuroraConfig, BrontesConfig}, error::Error, models::{Checkpoint, CheckpointUpdate, ClusterBlockActivity, DataSource, IntervalData, MarkoutTime, UnifiedLVRData},
-     writer::ParallelParquetWriter, 
-     USDeUSDT_DEPLOYMENT, 
-     MARKOUT_TIMES, MARKOUT_TIME_MAPPING, 
-     PEPE_DEPLOYMENT_V2, PEPE_DEPLOYMENT_V3,
-      POOL_ADDRESSES, POOL_NAMES, BRONTES_ADDRESSES, WETH_USDT_100_DEPLOYMENT
+    api::{common::bucket_index, precompute::{PrecomputedWriter, AGGREGATE_POOL_ADDRESS}}, config::{OutputLayout, ParquetWriteOptions, RetryConfig}, error::{is_transient_error, Error}, models::{pool_key, Address, Cents, Checkpoint, CheckpointSnapshot, CheckpointUpdate, ChunkMarkoutTotals, ChunkSummary, ChunkTimings, ClusterBlockActivity, CompletenessWarning, DataSource, IntervalData, MarkoutTime, RawLvrRow, TopLvr, UnifiedLVRData},
+     schema::CHECKPOINT_DIGEST_COLUMN, source::{DatabaseSource, LvrSource}, storage::{parquet_reader, retry_with}, writer::{list_checkpoints, list_interval_files, ParallelParquetWriter, CLUSTER_ACTIVITY_PATH}, 
+     tdigest::TDigestConfig, CompletenessCheck, FetchCache, FetchKey, MetricsRegistry, MARKOUT_TIMES, PoolRegistry
 };
 use anyhow::Result;
 use dashmap::DashMap;
-use ordered_float::OrderedFloat;
-use std::{collections::{HashSet,HashMap}, sync::Arc};
-use tracing::{info, error, warn, debug};
-use object_store::ObjectStore;
-use std::sync::atomic::Ordering;
-use futures::stream::{FuturesOrdered, StreamExt};
+use tokio_util::sync::CancellationToken;
+use std::{collections::{BTreeMap, HashMap, HashSet}, sync::Arc, time::Instant};
+use tracing::{field::Empty, info, info_span, error, warn, debug, Instrument};
+use object_store::{path::Path, ObjectStore};
+use arrow::{array::{StringArray, UInt64Array}, record_batch::RecordBatch};
+use std::sync::atomic::{AtomicU64, Ordering};
+use futures::future::BoxFuture;
+use futures::stream::{FuturesOrdered, StreamExt, TryStreamExt};
 use futures::lock::Mutex;
-use tokio::sync::Barrier;
 use anyhow::Context;
 
 const BLOCKS_PER_DAY: u64 = 7200;
 const INTERVALS_PER_FILE: u64 = 30;
-const BLOCKS_PER_CHUNK: u64 = BLOCKS_PER_DAY * INTERVALS_PER_FILE;
-const MAX_CHUNK_SIZE: usize = 100_000;
+/// Blocks per `intervals/` file; only the file ending at the run's end
+/// block may be shorter
+pub const BLOCKS_PER_CHUNK: u64 = BLOCKS_PER_DAY * INTERVALS_PER_FILE;
+// Activity bitmaps hold a whole chunk; a smaller one flushes mid-chunk and
+// drops the blocks of pools walked after the flush
+const MAX_CHUNK_SIZE: usize = BLOCKS_PER_CHUNK as usize;
+const PRECOMPUTE_CONCURRENCY: usize = 4;
+
+/// Runs after each chunk is committed; an error stops processing
+pub type ValidationCallback = Arc<dyn for<'a> Fn(&'a Arc<dyn ObjectStore>) -> BoxFuture<'a, Result<()>> + Send + Sync>;
+
+/// Runs on a chunk's intervals before anything of the chunk is written, with
+/// the chunk's start and end block; an error stops processing
+pub type PreWriteHook = Arc<dyn for<'a> Fn(&'a [IntervalData], u64, u64) -> BoxFuture<'a, Result<()>> + Send + Sync>;
 
 // Structure to hold processed data before committing
 #[derive(Debug)]
 struct ProcessedData {
-    intervals: Vec<IntervalData>
+    intervals: Vec<IntervalData>,
+    /// Per-block rows of the raw pools, by lowercased address
+    raw_series: BTreeMap<Address, Vec<RawLvrRow>>,
+}
+
+// One markout's theoretical rows of registry pools, by registry address
+#[derive(Clone)]
+struct TheoreticalMarkout {
+    markout_time: MarkoutTime,
+    pool_data: HashMap<Address, Vec<UnifiedLVRData>>,
+    /// Rows the source returned, including those of unknown pools
+    rows: u64,
+    /// Source rows dropped in whole or in part for not parsing
+    malformed_rows: u64,
+}
+
+// What one source query of a chunk returned
+#[derive(Clone)]
+enum CachedFetch {
+    Theoretical(TheoreticalMarkout),
+    Realized(Vec<UnifiedLVRData>),
+}
+
+/// Source queries of one chunk: the theoretical markouts and the realized rows
+fn fetches_per_chunk() -> usize {
+    MARKOUT_TIMES.len() + 1
+}
+
+// A fetched and processed chunk waiting for its turn to be committed
+struct PreparedChunk {
+    chunk_idx: u64,
+    chunk_start: u64,
+    chunk_end: u64,
+    processed_data: ProcessedData,
+    checkpoint_updates: Vec<CheckpointUpdate>,
+    summary: ChunkSummary,
 }
 
 pub struct ParallelLVRProcessor {
     start_block: u64,
-    end_block: u64,
+    /// Lowered at the start of a run when clamping to the sources
+    end_block: AtomicU64,
     checkpoints: Arc<DashMap<(String, MarkoutTime), Checkpoint>>,
     cluster_activity: Arc<DashMap<(String, MarkoutTime), ClusterBlockActivity>>,
-    aurora_connection: Arc<AuroraConnection>,
-    brontes_connection: Arc<BrontesConnection>,
-    parquet_writer: Arc<Mutex<ParallelParquetWriter>>,
-    update_barrier: Arc<Barrier>,
+    source: Arc<dyn LvrSource>,
+    /// Synchronized internally; chunks and checkpoint writes share it
+    parquet_writer: Arc<ParallelParquetWriter>,
+    /// Held while checkpoints are updated or written, so a write never
+    /// clears the dirty flag of an update it did not snapshot
+    checkpoint_lock: Arc<Mutex<()>>,
     object_store: Arc<dyn ObjectStore>,
     max_chunk_size: usize, // For ClusterBlockActivity bit vectors
+    write_options: ParquetWriteOptions,
+    layout: OutputLayout,
+    parallel_chunks: usize,
+    retry_config: RetryConfig,
+    pools: Arc<PoolRegistry>,
+    /// Pools the precompute after the run covers; `pools` when unset
+    precompute_pools: Option<Arc<PoolRegistry>>,
+    pre_write_hook: Option<PreWriteHook>,
+    overwrite: bool,
+    /// Load the stored checkpoints before processing
+    resume: bool,
+    metrics: Option<Arc<MetricsRegistry>>,
+    /// Lowercased addresses whose per-block LVR is written under `raw/`
+    raw_pools: HashSet<Address>,
+    /// Digests of new checkpoints; resumed ones keep their stored settings
+    digest_config: TDigestConfig,
+    /// Stops fetching when cancelled; chunks already being committed finish
+    cancellation: CancellationToken,
+    /// Per-markout row counts of the chunks fetched so far
+    completeness: CompletenessCheck,
+    /// Fail a chunk with a completeness warning instead of committing it
+    strict_source_completeness: bool,
+    /// Fetches of the chunks being prepared, reused by their retries
+    fetch_cache: FetchCache<CachedFetch>,
+    /// End the run at the latest block every source has data for
+    clamp_to_source: bool,
+    /// Merge the registry's pools into existing interval files, leaving
+    /// checkpoints and other pools alone
+    backfill: bool,
 }
 
 impl ParallelLVRProcessor {
@@ -48,100 +125,301 @@ impl ParallelLVRProcessor {
         end_block: u64,
         object_store: Arc<dyn ObjectStore>
     ) -> Result<Self, Error> {
-        let aurora_config = AuroraConfig::from_env()?;
-        let brontes_config = BrontesConfig::from_env()?;
-        
-        let aurora_connection = Arc::new(AuroraConnection::new(aurora_config)?);
-        let brontes_connection = Arc::new(BrontesConnection::new(brontes_config)?);
-        let parquet_writer = Arc::new(Mutex::new(ParallelParquetWriter::new(object_store.clone())));
+        let source = Arc::new(DatabaseSource::from_env()?);
+        let parquet_writer = Arc::new(ParallelParquetWriter::new(object_store.clone()));
 
         Ok(Self {
             start_block,
-            end_block,
+            end_block: AtomicU64::new(end_block),
             checkpoints: Arc::new(DashMap::new()),
             cluster_activity: Arc::new(DashMap::new()),
-            aurora_connection,
-            brontes_connection,
+            source,
             parquet_writer,
-            update_barrier: Arc::new(Barrier::new(1)),
+            checkpoint_lock: Arc::new(Mutex::new(())),
             object_store,
-            max_chunk_size: MAX_CHUNK_SIZE
+            max_chunk_size: MAX_CHUNK_SIZE,
+            write_options: ParquetWriteOptions::default(),
+            layout: OutputLayout::default(),
+            parallel_chunks: 1,
+            retry_config: RetryConfig::default(),
+            pools: Arc::new(PoolRegistry::default()),
+            precompute_pools: None,
+            pre_write_hook: None,
+            overwrite: false,
+            resume: false,
+            metrics: None,
+            raw_pools: HashSet::new(),
+            digest_config: TDigestConfig::default(),
+            cancellation: CancellationToken::new(),
+            completeness: CompletenessCheck::default(),
+            strict_source_completeness: false,
+            clamp_to_source: false,
+            backfill: false,
+            fetch_cache: FetchCache::new(fetches_per_chunk()),
         })
     }
 
-    fn get_deployment_block(&self, pool_address: &str) -> u64 {
-        match pool_address.to_lowercase().as_str() {
-            "0x11950d141ecb863f01007add7d1a342041227b58" => *PEPE_DEPLOYMENT_V3,
-            "0xa43fe16908251ee70ef74718545e4fe6c5ccec9f" => *PEPE_DEPLOYMENT_V2,
-            "0x435664008f38b0650fbc1c9fc971d0a3bc2f1e47" => *USDeUSDT_DEPLOYMENT,
-            "0xc7bbec68d12a0d1830360f8ec58fa599ba1b0e9b" => *WETH_USDT_100_DEPLOYMENT,
-            _ => 0, // Pre-merge pools
-        }
+    pub fn with_write_options(mut self, write_options: ParquetWriteOptions) -> Self {
+        self.write_options = write_options;
+        self.parquet_writer = Arc::new(self.build_writer());
+        self
+    }
+
+    /// Layout interval files are written and read in
+    pub fn with_layout(mut self, layout: OutputLayout) -> Self {
+        self.layout = layout;
+        self.parquet_writer = Arc::new(self.build_writer());
+        self
+    }
+
+    fn build_writer(&self) -> ParallelParquetWriter {
+        ParallelParquetWriter::new(self.object_store.clone())
+            .with_retry_policy(self.retry_config.write.clone())
+            .with_write_options(self.write_options.clone())
+            .with_layout(self.layout)
+    }
+
+    /// Chunks fetched and processed concurrently; commits stay sequential
+    pub fn with_parallel_chunks(mut self, parallel_chunks: usize) -> Self {
+        self.parallel_chunks = parallel_chunks.max(1);
+        self.fetch_cache = FetchCache::new(self.parallel_chunks * fetches_per_chunk());
+        self
+    }
+
+    /// Replaces the Aurora and Brontes databases, which `new` connects to
+    pub fn with_source(mut self, source: Arc<dyn LvrSource>) -> Self {
+        self.source = source;
+        self
+    }
+
+    /// The chunk and write policies apply here; the database policy belongs
+    /// to the source (see `SourceSpec::open`)
+    pub fn with_retry_config(mut self, retry_config: RetryConfig) -> Self {
+        self.retry_config = retry_config;
+        self.parquet_writer = Arc::new(self.build_writer());
+        self
+    }
+
+    pub fn with_pool_registry(mut self, pools: Arc<PoolRegistry>) -> Self {
+        self.pools = pools;
+        self
+    }
+
+    /// Pools the precompute after the run covers, when they differ from the
+    /// processed ones: a backfill processes a few pools, but precomputed
+    /// files cover every pool
+    pub fn with_precompute_pool_registry(mut self, pools: Arc<PoolRegistry>) -> Self {
+        self.precompute_pools = Some(pools);
+        self
+    }
+
+    pub fn with_pre_write_hook(mut self, hook: PreWriteHook) -> Self {
+        self.pre_write_hook = Some(hook);
+        self
+    }
+
+    /// Reprocess chunks whose interval file already exists instead of
+    /// skipping them. The old file is deleted first and nothing is subtracted
+    /// from the checkpoints, so overwriting is only correct in a run that
+    /// starts from fresh checkpoints.
+    pub fn with_overwrite(mut self, overwrite: bool) -> Self {
+        self.overwrite = overwrite;
+        self
+    }
+
+    /// Continue an earlier run: the stored checkpoints, digests included, are
+    /// loaded before processing, and the chunks it wrote are skipped as usual.
+    /// Cluster activity is only stored at the end of a run, so after an
+    /// interrupted one this run's partial activity is not written and
+    /// precompute estimates it from the checkpoints instead. Without it, a
+    /// run over a range only partly processed fails before fetching anything.
+    pub fn with_resume(mut self, resume: bool) -> Self {
+        self.resume = resume;
+        self
+    }
+
+    /// Pools whose per-block LVR is kept beside the intervals, under
+    /// `raw/<pool>/`
+    pub fn with_raw_pools(mut self, raw_pools: &[Address]) -> Self {
+        self.raw_pools = raw_pools.iter().cloned().collect();
+        self
+    }
+
+    /// Scale function and base compression of the digests of checkpoints
+    /// this run creates
+    pub fn with_digest_config(mut self, digest_config: TDigestConfig) -> Self {
+        self.digest_config = digest_config;
+        self
+    }
+
+    /// Cancelling `cancellation` abandons the chunks being fetched, their
+    /// queries and retry delays included, and fails `process_blocks` with
+    /// `Error::Cancelled`. Committed chunks stay written, so the run can be
+    /// continued with `with_resume`.
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    /// Fail a chunk, and with it the run, when a markout's row count looks
+    /// like a silent upstream gap; otherwise the chunk is committed and the
+    /// warning kept in its summary
+    pub fn with_strict_source_completeness(mut self, strict: bool) -> Self {
+        self.strict_source_completeness = strict;
+        self
+    }
+
+    /// End the run at the latest block every source query has data for when
+    /// that is before the end block; the lag is logged either way
+    pub fn with_clamp_to_source(mut self, clamp: bool) -> Self {
+        self.clamp_to_source = clamp;
+        self
+    }
+
+    /// Correct already written chunks for just the registry's pools: each
+    /// chunk's rows for those pools replace theirs in its interval file and
+    /// every other pool's rows are kept. Chunks without an interval file
+    /// fail the run. Checkpoints, chunk summaries and cluster activity are
+    /// not touched, since they cover every pool and the whole history; a
+    /// backfill that changes a pool's totals leaves its checkpoints
+    /// disagreeing with its intervals until the pool is reprocessed.
+    pub fn with_backfill(mut self, backfill: bool) -> Self {
+        self.backfill = backfill;
+        self
+    }
+
+    /// The block the run ends at, after any clamping to the sources
+    pub fn end_block(&self) -> u64 {
+        self.end_block.load(Ordering::Acquire)
+    }
+
+    /// Records every committed chunk's phase timings in `metrics`
+    pub fn with_metrics(mut self, metrics: Arc<MetricsRegistry>) -> Self {
+        self.metrics = Some(metrics);
+        self
     }
 
     pub async fn process_blocks(
         &self,
-        validation_callback: Option<fn(&Arc<dyn ObjectStore>) -> futures::future::BoxFuture<'_, Result<()>>>
+        validation_callback: Option<ValidationCallback>
     ) -> Result<()> {
-        info!("Starting block processing from {} to {}", self.start_block, self.end_block);
-        let total_blocks = self.end_block - self.start_block;
-        let total_chunks = (total_blocks + BLOCKS_PER_CHUNK - 1) / BLOCKS_PER_CHUNK;
-        let mut processed_blocks = 0;
-        
-        for chunk_idx in 0..total_chunks {
-            let chunk_start = self.start_block + (chunk_idx * BLOCKS_PER_CHUNK);
-            let chunk_end = std::cmp::min(chunk_start + BLOCKS_PER_CHUNK, self.end_block);
-            
-            match self.process_chunk_with_retries(chunk_idx, chunk_start, chunk_end, total_chunks).await {
-                Ok(_) => {
-                    processed_blocks += chunk_end - chunk_start;
-                    info!(
-                        "Successfully processed chunk {}/{}, progress: {:.2}% ({}/{} blocks)", 
-                        chunk_idx + 1, total_chunks,
-                        (processed_blocks as f64 / total_blocks as f64) * 100.0,
-                        processed_blocks, total_blocks
-                    );
-    
-                    // Run validation after each chunk if callback is provided
-                    if let Some(validate) = validation_callback {
-                        match validate(&self.object_store).await {
-                            Ok(_) => info!("Validation passed for chunk {}/{}", chunk_idx + 1, total_chunks),
-                            Err(e) => {
-                                error!("Validation failed for chunk {}/{}: {}", chunk_idx + 1, total_chunks, e);
-                                return Err(e);
-                            }
+        self.plan_end_block().await?;
+        info!(
+            "Starting block processing from {} to {} ({} chunks in flight)",
+            self.start_block, self.end_block(), self.parallel_chunks
+        );
+        let total_blocks = self.end_block() - self.start_block;
+        let total_chunks = total_blocks.div_ceil(BLOCKS_PER_CHUNK);
+        if !self.resume && !self.overwrite && !self.backfill {
+            self.check_unprocessed(total_chunks).await?;
+        }
+
+        let mut activity_complete = true;
+        if self.resume {
+            let loaded = self.load_checkpoints().await?;
+            activity_complete = self.load_cluster_activity().await?;
+            info!(
+                "Resuming from {} stored checkpoints ({})",
+                loaded,
+                if activity_complete { "with cluster activity" } else { "no cluster activity stored" }
+            );
+        }
+
+        // Up to `parallel_chunks` chunks are fetched and processed at once;
+        // `buffered` yields them in chunk order, and the consumer commits one
+        // at a time so checkpoints and interval files match a sequential run
+        let (prepared_tx, prepared_rx) = tokio::sync::mpsc::channel(1);
+        let producer = async move {
+            let mut prepared = futures::stream::iter(0..total_chunks)
+                .map(|chunk_idx| {
+                    let (chunk_start, chunk_end) = self.chunk_bounds(chunk_idx);
+                    self.prepare_unless_written(chunk_idx, chunk_start, chunk_end, total_chunks)
+                })
+                .buffered(self.parallel_chunks);
+            while let Some(result) = prepared.next().await {
+                // The consumer hung up after a failure; stop fetching
+                if prepared_tx.send(result).await.is_err() {
+                    break;
+                }
+            }
+        };
+
+        let consumer = async {
+            // Owned, so a failure drops the receiver and stops the producer
+            let mut prepared_rx = prepared_rx;
+            let mut processed_blocks = 0;
+            let mut skipped_chunks = 0;
+            while let Some(result) = prepared_rx.recv().await {
+                let Some(prepared) = result? else {
+                    skipped_chunks += 1;
+                    continue;
+                };
+                let (chunk_idx, chunk_start, chunk_end) = (prepared.chunk_idx, prepared.chunk_start, prepared.chunk_end);
+                self.commit_chunk(prepared).await?;
+
+                processed_blocks += chunk_end - chunk_start;
+                info!(
+                    "Successfully processed chunk {}/{}, progress: {:.2}% ({}/{} blocks)", 
+                    chunk_idx + 1, total_chunks,
+                    (processed_blocks as f64 / total_blocks as f64) * 100.0,
+                    processed_blocks, total_blocks
+                );
+
+                // Run validation after each chunk if callback is provided
+                if let Some(validate) = &validation_callback {
+                    match validate(&self.object_store).await {
+                        Ok(_) => info!("Validation passed for chunk {}/{}", chunk_idx + 1, total_chunks),
+                        Err(e) => {
+                            error!("Validation failed for chunk {}/{}: {}", chunk_idx + 1, total_chunks, e);
+                            return Err(e);
                         }
                     }
-                },
-                Err(e) => return Err(e),
+                }
             }
+            Ok(skipped_chunks)
+        };
+
+        let ((), result) = tokio::join!(producer, consumer);
+        let skipped_chunks = result?;
+
+        if self.backfill {
+            info!(
+                "Backfilled {} chunks for {} pools; checkpoints are unchanged",
+                total_chunks, self.pools.pools().len()
+            );
+            return self.run_precomputation().await;
         }
 
-        // Persist cluster activity data before finalization
-        match self.persist_cluster_activity().await {
-            Ok(_) => info!("Successfully persisted cluster activity data"),
-            Err(e) => {
-                error!("Failed to persist cluster activity data: {}", e);
-                // Don't fail the whole process for this error, just log it
-            }
+        // Nothing was fetched, so the stored checkpoints and precomputed
+        // files are already up to date
+        if skipped_chunks == total_chunks {
+            info!("All {} chunks were already processed; nothing to do", total_chunks);
+            return Ok(());
         }
 
-        // Finalize all checkpoints with delta_final
-        info!("Finalizing checkpoints with delta_final parameter...");
-        for checkpoint in self.checkpoints.iter_mut() {
-            if let Err(e) = checkpoint.value().finalize() {
-                error!("Failed to finalize checkpoint for {}-{}: {}", 
-                    checkpoint.pair_address, checkpoint.markout_time, e);
+        // Persist cluster activity data before finalization
+        if !activity_complete {
+            warn!("Not writing cluster activity: it only covers the chunks of this resumed run");
+        } else {
+            match self.persist_cluster_activity().await {
+                Ok(_) => info!("Successfully persisted cluster activity data"),
+                Err(e) => {
+                    error!("Failed to persist cluster activity data: {}", e);
+                    // Don't fail the whole process for this error, just log it
+                }
             }
         }
 
-        // Write the finalized checkpoints one last time
-        self.write_checkpoints().await?;
+        // Snapshots finalize a copy of each digest with delta_final, so the
+        // live digests keep their partial state for a later --resume. Every
+        // checkpoint is written one last time, dirty or not.
+        info!("Writing final checkpoints...");
+        self.write_checkpoints(true).await?;
         info!("Successfully finalized all checkpoints");
         
         info!(
             "Successfully completed processing all blocks from {} to {}", 
-            self.start_block, self.end_block
+            self.start_block, self.end_block()
         );
 
         // Run precomputation after successful processing
@@ -157,223 +435,487 @@ impl ParallelLVRProcessor {
         Ok(())
     }
 
-    async fn process_chunk_with_retries(
+    /// Fails when some, but not all, of the run's chunks have an interval
+    /// file. They would be skipped while checkpoints covering only the
+    /// others replace the stored ones, which cover them too.
+    async fn check_unprocessed(&self, total_chunks: u64) -> Result<()> {
+        let written: HashSet<(u64, u64)> = list_interval_files(self.object_store.as_ref(), self.layout)
+            .await?
+            .into_iter()
+            .map(|(_, start, end)| (start, end))
+            .collect();
+        let processed = (0..total_chunks).filter(|&i| written.contains(&self.chunk_bounds(i))).count() as u64;
+        if processed > 0 && processed < total_chunks {
+            return Err(Error::Config(format!(
+                "{} of the {} chunks from block {} to {} are already processed. Pass --resume to add the \
+                 others to the stored checkpoints, or --overwrite to reprocess every chunk from fresh checkpoints.",
+                processed, total_chunks, self.start_block, self.end_block()
+            )).into());
+        }
+        Ok(())
+    }
+
+    fn chunk_bounds(&self, chunk_idx: u64) -> (u64, u64) {
+        let chunk_start = self.start_block + (chunk_idx * BLOCKS_PER_CHUNK);
+        (chunk_start, std::cmp::min(chunk_start + BLOCKS_PER_CHUNK, self.end_block()))
+    }
+
+    /// Logs how far short of the end block each source query's data ends
+    /// and, when clamping, ends the run at the earliest of them. Without
+    /// clamping, a source that cannot answer only costs the report.
+    async fn plan_end_block(&self) -> Result<()> {
+        let end_block = self.end_block();
+        let heads = match self.source.latest_blocks().await {
+            Ok(heads) => heads,
+            Err(e) if self.clamp_to_source => {
+                return Err(e.context("Failed to query the latest source blocks to clamp the run to"));
+            }
+            Err(e) => {
+                warn!("Failed to query the latest source blocks: {:#}", e);
+                return Ok(());
+            }
+        };
+        if heads.is_empty() {
+            if self.clamp_to_source {
+                warn!("The source does not report its latest blocks; the run still ends at block {}", end_block);
+            }
+            return Ok(());
+        }
+
+        for head in &heads {
+            match head.latest_block {
+                Some(latest) if latest < end_block => warn!(
+                    "{} has data up to block {}, {} blocks short of the end block {}",
+                    head.name, latest, end_block - latest, end_block
+                ),
+                Some(latest) => info!("{} has data up to block {}", head.name, latest),
+                None => warn!("{} has no data", head.name),
+            }
+        }
+
+        let available = heads.iter().map(|head| head.latest_block.unwrap_or(0)).min().unwrap_or(end_block);
+        if !self.clamp_to_source || available >= end_block {
+            return Ok(());
+        }
+        if available <= self.start_block {
+            return Err(Error::Processing(format!(
+                "Nothing to process: the sources only have data up to block {}, before the start block {}",
+                available, self.start_block
+            )).into());
+        }
+        info!("Clamping the run's end block from {} to {}, the latest block every source has", end_block, available);
+        self.end_block.store(available, Ordering::Release);
+        Ok(())
+    }
+
+    /// `None` when the chunk's interval file already exists and overwriting
+    /// is off; the chunk is then neither fetched nor added to the checkpoints
+    async fn prepare_unless_written(
         &self,
         chunk_idx: u64,
         chunk_start: u64,
         chunk_end: u64,
         total_chunks: u64,
-    ) -> Result<()> {
-        let max_retries = 20;
-        let mut attempt = 0;
+    ) -> Result<Option<PreparedChunk>> {
+        let path = self.layout.interval_path(chunk_start, chunk_end);
+        match self.object_store.head(&path).await {
+            Ok(_) if self.backfill => {}
+            Err(object_store::Error::NotFound { .. }) if self.backfill => {
+                return Err(Error::Processing(format!(
+                    "Cannot backfill chunk {}/{}: {} does not exist. Backfills must cover chunks already \
+                     processed, starting and ending on their boundaries.",
+                    chunk_idx + 1, total_chunks, path
+                )).into());
+            }
+            Ok(_) if !self.overwrite => {
+                info!("Skipping chunk {}/{}: {} already exists", chunk_idx + 1, total_chunks, path);
+                return Ok(None);
+            }
+            Ok(_) => {
+                info!("Overwriting {} for chunk {}/{}", path, chunk_idx + 1, total_chunks);
+                self.object_store.delete(&path).await?;
+            }
+            Err(object_store::Error::NotFound { .. }) => {}
+            Err(e) => return Err(e.into()),
+        }
 
-        loop {
-            attempt += 1;
-            info!(
-                "Processing chunk {}/{} (blocks {} to {}), attempt {}/{}",
-                chunk_idx + 1, total_chunks, chunk_start, chunk_end, attempt, max_retries
-            );
+        self.prepare_chunk_with_retries(chunk_idx, chunk_start, chunk_end, total_chunks)
+            .await
+            .map(Some)
+    }
 
-            match self.process_chunk(chunk_start, chunk_end).await {
-                Ok(_) => break Ok(()),
-                Err(e) => {
-                    if attempt >= max_retries {
-                        error!(
-                            "Chunk {}/{} failed after {} attempts: {}", 
-                            chunk_idx + 1, total_chunks, max_retries, e
-                        );
-                        break Err(e);
-                    }
-                    
-                    let delay = std::time::Duration::from_secs(5 * attempt as u64);
-                    warn!(
-                        "Chunk {}/{} failed (attempt {}/{}): {}. Retrying in {} seconds...",
-                        chunk_idx + 1, total_chunks, attempt, max_retries, e, delay.as_secs()
-                    );
-                    tokio::time::sleep(delay).await;
-                }
+    /// Prepares a chunk under the chunk retry policy. Only transient errors
+    /// are retried; a logic error surfaces on its first occurrence.
+    async fn prepare_chunk_with_retries(
+        &self,
+        chunk_idx: u64,
+        chunk_start: u64,
+        chunk_end: u64,
+        total_chunks: u64,
+    ) -> Result<PreparedChunk> {
+        let policy = &self.retry_config.chunk;
+        let description = format!("chunk {}/{} (blocks {} to {})", chunk_idx + 1, total_chunks, chunk_start, chunk_end);
+        let mut attempts = 0u64;
+
+        let retried = retry_with(policy, &description, is_transient_error, || {
+            attempts += 1;
+            info!("Processing {}, attempt {}/{}", description, attempts, policy.max_attempts);
+            self.prepare_chunk(chunk_idx, chunk_start, chunk_end)
+        });
+        let result = tokio::select! {
+            biased;
+            () = self.cancellation.cancelled() => {
+                warn!("Abandoning {}: the run was cancelled", description);
+                return Err(Error::Cancelled(description).into());
+            }
+            result = retried => result,
+        };
+        // No more attempts will read the chunk's fetches
+        self.fetch_cache.invalidate(chunk_start, chunk_end);
+
+        match result {
+            Ok(mut prepared) => {
+                prepared.summary.retries = attempts - 1;
+                Ok(prepared)
+            }
+            Err(e) => {
+                error!("Chunk {}/{} failed after {} attempts: {}", chunk_idx + 1, total_chunks, attempts, e);
+                Err(e)
             }
         }
     }
 
-    async fn process_chunk(&self, chunk_start: u64, chunk_end: u64) -> Result<()> {
+    /// Fetches and processes a chunk without touching shared state, so any
+    /// number of chunks can be prepared concurrently
+    async fn prepare_chunk(&self, chunk_idx: u64, chunk_start: u64, chunk_end: u64) -> Result<PreparedChunk> {
         // Fetch data from both sources concurrently
-        let (aurora_results, brontes_results) = self.fetch_data(chunk_start, chunk_end).await?;
+        let span = info_span!("fetch_data", chunk_start, chunk_end, aurora_rows = Empty, brontes_rows = Empty);
+        let (aurora_results, brontes_results, mut timings) = self
+            .fetch_data(chunk_start, chunk_end)
+            .instrument(span.clone())
+            .await?;
+        let aurora_rows = aurora_results.iter().map(|markout| markout.rows).sum();
+        let malformed_aurora_rows = aurora_results.iter().map(|markout| markout.malformed_rows).sum();
+        let brontes_rows = brontes_results.len() as u64;
+        span.record("aurora_rows", aurora_rows).record("brontes_rows", brontes_rows);
+        let completeness_warnings = self.check_completeness(chunk_start, chunk_end, &aurora_results)?;
     
         // Process the results but don't update checkpoints yet
+        let span = info_span!("process_results", chunk_start, chunk_end, intervals = Empty, duration_ms = Empty);
+        let started = Instant::now();
         let (processed_data, checkpoint_updates) = self
             .process_results(chunk_start, chunk_end, aurora_results, brontes_results)
+            .instrument(span.clone())
             .await?;
-    
+        timings.process_ms = started.elapsed().as_millis() as u64;
+        span.record("intervals", processed_data.intervals.len()).record("duration_ms", timings.process_ms);
+        let mut source_counts = BTreeMap::new();
+        for (source, count) in processed_data.intervals.iter().flat_map(|interval| &interval.source_counts) {
+            *source_counts.entry(source.clone()).or_insert(0) += count;
+        }
+
+        Ok(PreparedChunk {
+            chunk_idx,
+            chunk_start,
+            chunk_end,
+            processed_data,
+            checkpoint_updates,
+            summary: ChunkSummary {
+                chunk_start,
+                chunk_end,
+                aurora_rows,
+                malformed_aurora_rows,
+                brontes_rows,
+                timings,
+                retries: 0,
+                write_retries: 0,
+                markouts: Vec::new(),
+                completeness_warnings,
+                source_counts,
+            },
+        })
+    }
+
+    /// Markouts the source returned no or anomalously few rows for, failing
+    /// the chunk instead when completeness is strict
+    fn check_completeness(
+        &self,
+        chunk_start: u64,
+        chunk_end: u64,
+        aurora_results: &[TheoreticalMarkout],
+    ) -> Result<Vec<CompletenessWarning>> {
+        let rows: Vec<(String, u64)> = aurora_results
+            .iter()
+            .map(|markout| (markout.markout_time.to_string(), markout.rows))
+            .collect();
+        let warnings = self.completeness.check(chunk_start, chunk_end, &rows);
+        for warning in &warnings {
+            warn!("Possible source gap: {}", warning);
+        }
+        if self.strict_source_completeness && !warnings.is_empty() {
+            let warnings: Vec<String> = warnings.iter().map(ToString::to_string).collect();
+            return Err(Error::Processing(format!("Incomplete source data: {}", warnings.join("; "))).into());
+        }
+        Ok(warnings)
+    }
+
+    /// Writes a prepared chunk's intervals and applies its checkpoint
+    /// updates. Chunks must be committed one at a time, in chunk order.
+    async fn commit_chunk(&self, prepared: PreparedChunk) -> Result<()> {
+        let PreparedChunk { chunk_start, chunk_end, processed_data, checkpoint_updates, mut summary, .. } = prepared;
+        // Chunks commit one at a time, so every retry counted meanwhile is
+        // one of this chunk's writes
+        let write_retries = self.parquet_writer.write_retries();
+
+        if let Some(hook) = &self.pre_write_hook {
+            hook(&processed_data.intervals, chunk_start, chunk_end)
+                .await
+                .with_context(|| format!("Pre-write check failed for chunk {}-{}", chunk_start, chunk_end))?;
+        }
+        if self.backfill {
+            return self.commit_backfill(chunk_start, chunk_end, processed_data).await;
+        }
+
         // Write interval data if needed
-        if chunk_end - chunk_start >= BLOCKS_PER_CHUNK || chunk_end == self.end_block {
-            if !processed_data.intervals.is_empty() {
-                let mut writer = self.parquet_writer.lock().await;
+        if (chunk_end - chunk_start >= BLOCKS_PER_CHUNK || chunk_end == self.end_block())
+            && !processed_data.intervals.is_empty() {
+                let span = info_span!("write_interval_data", chunk_start, chunk_end, rows = processed_data.intervals.len(), duration_ms = Empty);
+                let started = Instant::now();
+                let writer = &self.parquet_writer;
+
+                // Before the interval file, whose existence marks the chunk
+                // as done for a rerun
+                futures::future::try_join_all(processed_data.raw_series.into_iter().map(|(pool_address, rows)| async move {
+                    writer.write_raw_series(&pool_address, rows, chunk_start, chunk_end).await
+                }))
+                .instrument(span.clone())
+                .await?;
                 writer
                     .write_interval_data(processed_data.intervals, chunk_start, chunk_end)
+                    .instrument(span.clone())
                     .await?;
+                summary.timings.interval_write_ms = started.elapsed().as_millis() as u64;
+                span.record("duration_ms", summary.timings.interval_write_ms);
             }
-        }
     
         // Atomically update and write checkpoints
-        self.atomic_checkpoint_update(checkpoint_updates).await?;
+        let span = info_span!("atomic_checkpoint_update", chunk_start, chunk_end, updates = checkpoint_updates.len(), duration_ms = Empty);
+        let started = Instant::now();
+        summary.markouts = self.atomic_checkpoint_update(checkpoint_updates)
+            .instrument(span.clone())
+            .await?;
+        summary.timings.checkpoint_update_ms = started.elapsed().as_millis() as u64;
+        span.record("duration_ms", summary.timings.checkpoint_update_ms);
+        summary.write_retries = self.parquet_writer.write_retries() - write_retries;
 
         self.finalize_cluster_activities().await;
+
+        let timings = &summary.timings;
+        info!(
+            "Chunk {}-{} timings: aurora fetch {}ms, brontes fetch {}ms, process {}ms, interval write {}ms, checkpoint update {}ms",
+            chunk_start, chunk_end, timings.aurora_fetch_ms, timings.brontes_fetch_ms,
+            timings.process_ms, timings.interval_write_ms, timings.checkpoint_update_ms
+        );
+        if let Some(metrics) = &self.metrics {
+            metrics.record_chunk(&summary);
+        }
+
+        // The summary only aids debugging; losing one must not fail a chunk
+        // whose data is already committed
+        if let Err(e) = self.parquet_writer.write_chunk_summary(&summary).await {
+            warn!("Failed to write summary for chunk {}-{}: {}", chunk_start, chunk_end, e);
+        }
     
         Ok(())
     }
     
 
+    /// Writes a backfilled chunk's raw series and merges its intervals into
+    /// the stored interval file
+    async fn commit_backfill(&self, chunk_start: u64, chunk_end: u64, processed_data: ProcessedData) -> Result<()> {
+        let writer = &self.parquet_writer;
+        futures::future::try_join_all(processed_data.raw_series.into_iter().map(|(pool_address, rows)| async move {
+            writer.write_raw_series(&pool_address, rows, chunk_start, chunk_end).await
+        }))
+        .await?;
+        writer
+            .merge_interval_data(processed_data.intervals, chunk_start, chunk_end, &self.pools)
+            .instrument(info_span!("merge_interval_data", chunk_start, chunk_end))
+            .await
+    }
+
     async fn fetch_data(
         &self,
         chunk_start: u64,
         chunk_end: u64,
-    ) -> Result<(Vec<Vec<LVRDetails>>, Vec<LVRAnalysis>)> {
-        // Create concurrent tasks for Aurora
-        let mut aurora_tasks = FuturesOrdered::new();
+    ) -> Result<(Vec<TheoreticalMarkout>, Vec<UnifiedLVRData>, ChunkTimings)> {
+        // Create concurrent tasks for the theoretical markouts
+        let mut theoretical_tasks = FuturesOrdered::new();
         for &time in MARKOUT_TIMES.iter() {
-            let index = *MARKOUT_TIME_MAPPING.get(&OrderedFloat(time))
-                .context("Invalid markout time mapping")?;
-            let task = self.aurora_connection.fetch_lvr_details(index, chunk_start, chunk_end);
-            aurora_tasks.push_back(task);
+            let markout_time = MarkoutTime::from_f64(time).context("Invalid markout time")?;
+            theoretical_tasks.push_back(self.fetch_theoretical_markout(markout_time, chunk_start, chunk_end));
         }
 
-        // Fetch Brontes data concurrently
-        let brontes_task = self.brontes_connection.fetch_lvr_analysis(chunk_start, chunk_end);
+        // Wait for all theoretical results
+        let theoretical = async {
+            let started = Instant::now();
+            let mut theoretical_results = Vec::new();
+            while let Some(result) = theoretical_tasks.next().await {
+                theoretical_results.push(result?);
+            }
+            Ok::<_, anyhow::Error>((theoretical_results, started.elapsed().as_millis() as u64))
+        };
+
+        // Fetch realized data concurrently
+        let realized = async {
+            let started = Instant::now();
+            let realized_results = self.fetch_realized(chunk_start, chunk_end).await?;
+            Ok::<_, anyhow::Error>((realized_results, started.elapsed().as_millis() as u64))
+        };
+
+        let (theoretical, realized) = tokio::join!(theoretical, realized);
+        let (theoretical_results, aurora_ms) = theoretical?;
+        let (realized_results, brontes_ms) = realized?;
+
+        let timings = ChunkTimings {
+            aurora_fetch_ms: aurora_ms,
+            brontes_fetch_ms: brontes_ms,
+            ..ChunkTimings::default()
+        };
+        Ok((theoretical_results, realized_results, timings))
+    }
+
+    /// The chunk's realized rows, from the fetch cache when an earlier
+    /// attempt of the chunk got them
+    async fn fetch_realized(&self, chunk_start: u64, chunk_end: u64) -> Result<Vec<UnifiedLVRData>> {
+        let key = FetchKey { source: DataSource::Brontes, markout_time: MarkoutTime::Brontes, chunk_start, chunk_end };
+        if let Some(CachedFetch::Realized(rows)) = self.fetch_cache.get(&key) {
+            debug!("Reusing the realized rows of chunk {}-{} from an earlier attempt", chunk_start, chunk_end);
+            return Ok(rows);
+        }
+        let rows = self.source.fetch_realized(&self.pools, chunk_start, chunk_end).await?;
+        self.fetch_cache.insert(key, CachedFetch::Realized(rows.clone()));
+        Ok(rows)
+    }
 
-        // Wait for all Aurora results
-        let mut aurora_results = Vec::new();
-        while let Some(result) = aurora_tasks.next().await {
-            aurora_results.push(result?);
+    /// Folds a markout's theoretical rows into per-pool vectors as the
+    /// source streams them, keyed by address. Rows of pools outside the
+    /// registry are dropped on arrival.
+    /// A markout an earlier attempt of the chunk fetched comes from the
+    /// fetch cache.
+    async fn fetch_theoretical_markout(
+        &self,
+        markout_time: MarkoutTime,
+        chunk_start: u64,
+        chunk_end: u64,
+    ) -> Result<TheoreticalMarkout> {
+        let key = FetchKey { source: DataSource::Aurora, markout_time, chunk_start, chunk_end };
+        if let Some(CachedFetch::Theoretical(markout)) = self.fetch_cache.get(&key) {
+            debug!("Reusing markout {} of chunk {}-{} from an earlier attempt", markout_time, chunk_start, chunk_end);
+            return Ok(markout);
         }
 
-        // Wait for Brontes results
-        let brontes_results = brontes_task.await?;
+        let mut stream = self.source
+            .stream_theoretical(&self.pools, markout_time, chunk_start, chunk_end)
+            .await?;
+
+        let mut markout = TheoreticalMarkout { markout_time, pool_data: HashMap::new(), rows: 0, malformed_rows: 0 };
+        while let Some(data) = stream.rows.try_next().await? {
+            markout.rows += 1;
+            if self.pools.contains(&data.pool_address) {
+                markout.pool_data.entry(data.pool_address.clone()).or_default().push(data);
+            }
+        }
 
-        Ok((aurora_results, brontes_results))
+        markout.malformed_rows = stream.malformed_rows();
+        if markout.malformed_rows > 0 {
+            warn!(
+                "{} source rows of markout {} in chunk {}-{} did not parse",
+                markout.malformed_rows, markout_time, chunk_start, chunk_end
+            );
+        }
+        self.fetch_cache.insert(key, CachedFetch::Theoretical(markout.clone()));
+        Ok(markout)
     }
 
     async fn process_results(
         &self,
         chunk_start: u64,
         chunk_end: u64,
-        aurora_results: Vec<Vec<LVRDetails>>,
-        brontes_results: Vec<LVRAnalysis>
+        theoretical_results: Vec<TheoreticalMarkout>,
+        realized_results: Vec<UnifiedLVRData>
     ) -> Result<(ProcessedData, Vec<CheckpointUpdate>)> {
-        let unified_data = DashMap::new();
+        let mut unified_data = HashMap::new();
         let mut checkpoint_updates = Vec::new();
         let mut successful_intervals = Vec::new();
+        let mut raw_series: BTreeMap<Address, Vec<RawLvrRow>> = BTreeMap::new();
     
-        // Process Aurora data
-        for (markout_idx, aurora_markout_data) in aurora_results.into_iter().enumerate() {
-            let markout_time = MarkoutTime::from_f64(MARKOUT_TIMES[markout_idx])
-                .context("Invalid markout time")?;
-    
-            for pool_address in POOL_ADDRESSES.iter() {
-                let pool_name = POOL_NAMES.get(*pool_address)
-                    .context("Unknown pool address")?;
-    
-                let aurora_data: Vec<UnifiedLVRData> = aurora_markout_data.iter()
-                    .filter_map(|detail| {
-                        self.parse_lvr_details(&detail.details, pool_name)
-                            .and_then(|lvr| {
-                                self.to_cents(lvr).ok().map(|cents| UnifiedLVRData {
-                                    block_number: detail.block_number,
-                                    lvr_cents: cents,
-                                    source: DataSource::Aurora,
-                                })
-                            })
-                    })
-                    .collect();
-    
-                if !aurora_data.is_empty() {
-                    unified_data.insert((pool_address.to_string(), markout_time), aurora_data);
-                }
+        // Theoretical data arrives already grouped by pool
+        for markout in theoretical_results {
+            for (pool_address, data) in markout.pool_data {
+                unified_data.insert((pool_address, markout.markout_time), data);
             }
         }
     
-        // Process Brontes data
-        let mut brontes_data: HashMap<String, Vec<UnifiedLVRData>> = HashMap::new();
+        // Process realized data
+        let mut brontes_data: HashMap<Address, Vec<UnifiedLVRData>> = HashMap::new();
     
         // First, collect all actual Brontes events
-        for result in brontes_results {
-            if result.block_number >= chunk_start && result.block_number < chunk_end {
-                if let Ok(cents) = self.to_cents(result.lvr) {
-                    brontes_data
-                        .entry(result.pool_address.to_lowercase())
-                        .or_default()
-                        .push(UnifiedLVRData {
-                            block_number: result.block_number,
-                            lvr_cents: cents,
-                            source: DataSource::Brontes,
-                        });
-                }
+        for data in realized_results {
+            if data.block_number >= chunk_start && data.block_number < chunk_end {
+                brontes_data.entry(data.pool_address.clone()).or_default().push(data);
             }
         }
     
-        // Process each Brontes pool
-        for pool_address in BRONTES_ADDRESSES.iter() {
-            // Get or create vector for this pool
+        // Brontes only reports blocks with events. Blocks without one are
+        // zeros, which the interval and checkpoint updates count from the
+        // block range, so they are never materialized.
+        for pool_address in self.pools.brontes_addresses() {
             let mut pool_data = brontes_data
-                .remove(*pool_address)
+                .remove(pool_address)
                 .unwrap_or_default();
-            
-            // Sort by block number for efficient lookup
+
+            // The sort is stable, so the first event reported for a block wins
             pool_data.sort_by_key(|data| data.block_number);
-            let event_blocks: HashSet<u64> = pool_data
-                .iter()
-                .map(|data| data.block_number)
-                .collect();
-    
-            // Add zeros for blocks without events
-            let mut complete_data = Vec::with_capacity((chunk_end - chunk_start) as usize);
-            
-            // Add existing events and zeros in sorted order
-            for block in chunk_start..chunk_end {
-                if event_blocks.contains(&block) {
-                    let event = pool_data
-                        .iter()
-                        .find(|data| data.block_number == block)
-                        .unwrap()
-                        .clone();
-                    complete_data.push(event);
-                } else {
-                    complete_data.push(UnifiedLVRData {
-                        block_number: block,
-                        lvr_cents: 0,
-                        source: DataSource::Brontes,
-                    });
-                }
-            }
-    
-            // Insert into unified data (we'll always have at least zeros)
+            pool_data.dedup_by_key(|data| data.block_number);
+
+            // Inserted even when empty so quiet pools still count their zeros
             unified_data.insert(
-                (pool_address.to_string(), MarkoutTime::Brontes),
-                complete_data
+                (pool_address.clone(), MarkoutTime::Brontes),
+                pool_data
             );
         }
     
-        // Process all data
-        for entry in unified_data.iter() {
-            let (key, data) = entry.pair();
-            let (pool_address, markout_time) = key;
-            
+        // The aggregate checkpoint's digest depends on the order pools'
+        // updates reach it, so they are made in a fixed order
+        let mut unified_data: Vec<_> = unified_data.into_iter().collect();
+        unified_data.sort_by_cached_key(|((pool_address, markout_time), _)| (markout_time.to_string(), pool_address.clone()));
+
+        for ((pool_address, markout_time), data) in &unified_data {
+
             // Add checkpoint update
             checkpoint_updates.push(CheckpointUpdate {
                 pool_address: pool_address.clone(),
-                markout_time: markout_time.clone(),
+                markout_time: *markout_time,
                 data: data.clone(),
                 chunk_start,
                 chunk_end,
             });
     
+            if self.raw_pools.contains(pool_address) {
+                let rows = block_values(&self.pools, chunk_start, chunk_end, pool_address, data)
+                    .into_iter()
+                    .map(|(block_number, lvr_cents)| RawLvrRow { block_number, markout_time: *markout_time, lvr_cents });
+                raw_series.entry(pool_address.clone()).or_default().extend(rows);
+            }
+
             // Calculate intervals
             match self.calculate_interval_metrics(
                 chunk_start,
                 chunk_end,
-                &pool_address,
-                markout_time.clone(),
-                &data,
+                pool_address,
+                *markout_time,
+                data,
             ) {
                 Ok(intervals) => successful_intervals.extend(intervals),
                 Err(e) => return Err(anyhow::anyhow!(
@@ -384,222 +926,104 @@ impl ParallelLVRProcessor {
         }
     
         Ok((
-            ProcessedData { intervals: successful_intervals },
+            ProcessedData { intervals: successful_intervals, raw_series },
             checkpoint_updates
         ))
     }
 
-    async fn atomic_checkpoint_update(&self, updates: Vec<CheckpointUpdate>) -> Result<()> {
-        // Apply all updates atomically
-        for update in updates {
-            self.update_checkpoint(
-                &update.pool_address,
-                update.markout_time,
-                &update.data,
-                update.chunk_start,
-                update.chunk_end,
-            ).await?;
+    fn checkpoint_state(&self) -> CheckpointState {
+        CheckpointState {
+            pools: Arc::clone(&self.pools),
+            checkpoints: Arc::clone(&self.checkpoints),
+            cluster_activity: Arc::clone(&self.cluster_activity),
+            digest_config: self.digest_config,
+            max_chunk_size: self.max_chunk_size,
         }
+    }
+
+    /// Applies a chunk's updates and writes the checkpoints they changed.
+    /// Returns what the updates added to the checkpoints, per markout time.
+    pub async fn atomic_checkpoint_update(&self, updates: Vec<CheckpointUpdate>) -> Result<Vec<ChunkMarkoutTotals>> {
+        let guard = Arc::clone(&self.checkpoint_lock).lock_owned().await;
+
+        // The apply is CPU-bound, so it runs on a blocking thread instead of
+        // a runtime worker. The guard goes with it, so a dropped caller
+        // cannot let a write in before the apply finishes.
+        let state = self.checkpoint_state();
+        let (_guard, totals) = tokio::task::spawn_blocking(move || (guard, state.apply(updates)))
+            .await
+            .map_err(|_| Error::Processing("Checkpoint update worker panicked".to_string()))?;
+        let totals = totals?;
         
         // Write all updates at once
-        self.write_checkpoints().await?;
+        self.write_checkpoints_locked(false).await?;
         
-        Ok(())
+        Ok(totals.into_iter()
+            .map(|(markout_time, (total_lvr_cents, non_zero_count))| ChunkMarkoutTotals {
+                markout_time,
+                total_lvr_cents,
+                non_zero_count,
+            })
+            .collect())
     }
 
-    async fn write_checkpoints(&self) -> Result<()> {
-        // Log the start of checkpoint writing
-        info!("Starting to write checkpoints.");
-    
-        // Wait for any in-flight updates to complete
-        let barrier = self.update_barrier.clone();
-    
-        // Spawn a task that waits for all updates
-        let barrier_wait = tokio::spawn(async move {
-            debug!("Waiting for the update barrier to synchronize.");
-            barrier.wait().await;
-        });
-    
-        // Wait for the barrier
-        barrier_wait.await?;
-    
-        // Now safely collect and write checkpoints
-        let checkpoints: Vec<_> = self
-            .checkpoints
-            .iter()
-            .map(|entry| entry.value().to_snapshot())
-            .collect();
-    
-        debug!(
-            "Collected {} checkpoints to write.",
-            checkpoints.len()
-        );
-    
-        let mut writer = self.parquet_writer.lock().await;
-        writer.write_checkpoints(checkpoints).await?;
-    
-        // Log the successful completion of checkpoint writing
-        info!("Successfully wrote checkpoints.");
-    
-        Ok(())
+    /// Writes the checkpoints changed since they were last written, or all
+    /// of them when `force` is set
+    pub async fn write_checkpoints(&self, force: bool) -> Result<()> {
+        let _guard = self.checkpoint_lock.lock().await;
+        self.write_checkpoints_locked(force).await
     }
-    
 
+    /// `write_checkpoints` for callers already holding `checkpoint_lock`
+    async fn write_checkpoints_locked(&self, force: bool) -> Result<()> {
+        info!("Starting to write checkpoints.");
 
-    fn to_cents(&self, value: f64) -> Result<u64> {
-        let cents = (value * 100.0).round();
-        
-        if cents > u64::MAX as f64 || cents < u64::MIN as f64 {
-            return Err(Error::Processing(
-                format!("LVR value {} too large for u64 cents representation", value)
-            ).into());
+        // Clearing the flag before the snapshot keeps an update that lands
+        // in between dirty, so it is written again next time
+        let mut keys = Vec::new();
+        let mut checkpoints = Vec::new();
+        for entry in self.checkpoints.iter() {
+            if entry.value().dirty.swap(false, Ordering::AcqRel) || force {
+                keys.push(entry.key().clone());
+                checkpoints.push(entry.value().to_snapshot());
+            }
         }
-        Ok(cents as u64)
-    }
 
-    async fn update_checkpoint(
-        &self,
-        pool_address: &str,
-        markout_time: MarkoutTime,
-        data: &[UnifiedLVRData],
-        chunk_start: u64,
-        chunk_end: u64,
-    ) -> Result<()> {
-        let deployment_block = self.get_deployment_block(pool_address);
-        let effective_start = chunk_start.max(deployment_block);
-    
-        if effective_start >= chunk_end {
+        debug!(
+            "Collected {} of {} checkpoints to write.",
+            checkpoints.len(),
+            self.checkpoints.len()
+        );
+        if checkpoints.is_empty() {
             return Ok(());
         }
-        
-        // Get cluster name for this pool (if it belongs to a cluster)
-        let cluster_name = crate::get_cluster_name(&pool_address.to_lowercase())
-            .map(|name| name.to_string());
-    
-        let checkpoint = self.checkpoints
-            .entry((pool_address.to_string(), markout_time.clone()))
-            .or_insert_with(|| Checkpoint::new(pool_address.to_string(), markout_time.clone()));
-    
-        // Create a map of block numbers to data points for efficient lookup
-        let block_data: HashMap<u64, &UnifiedLVRData> = data.iter()
-            .filter(|d| d.block_number >= effective_start && d.block_number < chunk_end)
-            .map(|d| (d.block_number, d))
-            .collect();
-    
-        let mut updates = 0;
-        let mut max_lvr = 0u64;
-        let mut max_lvr_block = 0u64;
-        let mut running_total = 0i64;
-        let mut bucket_counts = [0u64; 7];  // Array for all bucket counts
-        let mut non_zero_values = Vec::new();
-    
-        // Process each block in the range
-        for block_number in effective_start..chunk_end {
-            updates += 1;
-            let mut has_nonzero_lvr = false;
-    
-            if let Some(data_point) = block_data.get(&block_number) {
-                let lvr_cents = data_point.lvr_cents;
-                
-                // Update running statistics
-                running_total += lvr_cents as i64;
-                
-                // Update max LVR if needed
-                if lvr_cents > max_lvr {
-                    max_lvr = lvr_cents;
-                    max_lvr_block = block_number;
-                }
-    
-                // Collect non-zero values for TDigest and track for cluster activity
-                if lvr_cents > 0 {
-                    non_zero_values.push(lvr_cents as f64 / 100.0);  // Convert to dollars for TDigest
-                    has_nonzero_lvr = true;
-                }
-    
-                // Update bucket counts
-                let dollars = lvr_cents as f64 / 100.0;
-                let bucket_idx = match dollars {
-                    x if x == 0.0 => 0,
-                    x if x <= 10.0 => 1,
-                    x if x <= 100.0 => 2,
-                    x if x <= 500.0 => 3,
-                    x if x <= 1000.0 => 4,
-                    x if x <= 10000.0 => 5,
-                    _ => 6,
-                };
-                bucket_counts[bucket_idx] += 1;
-            } else {
-                // Count zero values
-                bucket_counts[0] += 1;
-            }
-    
-            // Update cluster activity tracking if this pool belongs to a cluster
-            if let Some(ref cluster) = cluster_name {
-                let key = (cluster.clone(), markout_time.clone());
-                // Using the cluster_activity DashMap with get_mut
-                if let Some(mut activity) = self.cluster_activity.get_mut(&key) {
-                    // Use the bit vector-based method to process this block
-                    activity.process_block(block_number, has_nonzero_lvr);
-                } else {
-                    // First time seeing this cluster+markout combination, initialize it
-                    let mut activity = ClusterBlockActivity::new(
-                        cluster.clone(), 
-                        markout_time.clone(), 
-                        chunk_start,
-                        self.max_chunk_size
-                    );
-                    activity.process_block(block_number, has_nonzero_lvr);
-                    self.cluster_activity.insert(key, activity);
-                }
-            }
-        }
-    
-        if updates > 0 {
-            // Update max LVR
-            checkpoint.update_max_lvr(max_lvr_block, max_lvr);
-            
-            // Update running total
-            checkpoint.running_total.fetch_add(running_total, Ordering::Release);
-    
-            // Update bucket counts atomically
-            let bucket_refs = [
-                &checkpoint.total_bucket_0,
-                &checkpoint.total_bucket_0_10,
-                &checkpoint.total_bucket_10_100,
-                &checkpoint.total_bucket_100_500,
-                &checkpoint.total_bucket_500_1000,
-                &checkpoint.total_bucket_1000_10000,
-                &checkpoint.total_bucket_10000_plus,
-            ];
-    
-            for (count, bucket) in bucket_counts.iter().zip(bucket_refs.iter()) {
-                bucket.fetch_add(*count, Ordering::Release);
-            }
-    
-            // Update TDigest with non-zero values
-            if let Ok(mut digest) = checkpoint.digest.lock() {
-                for value in non_zero_values {
-                    digest.add(value);
+
+        if let Err(e) = self.parquet_writer.write_checkpoints(checkpoints).await {
+            // Some of these may have been written; retrying all is harmless
+            for key in keys {
+                if let Some(checkpoint) = self.checkpoints.get(&key) {
+                    checkpoint.dirty.store(true, Ordering::Release);
                 }
             }
-    
-            // Update last processed block
-            checkpoint.last_updated_block.fetch_max(chunk_end - 1, Ordering::Release);
+            return Err(e);
         }
-    
+
+        info!("Successfully wrote checkpoints.");
         Ok(())
     }
 
-    fn calculate_interval_metrics(
+    /// Per-interval totals of one pool's data for a chunk. `data` only needs
+    /// the blocks with a value; every other block counts as zero.
+    pub fn calculate_interval_metrics(
         &self,
         chunk_start: u64,
         chunk_end: u64,
-        pool_address: &str,
+        pool_address: &Address,
         markout_time: MarkoutTime,
         data: &[UnifiedLVRData],
     ) -> Result<Vec<IntervalData>> {
         let blocks_per_interval = BLOCKS_PER_DAY;
-        let deployment_block = self.get_deployment_block(pool_address);
+        let deployment_block = self.pools.deployment_block(pool_address.as_str());
     
         // Adjust chunk boundaries based on deployment block
         let effective_chunk_start = chunk_start.max(deployment_block);
@@ -609,67 +1033,53 @@ impl ParallelLVRProcessor {
             return Ok(Vec::new());
         }
     
-        // Create map to store data for each block
-        let block_data: DashMap<u64, u64> = DashMap::new();
-        
-        // Map all available data points within effective range
-        data.iter()
+        let block_data = block_values(&self.pools, chunk_start, chunk_end, pool_address, data);
+
+        // Every interval with at least one block after deployment
+        let first_interval = (effective_chunk_start - chunk_start) / blocks_per_interval;
+        let last_interval = (chunk_end - 1 - chunk_start) / blocks_per_interval;
+
+        // Blocks with a value in each interval by the source of that value,
+        // which like the value itself is the last row given for the block
+        let block_sources: BTreeMap<u64, &DataSource> = data.iter()
             .filter(|d| d.block_number >= effective_chunk_start && d.block_number < chunk_end)
-            .for_each(|data_point| {
-                block_data.insert(data_point.block_number, data_point.lvr_cents);
-            });
-    
-        // Create interval groups with explicit zero handling
-        let interval_groups: DashMap<u64, Vec<(u64, u64)>> = DashMap::new();
-        
-        // Process each block in range, mapping to intervals and tracking block numbers
-        for block_number in effective_chunk_start..chunk_end {
-            let interval_id = (block_number - chunk_start) / blocks_per_interval;
-            let value = block_data.get(&block_number).map(|v| *v).unwrap_or(0);
-            
-            interval_groups
-                .entry(interval_id)
-                .and_modify(|v| v.push((block_number, value)))
-                .or_insert_with(|| vec![(block_number, value)]);
+            .map(|d| (d.block_number, &d.source))
+            .collect();
+        let mut source_counts = vec![BTreeMap::new(); (last_interval - first_interval + 1) as usize];
+        for (block_number, &source) in &block_sources {
+            let interval = (block_number - chunk_start) / blocks_per_interval - first_interval;
+            *source_counts[interval as usize].entry(source.clone()).or_insert(0) += 1;
         }
-    
-        // Calculate metrics for each interval
-        let result: Vec<_> = interval_groups
-            .into_iter()
-            .map(|(interval_id, blocks)| {
+
+        let result: Vec<_> = (first_interval..=last_interval)
+            .zip(source_counts)
+            .map(|(interval_id, source_counts)| {
                 // Calculate interval boundaries
                 let interval_start = chunk_start + (interval_id * blocks_per_interval);
                 let interval_end = (interval_start + blocks_per_interval).min(chunk_end);
                 
-                // Calculate effective range for this interval
+                // Only count blocks after deployment
                 let effective_interval_start = interval_start.max(deployment_block);
-                
-                // Count total blocks in effective range
-                let total_count = if effective_interval_start >= interval_end {
-                    0
-                } else {
-                    // Only count blocks after deployment
-                    blocks.iter()
-                        .filter(|(block_number, _)| *block_number >= effective_interval_start)
-                        .count() as u64
-                };
+                let total_count = interval_end - effective_interval_start;
     
                 // Count non-zero values in effective range
-                let non_zero_values: Vec<_> = blocks.iter()
-                    .filter(|(block_number, value)| {
-                        *block_number >= effective_interval_start && *value > 0
-                    })
-                    .map(|(_, value)| *value)
+                let non_zero_values: Vec<_> = block_data
+                    .range(effective_interval_start..interval_end)
+                    .map(|(_, &value)| value)
+                    .filter(|&value| value != Cents::ZERO)
                     .collect();
     
                 IntervalData {
                     interval_id,
                     pair_address: pool_address.to_string(),
-                    markout_time: markout_time.clone(),
+                    markout_time,
                     total_lvr_cents: non_zero_values.iter().sum(),
-                    max_lvr_cents: non_zero_values.iter().copied().max().unwrap_or(0),
+                    max_lvr_cents: non_zero_values.iter().copied().max().unwrap_or(Cents::ZERO).positive_part(),
                     non_zero_count: non_zero_values.len() as u64,
                     total_count,
+                    start_block: effective_interval_start,
+                    end_block: interval_end,
+                    source_counts,
                 }
             })
             .collect();
@@ -677,6 +1087,79 @@ impl ParallelLVRProcessor {
         Ok(result)
     }
 
+    /// Reads a parquet file from the output store
+    async fn read_stored_file(&self, path: &Path) -> Result<Vec<RecordBatch>> {
+        let bytes = self.object_store.get(path).await?.bytes().await?;
+        parquet_reader(bytes, path, 1024)?
+            .collect::<Result<Vec<_>, _>>()
+            .with_context(|| format!("Failed to read {}", path))
+    }
+
+    /// Loads the stored checkpoints into memory, returning how many
+    async fn load_checkpoints(&self) -> Result<usize> {
+        let paths = list_checkpoints(self.object_store.as_ref()).await?;
+        let mut loaded = 0;
+        for path in paths {
+            for batch in self.read_stored_file(&path).await? {
+                let has_digest = batch.schema().column_with_name(CHECKPOINT_DIGEST_COLUMN).is_some();
+                for snapshot in CheckpointSnapshot::from_record_batch(&batch)
+                    .with_context(|| format!("Failed to read checkpoint {}", path))?
+                {
+                    if !has_digest && snapshot.non_zero_samples > 0 {
+                        warn!("Checkpoint {} has no stored digest; its quantiles only cover this run", path);
+                    }
+                    // The registry's deployment block replaces the stored one,
+                    // and older checkpoints' processed range is inferred
+                    let deployment_block = self.pools.deployment_block(&snapshot.pair_address);
+                    let (first_processed_block, blocks_processed) = snapshot.processed_blocks();
+                    self.checkpoints.insert(
+                        (pool_key(&snapshot.pair_address), snapshot.markout_time),
+                        Checkpoint::from_snapshot(&snapshot)
+                            .with_deployment_block(deployment_block)
+                            .with_processed_blocks(first_processed_block, blocks_processed),
+                    );
+                    loaded += 1;
+                }
+            }
+        }
+        Ok(loaded)
+    }
+
+    /// Seeds cluster activity from the stored activity file; false when
+    /// there is none
+    async fn load_cluster_activity(&self) -> Result<bool> {
+        let path = Path::from(CLUSTER_ACTIVITY_PATH);
+        let batches = match self.object_store.head(&path).await {
+            Ok(_) => self.read_stored_file(&path).await?,
+            Err(object_store::Error::NotFound { .. }) => return Ok(false),
+            Err(e) => return Err(e.into()),
+        };
+
+        for batch in batches {
+            let column = |name: &str| batch.column_by_name(name)
+                .with_context(|| format!("Cluster activity is missing {}", name));
+            let clusters = column("cluster_name")?.as_any().downcast_ref::<StringArray>().context("cluster_name is not Utf8")?;
+            let markouts = column("markout_time")?.as_any().downcast_ref::<StringArray>().context("markout_time is not Utf8")?;
+            let totals = column("total_blocks")?.as_any().downcast_ref::<UInt64Array>().context("total_blocks is not UInt64")?;
+            let non_zero = column("non_zero_blocks")?.as_any().downcast_ref::<UInt64Array>().context("non_zero_blocks is not UInt64")?;
+
+            for i in 0..batch.num_rows() {
+                let Some(markout_time) = MARKOUT_TIMES.iter()
+                    .filter_map(|&markout| MarkoutTime::from_f64(markout))
+                    .chain([MarkoutTime::Brontes])
+                    .find(|markout| markout.to_string() == markouts.value(i))
+                else {
+                    warn!("Skipping cluster activity with unknown markout {}", markouts.value(i));
+                    continue;
+                };
+                let activity = ClusterBlockActivity::new(clusters.value(i).to_string(), markout_time, self.start_block, self.max_chunk_size)
+                    .with_accumulated(totals.value(i), non_zero.value(i));
+                self.cluster_activity.insert((clusters.value(i).to_string(), markout_time), activity);
+            }
+        }
+        Ok(true)
+    }
+
     async fn persist_cluster_activity(&self) -> Result<()> {
         info!("Persisting cluster activity data");
         
@@ -685,8 +1168,7 @@ impl ParallelLVRProcessor {
             return Ok(());
         }
         
-        let writer = self.parquet_writer.lock().await;
-        writer.write_cluster_activity(&self.cluster_activity).await?;
+        self.parquet_writer.write_cluster_activity(&self.cluster_activity).await?;
         
         info!("Successfully persisted cluster activity data");
         Ok(())
@@ -703,76 +1185,209 @@ impl ParallelLVRProcessor {
     pub async fn run_precomputation(&self) -> Result<()> {
         info!("Starting precomputation phase...");
         
-        let precomputed_writer = PrecomputedWriter::new(self.object_store.clone());
-        
-        // Run all precomputation methods sequentially
-        precomputed_writer.write_running_totals().await?;
-        info!("Completed running totals precomputation");
-        
-        precomputed_writer.write_pool_totals().await?;
-        info!("Completed pool totals precomputation");
-        
-        precomputed_writer.write_max_lvr().await?;
-        info!("Completed max LVR precomputation");
-        
-        precomputed_writer.write_non_zero_proportions().await?;
-        info!("Completed non-zero proportions precomputation");
-        
-        precomputed_writer.write_histograms().await?;
-        info!("Completed histograms precomputation");
-        
-        precomputed_writer.write_percentile_bands().await?;
-        info!("Completed percentile bands precomputation");
-        
-        precomputed_writer.write_quartile_plots().await?;
-        info!("Completed quartile plots precomputation");
-        
-        precomputed_writer.write_daily_time_series().await?;
-        info!("Completed daily time series precomputation");
-        
-        precomputed_writer.write_cluster_proportions().await?;
-        info!("Completed cluster proportions precomputation");
-        
-        precomputed_writer.write_cluster_histograms().await?;
-        info!("Completed cluster histograms precomputation");
-        
-        precomputed_writer.write_monthly_cluster_totals().await?;
-        info!("Completed monthly cluster totals precomputation");
-    
-        precomputed_writer.write_distribution_metrics().await?;
-        info!("Completed distribution metrics precomputation");
+        let precomputed_writer = PrecomputedWriter::new(self.object_store.clone())
+            .with_write_options(self.write_options.clone())
+            .with_retry_policy(self.retry_config.write.clone())
+            .with_layout(self.layout)
+            .with_pool_registry(Arc::clone(self.precompute_pools.as_ref().unwrap_or(&self.pools)));
+        precomputed_writer.run_all(PRECOMPUTE_CONCURRENCY).await?;
     
         info!("Successfully completed all metric precomputations");
         Ok(())
     }
+}
+
+/// What applying checkpoint updates touches, shared with the processor so
+/// the apply can run off the runtime
+struct CheckpointState {
+    pools: Arc<PoolRegistry>,
+    checkpoints: Arc<DashMap<(String, MarkoutTime), Checkpoint>>,
+    cluster_activity: Arc<DashMap<(String, MarkoutTime), ClusterBlockActivity>>,
+    digest_config: TDigestConfig,
+    max_chunk_size: usize,
+}
+
+impl CheckpointState {
+    /// Applies `updates`, returning what they added per markout time
+    fn apply(&self, updates: Vec<CheckpointUpdate>) -> Result<BTreeMap<String, (Cents, u64)>> {
+        // A markout's updates share its aggregate checkpoint and cluster
+        // activity, whose digests and bit vectors depend on the order values
+        // arrive in. Each markout is applied in order by one worker while the
+        // markouts run in parallel, so the result matches applying `updates`
+        // one at a time; `process_results` lists them in a fixed order.
+        let mut by_markout: BTreeMap<String, Vec<CheckpointUpdate>> = BTreeMap::new();
+        for update in updates {
+            by_markout.entry(update.markout_time.to_string()).or_default().push(update);
+        }
+        let worker_count = std::thread::available_parallelism()
+            .map_or(1, |n| n.get())
+            .min(by_markout.len())
+            .max(1);
+        let mut workers: Vec<Vec<(String, Vec<CheckpointUpdate>)>> = (0..worker_count).map(|_| Vec::new()).collect();
+        for (i, group) in by_markout.into_iter().enumerate() {
+            workers[i % worker_count].push(group);
+        }
+
+        let results = std::thread::scope(|scope| {
+            let handles: Vec<_> = workers.into_iter()
+                .map(|groups| scope.spawn(move || {
+                    groups.into_iter()
+                        .map(|(markout_time, updates)| {
+                            let mut markout_totals = (Cents::ZERO, 0u64);
+                            for update in &updates {
+                                let (total_lvr_cents, non_zero_count) = self.update_checkpoint(
+                                    &update.pool_address,
+                                    update.markout_time,
+                                    &update.data,
+                                    update.chunk_start,
+                                    update.chunk_end,
+                                )?;
+                                markout_totals.0 += total_lvr_cents;
+                                markout_totals.1 += non_zero_count;
+                            }
+                            Ok((markout_time, markout_totals))
+                        })
+                        .collect::<Result<Vec<_>>>()
+                }))
+                .collect();
+            handles.into_iter()
+                .map(|handle| handle.join().map_err(|_| Error::Processing("Checkpoint update worker panicked".to_string()))?)
+                .collect::<Result<Vec<_>>>()
+        })?;
+        Ok(results.into_iter().flatten().collect())
+    }
+
+    fn update_checkpoint(
+        &self,
+        pool_address: &Address,
+        markout_time: MarkoutTime,
+        data: &[UnifiedLVRData],
+        chunk_start: u64,
+        chunk_end: u64,
+    ) -> Result<(Cents, u64)> {
+        let deployment_block = self.pools.deployment_block(pool_address.as_str());
+        let effective_start = chunk_start.max(deployment_block);
     
-    fn parse_lvr_details(&self, details_str: &str, target_pool_name: &str) -> Option<f64> {
-        // Attempt to parse as a vector of vectors of strings
-        if let Ok(details) = serde_json::from_str::<Vec<Vec<String>>>(details_str) {
-            for entry in details {
-                if entry.len() == 2 {
-                    let pool_name = &entry[0];
-                    let value_str = &entry[1];
+        if effective_start >= chunk_end {
+            return Ok((Cents::ZERO, 0));
+        }
+        
+        // Get cluster name for this pool (if it belongs to a cluster)
+        let cluster_name = self.pools.cluster_name(pool_address.as_str())
+            .map(|name| name.to_string());
     
-                    if pool_name == target_pool_name {
-                        // Parse value_str as JSON to extract 'dollarValue'
-                        if let Ok(detail) = serde_json::from_str::<HashMap<String, serde_json::Value>>(value_str) {
-                            if let Some(dollar_value) = detail.get("dollarValue") {
-                                return dollar_value.as_f64();
-                            }
-                        }
-                        // Fall back to parsing value_str as a float
-                        if let Ok(value) = value_str.parse::<f64>() {
-                            return Some(value);
-                        }
-                    }
+        let block_data = block_values(&self.pools, chunk_start, chunk_end, pool_address, data);
+    
+        let updates = chunk_end - effective_start;
+        let mut top_lvr = TopLvr::new();
+        let mut running_total = Cents::ZERO;
+        let mut bucket_counts = [0u64; 7];  // Array for all bucket counts
+        let mut negative_count = 0u64;
+        let mut non_zero_values = Vec::new();
+        let mut non_zero_count = 0u64;
+
+        // Blocks without data are zeros
+        bucket_counts[0] += updates - block_data.len() as u64;
+    
+        for (&block_number, &lvr_cents) in &block_data {
+            // Update running statistics
+            running_total += lvr_cents;
+
+            // Negative values net against the running total but are kept
+            // out of the max, digest and the positive histogram buckets
+            if lvr_cents.is_negative() {
+                negative_count += 1;
+            } else {
+                top_lvr.push(block_number, lvr_cents.positive_part());
+
+                // Collect non-zero values for TDigest
+                if lvr_cents.is_positive() {
+                    non_zero_values.push(lvr_cents.to_dollars());  // TDigest works in dollars
+                }
+
+                // Update bucket counts; index 0 is the zero bucket
+                let bucket_idx = bucket_index(lvr_cents.positive_part()).map_or(0, |i| i + 1);
+                bucket_counts[bucket_idx] += 1;
+            }
+        }
+    
+        // Update cluster activity tracking if this pool belongs to a cluster;
+        // activity records every block, so this walk covers the whole range
+        if let Some(cluster) = cluster_name {
+            let mut activity = self.cluster_activity
+                .entry((cluster.clone(), markout_time))
+                .or_insert_with(|| ClusterBlockActivity::new(
+                    cluster,
+                    markout_time,
+                    chunk_start,
+                    self.max_chunk_size
+                ));
+            activity.begin_chunk(chunk_start);
+            for block_number in effective_start..chunk_end {
+                let has_nonzero_lvr = block_data.get(&block_number).is_some_and(|&lvr_cents| lvr_cents != Cents::ZERO);
+                activity.process_block(block_number, has_nonzero_lvr);
+            }
+        }
+    
+        if updates > 0 {
+            non_zero_count = non_zero_values.len() as u64 + negative_count;
+
+            // The aggregate checkpoint takes the same deltas as the pool's.
+            // Entries are taken one at a time so two shard locks are never held.
+            for pair_address in [pool_address.as_str(), AGGREGATE_POOL_ADDRESS] {
+                let checkpoint = self.checkpoints
+                    .entry((pair_address.to_string(), markout_time))
+                    .or_insert_with(|| {
+                        Checkpoint::with_config(pair_address.to_string(), markout_time, &self.digest_config)
+                            .with_deployment_block(self.pools.deployment_block(pair_address))
+                    });
+
+                // Merge the chunk's largest values; once per entry keeps the
+                // checkpoint lock out of the per-block loop
+                for (lvr_cents, block_number) in top_lvr.entries() {
+                    checkpoint.update_max_lvr(block_number, lvr_cents);
                 }
+
+                // Update running total
+                checkpoint.running_total.fetch_add(running_total.as_i64(), Ordering::Release);
+
+                // Update bucket counts atomically
+                let bucket_refs = [
+                    &checkpoint.total_bucket_0,
+                    &checkpoint.total_bucket_0_10,
+                    &checkpoint.total_bucket_10_100,
+                    &checkpoint.total_bucket_100_500,
+                    &checkpoint.total_bucket_500_1000,
+                    &checkpoint.total_bucket_1000_10000,
+                    &checkpoint.total_bucket_10000_plus,
+                ];
+
+                for (count, bucket) in bucket_counts.iter().zip(bucket_refs.iter()) {
+                    bucket.fetch_add(*count, Ordering::Release);
+                }
+                checkpoint.total_bucket_negative.fetch_add(negative_count, Ordering::Release);
+
+                // Update TDigest with non-zero values
+                if let Ok(mut digest) = checkpoint.digest.lock() {
+                    digest.add_all(&non_zero_values);
+                }
+
+                checkpoint.record_processed(effective_start, chunk_end);
+                checkpoint.dirty.store(true, Ordering::Release);
             }
-        } else {
-            // Log the parsing error for debugging
-            error!("Failed to parse details_str as Vec<Vec<String>>");
         }
     
-        None
+        Ok((running_total, non_zero_count))
     }
-}
\ No newline at end of file
+}
+
+/// The values that count for one pool's chunk, by block: blocks outside
+/// the chunk or before deployment are dropped and a later value for a
+/// block replaces an earlier one. Blocks without one are zeros.
+fn block_values(pools: &PoolRegistry, chunk_start: u64, chunk_end: u64, pool_address: &Address, data: &[UnifiedLVRData]) -> BTreeMap<u64, Cents> {
+    let effective_start = chunk_start.max(pools.deployment_block(pool_address.as_str()));
+    data.iter()
+        .filter(|d| d.block_number >= effective_start && d.block_number < chunk_end)
+        .map(|d| (d.block_number, d.lvr_cents))
+        .collect()
+}
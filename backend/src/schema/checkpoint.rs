@@ -0,0 +1,449 @@
+use arrow::{
+    array::{Array, ArrayRef, Float64Array, Int64Array, ListArray, StringArray, UInt16Array, UInt64Array},
+    compute::{cast_with_options, CastOptions},
+    datatypes::{DataType, Field, Schema, SchemaRef, UInt64Type},
+    record_batch::RecordBatch,
+};
+use std::sync::Arc;
+use anyhow::{Context, Result};
+use crate::models::{Cents, CheckpointSnapshot, MarkoutTime};
+use crate::Error;
+use crate::tdigest::{DistributionMetrics, OnlineStats, TDigest};
+use super::SchemaVersion;
+
+/// Column of `checkpoints/pool={pool}/markout={markout}.parquet` holding the checkpoint's
+/// live digest as JSON: centroids, buffered values, moments and compression
+/// parameters, all of which shape later merges. Checkpoints written before it
+/// existed resume with an empty digest that keeps their sample count and
+/// moments.
+pub const CHECKPOINT_DIGEST_COLUMN: &str = "digest";
+
+/// Column of a checkpoint file holding its `CheckpointSchemaVersion` number
+pub const CHECKPOINT_SCHEMA_VERSION_COLUMN: &str = "schema_version";
+
+/// Layout of a checkpoint file, recorded in its `schema_version` column
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CheckpointSchemaVersion {
+    /// Written before the column existed. `running_total` may be UInt64 and
+    /// bucket counts Int64, and the negative, top LVR, min/max, robust
+    /// statistics and digest columns may be missing.
+    Unversioned,
+    /// Every column of `checkpoint_schema` but `deployment_block` and the
+    /// processed range
+    V1,
+    /// Every column of `checkpoint_schema` but the processed range,
+    /// `first_processed_block` and `blocks_processed`
+    V2,
+    /// Every column of `checkpoint_schema`
+    V3,
+}
+
+impl CheckpointSchemaVersion {
+    /// The version `ParallelParquetWriter` writes
+    pub const CURRENT: CheckpointSchemaVersion = CheckpointSchemaVersion::V3;
+
+    pub fn number(self) -> u16 {
+        match self {
+            CheckpointSchemaVersion::Unversioned => 0,
+            CheckpointSchemaVersion::V1 => 1,
+            CheckpointSchemaVersion::V2 => 2,
+            CheckpointSchemaVersion::V3 => 3,
+        }
+    }
+
+    /// This version as recorded in checkpoint footers, beside the
+    /// `schema_version` column
+    pub fn schema_version(self) -> SchemaVersion {
+        SchemaVersion::new(self.number() as u32, 0)
+    }
+
+    /// Columns of `checkpoint_schema` added after this version, which its
+    /// files lack
+    pub fn missing_columns(self) -> &'static [&'static str] {
+        match self {
+            CheckpointSchemaVersion::Unversioned | CheckpointSchemaVersion::V1 => {
+                &["deployment_block", "first_processed_block", "blocks_processed"]
+            }
+            CheckpointSchemaVersion::V2 => &["first_processed_block", "blocks_processed"],
+            CheckpointSchemaVersion::V3 => &[],
+        }
+    }
+
+    /// The version of a checkpoint batch, failing for ones newer than this
+    /// binary reads
+    pub fn of_batch(batch: &RecordBatch) -> Result<Self> {
+        let Some(column) = batch.column_by_name(CHECKPOINT_SCHEMA_VERSION_COLUMN) else {
+            return Ok(CheckpointSchemaVersion::Unversioned);
+        };
+        let versions = column.as_any().downcast_ref::<UInt16Array>()
+            .context("Checkpoint schema_version is not UInt16")?;
+        match versions.iter().flatten().max() {
+            None => Ok(Self::CURRENT),
+            Some(1) => Ok(CheckpointSchemaVersion::V1),
+            Some(2) => Ok(CheckpointSchemaVersion::V2),
+            Some(3) => Ok(CheckpointSchemaVersion::V3),
+            Some(version) => Err(Error::Processing(format!(
+                "Checkpoint schema_version {} is newer than this binary reads ({})",
+                version,
+                Self::CURRENT.number()
+            )).into()),
+        }
+    }
+}
+
+/// Arrow schema of a checkpoint file as written now; `from_record_batch`
+/// also reads the older layouts
+pub fn checkpoint_schema() -> SchemaRef {
+    let uint64 = |name| Field::new(name, DataType::UInt64, false);
+    let float64 = |name| Field::new(name, DataType::Float64, false);
+    let uint64_list = |name| {
+        Field::new(name, DataType::List(Arc::new(Field::new("item", DataType::UInt64, true))), false)
+    };
+    Arc::new(Schema::new(vec![
+        Field::new("pair_address", DataType::Utf8, false),
+        Field::new("markout_time", DataType::Utf8, false),
+        uint64("max_lvr_block"),
+        uint64("max_lvr_value"),
+        uint64_list("top_lvr_values"),
+        uint64_list("top_lvr_blocks"),
+        Field::new("running_total", DataType::Int64, false),
+        uint64("total_bucket_0"),
+        uint64("total_bucket_0_10"),
+        uint64("total_bucket_10_100"),
+        uint64("total_bucket_100_500"),
+        uint64("total_bucket_500_1000"),
+        uint64("total_bucket_1000_10000"),
+        uint64("total_bucket_10000_plus"),
+        uint64("total_bucket_negative"),
+        uint64("last_updated_block"),
+        uint64("first_processed_block"),
+        uint64("blocks_processed"),
+        uint64("deployment_block"),
+        float64("non_zero_proportion"),
+        uint64("non_zero_samples"),
+        uint64("rejected_samples"),
+        uint64("percentile_25_cents"),
+        uint64("median_cents"),
+        uint64("percentile_75_cents"),
+        uint64("trimmed_mean_cents"),
+        uint64("iqr_cents"),
+        float64("mean"),
+        float64("std_dev"),
+        float64("skewness"),
+        float64("kurtosis"),
+        // Exact range of the positive values, null before the first
+        Field::new("min_nonzero_cents", DataType::UInt64, true),
+        Field::new("max_nonzero_cents", DataType::UInt64, true),
+        // Digest diagnostics, also derivable from the digest itself
+        uint64("digest_centroids"),
+        uint64("digest_merges"),
+        uint64("digest_adaptations"),
+        uint64("digest_peak_buffer"),
+        uint64("digest_delta_final"),
+        uint64("digest_buffer_size"),
+        Field::new(CHECKPOINT_DIGEST_COLUMN, DataType::Utf8, false),
+        Field::new(CHECKPOINT_SCHEMA_VERSION_COLUMN, DataType::UInt16, false),
+    ]))
+}
+
+impl CheckpointSnapshot {
+    /// A batch of `rows` in `checkpoint_schema`
+    pub fn to_record_batch(rows: &[CheckpointSnapshot]) -> Result<RecordBatch> {
+        let uint64 = |value: fn(&CheckpointSnapshot) -> u64| -> ArrayRef {
+            Arc::new(UInt64Array::from_iter_values(rows.iter().map(value)))
+        };
+        let float64 = |value: fn(&CheckpointSnapshot) -> f64| -> ArrayRef {
+            Arc::new(Float64Array::from_iter_values(rows.iter().map(value)))
+        };
+        let nullable_uint64 = |value: fn(&CheckpointSnapshot) -> Option<u64>| -> ArrayRef {
+            Arc::new(rows.iter().map(value).collect::<UInt64Array>())
+        };
+        // One list per row holding one half of its top LVR entries
+        let top_lvr = |half: fn(&(u64, u64)) -> u64| -> ArrayRef {
+            Arc::new(ListArray::from_iter_primitive::<UInt64Type, _, _>(
+                rows.iter().map(|row| Some(row.top_lvr.iter().map(|entry| Some(half(entry))).collect::<Vec<_>>())),
+            ))
+        };
+        let digests = rows.iter()
+            .map(|row| serde_json::to_string(&row.digest))
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to serialize checkpoint digest")?;
+
+        RecordBatch::try_new(checkpoint_schema(), vec![
+            Arc::new(StringArray::from_iter_values(rows.iter().map(|row| row.pair_address.as_str()))),
+            Arc::new(StringArray::from_iter_values(rows.iter().map(|row| row.markout_time.to_string()))),
+            uint64(|row| row.max_lvr_block),
+            uint64(|row| row.max_lvr_value),
+            top_lvr(|&(value, _)| value),
+            top_lvr(|&(_, block)| block),
+            Arc::new(Int64Array::from_iter_values(rows.iter().map(|row| row.running_total.as_i64()))),
+            uint64(|row| row.total_bucket_0),
+            uint64(|row| row.total_bucket_0_10),
+            uint64(|row| row.total_bucket_10_100),
+            uint64(|row| row.total_bucket_100_500),
+            uint64(|row| row.total_bucket_500_1000),
+            uint64(|row| row.total_bucket_1000_10000),
+            uint64(|row| row.total_bucket_10000_plus),
+            uint64(|row| row.total_bucket_negative),
+            uint64(|row| row.last_updated_block),
+            uint64(|row| row.first_processed_block),
+            uint64(|row| row.blocks_processed),
+            uint64(|row| row.deployment_block),
+            float64(|row| row.non_zero_proportion),
+            uint64(|row| row.non_zero_samples),
+            uint64(|row| row.rejected_samples),
+            uint64(|row| row.percentile_25_cents),
+            uint64(|row| row.median_cents),
+            uint64(|row| row.percentile_75_cents),
+            uint64(|row| row.trimmed_mean_cents),
+            uint64(|row| row.iqr_cents),
+            float64(|row| row.mean),
+            float64(|row| row.std_dev),
+            float64(|row| row.skewness),
+            float64(|row| row.kurtosis),
+            nullable_uint64(|row| row.min_nonzero_cents),
+            nullable_uint64(|row| row.max_nonzero_cents),
+            uint64(|row| row.digest_diagnostics.centroid_count as u64),
+            uint64(|row| row.digest_diagnostics.merges),
+            uint64(|row| row.digest_diagnostics.adaptations),
+            uint64(|row| row.digest_diagnostics.peak_buffer as u64),
+            uint64(|row| row.digest_delta_final),
+            uint64(|row| row.digest_buffer_size),
+            Arc::new(StringArray::from_iter_values(digests)),
+            Arc::new(UInt16Array::from_iter_values(rows.iter().map(|_| CheckpointSchemaVersion::CURRENT.number()))),
+        ]).context("Failed to create checkpoint record batch")
+    }
+
+    /// Reads every row of a checkpoint batch of any version this binary
+    /// knows. Unversioned batches have their counts coerced to the current
+    /// types, and their missing columns read as 0, empty or null; columns
+    /// added since a versioned batch's version read as 0.
+    pub fn from_record_batch(batch: &RecordBatch) -> Result<Vec<Self>> {
+        match CheckpointSchemaVersion::of_batch(batch)? {
+            CheckpointSchemaVersion::Unversioned => Self::parse_batch(&upgrade_unversioned_checkpoint(batch)?),
+            version => {
+                let schema = batch.schema();
+                let expected = checkpoint_schema();
+                let fields = expected.fields().iter()
+                    .filter(|field| !version.missing_columns().contains(&field.name().as_str()));
+                for field in fields {
+                    match schema.field_with_name(field.name()) {
+                        Ok(found) if found.data_type() == field.data_type() => {}
+                        Ok(found) => return Err(Error::Processing(format!(
+                            "Checkpoint column {} is {}, expected {}", field.name(), found.data_type(), field.data_type()
+                        )).into()),
+                        Err(_) => return Err(Error::Processing(format!(
+                            "Checkpoint is missing column {}", field.name()
+                        )).into()),
+                    }
+                }
+                Self::parse_batch(batch)
+            }
+        }
+    }
+
+    /// Bucket counts of the positive values, in `BUCKET_CONFIG` order
+    pub fn positive_bucket_counts(&self) -> [u64; 6] {
+        [
+            self.total_bucket_0_10,
+            self.total_bucket_10_100,
+            self.total_bucket_100_500,
+            self.total_bucket_500_1000,
+            self.total_bucket_1000_10000,
+            self.total_bucket_10000_plus,
+        ]
+    }
+
+    /// Blocks with a non-zero value, negative ones included
+    pub fn non_zero_blocks(&self) -> u64 {
+        self.positive_bucket_counts().iter().sum::<u64>() + self.total_bucket_negative
+    }
+
+    /// Every block the checkpoint counted
+    pub fn total_blocks(&self) -> u64 {
+        self.total_bucket_0 + self.non_zero_blocks()
+    }
+
+    /// The stored `deployment_block`, or `registered` (the pool registry's)
+    /// for checkpoints written before it was, which read 0
+    pub fn deployment_block_or(&self, registered: u64) -> u64 {
+        if self.deployment_block > 0 { self.deployment_block } else { registered }
+    }
+
+    /// Counted blocks at or after `deployment_block`. Checkpoints written
+    /// before the processor started pools at their deployment counted the
+    /// blocks before it as zeros; those are left out.
+    pub fn blocks_since_deployment(&self, deployment_block: u64) -> u64 {
+        let deployed = (self.last_updated_block + 1).saturating_sub(deployment_block);
+        let pre_deployment = self.total_blocks().saturating_sub(deployed).min(self.total_bucket_0);
+        self.total_blocks() - pre_deployment
+    }
+
+    /// The first block the checkpoint processed and how many blocks it
+    /// counted. Checkpoints stored before these were are taken to have
+    /// counted every block up to `last_updated_block` once, with no gaps.
+    pub fn processed_blocks(&self) -> (u64, u64) {
+        if self.blocks_processed > 0 || self.total_blocks() == 0 {
+            return (self.first_processed_block, self.blocks_processed);
+        }
+        let blocks = self.total_blocks();
+        ((self.last_updated_block + 1).saturating_sub(blocks), blocks)
+    }
+
+    /// The blocks `start..end` the checkpoint declares it covered; `None`
+    /// before its first update and for checkpoints stored before the range
+    /// was, whose range is only inferred
+    pub fn processed_range(&self) -> Option<(u64, u64)> {
+        (self.blocks_processed > 0).then_some((self.first_processed_block, self.last_updated_block + 1))
+    }
+
+    fn parse_batch(batch: &RecordBatch) -> Result<Vec<Self>> {
+        fn column<'a, A: Array + 'static>(batch: &'a RecordBatch, name: &str) -> Option<&'a A> {
+            batch.column_by_name(name).and_then(|column| column.as_any().downcast_ref::<A>())
+        }
+        let uint64 = |name: &str| -> Result<&UInt64Array> {
+            column::<UInt64Array>(batch, name)
+                .with_context(|| format!("Checkpoint is missing UInt64 column {}", name))
+        };
+        let float64 = |name: &str| -> Result<&Float64Array> {
+            column::<Float64Array>(batch, name)
+                .with_context(|| format!("Checkpoint is missing Float64 column {}", name))
+        };
+        let list_values = |list: Option<&ListArray>, i: usize| -> Option<std::sync::Arc<dyn Array>> {
+            list.map(|list| list.value(i))
+        };
+        let float64_value = |name: &str, i: usize| -> Result<f64> { Ok(float64(name)?.value(i)) };
+
+        let pair_addresses = column::<StringArray>(batch, "pair_address").context("Checkpoint is missing pair_address")?;
+        let markout_times = column::<StringArray>(batch, "markout_time").context("Checkpoint is missing markout_time")?;
+        let running_totals = column::<Int64Array>(batch, "running_total").context("Checkpoint is missing running_total")?;
+        let negatives = column::<UInt64Array>(batch, "total_bucket_negative");
+        let top_values = column::<ListArray>(batch, "top_lvr_values");
+        let top_blocks = column::<ListArray>(batch, "top_lvr_blocks");
+        let digests = column::<StringArray>(batch, CHECKPOINT_DIGEST_COLUMN);
+        let min_values = column::<UInt64Array>(batch, "min_nonzero_cents");
+        let max_values = column::<UInt64Array>(batch, "max_nonzero_cents");
+        let trimmed_means = column::<UInt64Array>(batch, "trimmed_mean_cents");
+        let iqrs = column::<UInt64Array>(batch, "iqr_cents");
+        let rejected = column::<UInt64Array>(batch, "rejected_samples");
+        let deployment_blocks = column::<UInt64Array>(batch, "deployment_block");
+        let first_processed_blocks = column::<UInt64Array>(batch, "first_processed_block");
+        let blocks_processed = column::<UInt64Array>(batch, "blocks_processed");
+        let nullable_value = |values: Option<&UInt64Array>, i: usize| {
+            values.filter(|values| values.is_valid(i)).map(|values| values.value(i))
+        };
+
+        (0..batch.num_rows())
+            .map(|i| {
+                let markout_str = markout_times.value(i);
+                let markout_time = markout_str
+                    .parse::<MarkoutTime>()
+                    .with_context(|| format!("Invalid markout_time in checkpoint: {}", markout_str))?;
+
+                let top_lvr = match (list_values(top_values, i), list_values(top_blocks, i)) {
+                    (Some(values), Some(blocks)) => {
+                        let values = values.as_any().downcast_ref::<UInt64Array>().context("Checkpoint top LVR values are not UInt64")?;
+                        let blocks = blocks.as_any().downcast_ref::<UInt64Array>().context("Checkpoint top LVR blocks are not UInt64")?;
+                        values.values().iter().copied().zip(blocks.values().iter().copied()).collect()
+                    }
+                    _ => Vec::new(),
+                };
+
+                let non_zero_samples = uint64("non_zero_samples")?.value(i);
+                let (mean, std_dev) = (float64_value("mean", i)?, float64_value("std_dev", i)?);
+                let (skewness, kurtosis) = (float64_value("skewness", i)?, float64_value("kurtosis", i)?);
+                let digest = match digests {
+                    Some(digests) => serde_json::from_str(digests.value(i))
+                        .with_context(|| format!("Invalid digest in checkpoint {}", pair_addresses.value(i)))?,
+                    None => TDigest::from_centroids(Vec::new(), 0.0, non_zero_samples, OnlineStats::from_metrics(&DistributionMetrics {
+                        mean,
+                        variance: std_dev * std_dev,
+                        std_dev,
+                        skewness,
+                        kurtosis,
+                        sample_count: non_zero_samples,
+                    })),
+                };
+
+                Ok(Self {
+                    pair_address: pair_addresses.value(i).to_string(),
+                    markout_time,
+                    max_lvr_value: uint64("max_lvr_value")?.value(i),
+                    max_lvr_block: uint64("max_lvr_block")?.value(i),
+                    top_lvr,
+                    running_total: Cents(running_totals.value(i)),
+                    total_bucket_0: uint64("total_bucket_0")?.value(i),
+                    total_bucket_0_10: uint64("total_bucket_0_10")?.value(i),
+                    total_bucket_10_100: uint64("total_bucket_10_100")?.value(i),
+                    total_bucket_100_500: uint64("total_bucket_100_500")?.value(i),
+                    total_bucket_500_1000: uint64("total_bucket_500_1000")?.value(i),
+                    total_bucket_1000_10000: uint64("total_bucket_1000_10000")?.value(i),
+                    total_bucket_10000_plus: uint64("total_bucket_10000_plus")?.value(i),
+                    total_bucket_negative: negatives.map_or(0, |negatives| negatives.value(i)),
+                    last_updated_block: uint64("last_updated_block")?.value(i),
+                    first_processed_block: first_processed_blocks.map_or(0, |blocks| blocks.value(i)),
+                    blocks_processed: blocks_processed.map_or(0, |blocks| blocks.value(i)),
+                    deployment_block: deployment_blocks.map_or(0, |blocks| blocks.value(i)),
+                    non_zero_proportion: float64("non_zero_proportion")?.value(i),
+                    percentile_25_cents: uint64("percentile_25_cents")?.value(i),
+                    median_cents: uint64("median_cents")?.value(i),
+                    percentile_75_cents: uint64("percentile_75_cents")?.value(i),
+                    trimmed_mean_cents: trimmed_means.map_or(0, |values| values.value(i)),
+                    iqr_cents: iqrs.map_or(0, |values| values.value(i)),
+                    non_zero_samples,
+                    rejected_samples: rejected.map_or(0, |values| values.value(i)),
+                    mean,
+                    std_dev,
+                    skewness,
+                    kurtosis,
+                    min_nonzero_cents: nullable_value(min_values, i),
+                    max_nonzero_cents: nullable_value(max_values, i),
+                    digest_diagnostics: digest.diagnostics(),
+                    digest_delta_final: digest.compression.delta_final,
+                    digest_buffer_size: digest.compression.buffer_size as u64,
+                    digest,
+                })
+            })
+            .collect()
+    }
+}
+
+/// The single row of a checkpoint file's batch
+pub fn read_checkpoint_snapshot(batch: &RecordBatch) -> Result<CheckpointSnapshot> {
+    let mut snapshots = CheckpointSnapshot::from_record_batch(batch)?;
+    if snapshots.len() != 1 {
+        return Err(Error::Processing(format!("Checkpoint batch has {} rows, expected 1", snapshots.len())).into());
+    }
+    Ok(snapshots.remove(0))
+}
+
+/// Casts the columns of an unversioned checkpoint whose integer type has
+/// since changed to their current type: `running_total` from before LVR was
+/// signed, and bucket counts once stored as Int64. Fails for values the new
+/// type cannot hold rather than reading them as null.
+fn upgrade_unversioned_checkpoint(batch: &RecordBatch) -> Result<RecordBatch> {
+    let current = checkpoint_schema();
+    let schema = batch.schema();
+    let options = CastOptions { safe: false, ..Default::default() };
+
+    let mut fields = Vec::with_capacity(schema.fields().len());
+    let mut columns = Vec::with_capacity(schema.fields().len());
+    for (field, column) in schema.fields().iter().zip(batch.columns()) {
+        match current.field_with_name(field.name()) {
+            Ok(expected) if expected.data_type() != field.data_type()
+                && matches!(field.data_type(), DataType::Int64 | DataType::UInt64) =>
+            {
+                let cast = cast_with_options(column, expected.data_type(), &options)
+                    .with_context(|| format!("Checkpoint {} does not fit {}", field.name(), expected.data_type()))?;
+                fields.push(field.as_ref().clone().with_data_type(expected.data_type().clone()));
+                columns.push(cast);
+            }
+            _ => {
+                fields.push(field.as_ref().clone());
+                columns.push(column.clone());
+            }
+        }
+    }
+    RecordBatch::try_new(Arc::new(Schema::new_with_metadata(fields, schema.metadata().clone())), columns)
+        .context("Failed to upgrade unversioned checkpoint columns")
+}
@@ -0,0 +1,240 @@
+use arrow::{
+    array::{Array, ArrayRef, Int64Array, ListArray, ListBuilder, StringArray, StringBuilder, UInt64Array},
+    datatypes::{DataType, Field, Int64Type, Schema, SchemaRef, UInt64Type},
+    record_batch::RecordBatch,
+};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use anyhow::{Context, Result};
+use crate::models::{Cents, ChunkMarkoutTotals, ChunkSummary, ChunkTimings, CompletenessWarning, DataSource};
+use super::SchemaVersion;
+
+/// Layout version recorded in chunk summary footers
+pub const CHUNK_SUMMARY_SCHEMA_VERSION: SchemaVersion = SchemaVersion::new(1, 1);
+
+// Column names of `chunks/{start}_{end}_summary.parquet`. Per-markout values
+// are list columns of equal length, one entry per markout time, and so are
+// the completeness warnings, one entry per flagged markout.
+pub const CHUNK_START_COLUMN: &str = "chunk_start";
+pub const CHUNK_END_COLUMN: &str = "chunk_end";
+pub const CHUNK_AURORA_ROWS_COLUMN: &str = "aurora_rows";
+pub const CHUNK_MALFORMED_AURORA_ROWS_COLUMN: &str = "malformed_aurora_rows";
+pub const CHUNK_BRONTES_ROWS_COLUMN: &str = "brontes_rows";
+pub const CHUNK_AURORA_FETCH_MS_COLUMN: &str = "aurora_fetch_ms";
+pub const CHUNK_BRONTES_FETCH_MS_COLUMN: &str = "brontes_fetch_ms";
+pub const CHUNK_PROCESS_MS_COLUMN: &str = "process_ms";
+pub const CHUNK_INTERVAL_WRITE_MS_COLUMN: &str = "interval_write_ms";
+pub const CHUNK_CHECKPOINT_UPDATE_MS_COLUMN: &str = "checkpoint_update_ms";
+pub const CHUNK_RETRIES_COLUMN: &str = "retries";
+pub const CHUNK_MARKOUT_TIMES_COLUMN: &str = "markout_times";
+pub const CHUNK_TOTAL_LVR_COLUMN: &str = "markout_total_lvr_cents";
+pub const CHUNK_NON_ZERO_COUNT_COLUMN: &str = "markout_non_zero_counts";
+pub const CHUNK_INCOMPLETE_MARKOUTS_COLUMN: &str = "incomplete_markout_times";
+pub const CHUNK_INCOMPLETE_ROWS_COLUMN: &str = "incomplete_markout_rows";
+pub const CHUNK_INCOMPLETE_EXPECTED_ROWS_COLUMN: &str = "incomplete_markout_expected_rows";
+pub const CHUNK_SOURCE_NAMES_COLUMN: &str = "source_names";
+pub const CHUNK_SOURCE_COUNTS_COLUMN: &str = "source_counts";
+pub const CHUNK_WRITE_RETRIES_COLUMN: &str = "write_retries";
+
+/// Arrow schema of a chunk summary file
+pub fn chunk_summary_schema() -> SchemaRef {
+    let list = |item: DataType| DataType::List(Arc::new(Field::new("item", item, true)));
+    Arc::new(Schema::new(vec![
+        Field::new(CHUNK_START_COLUMN, DataType::UInt64, false),
+        Field::new(CHUNK_END_COLUMN, DataType::UInt64, false),
+        Field::new(CHUNK_AURORA_ROWS_COLUMN, DataType::UInt64, false),
+        Field::new(CHUNK_BRONTES_ROWS_COLUMN, DataType::UInt64, false),
+        Field::new(CHUNK_AURORA_FETCH_MS_COLUMN, DataType::UInt64, false),
+        Field::new(CHUNK_BRONTES_FETCH_MS_COLUMN, DataType::UInt64, false),
+        Field::new(CHUNK_PROCESS_MS_COLUMN, DataType::UInt64, false),
+        Field::new(CHUNK_INTERVAL_WRITE_MS_COLUMN, DataType::UInt64, false),
+        Field::new(CHUNK_CHECKPOINT_UPDATE_MS_COLUMN, DataType::UInt64, false),
+        Field::new(CHUNK_RETRIES_COLUMN, DataType::UInt64, false),
+        Field::new(CHUNK_MARKOUT_TIMES_COLUMN, list(DataType::Utf8), false),
+        Field::new(CHUNK_TOTAL_LVR_COLUMN, list(DataType::Int64), false),
+        Field::new(CHUNK_NON_ZERO_COUNT_COLUMN, list(DataType::UInt64), false),
+        Field::new(CHUNK_MALFORMED_AURORA_ROWS_COLUMN, DataType::UInt64, false),
+        Field::new(CHUNK_INCOMPLETE_MARKOUTS_COLUMN, list(DataType::Utf8), false),
+        Field::new(CHUNK_INCOMPLETE_ROWS_COLUMN, list(DataType::UInt64), false),
+        Field::new(CHUNK_INCOMPLETE_EXPECTED_ROWS_COLUMN, list(DataType::UInt64), false),
+        Field::new(CHUNK_SOURCE_NAMES_COLUMN, list(DataType::Utf8), false),
+        Field::new(CHUNK_SOURCE_COUNTS_COLUMN, list(DataType::UInt64), false),
+        Field::new(CHUNK_WRITE_RETRIES_COLUMN, DataType::UInt64, false),
+    ]))
+}
+
+impl ChunkSummary {
+    /// Single-row batch in `chunk_summary_schema`
+    pub fn to_record_batch(&self) -> Result<RecordBatch> {
+        let mut markout_times = ListBuilder::new(StringBuilder::new());
+        markout_times.append_value(self.markouts.iter().map(|m| Some(m.markout_time.as_str())));
+        let mut incomplete_markouts = ListBuilder::new(StringBuilder::new());
+        incomplete_markouts.append_value(self.completeness_warnings.iter().map(|w| Some(w.markout_time.as_str())));
+        let mut source_names = ListBuilder::new(StringBuilder::new());
+        source_names.append_value(self.source_counts.keys().map(|source| Some(source.as_str())));
+        let uint64_list = |values: Vec<Option<u64>>| -> ArrayRef {
+            Arc::new(ListArray::from_iter_primitive::<UInt64Type, _, _>([Some(values)]))
+        };
+        let int64_list = |values: Vec<Option<i64>>| -> ArrayRef {
+            Arc::new(ListArray::from_iter_primitive::<Int64Type, _, _>([Some(values)]))
+        };
+        let scalar = |value: u64| -> ArrayRef { Arc::new(UInt64Array::from(vec![value])) };
+
+        RecordBatch::try_new(chunk_summary_schema(), vec![
+            scalar(self.chunk_start),
+            scalar(self.chunk_end),
+            scalar(self.aurora_rows),
+            scalar(self.brontes_rows),
+            scalar(self.timings.aurora_fetch_ms),
+            scalar(self.timings.brontes_fetch_ms),
+            scalar(self.timings.process_ms),
+            scalar(self.timings.interval_write_ms),
+            scalar(self.timings.checkpoint_update_ms),
+            scalar(self.retries),
+            Arc::new(markout_times.finish()),
+            int64_list(self.markouts.iter().map(|m| Some(m.total_lvr_cents.as_i64())).collect()),
+            uint64_list(self.markouts.iter().map(|m| Some(m.non_zero_count)).collect()),
+            scalar(self.malformed_aurora_rows),
+            Arc::new(incomplete_markouts.finish()),
+            uint64_list(self.completeness_warnings.iter().map(|w| Some(w.rows)).collect()),
+            uint64_list(self.completeness_warnings.iter().map(|w| Some(w.expected_rows)).collect()),
+            Arc::new(source_names.finish()),
+            uint64_list(self.source_counts.values().map(|&count| Some(count)).collect()),
+            scalar(self.write_retries),
+        ]).context("Failed to create chunk summary record batch")
+    }
+
+    /// Reads every row of a chunk summary batch
+    pub fn from_record_batch(batch: &RecordBatch) -> Result<Vec<Self>> {
+        let scalar = |name: &str| -> Result<&UInt64Array> {
+            batch.column_by_name(name)
+                .and_then(|column| column.as_any().downcast_ref::<UInt64Array>())
+                .with_context(|| format!("Chunk summary is missing UInt64 column {}", name))
+        };
+        let list = |name: &str| -> Result<&ListArray> {
+            batch.column_by_name(name)
+                .and_then(|column| column.as_any().downcast_ref::<ListArray>())
+                .with_context(|| format!("Chunk summary is missing list column {}", name))
+        };
+
+        let starts = scalar(CHUNK_START_COLUMN)?;
+        let ends = scalar(CHUNK_END_COLUMN)?;
+        let aurora_rows = scalar(CHUNK_AURORA_ROWS_COLUMN)?;
+        let brontes_rows = scalar(CHUNK_BRONTES_ROWS_COLUMN)?;
+        let aurora_fetch_ms = scalar(CHUNK_AURORA_FETCH_MS_COLUMN)?;
+        let brontes_fetch_ms = scalar(CHUNK_BRONTES_FETCH_MS_COLUMN)?;
+        // Summaries written before these phases were timed, or before
+        // malformed rows or write retries were counted, lack them
+        let optional = |name: &str| scalar(name).ok();
+        let malformed_aurora_rows = optional(CHUNK_MALFORMED_AURORA_ROWS_COLUMN);
+        let process_ms = optional(CHUNK_PROCESS_MS_COLUMN);
+        let interval_write_ms = optional(CHUNK_INTERVAL_WRITE_MS_COLUMN);
+        let checkpoint_update_ms = optional(CHUNK_CHECKPOINT_UPDATE_MS_COLUMN);
+        let write_retries = optional(CHUNK_WRITE_RETRIES_COLUMN);
+        let retries = scalar(CHUNK_RETRIES_COLUMN)?;
+        let markout_times = list(CHUNK_MARKOUT_TIMES_COLUMN)?;
+        let totals = list(CHUNK_TOTAL_LVR_COLUMN)?;
+        let non_zero_counts = list(CHUNK_NON_ZERO_COUNT_COLUMN)?;
+        // Summaries written before completeness was checked lack these
+        let incomplete = match (
+            list(CHUNK_INCOMPLETE_MARKOUTS_COLUMN),
+            list(CHUNK_INCOMPLETE_ROWS_COLUMN),
+            list(CHUNK_INCOMPLETE_EXPECTED_ROWS_COLUMN),
+        ) {
+            (Ok(markouts), Ok(rows), Ok(expected)) => Some((markouts, rows, expected)),
+            _ => None,
+        };
+        // Summaries written before provenance was recorded lack these
+        let sources = match (list(CHUNK_SOURCE_NAMES_COLUMN), list(CHUNK_SOURCE_COUNTS_COLUMN)) {
+            (Ok(names), Ok(counts)) => Some((names, counts)),
+            _ => None,
+        };
+
+        (0..batch.num_rows())
+            .map(|i| {
+                let markout_times = markout_times.value(i);
+                let markout_times = markout_times.as_any().downcast_ref::<StringArray>()
+                    .context("Chunk summary markout times are not strings")?;
+                let totals = totals.value(i);
+                let totals = totals.as_any().downcast_ref::<Int64Array>()
+                    .context("Chunk summary totals are not Int64")?;
+                let non_zero_counts = non_zero_counts.value(i);
+                let non_zero_counts = non_zero_counts.as_any().downcast_ref::<UInt64Array>()
+                    .context("Chunk summary non-zero counts are not UInt64")?;
+                if totals.len() != markout_times.len() || non_zero_counts.len() != markout_times.len() {
+                    return Err(anyhow::anyhow!(
+                        "Chunk summary {}-{} has mismatched markout lists", starts.value(i), ends.value(i)
+                    ));
+                }
+
+                let mut completeness_warnings = Vec::new();
+                if let Some((markouts, rows, expected)) = incomplete {
+                    let (markouts, rows, expected) = (markouts.value(i), rows.value(i), expected.value(i));
+                    let markouts = markouts.as_any().downcast_ref::<StringArray>()
+                        .context("Chunk summary incomplete markouts are not strings")?;
+                    let rows = rows.as_any().downcast_ref::<UInt64Array>()
+                        .context("Chunk summary incomplete rows are not UInt64")?;
+                    let expected = expected.as_any().downcast_ref::<UInt64Array>()
+                        .context("Chunk summary expected rows are not UInt64")?;
+                    if rows.len() != markouts.len() || expected.len() != markouts.len() {
+                        return Err(anyhow::anyhow!(
+                            "Chunk summary {}-{} has mismatched completeness lists", starts.value(i), ends.value(i)
+                        ));
+                    }
+                    completeness_warnings = (0..markouts.len())
+                        .map(|j| CompletenessWarning {
+                            markout_time: markouts.value(j).to_string(),
+                            chunk_start: starts.value(i),
+                            chunk_end: ends.value(i),
+                            rows: rows.value(j),
+                            expected_rows: expected.value(j),
+                        })
+                        .collect();
+                }
+
+                let mut source_counts = BTreeMap::new();
+                if let Some((names, counts)) = sources {
+                    let (names, counts) = (names.value(i), counts.value(i));
+                    let names = names.as_any().downcast_ref::<StringArray>()
+                        .context("Chunk summary source names are not strings")?;
+                    let counts = counts.as_any().downcast_ref::<UInt64Array>()
+                        .context("Chunk summary source counts are not UInt64")?;
+                    if counts.len() != names.len() {
+                        return Err(anyhow::anyhow!(
+                            "Chunk summary {}-{} has mismatched source lists", starts.value(i), ends.value(i)
+                        ));
+                    }
+                    for j in 0..names.len() {
+                        *source_counts.entry(names.value(j).parse::<DataSource>()?).or_insert(0) += counts.value(j);
+                    }
+                }
+
+                Ok(Self {
+                    chunk_start: starts.value(i),
+                    chunk_end: ends.value(i),
+                    aurora_rows: aurora_rows.value(i),
+                    malformed_aurora_rows: malformed_aurora_rows.map_or(0, |column| column.value(i)),
+                    brontes_rows: brontes_rows.value(i),
+                    timings: ChunkTimings {
+                        aurora_fetch_ms: aurora_fetch_ms.value(i),
+                        brontes_fetch_ms: brontes_fetch_ms.value(i),
+                        process_ms: process_ms.map_or(0, |column| column.value(i)),
+                        interval_write_ms: interval_write_ms.map_or(0, |column| column.value(i)),
+                        checkpoint_update_ms: checkpoint_update_ms.map_or(0, |column| column.value(i)),
+                    },
+                    retries: retries.value(i),
+                    write_retries: write_retries.map_or(0, |column| column.value(i)),
+                    markouts: (0..markout_times.len())
+                        .map(|j| ChunkMarkoutTotals {
+                            markout_time: markout_times.value(j).to_string(),
+                            total_lvr_cents: Cents(totals.value(j)),
+                            non_zero_count: non_zero_counts.value(j),
+                        })
+                        .collect(),
+                    completeness_warnings,
+                    source_counts,
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,295 @@
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use object_store::path::Path;
+use parquet::file::{metadata::{FileMetaData, KeyValue}, reader::FileReader, serialized_reader::SerializedFileReader};
+use std::collections::HashMap;
+use std::fmt;
+use anyhow::{Context, Result};
+use tracing::warn;
+use crate::api::ROLLING_WINDOW_INTERVALS;
+use crate::models::Address;
+use super::provenance_metadata;
+
+// Footer keys recorded in every precomputed file
+pub const DATASET_METADATA_KEY: &str = "lvr.dataset";
+pub const SCHEMA_VERSION_METADATA_KEY: &str = "lvr.schema_version";
+pub const CRATE_VERSION_METADATA_KEY: &str = "lvr.crate_version";
+pub const GIT_HASH_METADATA_KEY: &str = "lvr.git_hash";
+pub const GENERATED_AT_METADATA_KEY: &str = "lvr.generated_at";
+pub const INPUT_MANIFEST_METADATA_KEY: &str = "lvr.input_manifest";
+
+/// `major.minor` version of a precomputed file's layout. Readers only
+/// understand files whose major version matches their own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SchemaVersion {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl SchemaVersion {
+    pub const fn new(major: u32, minor: u32) -> Self {
+        Self { major, minor }
+    }
+
+    pub fn parse(value: &str) -> Result<Self> {
+        let (major, minor) = value.split_once('.').unwrap_or((value, "0"));
+        Ok(Self {
+            major: major.parse().with_context(|| format!("Invalid schema version '{}'", value))?,
+            minor: minor.parse().with_context(|| format!("Invalid schema version '{}'", value))?,
+        })
+    }
+}
+
+impl fmt::Display for SchemaVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// Every file `PrecomputedWriter` produces and the API serves
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DatasetKind {
+    IndividualRunningTotals,
+    AggregateRunningTotals,
+    PoolTotals,
+    MonthlyPoolTotals,
+    Concentration,
+    ActivityRuns,
+    PoolCorrelations,
+    MaxLvr,
+    NonZeroProportions,
+    Histograms,
+    EquiDepthHistograms,
+    PercentileBands,
+    QuartilePlots,
+    DistributionMetrics,
+    DailyTimeSeries,
+    RollingSeries { window: usize },
+    ClusterProportions,
+    ClusterHistograms,
+    ClusterMonthlyTotals,
+    ClusterNonZero,
+}
+
+impl DatasetKind {
+    pub const ALL: [DatasetKind; 20] = [
+        DatasetKind::IndividualRunningTotals,
+        DatasetKind::AggregateRunningTotals,
+        DatasetKind::PoolTotals,
+        DatasetKind::MonthlyPoolTotals,
+        DatasetKind::Concentration,
+        DatasetKind::ActivityRuns,
+        DatasetKind::PoolCorrelations,
+        DatasetKind::MaxLvr,
+        DatasetKind::NonZeroProportions,
+        DatasetKind::Histograms,
+        DatasetKind::EquiDepthHistograms,
+        DatasetKind::PercentileBands,
+        DatasetKind::QuartilePlots,
+        DatasetKind::DistributionMetrics,
+        DatasetKind::DailyTimeSeries,
+        DatasetKind::RollingSeries { window: ROLLING_WINDOW_INTERVALS },
+        DatasetKind::ClusterProportions,
+        DatasetKind::ClusterHistograms,
+        DatasetKind::ClusterMonthlyTotals,
+        DatasetKind::ClusterNonZero,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DatasetKind::IndividualRunningTotals => "individual_running_totals",
+            DatasetKind::AggregateRunningTotals => "aggregate_running_totals",
+            DatasetKind::PoolTotals => "pool_totals",
+            DatasetKind::MonthlyPoolTotals => "monthly_pool_totals",
+            DatasetKind::Concentration => "concentration",
+            DatasetKind::ActivityRuns => "activity_runs",
+            DatasetKind::PoolCorrelations => "pool_correlations",
+            DatasetKind::MaxLvr => "max_lvr",
+            DatasetKind::NonZeroProportions => "non_zero_proportions",
+            DatasetKind::Histograms => "histograms",
+            DatasetKind::EquiDepthHistograms => "equidepth_histograms",
+            DatasetKind::PercentileBands => "percentile_bands",
+            DatasetKind::QuartilePlots => "quartile_plots",
+            DatasetKind::DistributionMetrics => "distribution_metrics",
+            DatasetKind::DailyTimeSeries => "daily_time_series",
+            DatasetKind::RollingSeries { .. } => "rolling_series",
+            DatasetKind::ClusterProportions => "cluster_proportions",
+            DatasetKind::ClusterHistograms => "cluster_histograms",
+            DatasetKind::ClusterMonthlyTotals => "cluster_monthly_totals",
+            DatasetKind::ClusterNonZero => "cluster_non_zero",
+        }
+    }
+
+    pub fn path(&self) -> Path {
+        let path = match self {
+            DatasetKind::IndividualRunningTotals => "precomputed/running_totals/individual.parquet",
+            DatasetKind::AggregateRunningTotals => "precomputed/running_totals/aggregate.parquet",
+            DatasetKind::PoolTotals => "precomputed/pool_metrics/totals.parquet",
+            DatasetKind::MonthlyPoolTotals => "precomputed/pool_metrics/monthly_totals.parquet",
+            DatasetKind::Concentration => "precomputed/pool_metrics/concentration.parquet",
+            DatasetKind::ActivityRuns => "precomputed/pool_metrics/activity_runs.parquet",
+            DatasetKind::PoolCorrelations => "precomputed/pool_metrics/correlations.parquet",
+            DatasetKind::MaxLvr => "precomputed/pool_metrics/max_lvr.parquet",
+            DatasetKind::NonZeroProportions => "precomputed/pool_metrics/non_zero.parquet",
+            DatasetKind::Histograms => "precomputed/distributions/histograms.parquet",
+            DatasetKind::EquiDepthHistograms => "precomputed/distributions/equidepth.parquet",
+            DatasetKind::PercentileBands => "precomputed/distributions/percentile_bands.parquet",
+            DatasetKind::QuartilePlots => "precomputed/distributions/quartile_plots.parquet",
+            DatasetKind::DistributionMetrics => "precomputed/distributions/metrics.parquet",
+            DatasetKind::DailyTimeSeries => "precomputed/distributions/daily_ts.parquet",
+            DatasetKind::RollingSeries { window } => {
+                return Path::from(format!("precomputed/time_series/rolling_{}.parquet", window));
+            }
+            DatasetKind::ClusterProportions => "precomputed/clusters/proportions.parquet",
+            DatasetKind::ClusterHistograms => "precomputed/clusters/histograms.parquet",
+            DatasetKind::ClusterMonthlyTotals => "precomputed/clusters/monthly_totals.parquet",
+            DatasetKind::ClusterNonZero => "precomputed/clusters/non_zero.parquet",
+        };
+        Path::from(path)
+    }
+
+    /// Per-pool partition of `IndividualRunningTotals`. Partitions share its
+    /// schema, sort order and footer.
+    pub fn pool_partition_path(pool_address: &Address) -> Path {
+        Path::from(format!("precomputed/running_totals/individual/pool={}.parquet", pool_address))
+    }
+
+    /// Bump the major version whenever a column is removed, renamed or
+    /// retyped; bump the minor version for additive changes
+    pub fn schema_version(&self) -> SchemaVersion {
+        match self {
+            // 2.0: LVR totals became signed (Int64)
+            DatasetKind::IndividualRunningTotals
+            | DatasetKind::AggregateRunningTotals
+            | DatasetKind::PoolTotals
+            | DatasetKind::MonthlyPoolTotals
+            | DatasetKind::RollingSeries { .. }
+            | DatasetKind::ClusterProportions
+            | DatasetKind::ClusterMonthlyTotals => SchemaVersion::new(2, 0),
+            DatasetKind::Concentration
+            | DatasetKind::ActivityRuns
+            | DatasetKind::PoolCorrelations
+            | DatasetKind::MaxLvr
+            | DatasetKind::Histograms
+            | DatasetKind::EquiDepthHistograms
+            | DatasetKind::PercentileBands
+            | DatasetKind::QuartilePlots
+            | DatasetKind::DailyTimeSeries
+            | DatasetKind::ClusterHistograms
+            | DatasetKind::ClusterNonZero => SchemaVersion::new(1, 0),
+            // 1.1: added deployment_block
+            DatasetKind::NonZeroProportions => SchemaVersion::new(1, 1),
+            // 1.1: added fraction_above_1000_dollars; 1.2: trimmed_mean_cents
+            // and iqr_cents
+            DatasetKind::DistributionMetrics => SchemaVersion::new(1, 2),
+        }
+    }
+
+    /// Columns rows are sorted by, so output is identical across runs over
+    /// the same inputs. Ties keep the order the writer produced them in.
+    pub fn sort_columns(&self) -> &'static [&'static str] {
+        match self {
+            DatasetKind::IndividualRunningTotals => &["block_number", "markout_time", "pool_address"],
+            DatasetKind::AggregateRunningTotals => &["block_number", "markout_time"],
+            DatasetKind::PoolTotals
+            | DatasetKind::MonthlyPoolTotals
+            | DatasetKind::ActivityRuns
+            | DatasetKind::MaxLvr
+            | DatasetKind::NonZeroProportions
+            | DatasetKind::QuartilePlots
+            | DatasetKind::DistributionMetrics => &["pool_address", "markout_time"],
+            DatasetKind::Concentration => &["markout_time"],
+            DatasetKind::PoolCorrelations => &["markout_time", "pool_a", "pool_b"],
+            DatasetKind::Histograms => &["pool_address", "markout_time", "bucket_range_start"],
+            DatasetKind::EquiDepthHistograms => &["pool_address", "markout_time", "bin"],
+            DatasetKind::PercentileBands => &["pool_address", "markout_time", "start_block"],
+            DatasetKind::DailyTimeSeries => &["markout_time", "start_block"],
+            DatasetKind::RollingSeries { .. } => &["markout_time", "pool_address", "end_block"],
+            DatasetKind::ClusterProportions
+            | DatasetKind::ClusterMonthlyTotals
+            | DatasetKind::ClusterNonZero => &["cluster_name", "markout_time"],
+            DatasetKind::ClusterHistograms => &["cluster_name", "markout_time", "bucket_range_start"],
+        }
+    }
+
+    /// Footer metadata identifying this dataset and the run that wrote it.
+    /// `block_range` spans the interval files it was computed from.
+    pub fn footer_metadata(
+        &self,
+        input_manifest: &str,
+        generated_at: &DateTime<Utc>,
+        block_range: Option<(u64, u64)>,
+    ) -> Vec<KeyValue> {
+        let mut metadata = vec![
+            KeyValue::new(DATASET_METADATA_KEY.to_string(), self.name().to_string()),
+            KeyValue::new(GENERATED_AT_METADATA_KEY.to_string(), generated_at.to_rfc3339()),
+            KeyValue::new(INPUT_MANIFEST_METADATA_KEY.to_string(), input_manifest.to_string()),
+        ];
+        metadata.extend(provenance_metadata(self.schema_version(), block_range));
+        metadata
+    }
+
+    /// Checks a file's footer against the schema this binary reads. Files
+    /// written before versioning and minor-version differences only warn.
+    pub fn check_footer(&self, metadata: &HashMap<String, String>) -> Result<()> {
+        let expected = self.schema_version();
+        let Some(version) = metadata.get(SCHEMA_VERSION_METADATA_KEY) else {
+            warn!("{} has no schema_version; it predates versioned precomputed files", self.path());
+            return Ok(());
+        };
+        let version = SchemaVersion::parse(version)?;
+
+        if let Some(dataset) = metadata.get(DATASET_METADATA_KEY) {
+            if dataset != self.name() {
+                return Err(anyhow::anyhow!(
+                    "{} holds dataset '{}', expected '{}'", self.path(), dataset, self.name()
+                ));
+            }
+        }
+
+        if version.major != expected.major {
+            return Err(anyhow::anyhow!(
+                "Precomputed data needs regeneration: {} has schema_version {} but this binary reads {}",
+                self.path(), version, expected
+            ));
+        }
+        if version.minor != expected.minor {
+            warn!(
+                "{} has schema_version {}, this binary expects {}",
+                self.path(), version, expected
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Reads the `lvr.*` footer entries of a parquet file
+pub fn read_footer_metadata(bytes: Bytes) -> Result<HashMap<String, String>> {
+    let reader = SerializedFileReader::new(bytes).context("Failed to read parquet footer")?;
+    Ok(footer_metadata(reader.metadata().file_metadata()))
+}
+
+/// The `lvr.*` entries of an already parsed footer
+pub fn footer_metadata(metadata: &FileMetaData) -> HashMap<String, String> {
+    metadata
+        .key_value_metadata()
+        .into_iter()
+        .flatten()
+        .filter(|kv| kv.key.starts_with("lvr."))
+        .filter_map(|kv| kv.value.clone().map(|value| (kv.key.clone(), value)))
+        .collect()
+}
+
+/// FNV-1a hash of the input files a precompute run read, as hex. Entries
+/// are hashed in sorted order so listing order does not matter.
+pub fn input_manifest_hash(mut entries: Vec<(String, u64)>) -> String {
+    entries.sort();
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for (location, size) in entries {
+        for byte in location.bytes().chain(size.to_le_bytes()) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    format!("{:016x}", hash)
+}
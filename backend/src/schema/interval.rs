@@ -0,0 +1,205 @@
+use arrow::{
+    array::{Array, Int64Array, ListArray, ListBuilder, StringArray, StringBuilder, UInt64Array, UInt64Builder},
+    compute::cast,
+    datatypes::{DataType, Field, Schema, SchemaRef},
+    record_batch::RecordBatch,
+};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use anyhow::{Context, Result};
+use tracing::warn;
+use crate::models::{Cents, DataSource, IntervalData, MarkoutTime};
+use super::SchemaVersion;
+
+/// Layout version recorded in interval file footers
+pub const INTERVAL_SCHEMA_VERSION: SchemaVersion = SchemaVersion::new(1, 0);
+
+// Column names of `intervals/{start}_{end}.parquet`, shared by the writer,
+// the precompute readers and the validator
+pub const INTERVAL_ID_COLUMN: &str = "interval_id";
+pub const INTERVAL_PAIR_ADDRESS_COLUMN: &str = "pair_address";
+pub const INTERVAL_MARKOUT_TIME_COLUMN: &str = "markout_time";
+pub const INTERVAL_TOTAL_LVR_COLUMN: &str = "total_lvr_cents";
+pub const INTERVAL_MAX_LVR_COLUMN: &str = "max_lvr_cents";
+pub const INTERVAL_NON_ZERO_COUNT_COLUMN: &str = "non_zero_count";
+pub const INTERVAL_TOTAL_COUNT_COLUMN: &str = "total_count";
+/// First block an interval covers; later than the interval's slot for
+/// pools deployed inside it
+pub const INTERVAL_START_BLOCK_COLUMN: &str = "start_block";
+/// Block after the last one an interval covers; the chunk's end block for
+/// an interval cut short by it
+pub const INTERVAL_END_BLOCK_COLUMN: &str = "end_block";
+/// Names of the sources an interval's values came from, and how many of
+/// its blocks each one gave; lists of equal length. Older files lack both.
+pub const INTERVAL_SOURCE_NAMES_COLUMN: &str = "source_names";
+pub const INTERVAL_SOURCE_COUNTS_COLUMN: &str = "source_counts";
+
+/// Column names accepted from older interval files, as (legacy, canonical)
+pub const LEGACY_INTERVAL_COLUMNS: &[(&str, &str)] = &[
+    ("pool_address", INTERVAL_PAIR_ADDRESS_COLUMN),
+];
+
+static LEGACY_WARNING_LOGGED: AtomicBool = AtomicBool::new(false);
+
+/// Canonical Arrow schema of an interval file
+pub fn interval_schema() -> SchemaRef {
+    let list = |item: DataType| DataType::List(Arc::new(Field::new("item", item, true)));
+    Arc::new(Schema::new(vec![
+        Field::new(INTERVAL_ID_COLUMN, DataType::UInt64, false),
+        Field::new(INTERVAL_PAIR_ADDRESS_COLUMN, DataType::Utf8, false),
+        Field::new(INTERVAL_MARKOUT_TIME_COLUMN, DataType::Utf8, false),
+        Field::new(INTERVAL_TOTAL_LVR_COLUMN, DataType::Int64, false),
+        Field::new(INTERVAL_MAX_LVR_COLUMN, DataType::UInt64, false),
+        Field::new(INTERVAL_NON_ZERO_COUNT_COLUMN, DataType::UInt64, false),
+        Field::new(INTERVAL_TOTAL_COUNT_COLUMN, DataType::UInt64, false),
+        Field::new(INTERVAL_START_BLOCK_COLUMN, DataType::UInt64, false),
+        Field::new(INTERVAL_END_BLOCK_COLUMN, DataType::UInt64, false),
+        Field::new(INTERVAL_SOURCE_NAMES_COLUMN, list(DataType::Utf8), false),
+        Field::new(INTERVAL_SOURCE_COUNTS_COLUMN, list(DataType::UInt64), false),
+    ]))
+}
+
+/// Renames legacy columns of an interval batch to their canonical names and
+/// widens the unsigned `total_lvr_cents` of files written before LVR was
+/// signed. Batches already in the canonical schema are returned unchanged.
+pub fn normalize_interval_batch(batch: RecordBatch) -> Result<RecordBatch> {
+    let schema = batch.schema();
+    let needs_rename = schema.fields().iter().any(|field| {
+        LEGACY_INTERVAL_COLUMNS.iter().any(|(legacy, _)| field.name() == legacy)
+    });
+    let needs_cast = schema.fields().iter().any(|field| {
+        field.name() == INTERVAL_TOTAL_LVR_COLUMN && field.data_type() == &DataType::UInt64
+    });
+    if !needs_rename && !needs_cast {
+        return Ok(batch);
+    }
+
+    let fields: Vec<Field> = schema.fields().iter()
+        .map(|field| {
+            match LEGACY_INTERVAL_COLUMNS.iter().find(|(legacy, _)| field.name() == legacy) {
+                Some((legacy, canonical)) => {
+                    if !LEGACY_WARNING_LOGGED.swap(true, Ordering::Relaxed) {
+                        warn!(
+                            "Interval file uses deprecated column '{}', reading it as '{}'; \
+                             regenerate intervals to use the current schema",
+                            legacy, canonical
+                        );
+                    }
+                    field.as_ref().clone().with_name(*canonical)
+                }
+                None if field.name() == INTERVAL_TOTAL_LVR_COLUMN => {
+                    field.as_ref().clone().with_data_type(DataType::Int64)
+                }
+                None => field.as_ref().clone(),
+            }
+        })
+        .collect();
+
+    let columns = schema.fields().iter()
+        .zip(batch.columns())
+        .map(|(field, column)| {
+            if field.name() == INTERVAL_TOTAL_LVR_COLUMN && field.data_type() == &DataType::UInt64 {
+                cast(column, &DataType::Int64).context("Failed to widen legacy total_lvr_cents")
+            } else {
+                Ok(column.clone())
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let normalized = Schema::new_with_metadata(fields, schema.metadata().clone());
+    RecordBatch::try_new(Arc::new(normalized), columns)
+        .context("Failed to normalize legacy interval columns")
+}
+
+impl IntervalData {
+    /// A batch of `rows` in `interval_schema`
+    pub fn to_record_batch(rows: &[IntervalData]) -> Result<RecordBatch> {
+        let mut source_names = ListBuilder::new(StringBuilder::new());
+        let mut source_counts = ListBuilder::new(UInt64Builder::new());
+        for row in rows {
+            source_names.append_value(row.source_counts.keys().map(|source| Some(source.as_str())));
+            source_counts.append_value(row.source_counts.values().map(|&count| Some(count)));
+        }
+
+        RecordBatch::try_new(interval_schema(), vec![
+            Arc::new(UInt64Array::from_iter_values(rows.iter().map(|d| d.interval_id))),
+            Arc::new(StringArray::from_iter_values(rows.iter().map(|d| d.pair_address.as_str()))),
+            Arc::new(StringArray::from_iter_values(rows.iter().map(|d| d.markout_time.to_string()))),
+            Arc::new(Int64Array::from_iter_values(rows.iter().map(|d| d.total_lvr_cents.as_i64()))),
+            Arc::new(UInt64Array::from_iter_values(rows.iter().map(|d| d.max_lvr_cents))),
+            Arc::new(UInt64Array::from_iter_values(rows.iter().map(|d| d.non_zero_count))),
+            Arc::new(UInt64Array::from_iter_values(rows.iter().map(|d| d.total_count))),
+            Arc::new(UInt64Array::from_iter_values(rows.iter().map(|d| d.start_block))),
+            Arc::new(UInt64Array::from_iter_values(rows.iter().map(|d| d.end_block))),
+            Arc::new(source_names.finish()),
+            Arc::new(source_counts.finish()),
+        ]).context("Failed to create interval data record batch")
+    }
+
+    /// Reads every row of an interval batch, normalizing legacy columns first
+    pub fn from_record_batch(batch: &RecordBatch) -> Result<Vec<Self>> {
+        let batch = normalize_interval_batch(batch.clone())?;
+        fn column<'a, A: Array + 'static>(batch: &'a RecordBatch, name: &str) -> Result<&'a A> {
+            batch.column_by_name(name)
+                .and_then(|column| column.as_any().downcast_ref::<A>())
+                .with_context(|| format!("Interval file is missing column {}", name))
+        }
+
+        let interval_ids = column::<UInt64Array>(&batch, INTERVAL_ID_COLUMN)?;
+        let pair_addresses = column::<StringArray>(&batch, INTERVAL_PAIR_ADDRESS_COLUMN)?;
+        let markout_times = column::<StringArray>(&batch, INTERVAL_MARKOUT_TIME_COLUMN)?;
+        let total_lvr = column::<Int64Array>(&batch, INTERVAL_TOTAL_LVR_COLUMN)?;
+        let max_lvr = column::<UInt64Array>(&batch, INTERVAL_MAX_LVR_COLUMN)?;
+        let non_zero_counts = column::<UInt64Array>(&batch, INTERVAL_NON_ZERO_COUNT_COLUMN)?;
+        let total_counts = column::<UInt64Array>(&batch, INTERVAL_TOTAL_COUNT_COLUMN)?;
+        let start_blocks = column::<UInt64Array>(&batch, INTERVAL_START_BLOCK_COLUMN)?;
+        let end_blocks = column::<UInt64Array>(&batch, INTERVAL_END_BLOCK_COLUMN)?;
+        // Files written before provenance was recorded lack both
+        let sources = match (
+            column::<ListArray>(&batch, INTERVAL_SOURCE_NAMES_COLUMN),
+            column::<ListArray>(&batch, INTERVAL_SOURCE_COUNTS_COLUMN),
+        ) {
+            (Ok(names), Ok(counts)) => Some((names, counts)),
+            _ => None,
+        };
+
+        (0..batch.num_rows())
+            .map(|i| {
+                let markout_time = markout_times.value(i)
+                    .parse::<MarkoutTime>()
+                    .with_context(|| format!("Invalid markout_time in interval file: {}", markout_times.value(i)))?;
+
+                let mut source_counts = BTreeMap::new();
+                if let Some((names, counts)) = sources {
+                    let (names, counts) = (names.value(i), counts.value(i));
+                    let names = names.as_any().downcast_ref::<StringArray>()
+                        .context("Interval source names are not strings")?;
+                    let counts = counts.as_any().downcast_ref::<UInt64Array>()
+                        .context("Interval source counts are not UInt64")?;
+                    if names.len() != counts.len() {
+                        return Err(anyhow::anyhow!(
+                            "Interval {} of {} has mismatched source lists", interval_ids.value(i), pair_addresses.value(i)
+                        ));
+                    }
+                    for j in 0..names.len() {
+                        *source_counts.entry(names.value(j).parse::<DataSource>()?).or_insert(0) += counts.value(j);
+                    }
+                }
+
+                Ok(Self {
+                    interval_id: interval_ids.value(i),
+                    pair_address: pair_addresses.value(i).to_string(),
+                    markout_time,
+                    total_lvr_cents: Cents(total_lvr.value(i)),
+                    max_lvr_cents: max_lvr.value(i),
+                    non_zero_count: non_zero_counts.value(i),
+                    total_count: total_counts.value(i),
+                    start_block: start_blocks.value(i),
+                    end_block: end_blocks.value(i),
+                    source_counts,
+                })
+            })
+            .collect()
+    }
+}
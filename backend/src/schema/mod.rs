@@ -0,0 +1,12 @@
+mod checkpoint;
+mod chunk_summary;
+mod dataset;
+mod interval;
+mod provenance;
+mod raw;
+pub use checkpoint::*;
+pub use chunk_summary::*;
+pub use dataset::*;
+pub use interval::*;
+pub use provenance::*;
+pub use raw::*;
@@ -0,0 +1,36 @@
+use parquet::file::metadata::KeyValue;
+use std::sync::OnceLock;
+use super::{SchemaVersion, CRATE_VERSION_METADATA_KEY, GIT_HASH_METADATA_KEY, SCHEMA_VERSION_METADATA_KEY};
+
+// Footer keys recorded in every file the pipeline writes, beside the crate
+// version and schema version
+pub const RUN_ID_METADATA_KEY: &str = "lvr.run_id";
+pub const START_BLOCK_METADATA_KEY: &str = "lvr.start_block";
+pub const END_BLOCK_METADATA_KEY: &str = "lvr.end_block";
+
+/// Identifies this process in the footers it writes: a UUID generated once
+/// per invocation
+pub fn run_id() -> &'static str {
+    static RUN_ID: OnceLock<String> = OnceLock::new();
+    RUN_ID.get_or_init(|| uuid::Uuid::new_v4().to_string())
+}
+
+/// Footer entries naming the binary and run that wrote a file, the layout
+/// version of its schema and, when known, the blocks `start..end` its data
+/// was computed from
+pub fn provenance_metadata(schema_version: SchemaVersion, block_range: Option<(u64, u64)>) -> Vec<KeyValue> {
+    let mut metadata = vec![
+        KeyValue::new(SCHEMA_VERSION_METADATA_KEY.to_string(), schema_version.to_string()),
+        KeyValue::new(CRATE_VERSION_METADATA_KEY.to_string(), env!("CARGO_PKG_VERSION").to_string()),
+        KeyValue::new(RUN_ID_METADATA_KEY.to_string(), run_id().to_string()),
+    ];
+    if let Some((start_block, end_block)) = block_range {
+        metadata.push(KeyValue::new(START_BLOCK_METADATA_KEY.to_string(), start_block.to_string()));
+        metadata.push(KeyValue::new(END_BLOCK_METADATA_KEY.to_string(), end_block.to_string()));
+    }
+    // Set by the release build; local builds simply omit it
+    if let Some(git_hash) = option_env!("LVR_GIT_HASH") {
+        metadata.push(KeyValue::new(GIT_HASH_METADATA_KEY.to_string(), git_hash.to_string()));
+    }
+    metadata
+}
@@ -0,0 +1,36 @@
+use arrow::{
+    array::{Int64Array, StringArray, UInt64Array},
+    datatypes::{DataType, Field, Schema, SchemaRef},
+    record_batch::RecordBatch,
+};
+use std::sync::Arc;
+use anyhow::{Context, Result};
+use crate::models::RawLvrRow;
+use super::SchemaVersion;
+
+/// Layout version recorded in raw file footers
+pub const RAW_SCHEMA_VERSION: SchemaVersion = SchemaVersion::new(1, 0);
+
+// Column names of `raw/{pool}/{start}_{end}.parquet`. Only blocks with a
+// value are stored; every other block of the chunk is zero.
+pub const RAW_BLOCK_NUMBER_COLUMN: &str = "block_number";
+pub const RAW_MARKOUT_TIME_COLUMN: &str = "markout_time";
+pub const RAW_LVR_COLUMN: &str = "lvr_cents";
+
+/// Arrow schema of a raw per-block file
+pub fn raw_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new(RAW_BLOCK_NUMBER_COLUMN, DataType::UInt64, false),
+        Field::new(RAW_MARKOUT_TIME_COLUMN, DataType::Utf8, false),
+        Field::new(RAW_LVR_COLUMN, DataType::Int64, false),
+    ]))
+}
+
+/// A batch of `rows` in `raw_schema`
+pub fn raw_record_batch(rows: &[RawLvrRow]) -> Result<RecordBatch> {
+    RecordBatch::try_new(raw_schema(), vec![
+        Arc::new(UInt64Array::from_iter_values(rows.iter().map(|row| row.block_number))),
+        Arc::new(StringArray::from_iter_values(rows.iter().map(|row| row.markout_time.to_string()))),
+        Arc::new(Int64Array::from_iter_values(rows.iter().map(|row| row.lvr_cents.as_i64()))),
+    ]).context("Failed to create raw LVR record batch")
+}
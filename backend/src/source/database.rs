@@ -0,0 +1,246 @@
+use crate::{
+    aurora::{AuroraConnection, LVRDetails}, brontes::BrontesConnection, config::{AuroraConfig, AuroraQueryLimits, BrontesConfig, BrontesQueryLimits, RetryConfig},
+    models::{Address, DataSource, MarkoutTime, UnifiedLVRData}, source::{to_cents, LvrSource, SourceHead, TheoreticalStream}, storage::RetryPolicy,
+    Error, MetricsRegistry, PoolRegistry, MARKOUT_TIMES, MARKOUT_TIME_MAPPING,
+};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
+use ordered_float::OrderedFloat;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tracing::debug;
+
+/// Theoretical LVR from Aurora, realized LVR from Brontes
+pub struct DatabaseSource {
+    aurora_connection: AuroraConnection,
+    brontes_connection: BrontesConnection,
+    retry_policy: RetryPolicy,
+}
+
+impl DatabaseSource {
+    pub fn new(aurora_connection: AuroraConnection, brontes_connection: BrontesConnection) -> Self {
+        Self {
+            aurora_connection,
+            brontes_connection,
+            retry_policy: RetryConfig::default().database,
+        }
+    }
+
+    pub fn from_env() -> Result<Self, Error> {
+        let aurora_connection = AuroraConnection::new(AuroraConfig::from_env()?)?;
+        let brontes_connection = BrontesConnection::new(BrontesConfig::from_env()?)?;
+        Ok(Self::new(aurora_connection, brontes_connection))
+    }
+
+    /// Batch size and query concurrency of the Aurora connection
+    pub fn with_aurora_limits(mut self, limits: AuroraQueryLimits) -> Self {
+        self.aurora_connection = self.aurora_connection.with_limits(limits);
+        self
+    }
+
+    pub fn with_brontes_limits(mut self, limits: BrontesQueryLimits) -> Self {
+        self.brontes_connection = self.brontes_connection.with_limits(limits);
+        self
+    }
+
+    /// Registers the Aurora pool and query metrics in `metrics`
+    pub fn with_metrics(mut self, metrics: Arc<MetricsRegistry>) -> Self {
+        self.aurora_connection = self.aurora_connection.with_metrics(metrics);
+        self
+    }
+
+    /// Policy for each batch query against Aurora and Brontes
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+}
+
+#[async_trait]
+impl LvrSource for DatabaseSource {
+    async fn fetch_theoretical(
+        &self,
+        pools: &PoolRegistry,
+        markout_time: MarkoutTime,
+        chunk_start: u64,
+        chunk_end: u64,
+    ) -> Result<Vec<UnifiedLVRData>> {
+        self.stream_theoretical(pools, markout_time, chunk_start, chunk_end)
+            .await?
+            .rows
+            .try_collect()
+            .await
+    }
+
+    /// Each details row is split into its pools' values and dropped as soon
+    /// as it arrives
+    async fn stream_theoretical<'a>(
+        &'a self,
+        pools: &'a PoolRegistry,
+        markout_time: MarkoutTime,
+        chunk_start: u64,
+        chunk_end: u64,
+    ) -> Result<TheoreticalStream<'a>> {
+        let index = markout_time.as_f64()
+            .and_then(|time| MARKOUT_TIME_MAPPING.get(&OrderedFloat(time)))
+            .context("Invalid markout time mapping")?;
+        let details = self.aurora_connection
+            .stream_lvr_details(*index, chunk_start, chunk_end, &self.retry_policy)
+            .await?;
+        Ok(theoretical_from_details(details, pools))
+    }
+
+    async fn fetch_realized(&self, pools: &PoolRegistry, chunk_start: u64, chunk_end: u64) -> Result<Vec<UnifiedLVRData>> {
+        let addresses: Vec<String> = pools.brontes_addresses().map(ToString::to_string).collect();
+        let analysis = self.brontes_connection
+            .fetch_lvr_analysis(&addresses, chunk_start, chunk_end, &self.retry_policy)
+            .await?;
+
+        // Rows whose address or value does not parse are dropped
+        Ok(analysis.into_iter()
+            .filter_map(|result| Some(UnifiedLVRData {
+                pool_address: Address::from_any_case(&result.pool_address).ok()?,
+                block_number: result.block_number,
+                lvr_cents: to_cents(result.lvr).ok()?,
+                source: DataSource::Brontes,
+            }))
+            .collect())
+    }
+
+    async fn latest_blocks(&self) -> Result<Vec<SourceHead>> {
+        let aurora = MARKOUT_TIMES.iter().map(|&time| async move {
+            let index = MARKOUT_TIME_MAPPING.get(&OrderedFloat(time)).context("Invalid markout time mapping")?;
+            Ok::<_, anyhow::Error>(SourceHead {
+                name: format!("aurora markout {:.1}", time),
+                latest_block: self.aurora_connection.latest_block(*index).await?,
+            })
+        });
+        let (mut heads, brontes) = tokio::try_join!(
+            futures::future::try_join_all(aurora),
+            self.brontes_connection.latest_block()
+        )?;
+        heads.push(SourceHead { name: "brontes".to_string(), latest_block: brontes });
+        Ok(heads)
+    }
+}
+
+/// Splits each details row into its pools' values as it arrives, counting
+/// the rows that do not parse in full
+pub fn theoretical_from_details<'a>(
+    details: BoxStream<'a, Result<LVRDetails>>,
+    pools: &'a PoolRegistry,
+) -> TheoreticalStream<'a> {
+    let malformed_rows = Arc::new(AtomicU64::new(0));
+    let malformed = Arc::clone(&malformed_rows);
+    let rows = details
+        .map_ok(move |detail| {
+            let parsed = parse_lvr_details(&detail.details);
+            if parsed.is_malformed() {
+                malformed.fetch_add(1, Ordering::Relaxed);
+                debug!(
+                    "Malformed LVR details for block {} at index {}: {} bad entries, row parsed: {}",
+                    detail.block_number, detail.index, parsed.malformed_entries, !parsed.malformed_row
+                );
+            }
+            stream::iter(parsed.pool_values(detail.block_number, pools).into_iter().map(Ok))
+        })
+        .try_flatten()
+        .boxed();
+    TheoreticalStream { rows, malformed_rows }
+}
+
+/// The value of one details entry, in either shape Aurora writes
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum LvrDetailValue {
+    /// `{"dollarValue": 12.5, ...}`; the other fields are ignored
+    Object {
+        #[serde(rename = "dollarValue")]
+        dollar_value: f64,
+    },
+    /// `12.5`
+    Bare(f64),
+}
+
+impl LvrDetailValue {
+    pub fn dollars(self) -> f64 {
+        match self {
+            Self::Object { dollar_value } | Self::Bare(dollar_value) => dollar_value,
+        }
+    }
+}
+
+/// An entry of a details row: a `[pool_name, value]` pair whose value is an
+/// `LvrDetailValue` encoded as a JSON string, or anything else
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LvrDetailEntry {
+    Pair(String, String),
+    Other(serde::de::IgnoredAny),
+}
+
+/// A details row parsed for every pool at once
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParsedLvrDetails {
+    /// Dollar LVR by pool name; the first usable entry of a pool wins
+    pub values: HashMap<String, f64>,
+    /// Entries that are not a pair of strings or whose value has neither
+    /// `LvrDetailValue` shape
+    pub malformed_entries: usize,
+    /// The row is not a JSON array, so it yielded nothing
+    pub malformed_row: bool,
+}
+
+impl ParsedLvrDetails {
+    /// Whether any part of the row was dropped for not parsing
+    pub fn is_malformed(&self) -> bool {
+        self.malformed_row || self.malformed_entries > 0
+    }
+
+    /// The value of every registry pool the row holds; each row holds every
+    /// pool's value for its block
+    pub fn pool_values(&self, block_number: u64, pools: &PoolRegistry) -> Vec<UnifiedLVRData> {
+        pools.pools()
+            .iter()
+            .zip(pools.addresses())
+            .filter_map(|(pool, address)| {
+                self.values.get(&pool.name)
+                    .and_then(|&lvr| to_cents(lvr).ok())
+                    .map(|cents| UnifiedLVRData {
+                        pool_address: address.clone(),
+                        block_number,
+                        lvr_cents: cents,
+                        source: DataSource::Aurora,
+                    })
+            })
+            .collect()
+    }
+}
+
+/// Parses a `details` string, a JSON array of `[pool_name, value]` entries.
+/// Entries and rows that do not parse are counted rather than failing it.
+pub fn parse_lvr_details(details_str: &str) -> ParsedLvrDetails {
+    let mut parsed = ParsedLvrDetails::default();
+    let Ok(entries) = serde_json::from_str::<Vec<LvrDetailEntry>>(details_str) else {
+        parsed.malformed_row = true;
+        return parsed;
+    };
+
+    for entry in entries {
+        let LvrDetailEntry::Pair(pool_name, value) = entry else {
+            parsed.malformed_entries += 1;
+            continue;
+        };
+        match serde_json::from_str::<LvrDetailValue>(&value) {
+            Ok(value) => {
+                parsed.values.entry(pool_name).or_insert(value.dollars());
+            }
+            Err(_) => parsed.malformed_entries += 1,
+        }
+    }
+
+    parsed
+}
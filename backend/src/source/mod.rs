@@ -0,0 +1,142 @@
+pub mod database;
+pub mod parquet_dump;
+
+pub use database::*;
+pub use parquet_dump::*;
+
+use crate::{config::AppConfig, models::{Cents, MarkoutTime, UnifiedLVRData}, Error, MetricsRegistry, PoolRegistry};
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::stream::{self, BoxStream, StreamExt};
+use object_store::local::LocalFileSystem;
+use std::{path::PathBuf, sync::{atomic::{AtomicU64, Ordering}, Arc}};
+use tracing::info;
+
+/// Where the processor reads LVR from. Rows may name any pool in any
+/// casing; the processor keeps the registry's pools and drops the rest.
+#[async_trait]
+pub trait LvrSource: Send + Sync {
+    /// Theoretical LVR of `markout_time` for the blocks in `chunk_start..chunk_end`
+    async fn fetch_theoretical(
+        &self,
+        pools: &PoolRegistry,
+        markout_time: MarkoutTime,
+        chunk_start: u64,
+        chunk_end: u64,
+    ) -> Result<Vec<UnifiedLVRData>>;
+
+    /// `fetch_theoretical` one row at a time, for sources that can hand rows
+    /// over before the whole chunk has been fetched. The default buffers.
+    async fn stream_theoretical<'a>(
+        &'a self,
+        pools: &'a PoolRegistry,
+        markout_time: MarkoutTime,
+        chunk_start: u64,
+        chunk_end: u64,
+    ) -> Result<TheoreticalStream<'a>> {
+        let rows = self.fetch_theoretical(pools, markout_time, chunk_start, chunk_end).await?;
+        Ok(TheoreticalStream::new(stream::iter(rows.into_iter().map(Ok)).boxed()))
+    }
+
+    /// Realized LVR for the blocks in `chunk_start..chunk_end`. Blocks
+    /// without a row are zeros.
+    async fn fetch_realized(&self, pools: &PoolRegistry, chunk_start: u64, chunk_end: u64) -> Result<Vec<UnifiedLVRData>>;
+
+    /// The latest block each of the source's queries has data for. Empty
+    /// when the source cannot tell, which the default does.
+    async fn latest_blocks(&self) -> Result<Vec<SourceHead>> {
+        Ok(Vec::new())
+    }
+}
+
+/// The latest block one query of a source has data for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceHead {
+    /// The query, as logged: `aurora markout 0.5`, `brontes`
+    pub name: String,
+    /// `None` when it has no data at all
+    pub latest_block: Option<u64>,
+}
+
+/// The rows `stream_theoretical` yields, and how many source rows it
+/// dropped for not parsing. The count is final once `rows` has ended.
+pub struct TheoreticalStream<'a> {
+    pub rows: BoxStream<'a, Result<UnifiedLVRData>>,
+    pub malformed_rows: Arc<AtomicU64>,
+}
+
+impl<'a> TheoreticalStream<'a> {
+    /// Rows of a source that drops nothing
+    pub fn new(rows: BoxStream<'a, Result<UnifiedLVRData>>) -> Self {
+        Self { rows, malformed_rows: Arc::new(AtomicU64::new(0)) }
+    }
+
+    pub fn malformed_rows(&self) -> u64 {
+        self.malformed_rows.load(Ordering::Relaxed)
+    }
+}
+
+/// Names the source when `lvr process` is run without `--source`
+pub const SOURCE_ENV_VAR: &str = "AURORA_SOURCE";
+
+/// The `--source` argument: `aurora` for the databases, `parquet:<path>`
+/// for a local parquet or CSV dump
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SourceSpec {
+    Database,
+    Parquet(PathBuf),
+}
+
+impl SourceSpec {
+    pub fn parse(spec: &str) -> Result<Self> {
+        match spec.split_once(':') {
+            None if spec == "aurora" => Ok(Self::Database),
+            Some(("parquet", path)) if !path.is_empty() => Ok(Self::Parquet(PathBuf::from(path))),
+            _ => Err(Error::Config(format!(
+                "Invalid source {:?}, expected \"aurora\" or \"parquet:<path>\"", spec
+            )).into()),
+        }
+    }
+
+    /// `arg` when given, else `AURORA_SOURCE`, else the databases
+    pub fn from_arg_or_env(arg: Option<&str>) -> Result<Self> {
+        match arg.map(String::from).or_else(|| std::env::var(SOURCE_ENV_VAR).ok()) {
+            Some(spec) => Self::parse(&spec),
+            None => Ok(Self::Database),
+        }
+    }
+
+    /// Opens the source; the database retry policy and query limits of
+    /// `config` apply to each database batch query, and the database
+    /// connections report to `metrics` when given
+    pub async fn open(&self, config: &AppConfig, metrics: Option<Arc<MetricsRegistry>>) -> Result<Arc<dyn LvrSource>> {
+        Ok(match self {
+            Self::Database => {
+                let (aurora, brontes) = (config.aurora, config.brontes);
+                info!(
+                    "Aurora queries fetch {} blocks each, at most {} at once, for up to {}s; Brontes queries run for up to {}s",
+                    aurora.batch_size, aurora.max_concurrent_queries, aurora.query_timeout_secs, brontes.query_timeout_secs
+                );
+                let mut source = DatabaseSource::from_env()?
+                    .with_retry_policy(config.retry.database.clone())
+                    .with_aurora_limits(aurora)
+                    .with_brontes_limits(brontes);
+                if let Some(metrics) = metrics {
+                    source = source.with_metrics(metrics);
+                }
+                Arc::new(source)
+            }
+            Self::Parquet(path) => {
+                let store = Arc::new(LocalFileSystem::new_with_prefix(path)?);
+                Arc::new(ParquetSource::open(store).await?)
+            }
+        })
+    }
+}
+
+/// Dollars to cents, rejecting values that do not fit an i64
+pub fn to_cents(value: f64) -> Result<Cents> {
+    Cents::from_dollars(value).ok_or_else(|| {
+        Error::Processing(format!("LVR value {} out of range for i64 cents representation", value)).into()
+    })
+}
@@ -0,0 +1,212 @@
+use crate::{
+    models::{Address, Cents, DataSource, MarkoutTime, UnifiedLVRData}, source::LvrSource, Error, PoolRegistry,
+};
+use anyhow::{Context, Result};
+use arrow::{
+    array::{Array, ArrayRef, Float64Array, Int64Array, StringArray, UInt64Array},
+    datatypes::{DataType, Field, Schema},
+    record_batch::RecordBatch,
+};
+use async_trait::async_trait;
+use object_store::{path::Path, ObjectStore};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReader;
+use std::{collections::HashMap, sync::Arc};
+use tracing::info;
+
+/// Theoretical rows: `pool_address` (Utf8), `block_number` (UInt64),
+/// `markout_time` (Float64) and `lvr_cents` (Int64)
+pub const THEORETICAL_DUMP_PATH: &str = "theoretical.parquet";
+/// Realized rows: `pool_address` (Utf8), `block_number` (UInt64) and
+/// `lvr_cents` (Int64)
+pub const REALIZED_DUMP_PATH: &str = "realized.parquet";
+/// Read when `THEORETICAL_DUMP_PATH` is missing: the same columns, with a
+/// header row naming them
+pub const THEORETICAL_CSV_DUMP_PATH: &str = "theoretical.csv";
+/// Read when `REALIZED_DUMP_PATH` is missing
+pub const REALIZED_CSV_DUMP_PATH: &str = "realized.csv";
+
+fn dump_schema(with_markout_time: bool) -> Schema {
+    let mut fields = vec![
+        Field::new("pool_address", DataType::Utf8, false),
+        Field::new("block_number", DataType::UInt64, false),
+    ];
+    if with_markout_time {
+        fields.push(Field::new("markout_time", DataType::Float64, false));
+    }
+    fields.push(Field::new("lvr_cents", DataType::Int64, false));
+    Schema::new(fields)
+}
+
+/// LVR read from a local parquet or CSV dump instead of the databases, so
+/// the pipeline runs without credentials. Both files are loaded when opened.
+pub struct ParquetSource {
+    /// Each markout's rows, sorted by block
+    theoretical: HashMap<MarkoutTime, Vec<UnifiedLVRData>>,
+    /// Sorted by block
+    realized: Vec<UnifiedLVRData>,
+}
+
+impl ParquetSource {
+    pub async fn open(store: Arc<dyn ObjectStore>) -> Result<Self> {
+        let mut theoretical: HashMap<MarkoutTime, Vec<UnifiedLVRData>> = HashMap::new();
+        let dump = read_dump(store.as_ref(), THEORETICAL_DUMP_PATH, THEORETICAL_CSV_DUMP_PATH, &dump_schema(true)).await?;
+        for batch in dump {
+            let markout_times = column::<Float64Array>(&batch, "markout_time")?;
+            for (i, data) in read_rows(&batch)?.into_iter().enumerate() {
+                let markout_time = MarkoutTime::from_f64(markout_times.value(i))
+                    .filter(|time| *time != MarkoutTime::Brontes)
+                    .ok_or_else(|| Error::Processing(format!(
+                        "Invalid markout_time {} in the theoretical dump", markout_times.value(i)
+                    )))?;
+                theoretical.entry(markout_time).or_default().push(data);
+            }
+        }
+
+        let mut realized = Vec::new();
+        for batch in read_dump(store.as_ref(), REALIZED_DUMP_PATH, REALIZED_CSV_DUMP_PATH, &dump_schema(false)).await? {
+            realized.extend(read_rows(&batch)?.into_iter().map(|data| UnifiedLVRData { source: DataSource::Brontes, ..data }));
+        }
+
+        // Stable, so rows for the same block keep their file order
+        for rows in theoretical.values_mut() {
+            rows.sort_by_key(|data| data.block_number);
+        }
+        realized.sort_by_key(|data| data.block_number);
+
+        info!(
+            "Loaded {} theoretical and {} realized rows from the parquet dump",
+            theoretical.values().map(Vec::len).sum::<usize>(),
+            realized.len()
+        );
+        Ok(Self { theoretical, realized })
+    }
+}
+
+#[async_trait]
+impl LvrSource for ParquetSource {
+    async fn fetch_theoretical(
+        &self,
+        _pools: &PoolRegistry,
+        markout_time: MarkoutTime,
+        chunk_start: u64,
+        chunk_end: u64,
+    ) -> Result<Vec<UnifiedLVRData>> {
+        Ok(self.theoretical
+            .get(&markout_time)
+            .map_or_else(Vec::new, |rows| block_range(rows, chunk_start, chunk_end).to_vec()))
+    }
+
+    async fn fetch_realized(&self, _pools: &PoolRegistry, chunk_start: u64, chunk_end: u64) -> Result<Vec<UnifiedLVRData>> {
+        Ok(block_range(&self.realized, chunk_start, chunk_end).to_vec())
+    }
+}
+
+/// The rows of block-sorted `rows` in `chunk_start..chunk_end`
+fn block_range(rows: &[UnifiedLVRData], chunk_start: u64, chunk_end: u64) -> &[UnifiedLVRData] {
+    let start = rows.partition_point(|data| data.block_number < chunk_start);
+    let end = rows.partition_point(|data| data.block_number < chunk_end);
+    &rows[start..end]
+}
+
+/// The parquet file at `path`, or when there is none the CSV file at
+/// `csv_path` parsed as `schema`
+async fn read_dump(store: &dyn ObjectStore, path: &str, csv_path: &str, schema: &Schema) -> Result<Vec<RecordBatch>> {
+    let bytes = match store.get(&Path::from(path)).await {
+        Ok(result) => result.bytes().await?,
+        Err(object_store::Error::NotFound { .. }) => {
+            let csv = store.get(&Path::from(csv_path))
+                .await
+                .with_context(|| format!("The dump has neither {} nor {}", path, csv_path))?
+                .bytes()
+                .await?;
+            return Ok(vec![read_csv(&csv, csv_path, schema)?]);
+        }
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {} from the dump", path)),
+    };
+
+    ParquetRecordBatchReader::try_new(bytes, 8192)
+        .map_err(|e| Error::Parquet(format!("Failed to open {}: {}", path, e)))?
+        .map(|batch| batch.map_err(|e| Error::Parquet(format!("Failed to read {}: {}", path, e)).into()))
+        .collect()
+}
+
+/// Comma separated `bytes` with a header row naming the columns of
+/// `schema`, in any order. Blank lines are skipped; fields are not quoted.
+fn read_csv(bytes: &[u8], path: &str, schema: &Schema) -> Result<RecordBatch> {
+    let text = std::str::from_utf8(bytes).map_err(|e| Error::Processing(format!("{} is not UTF-8: {}", path, e)))?;
+    let mut lines = text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+    let header: Vec<&str> = lines.next().map_or_else(Vec::new, |(_, line)| line.split(',').map(str::trim).collect());
+    let positions = schema
+        .fields()
+        .iter()
+        .map(|field| {
+            header.iter().position(|name| name == field.name()).ok_or_else(|| {
+                Error::Processing(format!("{} has no {} column", path, field.name())).into()
+            })
+        })
+        .collect::<Result<Vec<usize>>>()?;
+
+    let mut columns: Vec<Vec<&str>> = vec![Vec::new(); positions.len()];
+    for (number, line) in lines {
+        let values: Vec<&str> = line.split(',').map(str::trim).collect();
+        if values.len() != header.len() {
+            return Err(Error::Processing(format!(
+                "{} line {} has {} fields, the header {}", path, number + 1, values.len(), header.len()
+            )).into());
+        }
+        for (column, &position) in columns.iter_mut().zip(&positions) {
+            column.push(values[position]);
+        }
+    }
+
+    let arrays = schema
+        .fields()
+        .iter()
+        .zip(columns)
+        .map(|(field, values)| {
+            let invalid = |value: &str| Error::Processing(format!("Invalid {} {:?} in {}", field.name(), value, path));
+            let array: ArrayRef = match field.data_type() {
+                DataType::UInt64 => Arc::new(UInt64Array::from(
+                    values.iter().map(|v| v.parse().map_err(|_| invalid(v))).collect::<Result<Vec<u64>, _>>()?
+                )),
+                DataType::Int64 => Arc::new(Int64Array::from(
+                    values.iter().map(|v| v.parse().map_err(|_| invalid(v))).collect::<Result<Vec<i64>, _>>()?
+                )),
+                DataType::Float64 => Arc::new(Float64Array::from(
+                    values.iter().map(|v| v.parse().map_err(|_| invalid(v))).collect::<Result<Vec<f64>, _>>()?
+                )),
+                _ => Arc::new(StringArray::from(values)),
+            };
+            Ok(array)
+        })
+        .collect::<Result<Vec<ArrayRef>>>()?;
+
+    RecordBatch::try_new(Arc::new(schema.clone()), arrays)
+        .map_err(|e| Error::Processing(format!("Failed to read {}: {}", path, e)).into())
+}
+
+/// The columns both dump files share, tagged as Aurora rows
+fn read_rows(batch: &RecordBatch) -> Result<Vec<UnifiedLVRData>> {
+    let pool_addresses = column::<StringArray>(batch, "pool_address")?;
+    let block_numbers = column::<UInt64Array>(batch, "block_number")?;
+    let lvr_cents = column::<Int64Array>(batch, "lvr_cents")?;
+
+    (0..batch.num_rows())
+        .map(|i| {
+            let pool_address = Address::from_any_case(pool_addresses.value(i))
+                .map_err(|e| Error::Processing(format!("Invalid pool_address in the dump: {}", e)))?;
+            Ok(UnifiedLVRData {
+                pool_address,
+                block_number: block_numbers.value(i),
+                lvr_cents: Cents(lvr_cents.value(i)),
+                source: DataSource::Aurora,
+            })
+        })
+        .collect()
+}
+
+fn column<'a, A: Array + 'static>(batch: &'a RecordBatch, name: &str) -> Result<&'a A> {
+    batch.column_by_name(name)
+        .and_then(|column| column.as_any().downcast_ref::<A>())
+        .ok_or_else(|| Error::Parquet(format!("Parquet dump is missing column {} or it has the wrong type", name)).into())
+}
@@ -0,0 +1,95 @@
+use anyhow::{Context, Result};
+use bytes::Bytes;
+use object_store::{path::Path, ObjectStore};
+use parquet::{
+    arrow::arrow_reader::ParquetRecordBatchReader,
+    file::{metadata::ParquetMetaDataReader, FOOTER_SIZE},
+};
+use tracing::warn;
+use super::{is_retryable, retry_with, RetryPolicy};
+
+/// Marker `atomic_put` puts in the names of its temporary objects
+pub const TEMPORARY_OBJECT_MARKER: &str = ".tmp-";
+
+/// Uploads a parquet file to a temporary key beside `path`, confirms the
+/// upload is complete (its size from a head request and the footer's row
+/// count from ranged reads), then copies it into place. Readers
+/// of `path` see either the previous object or the full new one, never a
+/// truncated upload. The temporary object is removed whether or not this succeeds.
+pub async fn atomic_put(
+    store: &dyn ObjectStore,
+    path: &Path,
+    payload: Bytes,
+    expected_rows: usize,
+) -> Result<()> {
+    let temp_path = Path::from(format!("{}{}{}", path, TEMPORARY_OBJECT_MARKER, uuid::Uuid::new_v4()));
+    let expected_bytes = payload.len();
+
+    let result = async {
+        store.put(&temp_path, payload.into()).await
+            .with_context(|| format!("Failed to upload {}", temp_path))?;
+
+        let uploaded_bytes = store.head(&temp_path).await?.size;
+        if uploaded_bytes != expected_bytes {
+            return Err(anyhow::anyhow!(
+                "Uploaded object {} is {} bytes, expected {}", temp_path, uploaded_bytes, expected_bytes
+            ));
+        }
+        let rows = footer_rows(store, &temp_path, uploaded_bytes).await
+            .with_context(|| format!("Uploaded object {} is not valid parquet", temp_path))?;
+        if rows != expected_rows as i64 {
+            return Err(anyhow::anyhow!(
+                "Uploaded object {} has {} rows, expected {}", temp_path, rows, expected_rows
+            ));
+        }
+
+        store.copy(&temp_path, path).await
+            .with_context(|| format!("Failed to move {} into place", temp_path))?;
+        Ok(())
+    }.await;
+
+    if let Err(e) = store.delete(&temp_path).await {
+        if !matches!(e, object_store::Error::NotFound { .. }) {
+            warn!("Failed to remove temporary object {}: {}", temp_path, e);
+        }
+    }
+
+    result
+}
+
+/// The row count in the footer of the `size`-byte parquet object at `path`,
+/// read without fetching the rest of the object
+async fn footer_rows(store: &dyn ObjectStore, path: &Path, size: usize) -> Result<i64> {
+    let footer_start = size.checked_sub(FOOTER_SIZE).context("Object is smaller than a parquet footer")?;
+    let footer = store.get_range(path, footer_start..size).await?;
+    let metadata_len = ParquetMetaDataReader::decode_footer(footer.as_ref().try_into()?)?;
+    let metadata_start = footer_start.checked_sub(metadata_len).context("Parquet footer is longer than the object")?;
+    let metadata = store.get_range(path, metadata_start..footer_start).await?;
+    Ok(ParquetMetaDataReader::decode_metadata(&metadata)?.file_metadata().num_rows())
+}
+
+/// `atomic_put` under `policy`. A failed verification (a truncated upload)
+/// is retried like any other transient error; permanent store errors are not.
+/// Returns how many attempts failed before one succeeded.
+pub async fn retry_atomic_put(
+    store: &dyn ObjectStore,
+    path: &Path,
+    payload: Bytes,
+    expected_rows: usize,
+    policy: &RetryPolicy,
+) -> Result<u32> {
+    let retryable = |e: &anyhow::Error| e.downcast_ref::<object_store::Error>().is_none_or(is_retryable);
+    let mut attempts = 0;
+    retry_with(policy, path.as_ref(), retryable, || {
+        attempts += 1;
+        atomic_put(store, path, payload.clone(), expected_rows)
+    }).await?;
+    Ok(attempts - 1)
+}
+
+/// Opens the parquet object read from `path`, failing with an error that
+/// names it when its footer is missing or corrupt
+pub fn parquet_reader(bytes: Bytes, path: &Path, batch_size: usize) -> Result<ParquetRecordBatchReader> {
+    ParquetRecordBatchReader::try_new(bytes, batch_size)
+        .with_context(|| format!("{} is not readable parquet (truncated or corrupt footer)", path))
+}
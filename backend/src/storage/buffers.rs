@@ -0,0 +1,66 @@
+use anyhow::Result;
+use arrow::record_batch::RecordBatch;
+use bytes::Bytes;
+use parquet::{arrow::ArrowWriter, file::properties::WriterProperties};
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Scratch buffers kept at most, one per write that can be encoding at once
+pub const MAX_POOLED_BUFFERS: usize = 16;
+/// Buffers that grew past this are freed rather than kept
+pub const MAX_POOLED_BUFFER_BYTES: usize = 16 * 1024 * 1024;
+
+/// Scratch buffers parquet files are encoded into. A buffer keeps the
+/// capacity earlier files grew it to, so encoding a file does not regrow a
+/// fresh `Vec` from empty; the result is copied out once at its final size,
+/// and that one `Bytes` is what every upload attempt shares.
+#[derive(Debug, Default)]
+pub struct BufferPool {
+    buffers: Mutex<Vec<Vec<u8>>>,
+}
+
+impl BufferPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The pool every writer uses unless given another
+    pub fn shared() -> Arc<BufferPool> {
+        static SHARED: OnceLock<Arc<BufferPool>> = OnceLock::new();
+        SHARED.get_or_init(|| Arc::new(BufferPool::new())).clone()
+    }
+
+    /// `batch` as a complete parquet file
+    pub fn encode_parquet(&self, batch: &RecordBatch, props: WriterProperties) -> Result<Bytes> {
+        let mut buffer = self.take();
+        let encoded = Self::encode_into(&mut buffer, batch, props).map(|()| Bytes::copy_from_slice(&buffer));
+        self.give_back(buffer);
+        encoded
+    }
+
+    /// Buffers waiting to be reused
+    pub fn pooled(&self) -> usize {
+        self.buffers.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    fn encode_into(buffer: &mut Vec<u8>, batch: &RecordBatch, props: WriterProperties) -> Result<()> {
+        let mut writer = ArrowWriter::try_new(buffer, batch.schema(), Some(props))?;
+        writer.write(batch)?;
+        writer.close()?;
+        Ok(())
+    }
+
+    fn take(&self) -> Vec<u8> {
+        self.buffers.lock().unwrap_or_else(|e| e.into_inner()).pop().unwrap_or_default()
+    }
+
+    fn give_back(&self, mut buffer: Vec<u8>) {
+        if buffer.capacity() > MAX_POOLED_BUFFER_BYTES {
+            return;
+        }
+        buffer.clear();
+        let mut buffers = self.buffers.lock().unwrap_or_else(|e| e.into_inner());
+        if buffers.len() < MAX_POOLED_BUFFERS {
+            buffers.push(buffer);
+        }
+    }
+}
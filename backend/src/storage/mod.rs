@@ -0,0 +1,8 @@
+mod atomic;
+mod buffers;
+mod pruned;
+mod retry;
+pub use atomic::*;
+pub use buffers::*;
+pub use pruned::*;
+pub use retry::*;
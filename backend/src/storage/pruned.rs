@@ -0,0 +1,98 @@
+use anyhow::{Context, Result};
+use arrow::{
+    array::{RecordBatch, StringArray},
+    compute::kernels::cmp::eq,
+};
+use futures::TryStreamExt;
+use object_store::{path::Path, ObjectStore};
+use parquet::{
+    arrow::{
+        arrow_reader::{ArrowPredicate, ArrowPredicateFn, ArrowReaderOptions, RowFilter},
+        async_reader::ParquetObjectReader,
+        ParquetRecordBatchStreamBuilder, ProjectionMask,
+    },
+    file::metadata::{ColumnChunkMetaData, ParquetMetaData},
+};
+use std::sync::Arc;
+use crate::Error;
+
+/// What `read_matching_rows` read
+pub struct MatchingRows {
+    /// Footer of the whole file
+    pub metadata: Arc<ParquetMetaData>,
+    /// Only the rows matching every filter
+    pub batches: Vec<RecordBatch>,
+    /// Row groups left after pruning
+    pub row_groups_read: usize,
+}
+
+/// Whether the column chunk's min/max statistics allow `value`
+fn statistics_admit(column: &ColumnChunkMetaData, value: &str) -> bool {
+    let Some(statistics) = column.statistics() else {
+        return true;
+    };
+    statistics.min_bytes_opt().is_none_or(|min| value.as_bytes() >= min)
+        && statistics.max_bytes_opt().is_none_or(|max| value.as_bytes() <= max)
+}
+
+/// The rows of the parquet object at `path` whose string columns equal the
+/// values in `filters`. Only the footer and page index are fetched for row
+/// groups whose statistics or bloom filters rule a value out, and within the
+/// other row groups only the pages holding matching rows are decoded.
+pub async fn read_matching_rows(
+    store: Arc<dyn ObjectStore>,
+    path: &Path,
+    filters: &[(&str, &str)],
+) -> Result<MatchingRows> {
+    let meta = store.head(path).await.with_context(|| format!("Failed to read {}", path))?;
+    let options = ArrowReaderOptions::new().with_page_index(true);
+    let mut builder = ParquetRecordBatchStreamBuilder::new_with_options(ParquetObjectReader::new(store, meta), options)
+        .await
+        .with_context(|| format!("{} is not readable parquet (truncated or corrupt footer)", path))?;
+    let metadata = builder.metadata().clone();
+    let schema = metadata.file_metadata().schema_descr_ptr();
+
+    let mut columns = Vec::new();
+    for &(name, value) in filters {
+        let column = (0..schema.num_columns())
+            .find(|&i| schema.column(i).path().string() == name)
+            .ok_or_else(|| Error::Processing(format!("{} has no column {}", path, name)))?;
+        columns.push((column, value.to_string()));
+    }
+
+    let mut row_groups = Vec::new();
+    'row_groups: for row_group in 0..metadata.num_row_groups() {
+        for (column, value) in &columns {
+            if !statistics_admit(metadata.row_group(row_group).column(*column), value) {
+                continue 'row_groups;
+            }
+            if let Some(bloom_filter) = builder.get_row_group_column_bloom_filter(row_group, *column).await? {
+                if !bloom_filter.check(&value.as_str()) {
+                    continue 'row_groups;
+                }
+            }
+        }
+        row_groups.push(row_group);
+    }
+
+    let predicates = columns
+        .into_iter()
+        .map(|(column, value)| {
+            let value = StringArray::new_scalar(value);
+            let predicate = ArrowPredicateFn::new(ProjectionMask::leaves(&schema, [column]), move |batch: RecordBatch| {
+                eq(batch.column(0), &value)
+            });
+            Box::new(predicate) as Box<dyn ArrowPredicate>
+        })
+        .collect();
+
+    let row_groups_read = row_groups.len();
+    let batches = builder
+        .with_row_groups(row_groups)
+        .with_row_filter(RowFilter::new(predicates))
+        .build()?
+        .try_collect()
+        .await
+        .with_context(|| format!("Failed to read {}", path))?;
+    Ok(MatchingRows { metadata, batches, row_groups_read })
+}
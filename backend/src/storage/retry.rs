@@ -0,0 +1,169 @@
+use bytes::Bytes;
+use object_store::{path::Path, ObjectStore, PutResult};
+use rand::Rng;
+use serde::{Deserialize, Deserializer};
+use std::future::Future;
+use std::time::Duration;
+use tracing::warn;
+use crate::Error;
+
+/// How often and how patiently a store, database or chunk operation is
+/// retried. In TOML, delays are given in (fractional) seconds as
+/// `base_delay_secs` and `max_delay_secs`; omitted keys take the defaults.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Total attempts, including the first
+    pub max_attempts: u32,
+    /// Delay before the second attempt; doubles on every further attempt
+    #[serde(rename = "base_delay_secs", deserialize_with = "deserialize_secs")]
+    pub base_delay: Duration,
+    /// Upper bound on any single delay, before jitter
+    #[serde(rename = "max_delay_secs", deserialize_with = "deserialize_secs")]
+    pub max_delay: Duration,
+    /// Fraction of each delay randomly added or removed, in `[0, 1]`
+    #[serde(deserialize_with = "deserialize_jitter")]
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_secs(2),
+            max_delay: Duration::from_secs(60),
+            jitter: 0.2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new(max_attempts: u32) -> Self {
+        Self { max_attempts, ..Self::default() }
+    }
+
+    pub fn with_base_delay(mut self, base_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Delay before retry number `retry` (1 for the first retry), without jitter
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.base_delay.saturating_mul(factor).min(self.max_delay)
+    }
+
+    /// `backoff(retry)` scaled by a random factor in `[1 - jitter, 1 + jitter]`
+    pub fn delay(&self, retry: u32) -> Duration {
+        let backoff = self.backoff(retry);
+        if self.jitter == 0.0 {
+            return backoff;
+        }
+        let factor = rand::thread_rng().gen_range(1.0 - self.jitter..=1.0 + self.jitter);
+        backoff.mul_f64(factor)
+    }
+}
+
+fn deserialize_secs<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let secs = f64::deserialize(deserializer)?;
+    Duration::try_from_secs_f64(secs)
+        .map_err(|e| serde::de::Error::custom(format!("Invalid delay {}: {}", secs, e)))
+}
+
+fn deserialize_jitter<'de, D>(deserializer: D) -> Result<f64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let jitter = f64::deserialize(deserializer)?;
+    if !(0.0..=1.0).contains(&jitter) {
+        return Err(serde::de::Error::custom(format!("Jitter {} is outside [0, 1]", jitter)));
+    }
+    Ok(jitter)
+}
+
+/// Whether a failed store call may succeed on a later attempt. Errors that
+/// describe the request itself (missing objects, bad paths, auth) are final.
+pub fn is_retryable(error: &object_store::Error) -> bool {
+    !matches!(
+        error,
+        object_store::Error::NotFound { .. }
+            | object_store::Error::InvalidPath { .. }
+            | object_store::Error::NotSupported { .. }
+            | object_store::Error::AlreadyExists { .. }
+            | object_store::Error::Precondition { .. }
+            | object_store::Error::NotModified { .. }
+            | object_store::Error::NotImplemented
+            | object_store::Error::PermissionDenied { .. }
+            | object_store::Error::Unauthenticated { .. }
+            | object_store::Error::UnknownConfigurationKey { .. }
+    )
+}
+
+/// Runs `operation` until it succeeds, fails with an error `retryable`
+/// rejects, or `policy.max_attempts` is reached; the last error is returned
+pub async fn retry_with<T, E, F, Fut>(
+    policy: &RetryPolicy,
+    description: &str,
+    retryable: impl Fn(&E) -> bool,
+    mut operation: F,
+) -> Result<T, E>
+where
+    E: std::fmt::Display,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut attempt = 1;
+    loop {
+        match operation().await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < policy.max_attempts && retryable(&e) => {
+                let delay = policy.delay(attempt);
+                warn!(
+                    "Attempt {}/{} failed for {}: {}. Retrying in {:.1} seconds...",
+                    attempt, policy.max_attempts, description, e, delay.as_secs_f64()
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// `operation`, failing with a transient `Error::Timeout` naming
+/// `description` once it has run for `budget`. The operation is dropped,
+/// so whatever it had in flight is abandoned.
+pub async fn with_timeout<T>(
+    budget: Duration,
+    description: &str,
+    operation: impl Future<Output = anyhow::Result<T>>,
+) -> anyhow::Result<T> {
+    match tokio::time::timeout(budget, operation).await {
+        Ok(result) => result,
+        Err(_) => Err(Error::Timeout(format!("{} after {:?}", description, budget)).into()),
+    }
+}
+
+/// Puts `bytes` at `path` under `policy`. Every attempt shares the same
+/// buffer, so retrying never copies the payload.
+pub async fn retry_put(
+    store: &dyn ObjectStore,
+    path: &Path,
+    bytes: Bytes,
+    policy: &RetryPolicy,
+) -> object_store::Result<PutResult> {
+    retry_with(policy, path.as_ref(), is_retryable, || store.put(path, bytes.clone().into())).await
+}
@@ -1,7 +1,211 @@
 use crate::stats::*;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::VecDeque;
+use std::f64::consts::{PI, TAU};
+use std::fmt::Display;
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Scale function bounding how much weight a centroid can hold at each
+/// quantile: a centroid spans at most 1 unit of `k(q)`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScaleFn {
+    /// `δ/2π · asin(2q - 1)`, centroid size ∝ √(q(1-q))
+    #[default]
+    K1,
+    /// `δ/4π · ln(q / (1-q))`, centroid size ∝ q(1-q): the same resolution
+    /// as `K1` at the median at equal δ, and finer in the tails
+    K2,
+}
+
+impl ScaleFn {
+    pub fn k(&self, delta: u64, q: f64) -> f64 {
+        match self {
+            ScaleFn::K1 => (delta as f64 / TAU) * (2.0 * q - 1.0).asin(),
+            ScaleFn::K2 => (delta as f64 / (2.0 * TAU)) * (q / (1.0 - q)).ln(),
+        }
+    }
+
+    pub fn inv_k(&self, k: f64, delta: u64) -> f64 {
+        match self {
+            ScaleFn::K1 => ((TAU * k / delta as f64).sin() + 1.0) / 2.0,
+            ScaleFn::K2 => 1.0 / (1.0 + (-4.0 * PI * k / delta as f64).exp()),
+        }
+    }
+}
+
+/// Hard caps on a digest's memory, far above what adaptation reaches on
+/// real data; see `TDigestConfig`
+pub const DEFAULT_MAX_BUFFER_SIZE: usize = 10_000;
+pub const DEFAULT_MAX_CENTROIDS: usize = 5_000;
+
+fn default_max_buffer_size() -> usize {
+    DEFAULT_MAX_BUFFER_SIZE
+}
+
+fn default_max_centroids() -> usize {
+    DEFAULT_MAX_CENTROIDS
+}
+
+/// Adaptation events `AdaptiveParameters::history` keeps, oldest dropped first
+pub const ADAPTATION_HISTORY_LEN: usize = 16;
+
+/// Factors `AdaptiveParameters` scales compression by. Defaults are the
+/// historical constants.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdaptationFactors {
+    /// Cap on the initial scale-up, as a multiple of the base parameters
+    pub max_initial_scale: f64,
+    /// Cap on the sample-size factor fine-tuning scales the base parameters by
+    pub max_size_factor: f64,
+    /// |skewness| above which fine-tuning adjusts by `skew_slope` per unit,
+    /// capped at `max_skew_adjustment`
+    pub skew_threshold: f64,
+    pub skew_slope: f64,
+    pub max_skew_adjustment: f64,
+    /// Adjustment of `1 + slope · (-kurtosis / reference)` for negative
+    /// excess kurtosis
+    pub platykurtic_slope: f64,
+    #[serde(deserialize_with = "deserialize_positive")]
+    pub platykurtic_reference: f64,
+    /// Adjustment of `1 - slope · (kurtosis / reference)` for positive
+    /// excess kurtosis
+    pub leptokurtic_slope: f64,
+    #[serde(deserialize_with = "deserialize_positive")]
+    pub leptokurtic_reference: f64,
+    /// Cap on the combined adjustment
+    pub max_adjustment: f64,
+    /// Below this many samples the adjustment is scaled by
+    /// `small_sample_factor`
+    pub small_sample_threshold: u64,
+    pub small_sample_factor: f64,
+}
+
+impl Default for AdaptationFactors {
+    fn default() -> Self {
+        Self {
+            max_initial_scale: 2.0,
+            max_size_factor: 3.0,
+            skew_threshold: 1.0,
+            skew_slope: 0.1,
+            max_skew_adjustment: 0.3,
+            platykurtic_slope: 0.2,
+            platykurtic_reference: 2.0,
+            leptokurtic_slope: 0.2,
+            leptokurtic_reference: 4.0,
+            max_adjustment: 0.2,
+            small_sample_threshold: 5000,
+            small_sample_factor: 0.8,
+        }
+    }
+}
+
+/// One adaptation that changed a digest's compression: the metrics it saw
+/// and the parameters before and after
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdaptationEvent {
+    pub metrics: DistributionMetrics,
+    pub old_delta_partial: u64,
+    pub old_delta_final: u64,
+    pub old_buffer_size: usize,
+    pub new_delta_partial: u64,
+    pub new_delta_final: u64,
+    pub new_buffer_size: usize,
+}
+
+/// `[tdigest]` in the config file: the scale function and the compression a
+/// digest starts with before `AdaptiveParameters` scales it up. Defaults
+/// reproduce the historical digests exactly.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TDigestConfig {
+    pub scale: ScaleFn,
+    #[serde(deserialize_with = "deserialize_positive")]
+    pub base_delta_partial: u64,
+    #[serde(deserialize_with = "deserialize_positive")]
+    pub base_delta_final: u64,
+    #[serde(deserialize_with = "deserialize_positive")]
+    pub buffer_size: usize,
+    /// The buffer is merged once it holds this many values, however far
+    /// adaptation has grown `buffer_size`
+    #[serde(deserialize_with = "deserialize_positive")]
+    pub max_buffer_size: usize,
+    /// Merges recompress with a halved delta until at most this many
+    /// centroids are left
+    #[serde(deserialize_with = "deserialize_positive")]
+    pub max_centroids: usize,
+    /// Upper bounds adaptation scales the base parameters up to
+    #[serde(deserialize_with = "deserialize_positive")]
+    pub scaled_delta_partial: u64,
+    #[serde(deserialize_with = "deserialize_positive")]
+    pub scaled_delta_final: u64,
+    #[serde(deserialize_with = "deserialize_positive")]
+    pub scaled_buffer_size: usize,
+    /// Samples before the initial scale-up, and before fine-tuning
+    #[serde(deserialize_with = "deserialize_positive")]
+    pub initial_scale_threshold: u64,
+    #[serde(deserialize_with = "deserialize_positive")]
+    pub adaptation_threshold: u64,
+    /// `[tdigest.adaptation]`
+    pub adaptation: AdaptationFactors,
+}
+
+impl Default for TDigestConfig {
+    fn default() -> Self {
+        Self {
+            scale: ScaleFn::K1,
+            base_delta_partial: 20,
+            base_delta_final: 10,
+            buffer_size: 200,
+            max_buffer_size: DEFAULT_MAX_BUFFER_SIZE,
+            max_centroids: DEFAULT_MAX_CENTROIDS,
+            scaled_delta_partial: 1000,
+            scaled_delta_final: 200,
+            scaled_buffer_size: 2000,
+            initial_scale_threshold: 2000,
+            adaptation_threshold: 10000,
+            adaptation: AdaptationFactors::default(),
+        }
+    }
+}
+
+impl TDigestConfig {
+    pub fn with_scale(mut self, scale: ScaleFn) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_caps(mut self, max_buffer_size: usize, max_centroids: usize) -> Self {
+        self.max_buffer_size = max_buffer_size;
+        self.max_centroids = max_centroids;
+        self
+    }
+
+    pub fn with_thresholds(mut self, initial_scale_threshold: u64, adaptation_threshold: u64) -> Self {
+        self.initial_scale_threshold = initial_scale_threshold;
+        self.adaptation_threshold = adaptation_threshold;
+        self
+    }
+
+    pub fn with_adaptation(mut self, adaptation: AdaptationFactors) -> Self {
+        self.adaptation = adaptation;
+        self
+    }
+}
+
+fn deserialize_positive<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Default + PartialEq + Display,
+{
+    let value = T::deserialize(deserializer)?;
+    if value == T::default() {
+        return Err(serde::de::Error::custom(format!("{} must be positive", value)));
+    }
+    Ok(value)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AdaptiveParameters {
     // Current parameters
     pub delta_partial: u64,
@@ -23,6 +227,28 @@ pub struct AdaptiveParameters {
     pub adaptation_threshold: u64,     // 10000 samples
     pub samples_seen: u64,
     pub adapted: bool,
+
+    // Hard caps, whatever the adapted parameters
+    #[serde(default = "default_max_buffer_size")]
+    pub max_buffer_size: usize,
+    #[serde(default = "default_max_centroids")]
+    pub max_centroids: usize,
+
+    /// Adaptations that changed the current parameters
+    #[serde(default)]
+    pub adaptations: u64,
+
+    #[serde(default)]
+    pub factors: AdaptationFactors,
+    /// The last `ADAPTATION_HISTORY_LEN` adaptations, see `history`
+    #[serde(default, skip_serializing_if = "VecDeque::is_empty")]
+    history: VecDeque<AdaptationEvent>,
+}
+
+impl Default for AdaptiveParameters {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl AdaptiveParameters {
@@ -48,22 +274,51 @@ impl AdaptiveParameters {
             adaptation_threshold: 10000,
             samples_seen: 0,
             adapted: false,
+
+            max_buffer_size: DEFAULT_MAX_BUFFER_SIZE,
+            max_centroids: DEFAULT_MAX_CENTROIDS,
+            adaptations: 0,
+            factors: AdaptationFactors::default(),
+            history: VecDeque::new(),
+        }
+    }
+
+    /// `new` with `config`'s base compression, bounds, thresholds and
+    /// factors instead of the defaults
+    pub fn with_config(config: &TDigestConfig) -> Self {
+        Self {
+            delta_partial: config.base_delta_partial,
+            delta_final: config.base_delta_final,
+            buffer_size: config.buffer_size,
+            base_delta_partial: config.base_delta_partial,
+            base_delta_final: config.base_delta_final,
+            base_buffer_size: config.buffer_size,
+            max_buffer_size: config.max_buffer_size,
+            max_centroids: config.max_centroids,
+            scaled_delta_partial: config.scaled_delta_partial,
+            scaled_delta_final: config.scaled_delta_final,
+            scaled_buffer_size: config.scaled_buffer_size,
+            initial_scale_threshold: config.initial_scale_threshold,
+            adaptation_threshold: config.adaptation_threshold,
+            factors: config.adaptation,
+            ..Self::new()
         }
     }
 
     pub fn fine_tune_parameters(&mut self, stats: &DistributionMetrics) {
         // Base scaling factor on sample size relative to our thresholds
+        let factors = &self.factors;
         let size_factor: f64 = (self.samples_seen as f64 / self.adaptation_threshold as f64)
-            .min(3.0);  // Cap at 3x
+            .min(factors.max_size_factor);
     
         // Start with neutral adjustment
         let mut adjustment: f64 = 1.0;
         
         // Adjust for skewness - more compression for highly skewed distributions
         let abs_skew: f64 = stats.skewness.abs();
-        if abs_skew > 1.0 {
-            adjustment *= 1.0 + (0.1 * (abs_skew - 1.0));  // Cap at 30% increase
-            adjustment = adjustment.min(0.3);
+        if abs_skew > factors.skew_threshold {
+            adjustment *= 1.0 + (factors.skew_slope * (abs_skew - factors.skew_threshold));
+            adjustment = adjustment.min(factors.max_skew_adjustment);
         }
     
         // Adjust for kurtosis
@@ -71,19 +326,17 @@ impl AdaptiveParameters {
         // For leptokurtic (positive excess kurtosis), decrease compression
         if stats.kurtosis < 0.0 {
             // More compression for platykurtic distributions
-            // Maximum 20% increase for highly platykurtic cases
-            adjustment *= 1.0 + (0.2 * (-stats.kurtosis / 2.0));
+            adjustment *= 1.0 + (factors.platykurtic_slope * (-stats.kurtosis / factors.platykurtic_reference));
         } else {
             // Less compression for leptokurtic distributions
-            // Maximum 20% decrease for highly leptokurtic cases
-            adjustment *= 1.0 - (0.2 * (stats.kurtosis / 4.0));
+            adjustment *= 1.0 - (factors.leptokurtic_slope * (stats.kurtosis / factors.leptokurtic_reference));
         }
 
-        adjustment = adjustment.min(0.2);
+        adjustment = adjustment.min(factors.max_adjustment);
     
         // Conservative compression for small samples
-        if self.samples_seen < 5000 {
-            adjustment *= 0.8;
+        if self.samples_seen < factors.small_sample_threshold {
+            adjustment *= factors.small_sample_factor;
         }
     
         // Calculate new parameters with upper bound
@@ -108,21 +361,45 @@ impl AdaptiveParameters {
         if self.samples_seen < self.initial_scale_threshold {
             return;
         }
-        
+
+        let before = (self.delta_partial, self.delta_final, self.buffer_size);
         if self.delta_partial == self.base_delta_partial {
             self.apply_initial_scaling();
-            return;
-        }
-        
-        if self.samples_seen >= self.adaptation_threshold {
+        } else if self.samples_seen >= self.adaptation_threshold {
             self.fine_tune_parameters(stats);
         }
+        if (self.delta_partial, self.delta_final, self.buffer_size) != before {
+            self.adaptations += 1;
+            if self.history.len() == ADAPTATION_HISTORY_LEN {
+                self.history.pop_front();
+            }
+            self.history.push_back(AdaptationEvent {
+                metrics: stats.clone(),
+                old_delta_partial: before.0,
+                old_delta_final: before.1,
+                old_buffer_size: before.2,
+                new_delta_partial: self.delta_partial,
+                new_delta_final: self.delta_final,
+                new_buffer_size: self.buffer_size,
+            });
+        }
+    }
+
+    /// The adaptations that changed the parameters, oldest first; only the
+    /// last `ADAPTATION_HISTORY_LEN` are kept
+    pub fn history(&self) -> &VecDeque<AdaptationEvent> {
+        &self.history
+    }
+
+    /// Buffered values that trigger a merge: `buffer_size` within the cap
+    pub fn merge_threshold(&self) -> usize {
+        self.buffer_size.min(self.max_buffer_size)
     }
 
     fn apply_initial_scaling(&mut self) {
         // Scale up parameters, but with safety limits for smaller datasets
         let scale_factor = (self.samples_seen as f64 / self.initial_scale_threshold as f64)
-            .min(2.0);  // Cap initial scaling at 2x
+            .min(self.factors.max_initial_scale);
 
         self.delta_partial = ((self.base_delta_partial as f64 * scale_factor)
             .min(self.scaled_delta_partial as f64)) as u64;
@@ -135,7 +412,7 @@ impl AdaptiveParameters {
     }
 
     pub fn should_merge(&self, buffer_count: usize) -> bool {
-        buffer_count >= self.buffer_size
+        buffer_count >= self.merge_threshold()
     }
 
     pub fn reset(&mut self) {
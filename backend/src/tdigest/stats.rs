@@ -32,7 +32,17 @@ pub struct OnlineStats {
     m2: f64,   // Second central moment
     m3: f64,   // Third central moment
     m4: f64,   // Fourth central moment
+    /// Rounding errors `combine` left out of mean, m2, m3 and m4, which are
+    /// what they sum to with them added back
+    #[serde(default)]
+    compensation: [f64; 4],
 }
+impl Default for OnlineStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl OnlineStats {
     pub fn new() -> Self {
         Self {
@@ -41,26 +51,39 @@ impl OnlineStats {
             m2: 0.0,
             m3: 0.0,
             m4: 0.0,
+            compensation: [0.0; 4],
         }
     }
 
-    /// Compute exact moments on first merge
+    /// Mean, m2, m3 and m4 with the compensation added back
+    fn compensated(&self) -> [f64; 4] {
+        [
+            self.mean + self.compensation[0],
+            self.m2 + self.compensation[1],
+            self.m3 + self.compensation[2],
+            self.m4 + self.compensation[3],
+        ]
+    }
+
+    /// Compute exact moments on first merge. NaN and infinite values are
+    /// skipped rather than poisoning every moment.
     pub fn create(values: &[f64]) -> Self {
         let mut stats = Self::new();
-        let n = values.len() as u64;
+        let finite = || values.iter().copied().filter(|x| x.is_finite());
+        let n = finite().count() as u64;
         if n == 0 {
             return stats;
         }
 
         // Calculate mean first
-        let mean: f64 = values.iter().sum::<f64>() / n as f64;
+        let mean: f64 = finite().sum::<f64>() / n as f64;
         
         // Calculate central moments
         let mut m2 = 0.0;
         let mut m3 = 0.0;
         let mut m4 = 0.0;
         
-        for &x in values {
+        for x in finite() {
             let delta = x - mean;
             let delta2 = delta * delta;
             m2 += delta2;
@@ -76,11 +99,69 @@ impl OnlineStats {
         stats
     }
 
+    /// `create` for values with repeat counts, e.g. from a frequency table:
+    /// the same moments as repeating each value `weights[i]` times. Pairs
+    /// with a non-finite value or a non-finite or non-positive weight are
+    /// skipped.
+    pub fn create_weighted(values: &[f64], weights: &[f64]) -> Self {
+        debug_assert_eq!(values.len(), weights.len());
+        let mut stats = Self::new();
+        let finite = || values.iter().copied().zip(weights.iter().copied())
+            .filter(|&(x, w)| x.is_finite() && w.is_finite() && w > 0.0);
+        let total_weight: f64 = finite().map(|(_, w)| w).sum();
+        if total_weight <= 0.0 {
+            return stats;
+        }
+
+        let mean = finite().map(|(x, w)| x * w).sum::<f64>() / total_weight;
+
+        let mut m2 = 0.0;
+        let mut m3 = 0.0;
+        let mut m4 = 0.0;
+
+        for (x, w) in finite() {
+            let delta = x - mean;
+            let delta2 = delta * delta;
+            m2 += w * delta2;
+            m3 += w * delta2 * delta;
+            m4 += w * delta2 * delta2;
+        }
+
+        stats.n = total_weight.round() as u64;
+        stats.mean = mean;
+        stats.m2 = m2;
+        stats.m3 = m3;
+        stats.m4 = m4;
+        stats
+    }
+
+    /// Rebuilds the central moments `to_metrics` was computed from, so stored
+    /// metrics can be combined. Lossy where `to_metrics` is: fewer than 3
+    /// samples reconstruct zero skew and fewer than 4 a normal kurtosis.
+    pub fn from_metrics(metrics: &DistributionMetrics) -> Self {
+        let n = metrics.sample_count as f64;
+        let variance = metrics.std_dev * metrics.std_dev;
+        Self {
+            n: metrics.sample_count,
+            mean: metrics.mean,
+            m2: variance * n,
+            m3: metrics.skewness * n * variance * metrics.std_dev,
+            m4: (metrics.kurtosis + 3.0) * variance * variance * n,
+            compensation: [0.0; 4],
+        }
+    }
+
     /// Batch Implementation of Pebay&Terriberry's general algorithm
     /// Assumes that we are computing moments for a finite population that we have sampled entirely
+    /// Sums are compensated, so the many small buffers partial merges combine
+    /// into a large total do not drift the higher moments
     pub fn combine(a: &Self, b: &Self) -> Self {
-        
-        let delta = b.mean - a .mean;
+        let [_, a_m2, a_m3, _] = a.compensated();
+        let [_, b_m2, b_m3, _] = b.compensated();
+
+        // The means are close, so their difference is taken before their
+        // compensations are added back
+        let delta = (b.mean - a.mean) + (b.compensation[0] - a.compensation[0]);
         let total = a.n as f64 + b.n as f64;
         
         let a_prop = a.n as f64 / total;
@@ -92,25 +173,33 @@ impl OnlineStats {
         let da_2 = da * da;
         let db_2 = db * db;
 
-        let m2 = a.m2 + b.m2 +
-        (a.n as f64 * db_2) + (b.n as f64 * da_2);
+        let m2_term = (a.n as f64 * db_2) + (b.n as f64 * da_2);
 
+        let m3_term = (a.n as f64 * db_2 * db)  + (b.n as f64 * da_2 * da) +
+        3.0 * delta * (a_m2 * b_prop + b_m2 * a_prop);
 
-        let m3 = a.m3 + b.m3 + 
-        (a.n as f64 * db_2 * db)  + (b.n as f64 * da_2 * da) +
-        3.0 * delta * (a.m2 * b_prop + b.m2 * a_prop);
+        let m4_term = (a.n as f64 * db_2 * db_2) + (b.n as f64 * da_2 * da_2) + 
+        4.0 * delta * (a_m3 * b_prop + b_m3 * a_prop) +
+        6.0 * (delta * delta) * (a_m2 * b_prop * b_prop + b_m2 * a_prop * a_prop);
 
-        let m4 = a.m4 + b.m4 +
-        (a.n as f64 * db_2 * db_2) + (b.n as f64 * da_2 * da_2) + 
-        4.0 * delta * (a.m3 * b_prop + b.m3 * a_prop) +
-        6.0 * (delta * delta) * (a.m2 * b_prop * b_prop + b.m2 * a_prop * a_prop);
+        let (mean, mean_error) = two_sum(a.mean, -db);
+        let mut compensation = [a.compensation[0] + mean_error, 0.0, 0.0, 0.0];
+        let mut moments = [0.0; 3];
+        let terms = [(a.m2, b.m2, m2_term), (a.m3, b.m3, m3_term), (a.m4, b.m4, m4_term)];
+        for (k, (a_moment, b_moment, term)) in terms.into_iter().enumerate() {
+            let (partial, partial_error) = two_sum(a_moment, b_moment);
+            let (moment, error) = two_sum(partial, term);
+            moments[k] = moment;
+            compensation[k + 1] = a.compensation[k + 1] + b.compensation[k + 1] + partial_error + error;
+        }
 
         Self {
             n: a.n + b.n,
-            mean: a.mean - db,
-            m2,
-            m3,
-            m4,
+            mean,
+            m2: moments[0],
+            m3: moments[1],
+            m4: moments[2],
+            compensation,
         }
     }
 
@@ -120,16 +209,17 @@ impl OnlineStats {
         }
 
         let n = self.n as f64;
+        let [mean, m2, m3, m4] = self.compensated();
         
         // Calculate population variance
-        let variance = self.m2 / n;
+        let variance = m2 / n;
         let std_dev = variance.sqrt();
 
         // Population skew
         let skewness = if self.n < 3 {
             0.0
         } else {
-            self.m3 / (n * variance * std_dev)
+            m3 / (n * variance * std_dev)
         };
 
         // Population excess kurtosis
@@ -137,12 +227,12 @@ impl OnlineStats {
             0.0
         } else {
             let n = self.n as f64;
-            let m4_normalized = self.m4 / n;
+            let m4_normalized = m4 / n;
             (m4_normalized / (variance * variance)) - 3.0
         };
 
         DistributionMetrics {
-            mean: self.mean,
+            mean,
             variance,
             std_dev,
             skewness,
@@ -151,3 +241,10 @@ impl OnlineStats {
         }
     }
 }
+
+/// `a + b` and the rounding error of that addition (Knuth's TwoSum)
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let sum = a + b;
+    let b_virtual = sum - a;
+    (sum, (a - (sum - b_virtual)) + (b - b_virtual))
+}
@@ -1,7 +1,6 @@
-use std::f64::consts::TAU;
 use serde::{Serialize, Deserialize};
 use crate::stats::*;
-use crate::compress::AdaptiveParameters;
+use crate::compress::{AdaptiveParameters, ScaleFn, TDigestConfig};
 
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Centroid {
@@ -15,7 +14,20 @@ impl Centroid {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// How hard a digest has worked: its size now and the merges and
+/// adaptations that shaped it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DigestDiagnostics {
+    pub centroid_count: usize,
+    /// Partial, finalizing and digest-to-digest merges
+    pub merges: u64,
+    /// Adaptations that changed the compression parameters
+    pub adaptations: u64,
+    /// Most values ever buffered at once
+    pub peak_buffer: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TDigest {
     /// A sorted list of centroids (once merged)
     pub centroids: Vec<Centroid>,
@@ -23,32 +35,100 @@ pub struct TDigest {
     /// Temporary buffer of raw data points (non-zero) that haven't been merged yet
     pub buffer: Vec<f64>,
 
+    /// Weights of the buffered points, or empty while they all weigh 1
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub buffer_weights: Vec<f64>,
+
     /// Adaptive compression parameters
     pub compression: AdaptiveParameters,
 
+    /// Scale function the merges bound centroid sizes with
+    #[serde(default)]
+    pub scale: ScaleFn,
+
     /// Total weight across all centroids (used for quantile calculations)
     pub total_weight: f64,
 
     /// Exact count of non-zero samples processed
     pub exact_samples: u64,
 
+    /// NaN and infinite values `add` skipped, counted so bad upstream data
+    /// shows up instead of poisoning the centroids and moments
+    #[serde(default)]
+    pub rejected_samples: u64,
+
     /// Running total (in dollars) used for calculating distribution metrics
     pub running_total: f64,
 
     /// Online statistics for tracking distribution metrics
     pub online_stats: OnlineStats,
+
+    /// Exact smallest and largest values added; `None` before the first one,
+    /// and for good once a digest holds values from before they were tracked
+    #[serde(default)]
+    pub min: Option<f64>,
+    #[serde(default)]
+    pub max: Option<f64>,
+
+    /// Merges run and the largest buffer seen, for `diagnostics`
+    #[serde(default)]
+    pub merges: u64,
+    #[serde(default)]
+    pub peak_buffer: usize,
+}
+
+impl Default for TDigest {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl TDigest {
     pub fn new() -> Self {
+        Self::with_config(&TDigestConfig::default())
+    }
+
+    pub fn with_config(config: &TDigestConfig) -> Self {
         Self {
             centroids: Vec::new(),
             buffer: Vec::new(),
-            compression: AdaptiveParameters::new(),
+            buffer_weights: Vec::new(),
+            compression: AdaptiveParameters::with_config(config),
+            scale: config.scale,
             total_weight: 0.0,
             exact_samples: 0,
+            rejected_samples: 0,
             running_total: 0.0,
             online_stats: OnlineStats::new(),
+            min: None,
+            max: None,
+            merges: 0,
+            peak_buffer: 0,
+        }
+    }
+
+    /// Rebuilds a digest from stored centroids, e.g. a checkpoint's. The
+    /// compression parameters are re-adapted to `online_stats`.
+    pub fn from_centroids(mut centroids: Vec<Centroid>, total_weight: f64, exact_samples: u64, online_stats: OnlineStats) -> Self {
+        centroids.sort_by(|a, b| a.mean.total_cmp(&b.mean));
+        let mut compression = AdaptiveParameters::new();
+        compression.adapt(&online_stats.to_metrics());
+
+        Self {
+            running_total: centroids.iter().map(|c| c.mean * c.weight).sum(),
+            centroids,
+            buffer: Vec::new(),
+            buffer_weights: Vec::new(),
+            compression,
+            scale: ScaleFn::K1,
+            total_weight,
+            exact_samples,
+            rejected_samples: 0,
+            online_stats,
+            min: None,
+            max: None,
+            merges: 0,
+            peak_buffer: 0,
         }
     }
 
@@ -56,19 +136,121 @@ impl TDigest {
         self.exact_samples
     }
 
+    pub fn diagnostics(&self) -> DigestDiagnostics {
+        DigestDiagnostics {
+            centroid_count: self.centroids.len(),
+            merges: self.merges,
+            adaptations: self.compression.adaptations,
+            peak_buffer: self.peak_buffer,
+        }
+    }
+
+    /// Whether `min` and `max` cover every value in the digest
+    pub fn tracks_range(&self) -> bool {
+        self.exact_samples == 0 || (self.min.is_some() && self.max.is_some())
+    }
+
     pub fn add(&mut self, x: f64) {
+        self.add_weighted(x, 1.0);
+    }
+
+    /// Adds `x` as if it had been added `w` times, e.g. a value and its count
+    /// from a frequency table. Non-positive and non-finite weights are
+    /// ignored; non-finite values are counted in `rejected_samples`.
+    pub fn add_weighted(&mut self, x: f64, w: f64) {
+        if !w.is_finite() || w <= 0.0 {
+            return;
+        }
+        if !x.is_finite() {
+            self.rejected_samples += w.round() as u64;
+            return;
+        }
+        if self.tracks_range() {
+            self.min = Some(self.min.map_or(x, |min| min.min(x)));
+            self.max = Some(self.max.map_or(x, |max| max.max(x)));
+        }
+        if w != 1.0 && self.buffer_weights.is_empty() {
+            self.buffer_weights = vec![1.0; self.buffer.len()];
+            self.buffer_weights.push(w);
+        } else if !self.buffer_weights.is_empty() {
+            self.buffer_weights.push(w);
+        }
         self.buffer.push(x);
-        self.exact_samples += 1;
-        self.total_weight += 1.0;
-        self.running_total += x;
+        self.peak_buffer = self.peak_buffer.max(self.buffer.len());
+        self.exact_samples += w.round() as u64;
+        self.total_weight += w;
+        self.running_total += x * w;
 
         // Use adaptive buffer size from compression parameters
-        if self.buffer.len() >= self.compression.buffer_size {
-            self.buffer.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
+        if self.compression.should_merge(self.buffer.len()) {
             self.partial_merge();
         }
     }
 
+    /// `add` of each of `values` in order, with the same result. The buffer
+    /// is extended up to the next merge point at a time, so merges happen
+    /// exactly where elementwise adds would run them.
+    pub fn add_all(&mut self, values: &[f64]) {
+        let mut rest = values;
+        while !rest.is_empty() {
+            let room = self.compression.merge_threshold().saturating_sub(self.buffer.len()).max(1);
+            let (batch, tail) = rest.split_at(room.min(rest.len()));
+            self.extend_buffer(batch);
+            if self.compression.should_merge(self.buffer.len()) {
+                self.partial_merge();
+            }
+            rest = tail;
+        }
+    }
+
+    /// Buffers unit-weight `values` without merging
+    fn extend_buffer(&mut self, values: &[f64]) {
+        let tracks_range = self.tracks_range();
+        let mut accepted = 0;
+        for &x in values {
+            if !x.is_finite() {
+                self.rejected_samples += 1;
+                continue;
+            }
+            if tracks_range {
+                self.min = Some(self.min.map_or(x, |min| min.min(x)));
+                self.max = Some(self.max.map_or(x, |max| max.max(x)));
+            }
+            self.buffer.push(x);
+            self.running_total += x;
+            accepted += 1;
+        }
+        if !self.buffer_weights.is_empty() {
+            self.buffer_weights.resize(self.buffer.len(), 1.0);
+        }
+        self.peak_buffer = self.peak_buffer.max(self.buffer.len());
+        self.exact_samples += accepted;
+        self.total_weight += accepted as f64;
+    }
+
+    /// The buffered points as unsorted centroids
+    fn buffer_centroids(&self) -> Vec<Centroid> {
+        if self.buffer_weights.is_empty() {
+            self.buffer.iter().map(|&x| Centroid::new(x, 1.0)).collect()
+        } else {
+            self.buffer.iter().zip(&self.buffer_weights).map(|(&x, &w)| Centroid::new(x, w)).collect()
+        }
+    }
+
+    /// Exact moments of the buffered points
+    fn buffer_stats(&self) -> OnlineStats {
+        if self.buffer_weights.is_empty() {
+            OnlineStats::create(&self.buffer)
+        } else {
+            OnlineStats::create_weighted(&self.buffer, &self.buffer_weights)
+        }
+    }
+
+    fn clear_buffer(&mut self) {
+        self.buffer.clear();
+        self.buffer_weights.clear();
+    }
+
     pub fn merge_sorted_centroids(a: &[Centroid], b: &[Centroid]) -> (Vec<Centroid>, f64) {
         let mut merged = Vec::with_capacity(a.len() + b.len());
         let mut total_weight = 0.0;
@@ -79,34 +261,89 @@ impl TDigest {
         while i < a.len() && j < b.len() {
             if a[i].mean <= b[j].mean {
                 total_weight += a[i].weight;
-                merged.push(a[i].clone());
+                merged.push(a[i]);
                 i += 1;
             } else {
                 total_weight += b[j].weight;
-                merged.push(b[j].clone());
+                merged.push(b[j]);
                 j += 1;
             }
         }
 
         for centroid in &a[i..] {
             total_weight += centroid.weight;
-            merged.push(centroid.clone());
+            merged.push(*centroid);
         }
         for centroid in &b[j..] {
             total_weight += centroid.weight;
-            merged.push(centroid.clone());
+            merged.push(*centroid);
         }
 
         (merged, total_weight)
     }
 
+    /// Merges all of `other` into this digest, as if its values had been
+    /// added here: the centroids and both buffers are merged and compressed
+    /// with the current `delta_partial`, and the moments, sample counts and
+    /// running totals are combined.
+    pub fn merge(&mut self, other: &TDigest) {
+        self.rejected_samples += other.rejected_samples;
+        if other.exact_samples == 0 {
+            return;
+        }
+        // An empty accumulator compresses like the digests merged into it
+        if self.exact_samples == 0 && self.buffer.is_empty() {
+            self.scale = other.scale;
+        }
+
+        // Buffered values are not in `online_stats` until merged
+        let full_stats = |digest: &TDigest| {
+            if digest.buffer.is_empty() {
+                digest.online_stats.clone()
+            } else if digest.centroids.is_empty() {
+                digest.buffer_stats()
+            } else {
+                OnlineStats::combine(&digest.online_stats, &digest.buffer_stats())
+            }
+        };
+        let stats = if self.exact_samples == 0 {
+            full_stats(other)
+        } else {
+            OnlineStats::combine(&full_stats(self), &full_stats(other))
+        };
+
+        let mut buffered = self.buffer_centroids();
+        buffered.extend(other.buffer_centroids());
+        buffered.sort_by(|a, b| a.mean.total_cmp(&b.mean));
+        let (centroids, _) = Self::merge_sorted_centroids(&self.centroids, &other.centroids);
+        let (merged, total_weight) = Self::merge_sorted_centroids(&centroids, &buffered);
+
+        self.online_stats = stats;
+        self.compression.adapt(&self.online_stats.to_metrics());
+        (self.min, self.max) = if self.tracks_range() && other.tracks_range() {
+            (
+                [self.min, other.min].into_iter().flatten().reduce(f64::min),
+                [self.max, other.max].into_iter().flatten().reduce(f64::max),
+            )
+        } else {
+            (None, None)
+        };
+        self.exact_samples += other.exact_samples;
+        self.running_total += other.running_total;
+        self.total_weight = total_weight;
+        self.clear_buffer();
+        self.centroids = self.stratified_merge(merged, self.compression.delta_partial);
+        self.merges += 1;
+        self.enforce_centroid_cap(self.compression.delta_partial);
+    }
+
     pub fn partial_merge(&mut self) {
         if self.buffer.is_empty() {
             return;
         }
 
         // Calculate OnlineStats for the buffer
-        let buffer_stats = OnlineStats::create(&self.buffer);
+        let buffer_stats = self.buffer_stats();
 
         // For the first merge, initialize online_stats with buffer stats
         if self.centroids.is_empty() {
@@ -120,10 +357,9 @@ impl TDigest {
         let metrics = self.online_stats.to_metrics();
         self.compression.adapt(&metrics);
 
-        let buffer_centroids: Vec<Centroid> = self.buffer
-            .iter()
-            .map(|&x| Centroid::new(x, 1.0))
-            .collect();
+        // Sorted, so the merged centroids are too
+        let mut buffer_centroids = self.buffer_centroids();
+        buffer_centroids.sort_unstable_by(|a, b| a.mean.total_cmp(&b.mean));
 
         let (merged, total_weight) = Self::merge_sorted_centroids(
             &self.centroids,
@@ -132,39 +368,46 @@ impl TDigest {
         
         self.centroids = self.stratified_merge(merged, self.compression.delta_partial);
         self.total_weight = total_weight;
-        self.buffer.clear();
+        self.clear_buffer();
+        self.merges += 1;
+        self.enforce_centroid_cap(self.compression.delta_partial);
     }
 
     pub fn finalizing_merge(&mut self) {
         if !self.buffer.is_empty() {
             // Process any remaining buffered values and update OnlineStats
-            let buffer_stats = OnlineStats::create(&self.buffer);
+            let buffer_stats = self.buffer_stats();
             self.online_stats = OnlineStats::combine(&self.online_stats, &buffer_stats);
 
             // Update compression parameters one last time before final merge
             let metrics = self.online_stats.to_metrics();
             self.compression.adapt(&metrics);
 
-            let buffered_digest = {
-                let mut temp_digest = TDigest::new();
-                temp_digest.centroids = self.buffer.iter()
-                    .map(|&x| Centroid::new(x, 1.0))
-                    .collect();
-                temp_digest.total_weight = self.buffer.len() as f64;
-                temp_digest
-            };
+            let mut buffer_centroids = self.buffer_centroids();
+            buffer_centroids.sort_unstable_by(|a, b| a.mean.total_cmp(&b.mean));
 
             let (merged_centroids, _) = Self::merge_sorted_centroids(
                 &self.centroids,
-                &buffered_digest.centroids
+                &buffer_centroids
             );
             
             self.centroids = merged_centroids;
-            self.buffer.clear();
+            self.clear_buffer();
         }
 
         // Use final compression parameter for the last merge
         self.stratified_merge_in_place(self.compression.delta_final);
+        self.merges += 1;
+        self.enforce_centroid_cap(self.compression.delta_final);
+    }
+
+    /// Recompresses with halved deltas, starting from the `delta` just used,
+    /// until the centroids fit `max_centroids` or the delta reaches 1
+    fn enforce_centroid_cap(&mut self, mut delta: u64) {
+        while self.centroids.len() > self.compression.max_centroids && delta > 1 {
+            delta /= 2;
+            self.stratified_merge_in_place(delta);
+        }
     }
 
     pub fn finalize(&mut self) {
@@ -207,14 +450,14 @@ impl TDigest {
 
         let mut write_index = 0;
         let mut read_index = 0;
-        let mut current = self.centroids[0].clone();
+        let mut current = self.centroids[0];
         read_index += 1;
 
         let mut q_0 = 0.0;
         let mut q_limit = self.weight_limit(q_0, delta);
 
         while read_index < self.centroids.len() {
-            let next = self.centroids[read_index].clone();
+            let next = self.centroids[read_index];
             let tentative_q = q_0 + (current.weight + next.weight) / self.total_weight;
 
             if tentative_q <= q_limit {
@@ -238,54 +481,154 @@ impl TDigest {
 
 
     pub fn k1(delta: u64, q: f64) -> f64 {
-        let z: f64 = (2.0 * q) - 1.0;
-        let b: f64 = (delta as f64)/TAU;
-        b * z.asin()
+        ScaleFn::K1.k(delta, q)
     }   
 
     pub fn inv_k1(k: f64, delta: u64) -> f64 {
-        let x: f64 = (TAU * k) / (delta as f64);
-        let x_sin = x.sin();
-        (x_sin + 1.0) / 2.0
+        ScaleFn::K1.inv_k(k, delta)
     }
 
     pub fn weight_limit(&self, q_0: f64, delta: u64) -> f64 {
-        TDigest::inv_k1(TDigest::k1(delta, q_0) + 1.0, delta)
+        self.scale.inv_k(self.scale.k(delta, q_0) + 1.0, delta)
     }
 
-    /// Returns (q * 100)th percentile value in dollars
+    /// Returns (q * 100)th percentile value in dollars, within the exact
+    /// minimum and maximum when they are tracked
     pub fn quantile(&self, q: f64) -> Option<f64> {
-        if q < 0.0 || q > 1.0 || self.centroids.is_empty() {
+        self.quantiles(&[q])[0]
+    }
+
+    /// `quantile` of each of `qs`, which must be in ascending order, in one
+    /// walk over the centroids
+    pub fn quantiles(&self, qs: &[f64]) -> Vec<Option<f64>> {
+        debug_assert!(qs.windows(2).all(|pair| pair[0] <= pair[1]), "quantiles must be ascending");
+        debug_assert!(self.centroids_sorted(), "centroids must be sorted by mean");
+
+        let mut results = Vec::with_capacity(qs.len());
+        let mut i = 0;
+        let mut cumulative_weight = 0.0;
+
+        for &q in qs {
+            if !(0.0..=1.0).contains(&q) || self.centroids.is_empty() {
+                results.push(None);
+                continue;
+            }
+
+            // Skip to the first centroid whose weight reaches the target
+            let target_weight = q * self.total_weight;
+            while i < self.centroids.len() && cumulative_weight + self.centroids[i].weight < target_weight {
+                cumulative_weight += self.centroids[i].weight;
+                i += 1;
+            }
+
+            let interpolated = match i {
+                0 => self.centroids[0].mean,
+                i if i == self.centroids.len() => self.centroids[i - 1].mean,
+                i => {
+                    let (prev_centroid, centroid) = (&self.centroids[i - 1], &self.centroids[i]);
+                    prev_centroid.mean
+                        + (target_weight - cumulative_weight)
+                            * (centroid.mean - prev_centroid.mean)
+                            / centroid.weight
+                }
+            };
+
+            results.push(Some(match (self.min, self.max) {
+                (Some(min), _) if q == 0.0 => min,
+                (_, Some(max)) if q == 1.0 => max,
+                (Some(min), Some(max)) => interpolated.clamp(min, max),
+                _ => interpolated,
+            }));
+        }
+
+        results
+    }
+
+    /// Every merge keeps the centroids sorted by mean; the quantile, CDF and
+    /// trimmed mean walks rely on it rather than re-sorting
+    pub fn centroids_sorted(&self) -> bool {
+        self.centroids.windows(2).all(|pair| pair[0].mean <= pair[1].mean)
+    }
+
+    /// Mean of the values between the `lo` and `hi` quantiles, e.g. 0.05 and
+    /// 0.95 for the 5%-trimmed mean. A centroid straddling a cut point counts
+    /// with the part of its weight inside it.
+    pub fn trimmed_mean(&self, lo: f64, hi: f64) -> Option<f64> {
+        if !(0.0..=1.0).contains(&lo) || !(0.0..=1.0).contains(&hi) || lo >= hi || self.centroids.is_empty() {
             return None;
         }
 
-        let mut sorted_centroids = self.centroids.clone();
-        sorted_centroids.sort_by(|a, b| a.mean.partial_cmp(&b.mean).unwrap());
+        debug_assert!(self.centroids_sorted(), "centroids must be sorted by mean");
 
-        let target_weight = q * self.total_weight;
+        let (lo_weight, hi_weight) = (lo * self.total_weight, hi * self.total_weight);
         let mut cumulative_weight = 0.0;
+        let mut kept_weight = 0.0;
+        let mut kept_sum = 0.0;
+
+        for centroid in &self.centroids {
+            let inside = (cumulative_weight + centroid.weight).min(hi_weight) - cumulative_weight.max(lo_weight);
+            if inside > 0.0 {
+                kept_weight += inside;
+                kept_sum += inside * centroid.mean;
+            }
+            cumulative_weight += centroid.weight;
+        }
 
-        for i in 0..sorted_centroids.len() {
-            let centroid = &sorted_centroids[i];
-            let next_cumulative_weight = cumulative_weight + centroid.weight;
+        (kept_weight > 0.0).then(|| kept_sum / kept_weight)
+    }
 
-            if next_cumulative_weight >= target_weight {
-                if i == 0 {
-                    return Some(centroid.mean);
-                }
+    /// Interquartile range, in dollars
+    pub fn iqr(&self) -> Option<f64> {
+        let quartiles = self.quantiles(&[0.25, 0.75]);
+        Some(quartiles[1]? - quartiles[0]?)
+    }
+
+    /// `bins` equal-probability intervals as (lower, upper, mass), edges in
+    /// dollars from consecutive quantiles. Empty for an empty digest or no bins.
+    pub fn equi_depth_histogram(&self, bins: usize) -> Vec<(f64, f64, f64)> {
+        if bins == 0 || self.centroids.is_empty() {
+            return Vec::new();
+        }
+
+        let qs: Vec<f64> = (0..=bins).map(|i| i as f64 / bins as f64).collect();
+        let edges: Vec<f64> = self.quantiles(&qs).into_iter().flatten().collect();
+        let mass = 1.0 / bins as f64;
+        edges.windows(2).map(|edge| (edge[0], edge[1], mass)).collect()
+    }
+
+    /// Fraction of the weight at or below `x` (in dollars), the inverse of
+    /// `quantile`: 0 below the first centroid, 1 from the last one on
+    pub fn cdf(&self, x: f64) -> Option<f64> {
+        if x.is_nan() || self.centroids.is_empty() {
+            return None;
+        }
+
+        debug_assert!(self.centroids_sorted(), "centroids must be sorted by mean");
+
+        let first = &self.centroids[0];
+        if x < first.mean {
+            return Some(0.0);
+        }
+        // `quantile` returns the first mean for every q up to its weight
+        let mut cumulative_weight = first.weight;
+        if x == first.mean {
+            return Some(cumulative_weight / self.total_weight);
+        }
+
+        for i in 1..self.centroids.len() {
+            let prev_centroid = &self.centroids[i - 1];
+            let centroid = &self.centroids[i];
 
-                let prev_centroid = &sorted_centroids[i - 1];
-                let prev_cumulative_weight = cumulative_weight;
-                let interpolated = prev_centroid.mean
-                    + (target_weight - prev_cumulative_weight)
-                        * (centroid.mean - prev_centroid.mean)
-                        / centroid.weight;
-                return Some(interpolated);
+            if x < centroid.mean {
+                let interpolated = cumulative_weight
+                    + (x - prev_centroid.mean) * centroid.weight
+                        / (centroid.mean - prev_centroid.mean);
+                return Some(interpolated / self.total_weight);
             }
 
-            cumulative_weight = next_cumulative_weight;
+            cumulative_weight += centroid.weight;
         }
 
-        sorted_centroids.last().map(|c| c.mean)
+        Some(1.0)
     }
 }
\ No newline at end of file
@@ -0,0 +1,2001 @@
+use super::support::{read_parquet, TestStore};
+use crate::*;
+use arrow::array::Array;
+use axum::extract::{Query, State};
+use object_store::ObjectStore;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{atomic::Ordering, Arc};
+
+/// Writes a single checkpoint with the given bucket counts, in checkpoint
+/// bucket order (0, 0_10, 10_100, 100_500, 500_1000, 1000_10000, 10000_plus)
+async fn seed_checkpoint(store: Arc<TestStore>, pool: &str, markout_time: MarkoutTime, buckets: [u64; 7]) {
+    let checkpoint = Checkpoint::new(pool.to_string(), markout_time);
+    checkpoint.total_bucket_0.store(buckets[0], Ordering::Release);
+    checkpoint.total_bucket_0_10.store(buckets[1], Ordering::Release);
+    checkpoint.total_bucket_10_100.store(buckets[2], Ordering::Release);
+    checkpoint.total_bucket_100_500.store(buckets[3], Ordering::Release);
+    checkpoint.total_bucket_500_1000.store(buckets[4], Ordering::Release);
+    checkpoint.total_bucket_1000_10000.store(buckets[5], Ordering::Release);
+    checkpoint.total_bucket_10000_plus.store(buckets[6], Ordering::Release);
+
+    let writer = ParallelParquetWriter::new(store);
+    writer.write_checkpoints(vec![checkpoint.to_snapshot()]).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_histogram_returns_checkpoint_buckets() {
+    let store = Arc::new(TestStore::new());
+    let pool = POOL_ADDRESSES[3];
+    seed_checkpoint(store.clone(), pool, MarkoutTime::Zero, [10, 1, 2, 3, 0, 4, 5]).await;
+    PrecomputedWriter::new(store.clone()).write_histograms().await.unwrap();
+
+    let state = Arc::new(AppState::new(store));
+    let response = get_lvr_histogram(
+        State(state),
+        ApiQuery(HistogramQuery {
+            pool_address: pool.parse().unwrap(),
+            markout_time: MarkoutTime::Zero,
+            detail: None,
+        }),
+    )
+    .await
+    .unwrap()
+    .0;
+
+    let labels: Vec<_> = response.buckets.iter().map(|b| b.label.as_str()).collect();
+    assert_eq!(labels, ["$0.01-$10", "$10-$100", "$100-$500", "$500-$1K", "$1K-$10K", "$10K+"]);
+    let counts: Vec<_> = response.buckets.iter().map(|b| b.count).collect();
+    assert_eq!(counts, [1, 2, 3, 0, 4, 5]);
+
+    // Every non-zero observation lands in exactly one bucket
+    assert_eq!(response.total_observations, 15);
+    assert_eq!(response.buckets.last().unwrap().range_end_dollars, None);
+    assert!(response.equidepth_bins.is_none());
+}
+
+#[tokio::test]
+async fn test_histogram_equidepth_detail_splits_digest_into_equal_mass_bins() {
+    let store = Arc::new(TestStore::new());
+    let pool = POOL_ADDRESSES[3].to_lowercase();
+    let checkpoint = Checkpoint::new(pool.clone(), MarkoutTime::Zero);
+    // Every value lands in the $0.01-$10 bucket
+    for i in 0..4_000 {
+        checkpoint.update_digest(0.01 + (i * 7 % 4_000) as f64 / 400.0).unwrap();
+    }
+    checkpoint.total_bucket_0_10.store(4_000, Ordering::Release);
+    ParallelParquetWriter::new(store.clone()).write_checkpoints(vec![checkpoint.to_snapshot()]).await.unwrap();
+    let writer = PrecomputedWriter::new(store.clone());
+    writer.write_histograms().await.unwrap();
+    writer.write_equidepth_histograms().await.unwrap();
+
+    let state = Arc::new(AppState::new(store));
+    let response = get_lvr_histogram(State(state), ApiQuery(HistogramQuery {
+        pool_address: pool.parse().unwrap(),
+        markout_time: MarkoutTime::Zero,
+        detail: Some(HistogramDetail::Equidepth),
+    })).await.unwrap().0;
+
+    // The fixed buckets are unchanged and the bins resolve the one bucket
+    assert_eq!(response.total_observations, 4_000);
+    let bins = response.equidepth_bins.unwrap();
+    assert_eq!(bins.len(), EQUIDEPTH_BINS);
+    assert!((bins.iter().map(|bin| bin.mass).sum::<f64>() - 1.0).abs() < 1e-12);
+    assert!(bins.windows(2).all(|pair| pair[0].range_end_dollars == pair[1].range_start_dollars));
+    // Distinct values, so every bin is a distinct slice of the bucket
+    assert!(bins.iter().all(|bin| bin.range_start_dollars < bin.range_end_dollars), "{:?}", bins);
+    assert!(bins[0].range_start_dollars >= 0.01 && bins[EQUIDEPTH_BINS - 1].range_end_dollars <= 10.01, "{:?}", bins);
+}
+
+#[tokio::test]
+async fn test_cluster_histogram_labels_match_counters() {
+    let store = Arc::new(TestStore::new());
+    let pool = POOL_ADDRESSES[0];
+    let cluster = PoolRegistry::default().cluster_name(pool).unwrap().to_string();
+    seed_checkpoint(store.clone(), pool, MarkoutTime::Brontes, [0, 1, 1, 1, 1, 1, 1]).await;
+    PrecomputedWriter::new(store.clone()).write_cluster_histograms().await.unwrap();
+
+    let state = Arc::new(AppState::new(store));
+    let response = get_cluster_histogram(State(state), ApiQuery(ClusterHistogramQuery { markout_time: None }))
+        .await
+        .unwrap()
+        .0;
+
+    assert_eq!(response.clusters.len(), 1);
+    let data = &response.clusters[0];
+    assert_eq!(data.name, cluster);
+    assert_eq!(data.buckets.len(), common::BUCKET_CONFIG.len());
+    for (bucket, spec) in data.buckets.iter().zip(common::BUCKET_CONFIG.iter()) {
+        assert_eq!(bucket.label, spec.label);
+        assert_eq!(bucket.range_start_dollars, spec.range_start);
+        assert_eq!(bucket.range_end_dollars, spec.range_end);
+        assert_eq!(bucket.count, 1, "{}", spec.label);
+    }
+}
+
+/// Asserts the stored `bucket_range_end` is null exactly for the `$10K+` rows
+async fn assert_open_bucket_is_null(store: &TestStore, path: &str) {
+    let batches = read_parquet(store, path).await;
+    assert!(!batches.is_empty());
+    for batch in batches {
+        let ends = common::get_float64_column(&batch, "bucket_range_end").unwrap();
+        let labels = common::get_string_column(&batch, "label").unwrap();
+        for i in 0..batch.num_rows() {
+            assert_eq!(ends.is_null(i), labels.value(i) == "$10K+", "{}", labels.value(i));
+            assert_eq!(
+                common::get_bucket_range_end(ends, i),
+                common::BUCKET_CONFIG.iter().find(|s| s.label == labels.value(i)).unwrap().range_end
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_histogram_files_store_open_bucket_as_null() {
+    let store = Arc::new(TestStore::new());
+    seed_checkpoint(store.clone(), POOL_ADDRESSES[0], MarkoutTime::Zero, [0, 1, 1, 1, 1, 1, 3]).await;
+    let writer = PrecomputedWriter::new(store.clone());
+    writer.write_histograms().await.unwrap();
+    writer.write_cluster_histograms().await.unwrap();
+
+    assert_open_bucket_is_null(&store, "precomputed/distributions/histograms.parquet").await;
+    assert_open_bucket_is_null(&store, "precomputed/clusters/histograms.parquet").await;
+}
+
+#[tokio::test]
+async fn test_monthly_pool_totals_sum_to_checkpoint_total() {
+    let store = Arc::new(TestStore::new());
+    let pepe = "0x11950d141ecb863f01007add7d1a342041227b58";
+    let usdc_weth = POOL_ADDRESSES[0].to_lowercase();
+    let starts = [15_537_392u64, 15_753_392, 15_969_392];
+
+    // PEPE is deployed in the second month; USDC-WETH is active throughout
+    // except for a quiet second month
+    let writer = ParallelParquetWriter::new(store.clone());
+    let mut lifetime = std::collections::HashMap::new();
+    for (month, &start) in starts.iter().enumerate() {
+        let mut rows = Vec::new();
+        for (pool, active) in [(pepe.to_string(), month > 0), (usdc_weth.clone(), month != 1)] {
+            for interval_id in 0..30u64 {
+                let cents = if active { 100 + interval_id * 7 + month as u64 } else { 0 };
+                *lifetime.entry(pool.clone()).or_insert(0u64) += cents;
+                rows.push(IntervalData {
+                    interval_id,
+                    pair_address: pool.clone(),
+                    markout_time: MarkoutTime::Brontes,
+                    total_lvr_cents: Cents(cents as i64),
+                    max_lvr_cents: cents,
+                    non_zero_count: u64::from(active),
+                    total_count: 7200,
+                    start_block: start + interval_id * 7200,
+                    end_block: start + (interval_id + 1) * 7200,
+                    source_counts: BTreeMap::new(),
+                });
+            }
+        }
+        writer.write_interval_data(rows, start, start + 216_000).await.unwrap();
+    }
+    let snapshots: Vec<_> = lifetime.iter().map(|(pool, total)| {
+        let checkpoint = Checkpoint::new(pool.clone(), MarkoutTime::Brontes);
+        checkpoint.running_total.store(*total as i64, Ordering::Release);
+        checkpoint.to_snapshot()
+    }).collect();
+    writer.write_checkpoints(snapshots).await.unwrap();
+
+    PrecomputedWriter::new(store.clone()).write_monthly_pool_totals().await.unwrap();
+
+    let state = Arc::new(AppState::new(store.clone()));
+    for pool in [pepe.to_string(), usdc_weth.clone()] {
+        let response = get_monthly_pool_totals(
+            State(state.clone()),
+            ApiQuery(MonthlyPoolTotalsQuery { pool_address: pool.parse().unwrap(), markout_time: None }),
+        )
+        .await
+        .unwrap()
+        .0;
+
+        let mut checkpoint_total = 0;
+        for path in store.paths().await.iter().filter(|p| p.starts_with("checkpoints/")) {
+            for batch in read_parquet(store.as_ref(), path).await {
+                let pools = common::get_string_column(&batch, "pair_address").unwrap();
+                let totals = common::get_int64_column(&batch, "running_total").unwrap();
+                for i in 0..batch.num_rows() {
+                    if pools.value(i).to_lowercase() == pool {
+                        checkpoint_total += totals.value(i);
+                    }
+                }
+            }
+        }
+        let monthly_sum: i64 = response.monthly_totals.iter().map(|m| m.total_lvr_cents.as_i64()).sum();
+        let difference = checkpoint_total.abs_diff(monthly_sum) as f64 / checkpoint_total as f64 * 100.0;
+        assert!(difference <= RUNNING_TOTAL_TOLERANCE_PERCENT, "{}: {} vs {}", pool, monthly_sum, checkpoint_total);
+
+        let first = response.monthly_totals.first().unwrap();
+        if pool == pepe {
+            assert_eq!(response.monthly_totals.len(), starts.len() - 1);
+            assert_eq!(first.time_range, INTERVAL_RANGES[&starts[1]]);
+            assert!(response.monthly_totals.iter().all(|m| m.total_lvr_cents.as_i64() > 0));
+        } else {
+            assert_eq!(first.time_range, INTERVAL_RANGES[&starts[0]]);
+            assert_eq!(response.monthly_totals[1].total_lvr_cents.as_i64(), 0);
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_concentration_of_two_equal_pools() {
+    let store = Arc::new(TestStore::new());
+    let snapshots: Vec<_> = POOL_ADDRESSES[..2].iter().map(|pool| {
+        let checkpoint = Checkpoint::new(pool.to_string(), MarkoutTime::Brontes);
+        checkpoint.running_total.store(50_000, Ordering::Release);
+        checkpoint.total_bucket_10_100.store(10, Ordering::Release);
+        checkpoint.to_snapshot()
+    }).collect();
+    ParallelParquetWriter::new(store.clone()).write_checkpoints(snapshots).await.unwrap();
+    PrecomputedWriter::new(store.clone()).write_concentration_metrics().await.unwrap();
+
+    let state = Arc::new(AppState::new(store));
+    let response = get_concentration(State(state), ApiQuery(ConcentrationQuery { markout_time: None }))
+        .await
+        .unwrap()
+        .0;
+
+    assert_eq!(response.pool_count, 2);
+    assert!((response.hhi - 0.5).abs() < 1e-12);
+    assert!((response.top_1_share - 0.5).abs() < 1e-12);
+    assert!((response.top_3_share - 1.0).abs() < 1e-12);
+    assert!((response.effective_pools - 2.0).abs() < 1e-12);
+}
+
+#[tokio::test]
+async fn test_rolling_series_serves_aggregate_and_missing_windows() {
+    let store = Arc::new(TestStore::new());
+    let rows = (0..10u64).map(|interval_id| IntervalData {
+        interval_id,
+        pair_address: POOL_ADDRESSES[0].to_string(),
+        markout_time: MarkoutTime::Brontes,
+        total_lvr_cents: Cents(100),
+        max_lvr_cents: 100,
+        non_zero_count: 1,
+        total_count: 7200,
+        start_block: 15_537_392 + interval_id * 7200,
+        end_block: 15_537_392 + (interval_id + 1) * 7200,
+        source_counts: BTreeMap::new(),
+    }).collect();
+    ParallelParquetWriter::new(store.clone()).write_interval_data(rows, 15_537_392, 15_753_392).await.unwrap();
+    PrecomputedWriter::new(store.clone()).write_rolling_series(7).await.unwrap();
+
+    let state = Arc::new(AppState::new(store));
+    let query = |window| RollingSeriesQuery { window, markout_time: None, pool_address: None };
+    let response = get_rolling_series(State(state.clone()), ApiQuery(query(None))).await.unwrap().0;
+    let totals: Vec<_> = response.points.iter().map(|p| p.rolling_total_cents.as_i64()).collect();
+    assert_eq!(totals, [100, 200, 300, 400, 500, 600, 700, 700, 700, 700]);
+
+    let missing = get_rolling_series(State(state), ApiQuery(query(Some(30)))).await;
+    assert_eq!(missing.unwrap_err(), axum::http::StatusCode::NOT_FOUND);
+}
+
+/// Writes an empty pool totals file whose footer claims `schema_version`
+async fn seed_pool_totals_file(store: &TestStore, schema_version: Option<&str>) {
+    let schema = Arc::new(arrow::datatypes::Schema::new(vec![
+        arrow::datatypes::Field::new("pool_address", arrow::datatypes::DataType::Utf8, false),
+        arrow::datatypes::Field::new("pool_name", arrow::datatypes::DataType::Utf8, false),
+        arrow::datatypes::Field::new("markout_time", arrow::datatypes::DataType::Utf8, false),
+        arrow::datatypes::Field::new("total_lvr_cents", arrow::datatypes::DataType::Int64, false),
+        arrow::datatypes::Field::new("non_zero_blocks", arrow::datatypes::DataType::UInt64, false),
+        arrow::datatypes::Field::new("total_blocks", arrow::datatypes::DataType::UInt64, false),
+    ]));
+    let metadata = schema_version
+        .map(|version| vec![parquet::file::metadata::KeyValue::new(SCHEMA_VERSION_METADATA_KEY.to_string(), version.to_string())])
+        .unwrap_or_default();
+    let props = ParquetWriteOptions::default().writer_properties_with_metadata(metadata);
+    let mut writer = parquet::arrow::ArrowWriter::try_new(Vec::new(), schema.clone(), Some(props)).unwrap();
+    writer.write(&arrow::record_batch::RecordBatch::new_empty(schema)).unwrap();
+    let payload = writer.into_inner().unwrap();
+    store.put(&DatasetKind::PoolTotals.path(), payload.into()).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_precomputed_footer_records_generation_metadata() {
+    let store = Arc::new(TestStore::new());
+    seed_checkpoint(store.clone(), POOL_ADDRESSES[0], MarkoutTime::Brontes, [1, 1, 0, 0, 0, 0, 0]).await;
+    PrecomputedWriter::new(store.clone()).write_pool_totals().await.unwrap();
+
+    let bytes = store.get(&DatasetKind::PoolTotals.path()).await.unwrap().bytes().await.unwrap();
+    let metadata = read_footer_metadata(bytes).unwrap();
+    assert_eq!(metadata[DATASET_METADATA_KEY], "pool_totals");
+    assert_eq!(metadata[SCHEMA_VERSION_METADATA_KEY], DatasetKind::PoolTotals.schema_version().to_string());
+    assert_eq!(metadata[CRATE_VERSION_METADATA_KEY], env!("CARGO_PKG_VERSION"));
+    assert!(metadata.contains_key(GENERATED_AT_METADATA_KEY));
+    assert_eq!(metadata[INPUT_MANIFEST_METADATA_KEY].len(), 16);
+}
+
+#[tokio::test]
+async fn test_newer_major_schema_needs_regeneration() {
+    let store = Arc::new(TestStore::new());
+    seed_pool_totals_file(&store, Some("3.0")).await;
+    let state = Arc::new(AppState::new(store.clone()));
+
+    let response = get_pool_totals(State(state.clone()), ApiQuery(PoolTotalsQuery { markout_time: None })).await;
+    assert_eq!(response.unwrap_err(), axum::http::StatusCode::BAD_REQUEST);
+
+    let schema = get_schema(State(state)).await.unwrap().0;
+    let pool_totals = schema.datasets.iter().find(|d| d.dataset == "pool_totals").unwrap();
+    assert!(pool_totals.present && pool_totals.needs_regeneration);
+    assert!(pool_totals.message.as_deref().unwrap().contains("needs regeneration"));
+    assert!(schema.datasets.iter().filter(|d| d.dataset != "pool_totals").all(|d| !d.present));
+
+    let err = Validator::new(store).check_precomputed_schemas().await.unwrap_err();
+    assert!(err.to_string().contains("Precomputed data needs regeneration"), "{}", err);
+}
+
+#[tokio::test]
+async fn test_unversioned_and_minor_versions_are_still_served() {
+    for version in [None, Some("2.7")] {
+        let store = Arc::new(TestStore::new());
+        seed_pool_totals_file(&store, version).await;
+        let state = Arc::new(AppState::new(store));
+        let response = get_pool_totals(State(state), ApiQuery(PoolTotalsQuery { markout_time: None })).await;
+        assert!(response.unwrap().0.totals.is_empty());
+    }
+}
+
+/// Rewrites a precomputed file with `column` of its last row increased by one
+/// (for running totals, the final total)
+async fn corrupt_last_row(store: &TestStore, kind: DatasetKind, column: &str) {
+    let batch = read_parquet(store, kind.path().as_ref()).await.pop().unwrap();
+    let index = batch.schema().index_of(column).unwrap();
+    let mut columns = batch.columns().to_vec();
+    columns[index] = if let Ok(values) = common::get_uint64_column(&batch, column) {
+        let mut corrupted = values.values().to_vec();
+        *corrupted.last_mut().unwrap() += 1;
+        Arc::new(arrow::array::UInt64Array::from(corrupted))
+    } else if let Ok(values) = common::get_int64_column(&batch, column) {
+        let mut corrupted = values.values().to_vec();
+        *corrupted.last_mut().unwrap() += 1;
+        Arc::new(arrow::array::Int64Array::from(corrupted))
+    } else {
+        let mut corrupted = common::get_float64_column(&batch, column).unwrap().values().to_vec();
+        *corrupted.last_mut().unwrap() += 1.0;
+        Arc::new(arrow::array::Float64Array::from(corrupted))
+    };
+    let batch = arrow::record_batch::RecordBatch::try_new(batch.schema(), columns).unwrap();
+
+    let mut writer = parquet::arrow::ArrowWriter::try_new(Vec::new(), batch.schema(), None).unwrap();
+    writer.write(&batch).unwrap();
+    store.put(&kind.path(), writer.into_inner().unwrap().into()).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_validate_precomputed_flags_corrupted_values() {
+    let store = Arc::new(TestStore::new());
+    let pools = [POOL_ADDRESSES[0].to_lowercase(), POOL_ADDRESSES[1].to_lowercase()];
+    let writer = ParallelParquetWriter::new(store.clone());
+
+    let mut rows = Vec::new();
+    let mut snapshots = Vec::new();
+    for (p, pool) in pools.iter().enumerate() {
+        let mut total = 0;
+        for interval_id in 0..5u64 {
+            let cents = 500 + interval_id * 11 + p as u64;
+            total += cents;
+            rows.push(IntervalData {
+                interval_id,
+                pair_address: pool.clone(),
+                markout_time: MarkoutTime::Brontes,
+                total_lvr_cents: Cents(cents as i64),
+                max_lvr_cents: cents,
+                non_zero_count: 1,
+                total_count: 7200,
+                start_block: 15_537_392 + interval_id * 7200,
+                end_block: 15_537_392 + (interval_id + 1) * 7200,
+                source_counts: BTreeMap::new(),
+            });
+        }
+        let checkpoint = Checkpoint::new(pool.clone(), MarkoutTime::Brontes);
+        checkpoint.running_total.store(total as i64, Ordering::Release);
+        checkpoint.total_bucket_0.store(35_995, Ordering::Release);
+        checkpoint.total_bucket_100_500.store(5, Ordering::Release);
+        snapshots.push(checkpoint.to_snapshot());
+    }
+    writer.write_interval_data(rows, 15_537_392, 15_753_392).await.unwrap();
+    writer.write_checkpoints(snapshots).await.unwrap();
+
+    let precomputed = PrecomputedWriter::new(store.clone());
+    precomputed.write_running_totals().await.unwrap();
+    precomputed.write_pool_totals().await.unwrap();
+    precomputed.write_histograms().await.unwrap();
+    precomputed.write_cluster_proportions().await.unwrap();
+
+    let validator = Validator::new(store.clone());
+    assert_eq!(validator.validate_precomputed().await.unwrap(), vec![]);
+
+    for (kind, column, invariant) in [
+        (DatasetKind::PoolTotals, "total_lvr_cents", POOL_TOTALS_MATCH_CHECKPOINTS_INVARIANT),
+        (DatasetKind::AggregateRunningTotals, "running_total_cents", AGGREGATE_MATCHES_POOLS_INVARIANT),
+        (DatasetKind::Histograms, "count", HISTOGRAM_COUNTS_MATCH_INVARIANT),
+        (DatasetKind::ClusterProportions, "proportion", CLUSTER_PROPORTIONS_SUM_INVARIANT),
+    ] {
+        let original = store.get(&kind.path()).await.unwrap().bytes().await.unwrap();
+        corrupt_last_row(&store, kind, column).await;
+
+        let violations = validator.validate_precomputed().await.unwrap();
+        assert_eq!(violations.len(), 1, "{:?}", violations);
+        assert_eq!(violations[0].file, kind.path().to_string());
+        assert_eq!(violations[0].invariant, invariant);
+        assert!(violations[0].to_string().contains(kind.path().as_ref()));
+
+        store.put(&kind.path(), original.into()).await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_validator_flags_a_running_total_series_that_decreases() {
+    let store = Arc::new(TestStore::new());
+    let pools = [POOL_ADDRESSES[0].to_lowercase(), POOL_ADDRESSES[1].to_lowercase()];
+    let writer = ParallelParquetWriter::new(store.clone());
+
+    let mut rows = Vec::new();
+    let mut snapshots = Vec::new();
+    for pool in &pools {
+        for interval_id in 0..4u64 {
+            rows.push(IntervalData {
+                interval_id,
+                pair_address: pool.clone(),
+                markout_time: MarkoutTime::Brontes,
+                total_lvr_cents: Cents(100),
+                max_lvr_cents: 100,
+                non_zero_count: 1,
+                total_count: 7200,
+                start_block: 15_537_392 + interval_id * 7200,
+                end_block: 15_537_392 + (interval_id + 1) * 7200,
+                source_counts: BTreeMap::new(),
+            });
+        }
+        let checkpoint = Checkpoint::new(pool.clone(), MarkoutTime::Brontes);
+        checkpoint.running_total.store(400, Ordering::Release);
+        checkpoint.total_bucket_0.store(28_796, Ordering::Release);
+        checkpoint.total_bucket_10_100.store(4, Ordering::Release);
+        snapshots.push(checkpoint.to_snapshot());
+    }
+    writer.write_interval_data(rows, 15_537_392, 15_753_392).await.unwrap();
+    writer.write_checkpoints(snapshots).await.unwrap();
+    PrecomputedWriter::new(store.clone()).write_running_totals().await.unwrap();
+
+    let validator = Validator::new(store.clone());
+    assert_eq!(validator.validate_precomputed().await.unwrap(), vec![]);
+
+    // Knock the first pool's third point below its second
+    let kind = DatasetKind::IndividualRunningTotals;
+    let batch = read_parquet(&*store, kind.path().as_ref()).await.pop().unwrap();
+    let blocks = common::get_uint64_column(&batch, "block_number").unwrap();
+    let pool_addresses = common::get_string_column(&batch, "pool_address").unwrap();
+    let points: Vec<usize> = (0..batch.num_rows()).filter(|&i| pool_addresses.value(i) == pools[0]).collect();
+    let (previous, decremented) = (points[1], points[2]);
+    let mut totals = common::get_int64_column(&batch, "running_total_cents").unwrap().values().to_vec();
+    totals[decremented] = totals[previous] - 1;
+
+    let index = batch.schema().index_of("running_total_cents").unwrap();
+    let mut columns = batch.columns().to_vec();
+    columns[index] = Arc::new(arrow::array::Int64Array::from(totals));
+    let batch = arrow::record_batch::RecordBatch::try_new(batch.schema(), columns).unwrap();
+    let mut parquet = parquet::arrow::ArrowWriter::try_new(Vec::new(), batch.schema(), None).unwrap();
+    parquet.write(&batch).unwrap();
+    store.put(&kind.path(), parquet.into_inner().unwrap().into()).await.unwrap();
+
+    let violations = validator.validate_precomputed().await.unwrap();
+    assert_eq!(violations.len(), 1, "{:?}", violations);
+    assert_eq!(violations[0].file, kind.path().to_string());
+    assert_eq!(violations[0].invariant, RUNNING_TOTALS_NON_DECREASING_INVARIANT);
+    let detail = &violations[0].detail;
+    assert!(detail.contains(&pools[0]) && detail.contains("markout brontes"), "{}", detail);
+    assert!(detail.ends_with(&format!("at block {}", blocks.value(decremented))), "{}", detail);
+
+    // A series that ends off its checkpoint is flagged even when it only rises
+    corrupt_last_row(&store, kind, "running_total_cents").await;
+    let violations = validator.validate_precomputed().await.unwrap();
+    let mismatch = violations
+        .iter()
+        .find(|v| v.invariant == RUNNING_TOTALS_MATCH_CHECKPOINTS_INVARIANT)
+        .unwrap_or_else(|| panic!("{:?}", violations));
+    assert!(mismatch.detail.contains("ends at 401") && mismatch.detail.contains("is 400"), "{}", mismatch.detail);
+}
+
+#[tokio::test]
+async fn test_validator_repair_regenerates_only_precomputed_failures() {
+    let store = Arc::new(TestStore::new());
+    let start = 15_537_392u64;
+    let pool = POOL_ADDRESSES[0].to_lowercase();
+
+    let rows = (0..30u64)
+        .map(|interval_id| IntervalData {
+            interval_id,
+            pair_address: pool.clone(),
+            markout_time: MarkoutTime::Brontes,
+            total_lvr_cents: Cents(if interval_id == 0 { 12_345 } else { 0 }),
+            max_lvr_cents: if interval_id == 0 { 12_345 } else { 0 },
+            non_zero_count: if interval_id == 0 { 1 } else { 0 },
+            total_count: 7200,
+            start_block: start + interval_id * 7200,
+            end_block: start + (interval_id + 1) * 7200,
+            source_counts: BTreeMap::new(),
+        })
+        .collect();
+    let checkpoint = |running_total: i64| {
+        let checkpoint = Checkpoint::new(pool.clone(), MarkoutTime::Brontes);
+        checkpoint.running_total.store(running_total, Ordering::Release);
+        checkpoint.total_bucket_0.store(BLOCKS_PER_CHUNK - 1, Ordering::Release);
+        checkpoint.total_bucket_100_500.store(1, Ordering::Release);
+        checkpoint.update_max_lvr(start, 12_345);
+        checkpoint.update_digest(123.45).unwrap();
+        checkpoint.to_snapshot()
+    };
+    let writer = ParallelParquetWriter::new(store.clone());
+    writer.write_interval_data(rows, start, start + BLOCKS_PER_CHUNK).await.unwrap();
+    writer.write_checkpoints(vec![checkpoint(12_345)]).await.unwrap();
+
+    let precomputed = PrecomputedWriter::new(store.clone());
+    precomputed.write_cluster_proportions().await.unwrap();
+    let validator = Validator::new(store.clone());
+    let report = validator.validate_all().await.unwrap();
+    assert_eq!(report.severity(), Severity::Info, "{:?}", report.findings);
+
+    let kind = DatasetKind::ClusterProportions;
+    corrupt_last_row(&store, kind, "proportion").await;
+    let repair = validator.validate_and_repair(&precomputed).await.unwrap();
+    assert_eq!(repair.regenerated, vec!["cluster_proportions"]);
+    assert!(repair.blocked_by.is_empty() && repair.unrepairable.is_empty());
+    assert!(repair.report.precomputed.is_empty(), "{:?}", repair.report.precomputed);
+    assert_eq!(repair.report.severity(), Severity::Info, "{:?}", repair.report.findings);
+
+    // With the checkpoint off its intervals the proportions stay as they are
+    corrupt_last_row(&store, kind, "proportion").await;
+    let corrupted = store.get(&kind.path()).await.unwrap().bytes().await.unwrap();
+    writer.write_checkpoints(vec![checkpoint(20_000)]).await.unwrap();
+    let repair = validator.validate_and_repair(&precomputed).await.unwrap();
+    assert!(repair.regenerated.is_empty());
+    assert_eq!(repair.blocked_by.iter().map(|finding| finding.check).collect::<Vec<_>>(), vec![RUNNING_TOTAL_CHECK]);
+    assert_eq!(repair.report.precomputed[0].invariant, CLUSTER_PROPORTIONS_SUM_INVARIANT);
+    assert_eq!(store.get(&kind.path()).await.unwrap().bytes().await.unwrap(), corrupted);
+}
+
+#[tokio::test]
+async fn test_negative_lvr_reconciles_through_validation_and_api() {
+    let store = Arc::new(TestStore::new());
+    let pool = POOL_ADDRESSES[0].to_lowercase();
+
+    // Two positive intervals and two negative ones, netting -400 cents
+    let interval_totals = [300i64, -900, 500, -300];
+    let rows = interval_totals
+        .iter()
+        .enumerate()
+        .map(|(interval_id, &cents)| IntervalData {
+            interval_id: interval_id as u64,
+            pair_address: pool.clone(),
+            markout_time: MarkoutTime::Brontes,
+            total_lvr_cents: Cents(cents),
+            max_lvr_cents: cents.max(0) as u64,
+            non_zero_count: 1,
+            total_count: 7200,
+            start_block: 15_537_392 + interval_id as u64 * 7200,
+            end_block: 15_537_392 + (interval_id as u64 + 1) * 7200,
+            source_counts: BTreeMap::new(),
+        })
+        .collect();
+
+    let checkpoint = Checkpoint::new(pool.clone(), MarkoutTime::Brontes);
+    checkpoint.running_total.store(interval_totals.iter().sum(), Ordering::Release);
+    checkpoint.total_bucket_0.store(4 * 7200 - 4, Ordering::Release);
+    checkpoint.total_bucket_100_500.store(1, Ordering::Release);
+    checkpoint.total_bucket_500_1000.store(1, Ordering::Release);
+    checkpoint.total_bucket_negative.store(2, Ordering::Release);
+    {
+        let mut digest = checkpoint.digest.lock().unwrap();
+        digest.add(3.0);
+        digest.add(5.0);
+    }
+    let snapshot = checkpoint.to_snapshot();
+    assert_eq!(snapshot.running_total.as_i64(), -400);
+    assert_eq!(snapshot.non_zero_proportion, 4.0 / (4.0 * 7200.0));
+
+    let writer = ParallelParquetWriter::new(store.clone());
+    writer.write_interval_data(rows, 15_537_392, 15_753_392).await.unwrap();
+    writer.write_checkpoints(vec![snapshot]).await.unwrap();
+
+    let precomputed = PrecomputedWriter::new(store.clone());
+    precomputed.write_running_totals().await.unwrap();
+    precomputed.write_pool_totals().await.unwrap();
+    precomputed.write_histograms().await.unwrap();
+    precomputed.write_cluster_proportions().await.unwrap();
+
+    let report = Validator::new(store.clone()).validate_all().await.unwrap();
+    let stats = &report.pools[&format!("{}_brontes", pool)];
+    assert_eq!((stats.checkpoint_total, stats.intervals_total, stats.difference), (-400, -400, 0));
+    assert!(stats.sample_count_match && stats.non_zero_counts_consistent, "{:?}", stats);
+    assert_eq!(report.precomputed, vec![]);
+
+    let state = Arc::new(AppState::new(store));
+    let totals = get_pool_totals(State(state.clone()), ApiQuery(PoolTotalsQuery { markout_time: None }))
+        .await
+        .unwrap()
+        .0
+        .totals;
+    assert_eq!(totals.iter().map(|t| t.total_lvr_cents.as_i64()).collect::<Vec<_>>(), vec![-400]);
+
+    let clusters = get_cluster_proportion(State(state), ApiQuery(ClusterQuery { markout_time: None }))
+        .await
+        .unwrap()
+        .0;
+    assert_eq!(clusters.total_lvr_cents.as_i64(), -400);
+}
+
+#[tokio::test]
+async fn test_distribution_metrics_report_outlier_robust_statistics() {
+    let store = Arc::new(TestStore::new());
+    let pool = POOL_ADDRESSES[0].to_lowercase();
+    let checkpoint = Checkpoint::new(pool.clone(), MarkoutTime::Brontes);
+    // Values spread evenly over $90-$110, plus 1% giant blocks
+    for i in 0..2_000 {
+        checkpoint.update_digest(90.0 + (i * 7 % 2_000) as f64 / 100.0).unwrap();
+    }
+    for i in 0..20 {
+        checkpoint.update_digest(1e6 + i as f64).unwrap();
+    }
+    let snapshot = checkpoint.to_snapshot();
+    assert!(snapshot.mean > 1_000.0, "{}", snapshot.mean);
+    assert!(snapshot.trimmed_mean_cents.abs_diff(10_000) < 500, "{}", snapshot.trimmed_mean_cents);
+    assert!(snapshot.iqr_cents.abs_diff(1_000) < 350, "{}", snapshot.iqr_cents);
+    let expected = (snapshot.trimmed_mean_cents, snapshot.iqr_cents);
+    ParallelParquetWriter::new(store.clone()).write_checkpoints(vec![snapshot]).await.unwrap();
+    PrecomputedWriter::new(store.clone()).write_distribution_metrics().await.unwrap();
+
+    let state = Arc::new(AppState::new(store));
+    for (pool_address, aggregate) in [(Some(pool.parse().unwrap()), None), (None, Some(true))] {
+        let response = get_distribution_metrics(State(state.clone()), ApiQuery(DistributionQuery {
+            pool_address,
+            markout_time: MarkoutTime::Brontes,
+            aggregate,
+        })).await.unwrap().0;
+        let stats = (response.trimmed_mean_cents.unwrap(), response.iqr_cents.unwrap());
+        if aggregate.is_none() {
+            // The pool row finalizes the stored digest, as the snapshot did
+            assert_eq!(stats, expected);
+        } else {
+            assert!(stats.0.abs_diff(10_000) < 500 && stats.1.abs_diff(1_000) < 350, "{:?}", response);
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_validator_flags_max_lvr_disagreeing_with_digest() {
+    let store = Arc::new(TestStore::new());
+    let checkpoint = |pool: &str, max_lvr_cents: u64| {
+        let checkpoint = Checkpoint::new(pool.to_lowercase(), MarkoutTime::Brontes);
+        checkpoint.total_bucket_100_500.store(2, Ordering::Release);
+        checkpoint.update_max_lvr(15_537_400, max_lvr_cents);
+        checkpoint.update_digest(123.45).unwrap();
+        checkpoint.update_digest(456.78).unwrap();
+        checkpoint.to_snapshot()
+    };
+    let (agreeing, disagreeing) = (checkpoint(POOL_ADDRESSES[0], 45_678), checkpoint(POOL_ADDRESSES[1], 99_999));
+    assert_eq!((agreeing.min_nonzero_cents, agreeing.max_nonzero_cents), (Some(12_345), Some(45_678)));
+    ParallelParquetWriter::new(store.clone()).write_checkpoints(vec![agreeing, disagreeing]).await.unwrap();
+
+    let path = store.paths().await.into_iter().find(|p| p.contains(&POOL_ADDRESSES[0].to_lowercase())).unwrap();
+    let stored = CheckpointSnapshot::from_record_batch(&read_parquet(store.as_ref(), &path).await[0]).unwrap().remove(0);
+    assert_eq!((stored.min_nonzero_cents, stored.max_nonzero_cents), (Some(12_345), Some(45_678)));
+
+    let report = Validator::new(store).validate_all().await.unwrap();
+    let stats = |pool: &str| &report.pools[&format!("{}_brontes", pool.to_lowercase())];
+    assert!(stats(POOL_ADDRESSES[0]).max_lvr_consistent);
+    let flagged = stats(POOL_ADDRESSES[1]);
+    assert!(!flagged.max_lvr_consistent);
+    assert_eq!((flagged.max_lvr_cents, flagged.tracked_max_cents), (99_999, Some(45_678)));
+}
+
+#[tokio::test]
+async fn test_validator_reports_non_finite_values_the_digest_rejected() {
+    let store = Arc::new(TestStore::new());
+    let checkpoint = Checkpoint::new(POOL_ADDRESSES[0].to_lowercase(), MarkoutTime::Brontes);
+    checkpoint.total_bucket_100_500.store(2, Ordering::Release);
+    checkpoint.update_max_lvr(15_537_400, 45_678);
+    for value in [123.45, f64::NAN, 456.78, f64::INFINITY] {
+        checkpoint.update_digest(value).unwrap();
+    }
+    let snapshot = checkpoint.to_snapshot();
+    assert_eq!((snapshot.non_zero_samples, snapshot.rejected_samples), (2, 2));
+    assert_eq!(snapshot.max_nonzero_cents, Some(45_678));
+    ParallelParquetWriter::new(store.clone()).write_checkpoints(vec![snapshot]).await.unwrap();
+
+    let path = store.paths().await.into_iter().next().unwrap();
+    let stored = CheckpointSnapshot::from_record_batch(&read_parquet(store.as_ref(), &path).await[0]).unwrap().remove(0);
+    assert_eq!(stored.rejected_samples, 2);
+    assert_eq!(Checkpoint::from_snapshot(&stored).to_snapshot().rejected_samples, 2, "Survives a resume");
+
+    let report = Validator::new(store).validate_all().await.unwrap();
+    let stats = &report.pools[&format!("{}_brontes", POOL_ADDRESSES[0].to_lowercase())];
+    assert_eq!(stats.rejected_samples, 2);
+    // Rejected values are in neither the digest nor the buckets
+    assert_eq!(stats.tdigest_samples, stats.bucket_sum_non_zero);
+    assert!(stats.max_lvr_consistent);
+}
+
+#[tokio::test]
+async fn test_validator_lists_stray_objects_without_counting_them() {
+    let store = Arc::new(TestStore::new());
+    let start = 15_537_392u64;
+    let pool = POOL_ADDRESSES[0].to_lowercase();
+    let row = IntervalData {
+        interval_id: 0,
+        pair_address: pool.clone(),
+        markout_time: MarkoutTime::Brontes,
+        total_lvr_cents: Cents(500),
+        max_lvr_cents: 500,
+        non_zero_count: 0,
+        total_count: BLOCKS_PER_CHUNK,
+        start_block: start,
+        end_block: start + BLOCKS_PER_CHUNK,
+        source_counts: BTreeMap::new(),
+    };
+    let checkpoint = Checkpoint::new(pool.clone(), MarkoutTime::Brontes);
+    checkpoint.running_total.store(500, Ordering::Release);
+    checkpoint.total_bucket_0.store(BLOCKS_PER_CHUNK, Ordering::Release);
+    let writer = ParallelParquetWriter::new(store.clone());
+    writer.write_interval_data(vec![row], start, start + BLOCKS_PER_CHUNK).await.unwrap();
+    writer.write_checkpoints(vec![checkpoint.to_snapshot()]).await.unwrap();
+    let report = Validator::new(store.clone()).validate_all().await.unwrap();
+    assert_eq!(report.severity(), Severity::Info, "{:?}", report.findings);
+
+    // A checkpoint under a misspelled address and the leftover of an
+    // interrupted interval write, both holding data that would not reconcile
+    let interval_path = format!("intervals/{}_{}.parquet", start, start + BLOCKS_PER_CHUNK);
+    let checkpoint_path = checkpoint_path(&pool, MarkoutTime::Brontes).to_string();
+    let misspelled = format!("checkpoints/{}_brontes.parquet", pool.replacen("0x", "0y", 1));
+    let leftover = format!("{}.tmp-5e1f", interval_path);
+    for (from, to) in [(&checkpoint_path, &misspelled), (&interval_path, &leftover)] {
+        let bytes = store.get(&object_store::path::Path::from(from.as_str())).await.unwrap().bytes().await.unwrap();
+        store.put(&object_store::path::Path::from(to.as_str()), bytes.into()).await.unwrap();
+    }
+    store.put(&object_store::path::Path::from("precomputed/notes.txt"), bytes::Bytes::from("x").into()).await.unwrap();
+
+    let report = Validator::new(store).validate_all().await.unwrap();
+    let unknown: Vec<_> = report.objects.unknown().map(|object| (object.path.as_str(), object.action)).collect();
+    assert_eq!(
+        unknown,
+        vec![
+            (misspelled.as_str(), CleanupAction::Review),
+            (leftover.as_str(), CleanupAction::Delete),
+            ("precomputed/notes.txt", CleanupAction::Delete),
+        ]
+    );
+    assert_eq!(report.objects.malformed().count(), 0);
+    assert_eq!(report.objects.recognized, 2);
+
+    // The numbers only see the recognized files
+    assert_eq!(report.pools.len(), 1);
+    assert_eq!(report.pools[&format!("{}_brontes", pool)].difference, 0);
+    assert_eq!(report.severity(), Severity::Warn);
+    assert!(report.findings.iter().all(|finding| finding.check == STRAY_OBJECT_CHECK), "{:?}", report.findings);
+}
+
+#[tokio::test]
+async fn test_validation_policy_decides_whether_the_same_dataset_passes() {
+    let store = Arc::new(TestStore::new());
+    let start = 15_537_392u64;
+    let pool = POOL_ADDRESSES[0].to_lowercase();
+    let key = format!("{}_0.0", pool);
+
+    // Intervals hold 0.5% less than the checkpoint
+    let rows = (0..30u64)
+        .map(|interval_id| IntervalData {
+            interval_id,
+            pair_address: pool.clone(),
+            markout_time: MarkoutTime::Zero,
+            total_lvr_cents: Cents(if interval_id == 0 { 995 } else { 0 }),
+            max_lvr_cents: 0,
+            non_zero_count: 0,
+            total_count: 7200,
+            start_block: start + interval_id * 7200,
+            end_block: start + (interval_id + 1) * 7200,
+            source_counts: BTreeMap::new(),
+        })
+        .collect();
+    let checkpoint = Checkpoint::new(pool.clone(), MarkoutTime::Zero);
+    checkpoint.running_total.store(1_000, Ordering::Release);
+    checkpoint.total_bucket_0.store(BLOCKS_PER_CHUNK, Ordering::Release);
+    let writer = ParallelParquetWriter::new(store.clone());
+    writer.write_interval_data(rows, start, start + BLOCKS_PER_CHUNK).await.unwrap();
+    writer.write_checkpoints(vec![checkpoint.to_snapshot()]).await.unwrap();
+
+    let validate = |policy: ValidationPolicy| {
+        let validator = Validator::new(store.clone()).with_policy(policy);
+        async move { validator.validate_all().await.unwrap() }
+    };
+
+    let report = validate(ValidationPolicy::default()).await;
+    assert_eq!(report.severity(), Severity::Warn, "{:?}", report.findings);
+    assert_eq!(report.findings.len(), 1);
+    assert_eq!((report.findings[0].check, report.findings[0].subject.as_str()), (RUNNING_TOTAL_CHECK, key.as_str()));
+
+    let strict = AppConfig::from_toml("[validation]\ntotal_pct_fail = 0.1").unwrap().validation;
+    assert_eq!(strict, ValidationPolicy { total_pct_fail: 0.1, ..ValidationPolicy::default() });
+    let report = validate(strict).await;
+    assert_eq!(report.severity(), Severity::Fail);
+    assert_eq!(report.failures().map(|finding| finding.check).collect::<Vec<_>>(), vec![RUNNING_TOTAL_CHECK]);
+
+    let loose = ValidationPolicy { total_pct_warn: 1.0, total_pct_fail: 5.0, ..ValidationPolicy::default() };
+    let report = validate(loose).await;
+    assert_eq!(report.severity(), Severity::Info);
+    assert_eq!(report.findings[0].severity, Severity::Info);
+
+    // A file two chunks on leaves a gap that fails unless processing is
+    // still writing chunks
+    let chunk_start = start + 2 * BLOCKS_PER_CHUNK;
+    let row = IntervalData {
+        interval_id: 0,
+        pair_address: pool.clone(),
+        markout_time: MarkoutTime::Zero,
+        total_lvr_cents: Cents(0),
+        max_lvr_cents: 0,
+        non_zero_count: 0,
+        total_count: 7200,
+        start_block: chunk_start,
+        end_block: chunk_start + 7200,
+        source_counts: BTreeMap::new(),
+    };
+    writer.write_interval_data(vec![row], chunk_start, chunk_start + BLOCKS_PER_CHUNK).await.unwrap();
+    let gap = |report: &ValidationReport| {
+        report.findings.iter().find(|finding| finding.check == COVERAGE_GAP_CHECK).map(|finding| finding.severity)
+    };
+    assert_eq!(gap(&validate(ValidationPolicy::default()).await), Some(Severity::Fail));
+    assert_eq!(gap(&validate(ValidationPolicy::default().during_processing()).await), Some(Severity::Warn));
+}
+
+#[tokio::test]
+async fn test_validator_attributes_a_shifted_interval_block_count_to_its_pool() {
+    let store = Arc::new(TestStore::new());
+    let start = 15_537_392u64;
+    let (early, late) = (POOL_ADDRESSES[0].to_lowercase(), POOL_ADDRESSES[1].to_lowercase());
+    let deployment_block = start + 10 * 7200 + 100;
+    let entries = PoolRegistry::default()
+        .pools()
+        .iter()
+        .cloned()
+        .map(|mut pool| {
+            if pool.address.to_lowercase() == late {
+                pool.deployment_block = deployment_block;
+            }
+            pool
+        })
+        .collect();
+    let pools = Arc::new(PoolRegistry::new(entries).unwrap());
+
+    // A chunk of 30 intervals laid out like the processor's, the late pool's
+    // starting at its deployment
+    let mut rows = Vec::new();
+    let mut snapshots = Vec::new();
+    for pool in [&early, &late] {
+        for markout_time in [MarkoutTime::Zero, MarkoutTime::Brontes] {
+            let first_block = if *pool == late { deployment_block } else { start };
+            let mut blocks = 0;
+            for interval_id in 0..30u64 {
+                let interval_end = start + (interval_id + 1) * 7200;
+                let interval_start = (start + interval_id * 7200).max(first_block);
+                if interval_start >= interval_end {
+                    continue;
+                }
+                rows.push(IntervalData {
+                    interval_id,
+                    pair_address: pool.clone(),
+                    markout_time,
+                    total_lvr_cents: Cents(0),
+                    max_lvr_cents: 0,
+                    non_zero_count: 0,
+                    total_count: interval_end - interval_start,
+                    start_block: interval_start,
+                    end_block: interval_end,
+                    source_counts: BTreeMap::new(),
+                });
+                blocks += interval_end - interval_start;
+            }
+            let checkpoint = Checkpoint::new(pool.clone(), markout_time);
+            checkpoint.total_bucket_0.store(blocks, Ordering::Release);
+            snapshots.push(checkpoint.to_snapshot());
+        }
+    }
+    let writer = ParallelParquetWriter::new(store.clone());
+    writer.write_checkpoints(snapshots).await.unwrap();
+    writer.write_interval_data(rows.clone(), start, start + BLOCKS_PER_CHUNK).await.unwrap();
+
+    let validator = Validator::new(store.clone()).with_pool_registry(pools);
+    let report = validator.validate_all().await.unwrap();
+    assert_eq!(report.pools[&format!("{}_0.0", early)].expected_blocks, Some(BLOCKS_PER_CHUNK));
+    assert_eq!(report.pools[&format!("{}_0.0", late)].expected_blocks, Some(start + BLOCKS_PER_CHUNK - deployment_block));
+    assert!(report.pools.iter().all(|(_, stats)| stats.block_counts_match()), "{:?}", report.pools);
+
+    // One of the late pool's brontes intervals claims a day too many
+    let shifted = rows
+        .iter_mut()
+        .find(|row| row.pair_address == late && row.markout_time == MarkoutTime::Brontes && row.interval_id == 20)
+        .unwrap();
+    shifted.total_count += 7200;
+    writer.write_interval_data(rows, start, start + BLOCKS_PER_CHUNK).await.unwrap();
+
+    let report = validator.validate_all().await.unwrap();
+    let mismatched: Vec<_> = report.pools.iter().filter(|(_, stats)| !stats.block_counts_match()).collect();
+    assert_eq!(mismatched.len(), 1, "{:?}", mismatched);
+    let (key, stats) = mismatched[0];
+    assert_eq!(*key, format!("{}_brontes", late));
+    assert_eq!((stats.interval_block_deviation, stats.checkpoint_block_deviation), (7200, 0));
+    let expected = (start + BLOCKS_PER_CHUNK - deployment_block) as f64;
+    assert!((stats.interval_block_deviation_percent - 7200.0 / expected * 100.0).abs() < 1e-9);
+}
+
+#[tokio::test]
+async fn test_non_zero_proportion_leaves_out_blocks_before_deployment() {
+    let pepe = "0x11950d141ecb863f01007add7d1a342041227b58".to_string();
+    let early = POOL_ADDRESSES[0].to_lowercase();
+    let deployment_block = *PEPE_DEPLOYMENT_V3;
+    let (chunk_start, chunk_end) = (deployment_block - 2_000, deployment_block - 2_000 + 7_200);
+
+    // Both count every block of the chunk as older binaries did, the pool
+    // deployed mid-chunk included
+    let write = |store: Arc<TestStore>, checkpoints: Vec<Checkpoint>| async move {
+        let rows = checkpoints
+            .iter()
+            .map(|checkpoint| IntervalData {
+                interval_id: 0,
+                pair_address: checkpoint.pair_address.clone(),
+                markout_time: MarkoutTime::Zero,
+                total_lvr_cents: Cents(200 * 500),
+                max_lvr_cents: 500,
+                non_zero_count: 200,
+                total_count: chunk_end - chunk_start.max(checkpoint.deployment_block),
+                start_block: chunk_start.max(checkpoint.deployment_block),
+                end_block: chunk_end,
+                source_counts: BTreeMap::new(),
+            })
+            .collect();
+        let writer = ParallelParquetWriter::new(store.clone());
+        writer.write_checkpoints(checkpoints.iter().map(Checkpoint::to_snapshot).collect()).await.unwrap();
+        writer.write_interval_data(rows, chunk_start, chunk_end).await.unwrap();
+        PrecomputedWriter::new(store).write_non_zero_proportions().await.unwrap();
+    };
+    let checkpoint = |pool: &str, counted_from: u64| {
+        let checkpoint = Checkpoint::new(pool.to_string(), MarkoutTime::Zero);
+        checkpoint.total_bucket_0.store(chunk_end - counted_from - 200, Ordering::Release);
+        checkpoint.total_bucket_0_10.store(200, Ordering::Release);
+        checkpoint.last_updated_block.store(chunk_end - 1, Ordering::Release);
+        checkpoint.running_total.store(200 * 500, Ordering::Release);
+        checkpoint
+    };
+    let proportion = |state: Arc<AppState>, pool: &str| {
+        let query = NonZeroProportionQuery { pool_address: pool.parse().unwrap(), markout_time: MarkoutTime::Zero };
+        async move { get_non_zero_proportion(State(state), ApiQuery(query)).await.unwrap().0 }
+    };
+
+    let legacy = Arc::new(TestStore::new());
+    write(legacy.clone(), vec![checkpoint(&pepe, chunk_start), checkpoint(&early, chunk_start)]).await;
+    let state = Arc::new(AppState::new(legacy.clone()));
+    let pepe_response = proportion(state.clone(), &pepe).await;
+    assert_eq!((pepe_response.total_blocks, pepe_response.deployment_block), (chunk_end - deployment_block, deployment_block));
+    assert_eq!(pepe_response.non_zero_proportion, 200.0 / 5_200.0);
+    let early_response = proportion(state, &early).await;
+    assert_eq!((early_response.total_blocks, early_response.deployment_block), (7_200, 0));
+    assert_eq!(early_response.non_zero_proportion, 200.0 / 7_200.0);
+
+    // Validation expects the blocks precompute divides by, so the legacy
+    // files' extra zeros show up as deviations
+    let report = Validator::new(legacy).validate_all().await.unwrap();
+    let stats = &report.pools[&format!("{}_0.0", pepe)];
+    assert_eq!(stats.expected_blocks, Some(pepe_response.total_blocks));
+    assert_eq!((stats.interval_block_deviation, stats.checkpoint_block_deviation), (2_000, 2_000));
+
+    // A checkpoint counting from the deployment block it stores
+    let current = Arc::new(TestStore::new());
+    let stored = checkpoint(&pepe, deployment_block).with_deployment_block(deployment_block);
+    write(current.clone(), vec![stored]).await;
+    let response = proportion(Arc::new(AppState::new(current.clone())), &pepe).await;
+    assert_eq!((response.total_blocks, response.non_zero_proportion), (5_200, 200.0 / 5_200.0));
+    let report = Validator::new(current).validate_all().await.unwrap();
+    assert!(report.pools[&format!("{}_0.0", pepe)].block_counts_match());
+}
+
+#[tokio::test]
+async fn test_validator_reports_brontes_totals_above_theoretical_markouts() {
+    let store = Arc::new(TestStore::new());
+    let inflated = POOL_ADDRESSES[0].to_lowercase();
+    let bounded = POOL_ADDRESSES[1].to_lowercase();
+    let totals = [
+        (&inflated, MarkoutTime::Zero, 1_000),
+        (&inflated, MarkoutTime::Positive1, 1_080),
+        (&inflated, MarkoutTime::Positive2, 1_500),
+        (&inflated, MarkoutTime::Brontes, 1_100),
+        (&bounded, MarkoutTime::Zero, 1_000),
+        (&bounded, MarkoutTime::Brontes, 900),
+    ];
+    let snapshots = totals
+        .iter()
+        .map(|(pool, markout_time, total)| {
+            let checkpoint = Checkpoint::new(pool.to_string(), *markout_time);
+            checkpoint.running_total.store(*total, Ordering::Release);
+            checkpoint.to_snapshot()
+        })
+        .collect();
+    ParallelParquetWriter::new(store.clone()).write_checkpoints(snapshots).await.unwrap();
+
+    let report = Validator::new(store.clone()).validate_all().await.unwrap();
+    assert_eq!(
+        report.realized_excess,
+        vec![RealizedExcess {
+            pair_address: inflated.clone(),
+            realized_total: 1_100,
+            min_theoretical_total: 1_000,
+            excess_percent: 10.0,
+            markouts: vec!["0.0".to_string(), "1.0".to_string()],
+        }]
+    );
+
+    let lenient = Validator::new(store).with_realized_tolerance(10.0).validate_all().await.unwrap();
+    assert_eq!(lenient.realized_excess, vec![]);
+}
+
+#[tokio::test]
+async fn test_validator_loads_files_concurrently_with_the_same_results() {
+    // Long enough that reads, not parquet decoding, dominate on a busy machine
+    let latency = std::time::Duration::from_millis(50);
+    let store = Arc::new(TestStore::new().with_latency(latency));
+    let start = 15_537_392u64;
+    let writer = ParallelParquetWriter::new(store.clone());
+    let mut snapshots = Vec::new();
+    for (p, pool) in POOL_ADDRESSES.iter().take(4).enumerate() {
+        let pool = pool.to_lowercase();
+        let checkpoint = Checkpoint::new(pool.clone(), MarkoutTime::Brontes);
+        checkpoint.running_total.store(16 * 100 * (p as i64 + 1), Ordering::Release);
+        snapshots.push(checkpoint.to_snapshot());
+    }
+    writer.write_checkpoints(snapshots).await.unwrap();
+    for k in 0..16 {
+        let chunk_start = start + k * BLOCKS_PER_CHUNK;
+        let rows = POOL_ADDRESSES
+            .iter()
+            .take(4)
+            .enumerate()
+            .map(|(p, pool)| IntervalData {
+                interval_id: 0,
+                pair_address: pool.to_lowercase(),
+                markout_time: MarkoutTime::Brontes,
+                total_lvr_cents: Cents(100 * (p as i64 + 1)),
+                max_lvr_cents: 0,
+                non_zero_count: 0,
+                total_count: 7200,
+                start_block: chunk_start,
+                end_block: chunk_start + 7200,
+                source_counts: BTreeMap::new(),
+            })
+            .collect();
+        writer.write_interval_data(rows, chunk_start, chunk_start + BLOCKS_PER_CHUNK).await.unwrap();
+    }
+
+    let timed = |concurrency: usize| {
+        let validator = Validator::new(store.clone()).with_concurrency(concurrency);
+        async move {
+            let started = std::time::Instant::now();
+            let report = validator.validate_all().await.unwrap();
+            (started.elapsed(), report)
+        }
+    };
+    let (sequential_wall, sequential) = timed(1).await;
+    let (parallel_wall, parallel) = timed(8).await;
+
+    let stats = |report: &ValidationReport| {
+        let mut pools: Vec<_> = report.pools.iter().map(|(key, stats)| (key.clone(), serde_json::to_value(stats).unwrap())).collect();
+        pools.sort_by(|a, b| a.0.cmp(&b.0));
+        pools
+    };
+    assert_eq!(stats(&sequential), stats(&parallel));
+    assert_eq!(sequential.legacy_bucket_checkpoints, parallel.legacy_bucket_checkpoints);
+    assert!(sequential.pools.values().all(|stats| stats.difference == 0), "{:?}", sequential.pools);
+    // The loaders' 16 interval reads overlap 8 at a time instead of
+    // queueing; the precomputed checks after them still read one at a time
+    assert!(sequential_wall >= latency * 16, "sequential {:?}", sequential_wall);
+    let saved = sequential_wall.saturating_sub(parallel_wall);
+    assert!(saved >= latency * 10, "parallel {:?} vs sequential {:?}", parallel_wall, sequential_wall);
+}
+
+#[tokio::test]
+async fn test_validator_names_the_checkpoint_invariant_a_corrupted_percentile_breaks() {
+    let store = Arc::new(TestStore::new());
+    let checkpoint = Checkpoint::new(POOL_ADDRESSES[0].to_lowercase(), MarkoutTime::Brontes);
+    checkpoint.total_bucket_0.store(10, Ordering::Release);
+    for (block, cents) in [(15_537_400, 120), (15_537_401, 340), (15_537_402, 560), (15_537_403, 780)] {
+        checkpoint.total_bucket_100_500.fetch_add(u64::from(cents < 500), Ordering::Release);
+        checkpoint.total_bucket_500_1000.fetch_add(u64::from(cents >= 500), Ordering::Release);
+        checkpoint.update_max_lvr(block, cents);
+        checkpoint.update_digest(cents as f64 / 100.0).unwrap();
+    }
+    let snapshot = checkpoint.to_snapshot();
+    let key = format!("{}_brontes", POOL_ADDRESSES[0].to_lowercase());
+    let writer = ParallelParquetWriter::new(store.clone());
+    writer.write_checkpoints(vec![snapshot.clone()]).await.unwrap();
+
+    let report = Validator::new(store.clone()).validate_all().await.unwrap();
+    let stats = &report.pools[&key];
+    assert_eq!(stats.internal_violations, vec![], "{:?}", snapshot);
+    assert!(stats.quantiles_ordered && stats.non_zero_proportion_valid && stats.moments_valid && stats.max_covers_quantiles);
+
+    let corrupted = CheckpointSnapshot { median_cents: snapshot.percentile_75_cents + 1, ..snapshot };
+    writer.write_checkpoints(vec![corrupted]).await.unwrap();
+    let report = Validator::new(store).validate_all().await.unwrap();
+    let stats = &report.pools[&key];
+    assert!(!stats.quantiles_ordered);
+    assert!(stats.non_zero_proportion_valid && stats.moments_valid && stats.max_covers_quantiles);
+    let invariants: Vec<_> = stats.internal_violations.iter().map(|violation| violation.invariant).collect();
+    assert_eq!(invariants, vec![QUANTILES_ORDERED_INVARIANT]);
+}
+
+#[tokio::test]
+async fn test_validator_flags_intervals_above_checkpoint_as_negative_difference() {
+    let store = Arc::new(TestStore::new());
+    let pool = POOL_ADDRESSES[0].to_lowercase();
+    let row = |interval_id: u64, cents: i64| IntervalData {
+        interval_id,
+        pair_address: pool.clone(),
+        markout_time: MarkoutTime::Brontes,
+        total_lvr_cents: Cents(cents),
+        max_lvr_cents: cents as u64,
+        non_zero_count: 1,
+        total_count: 7200,
+        start_block: 15_537_392 + interval_id * 7200,
+        end_block: 15_537_392 + (interval_id + 1) * 7200,
+        source_counts: BTreeMap::new(),
+    };
+
+    // The checkpoint only saw the first interval; the second was written by a
+    // reprocess that never reached the checkpoint
+    let checkpoint = Checkpoint::new(pool.clone(), MarkoutTime::Brontes);
+    checkpoint.running_total.store(1_000, Ordering::Release);
+    let writer = ParallelParquetWriter::new(store.clone());
+    writer.write_interval_data(vec![row(0, 1_000), row(1, 1_000)], 15_537_392, 15_753_392).await.unwrap();
+    writer.write_checkpoints(vec![checkpoint.to_snapshot()]).await.unwrap();
+
+    let report = Validator::new(store.clone()).validate_all().await.unwrap();
+    let stats = &report.pools[&format!("{}_brontes", pool)];
+    assert_eq!((stats.checkpoint_total, stats.intervals_total, stats.difference), (1_000, 2_000, -1_000));
+    assert_eq!(stats.difference_percent, -100.0);
+    assert!(stats.has_significant_difference());
+
+    // A checkpoint that saw nothing is measured against the intervals
+    let empty = Checkpoint::new(POOL_ADDRESSES[1].to_lowercase(), MarkoutTime::Brontes);
+    writer.write_checkpoints(vec![checkpoint.to_snapshot(), empty.to_snapshot()]).await.unwrap();
+    let mut rows = vec![row(0, 1_000)];
+    rows.push(IntervalData { pair_address: POOL_ADDRESSES[1].to_lowercase(), ..row(0, 500) });
+    writer.write_interval_data(rows, 15_537_392, 15_753_392).await.unwrap();
+    let report = Validator::new(store).validate_all().await.unwrap();
+    let stats = &report.pools[&format!("{}_brontes", POOL_ADDRESSES[1].to_lowercase())];
+    assert_eq!((stats.difference, stats.difference_percent), (-500, -100.0));
+    let reconciled = &report.pools[&format!("{}_brontes", pool)];
+    assert_eq!(reconciled.difference, 0);
+    assert!(!reconciled.has_significant_difference());
+}
+
+#[tokio::test]
+async fn test_validator_reports_interval_coverage_gaps_and_overlaps() {
+    let store = Arc::new(TestStore::new());
+    let start = 15_537_392u64;
+    let write_chunk = |start_block: u64, end_block: u64| {
+        let store = store.clone();
+        async move {
+            let row = IntervalData {
+                interval_id: 0,
+                pair_address: POOL_ADDRESSES[0].to_lowercase(),
+                markout_time: MarkoutTime::Brontes,
+                total_lvr_cents: Cents(100),
+                max_lvr_cents: 100,
+                non_zero_count: 1,
+                total_count: 7200,
+                start_block,
+                end_block: start_block + 7200,
+                source_counts: BTreeMap::new(),
+            };
+            ParallelParquetWriter::new(store).write_interval_data(vec![row], start_block, end_block).await.unwrap();
+        }
+    };
+    for k in 0..3 {
+        write_chunk(start + k * BLOCKS_PER_CHUNK, start + (k + 1) * BLOCKS_PER_CHUNK).await;
+    }
+    let validator = Validator::new(store.clone());
+    let coverage = validator.check_interval_coverage().await.unwrap();
+    assert!(coverage.is_complete() && coverage.misaligned.is_empty(), "{:?}", coverage);
+    assert_eq!(coverage.range, Some((start, start + 3 * BLOCKS_PER_CHUNK)));
+
+    let middle = format!("intervals/{}_{}.parquet", start + BLOCKS_PER_CHUNK, start + 2 * BLOCKS_PER_CHUNK);
+    store.delete(&object_store::path::Path::from(middle)).await.unwrap();
+    let report = validator.validate_all().await.unwrap();
+    assert_eq!(
+        report.coverage.gaps,
+        vec![CoverageGap { start_block: start + BLOCKS_PER_CHUNK, end_block: start + 2 * BLOCKS_PER_CHUNK }]
+    );
+    assert!(report.coverage.overlaps.is_empty());
+
+    // Halfway through the first chunk to halfway through the missing one
+    let overlapping = (start + BLOCKS_PER_CHUNK / 2, start + 3 * BLOCKS_PER_CHUNK / 2);
+    write_chunk(overlapping.0, overlapping.1).await;
+    let coverage = validator.check_interval_coverage().await.unwrap();
+    assert_eq!(
+        coverage.overlaps,
+        vec![CoverageOverlap {
+            first: format!("intervals/{}_{}.parquet", start, start + BLOCKS_PER_CHUNK),
+            second: format!("intervals/{}_{}.parquet", overlapping.0, overlapping.1),
+            start_block: overlapping.0,
+            end_block: start + BLOCKS_PER_CHUNK,
+        }]
+    );
+    assert_eq!(coverage.gaps, vec![CoverageGap { start_block: overlapping.1, end_block: start + 2 * BLOCKS_PER_CHUNK }]);
+    assert_eq!(coverage.misaligned, vec![format!("intervals/{}_{}.parquet", overlapping.0, overlapping.1)]);
+}
+
+#[tokio::test]
+async fn test_activity_runs_of_alternating_series() {
+    let store = Arc::new(TestStore::new());
+    let start = 15_537_392u64;
+    let steady = POOL_ADDRESSES[0].to_lowercase();
+    let intermittent = POOL_ADDRESSES[1].to_lowercase();
+    let dormant = POOL_ADDRESSES[2].to_lowercase();
+
+    // Three pre-deployment intervals, then runs of 2 on / 1 off / 1 on /
+    // 3 off / 4 on / 2 off
+    let pattern = [
+        false, false, false, true, true, false, true, false, false, false, true, true, true, true, false, false,
+    ];
+    let mut rows = Vec::new();
+    for (interval_id, &active) in pattern.iter().enumerate() {
+        for (pool, active) in [(&steady, true), (&intermittent, active), (&dormant, false)] {
+            rows.push(IntervalData {
+                interval_id: interval_id as u64,
+                pair_address: pool.clone(),
+                markout_time: MarkoutTime::Brontes,
+                total_lvr_cents: Cents(if active { 250 } else { 0 }),
+                max_lvr_cents: if active { 250 } else { 0 },
+                non_zero_count: u64::from(active),
+                total_count: 7200,
+                start_block: start + interval_id as u64 * 7200,
+                end_block: start + (interval_id as u64 + 1) * 7200,
+                source_counts: BTreeMap::new(),
+            });
+        }
+    }
+    let end = start + pattern.len() as u64 * 7200;
+    ParallelParquetWriter::new(store.clone()).write_interval_data(rows, start, end).await.unwrap();
+
+    PrecomputedWriter::new(store.clone()).write_activity_runs().await.unwrap();
+
+    let state = Arc::new(AppState::new(store));
+    let query = |pool_address: Option<&str>| ActivityRunsQuery {
+        markout_time: None,
+        pool_address: pool_address.map(|address| address.parse().unwrap()),
+    };
+
+    let response = get_activity_runs(State(state.clone()), ApiQuery(query(Some(&intermittent)))).await.unwrap().0;
+    let runs = &response.pools[0];
+    assert_eq!(runs.first_active_block, start + 4 * 7200);
+    assert_eq!(runs.dry_spells, 3);
+    assert_eq!(runs.longest_dry_spell, 3);
+    assert_eq!(runs.mean_dry_spell, 2.0);
+    assert_eq!(runs.longest_active_streak, 4);
+
+    let all = get_activity_runs(State(state.clone()), ApiQuery(query(None))).await.unwrap().0;
+    assert_eq!(all.pools.len(), 2);
+    let steady_runs = all.pools.iter().find(|p| p.pool_address == steady).unwrap();
+    assert_eq!((steady_runs.dry_spells, steady_runs.longest_active_streak), (0, pattern.len() as u64));
+
+    let never_active = get_activity_runs(State(state), ApiQuery(query(Some(&dormant)))).await;
+    assert_eq!(never_active.unwrap_err(), axum::http::StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_correlations_of_identical_and_independent_pools() {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    let mut rng = StdRng::seed_from_u64(1087);
+
+    let store = Arc::new(TestStore::new());
+    let start = 15_537_392u64;
+    let intervals = 400u64;
+    let late_deployment = 100u64;
+    let mut pools: Vec<String> = POOL_ADDRESSES[..3].iter().map(|p| p.to_lowercase()).collect();
+    pools.sort();
+    let (first, twin, independent) = (&pools[0], &pools[1], &pools[2]);
+
+    let mut rows = Vec::new();
+    let row = |interval_id: u64, pool: &String, cents: u64| IntervalData {
+        interval_id,
+        pair_address: pool.clone(),
+        markout_time: MarkoutTime::Brontes,
+        total_lvr_cents: Cents(cents as i64),
+        max_lvr_cents: cents,
+        non_zero_count: u64::from(cents > 0),
+        total_count: 7200,
+        start_block: start + interval_id * 7200,
+        end_block: start + (interval_id + 1) * 7200,
+        source_counts: BTreeMap::new(),
+    };
+    for interval_id in 0..intervals {
+        let cents = rng.gen_range(1..100_000);
+        rows.push(row(interval_id, first, cents));
+        rows.push(row(interval_id, twin, cents));
+        // Deployed late, with no rows for quiet intervals afterwards
+        let independent_cents = rng.gen_range(0..100_000);
+        if interval_id >= late_deployment && independent_cents % 5 != 0 {
+            rows.push(row(interval_id, independent, independent_cents));
+        }
+    }
+    let end = start + intervals * 7200;
+    ParallelParquetWriter::new(store.clone()).write_interval_data(rows, start, end).await.unwrap();
+
+    PrecomputedWriter::new(store.clone()).write_pool_correlations().await.unwrap();
+
+    let state = Arc::new(AppState::new(store));
+    let response = get_correlations(State(state.clone()), ApiQuery(CorrelationsQuery { markout_time: None })).await.unwrap().0;
+    assert_eq!(response.correlations.len(), 3);
+    let pair = |a: &String, b: &String| response.correlations.iter()
+        .find(|c| &c.pool_a == a && &c.pool_b == b)
+        .unwrap();
+
+    let twins = pair(first, twin);
+    assert!((twins.correlation - 1.0).abs() < 1e-9);
+    assert_eq!(twins.n_intervals, intervals);
+
+    for pool in [first, twin] {
+        let unrelated = pair(pool, independent);
+        assert!(unrelated.correlation.abs() < 0.15, "correlation {}", unrelated.correlation);
+        assert_eq!(unrelated.n_intervals, intervals - late_deployment);
+    }
+
+    let missing = get_correlations(State(state), ApiQuery(CorrelationsQuery { markout_time: Some(MarkoutTime::Positive2) })).await;
+    assert_eq!(missing.unwrap_err(), axum::http::StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_aggregate_distribution_metrics_combine_pools() {
+    let store = Arc::new(TestStore::new());
+    let pools = [POOL_ADDRESSES[0].to_lowercase(), POOL_ADDRESSES[1].to_lowercase()];
+    let mut pooled = Vec::new();
+    let mut snapshots = Vec::new();
+    for (p, pool) in pools.iter().enumerate() {
+        let checkpoint = Checkpoint::new(pool.clone(), MarkoutTime::Brontes);
+        for i in 0..(40 + p * 25) {
+            let value = 5.0 + ((i * 37 + p * 11) % 53) as f64 * (1.0 + p as f64);
+            checkpoint.update_digest(value).unwrap();
+            pooled.push(value);
+        }
+        checkpoint.finalize().unwrap();
+        snapshots.push(checkpoint.to_snapshot());
+    }
+    ParallelParquetWriter::new(store.clone()).write_checkpoints(snapshots).await.unwrap();
+    PrecomputedWriter::new(store.clone()).write_distribution_metrics().await.unwrap();
+
+    let state = Arc::new(AppState::new(store));
+    let query = |pool_address: Option<&str>, aggregate: Option<bool>| DistributionQuery {
+        pool_address: pool_address.map(|address| address.parse().unwrap()),
+        markout_time: MarkoutTime::Brontes,
+        aggregate,
+    };
+
+    let aggregate = get_distribution_metrics(State(state.clone()), ApiQuery(query(None, Some(true)))).await.unwrap().0;
+    let expected = OnlineStats::create(&pooled).to_metrics();
+    assert_eq!(aggregate.pool_address, AGGREGATE_POOL_ADDRESS);
+    for (computed, expected) in [
+        (aggregate.mean_dollars, expected.mean),
+        (aggregate.std_dev_dollars, expected.std_dev),
+        (aggregate.skewness, expected.skewness),
+        (aggregate.kurtosis, expected.kurtosis),
+    ] {
+        assert!((computed - expected).abs() <= 1e-9 * expected.abs().max(1.0), "{} vs {}", computed, expected);
+    }
+
+    let single = get_distribution_metrics(State(state.clone()), ApiQuery(query(Some(&pools[0]), None))).await.unwrap().0;
+    assert_eq!(single.pool_address, pools[0]);
+
+    let missing_pool = get_distribution_metrics(State(state), ApiQuery(query(None, None))).await;
+    assert_eq!(missing_pool.unwrap_err(), axum::http::StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_distribution_metrics_report_fraction_above_1000_dollars() {
+    let store = Arc::new(TestStore::new());
+    let pools = [POOL_ADDRESSES[0].to_lowercase(), POOL_ADDRESSES[1].to_lowercase()];
+    let mut pooled = Vec::new();
+    let mut digests = Vec::new();
+    let mut snapshots = Vec::new();
+    for (pool, step) in pools.iter().zip([3.0, 0.5]) {
+        let checkpoint = Checkpoint::new(pool.clone(), MarkoutTime::Brontes);
+        for i in 0..1_000 {
+            let value = 1.0 + i as f64 * step;
+            checkpoint.update_digest(value).unwrap();
+            pooled.push(value);
+        }
+        digests.push(checkpoint.digest.lock().unwrap().clone());
+        snapshots.push(checkpoint.to_snapshot());
+    }
+    ParallelParquetWriter::new(store.clone()).write_checkpoints(snapshots).await.unwrap();
+    PrecomputedWriter::new(store.clone()).write_distribution_metrics().await.unwrap();
+
+    let state = Arc::new(AppState::new(store));
+    let fraction = |pool_address: Option<&String>| {
+        let state = state.clone();
+        let query = DistributionQuery {
+            pool_address: pool_address.map(|address| address.parse().unwrap()),
+            markout_time: MarkoutTime::Brontes,
+            aggregate: Some(pool_address.is_none()),
+        };
+        async move {
+            get_distribution_metrics(State(state), ApiQuery(query)).await.unwrap().0.fraction_above_1000_dollars.unwrap()
+        }
+    };
+    let exact = |values: &[f64]| values.iter().filter(|&&x| x > 1000.0).count() as f64 / values.len() as f64;
+
+    // Pools read their finalized digest, like their quartiles
+    let mut finalized = digests[0].clone();
+    finalized.finalize();
+    let first = fraction(Some(&pools[0])).await;
+    assert_eq!(Some(first), fraction_above(&finalized, 1000.0));
+    assert!((first - exact(&pooled[..1_000])).abs() < 0.15, "{}", first);
+    assert_eq!(fraction(Some(&pools[1])).await, 0.0);
+
+    // The aggregate merges the pools' digests in address order
+    let mut merged = TDigest::new();
+    for digest in &digests {
+        merged.merge(digest);
+    }
+    let aggregate = fraction(None).await;
+    assert_eq!(Some(aggregate), fraction_above(&merged, 1000.0));
+    assert!((aggregate - exact(&pooled)).abs() < 0.15, "{}", aggregate);
+}
+
+#[tokio::test]
+async fn test_single_pool_running_total_reads_only_its_partition() {
+    let store = Arc::new(TestStore::new());
+    let pool = POOL_ADDRESSES[1].to_lowercase();
+    let mut rows = Vec::new();
+    for interval_id in 0..20u64 {
+        for (p, address) in POOL_ADDRESSES.iter().take(3).enumerate() {
+            rows.push(IntervalData {
+                interval_id,
+                pair_address: address.to_string(),
+                markout_time: MarkoutTime::Brontes,
+                total_lvr_cents: Cents(100 + interval_id as i64 * 3 + p as i64),
+                max_lvr_cents: 100,
+                non_zero_count: 1,
+                total_count: 7200,
+                start_block: 15_537_392 + interval_id * 7200,
+                end_block: 15_537_392 + (interval_id + 1) * 7200,
+                source_counts: BTreeMap::new(),
+            });
+        }
+    }
+    ParallelParquetWriter::new(store.clone()).write_interval_data(rows, 15_537_392, 15_681_392).await.unwrap();
+    PrecomputedWriter::new(store.clone()).write_running_totals().await.unwrap();
+
+    let state = Arc::new(AppState::new(store.clone()));
+    let query = || TimeRangeQuery {
+        start_block: None,
+        end_block: None,
+        markout_time: None,
+        aggregate: None,
+        pool: Some(pool.parse().unwrap()),
+    };
+
+    let gets_before = store.gets().len();
+    let partitioned = get_running_total(State(state.clone()), ApiQuery(query())).await.unwrap().0;
+    assert_eq!(store.gets()[gets_before..], [DatasetKind::pool_partition_path(&pool.parse().unwrap()).to_string()]);
+    assert_eq!(partitioned.len(), 20);
+
+    // Values match the combined file, which is still read when no partition exists
+    store.delete(&DatasetKind::pool_partition_path(&pool.parse().unwrap())).await.unwrap();
+    let combined = get_running_total(State(state), ApiQuery(query())).await.unwrap().0;
+    let totals = |points: &[RunningTotal]| points.iter()
+        .map(|p| (p.block_number, p.markout.clone(), p.pool_address.clone(), p.running_total_cents))
+        .collect::<Vec<_>>();
+    assert_eq!(totals(&partitioned), totals(&combined));
+
+    let store = Arc::new(TestStore::new());
+    PrecomputedWriter::new(store.clone()).with_combined_running_totals(false).write_running_totals().await.unwrap();
+    let paths = store.paths().await;
+    assert!(!paths.contains(&DatasetKind::IndividualRunningTotals.path().to_string()));
+    assert!(paths.contains(&DatasetKind::pool_partition_path(&pool.parse().unwrap()).to_string()));
+}
+
+#[tokio::test]
+async fn test_markout_time_query_matches_any_spelling_of_the_stored_markout() {
+    let store = Arc::new(TestStore::new());
+    let pool = POOL_ADDRESSES[0].to_lowercase();
+    let rows = [MarkoutTime::Negative05, MarkoutTime::Zero]
+        .into_iter()
+        .map(|markout_time| IntervalData {
+            interval_id: 0,
+            pair_address: pool.clone(),
+            markout_time,
+            total_lvr_cents: Cents(100),
+            max_lvr_cents: 100,
+            non_zero_count: 1,
+            total_count: 7200,
+            start_block: 15_537_392,
+            end_block: 15_537_392 + 7200,
+            source_counts: BTreeMap::new(),
+        })
+        .collect();
+    ParallelParquetWriter::new(store.clone()).write_interval_data(rows, 15_537_392, 15_681_392).await.unwrap();
+    PrecomputedWriter::new(store.clone()).write_running_totals().await.unwrap();
+    let state = Arc::new(AppState::new(store));
+
+    for spelling in ["-0.5", "-0.50", "-.5"] {
+        let uri = format!("http://localhost/running_total?pool={}&markout_time={}", pool, spelling).parse().unwrap();
+        let query = Query::<TimeRangeQuery>::try_from_uri(&uri).unwrap();
+        let points = get_running_total(State(state.clone()), ApiQuery(query.0)).await.unwrap().0;
+        assert!(!points.is_empty(), "{} matched nothing", spelling);
+        assert!(points.iter().all(|p| p.markout == "-0.5"), "{}", spelling);
+    }
+
+    let uri = "http://localhost/running_total?markout_time=0.25".parse().unwrap();
+    assert!(Query::<TimeRangeQuery>::try_from_uri(&uri).is_err());
+}
+
+/// Runs the query extractor on `uri` the way the router does
+async fn extract_query<T: serde::de::DeserializeOwned>(uri: &str) -> Result<T, (axum::http::StatusCode, String)> {
+    use axum::extract::FromRequestParts;
+    let (mut parts, _) = axum::http::Request::get(uri).body(()).unwrap().into_parts();
+    ApiQuery::<T>::from_request_parts(&mut parts, &()).await.map(|ApiQuery(params)| params)
+}
+
+#[tokio::test]
+async fn test_malformed_pool_addresses_are_rejected_and_any_valid_spelling_matches() {
+    let pools = PoolRegistry::default();
+    let pool = pools.addresses()[0].clone();
+
+    for uri in [
+        "http://localhost/histogram?pool_address=0xZZZZ&markout_time=0.0".to_string(),
+        "http://localhost/non_zero_proportion?pool_address=88e6a0c2ddd26feeb64f039a2c41296fcb3f5640&markout_time=0.0".to_string(),
+        format!("http://localhost/histogram?pool_address={}0&markout_time=0.0", pool),
+    ] {
+        let (status, message) = extract_query::<HistogramQuery>(&uri).await.unwrap_err();
+        assert_eq!(status, axum::http::StatusCode::UNPROCESSABLE_ENTITY, "{}", uri);
+        assert!(message.contains("Invalid address"), "{}", message);
+    }
+
+    // A mistyped checksum names the address it should have been
+    let uri = "http://localhost/running_total?pool=0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD";
+    let (status, message) = extract_query::<TimeRangeQuery>(uri).await.unwrap_err();
+    assert_eq!(status, axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(message.contains("did you mean 0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"), "{}", message);
+
+    let checksummed = pool.checksummed();
+    for spelling in [pool.to_string(), checksummed.clone(), format!("0x{}", checksummed[2..].to_uppercase())] {
+        let uri = format!("http://localhost/non_zero_proportion?pool_address={}&markout_time=brontes", spelling);
+        let query = extract_query::<NonZeroProportionQuery>(&uri).await.unwrap();
+        assert_eq!(query.pool_address, pool);
+        assert!(pools.contains(&query.pool_address));
+        assert_eq!(pools.get(&spelling).unwrap().name, pools.pools()[0].name);
+    }
+
+    // Well-formed but unknown pools are still a 400 from the handler
+    let store = Arc::new(TestStore::new());
+    let state = Arc::new(AppState::new(store));
+    let unknown = extract_query::<NonZeroProportionQuery>(
+        "http://localhost/non_zero_proportion?pool_address=0x00000000000000000000000000000000000000ab&markout_time=brontes",
+    )
+    .await
+    .unwrap();
+    let response = get_non_zero_proportion(State(state), ApiQuery(unknown)).await;
+    assert_eq!(response.unwrap_err(), axum::http::StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_custom_pool_registry_flows_through_precompute_and_api() {
+    let new_pool = "0x00000000000000000000000000000000000000AB";
+    let pools = Arc::new(PoolRegistry::from_toml(&format!(
+        r#"
+        [[pools]]
+        address = "{}"
+        name = "USDC-WETH-5bps"
+        cluster = "USDC-WETH"
+        token0_decimals = 6
+        token1_decimals = 18
+
+        [[pools]]
+        address = "{}"
+        name = "FOO-WETH-30bps"
+        cluster = "Foo Pairs"
+        deployment_block = 19000000
+        brontes_tracked = false
+        token0_decimals = 9
+        token1_decimals = 18
+        "#,
+        POOL_ADDRESSES[0], new_pool
+    )).unwrap());
+    assert_eq!(pools.deployment_block(new_pool), 19_000_000);
+    assert_eq!(pools.brontes_addresses().map(Address::as_str).collect::<Vec<_>>(), [POOL_ADDRESSES[0].to_lowercase()]);
+
+    let store = Arc::new(TestStore::new());
+    let snapshots: Vec<_> = [(POOL_ADDRESSES[0], 300i64), (new_pool, 100)].iter().map(|&(pool, total)| {
+        let checkpoint = Checkpoint::new(pool.to_lowercase(), MarkoutTime::Brontes);
+        checkpoint.running_total.store(total, Ordering::Release);
+        checkpoint.total_bucket_0_10.store(1, Ordering::Release);
+        checkpoint.to_snapshot()
+    }).collect();
+    ParallelParquetWriter::new(store.clone()).write_checkpoints(snapshots).await.unwrap();
+
+    let writer = PrecomputedWriter::new(store.clone()).with_pool_registry(pools.clone());
+    writer.write_pool_totals().await.unwrap();
+    writer.write_cluster_proportions().await.unwrap();
+
+    let state = Arc::new(AppState::new(store.clone()).with_pool_registry(pools));
+    let listed = get_pools(State(state.clone())).await.0.pools;
+    let listed: Vec<_> = listed.iter().map(|p| (p.pool_name.as_str(), p.cluster.as_deref(), p.token0_decimals)).collect();
+    assert_eq!(listed, [("USDC-WETH-5bps", Some("USDC-WETH"), 6), ("FOO-WETH-30bps", Some("Foo Pairs"), 9)]);
+
+    let totals = get_pool_totals(State(state.clone()), ApiQuery(PoolTotalsQuery { markout_time: None }))
+        .await
+        .unwrap()
+        .0
+        .totals;
+    let foo = totals.iter().find(|t| t.pool_address == new_pool.to_lowercase()).unwrap();
+    assert_eq!((foo.pool_name.as_str(), foo.total_lvr_cents.as_i64()), ("FOO-WETH-30bps", 100));
+
+    let pie = get_cluster_proportion(State(state), ApiQuery(ClusterQuery { markout_time: None }))
+        .await
+        .unwrap()
+        .0;
+    let mut clusters: Vec<_> = pie.clusters.iter().map(|c| (c.name.as_str(), c.total_lvr_cents.as_i64())).collect();
+    clusters.sort();
+    assert_eq!(clusters, [("Foo Pairs", 100), ("USDC-WETH", 300)]);
+
+    // The embedded registry does not know the new pool
+    PrecomputedWriter::new(store.clone()).write_pool_totals().await.unwrap();
+    let state = Arc::new(AppState::new(store));
+    let totals = get_pool_totals(State(state), ApiQuery(PoolTotalsQuery { markout_time: None })).await.unwrap().0.totals;
+    assert!(totals.iter().all(|t| t.pool_address != new_pool.to_lowercase()));
+}
+
+#[test]
+fn test_pool_registry_rejects_invalid_files() {
+    let entry = |address: &str| format!(
+        "[[pools]]\naddress = \"{}\"\nname = \"A-B\"\ntoken0_decimals = 18\ntoken1_decimals = 18\n",
+        address
+    );
+    let pool = POOL_ADDRESSES[0];
+    assert!(PoolRegistry::from_toml(&entry(pool)).is_ok());
+    assert!(PoolRegistry::from_toml(&(entry(pool) + &entry(&pool.to_uppercase().replace("0X", "0x")))).is_err());
+    assert!(PoolRegistry::from_toml(&entry("0x1234")).is_err());
+    assert!(PoolRegistry::from_toml("pools = []").is_err());
+
+    let embedded = PoolRegistry::default();
+    assert_eq!(embedded.pools().len(), POOL_ADDRESSES.len());
+    assert_eq!(embedded.deployment_block("0x435664008F38B0650fBC1C9fc971D0A3Bc2f1e47"), *USDeUSDT_DEPLOYMENT);
+    assert_eq!(embedded.cluster_name(POOL_ADDRESSES[0]), Some("USDC-WETH"));
+    assert_eq!(embedded.get(POOL_ADDRESSES[2]).map(|p| (p.token0_decimals, p.token1_decimals)), Some((18, 6)));
+}
+
+/// Checks `value` serializes to the fixture `name` of the golden responses,
+/// and that the fixture deserializes back to the same JSON
+fn assert_golden<T: serde::Serialize + serde::de::DeserializeOwned>(
+    golden: &mut serde_json::Map<String, serde_json::Value>,
+    name: &str,
+    value: T,
+) {
+    let expected = golden.remove(name).unwrap_or_else(|| panic!("No golden fixture for {}", name));
+    assert_eq!(serde_json::to_value(&value).unwrap(), expected, "{} no longer serializes like its fixture", name);
+    let parsed: T = serde_json::from_value(expected.clone()).unwrap_or_else(|e| panic!("{}: {}", name, e));
+    assert_eq!(serde_json::to_value(&parsed).unwrap(), expected, "{} does not round-trip", name);
+}
+
+#[test]
+fn test_responses_match_their_golden_json() {
+    let fixtures = std::fs::read_to_string(format!("{}/testdata/api/responses.json", env!("CARGO_MANIFEST_DIR"))).unwrap();
+    let serde_json::Value::Object(mut golden) = serde_json::from_str(&fixtures).unwrap() else {
+        panic!("Golden responses are not an object");
+    };
+    let pool = || POOL_ADDRESSES[0].to_lowercase();
+    let bucket = || HistogramBucket { range_start_dollars: 10.0, range_end_dollars: Some(100.0), count: 4, label: "$10-$100".to_string() };
+    let top_bucket = || HistogramBucket { range_start_dollars: 10_000.0, range_end_dollars: None, count: 1, label: "$10K+".to_string() };
+
+    assert_golden(&mut golden, "HealthResponse", HealthResponse {
+        status: "OK".to_string(),
+        version: "0.1.0".to_string(),
+        timestamp: "2024-01-02 3:04:05.0 +00:00:00".to_string(),
+    });
+    assert_golden(&mut golden, "RunningTotal", vec![RunningTotal {
+        block_number: 15_537_393,
+        markout: "0.5".to_string(),
+        pool_name: Some("USDC/WETH (5bps)".to_string()),
+        pool_address: Some(pool()),
+        running_total_cents: Cents(-1_250),
+    }]);
+    assert_golden(&mut golden, "IntervalAPIData", IntervalAPIData { total: 12, file_path: "intervals/0_216000.parquet".to_string() });
+    assert_golden(&mut golden, "LVRRatioResponse", LVRRatioResponse {
+        ratios: vec![MarkoutRatio { markout_time: "1.0".to_string(), ratio: 0.25, realized_lvr_cents: 100, theoretical_lvr_cents: 400 }],
+    });
+    assert_golden(&mut golden, "PoolTotalsResponse", PoolTotalsResponse {
+        totals: vec![PoolTotal { pool_name: "USDC/WETH (5bps)".to_string(), pool_address: pool(), total_lvr_cents: Cents(98_765) }],
+    });
+    assert_golden(&mut golden, "MonthlyPoolTotalsResponse", MonthlyPoolTotalsResponse {
+        pool_name: "USDC/WETH (5bps)".to_string(),
+        pool_address: pool(),
+        markout_time: "brontes".to_string(),
+        monthly_totals: vec![MonthlyPoolTotal { time_range: "Jan 2023".to_string(), total_lvr_cents: Cents(5_000) }],
+    });
+    assert_golden(&mut golden, "SchemaResponse", SchemaResponse {
+        datasets: vec![DatasetSchemaInfo {
+            dataset: "running_totals".to_string(),
+            path: "precomputed/running_totals/running_totals.parquet".to_string(),
+            expected_schema_version: "1.0".to_string(),
+            present: true,
+            metadata: HashMap::from([("lvr.schema_version".to_string(), "1.0".to_string())]),
+            needs_regeneration: false,
+            message: None,
+        }],
+    });
+    assert_golden(&mut golden, "RollingSeriesResponse", RollingSeriesResponse {
+        window: 7,
+        markout_time: "0.0".to_string(),
+        pool_address: None,
+        points: vec![RollingPoint { end_block: 15_587_792, window_intervals: 7, rolling_total_cents: Cents(70_000) }],
+    });
+    assert_golden(&mut golden, "ConcentrationResponse", ConcentrationResponse {
+        markout_time: "0.0".to_string(),
+        pool_count: 4,
+        hhi: 0.375,
+        top_1_share: 0.5,
+        top_3_share: 0.875,
+        top_5_share: 1.0,
+        effective_pools: 2.5,
+    });
+    assert_golden(&mut golden, "ActivityRunsResponse", ActivityRunsResponse {
+        markout_time: "brontes".to_string(),
+        pools: vec![PoolActivityRuns {
+            pool_name: "USDC/WETH (5bps)".to_string(),
+            pool_address: pool(),
+            first_active_block: 15_537_400,
+            dry_spells: 3,
+            longest_dry_spell: 120,
+            mean_dry_spell: 45.5,
+            longest_active_streak: 9,
+        }],
+    });
+    assert_golden(&mut golden, "CorrelationsResponse", CorrelationsResponse {
+        markout_time: "0.0".to_string(),
+        correlations: vec![PoolCorrelation {
+            pool_a: pool(),
+            pool_a_name: "USDC/WETH (5bps)".to_string(),
+            pool_b: POOL_ADDRESSES[1].to_lowercase(),
+            pool_b_name: "USDC/WETH (30bps)".to_string(),
+            correlation: 0.75,
+            n_intervals: 30,
+        }],
+    });
+    assert_golden(&mut golden, "PoolsResponse", PoolsResponse {
+        pools: vec![PoolInfo {
+            pool_address: pool(),
+            pool_name: "USDC/WETH (5bps)".to_string(),
+            cluster: Some("stable_pairs".to_string()),
+            deployment_block: 0,
+            brontes_tracked: true,
+            token0_decimals: 6,
+            token1_decimals: 18,
+        }],
+    });
+    assert_golden(&mut golden, "MaxLVRResponse", MaxLVRResponse {
+        pools: vec![MaxLVRPoolData { pool_name: "USDC/WETH (5bps)".to_string(), pool_address: pool(), block_number: 16_000_000, lvr_cents: 1_234_567 }],
+    });
+    assert_golden(&mut golden, "HistogramResponse", HistogramResponse {
+        pool_name: "USDC/WETH (5bps)".to_string(),
+        pool_address: pool(),
+        buckets: vec![bucket(), top_bucket()],
+        total_observations: 5,
+        equidepth_bins: Some(vec![EquiDepthBin { range_start_dollars: 10.0, range_end_dollars: 12.5, mass: 0.125 }]),
+    });
+    assert_golden(&mut golden, "HistogramResponseWithoutDetail", HistogramResponse {
+        pool_name: "USDC/WETH (5bps)".to_string(),
+        pool_address: pool(),
+        buckets: vec![top_bucket()],
+        total_observations: 1,
+        equidepth_bins: None,
+    });
+    assert_golden(&mut golden, "NonZeroProportionResponse", NonZeroProportionResponse {
+        pool_name: "USDC/WETH (5bps)".to_string(),
+        pool_address: pool(),
+        non_zero_proportion: 0.0625,
+        total_blocks: 3_200,
+        non_zero_blocks: 200,
+        deployment_block: 15_537_393,
+    });
+    assert_golden(&mut golden, "PercentileBandResponse", PercentileBandResponse {
+        pool_name: "USDC/WETH (5bps)".to_string(),
+        pool_address: pool(),
+        markout_time: "-1.0".to_string(),
+        data_points: vec![PercentileDataPoint {
+            start_block: 15_537_392,
+            end_block: 15_753_392,
+            total_lvr_dollars: 1_500.5,
+            percentile_25_dollars: 0.25,
+            median_dollars: 1.5,
+            percentile_75_dollars: 12.75,
+        }],
+    });
+    assert_golden(&mut golden, "ClusterPieResponse", ClusterPieResponse {
+        clusters: vec![ClusterTotal { name: "stable_pairs".to_string(), total_lvr_cents: Cents(40_000) }],
+        total_lvr_cents: Cents(40_000),
+    });
+    assert_golden(&mut golden, "ClusterHistogramResponse", ClusterHistogramResponse {
+        clusters: vec![ClusterHistogramData {
+            name: "stable_pairs".to_string(),
+            buckets: vec![ClusterHistogramBucket { range_start_dollars: 0.01, range_end_dollars: Some(10.0), count: 8, label: "$0.01-$10".to_string() }],
+            total_observations: 8,
+        }],
+    });
+    assert_golden(&mut golden, "ClusterMonthlyResponse", ClusterMonthlyResponse {
+        monthly_data: vec![MonthlyData {
+            time_range: "Jan 2023".to_string(),
+            cluster_totals_cents: HashMap::from([("stable_pairs".to_string(), Cents(3_000)), ("pepe".to_string(), Cents(-500))]),
+            total_lvr_cents: Cents(2_500),
+        }],
+        clusters: vec!["pepe".to_string(), "stable_pairs".to_string()],
+    });
+    assert_golden(&mut golden, "ClusterNonZeroResponse", ClusterNonZeroResponse {
+        clusters: vec![ClusterNonZero { name: "stable_pairs".to_string(), total_observations: 16, non_zero_observations: 4, non_zero_proportion: 0.25 }],
+    });
+    assert_golden(&mut golden, "QuartilePlotResponse", QuartilePlotResponse {
+        markout_time: "0.5".to_string(),
+        pool_name: "USDC/WETH (5bps)".to_string(),
+        pool_address: pool(),
+        percentile_25_cents: 25,
+        median_cents: 150,
+        percentile_75_cents: 1_275,
+    });
+    assert_golden(&mut golden, "DistributionResponse", DistributionResponse {
+        pool_name: "All Pools".to_string(),
+        pool_address: AGGREGATE_POOL_ADDRESS.to_string(),
+        markout_time: "brontes".to_string(),
+        mean_dollars: 12.5,
+        std_dev_dollars: 30.25,
+        skewness: 2.5,
+        kurtosis: 9.75,
+        fraction_above_1000_dollars: Some(0.015625),
+        trimmed_mean_cents: Some(900),
+        iqr_cents: None,
+    });
+    assert_golden(&mut golden, "TotalLVRResponse", TotalLVRResponse {
+        markout_totals: vec![MarkoutTotal { markout_time: "2.0".to_string(), total_dollars: 123_456.75 }],
+    });
+    assert_golden(&mut golden, "RawSeriesResponse", RawSeriesResponse {
+        pool_name: "USDC/WETH (5bps)".to_string(),
+        pool_address: pool(),
+        points: vec![RawLvrPoint { block_number: 15_537_400, markout_time: "brontes".to_string(), lvr_cents: Cents(-40) }],
+    });
+
+    let unchecked: Vec<&String> = golden.keys().collect();
+    assert!(unchecked.is_empty(), "Golden fixtures without a response: {:?}", unchecked);
+}
+
+/// Pool totals, concentration and one histogram, as the API serves them
+/// after precomputing from whatever checkpoints `store` holds
+async fn checkpoint_backed_responses(store: Arc<TestStore>, pool: &str) -> serde_json::Value {
+    let precompute = PrecomputedWriter::new(store.clone());
+    precompute.write_pool_totals().await.unwrap();
+    precompute.write_concentration_metrics().await.unwrap();
+    precompute.write_histograms().await.unwrap();
+
+    let state = Arc::new(AppState::new(store));
+    let totals = get_pool_totals(State(state.clone()), ApiQuery(PoolTotalsQuery { markout_time: Some(MarkoutTime::Zero) }))
+        .await
+        .unwrap()
+        .0;
+    let concentration = get_concentration(State(state.clone()), ApiQuery(ConcentrationQuery { markout_time: Some(MarkoutTime::Zero) }))
+        .await
+        .unwrap()
+        .0;
+    let histogram = get_lvr_histogram(
+        State(state),
+        ApiQuery(HistogramQuery { pool_address: pool.parse().unwrap(), markout_time: MarkoutTime::Brontes, detail: None }),
+    )
+    .await
+    .unwrap()
+    .0;
+    serde_json::json!({ "totals": totals, "concentration": concentration, "histogram": histogram })
+}
+
+#[tokio::test]
+async fn test_migrated_flat_checkpoints_serve_identical_responses() {
+    let store = Arc::new(TestStore::new());
+    let mut snapshots = Vec::new();
+    for (i, pool) in POOL_ADDRESSES[..2].iter().enumerate() {
+        for markout_time in [MarkoutTime::Zero, MarkoutTime::Brontes] {
+            let checkpoint = Checkpoint::new(pool.to_string(), markout_time);
+            checkpoint.running_total.store(40_000 + 10_000 * i as i64, Ordering::Release);
+            checkpoint.total_bucket_0.store(10, Ordering::Release);
+            checkpoint.total_bucket_10_100.store(2 + i as u64, Ordering::Release);
+            checkpoint.total_bucket_1000_10000.store(4, Ordering::Release);
+            snapshots.push(checkpoint.to_snapshot());
+        }
+    }
+    ParallelParquetWriter::new(store.clone()).write_checkpoints(snapshots).await.unwrap();
+
+    // Move what the writer stored to the layout of earlier releases
+    for path in store.paths().await {
+        let bytes = store.get(&path.as_str().into()).await.unwrap().bytes().await.unwrap();
+        let snapshot = read_checkpoint_snapshot(&read_parquet(store.as_ref(), &path).await[0]).unwrap();
+        let flat = flat_checkpoint_path(&snapshot.pair_address, snapshot.markout_time);
+        store.put(&flat, bytes.into()).await.unwrap();
+        store.delete(&path.as_str().into()).await.unwrap();
+        assert_eq!(parse_checkpoint_path(flat.as_ref()).unwrap().0, CheckpointLayout::Flat);
+    }
+    let flat = checkpoint_backed_responses(store.clone(), POOL_ADDRESSES[0]).await;
+
+    assert_eq!(migrate_checkpoint_layout(store.as_ref()).await.unwrap(), 4);
+    let checkpoints = list_checkpoints(store.as_ref()).await.unwrap();
+    assert_eq!(checkpoints.len(), 4);
+    for path in &checkpoints {
+        assert_eq!(parse_checkpoint_path(path.as_ref()).unwrap().0, CheckpointLayout::Partitioned, "{}", path);
+    }
+    assert_eq!(checkpoint_backed_responses(store.clone(), POOL_ADDRESSES[0]).await, flat);
+
+    // A flat leftover beside its partitioned successor is neither read nor
+    // migrated over it
+    let stale = flat_checkpoint_path(POOL_ADDRESSES[0], MarkoutTime::Zero);
+    let stale_bytes = store.get(&checkpoints[0]).await.unwrap().bytes().await.unwrap();
+    store.put(&stale, stale_bytes.into()).await.unwrap();
+    assert_eq!(list_checkpoints(store.as_ref()).await.unwrap(), checkpoints);
+    assert_eq!(checkpoint_backed_responses(store.clone(), POOL_ADDRESSES[0]).await, flat);
+    assert_eq!(migrate_checkpoint_layout(store.as_ref()).await.unwrap(), 1);
+    assert!(!store.paths().await.contains(&stale.to_string()));
+}
+
+#[tokio::test]
+async fn test_percentile_band_reads_only_the_requested_pool() {
+    let store = Arc::new(TestStore::new());
+    let writer = ParallelParquetWriter::new(store.clone());
+    let start = 15_537_393;
+    for chunk in 0..200u64 {
+        let chunk_start = start + chunk * 216_000;
+        let mut rows = Vec::new();
+        for (p, pool) in POOL_ADDRESSES.iter().enumerate() {
+            for markout_time in [MarkoutTime::Brontes, MarkoutTime::Zero] {
+                rows.extend((0..30u64).map(|interval_id| IntervalData {
+                    interval_id,
+                    pair_address: pool.to_string(),
+                    markout_time,
+                    total_lvr_cents: Cents(100 + (interval_id * 31 + p as u64 * 7 + chunk) as i64 % 500),
+                    max_lvr_cents: 500,
+                    non_zero_count: 10,
+                    total_count: 7200,
+                    start_block: chunk_start + interval_id * 7200,
+                    end_block: chunk_start + (interval_id + 1) * 7200,
+                    source_counts: BTreeMap::new(),
+                }));
+            }
+        }
+        writer.write_interval_data(rows, chunk_start, chunk_start + 216_000).await.unwrap();
+    }
+    // One row group per pool and markout, as a much larger history would have
+    let options = ParquetWriteOptions { max_row_group_size: 200, ..ParquetWriteOptions::default() };
+    PrecomputedWriter::new(store.clone()).with_write_options(options).write_percentile_bands().await.unwrap();
+    let size = store.head(&DatasetKind::PercentileBands.path()).await.unwrap().size;
+
+    let pool = POOL_ADDRESSES[5];
+    let before = store.bytes_read();
+    let response = get_percentile_band(
+        State(Arc::new(AppState::new(store.clone()))),
+        ApiQuery(PercentileBandQuery {
+            start_block: Some(start),
+            end_block: Some(start + 200 * 216_000),
+            pool_address: Some(pool.parse().unwrap()),
+            markout_time: Some(MarkoutTime::Brontes),
+        }),
+    )
+    .await
+    .unwrap()
+    .0;
+    let read = store.bytes_read() - before;
+
+    assert_eq!(response.data_points.len(), 200);
+    assert!(response.data_points.windows(2).all(|pair| pair[0].start_block < pair[1].start_block));
+    assert!(read * 4 < size, "read {} of {} bytes", read, size);
+}
@@ -0,0 +1,471 @@
+use crate::aurora::{AuroraConnection, QueryLimiter};
+use crate::brontes::BrontesConnection;
+use crate::credentials::{connect_with_credentials, CredentialProvider, Credentials, SecretsManagerCredentials};
+use crate::failover::{Endpoint, HostFailover};
+use crate::reconnect::ReconnectPolicy;
+use super::support::captured_logs;
+use crate::*;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::net::{TcpListener, TcpStream};
+
+fn aurora_config(port: u16) -> AuroraConfig {
+    AuroraConfig {
+        hosts: Vec::new(),
+        gcp_host: "127.0.0.1".to_string(),
+        public_host: "127.0.0.1".to_string(),
+        port,
+        user: "lvr".to_string(),
+        password: "lvr".to_string(),
+        credentials: CredentialSource::Env,
+        database: "lvr".to_string(),
+        connection_timeout: 1,
+        retry_interval: 0,
+        host_cooldown: 60,
+        ssl_verify: true,
+        ssl_ca_path: None,
+        limits: AuroraQueryLimits::default(),
+    }
+}
+
+fn brontes_config(port: u16) -> BrontesConfig {
+    BrontesConfig {
+        host: "127.0.0.1".to_string(),
+        port,
+        user: "lvr".to_string(),
+        password: "lvr".to_string(),
+        credentials: CredentialSource::Env,
+        connection_timeout: 1,
+        retry_interval: 0,
+    }
+}
+
+/// A port nothing listens on
+async fn closed_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap().port()
+}
+
+/// A port that accepts connections and never answers on them
+async fn silent_port() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let mut held = Vec::new();
+        while let Ok((socket, _)) = listener.accept().await {
+            held.push(socket);
+        }
+    });
+    port
+}
+
+#[tokio::test]
+async fn test_probe_reports_refused_databases_as_unreachable() {
+    let port = closed_port().await;
+    let aurora = AuroraConnection::new(aurora_config(port)).unwrap().with_reconnect(2, Duration::ZERO);
+    let brontes = BrontesConnection::new(brontes_config(port)).unwrap().with_reconnect(2, Duration::ZERO);
+    assert!(!aurora.is_connected().await);
+    assert!(!brontes.is_connected().await);
+
+    for status in [probe_database("aurora", &aurora).await, probe_database("brontes", &brontes).await] {
+        assert!(!status.reachable());
+        let error = status.error.as_deref().unwrap();
+        assert!(error.contains("Failed to connect after max"), "{}", error);
+        assert!(status.to_string().starts_with(&format!("{:<8} unreachable", status.name)), "{}", status);
+    }
+    assert!(!aurora.is_connected().await);
+    assert!(!brontes.is_connected().await);
+}
+
+#[tokio::test]
+async fn test_connect_times_out_on_unresponsive_servers() {
+    let port = silent_port().await;
+    let aurora = AuroraConnection::new(aurora_config(port)).unwrap().with_reconnect(1, Duration::ZERO);
+    let brontes = BrontesConnection::new(brontes_config(port)).unwrap().with_reconnect(1, Duration::ZERO);
+
+    let (aurora, brontes) = tokio::join!(probe_database("aurora", &aurora), probe_database("brontes", &brontes));
+    for status in [aurora, brontes] {
+        let error = status.error.as_deref().unwrap();
+        assert!(error.contains("timed out after 1s"), "{}", error);
+        assert!(status.elapsed < Duration::from_secs(3), "{:?}", status.elapsed);
+    }
+}
+
+#[tokio::test]
+async fn test_query_limiter_never_exceeds_its_limit() {
+    let limiter = Arc::new(QueryLimiter::new(3));
+    let queries = (0..20).map(|i| {
+        let limiter = limiter.clone();
+        tokio::spawn(async move {
+            limiter.run(async move {
+                tokio::time::sleep(Duration::from_millis(5 + i % 4)).await;
+                i
+            }).await
+        })
+    });
+    let results: Vec<u64> = futures::future::try_join_all(queries).await.unwrap();
+
+    assert_eq!(results, (0..20).collect::<Vec<_>>());
+    assert_eq!(limiter.peak_in_flight(), 3);
+}
+
+#[test]
+fn test_aurora_limits_come_from_the_config() {
+    let config = AppConfig::from_toml("[aurora]\nbatch_size = 500\nmax_concurrent_queries = 2").unwrap();
+    assert_eq!(config.aurora, AuroraQueryLimits { batch_size: 500, max_concurrent_queries: 2, ..Default::default() });
+    assert_eq!(AppConfig::from_toml("").unwrap().aurora, AuroraQueryLimits::default());
+    assert!(AppConfig::from_toml("[aurora]\nmax_concurrent_queries = 0").is_err());
+    assert!(AppConfig::from_toml("[aurora]\nbatch_size = 0").is_err());
+    assert!(AppConfig::from_toml("[aurora]\nquery_timeout_secs = 0").is_err());
+    assert!(AppConfig::from_toml("[brontes]\nquery_timeout_secs = 0").is_err());
+    let timeouts = AppConfig::from_toml("[aurora]\nquery_timeout_secs = 30\n[brontes]\nquery_timeout_secs = 20").unwrap();
+    assert_eq!((timeouts.aurora.query_timeout(), timeouts.brontes.query_timeout()), (Duration::from_secs(30), Duration::from_secs(20)));
+
+    let aurora = AuroraConnection::new(aurora_config(3306)).unwrap().with_limits(config.aurora);
+    assert_eq!(aurora.limits(), config.aurora);
+    assert_eq!(aurora.limiter().limit(), 2);
+}
+
+#[test]
+fn test_aurora_ssl_opts_follow_the_config() {
+    use crate::aurora::aurora_ssl_opts;
+    use mysql_async::SslOpts;
+
+    let (verified, logs) = captured_logs(|| aurora_ssl_opts(&aurora_config(3306), false).unwrap());
+    assert_eq!(verified, SslOpts::default());
+    assert!(!verified.accept_invalid_certs() && !verified.skip_domain_validation());
+    assert!(logs.is_empty());
+
+    let bundle = std::env::temp_dir().join(format!("aurora-ca-{}.pem", std::process::id()));
+    std::fs::write(&bundle, "-----BEGIN CERTIFICATE-----\n").unwrap();
+    let with_ca = AuroraConfig { ssl_ca_path: Some(bundle.clone()), ..aurora_config(3306) };
+    let opts = aurora_ssl_opts(&with_ca, false).unwrap();
+    assert_eq!(opts, SslOpts::default().with_root_certs(vec![bundle.clone().into()]));
+    std::fs::remove_file(&bundle).unwrap();
+    let error = aurora_ssl_opts(&with_ca, false).unwrap_err();
+    assert!(error.to_string().contains("CA bundle"), "{}", error);
+
+    // Turning verification off needs the opt-in
+    let insecure = AuroraConfig { ssl_verify: false, ..aurora_config(3306) };
+    let error = aurora_ssl_opts(&insecure, false).unwrap_err();
+    assert!(error.to_string().contains(ALLOW_INSECURE_SSL_ENV_VAR), "{}", error);
+    let (opts, logs) = captured_logs(|| aurora_ssl_opts(&insecure, true).unwrap());
+    assert_eq!(opts, SslOpts::default().with_danger_accept_invalid_certs(true));
+    assert!(logs.contains("WARN") && logs.contains("INSECURE"), "{}", logs);
+}
+
+#[test]
+fn test_aurora_ssl_settings_default_to_verification() {
+    let toml = "gcp_host = \"db\"\npublic_host = \"db\"\nport = 3306\nuser = \"lvr\"\npassword = \"lvr\"\n\
+        database = \"lvr\"\nconnection_timeout = 30\nretry_interval = 5\n";
+    let config: AuroraConfig = toml::from_str(toml).unwrap();
+    assert!(config.ssl_verify);
+    assert_eq!(config.ssl_ca_path, None);
+
+    let config: AuroraConfig = toml::from_str(&format!("{}ssl_verify = false\nssl_ca_path = \"/etc/rds.pem\"\n", toml)).unwrap();
+    assert!(!config.ssl_verify);
+    assert_eq!(config.ssl_ca_path, Some(std::path::PathBuf::from("/etc/rds.pem")));
+}
+
+#[test]
+fn test_only_transient_mysql_errors_are_retried() {
+    let server = |code| mysql_async::Error::Server(mysql_async::ServerError {
+        code,
+        message: "error".to_string(),
+        state: "HY000".to_string(),
+    });
+    for code in [1205, 1213, 3024] {
+        assert!(is_transient_error(&anyhow::Error::from(server(code))), "{}", code);
+    }
+    // Syntax error, unknown table, access denied
+    for code in [1064, 1146, 1045] {
+        assert!(!is_transient_error(&anyhow::Error::from(server(code))), "{}", code);
+    }
+    let io = mysql_async::Error::Io(mysql_async::IoError::Io(std::io::ErrorKind::ConnectionReset.into()));
+    let io = anyhow::Error::from(io).context("Batch query failed");
+    assert!(is_transient_error(&io));
+
+    // Only a lost connection fails over to another host
+    assert!(is_connection_error(&io));
+    assert!(is_connection_error(&anyhow::Error::from(server(1040))));
+    assert!(!is_connection_error(&anyhow::Error::from(server(1213))));
+}
+
+#[tokio::test]
+async fn test_failover_connects_to_the_first_endpoint_that_answers() {
+    assert_eq!(Endpoint::parse("replica.example:3307", 3306).unwrap().port, 3307);
+    assert_eq!(Endpoint::parse(" replica.example ", 3306).unwrap().to_string(), "replica.example:3306");
+    assert!(Endpoint::parse("replica.example:port", 3306).is_err());
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoints = vec![
+        Endpoint { host: "127.0.0.1".to_string(), port: closed_port().await },
+        Endpoint { host: "127.0.0.1".to_string(), port: listener.local_addr().unwrap().port() },
+    ];
+    let failover = HostFailover::new("test", endpoints);
+    let attempts = AtomicUsize::new(0);
+    let connect = |endpoint: Endpoint| {
+        attempts.fetch_add(1, Ordering::Relaxed);
+        async move { Ok(TcpStream::connect((endpoint.host, endpoint.port)).await?) }
+    };
+
+    let (endpoint, _stream) = failover.connect(connect).await.unwrap();
+    assert_eq!((endpoint, failover.current()), (1, 1));
+    assert!(!failover.is_healthy(0));
+    assert_eq!(failover.candidates(), vec![1]);
+
+    // The endpoint that worked is tried first, and the failed one not at all
+    let (endpoint, _stream) = failover.connect(connect).await.unwrap();
+    assert_eq!((endpoint, attempts.load(Ordering::Relaxed)), (1, 3));
+
+    // Once its cooldown is over, a failed endpoint is a fallback again
+    let failover = HostFailover::new("test", failover.endpoints().to_vec()).with_cooldown(Duration::ZERO);
+    failover.connect(connect).await.unwrap();
+    assert_eq!(failover.candidates(), vec![1, 0]);
+}
+
+#[tokio::test]
+async fn test_aurora_tries_every_configured_host() {
+    let (closed, silent) = (closed_port().await, silent_port().await);
+    let mut config = aurora_config(3306);
+    config.hosts = vec![format!("127.0.0.1:{}", closed), format!("127.0.0.1:{}", silent)];
+    let aurora = AuroraConnection::new(config).unwrap().with_reconnect(1, Duration::ZERO);
+    assert_eq!(aurora.failover().endpoints().len(), 2);
+
+    let status = probe_database("aurora", &aurora).await;
+    let error = status.error.as_deref().unwrap();
+    assert!(error.contains("Every Aurora endpoint failed"), "{}", error);
+    assert!(error.contains(&format!("127.0.0.1:{}: ", closed)), "{}", error);
+    assert!(error.contains(&format!("127.0.0.1:{}: Database error: Test connection timed out", silent)), "{}", error);
+    assert!(!aurora.failover().is_healthy(0) && !aurora.failover().is_healthy(1));
+}
+
+#[tokio::test]
+async fn test_brontes_queries_time_out_at_their_budget() {
+    let brontes = BrontesConnection::new(brontes_config(silent_port().await)).unwrap()
+        .with_limits(BrontesQueryLimits { query_timeout_secs: 1 });
+    let policy = RetryPolicy::new(2).with_base_delay(Duration::ZERO);
+
+    let started = Instant::now();
+    let error = brontes.fetch_lvr_analysis(&[POOL_ADDRESSES[0].to_lowercase()], 0, 10, &policy).await.unwrap_err();
+    let elapsed = started.elapsed();
+
+    // Each attempt ran for the whole budget, and the timeout was retried
+    assert!(elapsed >= Duration::from_secs(2) && elapsed < Duration::from_millis(3_500), "{:?}", elapsed);
+    assert!(format!("{:#}", error).contains("Timed out: LVR analysis batch 0-10 after 1s"), "{:#}", error);
+    assert!(is_transient_error(&error));
+}
+
+/// Hands out `old` until refreshed, then `new-<refreshes>`
+#[derive(Default)]
+struct RotatingCredentials {
+    refreshes: AtomicUsize,
+}
+
+#[async_trait::async_trait]
+impl CredentialProvider for RotatingCredentials {
+    async fn credentials(&self) -> anyhow::Result<Credentials> {
+        let password = match self.refreshes.load(Ordering::Acquire) {
+            0 => "old".to_string(),
+            refreshes => format!("new-{}", refreshes),
+        };
+        Ok(Credentials { user: "lvr".to_string(), password })
+    }
+
+    async fn refresh(&self) -> anyhow::Result<Credentials> {
+        self.refreshes.fetch_add(1, Ordering::AcqRel);
+        self.credentials().await
+    }
+}
+
+/// An HTTP server answering each request with `respond(request)`, keeping
+/// the lowercased requests it got
+async fn http_server<F>(respond: F) -> (u16, Arc<std::sync::Mutex<Vec<String>>>)
+where
+    F: Fn(&str) -> (u16, Vec<u8>) + Send + Sync + 'static,
+{
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let requests = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let seen = Arc::clone(&requests);
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let mut request = Vec::new();
+            let mut buffer = [0u8; 4096];
+            loop {
+                let read = socket.read(&mut buffer).await.unwrap_or(0);
+                request.extend_from_slice(&buffer[..read]);
+                let text = String::from_utf8_lossy(&request).to_lowercase();
+                let complete = text.split_once("\r\n\r\n").is_some_and(|(head, body)| {
+                    let length = head
+                        .lines()
+                        .find_map(|line| line.strip_prefix("content-length:"))
+                        .and_then(|length| length.trim().parse().ok())
+                        .unwrap_or(0);
+                    body.len() >= length
+                });
+                if read == 0 || complete {
+                    break;
+                }
+            }
+            let request = String::from_utf8_lossy(&request).to_lowercase();
+            let (status, body) = respond(&request);
+            seen.lock().unwrap().push(request);
+            let head = format!(
+                "HTTP/1.1 {} Status\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                status,
+                body.len()
+            );
+            let _ = socket.write_all(&[head.into_bytes(), body].concat()).await;
+        }
+    });
+    (port, requests)
+}
+
+#[tokio::test]
+async fn test_secrets_manager_credentials_are_cached_until_refreshed() {
+    let fetches = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&fetches);
+    let (port, requests) = http_server(move |_| {
+        let fetch = counter.fetch_add(1, Ordering::AcqRel) + 1;
+        let secret = serde_json::json!({ "username": "lvr", "password": format!("rotated-{}", fetch), "engine": "mysql" });
+        (200, serde_json::json!({ "ARN": "arn", "SecretString": secret.to_string() }).to_string().into_bytes())
+    })
+    .await;
+    let arn = "arn:aws:secretsmanager:eu-west-1:123456789012:secret:aurora-lvr-AbCdEf";
+    assert!(SecretsManagerCredentials::new("not-an-arn").is_err());
+    let aws_credential = object_store::aws::AwsCredential { key_id: "AKID".to_string(), secret_key: "secret".to_string(), token: None };
+    let provider = SecretsManagerCredentials::new(arn).unwrap()
+        .with_endpoint(format!("http://127.0.0.1:{}", port))
+        .with_aws_credentials(Arc::new(object_store::StaticCredentialProvider::new(aws_credential)));
+
+    assert_eq!(provider.credentials().await.unwrap().password, "rotated-1");
+    assert_eq!(provider.credentials().await.unwrap().password, "rotated-1");
+    assert_eq!(provider.refresh().await.unwrap().password, "rotated-2");
+    assert_eq!(provider.credentials().await.unwrap().password, "rotated-2");
+    assert_eq!(fetches.load(Ordering::Acquire), 2);
+
+    let request = requests.lock().unwrap()[0].clone();
+    assert!(request.contains("x-amz-target: secretsmanager.getsecretvalue"), "{}", request);
+    assert!(request.contains("credential=akid/"), "{}", request);
+    assert!(request.contains("/eu-west-1/secretsmanager/aws4_request"), "{}", request);
+    assert!(request.contains(&arn.to_lowercase()), "{}", request);
+}
+
+#[tokio::test]
+async fn test_rejected_credentials_are_refreshed_before_reconnecting() {
+    let provider = RotatingCredentials::default();
+    let denied = || mysql_async::Error::Server(mysql_async::ServerError {
+        code: 1045,
+        message: "Access denied for user 'lvr'".to_string(),
+        state: "28000".to_string(),
+    });
+    let built = std::sync::Mutex::new(Vec::new());
+    let build_pool = |credentials: Credentials| {
+        built.lock().unwrap().push(credentials.password.clone());
+        let result = if credentials.password == "old" { Err(anyhow::Error::from(denied())) } else { Ok(credentials) };
+        async move { result }
+    };
+
+    let credentials = connect_with_credentials(&provider, "test", build_pool).await.unwrap();
+    assert_eq!(credentials.password, "new-1");
+    assert_eq!(*built.lock().unwrap(), vec!["old", "new-1"]);
+    assert!(is_auth_error(&anyhow::Error::from(denied()).context("Failed to verify connection")));
+
+    // Other failures are not worth a refresh
+    let refused = |_| async { Err::<(), _>(anyhow::Error::from(std::io::Error::from(std::io::ErrorKind::ConnectionRefused))) };
+    assert!(connect_with_credentials(&provider, "test", refused).await.is_err());
+    assert_eq!(provider.refreshes.load(Ordering::Acquire), 1);
+}
+
+#[tokio::test]
+async fn test_brontes_rebuilds_its_client_after_rejected_credentials() {
+    let (port, requests) = http_server(|request| {
+        if request.contains("x-clickhouse-key: old") {
+            (516, "Code: 516. DB::Exception: lvr: Authentication failed: password is incorrect. (AUTHENTICATION_FAILED)".into())
+        } else {
+            (404, "Code: 60. DB::Exception: Unknown table expression identifier 'brontes.block_analysis'. (UNKNOWN_TABLE)".into())
+        }
+    })
+    .await;
+    let provider = Arc::new(RotatingCredentials::default());
+    let brontes = BrontesConnection::new(brontes_config(port)).unwrap().with_credentials(provider.clone());
+    let policy = RetryPolicy::new(2).with_base_delay(Duration::ZERO);
+
+    let error = brontes.fetch_lvr_analysis(&[POOL_ADDRESSES[0].to_lowercase()], 0, 10, &policy).await.unwrap_err();
+
+    // The retry went out with the refreshed password and failed on the query
+    assert!(format!("{:#}", error).contains("UNKNOWN_TABLE"), "{:#}", error);
+    let keys: Vec<String> = requests
+        .lock()
+        .unwrap()
+        .iter()
+        .filter_map(|request| request.lines().find_map(|line| line.strip_prefix("x-clickhouse-key: ")).map(String::from))
+        .collect();
+    assert_eq!(keys, vec!["old", "new-1"]);
+    assert_eq!(provider.refreshes.load(Ordering::Acquire), 1);
+}
+
+#[tokio::test]
+async fn test_a_failed_first_connection_counts_one_reconnect() {
+    let metrics = Arc::new(MetricsRegistry::new());
+    let opened = AtomicUsize::new(0);
+    let flaky_pool = || {
+        let attempt = opened.fetch_add(1, Ordering::AcqRel);
+        async move {
+            if attempt == 0 {
+                return Err(Error::Database("Test connection timed out after 1s".to_string()).into());
+            }
+            Ok(attempt)
+        }
+    };
+
+    let pool = ReconnectPolicy::new(3, Duration::ZERO).connect("test", Some(&metrics), flaky_pool).await.unwrap();
+    assert_eq!(pool, 1);
+    for block in 0..3u64 {
+        assert_eq!(metrics.time_aurora_query(async { Ok::<_, anyhow::Error>(block) }).await.unwrap(), block);
+    }
+    let rendered = metrics.render();
+    assert!(rendered.contains("aurora_pool_created_total 1\n"), "{}", rendered);
+    assert!(rendered.contains("aurora_reconnects_total 1\n"), "{}", rendered);
+    assert!(rendered.contains("aurora_query_errors_total 0\n"), "{}", rendered);
+    assert!(rendered.contains("aurora_query_seconds_count 3\n"), "{}", rendered);
+    assert!(rendered.contains("aurora_query_seconds_bucket{le=\"+Inf\"} 3\n"), "{}", rendered);
+
+    // An unreachable Aurora retries under its reconnect policy and opens nothing
+    let aurora = AuroraConnection::new(aurora_config(closed_port().await)).unwrap()
+        .with_reconnect(3, Duration::ZERO)
+        .with_metrics(Arc::clone(&metrics));
+    let error = aurora.connect().await.unwrap_err();
+    assert!(format!("{:#}", error).contains("Failed to connect after maximum attempts"), "{:#}", error);
+    let failed = metrics.time_aurora_query(async { Err::<(), _>(error) }).await;
+    assert!(failed.is_err());
+    let rendered = metrics.render();
+    assert!(rendered.contains("aurora_pool_created_total 1\n"), "{}", rendered);
+    assert!(rendered.contains("aurora_reconnects_total 3\n"), "{}", rendered);
+    assert!(rendered.contains("aurora_query_errors_total 1\n"), "{}", rendered);
+}
+
+#[tokio::test]
+async fn test_brontes_rows_map_to_lvr_analysis() {
+    let recorded = std::fs::read(format!("{}/testdata/brontes/lvr_analysis.rowbinary.lz4", env!("CARGO_MANIFEST_DIR"))).unwrap();
+    let (port, requests) = http_server(move |_| (200, recorded.clone())).await;
+    let brontes = BrontesConnection::new(brontes_config(port)).unwrap();
+    let pools = [POOL_ADDRESSES[0].to_lowercase(), POOL_ADDRESSES[1].to_lowercase()];
+
+    let rows = brontes.fetch_lvr_analysis(&pools, 15537393, 15537403, &RetryPolicy::new(1)).await.unwrap();
+
+    let rows: Vec<_> = rows.iter().map(|row| (row.pool_address.as_str(), row.block_number, row.lvr)).collect();
+    assert_eq!(rows, vec![
+        (pools[0].as_str(), 15537394, 12.5),
+        (pools[1].as_str(), 15537394, 0.0734),
+        (pools[0].as_str(), 15537401, 1843.2909),
+    ]);
+    // The recorded rows answer the query for these pools and block bounds
+    let request = requests.lock().unwrap()[0].clone();
+    for expected in ["block_number+%3e+15537393", "block_number+%3c%3d+15537403", &pools[0], &pools[1], "format+rowbinary"] {
+        assert!(request.contains(expected), "{} not in {}", expected, request);
+    }
+}
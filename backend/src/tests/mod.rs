@@ -1,2 +1,17 @@
 pub mod test;
-pub use test::*;
\ No newline at end of file
+pub use test::*;
+
+#[cfg(test)]
+mod support;
+#[cfg(test)]
+mod precompute_test;
+#[cfg(test)]
+mod writer_test;
+#[cfg(test)]
+mod storage_test;
+#[cfg(test)]
+mod api_test;
+#[cfg(test)]
+mod processor_test;
+#[cfg(test)]
+mod db_test;
@@ -0,0 +1,628 @@
+use super::support::{read_parquet, track_allocations, TestStore};
+use crate::*;
+use object_store::ObjectStore;
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+#[tokio::test]
+async fn test_run_all_overlaps_tasks() {
+    let latency = Duration::from_millis(100);
+    let store: Arc<dyn ObjectStore> = Arc::new(TestStore::new().with_latency(latency));
+    let writer = PrecomputedWriter::new(store);
+
+    let started = Instant::now();
+    let timings = writer.run_all(PRECOMPUTE_TASKS.len()).await.unwrap();
+    let wall = started.elapsed();
+
+    assert_eq!(timings.len(), PRECOMPUTE_TASKS.len());
+    let sum: Duration = timings.iter().map(|(_, d)| *d).sum();
+    let max = timings.iter().map(|(_, d)| *d).max().unwrap();
+
+    assert!(max >= latency);
+    assert!(wall < sum / 2, "wall {:?} should be well under sum {:?}", wall, sum);
+    assert!(wall < max * 2, "wall {:?} should be near max {:?}", wall, max);
+}
+
+#[tokio::test]
+async fn test_run_all_reports_failing_task() {
+    let store: Arc<dyn ObjectStore> = Arc::new(TestStore::new().fail_list("intervals"));
+    let writer = PrecomputedWriter::new(store);
+
+    let err = writer.run_all(1).await.unwrap_err();
+    let message = format!("{:#}", err);
+
+    // running_totals is the first task to touch intervals/ at concurrency 1
+    assert!(message.contains("running_totals"), "unexpected error: {}", message);
+}
+
+fn parquet_payload(values: Vec<u64>) -> bytes::Bytes {
+    let batch = arrow::record_batch::RecordBatch::try_from_iter([(
+        "value",
+        Arc::new(arrow::array::UInt64Array::from(values)) as arrow::array::ArrayRef,
+    )])
+    .unwrap();
+    let mut buffer = Vec::new();
+    let mut writer = parquet::arrow::ArrowWriter::try_new(&mut buffer, batch.schema(), None).unwrap();
+    writer.write(&batch).unwrap();
+    writer.close().unwrap();
+    bytes::Bytes::from(buffer)
+}
+
+async fn stored_rows(store: &TestStore, path: &object_store::path::Path) -> i64 {
+    let bytes = store.get(path).await.unwrap().bytes().await.unwrap();
+    let reader = parquet::file::reader::SerializedFileReader::new(bytes).unwrap();
+    parquet::file::reader::FileReader::metadata(&reader).file_metadata().num_rows()
+}
+
+#[tokio::test]
+async fn test_interrupted_put_keeps_previous_object() {
+    let store = TestStore::new();
+    let path = object_store::path::Path::from("precomputed/clusters/proportions.parquet");
+
+    atomic_put(&store, &path, parquet_payload(vec![1, 2, 3]), 3).await.unwrap();
+
+    store.truncate_next_puts(1);
+    atomic_put(&store, &path, parquet_payload(vec![1, 2, 3, 4, 5]), 5).await.unwrap_err();
+
+    assert_eq!(stored_rows(&store, &path).await, 3);
+    assert_eq!(store.paths().await, vec![path.to_string()], "temporary object left behind");
+}
+
+#[tokio::test]
+async fn test_atomic_put_verifies_from_the_footer_only() {
+    let store = TestStore::new();
+    let path = object_store::path::Path::from("precomputed/clusters/proportions.parquet");
+    let payload = parquet_payload((0..100_000).collect());
+    let payload_bytes = payload.len();
+
+    atomic_put(&store, &path, payload, 100_000).await.unwrap();
+    assert!(store.bytes_read() < payload_bytes / 10, "read {} of {} bytes", store.bytes_read(), payload_bytes);
+
+    // A wrong row count is still caught from the footer
+    atomic_put(&store, &path, parquet_payload(vec![1, 2, 3]), 4).await.unwrap_err();
+    assert_eq!(stored_rows(&store, &path).await, 100_000);
+}
+
+#[tokio::test]
+async fn test_precomputed_writes_recover_from_interrupted_put() {
+    let store = Arc::new(TestStore::new());
+    store.truncate_next_puts(1);
+    let writer = PrecomputedWriter::new(store.clone());
+
+    writer.write_pool_totals().await.unwrap();
+
+    let path = object_store::path::Path::from("precomputed/pool_metrics/totals.parquet");
+    assert_eq!(store.paths().await, vec![path.to_string()]);
+    stored_rows(&store, &path).await;
+}
+
+async fn write_synthetic_intervals(store: Arc<TestStore>, files: u64) {
+    let writer = ParallelParquetWriter::new(store);
+    // Written newest first so the precompute has to order the listing itself
+    for file in (0..files).rev() {
+        let start = 15_537_392 + file * 216_000;
+        let mut rows = Vec::new();
+        for interval_id in 0..30u64 {
+            for (p, pool) in POOL_ADDRESSES.iter().enumerate() {
+                for (m, markout) in MARKOUT_TIMES.iter().enumerate() {
+                    let seed = file * 7919 + interval_id * 104_729 + p as u64 * 31 + m as u64;
+                    rows.push(IntervalData {
+                        interval_id,
+                        pair_address: pool.to_string(),
+                        markout_time: MarkoutTime::from_f64(*markout).unwrap(),
+                        total_lvr_cents: Cents((seed % 50_000) as i64),
+                        max_lvr_cents: seed % 5_000,
+                        non_zero_count: seed % 7,
+                        total_count: 7200,
+                        start_block: start + interval_id * 7200,
+                        end_block: start + (interval_id + 1) * 7200,
+                        source_counts: BTreeMap::new(),
+                    });
+                }
+            }
+        }
+        writer.write_interval_data(rows, start, start + 216_000).await.unwrap();
+    }
+}
+
+/// Checkpoints for every pool and markout written by `write_synthetic_intervals`
+async fn write_synthetic_checkpoints(store: Arc<TestStore>) {
+    let mut snapshots = Vec::new();
+    for (p, pool) in POOL_ADDRESSES.iter().enumerate() {
+        for (m, markout) in MARKOUT_TIMES.iter().enumerate() {
+            let checkpoint = Checkpoint::new(pool.to_string(), MarkoutTime::from_f64(*markout).unwrap());
+            let seed = (p * 13 + m) as u64;
+            checkpoint.running_total.store(1_000 + seed as i64 * 977, std::sync::atomic::Ordering::Release);
+            checkpoint.total_bucket_0.store(seed % 5, std::sync::atomic::Ordering::Release);
+            checkpoint.total_bucket_10_100.store(seed % 11 + 1, std::sync::atomic::Ordering::Release);
+            for i in 0..=seed % 9 {
+                checkpoint.update_digest(10.0 + (i * 37 + seed) as f64).unwrap();
+            }
+            snapshots.push(checkpoint.to_snapshot());
+        }
+    }
+    ParallelParquetWriter::new(store).write_checkpoints(snapshots).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_precompute_output_is_byte_identical_across_runs() {
+    let store = Arc::new(TestStore::new());
+    write_synthetic_intervals(store.clone(), 2).await;
+    write_synthetic_checkpoints(store.clone()).await;
+    let generated_at = chrono::Utc::now();
+
+    async fn precomputed_files(store: &Arc<TestStore>) -> Vec<(String, bytes::Bytes)> {
+        let mut files = Vec::new();
+        for path in store.paths().await.into_iter().filter(|p| p.starts_with("precomputed/")) {
+            let bytes = store.get(&object_store::path::Path::from(path.as_str())).await.unwrap().bytes().await.unwrap();
+            files.push((path, bytes));
+        }
+        files
+    }
+
+    PrecomputedWriter::new(store.clone()).with_generated_at(generated_at).run_all(4).await.unwrap();
+    let first = precomputed_files(&store).await;
+    PrecomputedWriter::new(store.clone()).with_generated_at(generated_at).run_all(4).await.unwrap();
+    let second = precomputed_files(&store).await;
+
+    // Running totals also write the aggregate and one partition per pool
+    assert_eq!(first.len(), PRECOMPUTE_TASKS.len() + 1 + PoolRegistry::default().pools().len());
+    for ((path, first), (_, second)) in first.iter().zip(&second) {
+        assert!(first == second, "{} differs between runs", path);
+    }
+
+    // Row groups record the order rows were sorted in
+    let bytes = store.get(&DatasetKind::Histograms.path()).await.unwrap().bytes().await.unwrap();
+    let reader = parquet::file::serialized_reader::SerializedFileReader::new(bytes).unwrap();
+    let row_group = parquet::file::reader::FileReader::metadata(&reader).row_group(0);
+    let sorted_by: Vec<_> = row_group.sorting_columns().unwrap().iter()
+        .map(|column| row_group.schema_descr().column(column.column_idx as usize).name().to_string())
+        .collect();
+    assert_eq!(sorted_by, DatasetKind::Histograms.sort_columns());
+    assert!(row_group.column(0).statistics().is_some());
+}
+
+/// Reference running totals computed by summing every interval row up front
+fn expected_individual_totals(files: u64) -> Vec<(u64, String, String, i64)> {
+    let mut per_block: std::collections::BTreeMap<(u64, String, String), i64> = Default::default();
+    for file in 0..files {
+        let start = 15_537_392 + file * 216_000;
+        for interval_id in 0..30u64 {
+            for (p, pool) in POOL_ADDRESSES.iter().enumerate() {
+                for (m, markout) in MARKOUT_TIMES.iter().enumerate() {
+                    let seed = file * 7919 + interval_id * 104_729 + p as u64 * 31 + m as u64;
+                    if seed.is_multiple_of(7) {
+                        continue;
+                    }
+                    let block = start + (interval_id + 1) * 7200;
+                    let markout = MarkoutTime::from_f64(*markout).unwrap().to_string();
+                    *per_block.entry((block, markout, pool.to_lowercase())).or_default() += (seed % 50_000) as i64;
+                }
+            }
+        }
+    }
+
+    let mut running: std::collections::HashMap<(String, String), i64> = Default::default();
+    per_block
+        .into_iter()
+        .map(|((block, markout, pool), cents)| {
+            let total = running.entry((pool.clone(), markout.clone())).or_default();
+            *total += cents;
+            (block, markout, pool, *total)
+        })
+        .collect()
+}
+
+async fn read_individual_totals(store: &TestStore) -> Vec<(u64, String, String, i64)> {
+    let bytes = store
+        .get(&object_store::path::Path::from("precomputed/running_totals/individual.parquet"))
+        .await
+        .unwrap()
+        .bytes()
+        .await
+        .unwrap();
+    let mut rows = Vec::new();
+    for batch in parquet::arrow::arrow_reader::ParquetRecordBatchReader::try_new(bytes, 1024).unwrap() {
+        let batch = batch.unwrap();
+        let blocks = common::get_uint64_column(&batch, "block_number").unwrap();
+        let markouts = common::get_string_column(&batch, "markout_time").unwrap();
+        let pools = common::get_string_column(&batch, "pool_address").unwrap();
+        let totals = common::get_int64_column(&batch, "running_total_cents").unwrap();
+        for i in 0..batch.num_rows() {
+            rows.push((blocks.value(i), markouts.value(i).to_string(), pools.value(i).to_string(), totals.value(i)));
+        }
+    }
+    rows
+}
+
+#[tokio::test]
+async fn test_running_totals_match_full_aggregation() {
+    let store = Arc::new(TestStore::new());
+    write_synthetic_intervals(store.clone(), 4).await;
+
+    PrecomputedWriter::new(store.clone()).write_running_totals().await.unwrap();
+
+    assert_eq!(read_individual_totals(&store).await, expected_individual_totals(4));
+}
+
+#[tokio::test]
+async fn test_running_totals_memory_is_bounded_per_file() {
+    let options = ParquetWriteOptions {
+        max_row_group_size: 8192,
+        ..ParquetWriteOptions::default()
+    };
+
+    let mut working_sets = Vec::new();
+    for files in [4, 16] {
+        let store = Arc::new(TestStore::new());
+        write_synthetic_intervals(store.clone(), files).await;
+        let writer = PrecomputedWriter::new(store.clone()).with_write_options(options.clone());
+
+        let (result, peak) = track_allocations(writer.write_running_totals()).await;
+        result.unwrap();
+
+        // The encoded outputs necessarily grow with the input; everything
+        // else should be bounded by a single interval file
+        let mut output_bytes = 0;
+        for path in store.paths().await {
+            if path.starts_with("precomputed/") {
+                output_bytes += store.head(&object_store::path::Path::from(path)).await.unwrap().size;
+            }
+        }
+        working_sets.push(peak.saturating_sub(output_bytes));
+    }
+
+    assert!(
+        working_sets[1] < working_sets[0] * 3 / 2,
+        "working set grew from {} to {} bytes for 4x the input",
+        working_sets[0],
+        working_sets[1]
+    );
+}
+
+#[test]
+fn test_percentile_edge_cases() {
+    assert_eq!(PrecomputedWriter::calculate_unweighted_percentile(&[], 50), 0.0);
+    assert_eq!(PrecomputedWriter::calculate_unweighted_percentile(&[1234], 25), 12.34);
+    assert_eq!(PrecomputedWriter::calculate_unweighted_percentile(&[500; 8], 75), 5.0);
+
+    // Unsorted input interpolates between the neighbouring order statistics
+    let values = [400, 100, 300, 200];
+    assert_eq!(PrecomputedWriter::calculate_unweighted_percentile(&values, 0), 1.0);
+    assert_eq!(PrecomputedWriter::calculate_unweighted_percentile(&values, 50), 2.5);
+    assert_eq!(PrecomputedWriter::calculate_unweighted_percentile(&values, 100), 4.0);
+
+    let mut sorted = values.to_vec();
+    sorted.sort_unstable();
+    for p in [25, 50, 75] {
+        assert_eq!(
+            PrecomputedWriter::percentile_of_sorted(&sorted, p),
+            PrecomputedWriter::calculate_unweighted_percentile(&values, p)
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_precompute_reads_checkpoint_identity_from_columns() {
+    let store = Arc::new(TestStore::new());
+    let pool = POOL_ADDRESSES[0];
+
+    let checkpoint = Checkpoint::new(pool.to_string(), MarkoutTime::Negative05);
+    checkpoint.running_total.store(12_345, std::sync::atomic::Ordering::Release);
+    checkpoint.total_bucket_0.store(5, std::sync::atomic::Ordering::Release);
+    checkpoint.total_bucket_0_10.store(3, std::sync::atomic::Ordering::Release);
+    checkpoint.update_max_lvr(15_600_000, 900);
+
+    let writer = ParallelParquetWriter::new(store.clone());
+    writer.write_checkpoints(vec![checkpoint.to_snapshot()]).await.unwrap();
+
+    // Move the checkpoint somewhere whose name says nothing about it
+    let original = store.paths().await.pop().unwrap();
+    let renamed = object_store::path::Path::from("checkpoints/renamed.parquet");
+    store.rename(&object_store::path::Path::from(original.as_str()), &renamed).await.unwrap();
+
+    let precompute = PrecomputedWriter::new(store.clone());
+    precompute.write_pool_totals().await.unwrap();
+    precompute.write_max_lvr().await.unwrap();
+
+    for path in ["precomputed/pool_metrics/totals.parquet", "precomputed/pool_metrics/max_lvr.parquet"] {
+        let batches = read_parquet(store.as_ref(), path).await;
+        assert_eq!(batches.len(), 1, "{}", path);
+        let batch = &batches[0];
+        assert_eq!(batch.num_rows(), 1, "{}", path);
+        assert_eq!(common::get_string_column(batch, "pool_address").unwrap().value(0), pool.to_lowercase());
+        assert_eq!(common::get_string_column(batch, "markout_time").unwrap().value(0), "-0.5");
+    }
+}
+
+#[tokio::test]
+async fn test_aggregate_quartiles_merge_pool_digests() {
+    let store = Arc::new(TestStore::new());
+
+    let mut checkpoints = Vec::new();
+    let mut expected = TDigest::new();
+    for (pool, offset) in [(POOL_ADDRESSES[0], 1.0), (POOL_ADDRESSES[1], 500.0)] {
+        let checkpoint = Checkpoint::new(pool.to_string(), MarkoutTime::Brontes);
+        let mut digest = TDigest::new();
+        for i in 0..2_000 {
+            digest.add(offset + i as f64 / 10.0);
+        }
+        expected.merge(&digest);
+        *checkpoint.digest.lock().unwrap() = digest;
+        checkpoints.push(checkpoint.to_snapshot());
+    }
+    // Quartiles that no merge of the pools could produce
+    let aggregate = Checkpoint::new(AGGREGATE_POOL_ADDRESS.to_string(), MarkoutTime::Brontes);
+    aggregate.digest.lock().unwrap().add(1e6);
+    checkpoints.push(aggregate.to_snapshot());
+
+    let writer = ParallelParquetWriter::new(store.clone());
+    writer.write_checkpoints(checkpoints).await.unwrap();
+    PrecomputedWriter::new(store.clone()).write_quartile_plots().await.unwrap();
+
+    let batch = read_parquet(store.as_ref(), "precomputed/distributions/quartile_plots.parquet").await.remove(0);
+    let pools = common::get_string_column(&batch, "pool_address").unwrap();
+    let row = (0..batch.num_rows()).find(|&i| pools.value(i) == AGGREGATE_POOL_ADDRESS).unwrap();
+    let cents = |q: f64| (expected.quantile(q).unwrap() * 100.0).round() as u64;
+    for (column, q) in [("percentile_25_cents", 0.25), ("median_cents", 0.5), ("percentile_75_cents", 0.75)] {
+        assert_eq!(common::get_uint64_column(&batch, column).unwrap().value(row), cents(q), "{}", column);
+    }
+}
+
+/// Rewrites an interval file the way legacy writers produced it: the pair
+/// address column under its old name, an unsigned `total_lvr_cents` and no
+/// block range columns
+async fn downgrade_interval_file(store: &TestStore, location: &str) {
+    use arrow::datatypes::DataType;
+
+    let batches = read_parquet(store, location).await;
+    let mut writer = None;
+    for mut batch in batches {
+        for column in [INTERVAL_START_BLOCK_COLUMN, INTERVAL_END_BLOCK_COLUMN] {
+            batch.remove_column(batch.schema().index_of(column).unwrap());
+        }
+        let fields: Vec<arrow::datatypes::Field> = batch.schema().fields().iter()
+            .map(|f| if f.name() == INTERVAL_PAIR_ADDRESS_COLUMN {
+                f.as_ref().clone().with_name("pool_address")
+            } else if f.name() == INTERVAL_TOTAL_LVR_COLUMN {
+                f.as_ref().clone().with_data_type(DataType::UInt64)
+            } else {
+                f.as_ref().clone()
+            })
+            .collect();
+        let columns = batch.schema().fields().iter()
+            .zip(batch.columns())
+            .map(|(f, column)| if f.name() == INTERVAL_TOTAL_LVR_COLUMN {
+                arrow::compute::cast(column, &DataType::UInt64).unwrap()
+            } else {
+                column.clone()
+            })
+            .collect();
+        let schema = Arc::new(arrow::datatypes::Schema::new(fields));
+        let legacy = arrow::record_batch::RecordBatch::try_new(schema.clone(), columns).unwrap();
+        writer
+            .get_or_insert_with(|| parquet::arrow::ArrowWriter::try_new(Vec::new(), schema, None).unwrap())
+            .write(&legacy)
+            .unwrap();
+    }
+    let payload = writer.unwrap().into_inner().unwrap();
+    store.put(&object_store::path::Path::from(location), payload.into()).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_precompute_reads_legacy_and_current_interval_files() {
+    let store = Arc::new(TestStore::new());
+    write_synthetic_intervals(store.clone(), 2).await;
+    let intervals = store.paths().await;
+    let legacy = intervals.iter().find(|p| p.starts_with("intervals/")).unwrap();
+    downgrade_interval_file(&store, legacy).await;
+
+    let writer = PrecomputedWriter::new(store.clone());
+    writer.write_running_totals().await.unwrap();
+    writer.write_percentile_bands().await.unwrap();
+
+    assert_eq!(read_individual_totals(&store).await, expected_individual_totals(2));
+    assert!(!read_parquet(store.as_ref(), "precomputed/distributions/percentile_bands.parquet").await.is_empty());
+}
+
+#[tokio::test]
+async fn test_legacy_final_interval_ends_with_its_file() {
+    let store = Arc::new(TestStore::new());
+    let pool = POOL_ADDRESSES[0];
+    let (start, end) = (19_857_392, 20_000_000);
+    let rows = [18u64, 19].iter().map(|&interval_id| IntervalData {
+        interval_id,
+        pair_address: pool.to_string(),
+        markout_time: MarkoutTime::Brontes,
+        total_lvr_cents: Cents(100),
+        max_lvr_cents: 100,
+        non_zero_count: 1,
+        total_count: 7200,
+        start_block: start + interval_id * 7200,
+        end_block: (start + (interval_id + 1) * 7200).min(end),
+        source_counts: BTreeMap::new(),
+    }).collect();
+    ParallelParquetWriter::new(store.clone()).write_interval_data(rows, start, end).await.unwrap();
+    let location = format!("intervals/{}_{}.parquet", start, end);
+    downgrade_interval_file(&store, &location).await;
+    assert!(read_parquet(store.as_ref(), &location).await[0].schema().index_of(INTERVAL_END_BLOCK_COLUMN).is_err());
+
+    PrecomputedWriter::new(store.clone()).write_running_totals().await.unwrap();
+    let blocks: Vec<u64> = read_individual_totals(&store).await.iter().map(|row| row.0).collect();
+    assert_eq!(blocks, vec![start + 19 * 7200, end]);
+}
+
+#[test]
+fn test_normalize_interval_batch_renames_legacy_columns() {
+    let legacy = arrow::record_batch::RecordBatch::try_from_iter([
+        ("pool_address", Arc::new(arrow::array::StringArray::from(vec!["0xabc"])) as arrow::array::ArrayRef),
+    ]).unwrap();
+    let normalized = normalize_interval_batch(legacy).unwrap();
+    assert!(normalized.schema().index_of(INTERVAL_PAIR_ADDRESS_COLUMN).is_ok());
+
+    let current = arrow::record_batch::RecordBatch::new_empty(interval_schema());
+    assert_eq!(normalize_interval_batch(current.clone()).unwrap(), current);
+}
+
+#[test]
+fn test_concentration_metrics_from_totals() {
+    assert_eq!(ConcentrationMetrics::from_totals(&[0, 0]), None);
+
+    let single = ConcentrationMetrics::from_totals(&[42]).unwrap();
+    assert_eq!((single.hhi, single.top_1_share, single.effective_pools), (1.0, 1.0, 1.0));
+
+    let skewed = ConcentrationMetrics::from_totals(&[10, 60, 10, 10, 5, 5]).unwrap();
+    assert!((skewed.top_1_share - 0.6).abs() < 1e-12);
+    assert!((skewed.top_3_share - 0.8).abs() < 1e-12);
+    assert!((skewed.top_5_share - 0.95).abs() < 1e-12);
+    assert!((skewed.hhi - 0.395).abs() < 1e-12);
+}
+
+#[test]
+fn test_activity_runs_from_activity() {
+    assert_eq!(ActivityRuns::from_activity(&[]), None);
+    assert_eq!(ActivityRuns::from_activity(&[false, false]), None);
+
+    // Leading dry intervals predate deployment; the trailing spell counts
+    let series = [false, false, true, true, false, true, false, false, false, true, false, false];
+    let runs = ActivityRuns::from_activity(&series).unwrap();
+    assert_eq!(runs.first_active_interval, 2);
+    assert_eq!(runs.dry_spells, 3);
+    assert_eq!(runs.longest_dry_spell, 3);
+    assert_eq!(runs.mean_dry_spell, 2.0);
+    assert_eq!(runs.longest_active_streak, 2);
+
+    let always_active = ActivityRuns::from_activity(&[true; 5]).unwrap();
+    assert_eq!((always_active.dry_spells, always_active.mean_dry_spell), (0, 0.0));
+    assert_eq!(always_active.longest_active_streak, 5);
+}
+
+#[test]
+fn test_pearson_correlation_of_identical_and_independent_series() {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    let mut rng = StdRng::seed_from_u64(1087);
+
+    let mut identical = PearsonCorrelation::new();
+    let mut independent = PearsonCorrelation::new();
+    let mut offset = PearsonCorrelation::new();
+    for _ in 0..10_000 {
+        let x: f64 = rng.gen_range(0.0..1_000_000.0);
+        identical.add(x, x);
+        independent.add(x, rng.gen_range(0.0..1_000_000.0));
+        // A large shared offset must not swamp the co-moment
+        offset.add(1e12 + x, 1e12 - x);
+    }
+    assert!((identical.correlation().unwrap() - 1.0).abs() < 1e-9);
+    assert!(independent.correlation().unwrap().abs() < 0.05);
+    assert!((offset.correlation().unwrap() + 1.0).abs() < 1e-6);
+    assert_eq!(identical.count(), 10_000);
+
+    let mut constant = PearsonCorrelation::new();
+    constant.add(1.0, 5.0);
+    assert_eq!(constant.correlation(), None);
+    constant.add(2.0, 5.0);
+    assert_eq!(constant.correlation(), None);
+}
+
+/// Rolling series rows as (end_block, pool_address, window_intervals, total)
+type RollingRow = (u64, Option<String>, u64, i64);
+
+/// Brute-force rolling sums over per-interval dailies, starting each
+/// series at its first non-zero interval
+fn brute_force_rolling(timeline: &[u64], dailies: &[i64], pool: Option<&str>, window: usize) -> Vec<RollingRow> {
+    let Some(first) = dailies.iter().position(|&c| c != 0) else {
+        return Vec::new();
+    };
+    (first..timeline.len())
+        .map(|i| {
+            let from = first.max((i + 1).saturating_sub(window));
+            let total = dailies[from..=i].iter().sum();
+            (timeline[i], pool.map(str::to_string), (i + 1 - from) as u64, total)
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_rolling_series_matches_brute_force() {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    // Two full files and the partial final file
+    let files = [(19_425_392u64, 19_641_392u64), (19_641_392, 19_857_392), (19_857_392, 20_000_000)];
+    let pools = &POOL_ADDRESSES[..3];
+
+    for seed in 0..4u64 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let store = Arc::new(TestStore::new());
+        let writer = ParallelParquetWriter::new(store.clone());
+
+        let mut timeline = Vec::new();
+        let mut dailies = vec![Vec::new(); pools.len()];
+        let deployments: Vec<usize> = pools.iter().map(|_| rng.gen_range(0..60)).collect();
+
+        for &(start, end) in &files {
+            let intervals = (end - start).div_ceil(7200);
+            let mut rows = Vec::new();
+            for interval_id in 0..intervals {
+                timeline.push((start + (interval_id + 1) * 7200).min(end));
+                for (p, pool) in pools.iter().enumerate() {
+                    let deployed = timeline.len() > deployments[p];
+                    let cents = if deployed && rng.gen_bool(0.8) { rng.gen_range(1..10_000) } else { 0 };
+                    dailies[p].push(cents);
+                    rows.push(IntervalData {
+                        interval_id,
+                        pair_address: pool.to_string(),
+                        markout_time: MarkoutTime::Brontes,
+                        total_lvr_cents: Cents(cents),
+                        max_lvr_cents: cents as u64,
+                        non_zero_count: u64::from(cents > 0),
+                        total_count: 7200,
+                        start_block: start + interval_id * 7200,
+                        end_block: (start + (interval_id + 1) * 7200).min(end),
+                        source_counts: BTreeMap::new(),
+                    });
+                }
+            }
+            writer.write_interval_data(rows, start, end).await.unwrap();
+        }
+
+        for window in [1, 3, 7] {
+            PrecomputedWriter::new(store.clone()).write_rolling_series(window).await.unwrap();
+
+            let mut expected = Vec::new();
+            let aggregate: Vec<i64> = (0..timeline.len()).map(|i| dailies.iter().map(|d| d[i]).sum()).collect();
+            expected.extend(brute_force_rolling(&timeline, &aggregate, None, window));
+            let mut sorted: Vec<_> = pools.iter().map(|p| p.to_lowercase()).zip(&dailies).collect();
+            sorted.sort();
+            for (pool, pool_dailies) in sorted {
+                expected.extend(brute_force_rolling(&timeline, pool_dailies, Some(&pool), window));
+            }
+
+            let mut actual = Vec::new();
+            let path = format!("precomputed/time_series/rolling_{}.parquet", window);
+            for batch in read_parquet(store.as_ref(), &path).await {
+                let blocks = common::get_uint64_column(&batch, "end_block").unwrap();
+                let pools = common::get_string_column(&batch, "pool_address").unwrap();
+                let intervals = common::get_uint64_column(&batch, "window_intervals").unwrap();
+                let totals = common::get_int64_column(&batch, "rolling_total_cents").unwrap();
+                for i in 0..batch.num_rows() {
+                    let pool = (!arrow::array::Array::is_null(pools, i)).then(|| pools.value(i).to_string());
+                    actual.push((blocks.value(i), pool, intervals.value(i), totals.value(i)));
+                }
+            }
+
+            assert_eq!(actual, expected, "seed {} window {}", seed, window);
+            assert_eq!(actual.iter().rfind(|r| r.1.is_none()).unwrap().0, 20_000_000);
+        }
+    }
+}
+
+#[test]
+fn test_rolling_sums_shrink_before_deployment() {
+    let timeline = [10, 20, 30, 40];
+    let values = std::collections::BTreeMap::from([(10, 0), (30, 5), (40, 7)]);
+    assert_eq!(rolling_sums(&timeline, &values, 3), vec![(30, 1, 5), (40, 2, 12)]);
+    assert!(rolling_sums(&timeline, &Default::default(), 3).is_empty());
+}
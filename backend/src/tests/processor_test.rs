@@ -0,0 +1,2097 @@
+use super::support::{capture_logs, empty_realized_batch, read_parquet, realized_batch, theoretical_batch, TestStore};
+use crate::*;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+const CHUNK_START: u64 = 15_537_392;
+const CHUNK_BLOCKS: u64 = 216_000;
+
+/// One update per pool for the chunk starting at `chunk_start`, each with a
+/// few non-zero blocks
+fn chunk_updates(pools: &[String], chunk_start: u64) -> Vec<CheckpointUpdate> {
+    pools
+        .iter()
+        .enumerate()
+        .map(|(p, pool)| CheckpointUpdate {
+            pool_address: pool.parse().unwrap(),
+            markout_time: MarkoutTime::Zero,
+            data: (0..5u64)
+                .map(|i| UnifiedLVRData {
+                    pool_address: pool.parse().unwrap(),
+                    block_number: chunk_start + i * 1_000 + p as u64,
+                    lvr_cents: Cents(100 + i as i64 * 37 - p as i64 * 80),
+                    source: DataSource::Aurora,
+                })
+                .collect(),
+            chunk_start,
+            chunk_end: chunk_start + CHUNK_BLOCKS,
+        })
+        .collect()
+}
+
+/// Objects written under `prefix` since the `since`th put, named by their
+/// final path rather than the temporary one they were uploaded to
+fn written_since(store: &TestStore, since: usize, prefix: &str) -> Vec<String> {
+    store.puts()[since..]
+        .iter()
+        .filter(|p| p.starts_with(prefix))
+        .map(|p| p.split(TEMPORARY_OBJECT_MARKER).next().unwrap().to_string())
+        .collect()
+}
+
+fn checkpoint_puts(store: &TestStore, since: usize) -> Vec<String> {
+    written_since(store, since, "checkpoints/")
+}
+
+#[tokio::test]
+async fn test_only_changed_checkpoints_are_rewritten() {
+    let pools: Vec<String> = PoolRegistry::default()
+        .pools()
+        .iter()
+        .filter(|pool| pool.deployment_block == 0)
+        .take(3)
+        .map(|pool| pool.address.clone())
+        .collect();
+    // The first chunk touches every pool, the second only the first pool
+    let chunks = [
+        (CHUNK_START, pools.clone()),
+        (CHUNK_START + CHUNK_BLOCKS, pools[..1].to_vec()),
+    ];
+
+    let store = Arc::new(TestStore::new());
+    let processor = ParallelLVRProcessor::new(CHUNK_START, CHUNK_START + 2 * CHUNK_BLOCKS, store.clone()).await.unwrap();
+    let always_store = Arc::new(TestStore::new());
+    let always = ParallelLVRProcessor::new(CHUNK_START, CHUNK_START + 2 * CHUNK_BLOCKS, always_store.clone()).await.unwrap();
+
+    let mut written = Vec::new();
+    for (chunk_start, chunk_pools) in &chunks {
+        let before = store.puts().len();
+        processor.atomic_checkpoint_update(chunk_updates(chunk_pools, *chunk_start)).await.unwrap();
+        written.push(checkpoint_puts(&store, before));
+
+        always.atomic_checkpoint_update(chunk_updates(chunk_pools, *chunk_start)).await.unwrap();
+        always.write_checkpoints(true).await.unwrap();
+    }
+    // Each chunk also changes the aggregate checkpoint
+    assert_eq!(written[0].len(), 4);
+    assert_eq!(written[1].len(), 2);
+    assert!(written[1].iter().any(|path| path.contains(&pools[0])), "{:?}", written[1]);
+    assert!(written[1].iter().any(|path| path.contains(AGGREGATE_POOL_ADDRESS)), "{:?}", written[1]);
+
+    // Nothing changed since the last write
+    let before = store.puts().len();
+    processor.write_checkpoints(false).await.unwrap();
+    assert_eq!(store.puts().len(), before);
+
+    // The final forced flush writes every checkpoint and leaves the same
+    // state as writing all of them after every chunk
+    processor.write_checkpoints(true).await.unwrap();
+    always.write_checkpoints(true).await.unwrap();
+    assert_eq!(checkpoint_puts(&store, before).len(), 4);
+
+    let paths = store.paths().await;
+    assert_eq!(paths, always_store.paths().await);
+    for path in paths.iter().filter(|p| p.starts_with("checkpoints/")) {
+        assert_eq!(read_parquet(store.as_ref(), path).await, read_parquet(always_store.as_ref(), path).await, "{}", path);
+    }
+}
+
+#[tokio::test]
+async fn test_configured_scale_reaches_new_checkpoint_digests() {
+    let pools: Vec<String> = PoolRegistry::default().pools().iter().take(2).map(|pool| pool.address.clone()).collect();
+    let store = Arc::new(TestStore::new());
+    let digest_config = TDigestConfig::default().with_scale(ScaleFn::K2);
+    let processor = ParallelLVRProcessor::new(CHUNK_START, CHUNK_START + CHUNK_BLOCKS, store.clone()).await.unwrap()
+        .with_digest_config(digest_config);
+    processor.atomic_checkpoint_update(chunk_updates(&pools, CHUNK_START)).await.unwrap();
+
+    let paths = checkpoint_puts(&store, 0);
+    assert_eq!(paths.len(), 3, "{:?}", paths);
+    for path in paths {
+        let snapshot = CheckpointSnapshot::from_record_batch(&read_parquet(store.as_ref(), &path).await[0]).unwrap().remove(0);
+        assert_eq!(snapshot.digest.scale, ScaleFn::K2, "{}", path);
+        assert_eq!(Checkpoint::from_snapshot(&snapshot).digest.lock().unwrap().scale, ScaleFn::K2, "Survives a resume");
+    }
+}
+
+#[tokio::test]
+async fn test_checkpoint_snapshots_round_trip_losslessly() {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    let mut rng = StdRng::seed_from_u64(1147);
+    let markouts = [MarkoutTime::Negative2, MarkoutTime::Zero, MarkoutTime::Positive15, MarkoutTime::Brontes];
+    for case in 0..32 {
+        let digest_config = if case % 2 == 0 { TDigestConfig::default() } else { TDigestConfig::default().with_scale(ScaleFn::K2) };
+        let checkpoint = Checkpoint::with_config(format!("0x{:040x}", case), markouts[case % markouts.len()], &digest_config);
+        checkpoint.running_total.store(rng.gen_range(-1_000_000..1_000_000), std::sync::atomic::Ordering::Release);
+        for bucket in [
+            &checkpoint.total_bucket_0, &checkpoint.total_bucket_0_10, &checkpoint.total_bucket_10_100,
+            &checkpoint.total_bucket_100_500, &checkpoint.total_bucket_500_1000, &checkpoint.total_bucket_1000_10000,
+            &checkpoint.total_bucket_10000_plus, &checkpoint.total_bucket_negative, &checkpoint.last_updated_block,
+        ] {
+            bucket.store(rng.gen_range(0..100_000), std::sync::atomic::Ordering::Release);
+        }
+        // Empty digests, partly buffered ones and ones with rejected values
+        for _ in 0..rng.gen_range(0..3_000) {
+            let value = if rng.gen_bool(0.01) { f64::NAN } else { rng.gen_range(0.01..50_000.0) };
+            checkpoint.update_digest(value).unwrap();
+            checkpoint.update_max_lvr(rng.gen_range(15_000_000..16_000_000), rng.gen_range(1..5_000_000));
+        }
+
+        let snapshot = checkpoint.to_snapshot();
+        let restored = Checkpoint::from_snapshot(&snapshot);
+        assert!(!restored.dirty.load(std::sync::atomic::Ordering::Acquire));
+        let expected = serde_json::to_value(&snapshot).unwrap();
+        assert_eq!(serde_json::to_value(CheckpointSnapshot::from(&restored)).unwrap(), expected, "case {}", case);
+
+        // And through the stored file a resume reads
+        let store = Arc::new(TestStore::new());
+        ParallelParquetWriter::new(store.clone()).write_checkpoints(vec![snapshot]).await.unwrap();
+        let path = checkpoint_puts(&store, 0).remove(0);
+        let stored = CheckpointSnapshot::from_record_batch(&read_parquet(store.as_ref(), &path).await[0]).unwrap().remove(0);
+        assert_eq!(serde_json::to_value(Checkpoint::from(&stored).to_snapshot()).unwrap(), expected, "case {} via {}", case, path);
+    }
+}
+
+/// Interval metrics computed block by block over zero-filled data
+fn brute_force_intervals(pool: &str, chunk_start: u64, chunk_end: u64, deployment: u64, values: &std::collections::HashMap<u64, i64>) -> Vec<IntervalData> {
+    let mut intervals: std::collections::BTreeMap<u64, IntervalData> = Default::default();
+    for block in chunk_start.max(deployment)..chunk_end {
+        let interval_id = (block - chunk_start) / 7200;
+        let interval = intervals.entry(interval_id).or_insert_with(|| IntervalData {
+            interval_id,
+            pair_address: pool.to_string(),
+            markout_time: MarkoutTime::Brontes,
+            total_lvr_cents: Cents(0),
+            max_lvr_cents: 0,
+            non_zero_count: 0,
+            total_count: 0,
+            start_block: block,
+            end_block: block,
+            source_counts: BTreeMap::new(),
+        });
+        interval.total_count += 1;
+        interval.end_block = block + 1;
+        if values.contains_key(&block) {
+            *interval.source_counts.entry(DataSource::Brontes).or_insert(0) += 1;
+        }
+        let value = values.get(&block).copied().unwrap_or(0);
+        if value != 0 {
+            interval.total_lvr_cents += Cents(value);
+            interval.max_lvr_cents = interval.max_lvr_cents.max(value.max(0) as u64);
+            interval.non_zero_count += 1;
+        }
+    }
+    intervals.into_values().collect()
+}
+
+#[tokio::test]
+async fn test_sparse_events_match_zero_filled_blocks() {
+    use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+    let registry = PoolRegistry::default();
+    // Deployed a third of the way into the chunk
+    let pool = registry.pools().iter().find(|pool| pool.deployment_block > 0).unwrap();
+    let chunk_start = pool.deployment_block - 70_000;
+    let chunk_end = chunk_start + CHUNK_BLOCKS;
+
+    let mut rng = StdRng::seed_from_u64(7);
+    let mut sparse: Vec<UnifiedLVRData> = (0..2_000)
+        .map(|_| UnifiedLVRData {
+            pool_address: pool.address.parse().unwrap(),
+            block_number: rng.gen_range(chunk_start..chunk_end),
+            lvr_cents: Cents(rng.gen_range(-500..20_000) * i64::from(rng.gen_bool(0.9))),
+            source: DataSource::Brontes,
+        })
+        .collect();
+    sparse.shuffle(&mut rng);
+    // Later values for a block replace earlier ones
+    let values: std::collections::HashMap<u64, i64> = sparse.iter().map(|d| (d.block_number, d.lvr_cents.as_i64())).collect();
+    let zero_filled: Vec<UnifiedLVRData> = (chunk_start..chunk_end)
+        .map(|block_number| UnifiedLVRData {
+            pool_address: pool.address.parse().unwrap(),
+            block_number,
+            lvr_cents: Cents(values.get(&block_number).copied().unwrap_or(0)),
+            source: DataSource::Brontes,
+        })
+        .collect();
+
+    let store = Arc::new(TestStore::new());
+    let processor = ParallelLVRProcessor::new(chunk_start, chunk_end, store.clone()).await.unwrap();
+    let filled_store = Arc::new(TestStore::new());
+    let filled = ParallelLVRProcessor::new(chunk_start, chunk_end, filled_store.clone()).await.unwrap();
+
+    let intervals = processor
+        .calculate_interval_metrics(chunk_start, chunk_end, &pool.address.parse().unwrap(), MarkoutTime::Brontes, &sparse)
+        .unwrap();
+    let expected = brute_force_intervals(&pool.address, chunk_start, chunk_end, pool.deployment_block, &values);
+    assert_eq!(intervals.len(), expected.len());
+    for (actual, expected) in intervals.iter().zip(&expected) {
+        assert_eq!(
+            (actual.interval_id, actual.total_lvr_cents, actual.max_lvr_cents, actual.non_zero_count, actual.total_count),
+            (expected.interval_id, expected.total_lvr_cents, expected.max_lvr_cents, expected.non_zero_count, expected.total_count),
+        );
+        assert_eq!(
+            (actual.start_block, actual.end_block),
+            (expected.start_block, expected.end_block),
+        );
+        assert_eq!(actual.source_counts, expected.source_counts);
+    }
+
+    let update = |data: Vec<UnifiedLVRData>| vec![CheckpointUpdate {
+        pool_address: pool.address.parse().unwrap(),
+        markout_time: MarkoutTime::Brontes,
+        data,
+        chunk_start,
+        chunk_end,
+    }];
+    let totals = processor.atomic_checkpoint_update(update(sparse)).await.unwrap();
+    assert_eq!(totals, filled.atomic_checkpoint_update(update(zero_filled)).await.unwrap());
+    processor.write_checkpoints(true).await.unwrap();
+    filled.write_checkpoints(true).await.unwrap();
+
+    let paths = store.paths().await;
+    assert_eq!(paths, filled_store.paths().await);
+    for path in paths.iter().filter(|p| p.starts_with("checkpoints/")) {
+        assert_eq!(read_parquet(store.as_ref(), path).await, read_parquet(filled_store.as_ref(), path).await, "{}", path);
+    }
+
+    // The checkpoint records where its counts start
+    let path = checkpoint_path(&pool.address, MarkoutTime::Brontes).to_string();
+    let snapshot = read_checkpoint_snapshot(&read_parquet(store.as_ref(), &path).await[0]).unwrap();
+    assert_eq!(snapshot.deployment_block, pool.deployment_block);
+    assert_eq!(snapshot.total_blocks(), chunk_end - pool.deployment_block);
+    assert_eq!(snapshot.blocks_since_deployment(snapshot.deployment_block), snapshot.total_blocks());
+}
+
+fn parquet_bytes(batch: &arrow::record_batch::RecordBatch) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    let mut writer = parquet::arrow::ArrowWriter::try_new(&mut buffer, batch.schema(), None).unwrap();
+    writer.write(batch).unwrap();
+    writer.close().unwrap();
+    buffer
+}
+
+#[test]
+fn test_lvr_details_are_parsed_once_into_every_pool_value() {
+    let registry = PoolRegistry::default();
+    let (first, second) = (&registry.pools()[0], &registry.pools()[1]);
+    let details = serde_json::to_string(&vec![
+        vec![first.name.clone(), r#"{"dollarValue": 12.5, "gasValue": 3}"#.to_string()],
+        vec![second.name.clone(), "3.25".to_string()],
+        vec!["UNKNOWN/POOL".to_string(), "1.0".to_string()],
+        vec!["UNPARSEABLE/POOL".to_string(), "n/a".to_string()],
+        vec!["TOO/LONG".to_string(), "1.0".to_string(), "2.0".to_string()],
+        // Only the first usable entry of a pool counts
+        vec![first.name.clone(), "99.0".to_string()],
+    ]).unwrap();
+
+    let parsed = parse_lvr_details(&details);
+    let values = &parsed.values;
+    assert_eq!(values.len(), 3);
+    assert_eq!((values[&first.name], values[&second.name], values["UNKNOWN/POOL"]), (12.5, 3.25, 1.0));
+    assert_eq!(parsed.malformed_entries, 2);
+    assert!(parse_lvr_details("{\"not\": \"an array\"}").values.is_empty());
+
+    let rows = parsed.pool_values(CHUNK_START + 1, &registry);
+    let cents: Vec<(&str, i64)> = rows.iter().map(|row| (row.pool_address.as_str(), row.lvr_cents.as_i64())).collect();
+    assert_eq!(cents, vec![(first.address.as_str(), 1_250), (second.address.as_str(), 325)]);
+    assert!(rows.iter().all(|row| row.block_number == CHUNK_START + 1 && row.source == DataSource::Aurora));
+}
+
+#[test]
+fn test_lvr_detail_payloads_parse_into_their_shapes() {
+    let parse = |value: &str| serde_json::from_str::<LvrDetailValue>(value).ok();
+    assert_eq!(
+        parse(r#"{"dollarValue": 1834.217, "gasValue": 0.0041, "volume": "52.3"}"#),
+        Some(LvrDetailValue::Object { dollar_value: 1834.217 })
+    );
+    assert_eq!(parse("0.0"), Some(LvrDetailValue::Bare(0.0)));
+    assert_eq!(parse("-12.75").map(LvrDetailValue::dollars), Some(-12.75));
+    for malformed in [r#"{"gasValue": 3}"#, r#"{"dollarValue": null}"#, r#"{"dollarValue": "12"}"#, "n/a", ""] {
+        assert_eq!(parse(malformed), None, "{}", malformed);
+    }
+
+    // Pool names are the registry's, values JSON inside JSON strings
+    let row = r#"[["USDC-WETH-5bps","{\"dollarValue\":412.09,\"gasValue\":0.002}"],["USDC-USDT-1bps","0.37"],["WETH-USDT-5bps","{\"gasValue\":1}"],["WBTC-WETH-30bps"],["DAI-USDC-1bps",2.5]]"#;
+    let parsed = parse_lvr_details(row);
+    assert_eq!(parsed.values, std::collections::HashMap::from([("USDC-WETH-5bps".to_string(), 412.09), ("USDC-USDT-1bps".to_string(), 0.37)]));
+    assert_eq!((parsed.malformed_entries, parsed.malformed_row, parsed.is_malformed()), (3, false, true));
+
+    for row in ["", "null", r#"{"USDC-WETH-5bps": 1.0}"#, "[[\"USDC-WETH-5bps\", \"1.0\"]"] {
+        let parsed = parse_lvr_details(row);
+        assert!(parsed.malformed_row && parsed.values.is_empty(), "{}", row);
+    }
+    assert!(!parse_lvr_details("[]").is_malformed());
+}
+
+/// Aurora details rows of every markout, parsed as `DatabaseSource` parses them
+struct DetailsSource {
+    rows: Vec<aurora::LVRDetails>,
+}
+
+#[async_trait::async_trait]
+impl LvrSource for DetailsSource {
+    async fn fetch_theoretical(&self, _: &PoolRegistry, _: MarkoutTime, _: u64, _: u64) -> anyhow::Result<Vec<UnifiedLVRData>> {
+        unreachable!("the processor streams")
+    }
+
+    async fn stream_theoretical<'a>(
+        &'a self,
+        pools: &'a PoolRegistry,
+        _: MarkoutTime,
+        chunk_start: u64,
+        chunk_end: u64,
+    ) -> anyhow::Result<TheoreticalStream<'a>> {
+        use futures::StreamExt;
+
+        let rows = self.rows.iter()
+            .filter(move |row| (chunk_start..chunk_end).contains(&row.block_number))
+            .map(|row| Ok(row.clone()));
+        Ok(theoretical_from_details(futures::stream::iter(rows).boxed(), pools))
+    }
+
+    async fn fetch_realized(&self, _: &PoolRegistry, _: u64, _: u64) -> anyhow::Result<Vec<UnifiedLVRData>> {
+        Ok(Vec::new())
+    }
+}
+
+#[tokio::test]
+async fn test_malformed_details_are_counted_in_chunk_summaries() {
+    let registry = PoolRegistry::default();
+    let pool = &registry.pools()[0];
+    let details = |block_number: u64, details: String| aurora::LVRDetails { block_number, details, index: 0 };
+    let rows = vec![
+        details(CHUNK_START + 1, format!(r#"[["{}","{{\"dollarValue\":1.5}}"]]"#, pool.name)),
+        details(CHUNK_START + 2, format!(r#"[["{}","2.25"],["{}","n/a"]]"#, pool.name, registry.pools()[1].name)),
+        details(CHUNK_START + 3, "not json".to_string()),
+    ];
+
+    let store = Arc::new(TestStore::new());
+    let processor = ParallelLVRProcessor::new(CHUNK_START, CHUNK_START + CHUNK_BLOCKS, store.clone()).await.unwrap()
+        .with_source(Arc::new(DetailsSource { rows }));
+    processor.process_blocks(None).await.unwrap();
+
+    let summary = read_chunk_summaries(store.as_ref()).await.unwrap().remove(0);
+    let markouts = MARKOUT_TIMES.len() as u64;
+    assert_eq!((summary.aurora_rows, summary.malformed_aurora_rows), (2 * markouts, 2 * markouts));
+    let total: i64 = summary.markouts.iter().filter(|m| m.markout_time != "brontes").map(|m| m.total_lvr_cents.as_i64()).sum();
+    assert_eq!(total, 375 * markouts as i64);
+    assert!(format_chunk_summaries(&[summary]).lines().next().unwrap().contains("malformed"));
+}
+
+/// A parquet source over an in-memory dump of the two batches
+async fn parquet_source(theoretical: &arrow::record_batch::RecordBatch, realized: &arrow::record_batch::RecordBatch) -> Arc<ParquetSource> {
+    use object_store::{path::Path, ObjectStore};
+
+    let dump = Arc::new(TestStore::new());
+    dump.put(&Path::from(THEORETICAL_DUMP_PATH), parquet_bytes(theoretical).into()).await.unwrap();
+    dump.put(&Path::from(REALIZED_DUMP_PATH), parquet_bytes(realized).into()).await.unwrap();
+    Arc::new(ParquetSource::open(dump).await.unwrap())
+}
+
+#[tokio::test]
+async fn test_process_blocks_from_parquet_source() {
+    use arrow::array::{Int64Array, StringArray};
+    use axum::extract::State;
+    use std::collections::HashMap;
+
+    let registry = PoolRegistry::default();
+    let theoretical_pools: Vec<&str> = registry.pools().iter()
+        .filter(|pool| pool.deployment_block == 0)
+        .take(2)
+        .map(|pool| pool.address.as_str())
+        .collect();
+    let realized_pools: Vec<String> = registry.brontes_addresses().take(2).map(ToString::to_string).collect();
+    let unknown_pool = "0x0000000000000000000000000000000000000001";
+    // A second chunk cut short by the end block
+    let end_block = CHUNK_START + CHUNK_BLOCKS + 50_000;
+
+    let mut expected: HashMap<(String, String), i64> = HashMap::new();
+    let (mut pools, mut blocks, mut markouts, mut values) = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    for (p, pool) in theoretical_pools.iter().chain([&unknown_pool]).enumerate() {
+        for (m, &markout) in MARKOUT_TIMES.iter().enumerate() {
+            for i in 0..12u64 {
+                let block = CHUNK_START + i * 22_000 + p as u64 * 7 + m as u64;
+                let value = 250 + (i as i64 * 61 + m as i64 * 13) % 900 - 300;
+                pools.push(pool.to_string());
+                blocks.push(block);
+                markouts.push(markout);
+                values.push(value);
+                if *pool != unknown_pool {
+                    let markout_time = MarkoutTime::from_f64(markout).unwrap().to_string();
+                    *expected.entry((pool.to_lowercase(), markout_time)).or_default() += value;
+                }
+            }
+        }
+    }
+    let theoretical = theoretical_batch(itertools::izip!(pools, blocks, markouts, values));
+
+    let (mut pools, mut blocks, mut values) = (Vec::new(), Vec::new(), Vec::new());
+    for (p, pool) in realized_pools.iter().map(String::as_str).chain([unknown_pool]).enumerate() {
+        for i in 0..9u64 {
+            let block = CHUNK_START + i * 29_000 + p as u64;
+            let value = 1_000 - i as i64 * 170;
+            // Brontes addresses may come back in any casing
+            pools.push(pool.to_uppercase().replacen("0X", "0x", 1));
+            blocks.push(block);
+            values.push(value);
+            if pool != unknown_pool {
+                *expected.entry((pool.to_string(), MarkoutTime::Brontes.to_string())).or_default() += value;
+            }
+        }
+    }
+    let realized = realized_batch(itertools::izip!(pools, blocks, values));
+
+    let source = parquet_source(&theoretical, &realized).await;
+
+    let store = Arc::new(TestStore::new());
+    let processor = ParallelLVRProcessor::new(CHUNK_START, end_block, store.clone()).await.unwrap()
+        .with_source(source);
+    processor.process_blocks(None).await.unwrap();
+
+    let mut totals: HashMap<(String, String), i64> = HashMap::new();
+    for path in store.paths().await.iter().filter(|p| p.starts_with("checkpoints/")) {
+        for batch in read_parquet(store.as_ref(), path).await {
+            let column = |name| batch.column_by_name(name).unwrap().clone();
+            let pool = column("pair_address").as_any().downcast_ref::<StringArray>().unwrap().value(0).to_lowercase();
+            let markout_time = column("markout_time").as_any().downcast_ref::<StringArray>().unwrap().value(0).to_string();
+            let total = column("running_total").as_any().downcast_ref::<Int64Array>().unwrap().value(0);
+            totals.insert((pool, markout_time), total);
+        }
+    }
+    for (key, total) in &expected {
+        assert_eq!(totals.get(key), Some(total), "{:?}", key);
+    }
+    assert!(totals.keys().all(|(pool, _)| pool != unknown_pool));
+
+    // The aggregate checkpoint of each markout sums every pool's
+    let mut pools_by_markout: HashMap<String, i64> = HashMap::new();
+    for ((_, markout_time), total) in &expected {
+        *pools_by_markout.entry(markout_time.clone()).or_default() += total;
+    }
+    for (markout_time, total) in &pools_by_markout {
+        assert_eq!(totals.get(&(AGGREGATE_POOL_ADDRESS.to_string(), markout_time.clone())), Some(total), "{}", markout_time);
+    }
+
+    let report = Validator::new(store.clone()).validate_all().await.unwrap();
+    assert!(!report.pools.is_empty());
+    assert!(report.pools.contains_key(&format!("{}_brontes", AGGREGATE_POOL_ADDRESS)));
+    for (key, stats) in &report.pools {
+        assert_eq!(stats.difference, 0, "{}", key);
+        assert!(stats.non_zero_counts_consistent, "{}", key);
+        assert!(stats.max_lvr_consistent, "{:?}", stats);
+        assert!(stats.block_counts_match(), "{}: {:?}", key, stats);
+    }
+    assert!(store.paths().await.contains(&DatasetKind::PoolTotals.path().to_string()));
+
+    let state = Arc::new(AppState::new(store.clone()));
+    let quartiles = get_quartile_plot(
+        State(state),
+        ApiQuery(QuartilePlotQuery { pool_address: None, markout_time: None, aggregate: Some(true) }),
+    ).await.unwrap().0;
+    assert_eq!(quartiles.pool_address, AGGREGATE_POOL_ADDRESS);
+    assert!(quartiles.median_cents > 0);
+    assert!(quartiles.percentile_25_cents <= quartiles.median_cents && quartiles.median_cents <= quartiles.percentile_75_cents);
+}
+
+#[tokio::test]
+async fn test_process_one_chunk_offline_from_the_fixture_dump() {
+    use arrow::array::{Int64Array, StringArray};
+    use std::collections::HashMap;
+
+    let spec = format!("parquet:{}/testdata/offline", env!("CARGO_MANIFEST_DIR"));
+    let source = SourceSpec::parse(&spec).unwrap()
+        .open(&AppConfig::default(), None)
+        .await
+        .unwrap();
+    let (chunk_start, chunk_end) = (CHUNK_START, CHUNK_START + CHUNK_BLOCKS);
+
+    let registry = PoolRegistry::default();
+    let mut expected: HashMap<(String, String), i64> = HashMap::new();
+    let mut fetched = Vec::new();
+    for &markout in MARKOUT_TIMES.iter() {
+        let markout_time = MarkoutTime::from_f64(markout).unwrap();
+        let rows = source.fetch_theoretical(&registry, markout_time, chunk_start, chunk_end).await.unwrap();
+        fetched.extend(rows.into_iter().map(|row| (row, markout_time)));
+    }
+    let realized = source.fetch_realized(&registry, chunk_start, chunk_end).await.unwrap();
+    fetched.extend(realized.into_iter().map(|row| (row, MarkoutTime::Brontes)));
+    assert!(fetched.len() > 80, "{}", fetched.len());
+    for (row, markout_time) in fetched {
+        if let Some(pool) = registry.get(row.pool_address.as_str()) {
+            *expected.entry((pool.address.to_lowercase(), markout_time.to_string())).or_default() += row.lvr_cents.as_i64();
+        }
+    }
+    assert_eq!(expected.len(), 2 * (MARKOUT_TIMES.len() + 1));
+
+    let store = Arc::new(TestStore::new());
+    let processor = ParallelLVRProcessor::new(chunk_start, chunk_end, store.clone()).await.unwrap()
+        .with_source(source);
+    processor.process_blocks(None).await.unwrap();
+
+    let mut totals: HashMap<(String, String), i64> = HashMap::new();
+    for path in store.paths().await.iter().filter(|p| p.starts_with("checkpoints/")) {
+        for batch in read_parquet(store.as_ref(), path).await {
+            let column = |name| batch.column_by_name(name).unwrap().clone();
+            let pool = column("pair_address").as_any().downcast_ref::<StringArray>().unwrap().value(0).to_lowercase();
+            let markout_time = column("markout_time").as_any().downcast_ref::<StringArray>().unwrap().value(0).to_string();
+            let total = column("running_total").as_any().downcast_ref::<Int64Array>().unwrap().value(0);
+            if total != 0 {
+                totals.insert((pool, markout_time), total);
+            }
+        }
+    }
+    for (key, total) in &expected {
+        assert_eq!(totals.get(key), Some(total), "{:?}", key);
+    }
+
+    let report = Validator::new(store.clone())
+        .with_policy(ValidationPolicy::default().during_processing())
+        .validate_all()
+        .await
+        .unwrap();
+    assert_eq!(report.failures().count(), 0, "{:?}", report.failures().collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn test_interval_files_count_blocks_by_source() {
+    use std::collections::{BTreeSet, HashMap};
+
+    let spec = format!("parquet:{}/testdata/offline", env!("CARGO_MANIFEST_DIR"));
+    let source = SourceSpec::parse(&spec).unwrap()
+        .open(&AppConfig::default(), None)
+        .await
+        .unwrap();
+    let (chunk_start, chunk_end) = (CHUNK_START, CHUNK_START + CHUNK_BLOCKS);
+
+    // Distinct blocks per (pool, markout), by source
+    let registry = PoolRegistry::default();
+    let mut blocks: HashMap<(String, String, DataSource), BTreeSet<u64>> = HashMap::new();
+    let mut fetched = Vec::new();
+    for &markout in MARKOUT_TIMES.iter() {
+        let markout_time = MarkoutTime::from_f64(markout).unwrap();
+        let rows = source.fetch_theoretical(&registry, markout_time, chunk_start, chunk_end).await.unwrap();
+        fetched.extend(rows.into_iter().map(|row| (row, markout_time)));
+    }
+    let realized = source.fetch_realized(&registry, chunk_start, chunk_end).await.unwrap();
+    fetched.extend(realized.into_iter().map(|row| (row, MarkoutTime::Brontes)));
+    for (row, markout_time) in fetched {
+        if let Some(pool) = registry.get(row.pool_address.as_str()) {
+            if row.block_number >= pool.deployment_block {
+                blocks.entry((pool.address.to_lowercase(), markout_time.to_string(), row.source.clone()))
+                    .or_default()
+                    .insert(row.block_number);
+            }
+        }
+    }
+    let expected: HashMap<_, u64> = blocks.into_iter().map(|(key, blocks)| (key, blocks.len() as u64)).collect();
+    assert!(expected.keys().any(|(_, _, source)| *source == DataSource::Aurora));
+    assert!(expected.keys().any(|(_, _, source)| *source == DataSource::Brontes));
+
+    let store = Arc::new(TestStore::new());
+    let processor = ParallelLVRProcessor::new(chunk_start, chunk_end, store.clone()).await.unwrap()
+        .with_source(source);
+    processor.process_blocks(None).await.unwrap();
+
+    let mut counts: HashMap<(String, String, DataSource), u64> = HashMap::new();
+    for batch in read_parquet(store.as_ref(), interval_path(chunk_start, chunk_end).as_ref()).await {
+        for interval in IntervalData::from_record_batch(&batch).unwrap() {
+            assert!(interval.source_counts.values().sum::<u64>() <= interval.total_count);
+            for (source, count) in interval.source_counts {
+                let key = (interval.pair_address.to_lowercase(), interval.markout_time.to_string(), source);
+                *counts.entry(key).or_default() += count;
+            }
+        }
+    }
+    assert_eq!(counts, expected);
+
+    let summary = read_chunk_summaries(store.as_ref()).await.unwrap().remove(0);
+    let mut totals = BTreeMap::new();
+    for ((_, _, source), count) in &expected {
+        *totals.entry(source.clone()).or_insert(0) += count;
+    }
+    assert_eq!(summary.source_counts, totals);
+    assert!(format_chunk_summaries(&[summary]).contains("sources: aurora="));
+}
+
+#[tokio::test]
+async fn test_hooks_run_once_per_chunk() {
+    use futures::FutureExt;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let pool = PoolRegistry::default().brontes_addresses().next().unwrap().to_string();
+    let theoretical = theoretical_batch([(pool.clone(), CHUNK_START, 0.0, 500)]);
+    let realized = realized_batch([(pool.clone(), CHUNK_START + CHUNK_BLOCKS, 700)]);
+
+    let validations = Arc::new(AtomicUsize::new(0));
+    let validation_callback: ValidationCallback = {
+        let validations = Arc::clone(&validations);
+        Arc::new(move |_store| {
+            validations.fetch_add(1, Ordering::SeqCst);
+            async { Ok(()) }.boxed()
+        })
+    };
+    // Each call records its chunk's start block and interval count
+    let pre_writes = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let pre_write_hook: PreWriteHook = {
+        let pre_writes = Arc::clone(&pre_writes);
+        Arc::new(move |intervals, chunk_start, _chunk_end| {
+            pre_writes.lock().unwrap().push((chunk_start, intervals.len()));
+            async { Ok(()) }.boxed()
+        })
+    };
+
+    let store = Arc::new(TestStore::new());
+    let processor = ParallelLVRProcessor::new(CHUNK_START, CHUNK_START + CHUNK_BLOCKS + 7_200, store.clone()).await.unwrap()
+        .with_source(parquet_source(&theoretical, &realized).await)
+        .with_pre_write_hook(pre_write_hook);
+    processor.process_blocks(Some(validation_callback)).await.unwrap();
+
+    assert_eq!(validations.load(Ordering::SeqCst), 2);
+    let pre_writes = pre_writes.lock().unwrap().clone();
+    assert_eq!(pre_writes.iter().map(|&(start, _)| start).collect::<Vec<_>>(), vec![CHUNK_START, CHUNK_START + CHUNK_BLOCKS]);
+    // Zero-LVR Brontes intervals are written too, so no chunk is empty
+    assert!(pre_writes.iter().all(|&(_, intervals)| intervals > 0));
+
+    // A failing pre-write check stops before the chunk's intervals are written
+    let store = Arc::new(TestStore::new());
+    let processor = ParallelLVRProcessor::new(CHUNK_START, CHUNK_START + CHUNK_BLOCKS, store.clone()).await.unwrap()
+        .with_source(parquet_source(&theoretical, &realized).await)
+        .with_pre_write_hook(Arc::new(|_, _, _| async { Err(anyhow::anyhow!("rejected")) }.boxed()));
+    assert!(processor.process_blocks(None).await.is_err());
+    assert!(store.paths().await.iter().all(|path| !path.starts_with("intervals/")));
+}
+
+/// A source whose every fetch fails, for runs that must not fetch
+struct UnreachableSource;
+
+#[async_trait::async_trait]
+impl LvrSource for UnreachableSource {
+    async fn fetch_theoretical(&self, _: &PoolRegistry, _: MarkoutTime, chunk_start: u64, _: u64) -> anyhow::Result<Vec<UnifiedLVRData>> {
+        Err(anyhow::anyhow!("unexpected theoretical fetch for chunk {}", chunk_start))
+    }
+
+    async fn fetch_realized(&self, _: &PoolRegistry, chunk_start: u64, _: u64) -> anyhow::Result<Vec<UnifiedLVRData>> {
+        Err(anyhow::anyhow!("unexpected realized fetch for chunk {}", chunk_start))
+    }
+}
+
+async fn store_contents(store: &TestStore) -> Vec<(String, bytes::Bytes)> {
+    use object_store::{path::Path, ObjectStore};
+
+    let mut contents = Vec::new();
+    for path in store.paths().await {
+        let bytes = store.get(&Path::from(path.as_str())).await.unwrap().bytes().await.unwrap();
+        contents.push((path, bytes));
+    }
+    contents
+}
+
+#[tokio::test]
+async fn test_rerunning_processed_range_is_a_no_op() {
+    let pool = PoolRegistry::default().brontes_addresses().next().unwrap().to_string();
+    let theoretical = theoretical_batch([(pool.clone(), CHUNK_START + 3, 0.5, 1_200)]);
+    let realized = realized_batch([(pool.clone(), CHUNK_START + 9, -40)]);
+    let end_block = CHUNK_START + CHUNK_BLOCKS + 7_200;
+
+    let store = Arc::new(TestStore::new());
+    ParallelLVRProcessor::new(CHUNK_START, end_block, store.clone()).await.unwrap()
+        .with_source(parquet_source(&theoretical, &realized).await)
+        .process_blocks(None).await.unwrap();
+    let contents = store_contents(&store).await;
+    let puts = store.puts().len();
+
+    ParallelLVRProcessor::new(CHUNK_START, end_block, store.clone()).await.unwrap()
+        .with_source(Arc::new(UnreachableSource))
+        .process_blocks(None).await.unwrap();
+    assert_eq!(store.puts().len(), puts);
+    assert_eq!(store_contents(&store).await, contents);
+
+    // Overwriting fetches and rewrites every chunk's intervals again
+    let first_interval = interval_path(CHUNK_START, CHUNK_START + CHUNK_BLOCKS).to_string();
+    assert!(ParallelLVRProcessor::new(CHUNK_START, end_block, store.clone()).await.unwrap()
+        .with_source(Arc::new(UnreachableSource))
+        .with_overwrite(true)
+        .process_blocks(None).await.is_err());
+    assert!(!store.paths().await.contains(&first_interval));
+
+    ParallelLVRProcessor::new(CHUNK_START, end_block, store.clone()).await.unwrap()
+        .with_source(parquet_source(&theoretical, &realized).await)
+        .with_overwrite(true)
+        .process_blocks(None).await.unwrap();
+    let rewritten = written_since(&store, puts, "intervals/");
+    assert_eq!(rewritten.len(), 2);
+    assert!(rewritten.contains(&first_interval));
+}
+
+#[tokio::test]
+async fn test_extending_a_processed_range_requires_resume() {
+    let pool = PoolRegistry::default().brontes_addresses().next().unwrap().to_string();
+    let theoretical = theoretical_batch([(pool.clone(), CHUNK_START + 3, 0.5, 1_200)]);
+    let realized = empty_realized_batch();
+    let running_totals = |store: Arc<TestStore>| async move {
+        let mut totals = BTreeMap::new();
+        for path in store.paths().await.iter().filter(|p| p.starts_with("checkpoints/")) {
+            let snapshot = CheckpointSnapshot::from_record_batch(&read_parquet(store.as_ref(), path).await[0]).unwrap().remove(0);
+            totals.insert(path.clone(), snapshot.running_total);
+        }
+        totals
+    };
+
+    let store = Arc::new(TestStore::new());
+    ParallelLVRProcessor::new(CHUNK_START, CHUNK_START + CHUNK_BLOCKS, store.clone()).await.unwrap()
+        .with_source(parquet_source(&theoretical, &realized).await)
+        .process_blocks(None).await.unwrap();
+    let totals = running_totals(store.clone()).await;
+    assert_eq!(totals[&checkpoint_path(&pool, MarkoutTime::Positive05).to_string()], Cents(1_200));
+    let contents = store_contents(&store).await;
+
+    // The second chunk's checkpoints alone would replace the stored ones
+    let end_block = CHUNK_START + 2 * CHUNK_BLOCKS;
+    let error = ParallelLVRProcessor::new(CHUNK_START, end_block, store.clone()).await.unwrap()
+        .with_source(parquet_source(&theoretical, &realized).await)
+        .process_blocks(None).await.unwrap_err();
+    assert!(format!("{:#}", error).contains("--resume"), "{:#}", error);
+    assert_eq!(store_contents(&store).await, contents);
+
+    ParallelLVRProcessor::new(CHUNK_START, end_block, store.clone()).await.unwrap()
+        .with_source(parquet_source(&theoretical, &realized).await)
+        .with_resume(true)
+        .process_blocks(None).await.unwrap();
+    assert_eq!(running_totals(store.clone()).await, totals);
+    assert!(store.paths().await.contains(&interval_path(CHUNK_START + CHUNK_BLOCKS, end_block).to_string()));
+}
+
+#[tokio::test]
+async fn test_checkpoints_declare_the_range_the_run_processed() {
+    let registry = PoolRegistry::default();
+    let late = registry.pools().iter().find(|pool| pool.deployment_block > 0).unwrap().clone();
+    let early = registry.pools().iter().find(|pool| pool.deployment_block == 0).unwrap().clone();
+    // Neither chunk- nor interval-aligned, with the late pool deployed inside
+    let start_block = late.deployment_block - 4_321;
+    let end_block = start_block + 20_000;
+    let theoretical = theoretical_batch([
+        (early.address.clone(), start_block + 3, 0.5, 1_200),
+        (late.address.clone(), late.deployment_block + 9, 0.5, 800),
+    ]);
+    let realized = empty_realized_batch();
+
+    let store = Arc::new(TestStore::new());
+    ParallelLVRProcessor::new(start_block, end_block, store.clone()).await.unwrap()
+        .with_source(parquet_source(&theoretical, &realized).await)
+        .process_blocks(None).await.unwrap();
+
+    let snapshot = |pool: &PoolEntry| {
+        let path = checkpoint_path(&pool.address, MarkoutTime::Positive05).to_string();
+        let store = store.clone();
+        async move { read_checkpoint_snapshot(&read_parquet(store.as_ref(), &path).await[0]).unwrap() }
+    };
+    let early_snapshot = snapshot(&early).await;
+    assert_eq!(early_snapshot.processed_range(), Some((start_block, end_block)));
+    assert_eq!(early_snapshot.blocks_processed, end_block - start_block);
+    let late_snapshot = snapshot(&late).await;
+    assert_eq!(late_snapshot.processed_range(), Some((late.deployment_block, end_block)));
+    assert_eq!(late_snapshot.blocks_processed, end_block - late.deployment_block);
+
+    let report = Validator::new(store.clone()).validate_all().await.unwrap();
+    for (pool, blocks) in [(&early, end_block - start_block), (&late, end_block - late.deployment_block)] {
+        let stats = &report.pools[&format!("{}_0.5", pool.address.to_lowercase())];
+        assert_eq!(stats.expected_blocks, Some(blocks));
+        assert!(stats.block_counts_match(), "{:?}", stats);
+    }
+
+    // A checkpoint declaring a later start expects fewer blocks than it counted
+    let mut shifted = early_snapshot.clone();
+    shifted.first_processed_block += 1_000;
+    ParallelParquetWriter::new(store.clone()).write_checkpoints(vec![shifted]).await.unwrap();
+    let report = Validator::new(store).validate_all().await.unwrap();
+    let stats = &report.pools[&format!("{}_0.5", early.address.to_lowercase())];
+    assert_eq!(stats.expected_blocks, Some(end_block - start_block - 1_000));
+    assert_eq!((stats.interval_block_deviation, stats.checkpoint_block_deviation), (1_000, 1_000));
+}
+
+#[tokio::test]
+async fn test_unaligned_end_block_attributes_running_totals() {
+    use axum::extract::State;
+
+    let pool = PoolRegistry::default().brontes_addresses().next().unwrap().to_string();
+    let second_chunk = CHUNK_START + CHUNK_BLOCKS;
+    // The last interval is cut short 2,801 blocks in
+    let end_block = second_chunk + 7_200 + 2_801;
+    let blocks = [CHUNK_START + 215_000, second_chunk + 100, second_chunk + 9_000, end_block - 1];
+    let theoretical = theoretical_batch(Vec::<(String, u64, f64, i64)>::new());
+    let realized = realized_batch(blocks.iter().zip([100, 200, 300, -50]).map(|(&block, value)| (pool.clone(), block, value)));
+
+    let store = Arc::new(TestStore::new());
+    ParallelLVRProcessor::new(CHUNK_START, end_block, store.clone()).await.unwrap()
+        .with_source(parquet_source(&theoretical, &realized).await)
+        .process_blocks(None).await.unwrap();
+
+    let intervals = read_parquet(store.as_ref(), interval_path(second_chunk, end_block).as_ref()).await;
+    // Every Brontes pool has a row per interval
+    let mut ranges = block_ranges(&intervals[0]);
+    ranges.sort();
+    ranges.dedup();
+    assert_eq!(ranges, vec![(second_chunk, second_chunk + 7_200), (second_chunk + 7_200, end_block)]);
+
+    let state = Arc::new(AppState::new(store));
+    let query = TimeRangeQuery {
+        start_block: None,
+        end_block: None,
+        markout_time: Some(MarkoutTime::Brontes),
+        aggregate: None,
+        pool: Some(pool.parse().unwrap()),
+    };
+    let points = get_running_total(State(state), ApiQuery(query)).await.unwrap().0;
+    let points: Vec<(u64, i64)> = points.iter().map(|p| (p.block_number, p.running_total_cents.as_i64())).collect();
+    assert_eq!(points, vec![(second_chunk, 100), (second_chunk + 7_200, 300), (end_block, 550)]);
+}
+
+/// (start_block, end_block) of every row of an interval batch
+fn block_ranges(batch: &arrow::record_batch::RecordBatch) -> Vec<(u64, u64)> {
+    use arrow::array::UInt64Array;
+
+    let column = |name| batch.column_by_name(name).unwrap().as_any().downcast_ref::<UInt64Array>().unwrap().values().to_vec();
+    column(INTERVAL_START_BLOCK_COLUMN).into_iter().zip(column(INTERVAL_END_BLOCK_COLUMN)).collect()
+}
+
+#[tokio::test]
+async fn test_cluster_activity_counts_overlapping_pools_once() {
+    use axum::extract::State;
+    use std::collections::{BTreeSet, HashMap};
+
+    // Two members of the same cluster, both live for the whole range
+    let registry = PoolRegistry::default();
+    let mut by_cluster: HashMap<&str, Vec<&str>> = HashMap::new();
+    for pool in registry.pools().iter().filter(|pool| pool.deployment_block == 0) {
+        if let Some(cluster) = pool.cluster.as_deref() {
+            by_cluster.entry(cluster).or_default().push(pool.address.as_str());
+        }
+    }
+    let (cluster, members) = by_cluster.into_iter().filter(|(_, members)| members.len() >= 2).min().unwrap();
+    let end_block = CHUNK_START + CHUNK_BLOCKS + 50_000;
+
+    // Both members are active on the same blocks, and the second on a few more
+    let mut active_blocks = BTreeSet::new();
+    let (mut pools, mut blocks, mut markouts, mut values) = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    for (p, pool) in members[..2].iter().enumerate() {
+        for i in 0..(20 + p as u64 * 5) {
+            let block = CHUNK_START + i * 10_000;
+            pools.push(pool.to_string());
+            blocks.push(block);
+            markouts.push(0.0);
+            values.push(150 + i as i64);
+            active_blocks.insert(block);
+        }
+    }
+    let theoretical = theoretical_batch(itertools::izip!(pools, blocks, markouts, values));
+    let realized = empty_realized_batch();
+
+    let store = Arc::new(TestStore::new());
+    let processor = ParallelLVRProcessor::new(CHUNK_START, end_block, store.clone()).await.unwrap()
+        .with_source(parquet_source(&theoretical, &realized).await);
+    processor.process_blocks(None).await.unwrap();
+    assert!(store.paths().await.contains(&CLUSTER_ACTIVITY_PATH.to_string()));
+
+    let state = Arc::new(AppState::new(store));
+    let response = get_cluster_non_zero(
+        State(state),
+        ApiQuery(ClusterNonZeroQuery { markout_time: Some(MarkoutTime::Zero) }),
+    ).await.unwrap().0;
+    let activity = response.clusters.iter().find(|c| c.name == cluster).unwrap();
+    assert_eq!(activity.total_observations, end_block - CHUNK_START);
+    assert_eq!(activity.non_zero_observations, active_blocks.len() as u64);
+    assert!(response.clusters.iter().all(|c| c.non_zero_proportion <= 1.0));
+}
+
+#[tokio::test]
+async fn test_cluster_activity_counts_pools_active_late_in_a_chunk() {
+    use axum::extract::State;
+    use std::collections::HashMap;
+
+    let registry = PoolRegistry::default();
+    let mut by_cluster: HashMap<&str, Vec<&str>> = HashMap::new();
+    for pool in registry.pools().iter().filter(|pool| pool.deployment_block == 0) {
+        if let Some(cluster) = pool.cluster.as_deref() {
+            by_cluster.entry(cluster).or_default().push(pool.address.as_str());
+        }
+    }
+    let (cluster, members) = by_cluster.into_iter().filter(|(_, members)| members.len() >= 2).min().unwrap();
+
+    // The first member is only active early in the chunk and the second
+    // only past its 100_000th block
+    let (mut pools, mut blocks, mut markouts, mut values) = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    for (pool, offset) in [(members[0], 10_000), (members[1], 150_000)] {
+        for i in 0..5 {
+            pools.push(pool.to_string());
+            blocks.push(CHUNK_START + offset + i * 10_000);
+            markouts.push(0.0);
+            values.push(150 + i as i64);
+        }
+    }
+    let theoretical = theoretical_batch(itertools::izip!(pools, blocks, markouts, values));
+    let realized = empty_realized_batch();
+
+    let store = Arc::new(TestStore::new());
+    ParallelLVRProcessor::new(CHUNK_START, CHUNK_START + CHUNK_BLOCKS, store.clone()).await.unwrap()
+        .with_source(parquet_source(&theoretical, &realized).await)
+        .process_blocks(None).await.unwrap();
+
+    let state = Arc::new(AppState::new(store));
+    let response = get_cluster_non_zero(
+        State(state),
+        ApiQuery(ClusterNonZeroQuery { markout_time: Some(MarkoutTime::Zero) }),
+    ).await.unwrap().0;
+    let activity = response.clusters.iter().find(|c| c.name == cluster).unwrap();
+    assert_eq!(activity.total_observations, CHUNK_BLOCKS);
+    assert_eq!(activity.non_zero_observations, 10);
+}
+
+#[tokio::test]
+async fn test_chunk_phase_timings_reach_metrics() {
+    let pool = PoolRegistry::default().pools()[0].address.clone();
+    let theoretical = theoretical_batch([(pool.clone(), CHUNK_START, 0.0, 120), (pool, CHUNK_START + CHUNK_BLOCKS, 0.0, 340)]);
+    let realized = empty_realized_batch();
+
+    let store = Arc::new(TestStore::new().with_latency(std::time::Duration::from_millis(5)));
+    let metrics = Arc::new(MetricsRegistry::new());
+    let end_block = CHUNK_START + CHUNK_BLOCKS + 50_000;
+    let processor = ParallelLVRProcessor::new(CHUNK_START, end_block, store.clone()).await.unwrap()
+        .with_source(parquet_source(&theoretical, &realized).await)
+        .with_metrics(metrics.clone());
+    processor.process_blocks(None).await.unwrap();
+
+    // Both writes go through the slowed store, so they are never instant
+    let summaries = read_chunk_summaries(store.as_ref()).await.unwrap();
+    assert_eq!(summaries.len(), 2);
+    for summary in &summaries {
+        assert!(summary.timings.interval_write_ms > 0, "{:?}", summary.timings);
+        assert!(summary.timings.checkpoint_update_ms > 0, "{:?}", summary.timings);
+    }
+
+    let rendered = metrics.render();
+    for (phase, _) in ChunkTimings::default().phases() {
+        assert!(rendered.contains(&format!("lvr_chunk_phase_seconds_count{{phase=\"{}\"}} 2\n", phase)), "{}", rendered);
+        assert!(rendered.contains(&format!("lvr_chunk_phase_seconds_bucket{{phase=\"{}\",le=\"+Inf\"}} 2\n", phase)), "{}", rendered);
+    }
+    assert!(rendered.contains("# TYPE lvr_chunk_phase_seconds histogram\n"));
+    assert!(rendered.contains(&format!("lvr_blocks_processed_total {}\n", end_block - CHUNK_START)));
+    assert!(rendered.contains("lvr_rows_fetched_total{source=\"aurora\"} 2\n"));
+}
+
+#[tokio::test]
+async fn test_boundary_values_land_in_labelled_buckets() {
+    use crate::common::{bucket_index, BUCKET_CONFIG};
+    use arrow::array::UInt64Array;
+
+    // Every bound, a cent either side of it; None is the zero bucket
+    let expected: [(u64, Option<&str>); 17] = [
+        (0, None),
+        (1, Some("$0.01-$10")),
+        (2, Some("$0.01-$10")),
+        (999, Some("$0.01-$10")),
+        (1_000, Some("$10-$100")),
+        (1_001, Some("$10-$100")),
+        (9_999, Some("$10-$100")),
+        (10_000, Some("$100-$500")),
+        (49_999, Some("$100-$500")),
+        (50_000, Some("$500-$1K")),
+        (50_001, Some("$500-$1K")),
+        (99_999, Some("$500-$1K")),
+        (100_000, Some("$1K-$10K")),
+        (100_001, Some("$1K-$10K")),
+        (999_999, Some("$1K-$10K")),
+        (1_000_000, Some("$10K+")),
+        (1_000_001, Some("$10K+")),
+    ];
+    for (cents, label) in expected {
+        assert_eq!(bucket_index(cents).map(|i| BUCKET_CONFIG[i].label), label, "{} cents", cents);
+    }
+
+    // The checkpoint counters agree with the labels
+    let pool = PoolRegistry::default().pools()[0].address.clone();
+    let store = Arc::new(TestStore::new());
+    let processor = ParallelLVRProcessor::new(CHUNK_START, CHUNK_START + CHUNK_BLOCKS, store.clone()).await.unwrap();
+    let data = expected.iter().enumerate()
+        .map(|(i, &(cents, _))| UnifiedLVRData {
+            pool_address: pool.parse().unwrap(),
+            block_number: CHUNK_START + i as u64,
+            lvr_cents: Cents(cents as i64),
+            source: DataSource::Aurora,
+        })
+        .collect();
+    processor.atomic_checkpoint_update(vec![CheckpointUpdate {
+        pool_address: pool.parse().unwrap(),
+        markout_time: MarkoutTime::Zero,
+        data,
+        chunk_start: CHUNK_START,
+        chunk_end: CHUNK_START + CHUNK_BLOCKS,
+    }]).await.unwrap();
+
+    let path = checkpoint_path(&pool, MarkoutTime::Zero).to_string();
+    let batch = read_parquet(store.as_ref(), &path).await.remove(0);
+    for spec in &BUCKET_CONFIG {
+        let count = batch.column_by_name(spec.column).unwrap().as_any().downcast_ref::<UInt64Array>().unwrap().value(0);
+        let labelled = expected.iter().filter(|(_, label)| *label == Some(spec.label)).count() as u64;
+        assert_eq!(count, labelled, "{}", spec.label);
+    }
+
+    // Checkpoints written without the bucket rule are reported
+    let report = Validator::new(store.clone()).validate_all().await.unwrap();
+    assert!(report.legacy_bucket_checkpoints.is_empty());
+    {
+        use object_store::{path::Path, ObjectStore};
+        store.put(&Path::from(path.as_str()), parquet_bytes(&batch).into()).await.unwrap();
+    }
+    let report = Validator::new(store).validate_all().await.unwrap();
+    assert_eq!(report.legacy_bucket_checkpoints, vec![path]);
+}
+
+#[tokio::test]
+async fn test_raw_series_sums_to_interval_totals() {
+    use arrow::array::{Int64Array, StringArray};
+    use axum::extract::State;
+    use std::collections::HashMap;
+
+    let registry = PoolRegistry::default();
+    let pools: Vec<String> = registry.pools().iter()
+        .filter(|pool| pool.deployment_block == 0)
+        .take(2)
+        .map(|pool| pool.address.to_lowercase())
+        .collect();
+    let raw_pool = &pools[0];
+    let end_block = CHUNK_START + CHUNK_BLOCKS + 50_000;
+    let markouts = [-1.0, 0.5];
+
+    let (mut addresses, mut blocks, mut markout_times, mut values) = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    for pool in &pools {
+        for &markout in &markouts {
+            for i in 0..15u64 {
+                addresses.push(pool.clone());
+                blocks.push(CHUNK_START + i * 17_500);
+                markout_times.push(markout);
+                values.push(400 - (i as i64 * 53) % 700);
+            }
+        }
+    }
+    // A later value for a block replaces the earlier one in both outputs
+    addresses.push(raw_pool.clone());
+    blocks.push(CHUNK_START);
+    markout_times.push(-1.0);
+    values.push(9_000);
+    let theoretical = theoretical_batch(itertools::izip!(addresses, blocks, markout_times, values));
+    let realized = empty_realized_batch();
+
+    let store = Arc::new(TestStore::new());
+    let processor = ParallelLVRProcessor::new(CHUNK_START, end_block, store.clone()).await.unwrap()
+        .with_source(parquet_source(&theoretical, &realized).await)
+        .with_raw_pools(&[Address::from_any_case(&raw_pool.to_uppercase().replacen("0X", "0x", 1)).unwrap()]);
+    processor.process_blocks(None).await.unwrap();
+
+    let paths = store.paths().await;
+    let raw_files: Vec<&String> = paths.iter().filter(|p| p.starts_with("raw/")).collect();
+    assert_eq!(raw_files, vec![
+        &raw_path(&raw_pool.parse().unwrap(), CHUNK_START, CHUNK_START + CHUNK_BLOCKS).to_string(),
+        &raw_path(&raw_pool.parse().unwrap(), CHUNK_START + CHUNK_BLOCKS, end_block).to_string(),
+    ]);
+
+    let mut raw_totals: HashMap<String, i64> = HashMap::new();
+    for path in &raw_files {
+        for batch in read_parquet(store.as_ref(), path).await {
+            let markout_times = batch.column_by_name(RAW_MARKOUT_TIME_COLUMN).unwrap().as_any().downcast_ref::<StringArray>().unwrap();
+            let lvr = batch.column_by_name(RAW_LVR_COLUMN).unwrap().as_any().downcast_ref::<Int64Array>().unwrap();
+            for i in 0..batch.num_rows() {
+                *raw_totals.entry(markout_times.value(i).to_string()).or_default() += lvr.value(i);
+            }
+        }
+    }
+    let mut interval_totals: HashMap<String, i64> = HashMap::new();
+    for path in paths.iter().filter(|p| p.starts_with("intervals/")) {
+        for batch in read_parquet(store.as_ref(), path).await {
+            let pool_addresses = batch.column_by_name(INTERVAL_PAIR_ADDRESS_COLUMN).unwrap().as_any().downcast_ref::<StringArray>().unwrap();
+            let markout_times = batch.column_by_name(INTERVAL_MARKOUT_TIME_COLUMN).unwrap().as_any().downcast_ref::<StringArray>().unwrap();
+            let totals = batch.column_by_name(INTERVAL_TOTAL_LVR_COLUMN).unwrap().as_any().downcast_ref::<Int64Array>().unwrap();
+            for i in 0..batch.num_rows() {
+                if pool_addresses.value(i).to_lowercase() == *raw_pool {
+                    *interval_totals.entry(markout_times.value(i).to_string()).or_default() += totals.value(i);
+                }
+            }
+        }
+    }
+    // Markouts without raw rows, like Brontes here, are all zeros
+    assert_eq!(raw_totals.len(), markouts.len());
+    for (markout_time, total) in &interval_totals {
+        assert_eq!(raw_totals.get(markout_time).copied().unwrap_or(0), *total, "{}", markout_time);
+    }
+    assert!(raw_totals.keys().all(|markout_time| interval_totals.contains_key(markout_time)));
+
+    let state = Arc::new(AppState::new(store.clone()));
+    let series = get_raw_series(
+        State(state),
+        ApiQuery(RawSeriesQuery {
+            pool_address: raw_pool.parse().unwrap(),
+            start_block: Some(CHUNK_START),
+            end_block: Some(CHUNK_START + 17_500 * 2),
+            markout_time: Some(MarkoutTime::Negative1),
+        }),
+    ).await.unwrap().0;
+    let points: Vec<(u64, i64)> = series.points.iter().map(|p| (p.block_number, p.lvr_cents.as_i64())).collect();
+    assert_eq!(points, vec![(CHUNK_START, 9_000), (CHUNK_START + 17_500, 400 - 53)]);
+}
+
+/// Updates for every pool at every markout over one chunk, enough values
+/// per update that the digests merge their buffers
+fn markout_updates(pools: &[String], chunk_start: u64) -> Vec<CheckpointUpdate> {
+    let mut updates = Vec::new();
+    for (p, pool) in pools.iter().enumerate() {
+        for (m, &markout) in MARKOUT_TIMES.iter().enumerate() {
+            updates.push(CheckpointUpdate {
+                pool_address: pool.parse().unwrap(),
+                markout_time: MarkoutTime::from_f64(markout).unwrap(),
+                data: (0..250u64)
+                    .map(|i| UnifiedLVRData {
+                        pool_address: pool.parse().unwrap(),
+                        block_number: chunk_start + i * 700 + p as u64,
+                        lvr_cents: Cents(((i * 7_919 + p as u64 * 104_729 + m as u64 * 31) % 250_000) as i64 - 20_000),
+                        source: DataSource::Aurora,
+                    })
+                    .collect(),
+                chunk_start,
+                chunk_end: chunk_start + CHUNK_BLOCKS,
+            });
+        }
+    }
+    updates
+}
+
+#[tokio::test]
+async fn test_parallel_checkpoint_apply_matches_sequential() {
+    let pools: Vec<String> = PoolRegistry::default().pools().iter()
+        .filter(|pool| pool.deployment_block == 0)
+        .take(6)
+        .map(|pool| pool.address.clone())
+        .collect();
+    let sequential_store = Arc::new(TestStore::new());
+    let sequential = ParallelLVRProcessor::new(CHUNK_START, CHUNK_START + CHUNK_BLOCKS, sequential_store.clone()).await.unwrap();
+    let mut sequential_totals: std::collections::BTreeMap<String, (i64, u64)> = Default::default();
+    for update in markout_updates(&pools, CHUNK_START) {
+        for totals in sequential.atomic_checkpoint_update(vec![update]).await.unwrap() {
+            let entry = sequential_totals.entry(totals.markout_time).or_default();
+            entry.0 += totals.total_lvr_cents.as_i64();
+            entry.1 += totals.non_zero_count;
+        }
+    }
+
+    let parallel_store = Arc::new(TestStore::new());
+    let parallel = ParallelLVRProcessor::new(CHUNK_START, CHUNK_START + CHUNK_BLOCKS, parallel_store.clone()).await.unwrap();
+    let parallel_totals: std::collections::BTreeMap<String, (i64, u64)> = parallel.atomic_checkpoint_update(markout_updates(&pools, CHUNK_START)).await.unwrap()
+        .into_iter()
+        .map(|totals| (totals.markout_time, (totals.total_lvr_cents.as_i64(), totals.non_zero_count)))
+        .collect();
+
+    assert_eq!(parallel_totals.len(), MARKOUT_TIMES.len());
+    assert_eq!(parallel_totals, sequential_totals);
+    let sequential_files = store_contents(&sequential_store).await;
+    assert_eq!(
+        sequential_files.iter().filter(|(path, _)| path.starts_with("checkpoints/")).count(),
+        (pools.len() + 1) * MARKOUT_TIMES.len()
+    );
+    assert_eq!(store_contents(&parallel_store).await, sequential_files);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_updates_to_one_key_are_order_independent() {
+    use arrow::array::{Int64Array, UInt64Array};
+
+    let pool = PoolRegistry::default().pools()[0].address.clone();
+    let update = |t: u64| CheckpointUpdate {
+        pool_address: pool.parse().unwrap(),
+        markout_time: MarkoutTime::Zero,
+        data: (0..50u64)
+            .map(|i| UnifiedLVRData {
+                pool_address: pool.parse().unwrap(),
+                block_number: CHUNK_START + t * 10_000 + i * 3,
+                lvr_cents: Cents(((t * 50 + i) * 4_099 % 150_000) as i64 - 5_000),
+                source: DataSource::Aurora,
+            })
+            .collect(),
+        chunk_start: CHUNK_START,
+        chunk_end: CHUNK_START + CHUNK_BLOCKS,
+    };
+
+    // The pool's and aggregate checkpoints' counters; the quantiles come
+    // from a digest, which depends on the order values arrive in
+    async fn counters(store: &TestStore, pool: &str) -> Vec<Vec<i128>> {
+        let mut counters = Vec::new();
+        for pair_address in [pool, AGGREGATE_POOL_ADDRESS] {
+            let path = checkpoint_path(pair_address, MarkoutTime::Zero).to_string();
+            let batch = read_parquet(store, &path).await.remove(0);
+            let mut row = Vec::new();
+            for (field, column) in batch.schema().fields().iter().zip(batch.columns()) {
+                let quantile_derived = ["median_cents", "trimmed_mean_cents", "iqr_cents"];
+                if field.name().starts_with("percentile_") || quantile_derived.contains(&field.name().as_str()) {
+                    continue;
+                }
+                if let Some(values) = column.as_any().downcast_ref::<UInt64Array>() {
+                    row.push(values.value(0) as i128);
+                } else if let Some(values) = column.as_any().downcast_ref::<Int64Array>() {
+                    row.push(values.value(0) as i128);
+                }
+            }
+            counters.push(row);
+        }
+        counters
+    }
+
+    let reversed_store = Arc::new(TestStore::new());
+    let reversed = ParallelLVRProcessor::new(CHUNK_START, CHUNK_START + CHUNK_BLOCKS, reversed_store.clone()).await.unwrap();
+    for t in (0..16).rev() {
+        reversed.atomic_checkpoint_update(vec![update(t)]).await.unwrap();
+    }
+
+    let concurrent_store = Arc::new(TestStore::new());
+    let concurrent = Arc::new(ParallelLVRProcessor::new(CHUNK_START, CHUNK_START + CHUNK_BLOCKS, concurrent_store.clone()).await.unwrap());
+    let tasks: Vec<_> = (0..16)
+        .map(|t| {
+            let processor = Arc::clone(&concurrent);
+            let update = update(t);
+            tokio::spawn(async move { processor.atomic_checkpoint_update(vec![update]).await.unwrap() })
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    let expected = counters(&reversed_store, &pool).await;
+    assert!(expected[0].iter().any(|&value| value != 0));
+    assert_eq!(counters(&concurrent_store, &pool).await, expected);
+}
+
+#[tokio::test]
+async fn test_resumed_run_matches_uninterrupted_quantiles() {
+    use arrow::array::UInt64Array;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use std::collections::HashMap;
+
+    let pool = PoolRegistry::default().pools().iter()
+        .find(|pool| pool.deployment_block == 0 && pool.cluster.is_some())
+        .unwrap()
+        .address
+        .clone();
+    let end_block = CHUNK_START + 4 * CHUNK_BLOCKS;
+
+    let mut rng = StdRng::seed_from_u64(1106);
+    let (mut pools, mut blocks, mut markouts, mut values) = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    for &markout in &MARKOUT_TIMES[..3] {
+        for block in (CHUNK_START..end_block).step_by(150) {
+            pools.push(pool.clone());
+            blocks.push(block);
+            markouts.push(markout);
+            // Skewed, like LVR: mostly small with a long tail
+            values.push((rng.gen::<f64>().powi(4) * 500_000.0) as i64 + 1);
+        }
+    }
+    let theoretical = theoretical_batch(itertools::izip!(pools, blocks, markouts, values));
+    let realized = empty_realized_batch();
+    let source = parquet_source(&theoretical, &realized).await;
+
+    async fn snapshots(store: &TestStore) -> HashMap<String, CheckpointSnapshot> {
+        let mut snapshots = HashMap::new();
+        for path in store.paths().await.iter().filter(|p| p.starts_with("checkpoints/")) {
+            for batch in read_parquet(store, path).await {
+                snapshots.insert(path.clone(), CheckpointSnapshot::from_record_batch(&batch).unwrap().remove(0));
+            }
+        }
+        snapshots
+    }
+
+    let single_store = Arc::new(TestStore::new());
+    ParallelLVRProcessor::new(CHUNK_START, end_block, single_store.clone()).await.unwrap()
+        .with_source(source.clone())
+        .process_blocks(None).await.unwrap();
+
+    let resumed_store = Arc::new(TestStore::new());
+    ParallelLVRProcessor::new(CHUNK_START, CHUNK_START + 2 * CHUNK_BLOCKS, resumed_store.clone()).await.unwrap()
+        .with_source(source.clone())
+        .process_blocks(None).await.unwrap();
+    ParallelLVRProcessor::new(CHUNK_START, end_block, resumed_store.clone()).await.unwrap()
+        .with_source(source)
+        .with_resume(true)
+        .process_blocks(None).await.unwrap();
+
+    let single = snapshots(&single_store).await;
+    let resumed = snapshots(&resumed_store).await;
+    // The pool's and the aggregate checkpoint of each markout with data
+    assert_eq!(single.values().filter(|snapshot| snapshot.non_zero_samples > 0).count(), 3 * 2);
+    assert_eq!(resumed.keys().collect::<std::collections::BTreeSet<_>>(), single.keys().collect());
+    for (path, expected) in &single {
+        let actual = &resumed[path];
+        assert_eq!(actual.digest.samples(), expected.non_zero_samples, "{}", path);
+        assert_eq!(actual.running_total, expected.running_total, "{}", path);
+        assert_eq!(actual.non_zero_samples, expected.non_zero_samples, "{}", path);
+        assert_eq!(actual.total_bucket_10000_plus, expected.total_bucket_10000_plus, "{}", path);
+        assert_eq!(actual.top_lvr, expected.top_lvr, "{}", path);
+        // The digest is stored whole, so the resumed one is the same digest
+        assert_eq!(
+            (actual.percentile_25_cents, actual.median_cents, actual.percentile_75_cents, actual.mean),
+            (expected.percentile_25_cents, expected.median_cents, expected.percentile_75_cents, expected.mean),
+            "{}", path
+        );
+    }
+
+    // The first run's cluster activity carries over
+    let activity = |store: Arc<TestStore>| async move {
+        read_parquet(store.as_ref(), CLUSTER_ACTIVITY_PATH).await.remove(0)
+            .column_by_name("total_blocks").unwrap().as_any().downcast_ref::<UInt64Array>().unwrap().values().to_vec()
+    };
+    assert_eq!(activity(resumed_store).await, activity(single_store).await);
+}
+
+/// Theoretical rows generated as the processor pulls them: every `stride`
+/// blocks, one row for each of three registry pools and one for a pool
+/// outside the registry.
+/// `buffered` runs hand them over through `fetch_theoretical` instead.
+struct SyntheticSource {
+    stride: u64,
+    buffered: bool,
+}
+
+impl SyntheticSource {
+    fn rows<'a>(&self, pools: &'a PoolRegistry, markout_time: MarkoutTime, chunk_start: u64, chunk_end: u64) -> impl Iterator<Item = UnifiedLVRData> + Send + 'a {
+        let markout = markout_time.as_f64().unwrap();
+        (chunk_start..chunk_end).step_by(self.stride as usize).flat_map(move |block_number| {
+            pools.pools().iter()
+                .take(3)
+                .map(|pool| pool.address.as_str())
+                .chain(["0x0000000000000000000000000000000000000001"])
+                .enumerate()
+                .map(move |(p, pool_address)| UnifiedLVRData {
+                    pool_address: pool_address.parse().unwrap(),
+                    block_number,
+                    lvr_cents: Cents(((block_number * 31 + p as u64 * 7) % 1_000) as i64 - 200 + (markout * 10.0) as i64),
+                    source: DataSource::Aurora,
+                })
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl LvrSource for SyntheticSource {
+    async fn fetch_theoretical(&self, pools: &PoolRegistry, markout_time: MarkoutTime, chunk_start: u64, chunk_end: u64) -> anyhow::Result<Vec<UnifiedLVRData>> {
+        Ok(self.rows(pools, markout_time, chunk_start, chunk_end).collect())
+    }
+
+    async fn stream_theoretical<'a>(
+        &'a self,
+        pools: &'a PoolRegistry,
+        markout_time: MarkoutTime,
+        chunk_start: u64,
+        chunk_end: u64,
+    ) -> anyhow::Result<TheoreticalStream<'a>> {
+        use futures::StreamExt;
+
+        if self.buffered {
+            let rows = self.fetch_theoretical(pools, markout_time, chunk_start, chunk_end).await?;
+            return Ok(TheoreticalStream::new(futures::stream::iter(rows.into_iter().map(Ok)).boxed()));
+        }
+        Ok(TheoreticalStream::new(futures::stream::iter(self.rows(pools, markout_time, chunk_start, chunk_end).map(Ok)).boxed()))
+    }
+
+    async fn fetch_realized(&self, _: &PoolRegistry, _: u64, _: u64) -> anyhow::Result<Vec<UnifiedLVRData>> {
+        Ok(Vec::new())
+    }
+}
+
+#[tokio::test]
+async fn test_streamed_theoretical_rows_match_buffered_checkpoints() {
+    let mut checkpoints = Vec::new();
+    let mut aurora_rows = Vec::new();
+    for buffered in [true, false] {
+        let store = Arc::new(TestStore::new());
+        ParallelLVRProcessor::new(CHUNK_START, CHUNK_START + CHUNK_BLOCKS, store.clone()).await.unwrap()
+            .with_source(Arc::new(SyntheticSource { stride: 997, buffered }))
+            .process_blocks(None).await.unwrap();
+
+        let stored: Vec<_> = store_contents(&store).await
+            .into_iter()
+            .filter(|(path, _)| path.starts_with("checkpoints/"))
+            .collect();
+        assert!(stored.iter().any(|(path, _)| path.contains(AGGREGATE_POOL_ADDRESS)));
+        checkpoints.push(stored);
+        aurora_rows.push(read_chunk_summaries(store.as_ref()).await.unwrap().remove(0).aurora_rows);
+    }
+
+    // Pool checkpoints and the aggregate ones they are folded into alike
+    assert!(checkpoints[0] == checkpoints[1]);
+    // Rows of the unknown pool are counted as fetched, then dropped
+    let blocks = CHUNK_BLOCKS.div_ceil(997);
+    assert_eq!(aurora_rows, vec![blocks * 4 * MARKOUT_TIMES.len() as u64; 2]);
+}
+
+#[tokio::test]
+async fn test_aggregate_checkpoints_are_identical_across_runs() {
+    let mut aggregates = Vec::new();
+    for _ in 0..2 {
+        let store = Arc::new(TestStore::new());
+        ParallelLVRProcessor::new(CHUNK_START, CHUNK_START + 2 * CHUNK_BLOCKS, store.clone()).await.unwrap()
+            .with_source(Arc::new(SyntheticSource { stride: 997, buffered: true }))
+            .process_blocks(None).await.unwrap();
+        let aggregate: Vec<_> = store_contents(&store).await
+            .into_iter()
+            .filter(|(path, _)| path.starts_with(&format!("checkpoints/pool={}/", AGGREGATE_POOL_ADDRESS)))
+            .collect();
+        assert!(!aggregate.is_empty());
+        aggregates.push(aggregate);
+    }
+    // The digests merge every pool in the same order both times
+    assert!(aggregates[0] == aggregates[1]);
+}
+
+/// `SyntheticSource` with one markout silently returning nothing
+struct GappedSource {
+    inner: SyntheticSource,
+    missing: MarkoutTime,
+}
+
+#[async_trait::async_trait]
+impl LvrSource for GappedSource {
+    async fn fetch_theoretical(&self, pools: &PoolRegistry, markout_time: MarkoutTime, chunk_start: u64, chunk_end: u64) -> anyhow::Result<Vec<UnifiedLVRData>> {
+        if markout_time == self.missing {
+            return Ok(Vec::new());
+        }
+        self.inner.fetch_theoretical(pools, markout_time, chunk_start, chunk_end).await
+    }
+
+    async fn fetch_realized(&self, _: &PoolRegistry, _: u64, _: u64) -> anyhow::Result<Vec<UnifiedLVRData>> {
+        Ok(Vec::new())
+    }
+}
+
+fn gapped_source() -> Arc<GappedSource> {
+    Arc::new(GappedSource { inner: SyntheticSource { stride: 997, buffered: true }, missing: MarkoutTime::Positive1 })
+}
+
+/// `SyntheticSource` answering every fetch after `delay`, like a remote
+/// database would
+struct SlowSource {
+    inner: SyntheticSource,
+    delay: std::time::Duration,
+}
+
+#[async_trait::async_trait]
+impl LvrSource for SlowSource {
+    async fn fetch_theoretical(&self, pools: &PoolRegistry, markout_time: MarkoutTime, chunk_start: u64, chunk_end: u64) -> anyhow::Result<Vec<UnifiedLVRData>> {
+        tokio::time::sleep(self.delay).await;
+        self.inner.fetch_theoretical(pools, markout_time, chunk_start, chunk_end).await
+    }
+
+    async fn fetch_realized(&self, pools: &PoolRegistry, chunk_start: u64, chunk_end: u64) -> anyhow::Result<Vec<UnifiedLVRData>> {
+        tokio::time::sleep(self.delay).await;
+        self.inner.fetch_realized(pools, chunk_start, chunk_end).await
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_parallel_chunks_match_sequential_output_in_less_time() {
+    let end_block = CHUNK_START + 3 * CHUNK_BLOCKS;
+    // A single pool keeps the per-chunk work small next to the fetch delay
+    let registry = PoolRegistry::default();
+    let addresses: Vec<String> = registry.pools().iter().take(1).map(|pool| pool.address.clone()).collect();
+    let pools = Arc::new(registry.subset(&addresses).unwrap());
+    let mut outputs = Vec::new();
+    let mut elapsed = Vec::new();
+    for parallel_chunks in [1, 3] {
+        let store = Arc::new(TestStore::new());
+        let slow = SlowSource {
+            inner: SyntheticSource { stride: 20_011, buffered: true },
+            delay: std::time::Duration::from_secs(3),
+        };
+        let started = std::time::Instant::now();
+        ParallelLVRProcessor::new(CHUNK_START, end_block, store.clone()).await.unwrap()
+            .with_pool_registry(Arc::clone(&pools))
+            .with_source(Arc::new(slow))
+            .with_parallel_chunks(parallel_chunks)
+            .process_blocks(None).await.unwrap();
+        elapsed.push(started.elapsed());
+        outputs.push(store_contents(&store).await
+            .into_iter()
+            .filter(|(path, _)| path.starts_with("intervals/") || path.starts_with("checkpoints/"))
+            .collect::<Vec<_>>());
+    }
+
+    assert_eq!(outputs[0].iter().filter(|(path, _)| path.starts_with("intervals/")).count(), 3);
+    assert!(outputs[0] == outputs[1]);
+    // Fetches of later chunks overlap the wait for earlier ones
+    assert!(elapsed[1] < elapsed[0], "{:?}", elapsed);
+}
+
+#[tokio::test]
+async fn test_an_empty_markout_is_flagged_in_the_chunk_summary() {
+    let store = Arc::new(TestStore::new());
+    ParallelLVRProcessor::new(CHUNK_START, CHUNK_START + CHUNK_BLOCKS, store.clone()).await.unwrap()
+        .with_source(gapped_source())
+        .process_blocks(None).await.unwrap();
+
+    let summary = read_chunk_summaries(store.as_ref()).await.unwrap().remove(0);
+    let expected_rows = CHUNK_BLOCKS.div_ceil(997) * 4;
+    assert_eq!(summary.completeness_warnings, vec![CompletenessWarning {
+        markout_time: "1.0".to_string(),
+        chunk_start: CHUNK_START,
+        chunk_end: CHUNK_START + CHUNK_BLOCKS,
+        rows: 0,
+        expected_rows,
+    }]);
+    assert_eq!(
+        summary.completeness_warnings[0].to_string(),
+        format!("markout 1.0 returned 0 rows for blocks {}-{}, expected about {}", CHUNK_START, CHUNK_START + CHUNK_BLOCKS, expected_rows)
+    );
+}
+
+#[tokio::test]
+async fn test_strict_completeness_fails_the_chunk() {
+    let store = Arc::new(TestStore::new());
+    let error = ParallelLVRProcessor::new(CHUNK_START, CHUNK_START + CHUNK_BLOCKS, store.clone()).await.unwrap()
+        .with_source(gapped_source())
+        .with_strict_source_completeness(true)
+        .process_blocks(None).await.unwrap_err();
+
+    assert!(format!("{:#}", error).contains("Incomplete source data: markout 1.0 returned 0 rows"), "{:#}", error);
+    assert!(read_chunk_summaries(store.as_ref()).await.unwrap().is_empty());
+}
+
+#[test]
+fn test_a_drop_in_every_markout_is_caught_by_the_history() {
+    let check = CompletenessCheck::default();
+    let markouts = |count: u64| -> Vec<(String, u64)> {
+        MARKOUT_TIMES.iter().map(|markout| (format!("{:.1}", markout), count)).collect()
+    };
+    for chunk in 0..3 {
+        assert!(check.check(chunk * 1_000, (chunk + 1) * 1_000, &markouts(400)).is_empty());
+    }
+
+    // The markouts agree with each other, but not with their earlier chunks
+    let warnings = check.check(3_000, 4_000, &markouts(20));
+    assert_eq!(warnings.len(), MARKOUT_TIMES.len());
+    assert!(warnings.iter().all(|warning| warning.rows == 20 && warning.expected_rows == 400));
+    // A modest dip is not a gap
+    assert!(check.check(4_000, 5_000, &markouts(300)).is_empty());
+}
+
+/// `SyntheticSource` counting its theoretical fetches, whose realized
+/// fetch fails transiently the first `failures` times
+struct FlakyRealizedSource {
+    inner: SyntheticSource,
+    failures: usize,
+    theoretical_fetches: std::sync::Mutex<std::collections::HashMap<MarkoutTime, usize>>,
+    realized_fetches: std::sync::atomic::AtomicUsize,
+}
+
+#[async_trait::async_trait]
+impl LvrSource for FlakyRealizedSource {
+    async fn fetch_theoretical(&self, pools: &PoolRegistry, markout_time: MarkoutTime, chunk_start: u64, chunk_end: u64) -> anyhow::Result<Vec<UnifiedLVRData>> {
+        *self.theoretical_fetches.lock().unwrap().entry(markout_time).or_default() += 1;
+        self.inner.fetch_theoretical(pools, markout_time, chunk_start, chunk_end).await
+    }
+
+    async fn fetch_realized(&self, _: &PoolRegistry, _: u64, _: u64) -> anyhow::Result<Vec<UnifiedLVRData>> {
+        if self.realized_fetches.fetch_add(1, std::sync::atomic::Ordering::SeqCst) < self.failures {
+            return Err(Error::Database("connection reset".to_string()).into());
+        }
+        Ok(Vec::new())
+    }
+}
+
+#[tokio::test]
+async fn test_chunk_retries_reuse_fetched_markouts() {
+    let source = Arc::new(FlakyRealizedSource {
+        inner: SyntheticSource { stride: 997, buffered: true },
+        failures: 2,
+        theoretical_fetches: Default::default(),
+        realized_fetches: Default::default(),
+    });
+    let retry_config = RetryConfig { chunk: RetryPolicy::new(3).with_base_delay(std::time::Duration::ZERO), ..RetryConfig::default() };
+    let store = Arc::new(TestStore::new());
+    ParallelLVRProcessor::new(CHUNK_START, CHUNK_START + CHUNK_BLOCKS, store.clone()).await.unwrap()
+        .with_source(source.clone())
+        .with_retry_config(retry_config)
+        .process_blocks(None).await.unwrap();
+
+    assert_eq!(source.realized_fetches.load(std::sync::atomic::Ordering::SeqCst), 3);
+    let fetches = source.theoretical_fetches.lock().unwrap().clone();
+    assert_eq!(fetches.len(), MARKOUT_TIMES.len());
+    assert!(fetches.values().all(|&count| count == 1), "{:?}", fetches);
+
+    let summary = read_chunk_summaries(store.as_ref()).await.unwrap().remove(0);
+    assert_eq!(summary.retries, 2);
+    assert_eq!(summary.aurora_rows, CHUNK_BLOCKS.div_ceil(997) * 4 * MARKOUT_TIMES.len() as u64);
+}
+
+#[test]
+fn test_fetch_cache_evicts_the_least_recently_used_and_invalidates_chunks() {
+    let key = |markout_time, chunk_start: u64| FetchKey { source: DataSource::Aurora, markout_time, chunk_start, chunk_end: chunk_start + 10 };
+    let cache = FetchCache::new(2);
+    cache.insert(key(MarkoutTime::Zero, 0), 1);
+    cache.insert(key(MarkoutTime::Positive1, 0), 2);
+    assert_eq!(cache.get(&key(MarkoutTime::Zero, 0)), Some(1));
+    cache.insert(key(MarkoutTime::Zero, 10), 3);
+    assert_eq!(cache.get(&key(MarkoutTime::Positive1, 0)), None);
+    assert_eq!(cache.get(&key(MarkoutTime::Zero, 0)), Some(1));
+
+    cache.invalidate(0, 10);
+    assert_eq!(cache.len(), 1);
+    assert_eq!(cache.get(&key(MarkoutTime::Zero, 10)), Some(3));
+    assert_eq!(cache.get(&FetchKey { source: DataSource::Brontes, ..key(MarkoutTime::Zero, 10) }), None);
+
+    let disabled = FetchCache::new(0);
+    disabled.insert(key(MarkoutTime::Zero, 0), 1);
+    assert!(disabled.is_empty());
+}
+
+/// `SyntheticSource` whose Brontes data ends at `brontes_head`
+struct LaggingSource {
+    inner: SyntheticSource,
+    aurora_head: u64,
+    brontes_head: u64,
+}
+
+#[async_trait::async_trait]
+impl LvrSource for LaggingSource {
+    async fn fetch_theoretical(&self, pools: &PoolRegistry, markout_time: MarkoutTime, chunk_start: u64, chunk_end: u64) -> anyhow::Result<Vec<UnifiedLVRData>> {
+        self.inner.fetch_theoretical(pools, markout_time, chunk_start, chunk_end).await
+    }
+
+    async fn fetch_realized(&self, _: &PoolRegistry, _: u64, _: u64) -> anyhow::Result<Vec<UnifiedLVRData>> {
+        Ok(Vec::new())
+    }
+
+    async fn latest_blocks(&self) -> anyhow::Result<Vec<SourceHead>> {
+        let mut heads: Vec<SourceHead> = MARKOUT_TIMES.iter()
+            .map(|time| SourceHead { name: format!("aurora markout {:.1}", time), latest_block: Some(self.aurora_head) })
+            .collect();
+        heads.push(SourceHead { name: "brontes".to_string(), latest_block: Some(self.brontes_head) });
+        Ok(heads)
+    }
+}
+
+#[tokio::test]
+async fn test_clamped_run_stops_at_the_latest_source_block() {
+    let end_block = CHUNK_START + 2 * CHUNK_BLOCKS;
+    let brontes_head = CHUNK_START + CHUNK_BLOCKS + 1_000;
+    let source = |brontes_head| Arc::new(LaggingSource {
+        inner: SyntheticSource { stride: 997, buffered: true },
+        aurora_head: end_block + 50,
+        brontes_head,
+    });
+
+    let store = Arc::new(TestStore::new());
+    let (logs, guard) = capture_logs();
+    let processor = ParallelLVRProcessor::new(CHUNK_START, end_block, store.clone()).await.unwrap()
+        .with_source(source(brontes_head))
+        .with_clamp_to_source(true);
+    processor.process_blocks(None).await.unwrap();
+    drop(guard);
+
+    assert_eq!(processor.end_block(), brontes_head);
+    let chunk_ends: Vec<u64> = read_chunk_summaries(store.as_ref()).await.unwrap().iter().map(|summary| summary.chunk_end).collect();
+    assert_eq!(chunk_ends, vec![CHUNK_START + CHUNK_BLOCKS, brontes_head]);
+    let logs = logs.contents();
+    assert!(logs.contains(&format!("brontes has data up to block {}, {} blocks short of the end block {}", brontes_head, end_block - brontes_head, end_block)), "{}", logs);
+    assert!(logs.contains(&format!("Clamping the run's end block from {} to {}", end_block, brontes_head)), "{}", logs);
+    assert!(logs.contains(&format!("Starting block processing from {} to {}", CHUNK_START, brontes_head)), "{}", logs);
+
+    // Without clamping the lag is only reported
+    let store = Arc::new(TestStore::new());
+    let processor = ParallelLVRProcessor::new(CHUNK_START, CHUNK_START + CHUNK_BLOCKS, store.clone()).await.unwrap()
+        .with_source(source(CHUNK_START + 10));
+    processor.process_blocks(None).await.unwrap();
+    assert_eq!(processor.end_block(), CHUNK_START + CHUNK_BLOCKS);
+    assert_eq!(read_chunk_summaries(store.as_ref()).await.unwrap()[0].chunk_end, CHUNK_START + CHUNK_BLOCKS);
+}
+
+/// A source whose queries the server never answers
+struct HangingSource;
+
+#[async_trait::async_trait]
+impl LvrSource for HangingSource {
+    async fn fetch_theoretical(&self, _: &PoolRegistry, _: MarkoutTime, _: u64, _: u64) -> anyhow::Result<Vec<UnifiedLVRData>> {
+        std::future::pending().await
+    }
+
+    async fn fetch_realized(&self, _: &PoolRegistry, _: u64, _: u64) -> anyhow::Result<Vec<UnifiedLVRData>> {
+        std::future::pending().await
+    }
+}
+
+#[tokio::test]
+async fn test_cancelled_run_exits_without_waiting_for_its_queries() {
+    use std::time::{Duration, Instant};
+
+    let cancellation = tokio_util::sync::CancellationToken::new();
+    let store = Arc::new(TestStore::new());
+    let processor = ParallelLVRProcessor::new(CHUNK_START, CHUNK_START + 3 * CHUNK_BLOCKS, store.clone()).await.unwrap()
+        .with_source(Arc::new(HangingSource))
+        .with_parallel_chunks(2)
+        .with_cancellation(cancellation.clone());
+
+    let started = Instant::now();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        cancellation.cancel();
+    });
+    let error = tokio::time::timeout(Duration::from_secs(5), processor.process_blocks(None))
+        .await
+        .expect("the cancelled run kept waiting")
+        .unwrap_err();
+
+    assert!(started.elapsed() < Duration::from_secs(2), "{:?}", started.elapsed());
+    assert!(matches!(error.downcast_ref::<Error>(), Some(Error::Cancelled(_))), "{:#}", error);
+    assert!(!is_transient_error(&error));
+    assert!(store.paths().await.iter().all(|path| !path.starts_with("intervals/")));
+}
+
+#[tokio::test]
+async fn test_backfill_replaces_only_the_targeted_pools_rows() {
+    let registry = PoolRegistry::default();
+    let pools: Vec<String> = registry.pools().iter()
+        .filter(|pool| pool.deployment_block == 0)
+        .take(3)
+        .map(|pool| pool.address.clone())
+        .collect();
+    let (mut addresses, mut blocks, mut values) = (Vec::new(), Vec::new(), Vec::new());
+    for (p, pool) in pools.iter().enumerate() {
+        for i in 0..10u64 {
+            addresses.push(pool.clone());
+            blocks.push(CHUNK_START + i * 21_000 + p as u64);
+            values.push(400 + i as i64 * 53 - p as i64 * 90);
+        }
+    }
+    let theoretical = theoretical_batch(itertools::izip!(addresses.clone(), blocks, std::iter::repeat(0.5), values));
+    let realized = empty_realized_batch();
+    let end_block = CHUNK_START + CHUNK_BLOCKS;
+
+    let store = Arc::new(TestStore::new());
+    ParallelLVRProcessor::new(CHUNK_START, end_block, store.clone()).await.unwrap()
+        .with_source(parquet_source(&theoretical, &realized).await)
+        .process_blocks(None).await.unwrap();
+    let path = interval_path(CHUNK_START, end_block);
+    let stored_rows = |store: Arc<TestStore>| {
+        let path = path.clone();
+        async move {
+            let mut rows = Vec::new();
+            for batch in read_parquet(store.as_ref(), path.as_ref()).await {
+                rows.extend(IntervalData::from_record_batch(&batch).unwrap());
+            }
+            rows
+        }
+    };
+    let processed = stored_rows(store.clone()).await;
+    let target = pools[0].to_lowercase();
+    assert!(processed.iter().any(|row| row.pair_address.to_lowercase() == target && row.total_lvr_cents.0 != 0));
+    let severity = Validator::new(store.clone()).validate_all().await.unwrap().severity();
+
+    // Corrupt the first pool's rows, as a bad source day would have
+    let corrupted: Vec<IntervalData> = processed.iter().cloned()
+        .map(|row| if row.pair_address.to_lowercase() == target {
+            IntervalData { total_lvr_cents: Cents(row.total_lvr_cents.0 + 10_000), ..row }
+        } else {
+            row
+        })
+        .collect();
+    ParallelParquetWriter::new(store.clone()).write_interval_data(corrupted.clone(), CHUNK_START, end_block).await.unwrap();
+    let checkpoints: Vec<String> = store.paths().await.into_iter().filter(|p| p.starts_with("checkpoints/")).collect();
+    let checkpoint_contents = futures::future::join_all(checkpoints.iter().map(|p| read_parquet(store.as_ref(), p))).await;
+    assert!(Validator::new(store.clone()).validate_all().await.unwrap().severity() > severity);
+
+    ParallelLVRProcessor::new(CHUNK_START, end_block, store.clone()).await.unwrap()
+        .with_source(parquet_source(&theoretical, &realized).await)
+        .with_pool_registry(Arc::new(registry.subset(&pools[..1]).unwrap()))
+        .with_precompute_pool_registry(Arc::new(registry.clone()))
+        .with_backfill(true)
+        .process_blocks(None).await.unwrap();
+
+    let backfilled = stored_rows(store.clone()).await;
+    assert_eq!(backfilled, processed);
+    let others = |rows: &[IntervalData]| -> Vec<IntervalData> {
+        rows.iter().filter(|row| row.pair_address.to_lowercase() != target).cloned().collect()
+    };
+    assert_eq!(others(&backfilled), others(&corrupted));
+    assert_eq!(
+        futures::future::join_all(checkpoints.iter().map(|p| read_parquet(store.as_ref(), p))).await,
+        checkpoint_contents
+    );
+    assert_eq!(Validator::new(store.clone()).validate_all().await.unwrap().severity(), severity);
+
+    // Ranges off the processed chunk grid have no interval file to merge into
+    let misaligned = ParallelLVRProcessor::new(CHUNK_START + 7_200, end_block, store.clone()).await.unwrap()
+        .with_source(parquet_source(&theoretical, &realized).await)
+        .with_pool_registry(Arc::new(registry.subset(&pools[..1]).unwrap()))
+        .with_backfill(true)
+        .process_blocks(None).await;
+    assert!(format!("{:#}", misaligned.unwrap_err()).contains("does not exist"));
+}
+
+#[tokio::test]
+async fn test_precompute_after_a_run_covers_the_whole_registry() {
+    use arrow::array::StringArray;
+
+    let default_registry = PoolRegistry::default();
+    let known = default_registry.pools().iter().find(|pool| pool.deployment_block == 0).unwrap().clone();
+    let added = PoolEntry {
+        address: "0x00000000000000000000000000000000000000ab".to_string(),
+        name: "FOO-WETH-30bps".to_string(),
+        cluster: None,
+        ..known.clone()
+    };
+    let registry = Arc::new(PoolRegistry::new(
+        default_registry.pools().iter().cloned().chain([added.clone()]).collect()
+    ).unwrap());
+
+    let addresses: Vec<String> = (0..10).flat_map(|_| [known.address.clone(), added.address.clone()]).collect();
+    let blocks: Vec<u64> = (0..addresses.len() as u64).map(|i| CHUNK_START + i * 9_001).collect();
+    let theoretical = theoretical_batch(itertools::izip!(addresses.clone(), blocks, std::iter::repeat(0.5), std::iter::repeat(250i64)));
+    let realized = empty_realized_batch();
+    let end_block = CHUNK_START + CHUNK_BLOCKS;
+
+    let totalled_pools = |store: Arc<TestStore>| async move {
+        let mut pools = Vec::new();
+        for batch in read_parquet(store.as_ref(), DatasetKind::PoolTotals.path().as_ref()).await {
+            let column = batch.column_by_name("pool_address").unwrap().as_any().downcast_ref::<StringArray>().unwrap();
+            pools.extend(column.iter().flatten().map(str::to_lowercase));
+        }
+        pools
+    };
+
+    let store = Arc::new(TestStore::new());
+    ParallelLVRProcessor::new(CHUNK_START, end_block, store.clone()).await.unwrap()
+        .with_source(parquet_source(&theoretical, &realized).await)
+        .with_pool_registry(registry.clone())
+        .process_blocks(None).await.unwrap();
+    assert!(totalled_pools(store.clone()).await.contains(&added.address));
+
+    // A backfill of the known pool still precomputes the added one
+    ParallelLVRProcessor::new(CHUNK_START, end_block, store.clone()).await.unwrap()
+        .with_source(parquet_source(&theoretical, &realized).await)
+        .with_pool_registry(Arc::new(registry.subset(std::slice::from_ref(&known.address)).unwrap()))
+        .with_precompute_pool_registry(registry.clone())
+        .with_backfill(true)
+        .process_blocks(None).await.unwrap();
+    let pools = totalled_pools(store.clone()).await;
+    assert!(pools.contains(&added.address), "{:?}", pools);
+    assert!(pools.contains(&known.address.to_lowercase()));
+}
+
+#[tokio::test]
+async fn test_hive_layout_run_reads_like_a_flat_one() {
+    use arrow::array::ArrayRef;
+    use arrow::record_batch::RecordBatch;
+    use axum::extract::State;
+
+    let registry = PoolRegistry::default();
+    let pools: Vec<String> = registry.pools().iter()
+        .filter(|pool| pool.deployment_block == 0)
+        .take(2)
+        .map(|pool| pool.address.clone())
+        .collect();
+    let end_block = CHUNK_START + 2 * CHUNK_BLOCKS;
+    let (mut addresses, mut blocks, mut values) = (Vec::new(), Vec::new(), Vec::new());
+    for (p, pool) in pools.iter().enumerate() {
+        for i in 0..20u64 {
+            addresses.push(pool.clone());
+            blocks.push(CHUNK_START + i * 21_000 + p as u64);
+            values.push(300 + i as i64 * 41 - p as i64 * 70);
+        }
+    }
+    let theoretical = theoretical_batch(itertools::izip!(addresses.clone(), blocks, std::iter::repeat(0.5), values));
+    let realized = empty_realized_batch();
+
+    let run = |layout: OutputLayout| {
+        let (theoretical, realized) = (theoretical.clone(), realized.clone());
+        async move {
+            let store = Arc::new(TestStore::new());
+            ParallelLVRProcessor::new(CHUNK_START, end_block, store.clone()).await.unwrap()
+                .with_source(parquet_source(&theoretical, &realized).await)
+                .with_layout(layout)
+                .process_blocks(None).await.unwrap();
+            store
+        }
+    };
+    let flat = run(OutputLayout::Flat).await;
+    let hive = run(OutputLayout::Hive).await;
+
+    let intervals: Vec<String> = hive.paths().await.into_iter().filter(|p| p.starts_with("intervals/")).collect();
+    assert_eq!(intervals, vec![
+        OutputLayout::Hive.interval_path(CHUNK_START, CHUNK_START + CHUNK_BLOCKS).to_string(),
+        OutputLayout::Hive.interval_path(CHUNK_START + CHUNK_BLOCKS, end_block).to_string(),
+    ]);
+    assert!(intervals[0].starts_with("intervals/year=2022/month=09/"));
+    assert!(intervals[1].starts_with("intervals/year=2022/month=10/"));
+
+    // Precompute reads the same rows either way; footers differ by run
+    let precomputed: Vec<String> = flat.paths().await.into_iter().filter(|p| p.starts_with("precomputed/")).collect();
+    assert!(precomputed.contains(&DatasetKind::AggregateRunningTotals.path().to_string()));
+    assert_eq!(hive.paths().await.into_iter().filter(|p| p.starts_with("precomputed/")).collect::<Vec<_>>(), precomputed);
+    for path in &precomputed {
+        let columns = |batches: Vec<RecordBatch>| -> Vec<Vec<ArrayRef>> {
+            batches.iter().map(|batch| batch.columns().to_vec()).collect()
+        };
+        assert_eq!(
+            columns(read_parquet(hive.as_ref(), path).await),
+            columns(read_parquet(flat.as_ref(), path).await),
+            "{}", path
+        );
+    }
+
+    let validator = Validator::new(hive.clone()).with_layout(OutputLayout::Hive);
+    assert!(validator.check_interval_coverage().await.unwrap().is_complete());
+    let report = validator.validate_all().await.unwrap();
+    assert_eq!(report.severity(), Validator::new(flat.clone()).validate_all().await.unwrap().severity());
+    assert!(report.objects.stray.is_empty(), "{:?}", report.objects.stray);
+
+    let quartiles = |store: Arc<TestStore>| async move {
+        get_quartile_plot(
+            State(Arc::new(AppState::new(store))),
+            ApiQuery(QuartilePlotQuery { pool_address: None, markout_time: None, aggregate: Some(true) }),
+        ).await.unwrap().0.median_cents
+    };
+    assert_eq!(quartiles(hive.clone()).await, quartiles(flat.clone()).await);
+}
+
+#[tokio::test]
+async fn test_failed_puts_are_retried_without_retrying_the_chunk() {
+    let store = Arc::new(TestStore::new().fail_first_put_of_each_object());
+    let instant = |attempts| RetryPolicy::new(attempts).with_base_delay(std::time::Duration::ZERO);
+    let retry_config = RetryConfig { chunk: instant(3), write: instant(3), ..RetryConfig::default() };
+    ParallelLVRProcessor::new(CHUNK_START, CHUNK_START + CHUNK_BLOCKS, store.clone()).await.unwrap()
+        .with_source(Arc::new(SyntheticSource { stride: 997, buffered: true }))
+        .with_retry_config(retry_config)
+        .process_blocks(None).await.unwrap();
+
+    let summary = read_chunk_summaries(store.as_ref()).await.unwrap().remove(0);
+    assert_eq!(summary.retries, 0);
+    // The interval file and every checkpoint the chunk wrote failed once
+    let checkpoints = store.paths().await.iter().filter(|p| p.starts_with("checkpoints/")).count() as u64;
+    assert_eq!(summary.write_retries, 1 + checkpoints);
+    let intervals = read_parquet(store.as_ref(), interval_path(CHUNK_START, CHUNK_START + CHUNK_BLOCKS).as_ref()).await;
+    assert!(intervals.iter().map(|batch| batch.num_rows()).sum::<usize>() > 0);
+}
+
+#[tokio::test]
+async fn test_a_run_after_compaction_keeps_other_pools_checkpoints() {
+    let registry = PoolRegistry::default();
+    let pools: Vec<String> = registry.pools().iter()
+        .filter(|pool| pool.deployment_block == 0)
+        .take(2)
+        .map(|pool| pool.address.clone())
+        .collect();
+    let theoretical = theoretical_batch(pools.iter().map(|pool| (pool.clone(), CHUNK_START + 3, 0.5, 1_200)));
+    let realized = empty_realized_batch();
+    let source = parquet_source(&theoretical, &realized).await;
+
+    let store = Arc::new(TestStore::new());
+    ParallelLVRProcessor::new(CHUNK_START, CHUNK_START + CHUNK_BLOCKS, store.clone()).await.unwrap()
+        .with_source(source.clone())
+        .process_blocks(None).await.unwrap();
+    compact_checkpoints(store.as_ref(), &ParallelParquetWriter::new(store.clone()), false).await.unwrap();
+
+    // A later range for the first pool only, without --resume
+    ParallelLVRProcessor::new(CHUNK_START + CHUNK_BLOCKS, CHUNK_START + 2 * CHUNK_BLOCKS, store.clone()).await.unwrap()
+        .with_source(source)
+        .with_pool_registry(Arc::new(registry.subset(&pools[..1]).unwrap()))
+        .process_blocks(None).await.unwrap();
+
+    let paths = store.paths().await;
+    assert!(!paths.contains(&CONSOLIDATED_CHECKPOINTS_PATH.to_string()));
+    let other = checkpoint_path(&pools[1], MarkoutTime::from_f64(0.5).unwrap());
+    let snapshot = CheckpointSnapshot::from_record_batch(&read_parquet(store.as_ref(), other.as_ref()).await[0]).unwrap().remove(0);
+    assert_eq!(snapshot.running_total, Cents(1_200));
+    assert_eq!(snapshot.last_updated_block, CHUNK_START + CHUNK_BLOCKS - 1);
+}
+
+#[tokio::test]
+async fn test_compacted_checkpoints_read_like_per_file_ones() {
+    use arrow::array::ArrayRef;
+    use arrow::record_batch::RecordBatch;
+    use axum::extract::State;
+    use object_store::ObjectStore;
+
+    let run = |end_block: u64, store: Arc<TestStore>| async move {
+        ParallelLVRProcessor::new(CHUNK_START, end_block, store.clone()).await.unwrap()
+            .with_source(Arc::new(SyntheticSource { stride: 997, buffered: true }))
+            .with_resume(true)
+            .process_blocks(None).await.unwrap();
+    };
+    let (per_file, compacted) = (Arc::new(TestStore::new()), Arc::new(TestStore::new()));
+    run(CHUNK_START + CHUNK_BLOCKS, per_file.clone()).await;
+    // Copied rather than run again, so both stores hold the same bytes
+    for path in per_file.paths().await {
+        let path = object_store::path::Path::from(path);
+        let bytes = per_file.get(&path).await.unwrap().bytes().await.unwrap();
+        compacted.put(&path, bytes.into()).await.unwrap();
+    }
+
+    let checkpoint_files = |store: Arc<TestStore>| async move {
+        store.paths().await.into_iter().filter(|p| p.starts_with("checkpoints/")).collect::<Vec<_>>()
+    };
+    let stored = checkpoint_files(per_file.clone()).await;
+    let writer = ParallelParquetWriter::new(compacted.clone());
+    assert_eq!(compact_checkpoints(compacted.as_ref(), &writer, false).await.unwrap(), stored.len());
+    assert_eq!(checkpoint_files(compacted.clone()).await, vec![CONSOLIDATED_CHECKPOINTS_PATH.to_string()]);
+
+    // Each reader of the consolidated file heads and gets it instead of
+    // listing and getting every per-file checkpoint; the one listing left
+    // is the input fingerprint
+    let generated_at = chrono::Utc::now();
+    let precompute = |store: Arc<TestStore>| async move {
+        let (gets, lists) = (store.gets().len(), store.lists().len());
+        PrecomputedWriter::new(store.clone()).with_generated_at(generated_at).run_all(4).await.unwrap();
+        let reads = store.gets()[gets..].iter().filter(|p| p.starts_with("checkpoints/")).count();
+        let lists = store.lists()[lists..].iter().filter(|p| p.starts_with("checkpoints")).count();
+        (reads, lists)
+    };
+    let (per_file_reads, per_file_lists) = precompute(per_file.clone()).await;
+    let (compacted_reads, compacted_lists) = precompute(compacted.clone()).await;
+    assert!(per_file_lists > 1);
+    assert_eq!((compacted_reads, compacted_lists), (2 * (per_file_lists - 1), 1));
+    assert!(per_file_reads > 10 * compacted_reads, "{} reads", per_file_reads);
+
+    let columns = |batches: Vec<RecordBatch>| -> Vec<Vec<ArrayRef>> {
+        batches.iter().map(|batch| batch.columns().to_vec()).collect()
+    };
+    let precomputed: Vec<String> = per_file.paths().await.into_iter().filter(|p| p.starts_with("precomputed/")).collect();
+    assert!(precomputed.contains(&DatasetKind::PoolTotals.path().to_string()));
+    for path in &precomputed {
+        assert_eq!(
+            columns(read_parquet(compacted.as_ref(), path).await),
+            columns(read_parquet(per_file.as_ref(), path).await),
+            "{}", path
+        );
+    }
+    let pool_totals = |store: Arc<TestStore>| async move {
+        let response = get_pool_totals(
+            State(Arc::new(AppState::new(store))),
+            ApiQuery(PoolTotalsQuery { markout_time: None }),
+        ).await.unwrap();
+        serde_json::to_value(response.0).unwrap()
+    };
+    assert_eq!(pool_totals(compacted.clone()).await, pool_totals(per_file.clone()).await);
+
+    let report = Validator::new(compacted.clone()).validate_all().await.unwrap();
+    assert_eq!(report.severity(), Validator::new(per_file.clone()).validate_all().await.unwrap().severity());
+    assert!(report.objects.stray.is_empty(), "{:?}", report.objects.stray);
+
+    // A resumed run writes the consolidated checkpoints back out, so the
+    // stale consolidated file can go
+    run(CHUNK_START + 2 * CHUNK_BLOCKS, per_file.clone()).await;
+    run(CHUNK_START + 2 * CHUNK_BLOCKS, compacted.clone()).await;
+    let resumed = checkpoint_files(compacted.clone()).await;
+    assert_eq!(resumed, checkpoint_files(per_file.clone()).await);
+    assert!(stored.iter().all(|path| resumed.contains(path)));
+    for path in &resumed {
+        let snapshot = |store: Arc<TestStore>| async move {
+            let snapshot = CheckpointSnapshot::from_record_batch(&read_parquet(store.as_ref(), path).await[0]).unwrap().remove(0);
+            (snapshot.running_total, snapshot.non_zero_samples, snapshot.last_updated_block, snapshot.top_lvr)
+        };
+        assert_eq!(snapshot(compacted.clone()).await, snapshot(per_file.clone()).await, "{}", path);
+    }
+}
@@ -0,0 +1,269 @@
+use super::support::{read_parquet, TestStore};
+use crate::*;
+use object_store::{path::Path, ObjectStore};
+use std::{sync::Arc, time::Duration};
+
+fn fast_policy(max_attempts: u32) -> RetryPolicy {
+    RetryPolicy::new(max_attempts).with_base_delay(Duration::from_millis(1))
+}
+
+#[tokio::test]
+async fn test_retry_put_recovers_from_transient_failures() {
+    let store = TestStore::new();
+    store.fail_next_puts(2);
+    let path = Path::from("objects/a");
+
+    retry_put(&store, &path, bytes::Bytes::from_static(b"payload"), &fast_policy(5)).await.unwrap();
+
+    assert_eq!(store.put_attempts(), 3);
+    let stored = store.get(&path).await.unwrap().bytes().await.unwrap();
+    assert_eq!(stored.as_ref(), b"payload");
+}
+
+#[tokio::test]
+async fn test_retry_put_stops_at_max_attempts() {
+    let store = TestStore::new();
+    store.fail_next_puts(10);
+
+    let result = retry_put(&store, &Path::from("objects/a"), bytes::Bytes::new(), &fast_policy(4)).await;
+
+    assert!(matches!(result, Err(object_store::Error::Generic { .. })));
+    assert_eq!(store.put_attempts(), 4);
+}
+
+#[tokio::test]
+async fn test_retry_put_does_not_retry_permanent_errors() {
+    let store = TestStore::new().deny_puts();
+
+    let result = retry_put(&store, &Path::from("objects/a"), bytes::Bytes::new(), &fast_policy(5)).await;
+
+    assert!(matches!(result, Err(object_store::Error::PermissionDenied { .. })));
+    assert_eq!(store.put_attempts(), 1);
+}
+
+#[tokio::test]
+async fn test_precomputed_writer_does_not_retry_permanent_errors() {
+    let store = Arc::new(TestStore::new().deny_puts());
+    let writer = PrecomputedWriter::new(store.clone()).with_retry_policy(fast_policy(5));
+
+    assert!(writer.write_pool_totals().await.is_err());
+    assert_eq!(store.put_attempts(), 1);
+}
+
+#[tokio::test]
+async fn test_checkpoint_writes_use_the_configured_attempts() {
+    let store = Arc::new(TestStore::new());
+    let writer = ParallelParquetWriter::new(store.clone()).with_retry_policy(fast_policy(5));
+    let checkpoint = Checkpoint::new(POOL_ADDRESSES[0].to_lowercase(), MarkoutTime::Brontes);
+    store.fail_next_puts(4);
+
+    writer.write_checkpoints(vec![checkpoint.to_snapshot()]).await.unwrap();
+
+    assert_eq!(store.put_attempts(), 5);
+}
+
+#[test]
+fn test_retry_delay_backoff_and_jitter_bounds() {
+    let policy = RetryPolicy::new(10)
+        .with_base_delay(Duration::from_millis(100))
+        .with_max_delay(Duration::from_millis(1000))
+        .with_jitter(0.5);
+
+    let expected = [100, 200, 400, 800, 1000, 1000];
+    for (retry, millis) in (1..).zip(expected) {
+        let backoff = Duration::from_millis(millis);
+        assert_eq!(policy.backoff(retry), backoff);
+        for _ in 0..200 {
+            let delay = policy.delay(retry);
+            assert!(delay >= backoff.mul_f64(0.5) && delay <= backoff.mul_f64(1.5), "{:?}", delay);
+        }
+    }
+
+    let exact = policy.with_jitter(0.0);
+    assert_eq!(exact.delay(3), Duration::from_millis(400));
+}
+
+#[tokio::test]
+async fn test_processing_errors_are_not_retried() {
+    let policy = RetryPolicy::new(5).with_base_delay(Duration::from_millis(10)).with_jitter(0.0);
+    let mut attempts = 0;
+
+    let result: anyhow::Result<()> = retry_with(&policy, "chunk", is_transient_error, || {
+        attempts += 1;
+        async { Err(anyhow::Error::from(Error::Processing("malformed pool configuration".to_string()))) }
+    }).await;
+
+    assert!(result.is_err());
+    assert_eq!(attempts, 1);
+}
+
+#[tokio::test]
+async fn test_connection_errors_retry_on_the_configured_schedule() {
+    let config = AppConfig::from_toml(
+        r#"
+        [retry.chunk]
+        max_attempts = 4
+        base_delay_secs = 0.02
+        jitter = 0.0
+        "#,
+    )
+    .unwrap();
+    let policy = config.retry.chunk;
+    assert_eq!(policy.max_attempts, 4);
+    assert_eq!(policy.base_delay, Duration::from_millis(20));
+    assert_eq!(config.retry.database, RetryConfig::default().database);
+    assert_eq!(config.retry.write, RetryConfig::default().write);
+
+    // Fails twice with a refused connection, wrapped the way the fetch paths
+    // add context, then succeeds
+    let mut attempts = 0;
+    let started = std::time::Instant::now();
+    let result = retry_with(&policy, "chunk", is_transient_error, || {
+        attempts += 1;
+        let attempt = attempts;
+        async move {
+            if attempt <= 2 {
+                let refused = std::io::Error::new(std::io::ErrorKind::ConnectionRefused, "connection refused");
+                return Err(anyhow::Error::new(refused).context("Failed to get connection from pool"));
+            }
+            Ok(attempt)
+        }
+    }).await;
+
+    assert_eq!(result.unwrap(), 3);
+    // 20ms before the second attempt, 40ms before the third
+    assert!(started.elapsed() >= Duration::from_millis(60));
+
+    assert!(is_transient_error(&Error::Database("timeout".to_string()).into()));
+    assert!(!is_transient_error(&anyhow::anyhow!("Invalid markout time")));
+    assert!(AppConfig::from_toml("[retry.database]\njitter = 1.5").is_err());
+}
+
+fn intervals(count: u64) -> Vec<IntervalData> {
+    (0..count)
+        .map(|i| IntervalData {
+            interval_id: i,
+            pair_address: POOL_ADDRESSES[0].to_string(),
+            markout_time: MarkoutTime::from_f64(MARKOUT_TIMES[0]).unwrap(),
+            total_lvr_cents: Cents(i as i64 * 100),
+            max_lvr_cents: 100,
+            non_zero_count: 1,
+            total_count: 7200,
+            start_block: i * 7200,
+            end_block: (i + 1) * 7200,
+            source_counts: std::collections::BTreeMap::new(),
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_interrupted_writes_never_leave_an_unreadable_object() {
+    let store = Arc::new(TestStore::new());
+    let writer = ParallelParquetWriter::new(store.clone()).with_retry_policy(fast_policy(1));
+    let path = interval_path(0, 21_600);
+
+    // Aborted before anything was in place: the final object never appears
+    store.truncate_next_puts(1);
+    assert!(writer.write_interval_data(intervals(3), 0, 21_600).await.is_err());
+    assert!(store.paths().await.is_empty(), "{:?}", store.paths().await);
+
+    // Aborted while overwriting: the previous object is still whole
+    writer.write_interval_data(intervals(3), 0, 21_600).await.unwrap();
+    store.truncate_next_puts(1);
+    assert!(writer.write_interval_data(intervals(2), 0, 21_600).await.is_err());
+    let rows = |batches: Vec<arrow::record_batch::RecordBatch>| batches.iter().map(|batch| batch.num_rows()).sum::<usize>();
+    assert_eq!(rows(read_parquet(store.as_ref(), path.as_ref()).await), 3);
+    assert_eq!(store.paths().await, vec![path.to_string()], "temporary object left behind");
+
+    // With retries the interrupted upload is simply repeated
+    let writer = ParallelParquetWriter::new(store.clone()).with_retry_policy(fast_policy(3));
+    store.truncate_next_puts(1);
+    writer.write_interval_data(intervals(2), 0, 21_600).await.unwrap();
+    assert_eq!(rows(read_parquet(store.as_ref(), path.as_ref()).await), 2);
+}
+
+#[tokio::test]
+async fn test_corrupt_objects_are_reported_by_path() {
+    let store = Arc::new(TestStore::new());
+    let path = interval_path(0, 21_600);
+    store.put(&path, bytes::Bytes::from_static(b"PAR1 truncated").into()).await.unwrap();
+
+    let error = PrecomputedWriter::new(store.clone()).write_running_totals().await.unwrap_err();
+    let message = format!("{:#}", error);
+    assert!(message.contains(path.as_ref()), "{}", message);
+    assert!(message.contains("corrupt footer"), "{}", message);
+}
+
+#[tokio::test]
+async fn test_single_pool_reads_skip_other_pools_row_groups() {
+    let pools: Vec<String> = (0..200).map(|i| format!("0x{:040x}", 2 * i)).collect();
+    let mut rows = Vec::new();
+    for pool in &pools {
+        for markout_time in [MarkoutTime::Brontes, MarkoutTime::Zero] {
+            rows.extend(intervals(90).into_iter().map(|row| IntervalData {
+                pair_address: pool.clone(),
+                markout_time,
+                ..row
+            }));
+        }
+    }
+    let store = Arc::new(TestStore::new());
+    let options = ParquetWriteOptions { max_row_group_size: 900, ..ParquetWriteOptions::default() };
+    let writer = ParallelParquetWriter::new(store.clone()).with_write_options(options);
+    // Shuffled by pool, so only the writer's sort groups them
+    rows.reverse();
+    writer.write_interval_data(rows, 0, 216_000).await.unwrap();
+
+    let path = interval_path(0, 216_000);
+    let size = store.head(&path).await.unwrap().size;
+    let columns = read_parquet(store.as_ref(), path.as_ref()).await[0].num_columns();
+    let before = store.bytes_read();
+    let matching = read_matching_rows(store.clone(), &path, &[
+        (INTERVAL_PAIR_ADDRESS_COLUMN, pools[123].as_str()),
+        (INTERVAL_MARKOUT_TIME_COLUMN, "brontes"),
+    ]).await.unwrap();
+    let read = store.bytes_read() - before;
+
+    let rows: usize = matching.batches.iter().map(|batch| batch.num_rows()).sum();
+    assert_eq!(rows, 90);
+    assert_eq!(matching.batches[0].num_columns(), columns);
+    for batch in &matching.batches {
+        let read_pools = batch.column_by_name(INTERVAL_PAIR_ADDRESS_COLUMN).unwrap();
+        let read_pools = read_pools.as_any().downcast_ref::<arrow::array::StringArray>().unwrap();
+        assert!(read_pools.iter().all(|pool| pool == Some(pools[123].as_str())));
+    }
+    assert_eq!(matching.row_groups_read, 1);
+    assert!(read * 4 < size, "read {} of {} bytes", read, size);
+
+    // A pool the file does not hold, between two that it does, is ruled
+    // out by the bloom filters where statistics cannot
+    let absent = format!("0x{:040x}", 2 * 123 + 1);
+    let none = read_matching_rows(store.clone(), &path, &[(INTERVAL_PAIR_ADDRESS_COLUMN, absent.as_str())]).await.unwrap();
+    assert_eq!(none.row_groups_read, 0);
+    assert!(none.batches.iter().all(|batch| batch.num_rows() == 0));
+}
+
+#[test]
+fn test_pooled_encoding_matches_a_fresh_buffer_and_reuses_it() {
+    let pool = BufferPool::new();
+    let props = ParquetWriteOptions::default().writer_properties();
+    let checkpoints: Vec<_> = (0..3)
+        .map(|i| {
+            let checkpoint = Checkpoint::new(format!("0x{:040x}", i), MarkoutTime::Brontes);
+            checkpoint.update_max_lvr(15_537_393 + i, 100 * i + 1);
+            checkpoint.to_snapshot()
+        })
+        .collect();
+
+    for checkpoint in &checkpoints {
+        let batch = CheckpointSnapshot::to_record_batch(std::slice::from_ref(checkpoint)).unwrap();
+        let mut fresh = Vec::new();
+        let mut writer = parquet::arrow::ArrowWriter::try_new(&mut fresh, batch.schema(), Some(props.clone())).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+
+        let pooled = pool.encode_parquet(&batch, props.clone()).unwrap();
+        assert_eq!(pooled.as_ref(), fresh.as_slice());
+        assert_eq!(pool.pooled(), 1, "the one scratch buffer is returned for the next file");
+    }
+}
@@ -0,0 +1,357 @@
+use async_trait::async_trait;
+use futures::stream::{BoxStream, StreamExt};
+use object_store::{
+    memory::InMemory, path::Path, GetOptions, GetResult, ListResult, MultipartUpload, ObjectMeta,
+    ObjectStore, PutMultipartOpts, PutOptions, PutPayload, PutResult, Result,
+};
+use std::{
+    fmt,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+/// In-memory object store with injectable latency and failures, used to
+/// exercise writer and precompute behaviour without touching disk.
+#[derive(Debug, Default)]
+pub struct TestStore {
+    inner: InMemory,
+    latency: Duration,
+    fail_list_prefixes: Vec<String>,
+    truncated_puts: AtomicUsize,
+    failed_puts: AtomicUsize,
+    deny_puts: bool,
+    fail_first_puts: bool,
+    /// Objects whose first put `fail_first_put_of_each_object` has failed
+    failed_objects: std::sync::Mutex<std::collections::HashSet<String>>,
+    put_attempts: AtomicUsize,
+    puts: std::sync::Mutex<Vec<String>>,
+    gets: std::sync::Mutex<Vec<String>>,
+    lists: std::sync::Mutex<Vec<String>>,
+    bytes_read: AtomicUsize,
+    puts_in_flight: AtomicUsize,
+    peak_puts_in_flight: AtomicUsize,
+}
+
+impl TestStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every get, put and list waits this long before reaching the inner store
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Listing any path under `prefix` returns an error
+    pub fn fail_list(mut self, prefix: &str) -> Self {
+        self.fail_list_prefixes.push(prefix.to_string());
+        self
+    }
+
+    /// The next `count` puts store only the first half of their payload and
+    /// then report an error, as an interrupted upload would
+    pub fn truncate_next_puts(&self, count: usize) {
+        self.truncated_puts.store(count, Ordering::SeqCst);
+    }
+
+    /// The next `count` puts fail with a transient error and store nothing
+    pub fn fail_next_puts(&self, count: usize) {
+        self.failed_puts.store(count, Ordering::SeqCst);
+    }
+
+    /// Every put fails with a permission error, which is never worth retrying
+    pub fn deny_puts(mut self) -> Self {
+        self.deny_puts = true;
+        self
+    }
+
+    /// The first put of every object fails with a transient error. A
+    /// temporary upload counts as a put of the object it is moved to.
+    pub fn fail_first_put_of_each_object(mut self) -> Self {
+        self.fail_first_puts = true;
+        self
+    }
+
+    /// Number of puts attempted so far, including failed ones
+    pub fn put_attempts(&self) -> usize {
+        self.put_attempts.load(Ordering::SeqCst)
+    }
+
+    /// Paths of every put attempted so far, in order
+    pub fn puts(&self) -> Vec<String> {
+        self.puts.lock().unwrap().clone()
+    }
+
+    /// Paths of every get so far, in order
+    pub fn gets(&self) -> Vec<String> {
+        self.gets.lock().unwrap().clone()
+    }
+
+    /// Prefix of every `list` call so far, empty for the whole store
+    pub fn lists(&self) -> Vec<String> {
+        self.lists.lock().unwrap().clone()
+    }
+
+    /// Most puts that were waiting out the latency at the same time
+    pub fn peak_puts_in_flight(&self) -> usize {
+        self.peak_puts_in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Bytes served by every get so far, ranged reads included
+    pub fn bytes_read(&self) -> usize {
+        self.bytes_read.load(Ordering::SeqCst)
+    }
+
+    /// Paths of every object currently held by the store
+    pub async fn paths(&self) -> Vec<String> {
+        self.inner
+            .list(None)
+            .map(|meta| meta.unwrap().location.to_string())
+            .collect()
+            .await
+    }
+
+    async fn delay(&self) {
+        if !self.latency.is_zero() {
+            tokio::time::sleep(self.latency).await;
+        }
+    }
+
+    fn injected(path: &str) -> object_store::Error {
+        object_store::Error::Generic {
+            store: "TestStore",
+            source: format!("injected failure for {}", path).into(),
+        }
+    }
+}
+
+impl fmt::Display for TestStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TestStore")
+    }
+}
+
+#[async_trait]
+impl ObjectStore for TestStore {
+    async fn put_opts(&self, location: &Path, payload: PutPayload, opts: PutOptions) -> Result<PutResult> {
+        let in_flight = self.puts_in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.peak_puts_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+        self.delay().await;
+        self.puts_in_flight.fetch_sub(1, Ordering::SeqCst);
+        self.put_attempts.fetch_add(1, Ordering::SeqCst);
+        self.puts.lock().unwrap().push(location.to_string());
+
+        if self.deny_puts {
+            return Err(object_store::Error::PermissionDenied {
+                path: location.to_string(),
+                source: "puts denied by TestStore".into(),
+            });
+        }
+        let object = location.as_ref().split(crate::TEMPORARY_OBJECT_MARKER).next().unwrap_or_default();
+        let fail = self
+            .failed_puts
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok()
+            || (self.fail_first_puts && self.failed_objects.lock().unwrap().insert(object.to_string()));
+        if fail {
+            return Err(Self::injected(location.as_ref()));
+        }
+
+        let truncate = self
+            .truncated_puts
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok();
+        if truncate {
+            let bytes = bytes::Bytes::from(payload);
+            let partial = bytes.slice(..bytes.len() / 2);
+            self.inner.put_opts(location, partial.into(), opts).await?;
+            return Err(Self::injected(location.as_ref()));
+        }
+
+        self.inner.put_opts(location, payload, opts).await
+    }
+
+    async fn put_multipart_opts(&self, location: &Path, opts: PutMultipartOpts) -> Result<Box<dyn MultipartUpload>> {
+        self.inner.put_multipart_opts(location, opts).await
+    }
+
+    async fn get_opts(&self, location: &Path, options: GetOptions) -> Result<GetResult> {
+        self.delay().await;
+        self.gets.lock().unwrap().push(location.to_string());
+        let head = options.head;
+        let result = self.inner.get_opts(location, options).await?;
+        if !head {
+            self.bytes_read.fetch_add(result.range.end - result.range.start, Ordering::SeqCst);
+        }
+        Ok(result)
+    }
+
+    async fn delete(&self, location: &Path) -> Result<()> {
+        self.inner.delete(location).await
+    }
+
+    fn list(&self, prefix: Option<&Path>) -> BoxStream<'_, Result<ObjectMeta>> {
+        let prefix_str = prefix.map(|p| p.to_string()).unwrap_or_default();
+        self.lists.lock().unwrap().push(prefix_str.clone());
+        if self.fail_list_prefixes.iter().any(|p| prefix_str.starts_with(p.as_str())) {
+            return futures::stream::once(async move { Err(Self::injected(&prefix_str)) }).boxed();
+        }
+
+        let inner = self.inner.list(prefix);
+        let latency = self.latency;
+        futures::stream::once(tokio::time::sleep(latency))
+            .flat_map(move |_| futures::stream::empty())
+            .chain(inner)
+            .boxed()
+    }
+
+    async fn list_with_delimiter(&self, prefix: Option<&Path>) -> Result<ListResult> {
+        self.delay().await;
+        self.inner.list_with_delimiter(prefix).await
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> Result<()> {
+        self.inner.copy(from, to).await
+    }
+
+    async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> Result<()> {
+        self.inner.copy_if_not_exists(from, to).await
+    }
+}
+
+/// Global allocator that, while `track_allocations` is running on a thread,
+/// records the peak number of live bytes allocated by that thread.
+pub struct TrackingAllocator;
+
+thread_local! {
+    static TRACKING: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+    static LIVE_BYTES: std::cell::Cell<isize> = const { std::cell::Cell::new(0) };
+    static PEAK_BYTES: std::cell::Cell<isize> = const { std::cell::Cell::new(0) };
+}
+
+unsafe impl std::alloc::GlobalAlloc for TrackingAllocator {
+    unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+        let ptr = std::alloc::System.alloc(layout);
+        if !ptr.is_null() && TRACKING.get() {
+            let live = LIVE_BYTES.get() + layout.size() as isize;
+            LIVE_BYTES.set(live);
+            PEAK_BYTES.set(PEAK_BYTES.get().max(live));
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+        if TRACKING.get() {
+            LIVE_BYTES.set(LIVE_BYTES.get() - layout.size() as isize);
+        }
+        std::alloc::System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: TrackingAllocator = TrackingAllocator;
+
+/// Runs `future` to completion on the current thread and returns its output
+/// with the peak bytes it held live at once. Callers must use a
+/// current-thread runtime so every allocation is observed.
+pub async fn track_allocations<F: std::future::Future>(future: F) -> (F::Output, usize) {
+    LIVE_BYTES.set(0);
+    PEAK_BYTES.set(0);
+    TRACKING.set(true);
+    let output = future.await;
+    TRACKING.set(false);
+    (output, PEAK_BYTES.get().max(0) as usize)
+}
+
+/// Reads every record batch of the parquet object at `path`
+pub async fn read_parquet(store: &dyn ObjectStore, path: &str) -> Vec<arrow::record_batch::RecordBatch> {
+    let bytes = store.get(&Path::from(path)).await.unwrap().bytes().await.unwrap();
+    parquet::arrow::arrow_reader::ParquetRecordBatchReader::try_new(bytes, 1024)
+        .unwrap()
+        .map(|batch| batch.unwrap())
+        .collect()
+}
+
+/// A theoretical dump batch of `(pool_address, block_number, markout_time, lvr_cents)` rows
+pub fn theoretical_batch<S: Into<String>>(
+    rows: impl IntoIterator<Item = (S, u64, f64, i64)>,
+) -> arrow::record_batch::RecordBatch {
+    use arrow::array::{ArrayRef, Float64Array, Int64Array, StringArray, UInt64Array};
+
+    let (mut pools, mut blocks, mut markouts, mut values) = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    for (pool, block, markout, value) in rows {
+        pools.push(pool.into());
+        blocks.push(block);
+        markouts.push(markout);
+        values.push(value);
+    }
+    arrow::record_batch::RecordBatch::try_from_iter([
+        ("pool_address", std::sync::Arc::new(StringArray::from(pools)) as ArrayRef),
+        ("block_number", std::sync::Arc::new(UInt64Array::from(blocks)) as ArrayRef),
+        ("markout_time", std::sync::Arc::new(Float64Array::from(markouts)) as ArrayRef),
+        ("lvr_cents", std::sync::Arc::new(Int64Array::from(values)) as ArrayRef),
+    ])
+    .unwrap()
+}
+
+/// A realized dump batch of `(pool_address, block_number, lvr_cents)` rows
+pub fn realized_batch<S: Into<String>>(rows: impl IntoIterator<Item = (S, u64, i64)>) -> arrow::record_batch::RecordBatch {
+    use arrow::array::{ArrayRef, Int64Array, StringArray, UInt64Array};
+
+    let (mut pools, mut blocks, mut values) = (Vec::new(), Vec::new(), Vec::new());
+    for (pool, block, value) in rows {
+        pools.push(pool.into());
+        blocks.push(block);
+        values.push(value);
+    }
+    arrow::record_batch::RecordBatch::try_from_iter([
+        ("pool_address", std::sync::Arc::new(StringArray::from(pools)) as ArrayRef),
+        ("block_number", std::sync::Arc::new(UInt64Array::from(blocks)) as ArrayRef),
+        ("lvr_cents", std::sync::Arc::new(Int64Array::from(values)) as ArrayRef),
+    ])
+    .unwrap()
+}
+
+/// A realized dump batch without rows, for runs on theoretical data only
+pub fn empty_realized_batch() -> arrow::record_batch::RecordBatch {
+    realized_batch(Vec::<(String, u64, i64)>::new())
+}
+
+/// What the tracing events of this thread wrote while it is in scope
+#[derive(Clone, Default)]
+pub struct LogCapture(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+impl LogCapture {
+    pub fn contents(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+impl std::io::Write for LogCapture {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Captures this thread's logs until the guard is dropped; with a
+/// current-thread runtime that includes the tasks it spawns
+pub fn capture_logs() -> (LogCapture, tracing::subscriber::DefaultGuard) {
+    let capture = LogCapture::default();
+    let writer = capture.clone();
+    let subscriber = tracing_subscriber::fmt().with_writer(move || writer.clone()).with_ansi(false).finish();
+    (capture, tracing::subscriber::set_default(subscriber))
+}
+
+/// Runs `f` and returns its output with what it logged
+pub fn captured_logs<T>(f: impl FnOnce() -> T) -> (T, String) {
+    let (capture, guard) = capture_logs();
+    let output = f();
+    drop(guard);
+    (output, capture.contents())
+}
@@ -85,28 +85,36 @@ pub mod tests {
         }
     }
 
+    /// `default`, or `LVR_TEST_SEED` when set, to explore other samples
+    fn test_seed(default: u64) -> u64 {
+        match std::env::var("LVR_TEST_SEED") {
+            Ok(seed) => seed.parse().expect("LVR_TEST_SEED must be an unsigned integer"),
+            Err(_) => default,
+        }
+    }
+
     // Helper functions to generate datasets
-    fn generate_normal_data(mean: f64, std_dev: f64, size: usize) -> (Vec<f64>, DataDistribution) {
+    fn generate_normal_data(mean: f64, std_dev: f64, size: usize, seed: u64) -> (Vec<f64>, DataDistribution) {
         let normal = Normal::new(mean, std_dev).unwrap();
-        let mut rng = thread_rng();
+        let mut rng = StdRng::seed_from_u64(seed);
         (
             normal.sample_iter(&mut rng).take(size).collect(),
             DataDistribution::Normal { mean, std_dev }
         )
     }
 
-    fn generate_lognormal_data(location: f64, scale: f64, size: usize) -> (Vec<f64>, DataDistribution) {
+    fn generate_lognormal_data(location: f64, scale: f64, size: usize, seed: u64) -> (Vec<f64>, DataDistribution) {
         let lognormal = LogNormal::new(location, scale).unwrap();
-        let mut rng = thread_rng();
+        let mut rng = StdRng::seed_from_u64(seed);
         (
             lognormal.sample_iter(&mut rng).take(size).collect(),
             DataDistribution::LogNormal { location, scale }
         )
     }
 
-    fn generate_uniform_data(lower: f64, upper: f64, size: usize) -> (Vec<f64>, DataDistribution) {
+    fn generate_uniform_data(lower: f64, upper: f64, size: usize, seed: u64) -> (Vec<f64>, DataDistribution) {
         let uniform = Uniform::new(lower, upper);
-        let mut rng = thread_rng();
+        let mut rng = StdRng::seed_from_u64(seed);
         (
             uniform.sample_iter(&mut rng).take(size).collect(),
             DataDistribution::Uniform { lower, upper }
@@ -116,7 +124,7 @@ pub mod tests {
     // --- Stats.rs Tests ---
     #[test]
     fn test_distribution_metrics_normal() {
-        let (data, dist) = generate_normal_data(10.0, 5.0, 10000); // Increased sample size
+        let (data, dist) = generate_normal_data(10.0, 5.0, 10000, test_seed(1)); // Increased sample size
         let online_stats = OnlineStats::create(&data);
         let computed_metrics = online_stats.to_metrics();
 
@@ -163,11 +171,81 @@ pub mod tests {
         }
     }
 
+    #[test]
+    fn test_combined_metrics_match_pooled_data() {
+        let mut rng = StdRng::seed_from_u64(test_seed(1085));
+        let first: Vec<f64> = LogNormal::new(1.0, 0.8).unwrap().sample_iter(&mut rng).take(3000).collect();
+        let second: Vec<f64> = Normal::new(40.0, 6.0).unwrap().sample_iter(&mut rng).take(700).collect();
+
+        // Round-trip each pool through the stored metrics, as the precompute does
+        let stored = |values: &[f64]| OnlineStats::from_metrics(&OnlineStats::create(values).to_metrics());
+        let combined = OnlineStats::combine(&stored(&first), &stored(&second)).to_metrics();
+
+        let pooled: Vec<f64> = first.iter().chain(&second).copied().collect();
+        let expected = OnlineStats::create(&pooled).to_metrics();
+
+        assert_eq!(combined.sample_count, expected.sample_count);
+        for (name, computed, expected) in [
+            ("mean", combined.mean, expected.mean),
+            ("variance", combined.variance, expected.variance),
+            ("skewness", combined.skewness, expected.skewness),
+            ("kurtosis", combined.kurtosis, expected.kurtosis),
+        ] {
+            assert!(relative_error(computed, expected) < 1e-9,
+                "{} mismatch: combined={}, pooled={}", name, computed, expected);
+        }
+    }
+
+    #[test]
+    fn test_many_small_combines_do_not_drift() {
+        // Compensated sum, for a reference the combines can be held to
+        fn neumaier(values: impl Iterator<Item = f64>) -> f64 {
+            let (mut sum, mut compensation) = (0.0f64, 0.0f64);
+            for x in values {
+                let t = sum + x;
+                compensation += if sum.abs() >= x.abs() { (sum - t) + x } else { (x - t) + sum };
+                sum = t;
+            }
+            sum + compensation
+        }
+
+        let mut rng = StdRng::seed_from_u64(test_seed(1117));
+        let data: Vec<f64> = LogNormal::new(1.0, 1.5).unwrap().sample_iter(&mut rng).take(1_000_000).collect();
+
+        let n = data.len() as f64;
+        let rough_mean = neumaier(data.iter().copied()) / n;
+        let mean = rough_mean + neumaier(data.iter().map(|x| x - rough_mean)) / n;
+        let moment = |k: i32| neumaier(data.iter().map(|x| (x - mean).powi(k)));
+        let variance = moment(2) / n;
+        let skewness = moment(3) / (n * variance * variance.sqrt());
+        let kurtosis = moment(4) / n / (variance * variance) - 3.0;
+
+        // 10,000 buffer-sized combines, as partial merges make over a long run
+        let mut chunks = data.chunks(100);
+        let mut stats = OnlineStats::create(chunks.next().unwrap());
+        for chunk in chunks {
+            stats = OnlineStats::combine(&stats, &OnlineStats::create(chunk));
+        }
+        let combined = stats.to_metrics();
+
+        assert_eq!(combined.sample_count, 1_000_000);
+        for (name, computed, expected) in [
+            ("mean", combined.mean, mean),
+            ("variance", combined.variance, variance),
+            ("skewness", combined.skewness, skewness),
+            ("kurtosis", combined.kurtosis, kurtosis),
+        ] {
+            assert!(relative_error(computed, expected) < 2e-15,
+                "{} drifted: combined={}, reference={}, relative error={:.1e}",
+                name, computed, expected, relative_error(computed, expected));
+        }
+    }
+
     #[test]
     fn test_distribution_metrics_lognormal() {
         let location = 0.5;
         let scale = 0.75;
-        let (data, dist) = generate_lognormal_data(location, scale, 10000);
+        let (data, dist) = generate_lognormal_data(location, scale, 10000, test_seed(5));
         let online_stats = OnlineStats::create(&data);
         let computed_metrics = online_stats.to_metrics();
 
@@ -218,7 +296,7 @@ pub mod tests {
     fn test_distribution_metrics_uniform() {
         let lower = -1.0;
         let upper = 1.0;
-        let (data, dist) = generate_uniform_data(lower, upper, 10000);
+        let (data, dist) = generate_uniform_data(lower, upper, 10000, test_seed(3));
         let online_stats = OnlineStats::create(&data);
         let computed_metrics = online_stats.to_metrics();
 
@@ -297,49 +375,50 @@ pub mod tests {
         let location = 1.0;  // μ parameter
         let scale = 1.5;    // σ parameter, larger value increases skewness
         let sample_size = 50000;
-        
-        let (data, _) = generate_lognormal_data(location, scale, sample_size);
-        let mut tdigest = TDigest::new();
-        
-        // Create sorted copy for exact percentile calculation
-        let mut sorted_data = data.clone();
-        sorted_data.sort_by(|a, b| a.partial_cmp(b).unwrap());
-        
-        // Add data to TDigest
-        for &x in &data {
-            tdigest.add(x);
-        }
-        tdigest.finalize();
-    
-        // Test key percentiles that are important for LVR analysis
-        let test_quantiles = [0.25, 0.5, 0.75];
-        
+
         // Increase tolerance for higher quantiles since lognormal 
         // distributions have more variance in the upper tail
         let quantile_tol = 0.35;  
+
+        // Each case is a fresh sample; a failure names the seed to rerun it
+        // with LVR_TEST_SEED
+        for case in 0..8 {
+            let seed = test_seed(1) + case;
+            let (data, _) = generate_lognormal_data(location, scale, sample_size, seed);
+            let mut tdigest = TDigest::new();
+        
+            // Create sorted copy for exact percentile calculation
+            let mut sorted_data = data.clone();
+            sorted_data.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        
+            // Add data to TDigest
+            for &x in &data {
+                tdigest.add(x);
+            }
+            tdigest.finalize();
     
-        for &q in &test_quantiles {
-            let expected = percentile(&sorted_data, q);
-            let computed = tdigest.quantile(q).unwrap();
+            // Test key percentiles that are important for LVR analysis
+            for &q in &[0.25, 0.5, 0.75] {
+                let expected = percentile(&sorted_data, q);
+                let computed = tdigest.quantile(q).unwrap();
             
-            assert!(relative_error(computed, expected) < quantile_tol,
-                "Quantile {} mismatch for lognormal distribution:\n\
-                 Expected: {:.4}\n\
-                 Computed: {:.4}\n\
-                 Relative error: {:.2}%\n\
-                 Parameters: μ={}, σ={}", 
-                q, expected, computed, 
-                relative_error(computed, expected) * 100.0,
-                location, scale);
+                assert!(relative_error(computed, expected) < quantile_tol,
+                    "Quantile {} mismatch for lognormal distribution (seed {}):\n\
+                     Expected: {:.4}\n\
+                     Computed: {:.4}\n\
+                     Relative error: {:.2}%\n\
+                     Parameters: μ={}, σ={}", 
+                    q, seed, expected, computed, 
+                    relative_error(computed, expected) * 100.0,
+                    location, scale);
+            }
         }
     }
 
-
-
     #[test]
     fn test_tdigest_merge() {
-        let (data1, _) = generate_normal_data(10.0, 5.0, 500);
-        let (data2, _) = generate_normal_data(20.0, 5.0, 500);
+        let (data1, _) = generate_normal_data(10.0, 5.0, 500, test_seed(8));
+        let (data2, _) = generate_normal_data(20.0, 5.0, 500, test_seed(9));
 
         let mut td1 = TDigest::new();
         let mut td2 = TDigest::new();
@@ -357,6 +436,55 @@ pub mod tests {
         assert!(!merged_centroids.is_empty(), "Merged centroids should not be empty");
     }
 
+    #[test]
+    fn test_tdigest_merge_of_halves_matches_full_digest() {
+        let mut rng = StdRng::seed_from_u64(test_seed(1107));
+        let lognormal = LogNormal::new(1.0, 1.2).unwrap();
+        let data: Vec<f64> = (0..20_000).map(|_| lognormal.sample(&mut rng)).collect();
+        let (first, second) = data.split_at(data.len() / 2);
+
+        let digest_of = |values: &[f64]| {
+            let mut digest = TDigest::new();
+            for &x in values {
+                digest.add(x);
+            }
+            digest
+        };
+        let mut full = digest_of(&data);
+        let mut merged = digest_of(first);
+        merged.merge(&digest_of(second));
+
+        assert_eq!(merged.samples(), full.samples());
+        assert!(merged.buffer.is_empty());
+        assert!(relative_error(merged.running_total, full.running_total) < 1e-9);
+
+        // The merge leaves no buffer, so its quantiles are read unfinalized;
+        // finalizing would compress the merged centroids a second time
+        full.finalize();
+        let (full_metrics, merged_metrics) = (full.online_stats.to_metrics(), merged.online_stats.to_metrics());
+        assert_eq!(merged_metrics.sample_count, data.len() as u64);
+        assert!(relative_error(merged_metrics.mean, full_metrics.mean) < 1e-9);
+        assert!(relative_error(merged_metrics.std_dev, full_metrics.std_dev) < 1e-9);
+
+        let mut sorted_data = data.clone();
+        sorted_data.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        for q in [0.25, 0.5, 0.75] {
+            let expected = full.quantile(q).unwrap();
+            let computed = merged.quantile(q).unwrap();
+            assert!(relative_error(computed, expected) < 0.25,
+                "Quantile {}: merged {:.4}, full {:.4}", q, computed, expected);
+            assert!(relative_error(computed, percentile(&sorted_data, q)) < 0.35);
+        }
+
+        // Merging into or from an empty digest keeps the other's values
+        let mut empty = TDigest::new();
+        empty.merge(&full);
+        assert_eq!(empty.samples(), full.samples());
+        assert_eq!(empty.quantile(0.5), full.quantile(0.5));
+        full.merge(&TDigest::new());
+        assert_eq!(full.samples(), data.len() as u64);
+    }
+
     #[test]
     fn test_tdigest_edge_cases() {
         let mut tdigest = TDigest::new();
@@ -367,6 +495,497 @@ pub mod tests {
         assert_eq!(tdigest.quantile(0.5), Some(42.0), "Single-value TDigest should return that value");
     }
 
+    #[test]
+    fn test_tdigest_cdf_inverts_quantile_lognormal() {
+        let mut rng = StdRng::seed_from_u64(test_seed(1108));
+        let lognormal = LogNormal::new(1.0, 1.5).unwrap();
+        let data: Vec<f64> = (0..50_000).map(|_| lognormal.sample(&mut rng)).collect();
+
+        let mut tdigest = TDigest::new();
+        for &x in &data {
+            tdigest.add(x);
+        }
+        tdigest.finalize();
+
+        // Every q up to the first centroid's weight maps to its mean
+        let first_weight = tdigest.cdf(tdigest.centroids[0].mean).unwrap();
+        for q in (1..20).map(|i| i as f64 / 20.0) {
+            let x = tdigest.quantile(q).unwrap();
+            let roundtrip = tdigest.cdf(x).unwrap();
+            assert!((roundtrip - q.max(first_weight)).abs() < 1e-9, "cdf(quantile({})) = {}", q, roundtrip);
+
+            let exact = data.iter().filter(|&&value| value <= x).count() as f64 / data.len() as f64;
+            assert!((roundtrip - exact).abs() < 0.1, "cdf({:.4}): digest {:.4}, exact {:.4}", x, roundtrip, exact);
+        }
+
+        let (lowest, highest) = data.iter().fold((f64::MAX, f64::MIN), |(lo, hi), &x| (lo.min(x), hi.max(x)));
+        assert_eq!(tdigest.cdf(lowest / 2.0), Some(0.0));
+        assert_eq!(tdigest.cdf(highest * 2.0), Some(1.0));
+    }
+
+    #[test]
+    fn test_tdigest_tracks_exact_min_and_max() {
+        let mut rng = StdRng::seed_from_u64(test_seed(1109));
+        let lognormal = LogNormal::new(1.0, 1.5).unwrap();
+        let data: Vec<f64> = (0..10_000).map(|_| lognormal.sample(&mut rng)).collect();
+        let (first, second) = data.split_at(3_000);
+        let (lowest, highest) = data.iter().fold((f64::MAX, f64::MIN), |(lo, hi), &x| (lo.min(x), hi.max(x)));
+
+        let mut tdigest = TDigest::new();
+        for &x in first {
+            tdigest.add(x);
+        }
+        let mut rest = TDigest::new();
+        for &x in second {
+            rest.add(x);
+        }
+        tdigest.merge(&rest);
+        assert_eq!((tdigest.min, tdigest.max), (Some(lowest), Some(highest)));
+        assert_eq!(tdigest.quantile(0.0), Some(lowest));
+        assert_eq!(tdigest.quantile(1.0), Some(highest));
+        for q in [0.001, 0.5, 0.999] {
+            let value = tdigest.quantile(q).unwrap();
+            assert!((lowest..=highest).contains(&value), "quantile({}) = {}", q, value);
+        }
+
+        let stored: TDigest = serde_json::from_str(&serde_json::to_string(&tdigest).unwrap()).unwrap();
+        assert_eq!((stored.min, stored.max), (Some(lowest), Some(highest)));
+
+        // A digest stored before the range was tracked never claims one
+        let mut legacy = stored.clone();
+        (legacy.min, legacy.max) = (None, None);
+        legacy.add(highest * 2.0);
+        assert_eq!((legacy.min, legacy.max), (None, None));
+        let mut merged = TDigest::new();
+        merged.merge(&legacy);
+        merged.merge(&stored);
+        assert_eq!((merged.min, merged.max), (None, None));
+    }
+
+    #[test]
+    fn test_tdigest_trimmed_mean_ignores_outliers() {
+        let mut rng = StdRng::seed_from_u64(test_seed(1110));
+        let normal = Normal::new(100.0, 10.0).unwrap();
+        let mut data: Vec<f64> = (0..10_000).map(|_| normal.sample(&mut rng)).collect();
+        // 1% giant blocks, all inside the trimmed top 5%
+        data.extend((0..100).map(|i| 1e6 + i as f64));
+        data.shuffle(&mut rng);
+
+        let mut tdigest = TDigest::new();
+        for &x in &data {
+            tdigest.add(x);
+        }
+        tdigest.finalize();
+
+        let plain_mean = tdigest.online_stats.to_metrics().mean;
+        let trimmed_mean = tdigest.trimmed_mean(0.05, 0.95).unwrap();
+        assert!(relative_error(plain_mean, 100.0) > 10.0, "plain mean {}", plain_mean);
+        assert!(relative_error(trimmed_mean, 100.0) < 0.05, "trimmed mean {}", trimmed_mean);
+
+        // The normal IQR is 1.349 standard deviations
+        let iqr = tdigest.iqr().unwrap();
+        assert!(relative_error(iqr, 13.49) < 0.35, "iqr {}", iqr);
+
+        // Trimming nothing weights every centroid fully
+        let full: f64 = tdigest.centroids.iter().map(|c| c.mean * c.weight).sum::<f64>() / tdigest.total_weight;
+        assert!(relative_error(tdigest.trimmed_mean(0.0, 1.0).unwrap(), full) < 1e-9);
+        assert_eq!(tdigest.trimmed_mean(0.5, 0.5), None);
+        assert_eq!(tdigest.trimmed_mean(-0.1, 0.9), None);
+        assert_eq!(TDigest::new().trimmed_mean(0.05, 0.95), None);
+        assert_eq!(TDigest::new().iqr(), None);
+    }
+
+    #[test]
+    fn test_tdigest_cdf_edge_cases() {
+        let mut tdigest = TDigest::new();
+        assert_eq!(tdigest.cdf(1.0), None, "cdf on empty TDigest should be None");
+
+        tdigest.add(42.0);
+        tdigest.finalize();
+        assert_eq!(tdigest.cdf(41.0), Some(0.0));
+        assert_eq!(tdigest.cdf(42.0), Some(1.0));
+        assert_eq!(tdigest.cdf(43.0), Some(1.0));
+        assert_eq!(tdigest.cdf(f64::NAN), None);
+    }
+
+    #[test]
+    fn test_tdigest_batched_quantiles_match_single_walks() {
+        let mut rng = StdRng::seed_from_u64(test_seed(1111));
+        let lognormal = LogNormal::new(1.0, 1.5).unwrap();
+        let mut tdigest = TDigest::new();
+        let mut other = TDigest::new();
+        for i in 0..20_000 {
+            let x = lognormal.sample(&mut rng);
+            if i % 3 == 0 { other.add(x) } else { tdigest.add(x) }
+            assert!(tdigest.centroids_sorted() && other.centroids_sorted());
+        }
+        tdigest.merge(&other);
+        assert!(tdigest.centroids_sorted(), "merge keeps centroids sorted");
+        tdigest.finalize();
+        assert!(tdigest.centroids_sorted(), "finalize keeps centroids sorted");
+
+        let qs = [0.0, 0.01, 0.25, 0.5, 0.5, 0.75, 0.99, 1.0];
+        let batched = tdigest.quantiles(&qs);
+        assert_eq!(batched.len(), qs.len());
+        for (&q, &value) in qs.iter().zip(&batched) {
+            assert_eq!(value, tdigest.quantile(q), "q={}", q);
+        }
+        assert_eq!(tdigest.quantiles(&[]), Vec::<Option<f64>>::new());
+        assert_eq!(tdigest.quantiles(&[-0.1, 0.5, 1.1])[0], None);
+        assert_eq!(tdigest.quantiles(&[-0.1, 0.5, 1.1])[2], None);
+        assert_eq!(TDigest::new().quantiles(&[0.25, 0.75]), vec![None, None]);
+    }
+
+    #[test]
+    fn test_tdigest_equi_depth_histogram_properties() {
+        let mut rng = StdRng::seed_from_u64(test_seed(1118));
+        for case in 0..60 {
+            let n = rng.gen_range(1..20_000);
+            let data: Vec<f64> = match case % 3 {
+                0 => LogNormal::new(1.0, 2.0).unwrap().sample_iter(&mut rng).take(n).collect(),
+                1 => Uniform::new(0.01, 10.0).sample_iter(&mut rng).take(n).collect(),
+                // Heavy ties, so several bins collapse onto one value
+                _ => (0..n).map(|_| rng.gen_range(1..4) as f64).collect(),
+            };
+            let mut digest = TDigest::new();
+            digest.add_all(&data);
+            digest.finalize();
+
+            let bins = rng.gen_range(1..64);
+            let histogram = digest.equi_depth_histogram(bins);
+            assert_eq!(histogram.len(), bins, "case {}", case);
+            let total_mass: f64 = histogram.iter().map(|&(_, _, mass)| mass).sum();
+            assert!((total_mass - 1.0).abs() < 1e-12, "case {}: masses sum to {}", case, total_mass);
+            for &(lower, upper, _) in &histogram {
+                assert!(lower <= upper, "case {}: bin ({}, {})", case, lower, upper);
+            }
+            for pair in histogram.windows(2) {
+                assert_eq!(pair[0].1, pair[1].0, "case {}: bins are not contiguous", case);
+            }
+            assert_eq!(histogram[0].0, digest.quantile(0.0).unwrap());
+            assert_eq!(histogram[bins - 1].1, digest.quantile(1.0).unwrap());
+        }
+
+        assert!(TDigest::new().equi_depth_histogram(20).is_empty());
+        let mut digest = TDigest::new();
+        digest.add(5.0);
+        digest.finalize();
+        assert!(digest.equi_depth_histogram(0).is_empty());
+        assert_eq!(digest.equi_depth_histogram(2), [(5.0, 5.0, 0.5), (5.0, 5.0, 0.5)]);
+    }
+
+    #[test]
+    fn test_tdigest_weighted_adds_match_repeated_values() {
+        let lognormal: LogNormal<f64> = LogNormal::new(1.0, 1.5).unwrap();
+        for seed in 0..8 {
+            let mut rng = StdRng::seed_from_u64(test_seed(1112) + seed);
+            // A frequency table of cent-rounded values, as interval data gives
+            let table: Vec<(f64, u64)> = (0..2_000)
+                .map(|_| ((lognormal.sample(&mut rng) * 100.0).round() / 100.0 + 0.01, rng.gen_range(1..=20)))
+                .collect();
+
+            let mut weighted = TDigest::new();
+            let mut repeated = TDigest::new();
+            let mut expanded = Vec::new();
+            for &(x, count) in &table {
+                weighted.add_weighted(x, count as f64);
+                for _ in 0..count {
+                    repeated.add(x);
+                    expanded.push(x);
+                }
+            }
+            weighted.finalize();
+            repeated.finalize();
+
+            assert_eq!(weighted.samples(), repeated.samples());
+            assert_eq!(weighted.total_weight, repeated.total_weight);
+            assert_eq!((weighted.min, weighted.max), (repeated.min, repeated.max));
+            assert!(weighted.buffer.is_empty() && weighted.buffer_weights.is_empty());
+            assert!(weighted.centroids_sorted());
+            assert!(relative_error(weighted.running_total, repeated.running_total) < 1e-9);
+
+            let (weighted_metrics, repeated_metrics) = (weighted.online_stats.to_metrics(), repeated.online_stats.to_metrics());
+            assert_eq!(weighted_metrics.sample_count, repeated_metrics.sample_count);
+            for (name, w, r) in [
+                ("mean", weighted_metrics.mean, repeated_metrics.mean),
+                ("variance", weighted_metrics.variance, repeated_metrics.variance),
+                ("skewness", weighted_metrics.skewness, repeated_metrics.skewness),
+                ("kurtosis", weighted_metrics.kurtosis, repeated_metrics.kurtosis),
+            ] {
+                assert!(relative_error(w, r) < 1e-6, "seed {}: {} {} vs {}", seed, name, w, r);
+            }
+
+            for q in [0.1, 0.25, 0.5, 0.75, 0.9] {
+                let exact = percentile(&expanded, q);
+                let (w, r) = (weighted.quantile(q).unwrap(), repeated.quantile(q).unwrap());
+                // Buffers fill at different points, so the compression differs;
+                // the weighted digest should be as accurate as the repeated one
+                assert!(relative_error(w, r) < 0.25, "seed {}: q={} weighted {} vs repeated {}", seed, q, w, r);
+                assert!(relative_error(w, exact) < 0.35, "seed {}: q={} weighted {} vs exact {}", seed, q, w, exact);
+            }
+        }
+    }
+
+    #[test]
+    fn test_tdigest_weighted_add_edge_cases() {
+        let mut tdigest = TDigest::new();
+        tdigest.add_weighted(5.0, 0.0);
+        tdigest.add_weighted(5.0, -1.0);
+        tdigest.add_weighted(5.0, f64::NAN);
+        tdigest.add_weighted(5.0, f64::INFINITY);
+        assert_eq!(tdigest.samples(), 0, "Non-positive and non-finite weights are ignored");
+        assert!(tdigest.buffer.is_empty());
+
+        let mut first_weighted = TDigest::new();
+        first_weighted.add_weighted(7.0, 3.0);
+        assert_eq!(first_weighted.buffer_weights, vec![3.0]);
+
+        // Unit weights never materialize the weights vector
+        tdigest.add(1.0);
+        tdigest.add_weighted(2.0, 1.0);
+        assert!(tdigest.buffer_weights.is_empty());
+        tdigest.add_weighted(3.0, 4.0);
+        assert_eq!(tdigest.buffer_weights, vec![1.0, 1.0, 4.0]);
+        assert_eq!(tdigest.samples(), 6);
+        assert_eq!(tdigest.running_total, 15.0);
+
+        // Digests are stored as JSON; unweighted buffers keep their old shape
+        let stored = serde_json::to_string(&tdigest).unwrap();
+        let restored: TDigest = serde_json::from_str(&stored).unwrap();
+        assert_eq!(restored.buffer_weights, tdigest.buffer_weights);
+        let mut unweighted = TDigest::new();
+        unweighted.add(1.0);
+        assert!(!serde_json::to_string(&unweighted).unwrap().contains("buffer_weights"));
+
+        tdigest.finalize();
+        assert_eq!(tdigest.quantile(1.0), Some(3.0));
+        assert_eq!(tdigest.online_stats.to_metrics().mean, 2.5);
+    }
+
+    #[test]
+    fn test_tdigest_rejects_non_finite_values() {
+        let mut rng = StdRng::seed_from_u64(test_seed(1113));
+        let lognormal: LogNormal<f64> = LogNormal::new(1.0, 1.5).unwrap();
+        let mut clean = TDigest::new();
+        let mut poisoned = TDigest::new();
+        // Enough values for several partial merges, whose sorts used to panic
+        for i in 0..20_000 {
+            let x = lognormal.sample(&mut rng);
+            clean.add(x);
+            poisoned.add(x);
+            match i % 1_000 {
+                0 => poisoned.add(f64::NAN),
+                1 => poisoned.add(f64::INFINITY),
+                2 => poisoned.add_weighted(f64::NEG_INFINITY, 3.0),
+                _ => {}
+            }
+        }
+        clean.finalize();
+        poisoned.finalize();
+
+        assert_eq!(poisoned.rejected_samples, 20 * 5);
+        assert_eq!(clean.rejected_samples, 0);
+        assert_eq!(poisoned.samples(), clean.samples());
+        assert_eq!(poisoned.running_total, clean.running_total);
+        assert_eq!((poisoned.min, poisoned.max), (clean.min, clean.max));
+        let qs = [0.0, 0.25, 0.5, 0.75, 0.99, 1.0];
+        assert_eq!(poisoned.quantiles(&qs), clean.quantiles(&qs));
+        let (poisoned_metrics, clean_metrics) = (poisoned.online_stats.to_metrics(), clean.online_stats.to_metrics());
+        assert_eq!(
+            (poisoned_metrics.mean, poisoned_metrics.variance, poisoned_metrics.skewness, poisoned_metrics.kurtosis),
+            (clean_metrics.mean, clean_metrics.variance, clean_metrics.skewness, clean_metrics.kurtosis)
+        );
+
+        // Merges carry the count, even from a digest with nothing else in it
+        let mut only_rejected = TDigest::new();
+        only_rejected.add(f64::NAN);
+        let mut merged = clean.clone();
+        merged.merge(&only_rejected);
+        merged.merge(&poisoned);
+        assert_eq!(merged.rejected_samples, 20 * 5 + 1);
+
+        let finite = [1.0, 2.0, 4.0];
+        let stats = OnlineStats::create(&[1.0, f64::NAN, 2.0, f64::NEG_INFINITY, 4.0]).to_metrics();
+        let expected = OnlineStats::create(&finite).to_metrics();
+        assert_eq!((stats.sample_count, stats.mean, stats.variance), (expected.sample_count, expected.mean, expected.variance));
+        let weighted = OnlineStats::create_weighted(&[1.0, f64::NAN, 2.0, 4.0], &[1.0, 5.0, 1.0, 1.0]).to_metrics();
+        assert_eq!((weighted.sample_count, weighted.mean, weighted.variance), (expected.sample_count, expected.mean, expected.variance));
+    }
+
+    #[test]
+    fn test_tdigest_k2_scale_improves_tail_accuracy() {
+        let lognormal: LogNormal<f64> = LogNormal::new(1.0, 1.5).unwrap();
+        let seeds = 10;
+        // Mean over seeds of [k1, k2] p99 value and rank errors and p99.9 value error
+        let (mut p99_errors, mut p99_rank_errors, mut p999_errors) = ([0.0; 2], [0.0; 2], [0.0; 2]);
+        for seed in 0..seeds {
+            let mut rng = StdRng::seed_from_u64(test_seed(1114) + seed);
+            let data: Vec<f64> = (0..50_000).map(|_| lognormal.sample(&mut rng)).collect();
+            let (exact_p99, exact_p999) = (percentile(&data, 0.99), percentile(&data, 0.999));
+
+            // Equal base deltas: only the scale function differs
+            for (i, scale) in [ScaleFn::K1, ScaleFn::K2].into_iter().enumerate() {
+                let mut digest = TDigest::with_config(&TDigestConfig::default().with_scale(scale));
+                for &x in &data {
+                    digest.add(x);
+                }
+                digest.finalize();
+                assert!(digest.centroids_sorted());
+
+                p99_errors[i] += relative_error(digest.quantile(0.99).unwrap(), exact_p99) / seeds as f64;
+                p99_rank_errors[i] += (digest.cdf(exact_p99).unwrap() - 0.99).abs() / seeds as f64;
+                p999_errors[i] += relative_error(digest.quantile(0.999).unwrap(), exact_p999) / seeds as f64;
+            }
+        }
+
+        assert!(p99_errors[1] < p99_errors[0], "p99 error k1 {} vs k2 {}", p99_errors[0], p99_errors[1]);
+        assert!(p99_rank_errors[1] < p99_rank_errors[0], "p99 rank error k1 {} vs k2 {}", p99_rank_errors[0], p99_rank_errors[1]);
+        assert!(p999_errors[1] < p999_errors[0] / 2.0, "p99.9 error k1 {} vs k2 {}", p999_errors[0], p999_errors[1]);
+    }
+
+    #[test]
+    fn test_tdigest_default_config_reproduces_original_digest() {
+        let config = TDigestConfig::default();
+        assert_eq!((config.scale, config.base_delta_partial, config.base_delta_final, config.buffer_size), (ScaleFn::K1, 20, 10, 200));
+        let (configured, original) = (AdaptiveParameters::with_config(&config), AdaptiveParameters::new());
+        assert_eq!(
+            (configured.delta_partial, configured.delta_final, configured.buffer_size, configured.base_buffer_size),
+            (original.delta_partial, original.delta_final, original.buffer_size, original.base_buffer_size)
+        );
+
+        // The original arcsin scale function
+        for &(delta, q) in &[(20, 0.0f64), (20, 0.3), (10, 0.5), (1000, 0.999)] {
+            let k = (delta as f64 / std::f64::consts::TAU) * (2.0 * q - 1.0).asin();
+            assert_eq!(ScaleFn::K1.k(delta, q), k);
+            assert_eq!(TDigest::k1(delta, q), k);
+            assert!((ScaleFn::K1.inv_k(k, delta) - q).abs() < 1e-12);
+        }
+        assert!((ScaleFn::K2.inv_k(ScaleFn::K2.k(20, 0.3), 20) - 0.3).abs() < 1e-12);
+
+        // Digests stored before the scale was configurable keep k1
+        let mut digest = TDigest::new();
+        digest.add(1.0);
+        let mut stored = serde_json::to_value(&digest).unwrap();
+        stored.as_object_mut().unwrap().remove("scale");
+        assert_eq!(serde_json::from_value::<TDigest>(stored).unwrap().scale, ScaleFn::K1);
+
+        let config = AppConfig::from_toml("[tdigest]\nscale = \"k2\"\nbase_delta_final = 40").unwrap().tdigest;
+        assert_eq!(config, TDigestConfig { scale: ScaleFn::K2, base_delta_final: 40, ..TDigestConfig::default() });
+        assert_eq!(AppConfig::from_toml("").unwrap().tdigest, TDigestConfig::default());
+        assert!(AppConfig::from_toml("[tdigest]\nscale = \"k3\"").is_err());
+        assert!(AppConfig::from_toml("[tdigest]\nbuffer_size = 0").is_err());
+
+        // An empty accumulator takes the scale of what is merged into it
+        let mut k2 = TDigest::with_config(&config);
+        k2.add(1.0);
+        let mut merged = TDigest::new();
+        merged.merge(&k2);
+        assert_eq!(merged.scale, ScaleFn::K2);
+    }
+
+    #[test]
+    fn test_tdigest_caps_hold_on_adversarial_sorted_data() {
+        let config = TDigestConfig::default().with_caps(500, 40);
+        let ascending: Vec<f64> = (1..=100_000).map(|i| i as f64 / 100.0).collect();
+        let descending: Vec<f64> = ascending.iter().rev().copied().collect();
+        // Geometric spacing puts every value in its own tail
+        let geometric: Vec<f64> = (0..100_000).map(|i| 1.0001f64.powi(i)).collect();
+
+        for (name, data) in [("ascending", &ascending), ("descending", &descending), ("geometric", &geometric)] {
+            for scale in [ScaleFn::K1, ScaleFn::K2] {
+                let mut tdigest = TDigest::with_config(&config.with_scale(scale));
+                for &x in data {
+                    tdigest.add(x);
+                    assert!(tdigest.buffer.len() < config.max_buffer_size, "{} {:?}", name, scale);
+                    assert!(tdigest.centroids.len() <= config.max_centroids, "{} {:?}: {}", name, scale, tdigest.centroids.len());
+                }
+                tdigest.finalize();
+                assert!(tdigest.centroids.len() <= config.max_centroids);
+                assert!(tdigest.centroids_sorted());
+                assert_eq!(tdigest.total_weight, data.len() as f64, "Capping keeps every value's weight");
+
+                let diagnostics = tdigest.diagnostics();
+                assert_eq!(diagnostics.centroid_count, tdigest.centroids.len());
+                assert!(diagnostics.peak_buffer <= config.max_buffer_size);
+                // Adaptation grows the buffer to the cap, so at least one
+                // merge per 500 values plus the final one
+                assert!(diagnostics.merges > data.len() as u64 / 500, "{} {:?}: {:?}", name, scale, diagnostics);
+                assert!(diagnostics.adaptations >= 1, "{} {:?}: {:?}", name, scale, diagnostics);
+            }
+        }
+
+        // Without the caps the same data outgrows both
+        let mut uncapped = TDigest::new();
+        let mut peak_centroids = 0;
+        for &x in &geometric {
+            uncapped.add(x);
+            peak_centroids = peak_centroids.max(uncapped.centroids.len());
+        }
+        assert!(peak_centroids > config.max_centroids, "{}", peak_centroids);
+        assert!(uncapped.diagnostics().peak_buffer > config.max_buffer_size, "{:?}", uncapped.diagnostics());
+
+        // A digest stored before the caps existed gets the defaults
+        let mut stored = serde_json::to_value(TDigest::new()).unwrap();
+        let compression = stored["compression"].as_object_mut().unwrap();
+        for key in ["max_buffer_size", "max_centroids", "adaptations"] {
+            compression.remove(key);
+        }
+        stored.as_object_mut().unwrap().remove("merges");
+        let restored: TDigest = serde_json::from_value(stored).unwrap();
+        assert_eq!(
+            (restored.compression.max_buffer_size, restored.compression.max_centroids),
+            (DEFAULT_MAX_BUFFER_SIZE, DEFAULT_MAX_CENTROIDS)
+        );
+        assert_eq!(restored.diagnostics(), DigestDiagnostics::default());
+
+        let config = AppConfig::from_toml("[tdigest]\nmax_centroids = 300").unwrap().tdigest;
+        assert_eq!((config.max_buffer_size, config.max_centroids), (DEFAULT_MAX_BUFFER_SIZE, 300));
+        assert!(AppConfig::from_toml("[tdigest]\nmax_buffer_size = 0").is_err());
+    }
+
+    #[test]
+    fn test_tdigest_add_all_matches_elementwise_adds() {
+        let mut rng = StdRng::seed_from_u64(test_seed(1116));
+        let lognormal: LogNormal<f64> = LogNormal::new(1.0, 1.5).unwrap();
+        let mut values: Vec<f64> = (0..60_000).map(|_| lognormal.sample(&mut rng)).collect();
+        values[10] = f64::NAN;
+        values[30_000] = f64::INFINITY;
+
+        let mut batched = TDigest::new();
+        let mut elementwise = TDigest::new();
+        // Uneven slices, like chunks with different numbers of non-zero blocks
+        let mut rest = &values[..];
+        while !rest.is_empty() {
+            let (slice, tail) = rest.split_at(rng.gen_range(1..5_000).min(rest.len()));
+            batched.add_all(slice);
+            for &x in slice {
+                elementwise.add(x);
+            }
+            assert_eq!(batched.buffer, elementwise.buffer);
+            rest = tail;
+        }
+        // Weighted values buffered in between keep their weights
+        batched.add_weighted(3.0, 2.0);
+        elementwise.add_weighted(3.0, 2.0);
+        batched.add_all(&values[..100]);
+        for &x in &values[..100] {
+            elementwise.add(x);
+        }
+
+        let state = |digest: &TDigest| serde_json::to_string(digest).unwrap();
+        assert_eq!(state(&batched), state(&elementwise));
+        assert_eq!(batched.rejected_samples, 3, "The NaN twice and the infinity");
+        batched.finalize();
+        elementwise.finalize();
+        assert_eq!(state(&batched), state(&elementwise));
+        let qs = [0.0, 0.25, 0.5, 0.75, 0.99, 1.0];
+        assert_eq!(batched.quantiles(&qs), elementwise.quantiles(&qs));
+
+        let mut empty = TDigest::new();
+        empty.add_all(&[]);
+        assert_eq!(state(&empty), state(&TDigest::new()));
+    }
+
     // --- AdaptiveParameters Tests ---
     #[test]
     fn test_adaptive_parameters_initial() {
@@ -429,7 +1048,59 @@ pub mod tests {
         assert_eq!(params.delta_final, params.base_delta_final);
         assert_eq!(params.buffer_size, params.base_buffer_size);
         assert_eq!(params.samples_seen, 0);
-        assert_eq!(params.adapted, false);
+        assert!(!params.adapted);
+    }
+
+    #[test]
+    fn test_adaptive_parameters_follow_configured_thresholds_and_record_history() {
+        let metrics = |sample_count: u64| DistributionMetrics {
+            mean: 0.0,
+            variance: 1.0,
+            std_dev: 1.0,
+            skewness: 0.0,
+            kurtosis: 0.0,
+            sample_count,
+        };
+        let config = TDigestConfig::default()
+            .with_thresholds(100, 1_000)
+            .with_adaptation(AdaptationFactors { max_adjustment: 1.0, small_sample_threshold: 0, ..AdaptationFactors::default() });
+        let mut params = AdaptiveParameters::with_config(&config);
+
+        // Nothing below the initial threshold or between the two thresholds
+        params.adapt(&metrics(99));
+        assert!(params.history().is_empty());
+        assert_eq!((params.delta_partial, params.delta_final, params.buffer_size), (20, 10, 200));
+
+        // 1.5x the base at 150 samples, under the 2x cap
+        params.adapt(&metrics(150));
+        assert_eq!((params.delta_partial, params.delta_final, params.buffer_size), (30, 15, 300));
+        params.adapt(&metrics(500));
+        assert_eq!(params.history().len(), 1);
+
+        // Neutral moments and an uncapped adjustment scale by samples alone
+        params.adapt(&metrics(2_000));
+        assert_eq!((params.delta_partial, params.delta_final, params.buffer_size), (40, 20, 400));
+        // The default cap of 0.2 would have held fine-tuning at the base
+        let mut capped = AdaptiveParameters::with_config(&TDigestConfig::default().with_thresholds(100, 1_000));
+        capped.adapt(&metrics(150));
+        capped.adapt(&metrics(2_000));
+        assert_eq!((capped.delta_partial, capped.delta_final, capped.buffer_size), (20, 10, 400));
+
+        let history: Vec<_> = params.history().iter()
+            .map(|event| (event.metrics.sample_count, event.old_delta_partial, event.new_delta_partial, event.old_delta_final, event.new_delta_final, event.old_buffer_size, event.new_buffer_size))
+            .collect();
+        assert_eq!(history, [(150, 20, 30, 10, 15, 200, 300), (2_000, 30, 40, 15, 20, 300, 400)]);
+        assert_eq!(params.adaptations, 2);
+
+        // Only the latest adaptations are kept, and they survive storage
+        for i in 0..2 * ADAPTATION_HISTORY_LEN {
+            params.adapt(&metrics(if i % 2 == 0 { 1_000 } else { 3_000 }));
+        }
+        assert_eq!(params.history().len(), ADAPTATION_HISTORY_LEN);
+        assert_eq!(params.history().back().unwrap().new_buffer_size, params.buffer_size);
+        let stored: AdaptiveParameters = serde_json::from_str(&serde_json::to_string(&params).unwrap()).unwrap();
+        assert_eq!(stored.history().len(), ADAPTATION_HISTORY_LEN);
+        assert_eq!(stored.factors, config.adaptation);
     }
 
     #[test]
@@ -485,6 +1156,131 @@ pub mod tests {
         assert_eq!(MarkoutTime::Brontes.to_string(), "brontes");
     }
 
+    #[test]
+    fn test_cents_convert_and_serialize_as_integer_cents() {
+        assert_eq!(Cents::from_dollars(12.345), Some(Cents(1235)));
+        assert_eq!(Cents::from_dollars(-0.004), Some(Cents(0)));
+        assert_eq!(Cents::from_dollars(-4.0), Some(Cents(-400)));
+        assert_eq!(Cents::from_dollars(f64::NAN), None);
+        assert_eq!(Cents::from_dollars(1e20), None);
+        assert_eq!(Cents(-1999).to_dollars(), -19.99);
+        assert_eq!(Cents(-5).positive_part(), 0);
+
+        assert_eq!(Cents(i64::MAX).checked_add(Cents(1)), None);
+        assert_eq!(Cents(i64::MIN).checked_sub(Cents(1)), None);
+        assert_eq!(Cents(i64::MAX).saturating_add(Cents(1)), Cents(i64::MAX));
+        assert_eq!([Cents(300), Cents(-100)].iter().sum::<Cents>(), Cents(200));
+
+        // Response fields keep the wire format of the i64 they replaced
+        let point = RunningTotal {
+            block_number: 1,
+            markout: "-0.5".to_string(),
+            pool_name: None,
+            pool_address: None,
+            running_total_cents: Cents(-400),
+        };
+        let json = serde_json::to_value(&point).unwrap();
+        assert_eq!(json["running_total_cents"], serde_json::json!(-400));
+        assert_eq!(serde_json::from_str::<Cents>("-400").unwrap(), Cents(-400));
+    }
+
+    #[test]
+    fn test_markout_time_parses_and_serializes_its_display_form() {
+        for markout in MarkoutTime::ALL {
+            let display = markout.to_string();
+            assert_eq!(display.parse::<MarkoutTime>().unwrap(), markout);
+
+            let json = serde_json::to_string(&markout).unwrap();
+            assert_eq!(json, format!("{:?}", display));
+            assert_eq!(serde_json::from_str::<MarkoutTime>(&json).unwrap(), markout);
+        }
+
+        // Other spellings of the same markout
+        for (spelling, markout) in [
+            ("0", MarkoutTime::Zero),
+            ("-0.50", MarkoutTime::Negative05),
+            ("+1.5", MarkoutTime::Positive15),
+            (" 2 ", MarkoutTime::Positive2),
+            ("BRONTES", MarkoutTime::Brontes),
+        ] {
+            assert_eq!(spelling.parse::<MarkoutTime>().unwrap(), markout, "{:?}", spelling);
+        }
+
+        for invalid in ["0.25", "abc", "NaN", "inf", ""] {
+            assert!(invalid.parse::<MarkoutTime>().is_err(), "{:?} parsed", invalid);
+            assert!(serde_json::from_str::<MarkoutTime>(&format!("{:?}", invalid)).is_err());
+        }
+    }
+
+    #[test]
+    fn test_data_sources_round_trip_through_their_names() {
+        for source in [DataSource::Aurora, DataSource::Brontes, DataSource::Custom("replay".to_string())] {
+            assert_eq!(source.to_string().parse::<DataSource>().unwrap(), source);
+            let json = serde_json::to_string(&source).unwrap();
+            assert_eq!(serde_json::from_str::<DataSource>(&json).unwrap(), source);
+        }
+        assert_eq!(" Brontes ".parse::<DataSource>().unwrap(), DataSource::Brontes);
+        assert!("".parse::<DataSource>().is_err());
+    }
+
+    #[test]
+    fn test_address_parses_checksums_and_rejects_malformed_input() {
+        // The EIP-55 examples
+        for checksummed in [
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+            "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+            "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+        ] {
+            let address: Address = checksummed.parse().unwrap();
+            assert_eq!(address.as_str(), checksummed.to_lowercase());
+            assert_eq!(address.checksummed(), checksummed);
+            assert!(address.matches(checksummed) && address.matches(&checksummed.to_uppercase()));
+
+            // Single-case spellings carry no checksum
+            let lowercase: Address = checksummed.to_lowercase().parse().unwrap();
+            let uppercase: Address = format!("0x{}", checksummed[2..].to_uppercase()).parse().unwrap();
+            assert_eq!((&lowercase, &uppercase), (&address, &address));
+
+            let json = serde_json::to_string(&address).unwrap();
+            assert_eq!(json, format!("{:?}", checksummed.to_lowercase()));
+            assert_eq!(serde_json::from_str::<Address>(&json).unwrap(), address);
+        }
+
+        let mistyped = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD";
+        let error = mistyped.parse::<Address>().unwrap_err().to_string();
+        assert!(error.contains("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"), "{}", error);
+
+        // Sources and stored files are not checksummed, so any casing is accepted there
+        let lenient = Address::from_any_case(mistyped).unwrap();
+        assert_eq!(lenient.as_str(), mistyped.to_lowercase());
+
+        // Registry lookups ignore casing, checksummed or not
+        let registry = PoolRegistry::default();
+        let pool = registry.addresses()[0].clone();
+        let mixed: String = pool.as_str().char_indices()
+            .map(|(i, c)| if i % 2 == 0 { c.to_ascii_uppercase() } else { c })
+            .collect::<String>()
+            .replacen("0X", "0x", 1);
+        assert_eq!(registry.get(&mixed).map(|entry| entry.address.to_lowercase()), Some(pool.to_string()));
+        assert_eq!(registry.address(&mixed), Some(&pool));
+        assert_eq!(registry.deployment_block(&mixed), registry.deployment_block(&pool));
+
+        for invalid in [
+            "",
+            "0x",
+            "5aaeb6053f3e94c9b9a09f33669435e7ef1beaed",
+            "0x5aaeb6053f3e94c9b9a09f33669435e7ef1bea",
+            "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed00",
+            "0xZZaeb6053f3e94c9b9a09f33669435e7ef1beaed",
+            " 0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed",
+        ] {
+            assert!(invalid.parse::<Address>().is_err(), "{:?} parsed", invalid);
+            assert!(serde_json::from_str::<Address>(&format!("{:?}", invalid)).is_err());
+            assert!(Address::from_any_case(invalid).is_err(), "{:?} parsed", invalid);
+        }
+    }
+
     #[test]
     fn test_process_block_basic() {
         let mut activity = ClusterBlockActivity::new(
@@ -643,5 +1439,33 @@ pub mod tests {
         assert_eq!(activity.total_blocks(), 3, "Should count 1 from first chunk + 2 from second chunk");
         assert_eq!(activity.non_zero_blocks(), 2, "Should count 0 from first chunk + 2 from second chunk");
     }
+
+    #[test]
+    fn test_begin_chunk_aligns_member_pools() {
+        let mut activity = ClusterBlockActivity::new(
+            "Test Cluster".to_string(),
+            MarkoutTime::Zero,
+            1000,
+            100
+        );
+
+        for i in 0..100 {
+            activity.process_block(1000 + i, false);
+        }
+
+        // A member deployed mid-chunk is processed first; the other member's
+        // earlier blocks of the same chunk must still be counted
+        activity.begin_chunk(1100);
+        for i in 50..100 {
+            activity.process_block(1100 + i, true);
+        }
+        activity.begin_chunk(1100);
+        for i in 0..100 {
+            activity.process_block(1100 + i, i % 2 == 0);
+        }
+
+        assert_eq!(activity.total_blocks(), 200, "Each block counted once");
+        assert_eq!(activity.non_zero_blocks(), 75, "25 even blocks before the deployment plus 50 after");
+    }
 }
 
@@ -0,0 +1,539 @@
+use super::support::{read_parquet, TestStore};
+use crate::*;
+use object_store::{path::Path, ObjectStore};
+use parquet::{
+    arrow::arrow_reader::ParquetRecordBatchReader,
+    basic::Compression,
+    file::reader::{FileReader, SerializedFileReader},
+};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+fn fixture_intervals() -> Vec<IntervalData> {
+    (0..20_000u64)
+        .map(|i| IntervalData {
+            interval_id: i % 30,
+            pair_address: POOL_ADDRESSES[(i % POOL_ADDRESSES.len() as u64) as usize].to_string(),
+            markout_time: MarkoutTime::from_f64(MARKOUT_TIMES[(i % MARKOUT_TIMES.len() as u64) as usize]).unwrap(),
+            total_lvr_cents: Cents(((i * 7919) % 100_000) as i64),
+            max_lvr_cents: (i * 104_729) % 10_000,
+            non_zero_count: i % 7200,
+            total_count: 7200,
+            start_block: i % 30 * 7200,
+            end_block: (i % 30 + 1) * 7200,
+            source_counts: BTreeMap::new(),
+        })
+        .collect()
+}
+
+async fn write_fixture(options: ParquetWriteOptions) -> (Arc<TestStore>, Path) {
+    let store = Arc::new(TestStore::new());
+    let writer = ParallelParquetWriter::new(store.clone()).with_write_options(options);
+    writer.write_interval_data(fixture_intervals(), 0, 216_000).await.unwrap();
+    (store, Path::from("intervals/0_216000.parquet"))
+}
+
+#[test]
+fn test_parquet_options_from_toml() {
+    let config = AppConfig::from_toml(
+        r#"
+        [parquet]
+        compression = "zstd(3)"
+        dictionary = false
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(config.parquet.compression_label(), "zstd(3)");
+    assert!(!config.parquet.dictionary);
+    assert_eq!(config.parquet.max_row_group_size, ParquetWriteOptions::default().max_row_group_size);
+
+    assert_eq!(AppConfig::from_toml("").unwrap().parquet, ParquetWriteOptions::default());
+    assert!(AppConfig::from_toml("[parquet]\ncompression = \"zstd\"").is_err());
+}
+
+#[tokio::test]
+async fn test_zstd_output_is_smaller_and_readable() {
+    let snappy = ParquetWriteOptions::default();
+    let zstd = ParquetWriteOptions {
+        compression: ParquetWriteOptions::parse_compression("zstd(3)").unwrap(),
+        ..ParquetWriteOptions::default()
+    };
+
+    let (snappy_store, path) = write_fixture(snappy).await;
+    let (zstd_store, _) = write_fixture(zstd).await;
+
+    let snappy_bytes = snappy_store.get(&path).await.unwrap().bytes().await.unwrap();
+    let zstd_bytes = zstd_store.get(&path).await.unwrap().bytes().await.unwrap();
+    assert!(
+        (zstd_bytes.len() as f64) < snappy_bytes.len() as f64 * 0.9,
+        "zstd {} bytes vs snappy {} bytes",
+        zstd_bytes.len(),
+        snappy_bytes.len()
+    );
+
+    let reader = SerializedFileReader::new(zstd_bytes.clone()).unwrap();
+    let column = reader.metadata().row_group(0).column(0);
+    assert!(matches!(column.compression(), Compression::ZSTD(_)));
+
+    let rows: usize = ParquetRecordBatchReader::try_new(zstd_bytes, 1024)
+        .unwrap()
+        .map(|batch| batch.unwrap().num_rows())
+        .sum();
+    assert_eq!(rows, fixture_intervals().len());
+}
+
+#[tokio::test]
+async fn test_precomputed_output_records_compression() {
+    let store = Arc::new(TestStore::new());
+    let options = ParquetWriteOptions {
+        compression: ParquetWriteOptions::parse_compression("zstd(3)").unwrap(),
+        ..ParquetWriteOptions::default()
+    };
+    PrecomputedWriter::new(store.clone())
+        .with_write_options(options)
+        .write_pool_totals()
+        .await
+        .unwrap();
+
+    let bytes = store
+        .get(&Path::from("precomputed/pool_metrics/totals.parquet"))
+        .await
+        .unwrap()
+        .bytes()
+        .await
+        .unwrap();
+    let reader = SerializedFileReader::new(bytes).unwrap();
+    let compression = reader
+        .metadata()
+        .file_metadata()
+        .key_value_metadata()
+        .unwrap()
+        .iter()
+        .find(|kv| kv.key == COMPRESSION_METADATA_KEY)
+        .and_then(|kv| kv.value.clone());
+    assert_eq!(compression.as_deref(), Some("zstd(3)"));
+}
+
+#[tokio::test]
+async fn test_checkpoint_keeps_bounded_top_lvr() {
+    let store = Arc::new(TestStore::new());
+    let checkpoint = Checkpoint::new(POOL_ADDRESSES[0].to_string(), MarkoutTime::Brontes);
+    // 40 distinct values in scrambled block order, plus a tie on the largest
+    for i in 0..40u64 {
+        checkpoint.update_max_lvr(15_000_000 + (i * 17) % 40, 1_000 + i * 10);
+    }
+    checkpoint.update_max_lvr(14_000_000, 1_390);
+
+    let snapshot = checkpoint.to_snapshot();
+    assert_eq!(snapshot.top_lvr.len(), TOP_LVR_CAPACITY);
+    assert_eq!(snapshot.top_lvr[0], (1_390, 14_000_000));
+    assert_eq!(snapshot.top_lvr[1], (1_390, 15_000_000 + (39 * 17) % 40));
+    assert_eq!(snapshot.top_lvr.last().unwrap().0, 1_000 + 25 * 10);
+    assert!(snapshot.top_lvr.windows(2).all(|w| w[0].0 >= w[1].0));
+    assert_eq!((snapshot.max_lvr_value, snapshot.max_lvr_block), (1_390, 14_000_000));
+
+    let writer = ParallelParquetWriter::new(store.clone());
+    writer.write_checkpoints(vec![snapshot.clone()]).await.unwrap();
+
+    let path = store.paths().await.pop().unwrap();
+    let batch = &read_parquet(store.as_ref(), &path).await[0];
+    let list = |name: &str| -> Vec<u64> {
+        let lists = batch.column_by_name(name).unwrap().as_any().downcast_ref::<arrow::array::ListArray>().unwrap();
+        let values = lists.value(0);
+        values.as_any().downcast_ref::<arrow::array::UInt64Array>().unwrap().values().to_vec()
+    };
+    let persisted: Vec<(u64, u64)> = list("top_lvr_values").into_iter().zip(list("top_lvr_blocks")).collect();
+    assert_eq!(persisted, snapshot.top_lvr);
+}
+
+#[tokio::test]
+async fn test_checkpoint_records_digest_diagnostics() {
+    let store = Arc::new(TestStore::new());
+    let checkpoint = Checkpoint::new(POOL_ADDRESSES[0].to_string(), MarkoutTime::Brontes);
+    for i in 0..5_000u64 {
+        checkpoint.update_digest(1.0 + (i * 7919 % 5_000) as f64 / 100.0).unwrap();
+    }
+    let snapshot = checkpoint.to_snapshot();
+    let diagnostics = snapshot.digest_diagnostics;
+    assert_eq!(diagnostics, checkpoint.digest.lock().unwrap().diagnostics(), "Taken from the live digest");
+    assert_eq!(diagnostics.centroid_count, snapshot.digest.centroids.len());
+    assert!(diagnostics.merges >= 5_000 / 400, "{:?}", diagnostics);
+    assert!(diagnostics.adaptations >= 1, "{:?}", diagnostics);
+    assert!(diagnostics.peak_buffer >= 200 && diagnostics.peak_buffer <= DEFAULT_MAX_BUFFER_SIZE, "{:?}", diagnostics);
+
+    ParallelParquetWriter::new(store.clone()).write_checkpoints(vec![snapshot]).await.unwrap();
+    let path = store.paths().await.pop().unwrap();
+    let batch = &read_parquet(store.as_ref(), &path).await[0];
+    let column = |name: &str| batch.column_by_name(name).unwrap().as_any().downcast_ref::<arrow::array::UInt64Array>().unwrap().value(0);
+    assert_eq!(
+        [column("digest_centroids"), column("digest_merges"), column("digest_adaptations"), column("digest_peak_buffer")],
+        [diagnostics.centroid_count as u64, diagnostics.merges, diagnostics.adaptations, diagnostics.peak_buffer as u64]
+    );
+    let stored = CheckpointSnapshot::from_record_batch(batch).unwrap().remove(0);
+    assert_eq!(stored.digest_diagnostics, diagnostics);
+
+    // The compression adaptation left the digest at
+    let compression = &stored.digest.compression;
+    assert_eq!(
+        [column("digest_delta_final"), column("digest_buffer_size")],
+        [compression.delta_final, compression.buffer_size as u64]
+    );
+    assert_eq!((stored.digest_delta_final, stored.digest_buffer_size), (compression.delta_final, compression.buffer_size as u64));
+    assert!(compression.delta_final > 10, "{:?}", compression);
+}
+
+fn chunk_summary(chunk_start: u64, brontes_cents: i64) -> ChunkSummary {
+    ChunkSummary {
+        chunk_start,
+        chunk_end: chunk_start + 216_000,
+        aurora_rows: 1_200,
+        malformed_aurora_rows: 3,
+        brontes_rows: 40,
+        timings: ChunkTimings {
+            aurora_fetch_ms: 350,
+            brontes_fetch_ms: 90,
+            process_ms: 40,
+            interval_write_ms: 25,
+            checkpoint_update_ms: 60,
+        },
+        retries: 1,
+        write_retries: 2,
+        markouts: vec![
+            ChunkMarkoutTotals { markout_time: "-0.5".to_string(), total_lvr_cents: Cents(12_345), non_zero_count: 7 },
+            ChunkMarkoutTotals { markout_time: "brontes".to_string(), total_lvr_cents: Cents(brontes_cents), non_zero_count: 3 },
+        ],
+        completeness_warnings: vec![CompletenessWarning {
+            markout_time: "1.0".to_string(),
+            chunk_start,
+            chunk_end: chunk_start + 216_000,
+            rows: 0,
+            expected_rows: 130,
+        }],
+        source_counts: BTreeMap::from([(DataSource::Aurora, 120), (DataSource::Brontes, 3)]),
+    }
+}
+
+#[tokio::test]
+async fn test_chunk_summaries_round_trip_in_block_order() {
+    let store = Arc::new(TestStore::new());
+    let later = chunk_summary(15_753_392, 500);
+    let earlier = chunk_summary(15_537_392, 250);
+
+    let writer = ParallelParquetWriter::new(store.clone());
+    writer.write_chunk_summary(&later).await.unwrap();
+    writer.write_chunk_summary(&earlier).await.unwrap();
+    assert!(store.paths().await.contains(&"chunks/15537392_15753392_summary.parquet".to_string()));
+
+    let summaries = read_chunk_summaries(store.as_ref()).await.unwrap();
+    assert_eq!(summaries, vec![earlier, later]);
+
+    let brontes_total: i64 = summaries.iter()
+        .flat_map(|s| &s.markouts)
+        .filter(|m| m.markout_time == "brontes")
+        .map(|m| m.total_lvr_cents.as_i64())
+        .sum();
+    assert_eq!(brontes_total, 750);
+
+    let table = format_chunk_summaries(&summaries);
+    // One line per markout, then one per completeness warning and one for
+    // the sources
+    assert_eq!(table.lines().count(), 1 + 2 * 4);
+    assert!(table.lines().nth(1).unwrap().contains("123.45"));
+    assert!(table.contains("warning: markout 1.0 returned 0 rows for blocks 15537392-15753392, expected about 130"));
+    assert!(table.contains("15537392   15753392 sources: aurora=120 brontes=3"));
+
+    assert_eq!(prefix_usage(store.as_ref(), "chunks").await.unwrap().0, 2);
+}
+
+#[test]
+fn test_interval_and_checkpoint_batches_round_trip_every_column() {
+    let mut intervals: Vec<IntervalData> = fixture_intervals().into_iter().take(50).collect();
+    intervals[7].total_lvr_cents = Cents(-12_345);
+    intervals[8].source_counts = BTreeMap::from([(DataSource::Aurora, 40), (DataSource::Custom("replay".to_string()), 2)]);
+    intervals[9].source_counts = BTreeMap::from([(DataSource::Brontes, 7)]);
+    let batch = IntervalData::to_record_batch(&intervals).unwrap();
+    assert_eq!(batch.schema(), interval_schema());
+    let parsed = IntervalData::from_record_batch(&batch).unwrap();
+    assert_eq!(serde_json::to_value(&parsed).unwrap(), serde_json::to_value(&intervals).unwrap());
+    assert_eq!(IntervalData::to_record_batch(&parsed).unwrap(), batch);
+
+    // Files written before provenance was recorded read as having none
+    let mut legacy = batch.clone();
+    for column in [INTERVAL_SOURCE_COUNTS_COLUMN, INTERVAL_SOURCE_NAMES_COLUMN] {
+        legacy.remove_column(legacy.schema().index_of(column).unwrap());
+    }
+    let parsed = IntervalData::from_record_batch(&legacy).unwrap();
+    assert!(parsed.iter().all(|interval| interval.source_counts.is_empty()));
+    assert_eq!(parsed[7].total_lvr_cents, Cents(-12_345));
+
+    // A fresh checkpoint has null min/max and no top entries; the others
+    // have every column set
+    let snapshots: Vec<CheckpointSnapshot> = (0..3u64)
+        .map(|case| {
+            let checkpoint = Checkpoint::new(POOL_ADDRESSES[case as usize].to_string(), MarkoutTime::Negative05);
+            checkpoint.running_total.store(-400 * case as i64, std::sync::atomic::Ordering::Release);
+            checkpoint.total_bucket_negative.store(case, std::sync::atomic::Ordering::Release);
+            for i in 0..case * 500 {
+                checkpoint.update_digest((i % 97) as f64 + 0.5).unwrap();
+                checkpoint.update_max_lvr(15_000_000 + i, i % 97 * 100 + 50);
+            }
+            checkpoint.to_snapshot()
+        })
+        .collect();
+    assert_eq!(snapshots[0].min_nonzero_cents, None);
+    assert!(snapshots[0].top_lvr.is_empty());
+
+    let batch = CheckpointSnapshot::to_record_batch(&snapshots).unwrap();
+    assert_eq!(batch.schema(), checkpoint_schema());
+    assert_eq!(batch.column_by_name("min_nonzero_cents").unwrap().null_count(), 1);
+    let parsed = CheckpointSnapshot::from_record_batch(&batch).unwrap();
+    assert_eq!(serde_json::to_value(&parsed).unwrap(), serde_json::to_value(&snapshots).unwrap());
+    assert_eq!(CheckpointSnapshot::to_record_batch(&parsed).unwrap(), batch);
+}
+
+/// A checkpoint as written before `schema_version`: unsigned running total,
+/// Int64 bucket counts and none of the later columns
+fn unversioned_checkpoint_batch(running_total: u64) -> arrow::record_batch::RecordBatch {
+    use arrow::array::{ArrayRef, Float64Array, Int64Array, StringArray, UInt64Array};
+    let uint64 = |value: u64| Arc::new(UInt64Array::from(vec![value])) as ArrayRef;
+    let int64 = |value: i64| Arc::new(Int64Array::from(vec![value])) as ArrayRef;
+    let float64 = |value: f64| Arc::new(Float64Array::from(vec![value])) as ArrayRef;
+    arrow::record_batch::RecordBatch::try_from_iter([
+        ("pair_address", Arc::new(StringArray::from(vec![POOL_ADDRESSES[0]])) as ArrayRef),
+        ("markout_time", Arc::new(StringArray::from(vec!["-1.0"])) as ArrayRef),
+        ("max_lvr_block", uint64(15_600_000)),
+        ("max_lvr_value", uint64(90_000)),
+        ("running_total", uint64(running_total)),
+        ("total_bucket_0", int64(100)),
+        ("total_bucket_0_10", int64(4)),
+        ("total_bucket_10_100", int64(3)),
+        ("total_bucket_100_500", int64(2)),
+        ("total_bucket_500_1000", int64(1)),
+        ("total_bucket_1000_10000", int64(0)),
+        ("total_bucket_10000_plus", int64(0)),
+        ("last_updated_block", uint64(15_600_100)),
+        ("non_zero_proportion", float64(10.0 / 110.0)),
+        ("non_zero_samples", uint64(10)),
+        ("percentile_25_cents", uint64(500)),
+        ("median_cents", uint64(2_000)),
+        ("percentile_75_cents", uint64(20_000)),
+        ("mean", float64(150.0)),
+        ("std_dev", float64(200.0)),
+        ("skewness", float64(1.5)),
+        ("kurtosis", float64(4.0)),
+    ])
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_unversioned_and_current_checkpoints_read_through_the_shared_reader() {
+    let legacy = unversioned_checkpoint_batch(250_000);
+    assert_eq!(CheckpointSchemaVersion::of_batch(&legacy).unwrap(), CheckpointSchemaVersion::Unversioned);
+    let snapshot = read_checkpoint_snapshot(&legacy).unwrap();
+    assert_eq!(snapshot.running_total, Cents(250_000));
+    assert_eq!(snapshot.markout_time, MarkoutTime::Negative1);
+    assert_eq!((snapshot.total_bucket_negative, snapshot.non_zero_blocks(), snapshot.total_blocks()), (0, 10, 110));
+    assert_eq!((snapshot.top_lvr.len(), snapshot.min_nonzero_cents, snapshot.trimmed_mean_cents), (0, None, 0));
+    assert_eq!(snapshot.non_zero_samples, 10);
+    // Its processed range is not declared, only inferred from its counts
+    assert_eq!(snapshot.processed_range(), None);
+    assert_eq!(snapshot.processed_blocks(), (15_600_101 - 110, 110));
+
+    // Too large for the signed total it is read as
+    assert!(read_checkpoint_snapshot(&unversioned_checkpoint_batch(u64::MAX)).is_err());
+
+    // What the writer stores now is versioned and read strictly
+    let store = Arc::new(TestStore::new());
+    ParallelParquetWriter::new(store.clone()).write_checkpoints(vec![snapshot.clone()]).await.unwrap();
+    let path = checkpoint_path(&snapshot.pair_address, snapshot.markout_time).to_string();
+    let current = read_parquet(store.as_ref(), &path).await.remove(0);
+    assert_eq!(CheckpointSchemaVersion::of_batch(&current).unwrap(), CheckpointSchemaVersion::CURRENT);
+    let stored = read_checkpoint_snapshot(&current).unwrap();
+    assert_eq!(serde_json::to_value(&stored).unwrap(), serde_json::to_value(&snapshot).unwrap());
+
+    let index = current.schema().index_of("total_bucket_negative").unwrap();
+    let mut missing = current.clone();
+    missing.remove_column(index);
+    let error = read_checkpoint_snapshot(&missing).unwrap_err();
+    assert!(error.to_string().contains("missing column total_bucket_negative"), "{}", error);
+
+    let with_version = |batch: &arrow::record_batch::RecordBatch, version: u16| {
+        let index = batch.schema().index_of(CHECKPOINT_SCHEMA_VERSION_COLUMN).unwrap();
+        let mut columns = batch.columns().to_vec();
+        columns[index] = Arc::new(arrow::array::UInt16Array::from(vec![version]));
+        arrow::record_batch::RecordBatch::try_new(batch.schema(), columns).unwrap()
+    };
+    assert!(CheckpointSchemaVersion::of_batch(&with_version(&current, 4)).is_err());
+
+    // Version 1 predates deployment_block, which it reads as 0
+    let mut v1 = with_version(&current, 1);
+    v1.remove_column(current.schema().index_of("deployment_block").unwrap());
+    assert_eq!(CheckpointSchemaVersion::of_batch(&v1).unwrap(), CheckpointSchemaVersion::V1);
+    assert_eq!(read_checkpoint_snapshot(&v1).unwrap().deployment_block, 0);
+    let mut missing = current.clone();
+    missing.remove_column(current.schema().index_of("deployment_block").unwrap());
+    assert!(read_checkpoint_snapshot(&missing).is_err());
+
+    // Version 2 predates the processed range
+    let mut v2 = with_version(&current, 2);
+    for column in ["first_processed_block", "blocks_processed"] {
+        v2.remove_column(v2.schema().index_of(column).unwrap());
+    }
+    assert_eq!(CheckpointSchemaVersion::of_batch(&v2).unwrap(), CheckpointSchemaVersion::V2);
+    let stored = read_checkpoint_snapshot(&v2).unwrap();
+    assert_eq!((stored.first_processed_block, stored.blocks_processed, stored.processed_range()), (0, 0, None));
+    assert_eq!(stored.processed_blocks(), (15_600_101 - 110, 110));
+    assert!(read_checkpoint_snapshot(&with_version(&v2, 3)).is_err());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_writes_share_one_writer() {
+    let store = Arc::new(TestStore::new().with_latency(std::time::Duration::from_millis(5)));
+    let writer = Arc::new(ParallelParquetWriter::new(store.clone()));
+    let chunk_row = |chunk: u64, pool: &str| IntervalData {
+        interval_id: 0,
+        pair_address: pool.to_string(),
+        markout_time: MarkoutTime::Brontes,
+        total_lvr_cents: Cents(chunk as i64),
+        max_lvr_cents: chunk,
+        non_zero_count: 1,
+        total_count: 7200,
+        start_block: chunk * 7200,
+        end_block: (chunk + 1) * 7200,
+        source_counts: BTreeMap::new(),
+    };
+
+    let mut tasks = Vec::new();
+    for chunk in 0..64u64 {
+        let writer = writer.clone();
+        tasks.push(tokio::spawn(async move {
+            let rows = POOL_ADDRESSES.iter().map(|pool| chunk_row(chunk, pool)).collect();
+            writer.write_interval_data(rows, chunk * 7200, (chunk + 1) * 7200).await
+        }));
+    }
+    // Every task rewrites the same two checkpoints
+    for version in 0..16i64 {
+        let writer = writer.clone();
+        tasks.push(tokio::spawn(async move {
+            let snapshots = POOL_ADDRESSES[..2]
+                .iter()
+                .map(|pool| {
+                    let mut snapshot = Checkpoint::new(pool.to_string(), MarkoutTime::Brontes).to_snapshot();
+                    snapshot.running_total = Cents(version);
+                    snapshot
+                })
+                .collect();
+            writer.write_checkpoints(snapshots).await
+        }));
+    }
+    tokio::time::timeout(std::time::Duration::from_secs(60), futures::future::try_join_all(tasks))
+        .await
+        .expect("concurrent writes deadlocked")
+        .unwrap()
+        .into_iter()
+        .collect::<anyhow::Result<Vec<()>>>()
+        .unwrap();
+
+    for chunk in 0..64u64 {
+        let path = interval_path(chunk * 7200, (chunk + 1) * 7200);
+        let batch = &read_parquet(store.as_ref(), path.as_ref()).await[0];
+        let totals = batch.column_by_name("total_lvr_cents").unwrap()
+            .as_any().downcast_ref::<arrow::array::Int64Array>().unwrap();
+        assert_eq!(batch.num_rows(), POOL_ADDRESSES.len());
+        assert!(totals.values().iter().all(|&total| total == chunk as i64), "{} holds another chunk", path);
+    }
+    for pool in &POOL_ADDRESSES[..2] {
+        let path = checkpoint_path(pool, MarkoutTime::Brontes);
+        let snapshot = read_checkpoint_snapshot(&read_parquet(store.as_ref(), path.as_ref()).await[0]).unwrap();
+        assert_eq!(snapshot.pair_address, *pool);
+        assert!((0..16).contains(&snapshot.running_total.0));
+    }
+    let paths = store.paths().await;
+    assert_eq!(paths.len(), 64 + 2, "{:?}", paths);
+
+    let peak = store.peak_puts_in_flight();
+    assert!(peak > 1 && peak <= MAX_CONCURRENT_WRITES, "{} puts in flight at once", peak);
+}
+
+#[tokio::test]
+async fn test_every_written_file_records_its_provenance() {
+    let store = Arc::new(TestStore::new());
+    let writer = ParallelParquetWriter::new(store.clone());
+    writer.write_interval_data(fixture_intervals(), 0, 216_000).await.unwrap();
+    let checkpoint = Checkpoint::new(POOL_ADDRESSES[0].to_string(), MarkoutTime::Brontes);
+    let mut snapshot = checkpoint.to_snapshot();
+    (snapshot.first_processed_block, snapshot.last_updated_block, snapshot.blocks_processed) = (100, 215_999, 215_900);
+    writer.write_checkpoints(vec![snapshot]).await.unwrap();
+    PrecomputedWriter::new(store.clone()).write_percentile_bands().await.unwrap();
+
+    let files = [
+        (Path::from("intervals/0_216000.parquet"), INTERVAL_SCHEMA_VERSION, Some(("0", "216000"))),
+        (checkpoint_path(POOL_ADDRESSES[0], MarkoutTime::Brontes), CheckpointSchemaVersion::CURRENT.schema_version(), Some(("100", "216000"))),
+        (DatasetKind::PercentileBands.path(), DatasetKind::PercentileBands.schema_version(), Some(("0", "216000"))),
+    ];
+    for (path, schema_version, block_range) in files {
+        let bytes = store.get(&path).await.unwrap().bytes().await.unwrap();
+        let metadata = read_footer_metadata(bytes).unwrap();
+        assert_eq!(metadata[CRATE_VERSION_METADATA_KEY], env!("CARGO_PKG_VERSION"), "{}", path);
+        assert_eq!(metadata[RUN_ID_METADATA_KEY], run_id(), "{}", path);
+        assert_eq!(metadata[SCHEMA_VERSION_METADATA_KEY], schema_version.to_string(), "{}", path);
+        let stored_range = metadata.get(START_BLOCK_METADATA_KEY).map(String::as_str).zip(metadata.get(END_BLOCK_METADATA_KEY).map(String::as_str));
+        assert_eq!(stored_range, block_range, "{}", path);
+
+        let report = inspect_object(store.as_ref(), &path).await.unwrap();
+        assert!(report.contains(&format!("{} = {}", RUN_ID_METADATA_KEY, run_id())), "{}", report);
+        assert!(report.contains(&format!("{} = {}", SCHEMA_VERSION_METADATA_KEY, schema_version)), "{}", report);
+    }
+
+    let report = inspect_object(store.as_ref(), &Path::from("intervals/0_216000.parquet")).await.unwrap();
+    assert!(report.contains("rows        20000"), "{}", report);
+    assert!(inspect_object(store.as_ref(), &Path::from("intervals/missing.parquet")).await.is_err());
+}
+
+/// Hive paths are plain `key=value` directories, so DuckDB prunes them with
+/// `read_parquet('intervals/*/*/*.parquet', hive_partitioning = true)` and a
+/// filter such as `WHERE year = 2022 AND month = 10`
+#[tokio::test]
+async fn test_hive_layout_partitions_interval_files_by_month() {
+    let config = AppConfig::from_toml("[output]\nlayout = \"hive\"").unwrap();
+    assert_eq!(config.output.layout, OutputLayout::Hive);
+    assert_eq!(AppConfig::from_toml("").unwrap().output.layout, OutputLayout::Flat);
+    assert!(AppConfig::from_toml("[output]\nlayout = \"nested\"").is_err());
+
+    let merge = 15_537_392;
+    let next_month = merge + 216_000;
+    assert_eq!(
+        OutputLayout::Hive.interval_path(merge, next_month).as_ref(),
+        "intervals/year=2022/month=09/15537392_15753392.parquet"
+    );
+    assert_eq!(
+        OutputLayout::Hive.interval_path(next_month, next_month + 216_000).as_ref(),
+        "intervals/year=2022/month=10/15753392_15969392.parquet"
+    );
+    for layout in [OutputLayout::Flat, OutputLayout::Hive] {
+        let path = layout.interval_path(merge, next_month);
+        assert_eq!(parse_interval_path(path.as_ref()), Some((layout, merge, next_month)));
+    }
+    // Filed under a month its start block is not in
+    assert_eq!(parse_interval_path("intervals/year=2023/month=01/15537392_15753392.parquet"), None);
+
+    let store = Arc::new(TestStore::new());
+    ParallelParquetWriter::new(store.clone())
+        .with_layout(OutputLayout::Hive)
+        .write_interval_data(fixture_intervals(), next_month, next_month + 216_000)
+        .await
+        .unwrap();
+    ParallelParquetWriter::new(store.clone())
+        .with_layout(OutputLayout::Hive)
+        .write_interval_data(fixture_intervals(), merge, next_month)
+        .await
+        .unwrap();
+    ParallelParquetWriter::new(store.clone()).write_interval_data(fixture_intervals(), 0, 216_000).await.unwrap();
+
+    let hive = list_interval_files(store.as_ref(), OutputLayout::Hive).await.unwrap();
+    let ranges: Vec<_> = hive.iter().map(|(_, start, end)| (*start, *end)).collect();
+    assert_eq!(ranges, vec![(merge, next_month), (next_month, next_month + 216_000)]);
+    let flat = list_interval_files(store.as_ref(), OutputLayout::Flat).await.unwrap();
+    assert_eq!(flat, vec![(Path::from("intervals/0_216000.parquet"), 0, 216_000)]);
+}
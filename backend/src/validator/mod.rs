@@ -1,2 +1,8 @@
+mod objects;
+mod policy;
+mod repair;
 mod validator;
+pub use objects::*;
+pub use policy::*;
+pub use repair::*;
 pub use validator::*;
\ No newline at end of file
@@ -0,0 +1,139 @@
+use serde::Serialize;
+use std::fmt;
+use crate::api::precompute::AGGREGATE_POOL_ADDRESS;
+use crate::config::PoolRegistry;
+use crate::models::MarkoutTime;
+use crate::schema::DatasetKind;
+use crate::storage::TEMPORARY_OBJECT_MARKER;
+use crate::writer::{parse_checkpoint_path, parse_interval_path, CONSOLIDATED_CHECKPOINTS_PATH};
+
+/// Prefixes `scan_unknown_objects` lists
+pub const SCANNED_PREFIXES: [&str; 3] = ["checkpoints", "intervals", "precomputed"];
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum StrayKind {
+    /// Named like nothing the pipeline writes, or for a pool it does not know
+    Unknown,
+    /// Under a known prefix but not parseable as its naming rule
+    Malformed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum CleanupAction {
+    /// Nothing reads it and nothing is lost by removing it
+    Delete,
+    /// May hold data worth keeping, e.g. under a renamed pool
+    Review,
+}
+
+impl fmt::Display for CleanupAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CleanupAction::Delete => write!(f, "delete"),
+            CleanupAction::Review => write!(f, "review"),
+        }
+    }
+}
+
+/// An object under a scanned prefix the pipeline did not write, or no longer
+/// would
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StrayObject {
+    pub path: String,
+    pub kind: StrayKind,
+    pub reason: String,
+    pub action: CleanupAction,
+}
+
+impl fmt::Display for StrayObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is {:?}: {} ({})", self.path, self.kind, self.reason, self.action)
+    }
+}
+
+/// Every object under `SCANNED_PREFIXES`, recognized or not
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ObjectScan {
+    pub recognized: usize,
+    /// By path
+    pub stray: Vec<StrayObject>,
+}
+
+impl ObjectScan {
+    pub fn unknown(&self) -> impl Iterator<Item = &StrayObject> {
+        self.stray.iter().filter(|object| object.kind == StrayKind::Unknown)
+    }
+
+    pub fn malformed(&self) -> impl Iterator<Item = &StrayObject> {
+        self.stray.iter().filter(|object| object.kind == StrayKind::Malformed)
+    }
+}
+
+fn parse_markout_time(markout: &str) -> Option<MarkoutTime> {
+    markout.parse().ok()
+}
+
+/// Checks `path` against the naming rule of its prefix and, for pool files,
+/// the registry. `None` when the pipeline would have written it.
+pub fn classify_object(path: &str, pools: &PoolRegistry) -> Option<StrayObject> {
+    let stray = |kind, reason: &str, action| {
+        Some(StrayObject { path: path.to_string(), kind, reason: reason.to_string(), action })
+    };
+    let known_pool = |pool: &str| pool == AGGREGATE_POOL_ADDRESS || pools.get(pool).is_some();
+
+    if path.contains(TEMPORARY_OBJECT_MARKER) {
+        return stray(StrayKind::Unknown, "temporary object left by an interrupted write", CleanupAction::Delete);
+    }
+
+    let (prefix, name) = path.split_once('/').unwrap_or(("", path));
+    match prefix {
+        "checkpoints" => {
+            if path == CONSOLIDATED_CHECKPOINTS_PATH {
+                return None;
+            }
+            let Some((_, pool, markout)) = parse_checkpoint_path(path) else {
+                return stray(
+                    StrayKind::Malformed,
+                    "not named pool=<pool>/markout=<markout>.parquet or <pool>_<markout>.parquet",
+                    CleanupAction::Review,
+                );
+            };
+            if parse_markout_time(markout).is_none() {
+                return stray(StrayKind::Malformed, "markout is not a known markout time", CleanupAction::Review);
+            }
+            if !known_pool(pool) {
+                return stray(StrayKind::Unknown, "checkpoint of a pool not in the registry", CleanupAction::Review);
+            }
+            None
+        }
+        "intervals" => match parse_interval_path(path) {
+            Some((_, start, end)) if start < end => None,
+            _ => stray(
+                StrayKind::Malformed,
+                "not named <start>_<end>.parquet or year=<yyyy>/month=<mm>/<start>_<end>.parquet with start < end",
+                CleanupAction::Review,
+            ),
+        },
+        "precomputed" => {
+            if DatasetKind::ALL.iter().any(|kind| kind.path().as_ref() == path) {
+                return None;
+            }
+            let rolling = name
+                .strip_prefix("time_series/rolling_")
+                .and_then(|rest| rest.strip_suffix(".parquet"));
+            if rolling.is_some_and(|window| window.parse::<usize>().is_ok()) {
+                return None;
+            }
+            let partition = name
+                .strip_prefix("running_totals/individual/pool=")
+                .and_then(|rest| rest.strip_suffix(".parquet"));
+            match partition {
+                Some(pool) if known_pool(pool) => None,
+                Some(_) => stray(StrayKind::Unknown, "running totals of a pool not in the registry", CleanupAction::Delete),
+                None => stray(StrayKind::Unknown, "not a precomputed dataset", CleanupAction::Delete),
+            }
+        }
+        _ => stray(StrayKind::Unknown, "outside the scanned prefixes", CleanupAction::Review),
+    }
+}
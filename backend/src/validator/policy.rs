@@ -0,0 +1,250 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use super::{ValidationReport, ValidationStats, RUNNING_TOTAL_TOLERANCE_PERCENT};
+
+// Checks a `Finding` comes from
+pub const RUNNING_TOTAL_CHECK: &str = "running_total_difference";
+pub const ZERO_COUNT_CHECK: &str = "zero_count_difference";
+pub const NON_ZERO_CONSISTENCY_CHECK: &str = "non_zero_count_consistency";
+pub const MAX_LVR_CHECK: &str = "max_lvr_matches_digest";
+pub const REJECTED_SAMPLES_CHECK: &str = "rejected_samples";
+pub const CHECKPOINT_INVARIANT_CHECK: &str = "checkpoint_invariant";
+pub const BLOCK_COUNT_CHECK: &str = "expected_block_count";
+pub const REALIZED_EXCESS_CHECK: &str = "realized_above_theoretical";
+pub const COVERAGE_GAP_CHECK: &str = "interval_coverage_gap";
+pub const COVERAGE_OVERLAP_CHECK: &str = "interval_coverage_overlap";
+pub const CHUNK_ALIGNMENT_CHECK: &str = "interval_chunk_alignment";
+pub const PRECOMPUTED_CHECK: &str = "precomputed_consistency";
+pub const LEGACY_BUCKETS_CHECK: &str = "legacy_bucket_rule";
+pub const STRAY_OBJECT_CHECK: &str = "stray_object";
+
+/// How much a finding matters; a report's severity is its worst finding's
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum Severity {
+    Info,
+    Warn,
+    Fail,
+}
+
+/// One discrepancy `validate_all` found, classified by the validator's policy
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Finding {
+    pub severity: Severity,
+    pub check: &'static str,
+    /// The `{pair_address}_{markout_time}` key, file or block range concerned
+    pub subject: String,
+    pub detail: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}: {}", self.check, self.subject, self.detail)
+    }
+}
+
+/// Thresholds deciding which findings warn and which fail a validation run.
+/// The defaults are the release checks; backfills loosen them and the
+/// per-chunk validation during processing uses `during_processing`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ValidationPolicy {
+    /// Running total differences above this percent warn; smaller non-zero
+    /// ones are informational
+    pub total_pct_warn: f64,
+    /// Running total differences above this percent fail
+    pub total_pct_fail: f64,
+    /// Zero-count differences larger than this fail; `None` only warns
+    pub zero_count_abs_fail: Option<u64>,
+    /// Non-zero counts disagreeing between digest, buckets and intervals
+    /// fail rather than warn
+    pub require_nonzero_consistency: bool,
+    /// Block count deviations above this percent fail; smaller ones warn
+    pub block_pct_fail: f64,
+    /// Brontes totals above a theoretical markout's fail rather than warn
+    pub realized_excess_fail: bool,
+    /// Precomputed files disagreeing with their sources fail rather than warn
+    pub precomputed_fail: bool,
+    /// Missing interval files fail rather than warn
+    pub coverage_gap_fail: bool,
+}
+
+impl Default for ValidationPolicy {
+    fn default() -> Self {
+        Self {
+            total_pct_warn: 0.0,
+            total_pct_fail: RUNNING_TOTAL_TOLERANCE_PERCENT,
+            zero_count_abs_fail: None,
+            require_nonzero_consistency: true,
+            block_pct_fail: RUNNING_TOTAL_TOLERANCE_PERCENT,
+            realized_excess_fail: false,
+            precomputed_fail: true,
+            coverage_gap_fail: true,
+        }
+    }
+}
+
+impl ValidationPolicy {
+    /// Precomputed files are only regenerated once processing finishes and
+    /// chunks still being written leave gaps, so neither fails mid-run
+    pub fn during_processing(self) -> Self {
+        Self { precomputed_fail: false, coverage_gap_fail: false, ..self }
+    }
+
+    fn fail_if(condition: bool) -> Severity {
+        if condition { Severity::Fail } else { Severity::Warn }
+    }
+
+    /// Every finding in `report`, pools in key order
+    pub fn classify(&self, report: &ValidationReport) -> Vec<Finding> {
+        let mut findings = Vec::new();
+
+        let mut pools: Vec<_> = report.pools.iter().collect();
+        pools.sort_by(|a, b| a.0.cmp(b.0));
+        for (key, stats) in pools {
+            findings.extend(self.classify_pool(key, stats));
+        }
+
+        for excess in &report.realized_excess {
+            findings.push(Finding {
+                severity: Self::fail_if(self.realized_excess_fail),
+                check: REALIZED_EXCESS_CHECK,
+                subject: excess.pair_address.clone(),
+                detail: excess.to_string(),
+            });
+        }
+
+        let coverage = &report.coverage;
+        for gap in &coverage.gaps {
+            findings.push(Finding {
+                severity: Self::fail_if(self.coverage_gap_fail),
+                check: COVERAGE_GAP_CHECK,
+                subject: gap.to_string(),
+                detail: format!("No interval file covers {}", gap),
+            });
+        }
+        for overlap in &coverage.overlaps {
+            findings.push(Finding {
+                severity: Severity::Fail,
+                check: COVERAGE_OVERLAP_CHECK,
+                subject: overlap.second.clone(),
+                detail: overlap.to_string(),
+            });
+        }
+        for path in &coverage.misaligned {
+            findings.push(Finding {
+                severity: Severity::Warn,
+                check: CHUNK_ALIGNMENT_CHECK,
+                subject: path.clone(),
+                detail: "Interval file is off the chunk layout".to_string(),
+            });
+        }
+
+        for violation in &report.precomputed {
+            findings.push(Finding {
+                severity: Self::fail_if(self.precomputed_fail),
+                check: PRECOMPUTED_CHECK,
+                subject: violation.file.clone(),
+                detail: violation.to_string(),
+            });
+        }
+
+        // Skipped by every check above, so they never fail the run
+        for stray in &report.objects.stray {
+            findings.push(Finding {
+                severity: Severity::Warn,
+                check: STRAY_OBJECT_CHECK,
+                subject: stray.path.clone(),
+                detail: format!("{:?}: {}; suggested action: {}", stray.kind, stray.reason, stray.action),
+            });
+        }
+
+        for path in &report.legacy_bucket_checkpoints {
+            findings.push(Finding {
+                severity: Severity::Info,
+                check: LEGACY_BUCKETS_CHECK,
+                subject: path.clone(),
+                detail: "Predates half-open buckets; values equal to a bucket bound count in the bucket below".to_string(),
+            });
+        }
+
+        findings
+    }
+
+    /// The findings of one pool and markout's checkpoint against its intervals
+    pub fn classify_pool(&self, key: &str, stats: &ValidationStats) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        let mut push = |severity, check, detail| {
+            findings.push(Finding { severity, check, subject: key.to_string(), detail });
+        };
+
+        if stats.difference != 0 {
+            let percent = stats.difference_percent.abs();
+            let severity = if percent > self.total_pct_fail {
+                Severity::Fail
+            } else if percent > self.total_pct_warn {
+                Severity::Warn
+            } else {
+                Severity::Info
+            };
+            push(severity, RUNNING_TOTAL_CHECK, format!(
+                "Checkpoint={}, Intervals={}, Difference={} ({:.2}%){}",
+                stats.checkpoint_total,
+                stats.intervals_total,
+                stats.difference,
+                stats.difference_percent,
+                if stats.difference < 0 { ", intervals exceed the checkpoint" } else { "" }
+            ));
+        }
+
+        let zero_count_difference = stats.checkpoint_zero_count.abs_diff(stats.interval_zero_count);
+        if zero_count_difference != 0 {
+            let fails = self.zero_count_abs_fail.is_some_and(|limit| zero_count_difference > limit);
+            push(Self::fail_if(fails), ZERO_COUNT_CHECK, format!(
+                "Checkpoint={}, Intervals={}, Difference={}",
+                stats.checkpoint_zero_count, stats.interval_zero_count, zero_count_difference
+            ));
+        }
+
+        if !stats.non_zero_counts_consistent {
+            push(Self::fail_if(self.require_nonzero_consistency), NON_ZERO_CONSISTENCY_CHECK, format!(
+                "TDigest={}, Negative={}, Intervals={}, Bucket sum={}",
+                stats.tdigest_samples, stats.negative_count, stats.non_zero_samples, stats.bucket_sum_non_zero
+            ));
+        }
+
+        if !stats.max_lvr_consistent {
+            push(Severity::Fail, MAX_LVR_CHECK, format!(
+                "Checkpoint={} cents, TDigest={:?}",
+                stats.max_lvr_cents, stats.tracked_max_cents
+            ));
+        }
+
+        for violation in &stats.internal_violations {
+            push(Severity::Fail, CHECKPOINT_INVARIANT_CHECK, violation.to_string());
+        }
+
+        if !stats.block_counts_match() {
+            let percent = stats
+                .interval_block_deviation_percent
+                .abs()
+                .max(stats.checkpoint_block_deviation_percent.abs());
+            push(Self::fail_if(percent > self.block_pct_fail), BLOCK_COUNT_CHECK, format!(
+                "Expected={:?}, Intervals deviate by {} ({:.2}%), Checkpoint by {} ({:.2}%)",
+                stats.expected_blocks,
+                stats.interval_block_deviation,
+                stats.interval_block_deviation_percent,
+                stats.checkpoint_block_deviation,
+                stats.checkpoint_block_deviation_percent
+            ));
+        }
+
+        if stats.rejected_samples > 0 {
+            push(Severity::Warn, REJECTED_SAMPLES_CHECK, format!(
+                "Digest rejected {} non-finite values; check the upstream data",
+                stats.rejected_samples
+            ));
+        }
+
+        findings
+    }
+}
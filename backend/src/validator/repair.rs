@@ -0,0 +1,84 @@
+use anyhow::Result;
+use tracing::{info, warn};
+use crate::api::precompute::PrecomputedWriter;
+use super::{
+    Finding, PrecomputedViolation, ValidationReport, Validator, AGGREGATE_MATCHES_POOLS_INVARIANT,
+    CLUSTER_PROPORTIONS_SUM_INVARIANT, HISTOGRAM_COUNTS_MATCH_INVARIANT, POOL_TOTALS_MATCH_CHECKPOINTS_INVARIANT,
+    PRECOMPUTED_CHECK, RUNNING_TOTALS_MATCH_CHECKPOINTS_INVARIANT, RUNNING_TOTALS_NON_DECREASING_INVARIANT,
+};
+
+/// The `PRECOMPUTE_TASKS` entry regenerating the file `invariant` checks
+pub fn repair_task(invariant: &str) -> Option<&'static str> {
+    match invariant {
+        AGGREGATE_MATCHES_POOLS_INVARIANT
+        | RUNNING_TOTALS_NON_DECREASING_INVARIANT
+        | RUNNING_TOTALS_MATCH_CHECKPOINTS_INVARIANT => Some("running_totals"),
+        POOL_TOTALS_MATCH_CHECKPOINTS_INVARIANT => Some("pool_totals"),
+        CLUSTER_PROPORTIONS_SUM_INVARIANT => Some("cluster_proportions"),
+        HISTOGRAM_COUNTS_MATCH_INVARIANT => Some("histograms"),
+        _ => None,
+    }
+}
+
+#[derive(Debug)]
+pub struct RepairReport {
+    /// Precompute tasks rerun, in the order their violations were found
+    pub regenerated: Vec<&'static str>,
+    /// Failing findings of the sources themselves. Regenerating from them
+    /// would only hide the problem, so any of these skips the repair.
+    pub blocked_by: Vec<Finding>,
+    /// Violations no precompute task is known to fix
+    pub unrepairable: Vec<PrecomputedViolation>,
+    /// The validation after the repair, or the only one when nothing was rerun
+    pub report: ValidationReport,
+}
+
+impl Validator {
+    /// Validates, and when the only failures are precomputed files
+    /// disagreeing with their sources, reruns the precompute tasks writing
+    /// them and validates again. There is at most one repair cycle: whatever
+    /// still fails afterwards is left in `report`.
+    pub async fn validate_and_repair(&self, writer: &PrecomputedWriter) -> Result<RepairReport> {
+        let report = self.validate_all().await?;
+        let blocked_by: Vec<Finding> = report
+            .failures()
+            .filter(|finding| finding.check != PRECOMPUTED_CHECK)
+            .cloned()
+            .collect();
+
+        let mut regenerated = Vec::new();
+        let mut unrepairable = Vec::new();
+        for violation in &report.precomputed {
+            match repair_task(violation.invariant) {
+                Some(task) if !regenerated.contains(&task) => regenerated.push(task),
+                Some(_) => {}
+                None => unrepairable.push(violation.clone()),
+            }
+        }
+
+        if regenerated.is_empty() {
+            return Ok(RepairReport { regenerated, blocked_by, unrepairable, report });
+        }
+        if !blocked_by.is_empty() {
+            warn!(
+                "Not repairing precomputed files: {} source findings fail validation",
+                blocked_by.len()
+            );
+            regenerated.clear();
+            return Ok(RepairReport { regenerated, blocked_by, unrepairable, report });
+        }
+
+        for &task in &regenerated {
+            info!("Repairing precomputed output: rerunning {}", task);
+            writer.run_task(task).await?;
+        }
+
+        let report = self.validate_all().await?;
+        info!(
+            "Revalidated after regenerating {}: {:?}",
+            regenerated.join(", "),
+            report.severity()
+        );
+        Ok(RepairReport { regenerated, blocked_by, unrepairable, report })
+    }
+}
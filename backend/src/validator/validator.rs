@@ -1,18 +1,66 @@
 use anyhow::{Context, Result};
+use arrow::array::{Array, Float64Array, Int64Array, StringArray, UInt64Array};
+use arrow::record_batch::RecordBatch;
 use object_store::ObjectStore;
-use parquet::arrow::arrow_reader::ParquetRecordBatchReader;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 use std::sync::Arc;
 use tracing::{info, warn, error};
-use futures::StreamExt;
+use dashmap::DashMap;
+use futures::{StreamExt, TryStreamExt};
+use serde::Serialize;
+use crate::schema::*;
+use crate::api::precompute::AGGREGATE_POOL_ADDRESS;
+use super::{classify_object, Finding, ObjectScan, Severity, ValidationPolicy, SCANNED_PREFIXES};
+use crate::config::{OutputLayout, PoolRegistry};
+use crate::models::{pool_key, CheckpointSnapshot, IntervalData, MarkoutTime};
+use crate::processor::BLOCKS_PER_CHUNK;
+use crate::storage::parquet_reader;
+use crate::writer::{list_checkpoints, list_interval_files};
+use crate::api::common::{BUCKET_RULE_METADATA_KEY, HALF_OPEN_BUCKET_RULE};
 
 const BATCH_SIZE: usize = 1024;
 
-#[derive(Debug)]
+/// Checkpoint and interval files each loader reads at once by default
+pub const DEFAULT_VALIDATION_CONCURRENCY: usize = 8;
+
+/// How far, in percent, a pool's brontes running total may exceed its
+/// smallest theoretical markout total before it is reported by default
+pub const DEFAULT_REALIZED_EXCESS_TOLERANCE_PERCENT: f64 = 1.0;
+
+/// Largest distance of a markout's cluster proportions from 1.0 that is
+/// still treated as rounding
+const PROPORTION_SUM_TOLERANCE: f64 = 1e-6;
+
+// Invariants checked by `validate_precomputed`
+pub const AGGREGATE_MATCHES_POOLS_INVARIANT: &str = "aggregate_running_total_matches_pools";
+pub const POOL_TOTALS_MATCH_CHECKPOINTS_INVARIANT: &str = "pool_totals_match_checkpoints";
+pub const CLUSTER_PROPORTIONS_SUM_INVARIANT: &str = "cluster_proportions_sum_to_one";
+pub const HISTOGRAM_COUNTS_MATCH_INVARIANT: &str = "histogram_counts_match_non_zero_blocks";
+pub const RUNNING_TOTALS_NON_DECREASING_INVARIANT: &str = "running_totals_non_decreasing";
+pub const RUNNING_TOTALS_MATCH_CHECKPOINTS_INVARIANT: &str = "running_totals_end_at_checkpoint_total";
+
+// Invariants checked by `validate_checkpoint_internals`
+pub const QUANTILES_ORDERED_INVARIANT: &str = "p25_le_median_le_p75";
+pub const NON_ZERO_PROPORTION_RANGE_INVARIANT: &str = "non_zero_proportion_in_unit_interval";
+pub const MOMENTS_VALID_INVARIANT: &str = "moments_finite_with_non_negative_std_dev";
+pub const NON_ZERO_SAMPLES_MATCH_BUCKETS_INVARIANT: &str = "non_zero_samples_match_non_zero_buckets";
+pub const MAX_COVERS_QUANTILES_INVARIANT: &str = "max_lvr_at_least_p75";
+
+/// Largest checkpoint/interval running total difference, in percent, that is
+/// not reported as a discrepancy
+pub const RUNNING_TOTAL_TOLERANCE_PERCENT: f64 = 1.0;
+
+#[derive(Debug, Serialize)]
 pub struct ValidationStats {
-    pub checkpoint_total: u64,
-    pub intervals_total: u64,
-    pub difference: u64,
+    pub checkpoint_total: i64,
+    pub intervals_total: i64,
+    /// `checkpoint_total - intervals_total`; negative when the intervals hold
+    /// more than the checkpoint, e.g. chunks counted twice after a partial
+    /// reprocess
+    pub difference: i64,
+    /// `difference` relative to the checkpoint total, or to the intervals
+    /// total when the checkpoint's is zero, keeping its sign
     pub difference_percent: f64,
     pub checkpoint_zero_count: u64,
     pub interval_zero_count: u64,
@@ -21,38 +69,312 @@ pub struct ValidationStats {
     pub tdigest_samples: u64,
     pub non_zero_samples: u64,
     pub bucket_sum_non_zero: u64,
+    pub negative_count: u64,
     pub sample_count_match: bool,
     pub non_zero_counts_consistent: bool,
+    pub max_lvr_cents: u64,
+    /// The digest's exact maximum; `None` for checkpoints that predate it
+    pub tracked_max_cents: Option<u64>,
+    /// The checkpoint's max LVR equals the digest's maximum, or there is none
+    pub max_lvr_consistent: bool,
+    /// NaN and infinite values the digest skipped, a sign of bad upstream data
+    pub rejected_samples: u64,
+    /// p25 <= median <= p75
+    pub quantiles_ordered: bool,
+    /// non_zero_proportion is within [0, 1]
+    pub non_zero_proportion_valid: bool,
+    /// The four moments are finite and std_dev is not negative
+    pub moments_valid: bool,
+    /// max_lvr_value is at least p75 when there are non-zero samples
+    pub max_covers_quantiles: bool,
+    /// Every invariant the checkpoint breaks on its own, without intervals
+    pub internal_violations: Vec<CheckpointInvariantViolation>,
+    /// Blocks from the pool's deployment to the end of each interval file's
+    /// range; `None` for the aggregate, which has no deployment block
+    pub expected_blocks: Option<u64>,
+    /// Interval `total_count` sum minus `expected_blocks`
+    pub interval_block_deviation: i64,
+    pub interval_block_deviation_percent: f64,
+    /// Checkpoint bucket-sum total minus `expected_blocks`
+    pub checkpoint_block_deviation: i64,
+    pub checkpoint_block_deviation_percent: f64,
+}
+
+/// A precomputed file that disagrees with the data it was derived from
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrecomputedViolation {
+    pub file: String,
+    pub invariant: &'static str,
+    pub detail: String,
+}
+
+impl fmt::Display for PrecomputedViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} violates {}: {}", self.file, self.invariant, self.detail)
+    }
+}
+
+/// A checkpoint whose columns contradict each other
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CheckpointInvariantViolation {
+    pub invariant: &'static str,
+    pub detail: String,
+}
+
+impl fmt::Display for CheckpointInvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "violates {}: {}", self.invariant, self.detail)
+    }
+}
+
+/// A pool whose realized (brontes) LVR is larger than its theoretical LVR at
+/// some markouts, which it is bounded by unless the brontes data is inflated
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RealizedExcess {
+    pub pair_address: String,
+    pub realized_total: i64,
+    pub min_theoretical_total: i64,
+    /// Excess over `min_theoretical_total`, relative to it
+    pub excess_percent: f64,
+    /// Theoretical markouts the realized total exceeds by more than the
+    /// tolerance, smallest total first
+    pub markouts: Vec<String>,
+}
+
+impl fmt::Display for RealizedExcess {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "brontes total {} of {} exceeds the smallest theoretical total {} by {:.2}% (markouts {})",
+            self.realized_total,
+            self.pair_address,
+            self.min_theoretical_total,
+            self.excess_percent,
+            self.markouts.join(", ")
+        )
+    }
+}
+
+/// Blocks `start_block..end_block` that no interval file covers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoverageGap {
+    pub start_block: u64,
+    pub end_block: u64,
+}
+
+impl fmt::Display for CoverageGap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "blocks {}..{}", self.start_block, self.end_block)
+    }
+}
+
+/// Two interval files that both cover `start_block..end_block`, so their
+/// intervals are counted twice
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoverageOverlap {
+    pub first: String,
+    pub second: String,
+    pub start_block: u64,
+    pub end_block: u64,
+}
+
+impl fmt::Display for CoverageOverlap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} and {} both cover blocks {}..{}", self.first, self.second, self.start_block, self.end_block)
+    }
+}
+
+/// How the `intervals/<start>_<end>.parquet` files tile the block range
+/// from the first file's start to the last file's end
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CoverageReport {
+    pub files: usize,
+    /// First and last block covered; `None` without interval files
+    pub range: Option<(u64, u64)>,
+    pub gaps: Vec<CoverageGap>,
+    pub overlaps: Vec<CoverageOverlap>,
+    /// Files off the `BLOCKS_PER_CHUNK` grid starting at the first file, or
+    /// shorter than a chunk without being the last
+    pub misaligned: Vec<String>,
+}
+
+impl CoverageReport {
+    /// From the (path, start_block, end_block) of every interval file
+    pub fn from_ranges(mut ranges: Vec<(String, u64, u64)>) -> Self {
+        ranges.sort_by(|a, b| (a.1, a.2, &a.0).cmp(&(b.1, b.2, &b.0)));
+        let mut report = Self { files: ranges.len(), ..Self::default() };
+        let Some(origin) = ranges.first().map(|range| range.1) else {
+            return report;
+        };
+
+        // The file reaching furthest so far, which any overlap is with
+        let mut furthest: Option<(&str, u64)> = None;
+        for (i, (path, start, end)) in ranges.iter().enumerate() {
+            let is_last = i + 1 == ranges.len();
+            if end <= start || (start - origin) % BLOCKS_PER_CHUNK != 0 || (end - start != BLOCKS_PER_CHUNK && !is_last) {
+                report.misaligned.push(path.clone());
+            }
+
+            match furthest {
+                Some((_, covered)) if *start > covered => report.gaps.push(CoverageGap { start_block: covered, end_block: *start }),
+                Some((other, covered)) if *start < covered => report.overlaps.push(CoverageOverlap {
+                    first: other.to_string(),
+                    second: path.clone(),
+                    start_block: *start,
+                    end_block: covered.min(*end),
+                }),
+                _ => {}
+            }
+            if furthest.is_none_or(|(_, covered)| *end > covered) {
+                furthest = Some((path, *end));
+            }
+        }
+
+        report.range = furthest.map(|(_, covered)| (origin, covered));
+        report
+    }
+
+    /// No block is missing or covered twice
+    pub fn is_complete(&self) -> bool {
+        self.gaps.is_empty() && self.overlaps.is_empty()
+    }
+}
+
+#[derive(Debug)]
+pub struct ValidationReport {
+    /// Checkpoint vs interval statistics, keyed by `{pair_address}_{markout_time}`
+    pub pools: HashMap<String, ValidationStats>,
+    pub precomputed: Vec<PrecomputedViolation>,
+    /// Checkpoint files bucketed before buckets excluded their upper bound,
+    /// where a value equal to a bound is counted one bucket lower
+    pub legacy_bucket_checkpoints: Vec<String>,
+    pub coverage: CoverageReport,
+    /// Pools whose brontes total is above a theoretical markout's, by pool
+    pub realized_excess: Vec<RealizedExcess>,
+    /// Objects the loaders skipped as unknown or malformed
+    pub objects: ObjectScan,
+    /// Everything above, classified by the validator's `ValidationPolicy`
+    pub findings: Vec<Finding>,
+}
+
+impl ValidationReport {
+    /// The worst finding's severity; `Info` without findings
+    pub fn severity(&self) -> Severity {
+        self.findings.iter().map(|finding| finding.severity).max().unwrap_or(Severity::Info)
+    }
+
+    /// Findings failing the run
+    pub fn failures(&self) -> impl Iterator<Item = &Finding> {
+        self.findings.iter().filter(|finding| finding.severity == Severity::Fail)
+    }
 }
 
 pub struct Validator {
     object_store: Arc<dyn ObjectStore>,
+    pools: Arc<PoolRegistry>,
+    concurrency: usize,
+    realized_tolerance_percent: f64,
+    policy: ValidationPolicy,
+    layout: OutputLayout,
 }
 
 #[derive(Debug)]
 struct CheckpointData {
-    running_total: u64,
+    running_total: i64,
     zero_count: u64,
     total_count: u64,
     exact_samples: u64,
     non_zero_bucket_sum: u64,
+    negative_count: u64,
+    max_lvr_value: u64,
+    max_nonzero_cents: Option<u64>,
+    rejected_samples: u64,
+    /// The block the checkpoint counted from, as precompute reads it
+    deployment_block: u64,
+    /// The blocks `start..end` the checkpoint declares it covered, if it
+    /// was stored with them
+    processed_range: Option<(u64, u64)>,
+    internal_violations: Vec<CheckpointInvariantViolation>,
 }
 
 #[derive(Debug, Default, Clone)]
 struct IntervalValidationData {
-    total_lvr: u64,
+    total_lvr: i64,
     non_zero_count: u64,
     total_count: u64,
 }
 
+impl ValidationStats {
+    /// The running totals differ by more than the default policy's failure
+    /// threshold in either direction
+    pub fn has_significant_difference(&self) -> bool {
+        self.difference_percent.abs() > RUNNING_TOTAL_TOLERANCE_PERCENT
+    }
+
+    /// Intervals and checkpoint each counted the pool's expected blocks
+    pub fn block_counts_match(&self) -> bool {
+        self.interval_block_deviation == 0 && self.checkpoint_block_deviation == 0
+    }
+
+    fn holds(&self, invariant: &str) -> bool {
+        !self.internal_violations.iter().any(|violation| violation.invariant == invariant)
+    }
+}
+
 impl Validator {
     pub fn new(object_store: Arc<dyn ObjectStore>) -> Self {
-        Self { object_store }
+        Self {
+            object_store,
+            pools: Arc::new(PoolRegistry::default()),
+            concurrency: DEFAULT_VALIDATION_CONCURRENCY,
+            realized_tolerance_percent: DEFAULT_REALIZED_EXCESS_TOLERANCE_PERCENT,
+            policy: ValidationPolicy::default(),
+            layout: OutputLayout::default(),
+        }
+    }
+
+    /// Files each loader reads at once; 1 reads them one after another
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// The deployment blocks expected block counts start from
+    pub fn with_pool_registry(mut self, pools: Arc<PoolRegistry>) -> Self {
+        self.pools = pools;
+        self
+    }
+
+    /// Which findings of `validate_all` warn and which fail
+    pub fn with_policy(mut self, policy: ValidationPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Layout of the interval files checked
+    pub fn with_layout(mut self, layout: OutputLayout) -> Self {
+        self.layout = layout;
+        self
     }
 
-    pub async fn validate_all(&self) -> Result<HashMap<String, ValidationStats>> {
-        let checkpoint_data = self.load_checkpoint_data().await?;
-        let interval_data = self.load_interval_data().await?;
+    pub fn policy(&self) -> &ValidationPolicy {
+        &self.policy
+    }
+
+    /// Percent a brontes total may exceed a theoretical markout's before it
+    /// is reported in `ValidationReport::realized_excess`
+    pub fn with_realized_tolerance(mut self, percent: f64) -> Self {
+        self.realized_tolerance_percent = percent;
+        self
+    }
+
+    pub async fn validate_all(&self) -> Result<ValidationReport> {
+        let ((checkpoint_data, legacy_bucket_checkpoints), interval_data, interval_ranges, objects) = tokio::try_join!(
+            self.load_checkpoint_data(),
+            self.load_interval_data(),
+            self.interval_ranges(),
+            self.scan_unknown_objects()
+        )?;
         
         let mut results = HashMap::new();
         
@@ -72,19 +394,43 @@ impl Validator {
                 0.0
             };
 
-            let difference = checkpoint.running_total.saturating_sub(interval.total_lvr);
-            let difference_percent = if checkpoint.running_total > 0 {
-                (difference as f64 / checkpoint.running_total as f64) * 100.0
+            let difference = checkpoint.running_total - interval.total_lvr;
+            let reference_total = if checkpoint.running_total != 0 {
+                checkpoint.running_total
+            } else {
+                interval.total_lvr
+            };
+            let difference_percent = if reference_total != 0 {
+                (difference as f64 / reference_total.unsigned_abs() as f64) * 100.0
             } else {
                 0.0
             };
 
-            // Check consistency between different non-zero count sources
-            let sample_count_match = checkpoint.exact_samples == interval.non_zero_count;
+            // Check consistency between different non-zero count sources.
+            // The digest and buckets only see positive LVR; intervals count
+            // negative blocks as non-zero too.
+            let sample_count_match = checkpoint.exact_samples + checkpoint.negative_count == interval.non_zero_count;
             let non_zero_counts_consistent = checkpoint.exact_samples == checkpoint.non_zero_bucket_sum &&
-                checkpoint.exact_samples == interval.non_zero_count;
+                sample_count_match;
+            // Both see every positive value, so they only differ if one was
+            // updated without the other
+            let max_lvr_consistent = checkpoint.max_nonzero_cents
+                .is_none_or(|max| max == checkpoint.max_lvr_value);
+
+            let expected_blocks = key
+                .rsplit_once('_')
+                .filter(|(pair_address, _)| *pair_address != AGGREGATE_POOL_ADDRESS)
+                .map(|_| expected_block_count(&interval_ranges, checkpoint.deployment_block, checkpoint.processed_range));
+            let deviation = |blocks: u64| {
+                let Some(expected) = expected_blocks else { return (0, 0.0) };
+                let deviation = blocks as i64 - expected as i64;
+                let percent = if expected > 0 { deviation as f64 / expected as f64 * 100.0 } else { 0.0 };
+                (deviation, percent)
+            };
+            let (interval_block_deviation, interval_block_deviation_percent) = deviation(interval.total_count);
+            let (checkpoint_block_deviation, checkpoint_block_deviation_percent) = deviation(checkpoint.total_count);
 
-            let stats = ValidationStats {
+            let mut stats = ValidationStats {
                 checkpoint_total: checkpoint.running_total,
                 intervals_total: interval.total_lvr,
                 difference,
@@ -96,137 +442,611 @@ impl Validator {
                 tdigest_samples: checkpoint.exact_samples,
                 non_zero_samples: interval.non_zero_count,
                 bucket_sum_non_zero: checkpoint.non_zero_bucket_sum,
+                negative_count: checkpoint.negative_count,
                 sample_count_match,
                 non_zero_counts_consistent,
+                max_lvr_cents: checkpoint.max_lvr_value,
+                tracked_max_cents: checkpoint.max_nonzero_cents,
+                max_lvr_consistent,
+                rejected_samples: checkpoint.rejected_samples,
+                quantiles_ordered: true,
+                non_zero_proportion_valid: true,
+                moments_valid: true,
+                max_covers_quantiles: true,
+                internal_violations: checkpoint.internal_violations,
+                expected_blocks,
+                interval_block_deviation,
+                interval_block_deviation_percent,
+                checkpoint_block_deviation,
+                checkpoint_block_deviation_percent,
             };
+            stats.quantiles_ordered = stats.holds(QUANTILES_ORDERED_INVARIANT);
+            stats.non_zero_proportion_valid = stats.holds(NON_ZERO_PROPORTION_RANGE_INVARIANT);
+            stats.moments_valid = stats.holds(MOMENTS_VALID_INVARIANT);
+            stats.max_covers_quantiles = stats.holds(MAX_COVERS_QUANTILES_INVARIANT);
 
-            self.log_validation_results(&key, &stats);
             results.insert(key, stats);
         }
 
-        Ok(results)
+        let precomputed = self.validate_precomputed().await?;
+        let coverage = CoverageReport::from_ranges(interval_ranges);
+        let realized_excess = self.check_realized_excess(&results);
+
+        let mut report = ValidationReport {
+            pools: results,
+            precomputed,
+            legacy_bucket_checkpoints,
+            coverage,
+            realized_excess,
+            objects,
+            findings: Vec::new(),
+        };
+        report.findings = self.policy.classify(&report);
+        log_findings(&report);
+        Ok(report)
+    }
+
+    /// Compares each pool's brontes checkpoint total with its theoretical
+    /// markouts' totals. Pools without a brontes checkpoint or without any
+    /// theoretical one are skipped.
+    pub fn check_realized_excess(&self, pools: &HashMap<String, ValidationStats>) -> Vec<RealizedExcess> {
+        let brontes = MarkoutTime::Brontes.to_string();
+        let mut realized_totals = HashMap::new();
+        let mut theoretical_totals: BTreeMap<&str, Vec<(i64, &str)>> = BTreeMap::new();
+        for (key, stats) in pools {
+            let Some((pair_address, markout_time)) = key.rsplit_once('_') else {
+                continue;
+            };
+            if markout_time == brontes {
+                realized_totals.insert(pair_address, stats.checkpoint_total);
+            } else {
+                theoretical_totals.entry(pair_address).or_default().push((stats.checkpoint_total, markout_time));
+            }
+        }
+
+        let excess_percent = |realized: i64, theoretical: i64| {
+            let excess = (realized - theoretical) as f64;
+            if theoretical != 0 {
+                excess / theoretical.unsigned_abs() as f64 * 100.0
+            } else if excess > 0.0 {
+                f64::INFINITY
+            } else {
+                0.0
+            }
+        };
+
+        let mut findings = Vec::new();
+        for (pair_address, mut theoretical) in theoretical_totals {
+            let Some(&realized) = realized_totals.get(pair_address) else { continue };
+            theoretical.sort();
+            let Some(&(min_theoretical_total, _)) = theoretical.first() else { continue };
+
+            let markouts: Vec<String> = theoretical
+                .iter()
+                .filter(|(total, _)| excess_percent(realized, *total) > self.realized_tolerance_percent)
+                .map(|(_, markout_time)| markout_time.to_string())
+                .collect();
+            if !markouts.is_empty() {
+                findings.push(RealizedExcess {
+                    pair_address: pair_address.to_string(),
+                    realized_total: realized,
+                    min_theoretical_total,
+                    excess_percent: excess_percent(realized, min_theoretical_total),
+                    markouts,
+                });
+            }
+        }
+        findings
+    }
+
+    /// Reads the block range of every interval file from its name and
+    /// reports the blocks no file covers, files covering the same blocks and
+    /// files off the chunk layout. Totals reconcile over a missing file, so
+    /// only this catches it.
+    pub async fn check_interval_coverage(&self) -> Result<CoverageReport> {
+        let coverage = CoverageReport::from_ranges(self.interval_ranges().await?);
+        log_coverage(&coverage);
+        Ok(coverage)
+    }
+
+    /// (path, start_block, end_block) of every interval file, from its name
+    async fn interval_ranges(&self) -> Result<Vec<(String, u64, u64)>> {
+        Ok(self
+            .list_interval_files()
+            .await?
+            .into_iter()
+            .map(|(location, start, end)| (location.to_string(), start, end))
+            .collect())
+    }
+
+    /// Cross-checks the precomputed files that exist against each other and
+    /// against the checkpoints they were built from. Missing files are
+    /// skipped, so this can run before precomputation has.
+    pub async fn validate_precomputed(&self) -> Result<Vec<PrecomputedViolation>> {
+        let mut violations = Vec::new();
+        let checkpoints = self.load_precompute_sources().await?;
+
+        self.check_aggregate_running_totals(&mut violations).await?;
+        self.check_running_total_series(&checkpoints, &mut violations).await?;
+        self.check_pool_totals(&checkpoints, &mut violations).await?;
+        self.check_cluster_proportions(&mut violations).await?;
+        self.check_histogram_counts(&checkpoints, &mut violations).await?;
+
+        if violations.is_empty() {
+            info!("Precomputed files are consistent with their sources");
+        }
+        Ok(violations)
+    }
+
+    /// The last aggregate running total of each markout must equal the sum
+    /// of every pool's last running total for that markout
+    async fn check_aggregate_running_totals(&self, violations: &mut Vec<PrecomputedViolation>) -> Result<()> {
+        let aggregate_kind = DatasetKind::AggregateRunningTotals;
+        let (Some(individual), Some(aggregate)) = (
+            self.read_precomputed(DatasetKind::IndividualRunningTotals).await?,
+            self.read_precomputed(aggregate_kind).await?,
+        ) else {
+            return Ok(());
+        };
+
+        // Latest (block_number, running_total) per pool/markout and per markout
+        let mut pool_finals: HashMap<(String, String), (u64, i64)> = HashMap::new();
+        for batch in &individual {
+            let blocks = uint64_column(batch, "block_number")?;
+            let markouts = string_column(batch, "markout_time")?;
+            let pools = string_column(batch, "pool_address")?;
+            let totals = int64_column(batch, "running_total_cents")?;
+            for i in 0..batch.num_rows() {
+                let latest = pool_finals
+                    .entry((pools.value(i).to_string(), markouts.value(i).to_string()))
+                    .or_insert((0, 0));
+                if blocks.value(i) >= latest.0 {
+                    *latest = (blocks.value(i), totals.value(i));
+                }
+            }
+        }
+        let mut expected: BTreeMap<String, i64> = BTreeMap::new();
+        for ((_, markout_time), (_, total)) in pool_finals {
+            *expected.entry(markout_time).or_insert(0) += total;
+        }
+
+        let mut aggregate_finals: BTreeMap<String, (u64, i64)> = BTreeMap::new();
+        for batch in &aggregate {
+            let blocks = uint64_column(batch, "block_number")?;
+            let markouts = string_column(batch, "markout_time")?;
+            let totals = int64_column(batch, "running_total_cents")?;
+            for i in 0..batch.num_rows() {
+                let latest = aggregate_finals.entry(markouts.value(i).to_string()).or_insert((0, 0));
+                if blocks.value(i) >= latest.0 {
+                    *latest = (blocks.value(i), totals.value(i));
+                }
+            }
+        }
+
+        for markout_time in expected.keys().chain(aggregate_finals.keys()).collect::<std::collections::BTreeSet<_>>() {
+            let pools_total = expected.get(markout_time).copied().unwrap_or(0);
+            let aggregate_total = aggregate_finals.get(markout_time).map(|(_, total)| *total).unwrap_or(0);
+            if pools_total != aggregate_total {
+                violations.push(PrecomputedViolation {
+                    file: aggregate_kind.path().to_string(),
+                    invariant: AGGREGATE_MATCHES_POOLS_INVARIANT,
+                    detail: format!(
+                        "markout {}: final aggregate total {} but pool totals sum to {}",
+                        markout_time, aggregate_total, pools_total
+                    ),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Each running total series, per pool and markout and per markout for
+    /// the aggregate, must end at its checkpoint's running total, within the
+    /// policy's warning threshold. LVR can be negative, so a series only has
+    /// to be non-decreasing when its checkpoint saw no negative block.
+    async fn check_running_total_series(
+        &self,
+        checkpoints: &HashMap<(String, String), CheckpointData>,
+        violations: &mut Vec<PrecomputedViolation>,
+    ) -> Result<()> {
+        for kind in [DatasetKind::IndividualRunningTotals, DatasetKind::AggregateRunningTotals] {
+            let Some(batches) = self.read_precomputed(kind).await? else {
+                continue;
+            };
+
+            // (block_number, running_total) by (pool, markout)
+            let mut series: BTreeMap<(String, String), Vec<(u64, i64)>> = BTreeMap::new();
+            for batch in &batches {
+                let blocks = uint64_column(batch, "block_number")?;
+                let markouts = string_column(batch, "markout_time")?;
+                let pools = match kind {
+                    DatasetKind::IndividualRunningTotals => Some(string_column(batch, "pool_address")?),
+                    _ => None,
+                };
+                let totals = int64_column(batch, "running_total_cents")?;
+                for i in 0..batch.num_rows() {
+                    let pool = pools.map_or(AGGREGATE_POOL_ADDRESS, |pools| pools.value(i));
+                    series
+                        .entry((pool_key(pool), markouts.value(i).to_string()))
+                        .or_default()
+                        .push((blocks.value(i), totals.value(i)));
+                }
+            }
+
+            for ((pool, markout_time), mut points) in series {
+                points.sort_by_key(|&(block, _)| block);
+                let checkpoint = checkpoints.get(&(pool.clone(), markout_time.clone()));
+
+                if checkpoint.is_some_and(|checkpoint| checkpoint.negative_count == 0) {
+                    if let Some(pair) = points.windows(2).find(|pair| pair[1].1 < pair[0].1) {
+                        violations.push(PrecomputedViolation {
+                            file: kind.path().to_string(),
+                            invariant: RUNNING_TOTALS_NON_DECREASING_INVARIANT,
+                            detail: format!(
+                                "{} markout {}: running total falls from {} at block {} to {} at block {}",
+                                pool, markout_time, pair[0].1, pair[0].0, pair[1].1, pair[1].0
+                            ),
+                        });
+                    }
+                }
+
+                let (Some(checkpoint), Some(&(last_block, last_total))) = (checkpoint, points.last()) else {
+                    continue;
+                };
+                let difference = (last_total - checkpoint.running_total).unsigned_abs();
+                let percent = if checkpoint.running_total != 0 {
+                    difference as f64 / checkpoint.running_total.unsigned_abs() as f64 * 100.0
+                } else if difference != 0 {
+                    f64::INFINITY
+                } else {
+                    0.0
+                };
+                if difference != 0 && percent > self.policy.total_pct_warn {
+                    violations.push(PrecomputedViolation {
+                        file: kind.path().to_string(),
+                        invariant: RUNNING_TOTALS_MATCH_CHECKPOINTS_INVARIANT,
+                        detail: format!(
+                            "{} markout {}: running total ends at {} at block {} but checkpoint running_total is {}",
+                            pool, markout_time, last_total, last_block, checkpoint.running_total
+                        ),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn check_pool_totals(
+        &self,
+        checkpoints: &HashMap<(String, String), CheckpointData>,
+        violations: &mut Vec<PrecomputedViolation>,
+    ) -> Result<()> {
+        let kind = DatasetKind::PoolTotals;
+        let Some(batches) = self.read_precomputed(kind).await? else {
+            return Ok(());
+        };
+
+        for batch in &batches {
+            let pools = string_column(batch, "pool_address")?;
+            let markouts = string_column(batch, "markout_time")?;
+            let totals = int64_column(batch, "total_lvr_cents")?;
+            for i in 0..batch.num_rows() {
+                let key = (pools.value(i).to_string(), markouts.value(i).to_string());
+                let detail = match checkpoints.get(&key) {
+                    Some(checkpoint) if checkpoint.running_total == totals.value(i) => continue,
+                    Some(checkpoint) => format!(
+                        "{} markout {}: total_lvr_cents {} but checkpoint running_total is {}",
+                        key.0, key.1, totals.value(i), checkpoint.running_total
+                    ),
+                    None => format!("{} markout {}: no checkpoint found", key.0, key.1),
+                };
+                violations.push(PrecomputedViolation {
+                    file: kind.path().to_string(),
+                    invariant: POOL_TOTALS_MATCH_CHECKPOINTS_INVARIANT,
+                    detail,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Proportions of each markout with any LVR must sum to 1.0
+    async fn check_cluster_proportions(&self, violations: &mut Vec<PrecomputedViolation>) -> Result<()> {
+        let kind = DatasetKind::ClusterProportions;
+        let Some(batches) = self.read_precomputed(kind).await? else {
+            return Ok(());
+        };
+
+        let mut markouts: BTreeMap<String, (i64, f64)> = BTreeMap::new();
+        for batch in &batches {
+            let markout_times = string_column(batch, "markout_time")?;
+            let totals = int64_column(batch, "total_lvr_cents")?;
+            let proportions = float64_column(batch, "proportion")?;
+            for i in 0..batch.num_rows() {
+                let entry = markouts.entry(markout_times.value(i).to_string()).or_insert((0, 0.0));
+                entry.0 += totals.value(i);
+                entry.1 += proportions.value(i);
+            }
+        }
+
+        for (markout_time, (total_lvr_cents, proportion_sum)) in markouts {
+            if total_lvr_cents > 0 && (proportion_sum - 1.0).abs() > PROPORTION_SUM_TOLERANCE {
+                violations.push(PrecomputedViolation {
+                    file: kind.path().to_string(),
+                    invariant: CLUSTER_PROPORTIONS_SUM_INVARIANT,
+                    detail: format!("markout {}: proportions sum to {}", markout_time, proportion_sum),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Each pool's histogram must account for every non-zero block its
+    /// checkpoint counted
+    async fn check_histogram_counts(
+        &self,
+        checkpoints: &HashMap<(String, String), CheckpointData>,
+        violations: &mut Vec<PrecomputedViolation>,
+    ) -> Result<()> {
+        let kind = DatasetKind::Histograms;
+        let Some(batches) = self.read_precomputed(kind).await? else {
+            return Ok(());
+        };
+
+        let mut histogram_counts: BTreeMap<(String, String), u64> = BTreeMap::new();
+        for batch in &batches {
+            let pools = string_column(batch, "pool_address")?;
+            let markouts = string_column(batch, "markout_time")?;
+            let counts = uint64_column(batch, "count")?;
+            for i in 0..batch.num_rows() {
+                *histogram_counts
+                    .entry((pools.value(i).to_string(), markouts.value(i).to_string()))
+                    .or_insert(0) += counts.value(i);
+            }
+        }
+
+        for ((pool_address, markout_time), count) in histogram_counts {
+            let non_zero_blocks = checkpoints
+                .get(&(pool_address.clone(), markout_time.clone()))
+                .map(|checkpoint| checkpoint.non_zero_bucket_sum)
+                .unwrap_or(0);
+            if count != non_zero_blocks {
+                violations.push(PrecomputedViolation {
+                    file: kind.path().to_string(),
+                    invariant: HISTOGRAM_COUNTS_MATCH_INVARIANT,
+                    detail: format!(
+                        "{} markout {}: histogram counts sum to {} but checkpoint has {} non-zero blocks",
+                        pool_address, markout_time, count, non_zero_blocks
+                    ),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a precomputed file in full, or `None` if it has not been written
+    async fn read_precomputed(&self, kind: DatasetKind) -> Result<Option<Vec<RecordBatch>>> {
+        let bytes = match self.object_store.get(&kind.path()).await {
+            Ok(result) => result.bytes().await?,
+            Err(object_store::Error::NotFound { .. }) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let reader = parquet_reader(bytes, &kind.path(), BATCH_SIZE)?;
+        let batches = reader
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .with_context(|| format!("Failed to read {}", kind.path()))?;
+        Ok(Some(batches))
     }
 
-    async fn load_checkpoint_data(&self) -> Result<HashMap<String, CheckpointData>> {
+    /// Checkpoints keyed the way precomputed files name them: lowercase pool
+    /// address and the parsed markout time
+    async fn load_precompute_sources(&self) -> Result<HashMap<(String, String), CheckpointData>> {
         let mut checkpoint_data = HashMap::new();
-        let checkpoint_prefix = object_store::path::Path::from("checkpoints");
-        let mut checkpoint_files = self.object_store.list(Some(&checkpoint_prefix));
 
-        while let Some(meta) = checkpoint_files.next().await {
-            let meta = meta?;
-            let bytes = self.object_store.get(&meta.location).await?.bytes().await?;
-            let reader = ParquetRecordBatchReader::try_new(bytes, BATCH_SIZE)?;
+        for location in self.list_checkpoint_files().await? {
+            let bytes = self.object_store.get(&location).await?.bytes().await?;
+            let reader = parquet_reader(bytes, &location, BATCH_SIZE)?;
 
             for batch in reader {
-                let batch = batch?;
-                let data = self.extract_checkpoint_batch_data(&batch)?;
-                checkpoint_data.insert(data.0, data.1);
+                for snapshot in CheckpointSnapshot::from_record_batch(&batch?)
+                    .with_context(|| format!("Failed to read checkpoint {}", location))?
+                {
+                    let key = (pool_key(&snapshot.pair_address), snapshot.markout_time.to_string());
+                    checkpoint_data.insert(key, self.checkpoint_data(&snapshot));
+                }
             }
         }
 
         Ok(checkpoint_data)
     }
 
-    async fn load_interval_data(&self) -> Result<HashMap<String, IntervalValidationData>> {
-        let mut interval_data = HashMap::new();
-        let intervals_prefix = object_store::path::Path::from("intervals");
-        let mut interval_files = self.object_store.list(Some(&intervals_prefix));
+    /// Checks the footer of every precomputed file that exists against the
+    /// schema versions this binary reads
+    pub async fn check_precomputed_schemas(&self) -> Result<()> {
+        for kind in DatasetKind::ALL {
+            let bytes = match self.object_store.get(&kind.path()).await {
+                Ok(result) => result.bytes().await?,
+                Err(object_store::Error::NotFound { .. }) => continue,
+                Err(e) => return Err(e.into()),
+            };
+            let metadata = read_footer_metadata(bytes)
+                .with_context(|| format!("Failed to read footer of {}", kind.path()))?;
+            kind.check_footer(&metadata)?;
+        }
+        info!("Precomputed files match the current schema versions");
+        Ok(())
+    }
 
-        while let Some(meta) = interval_files.next().await {
-            let meta = meta?;
-            let bytes = self.object_store.get(&meta.location).await?.bytes().await?;
-            let reader = ParquetRecordBatchReader::try_new(bytes, BATCH_SIZE)?;
+    /// `list_interval_files` in this validator's layout, without the objects
+    /// `classify_object` flags as stray; those are `scan_unknown_objects`'
+    /// to report
+    async fn list_interval_files(&self) -> Result<Vec<(object_store::path::Path, u64, u64)>> {
+        Ok(list_interval_files(self.object_store.as_ref(), self.layout)
+            .await?
+            .into_iter()
+            .filter(|(location, _, _)| classify_object(location.as_ref(), &self.pools).is_none())
+            .collect())
+    }
 
-            for batch in reader {
-                let batch = batch?;
-                self.process_interval_batch(&batch, &mut interval_data)?;
+    /// `list_checkpoints`, likewise without stray objects
+    async fn list_checkpoint_files(&self) -> Result<Vec<object_store::path::Path>> {
+        Ok(list_checkpoints(self.object_store.as_ref())
+            .await?
+            .into_iter()
+            .filter(|location| classify_object(location.as_ref(), &self.pools).is_none())
+            .collect())
+    }
+
+    /// Classifies every object under `SCANNED_PREFIXES` by its name and the
+    /// pool registry. Stray objects are skipped by the numeric checks, so
+    /// they are reported here with what to do about them instead.
+    pub async fn scan_unknown_objects(&self) -> Result<ObjectScan> {
+        let mut scan = ObjectScan::default();
+        for prefix in SCANNED_PREFIXES {
+            let prefix = object_store::path::Path::from(prefix);
+            let files: Vec<_> = self.object_store.list(Some(&prefix)).try_collect().await?;
+            for meta in files {
+                match classify_object(meta.location.as_ref(), &self.pools) {
+                    Some(stray) => scan.stray.push(stray),
+                    None => scan.recognized += 1,
+                }
             }
         }
+        scan.stray.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(scan)
+    }
+
+    /// Checkpoints keyed by `{pair_address}_{markout_time}`, and the files
+    /// written under the old bucket rule
+    async fn load_checkpoint_data(&self) -> Result<(HashMap<String, CheckpointData>, Vec<String>)> {
+        let checkpoint_data = DashMap::new();
+        let files = self.list_checkpoint_files().await?;
+
+        let mut legacy_bucket_checkpoints = futures::stream::iter(files)
+            .map(|location| {
+                let checkpoint_data = &checkpoint_data;
+                async move {
+                    let bytes = self.object_store.get(&location).await?.bytes().await?;
+                    let metadata = read_footer_metadata(bytes.clone())
+                        .with_context(|| format!("Failed to read footer of {}", location))?;
+                    let is_legacy =
+                        metadata.get(BUCKET_RULE_METADATA_KEY).map(String::as_str) != Some(HALF_OPEN_BUCKET_RULE);
+                    let reader = parquet_reader(bytes, &location, BATCH_SIZE)?;
+
+                    for batch in reader {
+                        for snapshot in CheckpointSnapshot::from_record_batch(&batch?)
+                            .with_context(|| format!("Failed to read checkpoint {}", location))?
+                        {
+                            let key = format!("{}_{}", snapshot.pair_address, snapshot.markout_time);
+                            checkpoint_data.insert(key, self.checkpoint_data(&snapshot));
+                        }
+                    }
+                    Ok::<_, anyhow::Error>(is_legacy.then(|| location.to_string()))
+                }
+            })
+            .buffer_unordered(self.concurrency)
+            .try_filter_map(|legacy| async move { Ok(legacy) })
+            .try_collect::<Vec<_>>()
+            .await?;
+
+        // Reads finish in any order
+        legacy_bucket_checkpoints.sort();
+        Ok((checkpoint_data.into_iter().collect(), legacy_bucket_checkpoints))
+    }
+
+    async fn load_interval_data(&self) -> Result<HashMap<String, IntervalValidationData>> {
+        let interval_data: DashMap<String, IntervalValidationData> = DashMap::new();
+        let files: Vec<_> = self.list_interval_files().await?.into_iter().map(|(location, _, _)| location).collect();
+
+        futures::stream::iter(files)
+            .map(|location| {
+                let interval_data = &interval_data;
+                async move {
+                    let bytes = self.object_store.get(&location).await?.bytes().await?;
+                    let reader = parquet_reader(bytes, &location, BATCH_SIZE)?;
+
+                    let mut file_data = HashMap::new();
+                    for batch in reader {
+                        self.process_interval_batch(&batch?, &mut file_data)?;
+                    }
+                    // Sums of integers, so the order files merge in does
+                    // not matter
+                    for (key, data) in file_data {
+                        let mut total = interval_data.entry(key).or_default();
+                        total.total_lvr += data.total_lvr;
+                        total.non_zero_count += data.non_zero_count;
+                        total.total_count += data.total_count;
+                    }
+                    Ok::<_, anyhow::Error>(())
+                }
+            })
+            .buffer_unordered(self.concurrency)
+            .try_collect::<()>()
+            .await?;
+
+        Ok(interval_data.into_iter().collect())
+    }
+
+    fn checkpoint_data(&self, snapshot: &CheckpointSnapshot) -> CheckpointData {
+        CheckpointData {
+            running_total: snapshot.running_total.as_i64(),
+            zero_count: snapshot.total_bucket_0,
+            total_count: snapshot.total_blocks(),
+            exact_samples: snapshot.non_zero_samples,
+            non_zero_bucket_sum: snapshot.positive_bucket_counts().iter().sum(),
+            negative_count: snapshot.total_bucket_negative,
+            max_lvr_value: snapshot.max_lvr_value,
+            max_nonzero_cents: snapshot.max_nonzero_cents,
+            rejected_samples: snapshot.rejected_samples,
+            deployment_block: snapshot.deployment_block_or(self.pools.deployment_block(&snapshot.pair_address)),
+            processed_range: snapshot.processed_range(),
+            internal_violations: self.validate_checkpoint_internals(snapshot),
+        }
+    }
+
+    /// Checks a checkpoint's quantiles, proportion, moments and counts
+    /// against each other. These only break when a column is corrupted or
+    /// written by a buggy snapshot, so each is reported by name.
+    pub fn validate_checkpoint_internals(&self, snapshot: &CheckpointSnapshot) -> Vec<CheckpointInvariantViolation> {
+        let mut violations = Vec::new();
+        let mut violate = |invariant, detail| violations.push(CheckpointInvariantViolation { invariant, detail });
+
+        let (p25, median, p75) = (snapshot.percentile_25_cents, snapshot.median_cents, snapshot.percentile_75_cents);
+        if !(p25 <= median && median <= p75) {
+            violate(QUANTILES_ORDERED_INVARIANT, format!("p25={} median={} p75={}", p25, median, p75));
+        }
 
-        Ok(interval_data)
-    }
-
-    fn extract_checkpoint_batch_data(&self, batch: &arrow::record_batch::RecordBatch) 
-        -> Result<(String, CheckpointData)> {
-        let pair_address = batch
-            .column(batch.schema().index_of("pair_address")?)
-            .as_any()
-            .downcast_ref::<arrow::array::StringArray>()
-            .context("Failed to get pair_address column")?
-            .value(0);
-
-        let markout_time = batch
-            .column(batch.schema().index_of("markout_time")?)
-            .as_any()
-            .downcast_ref::<arrow::array::StringArray>()
-            .context("Failed to get markout_time column")?
-            .value(0);
-
-        let running_total = batch
-            .column(batch.schema().index_of("running_total")?)
-            .as_any()
-            .downcast_ref::<arrow::array::UInt64Array>()
-            .context("Failed to get running_total column")?
-            .value(0);
-
-        let zero_count = batch
-            .column(batch.schema().index_of("total_bucket_0")?)
-            .as_any()
-            .downcast_ref::<arrow::array::UInt64Array>()
-            .context("Failed to get total_bucket_0 column")?
-            .value(0);
-
-        let exact_samples = batch
-            .column(batch.schema().index_of("non_zero_samples")?)
-            .as_any()
-            .downcast_ref::<arrow::array::UInt64Array>()
-            .context("Failed to get non_zero_samples count")?
-            .value(0);
-
-        // Calculate total count and non-zero bucket sum
-        let (total_count, non_zero_bucket_sum) = self.get_bucket_counts(batch)?;
-
-        Ok((
-            format!("{}_{}", pair_address, markout_time),
-            CheckpointData {
-                running_total,
-                zero_count,
-                total_count,
-                exact_samples,
-                non_zero_bucket_sum,
-            },
-        ))
-    }
-
-    fn get_bucket_counts(&self, batch: &arrow::record_batch::RecordBatch) -> Result<(u64, u64)> {
-        let mut total_count = 0u64;
-        let mut non_zero_sum = 0u64;
-        
-        let bucket_names = [
-            "total_bucket_0",
-            "total_bucket_0_10",
-            "total_bucket_10_100",
-            "total_bucket_100_500",
-            "total_bucket_500_1000",
-            "total_bucket_1000_10000",
-            "total_bucket_10000_plus",
+        let non_zero_proportion = snapshot.non_zero_proportion;
+        if !(0.0..=1.0).contains(&non_zero_proportion) {
+            violate(NON_ZERO_PROPORTION_RANGE_INVARIANT, format!("non_zero_proportion={}", non_zero_proportion));
+        }
+
+        let moments = [
+            ("mean", snapshot.mean),
+            ("std_dev", snapshot.std_dev),
+            ("skewness", snapshot.skewness),
+            ("kurtosis", snapshot.kurtosis),
         ];
+        if moments.iter().any(|(_, value)| !value.is_finite()) || snapshot.std_dev < 0.0 {
+            let detail = moments.iter().map(|(name, value)| format!("{}={}", name, value)).collect::<Vec<_>>();
+            violate(MOMENTS_VALID_INVARIANT, detail.join(" "));
+        }
 
-        for (idx, name) in bucket_names.iter().enumerate() {
-            let count = batch
-                .column(batch.schema().index_of(name)?)
-                .as_any()
-                .downcast_ref::<arrow::array::UInt64Array>()
-                .context(format!("Failed to get {} column", name))?
-                .value(0);
-            
-            total_count += count;
-            if idx > 0 {  // Skip zero bucket when summing non-zero counts
-                non_zero_sum += count;
-            }
+        let non_zero_samples = snapshot.non_zero_samples;
+        let non_zero_bucket_sum: u64 = snapshot.positive_bucket_counts().iter().sum();
+        if non_zero_samples != non_zero_bucket_sum {
+            violate(
+                NON_ZERO_SAMPLES_MATCH_BUCKETS_INVARIANT,
+                format!("non_zero_samples={} non-zero bucket sum={}", non_zero_samples, non_zero_bucket_sum),
+            );
         }
 
-        Ok((total_count, non_zero_sum))
+        let max_lvr_value = snapshot.max_lvr_value;
+        if non_zero_samples > 0 && max_lvr_value < p75 {
+            violate(MAX_COVERS_QUANTILES_INVARIANT, format!("max_lvr_value={} p75={}", max_lvr_value, p75));
+        }
+
+        violations
     }
 
     fn process_interval_batch(
@@ -234,109 +1054,98 @@ impl Validator {
         batch: &arrow::record_batch::RecordBatch,
         interval_data: &mut HashMap<String, IntervalValidationData>,
     ) -> Result<()> {
-        let pair_addresses = batch
-            .column(batch.schema().index_of("pair_address")?)
-            .as_any()
-            .downcast_ref::<arrow::array::StringArray>()
-            .context("Failed to get pair_address column")?;
-
-        let markout_times = batch
-            .column(batch.schema().index_of("markout_time")?)
-            .as_any()
-            .downcast_ref::<arrow::array::StringArray>()
-            .context("Failed to get markout_time column")?;
-
-        let total_lvr_cents = batch
-            .column(batch.schema().index_of("total_lvr_cents")?)
-            .as_any()
-            .downcast_ref::<arrow::array::UInt64Array>()
-            .context("Failed to get total_lvr_cents column")?;
-
-        let total_counts = batch
-            .column(batch.schema().index_of("total_count")?)
-            .as_any()
-            .downcast_ref::<arrow::array::UInt64Array>()
-            .context("Failed to get total_count column")?;
-
-        let non_zero_counts = batch
-            .column(batch.schema().index_of("non_zero_count")?)
-            .as_any()
-            .downcast_ref::<arrow::array::UInt64Array>()
-            .context("Failed to get non_zero_count column")?;
-
-        for i in 0..batch.num_rows() {
-            let key = format!("{}_{}", pair_addresses.value(i), markout_times.value(i));
-            let data = interval_data.entry(key).or_default();
-            
-            data.total_lvr += total_lvr_cents.value(i);
-            data.total_count += total_counts.value(i);
-            data.non_zero_count += non_zero_counts.value(i);
+        for interval in IntervalData::from_record_batch(batch)? {
+            // The aggregate checkpoint covers every pool's intervals
+            for pair_address in [interval.pair_address.as_str(), AGGREGATE_POOL_ADDRESS] {
+                let data = interval_data.entry(format!("{}_{}", pair_address, interval.markout_time)).or_default();
+                data.total_lvr += interval.total_lvr_cents.as_i64();
+                data.total_count += interval.total_count;
+                data.non_zero_count += interval.non_zero_count;
+            }
         }
 
         Ok(())
     }
+}
 
-    fn log_validation_results(&self, key: &str, stats: &ValidationStats) {
-        let mut errors = Vec::new();
-        
-        // Check for non-zero count inconsistencies
-        if !stats.non_zero_counts_consistent {
-            errors.push(format!(
-                "Non-zero count mismatch: TDigest={}, Intervals={}, Bucket sum={}", 
-                stats.tdigest_samples, 
-                stats.non_zero_samples, 
-                stats.bucket_sum_non_zero
-            ));
-        }
-    
-        // Check for total value discrepancies
-        if stats.difference != 0 {
-            errors.push(format!(
-                "Total mismatch: Checkpoint={}, Intervals={}, Difference={}({:.2}%)", 
-                stats.checkpoint_total, 
-                stats.intervals_total, 
-                stats.difference, 
-                stats.difference_percent
-            ));
-        }
-    
-        // Check for zero count discrepancies
-        let zero_count_difference = (stats.checkpoint_zero_count as i64 - stats.interval_zero_count as i64).abs();
-        if zero_count_difference != 0 {
-            errors.push(format!(
-                "Zero count mismatch: Checkpoint={}, Intervals={}, Difference={}", 
-                stats.checkpoint_zero_count, 
-                stats.interval_zero_count, 
-                zero_count_difference
-            ));
-        }
-    
-        // Log appropriate message based on validation results
-        if errors.is_empty() {
-            info!(
-                "Validation passed for {}: Total {}, Non-zero counts consistent ({} samples), Zero count: {}", 
-                key, 
-                stats.checkpoint_total, 
-                stats.tdigest_samples, 
-                stats.checkpoint_zero_count
-            );
-        } else {
-            // Determine if discrepancies are significant
-            let has_significant_errors = stats.difference_percent.abs() > 1.0 || !stats.non_zero_counts_consistent;
-            
-            if has_significant_errors {
-                error!(
-                    "Significant discrepancies for {}:\n{}", 
-                    key,
-                    errors.join("\n")
-                );
-            } else {
-                warn!(
-                    "Minor discrepancies for {}:\n{}", 
-                    key,
-                    errors.join("\n")
-                );
-            }
+fn log_findings(report: &ValidationReport) {
+    for finding in &report.findings {
+        match finding.severity {
+            Severity::Info => info!("{}", finding),
+            Severity::Warn => warn!("{}", finding),
+            Severity::Fail => error!("{}", finding),
         }
     }
-}
\ No newline at end of file
+
+    let mut passed: Vec<_> = report
+        .pools
+        .iter()
+        .filter(|(key, _)| !report.findings.iter().any(|finding| &finding.subject == *key))
+        .collect();
+    passed.sort_by(|a, b| a.0.cmp(b.0));
+    for (key, stats) in passed {
+        info!(
+            "Validation passed for {}: Total {}, Non-zero counts consistent ({} samples), Zero count: {}",
+            key, stats.checkpoint_total, stats.tdigest_samples, stats.checkpoint_zero_count
+        );
+    }
+}
+
+fn log_coverage(coverage: &CoverageReport) {
+    for gap in &coverage.gaps {
+        error!("Interval coverage gap: no file covers {}", gap);
+    }
+    for overlap in &coverage.overlaps {
+        error!("Interval coverage overlap: {}", overlap);
+    }
+    if !coverage.misaligned.is_empty() {
+        warn!("Interval files off the chunk layout: {}", coverage.misaligned.join(", "));
+    }
+    if coverage.is_complete() {
+        info!("{} interval files cover {:?} without gaps or overlaps", coverage.files, coverage.range);
+    }
+}
+
+/// Blocks of the interval file ranges at or after `deployment_block` and
+/// inside the checkpoint's processed range, which is what a pool's
+/// `total_count` sums to when every block is counted once. Interval files
+/// outside the range, such as those of another run, are left out; without
+/// a declared range every file counts.
+fn expected_block_count(ranges: &[(String, u64, u64)], deployment_block: u64, processed_range: Option<(u64, u64)>) -> u64 {
+    let (first, end_of_range) = processed_range.unwrap_or((0, u64::MAX));
+    ranges.iter()
+        .map(|(_, start, end)| (*end).min(end_of_range).saturating_sub((*start).max(deployment_block).max(first)))
+        .sum()
+}
+
+fn string_column<'a>(batch: &'a RecordBatch, name: &str) -> Result<&'a StringArray> {
+    batch
+        .column(batch.schema().index_of(name)?)
+        .as_any()
+        .downcast_ref::<StringArray>()
+        .with_context(|| format!("Failed to get {} column", name))
+}
+
+fn uint64_column<'a>(batch: &'a RecordBatch, name: &str) -> Result<&'a UInt64Array> {
+    batch
+        .column(batch.schema().index_of(name)?)
+        .as_any()
+        .downcast_ref::<UInt64Array>()
+        .with_context(|| format!("Failed to get {} column", name))
+}
+
+fn int64_column<'a>(batch: &'a RecordBatch, name: &str) -> Result<&'a Int64Array> {
+    batch
+        .column(batch.schema().index_of(name)?)
+        .as_any()
+        .downcast_ref::<Int64Array>()
+        .with_context(|| format!("Failed to get {} column", name))
+}
+
+fn float64_column<'a>(batch: &'a RecordBatch, name: &str) -> Result<&'a Float64Array> {
+    batch
+        .column(batch.schema().index_of(name)?)
+        .as_any()
+        .downcast_ref::<Float64Array>()
+        .with_context(|| format!("Failed to get {} column", name))
+}
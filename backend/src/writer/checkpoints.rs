@@ -0,0 +1,185 @@
+use anyhow::{Context, Result};
+use futures::StreamExt;
+use object_store::{path::Path, ObjectStore};
+use std::collections::HashSet;
+use tracing::{info, warn};
+use crate::models::{pool_key, CheckpointSnapshot, MarkoutTime};
+use crate::schema::read_checkpoint_snapshot;
+use crate::storage::{parquet_reader, TEMPORARY_OBJECT_MARKER};
+use super::ParallelParquetWriter;
+
+/// Prefix every checkpoint is stored under, in either layout
+pub const CHECKPOINTS_PREFIX: &str = "checkpoints";
+/// Every checkpoint in one file, one row per pool and markout, as written by
+/// `compact_checkpoints`
+pub const CONSOLIDATED_CHECKPOINTS_PATH: &str = "checkpoints/all.parquet";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckpointLayout {
+    /// `checkpoints/<pool>_<markout>.parquet`, written before checkpoints
+    /// were partitioned
+    Flat,
+    /// `checkpoints/pool=<pool>/markout=<markout>.parquet`
+    Partitioned,
+}
+
+/// Where `ParallelParquetWriter::write_checkpoints` stores the checkpoint of
+/// `pair_address` at `markout_time`
+pub fn checkpoint_path(pair_address: &str, markout_time: MarkoutTime) -> Path {
+    Path::from(format!(
+        "{}/pool={}/markout={}.parquet",
+        CHECKPOINTS_PREFIX,
+        pool_key(pair_address),
+        markout_time
+    ))
+}
+
+/// Where checkpoints were stored before the partitioned layout
+pub fn flat_checkpoint_path(pair_address: &str, markout_time: MarkoutTime) -> Path {
+    Path::from(format!("{}/{}_{}.parquet", CHECKPOINTS_PREFIX, pair_address, markout_time))
+}
+
+/// Layout, pool and markout string of a path named like either checkpoint
+/// layout. Nothing is checked beyond the name's shape.
+pub fn parse_checkpoint_path(path: &str) -> Option<(CheckpointLayout, &str, &str)> {
+    let name = path.strip_prefix(CHECKPOINTS_PREFIX)?.strip_prefix('/')?;
+    if let Some(rest) = name.strip_prefix("pool=") {
+        let (pool, file) = rest.split_once('/')?;
+        let markout = file.strip_prefix("markout=")?.strip_suffix(".parquet")?;
+        return (!pool.is_empty() && !markout.contains('/')).then_some((CheckpointLayout::Partitioned, pool, markout));
+    }
+    let (pool, markout) = name.strip_suffix(".parquet")?.rsplit_once('_')?;
+    (!pool.contains('/')).then_some((CheckpointLayout::Flat, pool, markout))
+}
+
+/// The stored checkpoint objects to read: just the consolidated file when
+/// there is one, which saves listing the per-file checkpoints, and otherwise
+/// `list_checkpoint_files`
+pub async fn list_checkpoints(store: &dyn ObjectStore) -> Result<Vec<Path>> {
+    let consolidated = Path::from(CONSOLIDATED_CHECKPOINTS_PATH);
+    match store.head(&consolidated).await {
+        Ok(_) => Ok(vec![consolidated]),
+        Err(object_store::Error::NotFound { .. }) => list_checkpoint_files(store).await,
+        Err(e) => Err(anyhow::Error::new(e).context(format!("Failed to look up {}", consolidated))),
+    }
+}
+
+/// Every per-file checkpoint object, in either layout. A flat checkpoint is
+/// left out when the partitioned layout holds the same pool and markout,
+/// since only a run predating the partitioned layout wrote it. Objects named
+/// like neither layout are kept; readers identify checkpoints by their
+/// columns, not their names.
+pub async fn list_checkpoint_files(store: &dyn ObjectStore) -> Result<Vec<Path>> {
+    let mut paths = Vec::new();
+    let mut listing = store.list(Some(&Path::from(CHECKPOINTS_PREFIX)));
+    while let Some(meta) = listing.next().await {
+        let location = meta.context("Failed to list checkpoints")?.location;
+        if !location.as_ref().contains(TEMPORARY_OBJECT_MARKER) && location.as_ref() != CONSOLIDATED_CHECKPOINTS_PATH {
+            paths.push(location);
+        }
+    }
+
+    let partitioned: HashSet<(String, &str)> = paths
+        .iter()
+        .filter_map(|path| match parse_checkpoint_path(path.as_ref()) {
+            Some((CheckpointLayout::Partitioned, pool, markout)) => Some((pool_key(pool), markout)),
+            _ => None,
+        })
+        .collect();
+    let shadowed: HashSet<&Path> = paths
+        .iter()
+        .filter(|path| match parse_checkpoint_path(path.as_ref()) {
+            Some((CheckpointLayout::Flat, pool, markout)) => partitioned.contains(&(pool_key(pool), markout)),
+            _ => false,
+        })
+        .collect();
+    for path in &shadowed {
+        warn!("Ignoring {}, which the partitioned layout supersedes", path);
+    }
+
+    let mut checkpoints: Vec<Path> = paths.iter().filter(|path| !shadowed.contains(path)).cloned().collect();
+    checkpoints.sort();
+    Ok(checkpoints)
+}
+
+/// Moves every checkpoint not in the partitioned layout to where
+/// `checkpoint_path` puts it, by the pool and markout in its columns.
+/// Checkpoints the partitioned layout already holds are removed rather
+/// than moved. Returns how many objects were moved or removed.
+pub async fn migrate_checkpoint_layout(store: &dyn ObjectStore) -> Result<usize> {
+    let mut listing = store.list(Some(&Path::from(CHECKPOINTS_PREFIX)));
+    let mut stored = Vec::new();
+    while let Some(meta) = listing.next().await {
+        stored.push(meta.context("Failed to list checkpoints")?.location);
+    }
+
+    let mut existing: HashSet<Path> = stored.iter().cloned().collect();
+    let mut migrated = 0;
+    for path in &stored {
+        if path.as_ref().contains(TEMPORARY_OBJECT_MARKER)
+            || path.as_ref() == CONSOLIDATED_CHECKPOINTS_PATH
+            || matches!(parse_checkpoint_path(path.as_ref()), Some((CheckpointLayout::Partitioned, _, _)))
+        {
+            continue;
+        }
+
+        let bytes = store.get(path).await?.bytes().await?;
+        let batch = parquet_reader(bytes, path, 1)?
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("Checkpoint {} holds no rows", path))?
+            .with_context(|| format!("Failed to read {}", path))?;
+        let snapshot = read_checkpoint_snapshot(&batch).with_context(|| format!("Failed to read checkpoint {}", path))?;
+        let target = checkpoint_path(&snapshot.pair_address, snapshot.markout_time);
+
+        if existing.contains(&target) {
+            warn!("{} already exists; removing {} instead of moving it", target, path);
+        } else {
+            store.copy(path, &target).await.with_context(|| format!("Failed to copy {} to {}", path, target))?;
+            info!("Moved {} to {}", path, target);
+            existing.insert(target);
+        }
+        store.delete(path).await.with_context(|| format!("Failed to remove {}", path))?;
+        migrated += 1;
+    }
+    Ok(migrated)
+}
+
+/// Writes every per-file checkpoint into `CONSOLIDATED_CHECKPOINTS_PATH`,
+/// ordered by pool and markout, and removes the per-file objects unless
+/// `keep_checkpoint_files`. Returns how many checkpoints were written; with
+/// no per-file checkpoints stored, nothing is written.
+pub async fn compact_checkpoints(
+    store: &dyn ObjectStore,
+    writer: &ParallelParquetWriter,
+    keep_checkpoint_files: bool,
+) -> Result<usize> {
+    let paths = list_checkpoint_files(store).await?;
+    if paths.is_empty() {
+        warn!("No per-file checkpoints to compact");
+        return Ok(0);
+    }
+
+    let mut snapshots = Vec::new();
+    for path in &paths {
+        let bytes = store.get(path).await?.bytes().await?;
+        for batch in parquet_reader(bytes, path, 1024)? {
+            let batch = batch.with_context(|| format!("Failed to read {}", path))?;
+            snapshots.extend(
+                CheckpointSnapshot::from_record_batch(&batch)
+                    .with_context(|| format!("Failed to read checkpoint {}", path))?,
+            );
+        }
+    }
+    snapshots.sort_by_cached_key(|snapshot| (pool_key(&snapshot.pair_address), snapshot.markout_time.to_string()));
+
+    writer.write_consolidated_checkpoints(&snapshots).await?;
+    info!("Compacted {} checkpoint files into {}", paths.len(), CONSOLIDATED_CHECKPOINTS_PATH);
+
+    if !keep_checkpoint_files {
+        for path in &paths {
+            store.delete(path).await.with_context(|| format!("Failed to remove {}", path))?;
+        }
+        info!("Removed {} checkpoint files", paths.len());
+    }
+    Ok(snapshots.len())
+}
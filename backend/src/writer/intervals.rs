@@ -0,0 +1,84 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, Utc};
+use futures::StreamExt;
+use object_store::{path::Path, ObjectStore};
+use tracing::warn;
+use crate::config::OutputLayout;
+use crate::storage::TEMPORARY_OBJECT_MARKER;
+use crate::MERGE_BLOCK;
+
+/// Prefix every interval file is stored under, in either layout
+pub const INTERVALS_PREFIX: &str = "intervals";
+/// Unix time of `MERGE_BLOCK`
+pub const MERGE_BLOCK_TIMESTAMP: i64 = 1_663_224_179;
+/// Seconds between post-merge slots
+pub const SECONDS_PER_SLOT: i64 = 12;
+
+/// Time of `block` assuming one block every slot since the merge. Missed
+/// slots make real blocks later than this, by a few days per year, so a
+/// block near the start of a month can be partitioned into the one before.
+pub fn estimated_block_time(block: u64) -> DateTime<Utc> {
+    let slots = block as i64 - *MERGE_BLOCK as i64;
+    DateTime::from_timestamp(MERGE_BLOCK_TIMESTAMP + slots * SECONDS_PER_SLOT, 0).unwrap_or_default()
+}
+
+impl OutputLayout {
+    /// Where the intervals of the chunk `chunk_start..chunk_end` are written
+    pub fn interval_path(&self, chunk_start: u64, chunk_end: u64) -> Path {
+        match self {
+            OutputLayout::Flat => Path::from(format!("{}/{}_{}.parquet", INTERVALS_PREFIX, chunk_start, chunk_end)),
+            OutputLayout::Hive => {
+                let month = estimated_block_time(chunk_start);
+                Path::from(format!(
+                    "{}/year={:04}/month={:02}/{}_{}.parquet",
+                    INTERVALS_PREFIX,
+                    month.year(),
+                    month.month(),
+                    chunk_start,
+                    chunk_end
+                ))
+            }
+        }
+    }
+}
+
+/// `OutputLayout::interval_path` in the default flat layout
+pub fn interval_path(chunk_start: u64, chunk_end: u64) -> Path {
+    OutputLayout::Flat.interval_path(chunk_start, chunk_end)
+}
+
+/// Layout and block range of a path named exactly as either layout names
+/// the chunk's interval file
+pub fn parse_interval_path(path: &str) -> Option<(OutputLayout, u64, u64)> {
+    let name = path.strip_prefix(INTERVALS_PREFIX)?.strip_prefix('/')?;
+    let layout = if name.contains('/') { OutputLayout::Hive } else { OutputLayout::Flat };
+    let (start, end) = name.rsplit('/').next()?.strip_suffix(".parquet")?.split_once('_')?;
+    let (start, end) = (start.parse().ok()?, end.parse().ok()?);
+    (layout.interval_path(start, end).as_ref() == path).then_some((layout, start, end))
+}
+
+/// `(path, start, end)` of every interval file in `layout`, by block range.
+/// Files in the other layout, or named like neither, are left out with a
+/// warning.
+pub async fn list_interval_files(store: &dyn ObjectStore, layout: OutputLayout) -> Result<Vec<(Path, u64, u64)>> {
+    let mut files = Vec::new();
+    let mut other_layout = 0;
+    let mut listing = store.list(Some(&Path::from(INTERVALS_PREFIX)));
+    while let Some(meta) = listing.next().await {
+        let location = meta.context("Failed to list interval files")?.location;
+        if location.as_ref().contains(TEMPORARY_OBJECT_MARKER) {
+            continue;
+        }
+        match parse_interval_path(location.as_ref()) {
+            Some((found, start, end)) if found == layout => files.push((location, start, end)),
+            Some(_) => other_layout += 1,
+            None => warn!("Ignoring {}, which is not named like an interval file", location),
+        }
+    }
+    if other_layout > 0 {
+        warn!("Ignoring {} interval files not in the {:?} layout", other_layout, layout);
+    }
+
+    files.sort_by_key(|(_, start, end)| (*start, *end));
+    Ok(files)
+}
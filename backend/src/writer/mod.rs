@@ -1,2 +1,6 @@
+mod checkpoints;
+mod intervals;
 mod writer;
-pub use writer::*;
\ No newline at end of file
+pub use checkpoints::*;
+pub use intervals::*;
+pub use writer::*;
@@ -1,133 +1,325 @@
 use arrow::{
-    array::{ArrayRef, StringArray, UInt64Array, Float64Array},
+    array::{StringArray, UInt64Array, Float64Array},
     record_batch::RecordBatch,
 };
 use object_store::{path::Path, ObjectStore};
 use parquet::{
-    arrow::ArrowWriter,
-    basic::Compression,
-    file::properties::WriterProperties,
+    basic::{Compression, ZstdLevel},
+    format::KeyValue,
 };
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use tokio::sync::Semaphore;
+use tokio::sync::{Mutex, OnceCell, Semaphore};
 use anyhow::{Result, Context};
-use bytes::Bytes;
 use futures::stream::{FuturesOrdered, StreamExt};
-use crate::models::{IntervalData, CheckpointSnapshot, ClusterBlockActivity, MarkoutTime};
+use crate::models::{Address, IntervalData, CheckpointSnapshot, ChunkSummary, ClusterBlockActivity, MarkoutTime, RawLvrRow};
+use crate::api::common::{BUCKET_RULE_METADATA_KEY, HALF_OPEN_BUCKET_RULE};
+use crate::config::{OutputLayout, ParquetWriteOptions, PoolRegistry};
+use crate::schema::{
+    provenance_metadata, raw_record_batch, CheckpointSchemaVersion, SchemaVersion, CHUNK_SUMMARY_SCHEMA_VERSION,
+    INTERVAL_SCHEMA_VERSION, RAW_SCHEMA_VERSION,
+};
+use crate::storage::{parquet_reader, retry_atomic_put, BufferPool, RetryPolicy};
+use super::{checkpoint_path, list_checkpoint_files, CONSOLIDATED_CHECKPOINTS_PATH};
 use tracing::{warn, error, debug, info};
 use dashmap::DashMap;
 
-const MAX_CONCURRENT_WRITES: usize = 8;
+/// Puts in flight at once, across every caller of one writer
+pub const MAX_CONCURRENT_WRITES: usize = 8;
+/// Layout version recorded in the cluster activity footer
+pub const CLUSTER_ACTIVITY_SCHEMA_VERSION: SchemaVersion = SchemaVersion::new(1, 0);
 
+/// Writes the processor's output. Every method takes `&self`, so one writer
+/// can be shared by concurrent callers: writes to distinct paths run
+/// concurrently, bounded by `MAX_CONCURRENT_WRITES`, while writes to the
+/// same path (e.g. one pool's checkpoint) are serialized in call order.
 pub struct ParallelParquetWriter {
     write_semaphore: Arc<Semaphore>,
+    /// Lock of each path being written; removed once nobody holds it
+    path_locks: DashMap<Path, Arc<Mutex<()>>>,
+    buffers: Arc<BufferPool>,
     object_store: Arc<dyn ObjectStore>,
-    max_retries: u32,
+    retry_policy: RetryPolicy,
+    write_options: ParquetWriteOptions,
+    layout: OutputLayout,
+    write_retries: AtomicU64,
+    /// Set once the first checkpoint write has expanded and removed the
+    /// consolidated file
+    consolidated_checkpoints_expanded: OnceCell<()>,
 }
 
 impl ParallelParquetWriter {
     pub fn new(object_store: Arc<dyn ObjectStore>) -> Self {
         Self {
             write_semaphore: Arc::new(Semaphore::new(MAX_CONCURRENT_WRITES)),
+            path_locks: DashMap::new(),
+            buffers: BufferPool::shared(),
             object_store,
-            max_retries: 20,
+            retry_policy: RetryPolicy::new(20),
+            write_options: ParquetWriteOptions::default(),
+            layout: OutputLayout::default(),
+            write_retries: AtomicU64::new(0),
+            consolidated_checkpoints_expanded: OnceCell::new(),
         }
     }
 
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn with_write_options(mut self, write_options: ParquetWriteOptions) -> Self {
+        self.write_options = write_options;
+        self
+    }
+
+    pub fn with_layout(mut self, layout: OutputLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
     // Path construction helpers
     fn get_interval_path(&self, chunk_start: u64, chunk_end: u64) -> Path {
-        Path::from(format!("intervals/{}_{}.parquet", chunk_start, chunk_end))
+        self.layout.interval_path(chunk_start, chunk_end)
     }
 
-    fn get_checkpoint_path(&self, pair_address: &str, markout_time: &str) -> Path {
-        Path::from(format!(
-            "checkpoints/{}_{}.parquet",
-            pair_address,
-            markout_time
-        ))
+    fn get_chunk_summary_path(&self, chunk_start: u64, chunk_end: u64) -> Path {
+        Path::from(format!("chunks/{}_{}_summary.parquet", chunk_start, chunk_end))
+    }
+
+    /// Runs `write` once no other write of `path` is running
+    async fn with_path_lock<T>(&self, path: &Path, write: impl Future<Output = Result<T>>) -> Result<T> {
+        let lock = self.path_locks.entry(path.clone()).or_default().clone();
+        let result = {
+            let _path_guard = lock.lock().await;
+            write.await
+        };
+        drop(lock);
+        self.path_locks.remove_if(path, |_, lock| Arc::strong_count(lock) == 1);
+        result
+    }
+
+    /// Encodes and stores `batch` at `path` once no other write of `path`
+    /// is running and a write permit is free
+    async fn put_batch(
+        &self,
+        path: Path,
+        batch: RecordBatch,
+        write_options: &ParquetWriteOptions,
+        retry_policy: &RetryPolicy,
+        metadata: Vec<KeyValue>,
+    ) -> Result<()> {
+        // The path lock is always taken before a permit, so a write
+        // waiting on its path never holds a permit another path needs
+        self.with_path_lock(&path, async {
+            debug!("Acquiring semaphore for {}...", path);
+            let _permit = self.write_semaphore.acquire().await?;
+            self.store_batch(path.clone(), batch, write_options, retry_policy, metadata).await
+        }).await
+    }
+
+    /// Encodes and stores `batch` at `path`, counting the put attempts that
+    /// failed in `write_retries`
+    async fn store_batch(
+        &self,
+        path: Path,
+        batch: RecordBatch,
+        write_options: &ParquetWriteOptions,
+        retry_policy: &RetryPolicy,
+        metadata: Vec<KeyValue>,
+    ) -> Result<()> {
+        let props = write_options.writer_properties_with_metadata(metadata);
+        let payload = self.buffers.encode_parquet(&batch, props)?;
+
+        let retries = retry_atomic_put(self.object_store.as_ref(), &path, payload, batch.num_rows(), retry_policy)
+            .await
+            .with_context(|| format!("Failed to write {}", path))?;
+        self.write_retries.fetch_add(retries as u64, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Put attempts that failed and were retried, across every write so far
+    pub fn write_retries(&self) -> u64 {
+        self.write_retries.load(Ordering::Relaxed)
     }
 
     pub async fn write_interval_data(
-        &mut self,
-        mut interval_data: Vec<IntervalData>,
+        &self,
+        interval_data: Vec<IntervalData>,
         chunk_start: u64,
         chunk_end: u64,
     ) -> Result<()> {
-        debug!("Acquiring semaphore for interval data write...");
-        let _permit = self.write_semaphore.acquire().await?;
-    
         if interval_data.is_empty() {
             warn!("No interval data to write for chunk {}-{}", chunk_start, chunk_end);
             return Ok(());
         }
     
-        // Sort interval data by interval_id
-        interval_data.sort_by_key(|data| data.interval_id);
-    
-        // Create a single batch for all data
-        let batch = create_record_batch_from_interval_data(interval_data)?;
-        let store = self.object_store.clone();
+        let batch = interval_batch(interval_data)?;
         let path = self.get_interval_path(chunk_start, chunk_end);
-        
-        // Single write operation
-        write_batch_to_store(store, path, batch, self.max_retries).await?;
-    
-        Ok(())
+        let metadata = provenance_metadata(INTERVAL_SCHEMA_VERSION, Some((chunk_start, chunk_end)));
+        self.put_batch(path, batch, &self.write_options, &self.retry_policy, metadata).await
+    }
+
+    /// Rewrites the interval file of `chunk_start..chunk_end` with the rows
+    /// of the pools in `replace_pools` swapped for `new_rows`. Every other pool's rows are kept as stored. Without an
+    /// existing file, only `new_rows` are written.
+    pub async fn merge_interval_data(
+        &self,
+        new_rows: Vec<IntervalData>,
+        chunk_start: u64,
+        chunk_end: u64,
+        replace_pools: &PoolRegistry,
+    ) -> Result<()> {
+        let path = self.get_interval_path(chunk_start, chunk_end);
+        // Held from the read to the write, so concurrent merges of one file
+        // each keep the other's rows
+        self.with_path_lock(&path, async {
+            let mut rows = Vec::new();
+            match self.object_store.get(&path).await {
+                Ok(stored) => {
+                    for batch in parquet_reader(stored.bytes().await?, &path, 8192)? {
+                        let batch = batch.with_context(|| format!("Failed to read {}", path))?;
+                        rows.extend(IntervalData::from_record_batch(&batch)?);
+                    }
+                }
+                Err(object_store::Error::NotFound { .. }) => {}
+                Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path)),
+            }
+
+            let kept = rows.len();
+            rows.retain(|row| replace_pools.get(&row.pair_address).is_none());
+            info!(
+                "Merging {} rows into {}, replacing {} of its {} rows",
+                new_rows.len(), path, kept - rows.len(), kept
+            );
+            rows.extend(new_rows);
+
+            let batch = interval_batch(rows)?;
+            let metadata = provenance_metadata(INTERVAL_SCHEMA_VERSION, Some((chunk_start, chunk_end)));
+            let _permit = self.write_semaphore.acquire().await?;
+            self.store_batch(path.clone(), batch, &self.write_options, &self.retry_policy, metadata).await
+        }).await
+    }
+
+    /// Writes one pool's per-block rows for a chunk, ZSTD-compressed whatever
+    /// the configured codec since these files are far larger than intervals
+    pub async fn write_raw_series(
+        &self,
+        pool_address: &Address,
+        mut rows: Vec<RawLvrRow>,
+        chunk_start: u64,
+        chunk_end: u64,
+    ) -> Result<()> {
+        rows.sort_by_key(|row| (row.block_number, row.markout_time.to_string()));
+        let batch = raw_record_batch(&rows)?;
+        let write_options = ParquetWriteOptions {
+            compression: Compression::ZSTD(ZstdLevel::default()),
+            ..self.write_options.clone()
+        };
+        let path = raw_path(pool_address, chunk_start, chunk_end);
+        let metadata = provenance_metadata(RAW_SCHEMA_VERSION, Some((chunk_start, chunk_end)));
+        self.put_batch(path, batch, &write_options, &self.retry_policy, metadata).await
+    }
+
+    pub async fn write_chunk_summary(&self, summary: &ChunkSummary) -> Result<()> {
+        let batch = summary.to_record_batch()?;
+        let path = self.get_chunk_summary_path(summary.chunk_start, summary.chunk_end);
+        let metadata = provenance_metadata(CHUNK_SUMMARY_SCHEMA_VERSION, Some((summary.chunk_start, summary.chunk_end)));
+        self.put_batch(path, batch, &self.write_options, &self.retry_policy, metadata).await
     }
 
     pub async fn write_checkpoints(
-        &mut self,
+        &self,
         checkpoints: Vec<CheckpointSnapshot>
     ) -> Result<()> {
-        debug!("Acquiring semaphore for checkpoint writes...");
-        let _permit = self.write_semaphore.acquire().await?;
-    
-        let mut checkpoint_tasks = FuturesOrdered::new();
+        // The consolidated file would no longer match the per-file
+        // checkpoints, so before the first write it is expanded into them
+        // and removed; concurrent writes wait for that
+        self.consolidated_checkpoints_expanded
+            .get_or_try_init(|| self.expand_consolidated_checkpoints())
+            .await?;
+
+        let mut checkpoint_writes = FuturesOrdered::new();
     
         for checkpoint in checkpoints {
-            let store = self.object_store.clone();
-            let path = self.get_checkpoint_path(
-                &checkpoint.pair_address, 
-                &checkpoint.markout_time.to_string()
-            );
-            
-            let task = tokio::spawn(async move {
-                let batch = create_record_batch_from_checkpoint(&checkpoint)?;
-                write_batch_to_store(store, path, batch, 3).await
-            });
-    
-            checkpoint_tasks.push_back(task);
+            checkpoint_writes.push_back(async move { self.put_checkpoint(&checkpoint).await });
         }
     
-        while let Some(result) = checkpoint_tasks.next().await {
-            match result {
-                Ok(Ok(_)) => continue,
-                Ok(Err(e)) => {
-                    error!("Checkpoint write failed: {}", e);
-                    return Err(e);
-                }
-                Err(e) => {
-                    error!("Task join failed: {}", e);
-                    return Err(anyhow::anyhow!("Task join failed: {}", e));
-                }
+        while let Some(result) = checkpoint_writes.next().await {
+            if let Err(e) = result {
+                error!("Checkpoint write failed: {}", e);
+                return Err(e);
             }
         }
     
         Ok(())
     }
 
+    async fn put_checkpoint(&self, checkpoint: &CheckpointSnapshot) -> Result<()> {
+        let path = checkpoint_path(&checkpoint.pair_address, checkpoint.markout_time);
+        let batch = CheckpointSnapshot::to_record_batch(std::slice::from_ref(checkpoint))?;
+        let mut metadata = vec![KeyValue::new(BUCKET_RULE_METADATA_KEY.to_string(), HALF_OPEN_BUCKET_RULE.to_string())];
+        metadata.extend(provenance_metadata(CheckpointSchemaVersion::CURRENT.schema_version(), checkpoint.processed_range()));
+        self.put_batch(path, batch, &self.write_options, &self.retry_policy, metadata).await
+    }
+
+    /// Writes every checkpoint of `CONSOLIDATED_CHECKPOINTS_PATH` that has no
+    /// per-file checkpoint to its own file, then removes the consolidated
+    /// file, so pools a run never updates keep their checkpoints
+    async fn expand_consolidated_checkpoints(&self) -> Result<()> {
+        let consolidated = Path::from(CONSOLIDATED_CHECKPOINTS_PATH);
+        let bytes = match self.object_store.get(&consolidated).await {
+            Ok(result) => result.bytes().await?,
+            Err(object_store::Error::NotFound { .. }) => return Ok(()),
+            Err(e) => return Err(anyhow::Error::new(e).context(format!("Failed to read {}", consolidated))),
+        };
+
+        let existing: HashSet<Path> = list_checkpoint_files(self.object_store.as_ref()).await?.into_iter().collect();
+        let mut expanded = 0;
+        for batch in parquet_reader(bytes, &consolidated, 1024)? {
+            let batch = batch.with_context(|| format!("Failed to read {}", consolidated))?;
+            for snapshot in CheckpointSnapshot::from_record_batch(&batch)
+                .with_context(|| format!("Failed to read checkpoint {}", consolidated))?
+            {
+                if !existing.contains(&checkpoint_path(&snapshot.pair_address, snapshot.markout_time)) {
+                    self.put_checkpoint(&snapshot).await?;
+                    expanded += 1;
+                }
+            }
+        }
+        info!("Expanded {} checkpoints of {} into per-file checkpoints", expanded, consolidated);
+
+        match self.object_store.delete(&consolidated).await {
+            Ok(()) | Err(object_store::Error::NotFound { .. }) => Ok(()),
+            Err(e) => Err(anyhow::Error::new(e).context(format!("Failed to remove stale {}", consolidated))),
+        }
+    }
+
+    /// Writes `checkpoints` as the rows of `CONSOLIDATED_CHECKPOINTS_PATH`
+    pub async fn write_consolidated_checkpoints(&self, checkpoints: &[CheckpointSnapshot]) -> Result<()> {
+        let batch = CheckpointSnapshot::to_record_batch(checkpoints)?;
+        let processed_range = checkpoints
+            .iter()
+            .filter_map(CheckpointSnapshot::processed_range)
+            .reduce(|(start, end), (other_start, other_end)| (start.min(other_start), end.max(other_end)));
+        let mut metadata = vec![KeyValue::new(BUCKET_RULE_METADATA_KEY.to_string(), HALF_OPEN_BUCKET_RULE.to_string())];
+        metadata.extend(provenance_metadata(CheckpointSchemaVersion::CURRENT.schema_version(), processed_range));
+        self.put_batch(Path::from(CONSOLIDATED_CHECKPOINTS_PATH), batch, &self.write_options, &self.retry_policy, metadata)
+            .await
+    }
+
     pub async fn write_cluster_activity(
         &self,
         cluster_activity: &DashMap<(String, MarkoutTime), ClusterBlockActivity>
     ) -> Result<()> {
-        debug!("Acquiring semaphore for cluster activity data write...");
-        let _permit = self.write_semaphore.acquire().await?;
-    
-        // Convert DashMap entries to a vector
-        let cluster_activities: Vec<_> = cluster_activity
+        // Convert DashMap entries to a vector, in a stable order
+        let mut cluster_activities: Vec<_> = cluster_activity
             .iter()
             .map(|entry| entry.value().clone())
             .collect();
+        cluster_activities.sort_by_cached_key(|activity| (activity.cluster_name.clone(), activity.markout_time.to_string()));
     
         if cluster_activities.is_empty() {
             warn!("No cluster activity data to write");
@@ -176,8 +368,9 @@ impl ParallelParquetWriter {
         )?;
     
         // Write to output file
-        let path = Path::from("precomputed/clusters/non_zero.parquet");
-        write_batch_to_store(self.object_store.clone(), path, batch, self.max_retries).await?;
+        let path = Path::from(CLUSTER_ACTIVITY_PATH);
+        let metadata = provenance_metadata(CLUSTER_ACTIVITY_SCHEMA_VERSION, None);
+        self.put_batch(path, batch, &self.write_options, &self.retry_policy, metadata).await?;
     
         info!("Successfully wrote cluster activity data");
         Ok(())
@@ -185,89 +378,23 @@ impl ParallelParquetWriter {
 }
 
 // Helper functions
-async fn write_batch_to_store(
-    store: Arc<dyn ObjectStore>,
-    path: Path,
-    batch: RecordBatch,
-    max_retries: u32,
-) -> Result<()> {
-    let props = WriterProperties::builder()
-        .set_compression(Compression::SNAPPY)
-        .set_write_batch_size(1024 * 1024)
-        .set_data_page_size_limit(1024 * 1024)
-        .build();
-
-    let mut buffer = Vec::new();
-    {
-        let mut writer = ArrowWriter::try_new(&mut buffer, batch.schema(), Some(props))?;
-        writer.write(&batch)?;
-        writer.close()?;
-    }
 
-    let mut retries = 0;
-    while retries < max_retries {
-        match store.put(&path, Bytes::from(buffer.clone()).into()).await {
-            Ok(_) => return Ok(()),
-            Err(e) if retries < max_retries - 1 => {
-                retries += 1;
-                let delay = std::time::Duration::from_secs(2u64.pow(retries));
-                warn!(
-                    "Write attempt {} failed for path {}: {}. Retrying in {} seconds...",
-                    retries, path, e, delay.as_secs()
-                );
-                tokio::time::sleep(delay).await;
-            }
-            Err(e) => return Err(e.into()),
-        }
-    }
-    
-    Err(anyhow::anyhow!("Failed to write after {} retries", max_retries))
+/// `rows` as one batch, sorted so the rows of one pool and markout are
+/// contiguous and row group statistics and bloom filters can rule other
+/// pools out
+fn interval_batch(mut rows: Vec<IntervalData>) -> Result<RecordBatch> {
+    rows.sort_by_cached_key(|data| (data.pair_address.clone(), data.markout_time.to_string(), data.interval_id));
+    IntervalData::to_record_batch(&rows)
 }
 
-fn create_record_batch_from_interval_data(data: Vec<IntervalData>) -> Result<RecordBatch> {
-    RecordBatch::try_from_iter([
-        ("interval_id", Arc::new(UInt64Array::from(data.iter().map(|d| d.interval_id).collect::<Vec<_>>())) as ArrayRef),
-        ("pair_address", Arc::new(StringArray::from(data.iter().map(|d| d.pair_address.clone()).collect::<Vec<_>>())) as ArrayRef),
-        ("markout_time", Arc::new(StringArray::from(data.iter().map(|d| d.markout_time.to_string()).collect::<Vec<_>>())) as ArrayRef),
-        ("total_lvr_cents", Arc::new(UInt64Array::from(data.iter().map(|d| d.total_lvr_cents).collect::<Vec<_>>())) as ArrayRef),
-        ("max_lvr_cents", Arc::new(UInt64Array::from(data.iter().map(|d| d.max_lvr_cents).collect::<Vec<_>>())) as ArrayRef),
-        ("non_zero_count", Arc::new(UInt64Array::from(data.iter().map(|d| d.non_zero_count).collect::<Vec<_>>())) as ArrayRef),
-        ("total_count", Arc::new(UInt64Array::from(data.iter().map(|d| d.total_count).collect::<Vec<_>>())) as ArrayRef),
-    ]).context("Failed to create interval data record batch")
+
+/// Where the processor records each cluster's block activity, read by the
+/// cluster non-zero precomputation
+pub const CLUSTER_ACTIVITY_PATH: &str = "cluster_activity/activity.parquet";
+
+/// Where the per-block rows of `pool_address` for the chunk
+/// `chunk_start..chunk_end` are written
+pub fn raw_path(pool_address: &Address, chunk_start: u64, chunk_end: u64) -> Path {
+    Path::from(format!("raw/{}/{}_{}.parquet", pool_address, chunk_start, chunk_end))
 }
 
-fn create_record_batch_from_checkpoint(checkpoint: &CheckpointSnapshot) -> Result<RecordBatch> {
-    RecordBatch::try_from_iter([
-        // Basic metrics
-        ("pair_address", Arc::new(StringArray::from(vec![checkpoint.pair_address.clone()])) as ArrayRef),
-        ("markout_time", Arc::new(StringArray::from(vec![checkpoint.markout_time.to_string()])) as ArrayRef),
-        ("max_lvr_block", Arc::new(UInt64Array::from(vec![checkpoint.max_lvr_block])) as ArrayRef),
-        ("max_lvr_value", Arc::new(UInt64Array::from(vec![checkpoint.max_lvr_value])) as ArrayRef),
-        ("running_total", Arc::new(UInt64Array::from(vec![checkpoint.running_total])) as ArrayRef),
-        
-        // Bucket distributions
-        ("total_bucket_0", Arc::new(UInt64Array::from(vec![checkpoint.total_bucket_0])) as ArrayRef),
-        ("total_bucket_0_10", Arc::new(UInt64Array::from(vec![checkpoint.total_bucket_0_10])) as ArrayRef),
-        ("total_bucket_10_100", Arc::new(UInt64Array::from(vec![checkpoint.total_bucket_10_100])) as ArrayRef),
-        ("total_bucket_100_500", Arc::new(UInt64Array::from(vec![checkpoint.total_bucket_100_500])) as ArrayRef),
-        ("total_bucket_500_1000", Arc::new(UInt64Array::from(vec![checkpoint.total_bucket_500_1000])) as ArrayRef),
-        ("total_bucket_1000_10000", Arc::new(UInt64Array::from(vec![checkpoint.total_bucket_1000_10000])) as ArrayRef),
-        ("total_bucket_10000_plus", Arc::new(UInt64Array::from(vec![checkpoint.total_bucket_10000_plus])) as ArrayRef),
-        
-        // Block and sample metrics
-        ("last_updated_block", Arc::new(UInt64Array::from(vec![checkpoint.last_updated_block])) as ArrayRef),
-        ("non_zero_proportion", Arc::new(Float64Array::from(vec![checkpoint.non_zero_proportion])) as ArrayRef),
-        ("non_zero_samples", Arc::new(UInt64Array::from(vec![checkpoint.non_zero_samples])) as ArrayRef),
-        
-        // Percentile metrics
-        ("percentile_25_cents", Arc::new(UInt64Array::from(vec![checkpoint.percentile_25_cents])) as ArrayRef),
-        ("median_cents", Arc::new(UInt64Array::from(vec![checkpoint.median_cents])) as ArrayRef),
-        ("percentile_75_cents", Arc::new(UInt64Array::from(vec![checkpoint.percentile_75_cents])) as ArrayRef),
-        
-        // Distribution metrics
-        ("mean", Arc::new(Float64Array::from(vec![checkpoint.mean])) as ArrayRef),
-        ("std_dev", Arc::new(Float64Array::from(vec![checkpoint.std_dev])) as ArrayRef),
-        ("skewness", Arc::new(Float64Array::from(vec![checkpoint.skewness])) as ArrayRef),
-        ("kurtosis", Arc::new(Float64Array::from(vec![checkpoint.kurtosis])) as ArrayRef),
-    ]).context("Failed to create checkpoint record batch")
-}
\ No newline at end of file